@@ -1,17 +1,39 @@
 use anyhow::Result;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+use tokio::sync::mpsc;
 use nucleo_matcher::{
     pattern::{CaseMatching, Normalization, Pattern},
     Matcher,
 };
 
-use crate::config::Config;
-use crate::desktop_entry::Entry;
-use crate::executor::{CommandStatus, TerminalMode};
-use crate::history::History;
-use crate::terminal::{EmbeddedTerminal, TerminalConfig};
-use crate::niri::NiriClient;
+use crate::clipboard;
+use crate::config::{Config, JobsOnExit, TimeoutAction};
+use crate::desktop_entry::{Entry, TYPED_COMMAND_PREFIX};
+use crate::dmenu::Dmenu;
+use crate::executables;
+use crate::executor::{CommandStatus, LaunchOptions, ModeRules, TerminalMode};
+use crate::glyphs::GlyphIcons;
+use crate::history::{History, UsageStats};
+use crate::hooks::{self, HookEvent};
+use crate::jobs::{self, Job};
+use crate::keymap::{self, KeyContext};
+use crate::list::ListedEntry;
+use crate::matching::{MatchJob, MatchWorker, Ranker};
+use crate::wakeup::Wakeup;
+use crate::terminal::{
+    EmbeddedTerminal, SearchMatch, SearchQuery, SelectionPoint, TerminalConfig,
+};
+use crate::compositor::{self, Backend, Compositor, Event, WindowInfo, WorkspaceReference};
+use crate::notify::{self, Notifier, NotifySend};
+use crate::output_file;
 use crate::pty::PtySession;
+use crate::search;
 use crate::ui::layout::GridLayout;
+use ratatui::layout::Rect;
+use crate::windows;
 
 /// Application mode - determines what UI to show and how to handle input
 /// TEAM_000: Phase 2, Unit 2.3 - State transitions
@@ -22,20 +44,36 @@ pub enum AppMode {
     /// Executing a command with PTY - showing output
     Executing {
         command: String,
-        /// NOTE: Reserved for mode-specific UI behavior (e.g., different status indicators)
-        #[allow(dead_code)]
+        /// Interactive commands are stopped with ^C rather than SIGTERM
         mode: TerminalMode,
+        /// When the command was started, for the elapsed time display
+        started: Instant,
+        /// When the user asked the command to stop (first Ctrl+C)
+        terminating: Option<Instant>,
     },
     /// Command finished, showing preserved output (terminal state preserved)
     PostExecution {
         command: String,
         exit_status: CommandStatus,
+        /// Total run time of the command
+        duration: Duration,
         /// When copy was attempted, for showing feedback
         copy_feedback: Option<std::time::Instant>,
+        /// When to return to the launcher on its own (successful commands
+        /// with `behavior.auto_dismiss_success_secs` set)
+        auto_dismiss_at: Option<Instant>,
     },
     /// TUI mode - full terminal handover (htop, vim, etc.)
     TuiHandover {
         command: String,
+        /// Shell, working directory and environment for the child
+        launch: LaunchOptions,
+    },
+    /// Background jobs list
+    Jobs {
+        /// Quitting was requested while jobs are running; waiting for the
+        /// user to confirm killing them
+        confirm_exit: bool,
     },
     /// Exit after launching a GUI app (or other exit conditions)
     Exit,
@@ -45,20 +83,25 @@ pub enum AppMode {
 pub struct App {
     /// Current application mode
     mode: AppMode,
-    /// All loaded desktop entries
-    entries: Vec<Entry>,
+    /// All loaded desktop entries (shared with the matcher thread)
+    entries: Arc<Vec<Entry>>,
     /// Filtered entries (indices into `entries`)
     filtered: Vec<usize>,
     /// Currently selected index in filtered list
     selected: usize,
+    /// Id of the entry the user moved the selection to
+    picked: Option<String>,
+    /// Matched character positions in the names of filtered entries, keyed
+    /// by index into `entries`
+    match_indices: HashMap<usize, Vec<u32>>,
     /// Current filter text
     filter: String,
     /// Whether we're in filter input mode
     filtering: bool,
     /// Configuration
     config: Config,
-    /// Niri IPC client
-    niri: Option<NiriClient>,
+    /// Compositor IPC client (niri, sway or Hyprland)
+    compositor: Option<Box<dyn Compositor>>,
     /// PTY session for current execution (if any)
     pty_session: Option<PtySession>,
     /// Terminal emulator for current execution
@@ -67,61 +110,625 @@ pub struct App {
     matcher: Matcher,
     /// TEAM_001: Usage history for frecency sorting
     history: History,
-    /// TEAM_001: Frecency weight from config
-    frecency_weight: f64,
     /// TEAM_004: Grid layout for 2-column display
     grid_layout: GridLayout,
+    /// Signaled when the running command prints or exits, so the main
+    /// loop shows it at once
+    wakeup: Option<Wakeup>,
+    /// Ctrl+E was pressed: Enter runs the selected entry elevated
+    elevation_armed: bool,
+    /// Category browser, shown instead of the entry grid while open
+    category_list: Option<CategoryList>,
+    /// Last launched command, for re-running from post-execution
+    last_run: Option<LastRun>,
+    /// Search in the command output, while active
+    output_search: Option<OutputSearch>,
+    /// Links from the output to choose from (`o` in post-execution)
+    link_picker: Option<LinkPicker>,
+    /// Result of the last output save, shown in the status bar
+    status_message: Option<StatusMessage>,
+    /// Desktop notifications for finished long-running commands
+    notifier: Box<dyn Notifier>,
+    /// Compiled `[terminal_mode]` rules from config
+    mode_rules: ModeRules,
+    /// Commands detached with Ctrl+B, oldest first
+    jobs: Vec<Job>,
+    /// Selected index in the jobs view
+    selected_job: usize,
+    /// Time and entry of the last click on a launcher card
+    last_click: Option<(Instant, usize)>,
+    /// Scroll offset of the help overlay, while it is open
+    help_scroll: Option<usize>,
+    /// Where the command output was last drawn, relative to the launcher
+    /// area like mouse events
+    output_area: Rect,
+    /// Detail pane for the selected entry is shown next to the grid
+    preview: bool,
+    /// Text icons for when the terminal can't show images
+    glyph_icons: Option<GlyphIcons>,
+    /// Window and workspace events from the compositor
+    compositor_events: Option<mpsc::UnboundedReceiver<Event>>,
+    /// GUI app just launched; drun exits once its window opens
+    awaiting_window: Option<AwaitedWindow>,
+    /// Command launched once its pre_exec hooks are done
+    pending_launch: Option<PendingLaunch>,
+    /// Windows or workspaces listed instead of applications
+    compositor_list: Option<CompositorListing>,
+    /// Whether drun's window floated before the running command tiled it
+    /// (None: not known)
+    floating_before_command: Option<bool>,
+    /// Compositor integration is on (config and `--no-compositor`)
+    compositor_enabled: bool,
+    /// Compositor connection state for the status bar
+    compositor_health: CompositorHealth,
+    /// When `compositor_health` was last refreshed
+    compositor_health_checked: Option<Instant>,
+    /// Lines to pick from instead of running entries (`--dmenu`)
+    dmenu: Option<Dmenu>,
+    /// Something was launched (or a window focused) since drun started
+    launched: bool,
+    /// Exit status of the last command run in the PTY or terminal
+    last_status: Option<CommandStatus>,
+    /// Exit when the output is dismissed, with the command's exit code
+    /// (`--propagate-exit`)
+    propagate_exit: bool,
+    /// Kitty keyboard protocol flags are pushed, to pop and push again
+    /// around TUI commands
+    keyboard_enhancement: bool,
+    /// Programs found on `$PATH` so far (also in `entries`)
+    executables: Vec<Entry>,
+    /// Background `$PATH` scan, until it is done
+    executable_scan: Option<mpsc::UnboundedReceiver<Vec<Entry>>>,
+    /// List programs on `$PATH` instead of applications (`--mode run`)
+    run_mode: bool,
+    /// Hidden behind the idle screen, waiting for `show` (`--daemon`)
+    hidden: bool,
+    /// What choosing an entry does
+    execution: ExecutionPolicy,
+    /// Command chosen under `ExecutionPolicy::Print`
+    printed: Option<PrintedCommand>,
+    /// Something on screen changed since the last draw
+    dirty: bool,
+    /// When the screen was last drawn
+    last_draw: Option<Instant>,
+    /// Ranks long lists off the UI thread, once started
+    match_worker: Option<MatchWorker>,
+    /// Counts the rankings asked for; results of older ones are dropped
+    match_generation: u64,
+    /// The match worker hasn't sent the complete ranking for the current
+    /// filter yet
+    ranking_pending: bool,
+    /// `entries` was replaced since the last ranking, so the listed
+    /// indices are stale and can't be shown until the new one is done
+    entries_replaced: bool,
+    /// History just forgotten, for undo
+    forgotten: Option<ForgottenHistory>,
 }
 
-impl App {
-    pub fn new(entries: Vec<Entry>, config: Config, niri_enabled: bool) -> Self {
-        let filtered: Vec<usize> = (0..entries.len()).collect();
-        
-        // Niri IPC: gracefully disabled if socket not found (e.g., over SSH)
-        let niri = if niri_enabled {
-            NiriClient::try_new()
+/// What choosing an entry does
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ExecutionPolicy {
+    /// Run it in the PTY or launch it detached
+    #[default]
+    Run,
+    /// Keep its expanded command for `main` to print, and exit
+    /// (`--print-only`)
+    Print,
+}
+
+/// An entry's command as `--print-only` prints it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PrintedCommand {
+    /// Desktop file id (`cmd:<text>` for typed commands)
+    pub id: String,
+    /// Exec with field codes expanded
+    pub command: String,
+}
+
+impl PrintedCommand {
+    /// `id<TAB>command`, or a JSON object with `json`
+    pub fn format(&self, json: bool) -> String {
+        if json {
+            serde_json::json!({ "id": self.id, "command": self.command }).to_string()
         } else {
-            None
+            format!("{}\t{}", self.id, self.command)
+        }
+    }
+}
+
+/// Compositor connection state, for the status bar indicator
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CompositorHealth {
+    /// Integration disabled, or not a compositor session: no indicator
+    Off,
+    Connected,
+    Disconnected {
+        /// Why, for the status message
+        reason: String,
+        /// drun runs under the compositor (e.g. `$NIRI_SOCKET` is set),
+        /// so this is a failure rather than it simply being absent
+        expected: bool,
+    },
+}
+
+/// How often the compositor indicator is refreshed
+const COMPOSITOR_HEALTH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// What compositor list is shown instead of applications
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompositorList {
+    /// Open windows (`--windows`, Ctrl+W)
+    Windows,
+    /// Workspaces (`--workspaces`)
+    Workspaces,
+}
+
+/// A compositor list in place of the application entries
+#[derive(Debug)]
+struct CompositorListing {
+    kind: CompositorList,
+    /// Application entries, back when the list closes
+    apps: Vec<Entry>,
+    /// Why the list is empty: the compositor isn't connected or didn't answer
+    error: Option<String>,
+}
+
+/// A launched GUI app whose window drun waits for before exiting
+#[derive(Debug, Clone)]
+pub struct AwaitedWindow {
+    /// Entry name, for the status bar
+    pub name: String,
+    /// app_ids the window may have, lowercase
+    app_ids: Vec<String>,
+    /// Exit anyway after this
+    deadline: Instant,
+}
+
+impl AwaitedWindow {
+    fn new(entry: &Entry, program: Option<&str>, wait: Duration) -> Self {
+        let mut app_ids: Vec<String> = Vec::new();
+        let mut add = |id: &str| {
+            let id = id.to_lowercase();
+            if !id.is_empty() && !app_ids.contains(&id) {
+                app_ids.push(id);
+            }
         };
+        if let Some(ref class) = entry.startup_wm_class {
+            add(class);
+        }
+        if !entry.is_typed_command() {
+            // Flatpak ids (org.mozilla.firefox) or the app name alone
+            add(&entry.id);
+            add(entry.id.rsplit('.').next().unwrap_or(&entry.id));
+        }
+        // The program run: the terminal for Terminal=true entries
+        if let Some(program) = program {
+            add(program.rsplit('/').next().unwrap_or(program));
+        }
+        Self {
+            name: entry.name.clone(),
+            app_ids,
+            deadline: Instant::now() + wait,
+        }
+    }
+
+    fn matches(&self, window: &WindowInfo) -> bool {
+        self.app_ids.contains(&window.app_id.to_lowercase())
+    }
+}
+
+/// Everything needed to launch a command again
+#[derive(Debug, Clone)]
+struct LastRun {
+    command: String,
+    mode: TerminalMode,
+    launch: LaunchOptions,
+    /// Entry the command came from, for history
+    entry_id: Option<String>,
+    /// Run through `behavior.elevate_command`
+    elevated: bool,
+}
+
+/// A launch waiting for its `[hooks] pre_exec` to finish
+struct PendingLaunch {
+    /// Thread running the hooks
+    hooks: std::thread::JoinHandle<()>,
+    /// Entry launched; None when re-running
+    entry: Option<Entry>,
+    run: LastRun,
+    cols: u16,
+    rows: u16,
+}
+
+/// History forgotten with Ctrl+Delete, until the undo window is over
+#[derive(Debug, Clone)]
+struct ForgottenHistory {
+    entry_id: String,
+    name: String,
+    stats: UsageStats,
+    at: Instant,
+}
+
+/// Search in command output (`/` in executing and post-execution modes)
+#[derive(Debug, Clone, Default)]
+pub struct OutputSearch {
+    pub query: SearchQuery,
+    /// The prompt is open and receives typed characters
+    pub editing: bool,
+    /// Match last jumped to
+    pub current: Option<SearchMatch>,
+    /// 1-based position of `current` and the number of matches, once a
+    /// search has run
+    pub position: Option<(usize, usize)>,
+}
+
+/// OSC 8 links found in the output, to open one
+#[derive(Debug, Clone)]
+pub struct LinkPicker {
+    pub links: Vec<String>,
+    pub selected: usize,
+}
+
+/// Short-lived message for the output view status bar
+#[derive(Debug, Clone)]
+pub struct StatusMessage {
+    pub text: String,
+    pub error: bool,
+    since: Instant,
+}
+
+/// How long a status message stays visible
+const STATUS_MESSAGE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long Ctrl+Delete undoes forgetting an entry's history
+const FORGET_UNDO_WINDOW: Duration = Duration::from_secs(5);
+
+/// Shortest time between draws while a command runs, so output pouring
+/// in is drawn once a frame rather than once a read
+const FRAME: Duration = Duration::from_millis(16);
+
+/// How long the main loop waits for input otherwise, for what's polled:
+/// jobs, timeouts, the heartbeat
+const IDLE_POLL: Duration = Duration::from_millis(100);
+
+/// Two clicks on the same card within this time run it
+const DOUBLE_CLICK: Duration = Duration::from_millis(300);
+
+/// Candidates from which on a match worker ranks them, when running
+const BACKGROUND_MATCH_MIN: usize = 2000;
+
+/// Longest time between draws, for the elapsed time, spinner and
+/// countdowns that change without an event
+const REDRAW_HEARTBEAT: Duration = Duration::from_secs(1);
+
+/// Main categories to drill into, with entry counts
+#[derive(Debug, Clone)]
+pub struct CategoryList {
+    pub categories: Vec<(String, usize)>,
+    pub selected: usize,
+}
+
+/// Merge `[[custom_entries]]` from config into the loaded desktop entries
+/// and apply `[entries]` overrides
+///
+/// Hidden entries are dropped here, so they never take part in frecency
+/// sorting or search.
+fn with_config_entries(mut entries: Vec<Entry>, config: &Config) -> Vec<Entry> {
+    entries.extend(config.custom_entries());
+    let mut entries = config.apply_overrides(entries);
+    entries.sort_by_key(|e| e.name.to_lowercase());
+    entries
+}
+
+/// `history` with its file loaded, if history is enabled
+fn load_history(mut history: History, config: &Config) -> History {
+    if config.history.enabled {
+        if let Err(e) = history.load() {
+            tracing::warn!("Failed to load history: {}", e);
+        }
+    }
+    history
+}
+
+/// Add entries for commands previously typed into the filter (from history)
+fn with_typed_commands(mut entries: Vec<Entry>, history: &History) -> Vec<Entry> {
+    let mut commands: Vec<&str> = history
+        .ids()
+        .filter_map(|id| id.strip_prefix(TYPED_COMMAND_PREFIX))
+        .collect();
+    commands.sort_unstable();
+    entries.extend(commands.into_iter().map(Entry::typed_command));
+    entries
+}
+
+/// Program and arguments for launching `entry` through the compositor
+/// (niri's `Spawn`, sway's and Hyprland's `exec`)
+///
+/// None when it needs what only a local launch has: a shell (custom
+/// entries and typed commands), a working directory or extra environment.
+/// `prefix` is the external terminal for Terminal=true entries.
+fn compositor_spawn_argv(entry: &Entry, prefix: &[String], launch: &LaunchOptions) -> Option<Vec<String>> {
+    if launch.working_dir.is_some() || !launch.env.is_empty() {
+        return None;
+    }
+    let argv = entry.argv()?;
+    Some(prefix.iter().cloned().chain(argv).collect())
+}
+
+/// Connection state of `compositor` (None: no socket was found at
+/// startup); `session` is the compositor drun should be talking to, if
+/// its environment variable is set
+fn compositor_health(compositor: Option<&dyn Compositor>, session: Option<Backend>) -> CompositorHealth {
+    let reason = match (compositor, session) {
+        (None, Some(backend)) => format!(
+            "${} is set but the socket doesn't exist",
+            backend.session_vars()[0]
+        ),
+        (None, None) => return CompositorHealth::Off,
+        (Some(wm), _) if !wm.socket_path().exists() => format!("the {} socket is gone", wm.name()),
+        (Some(wm), _) if !wm.is_available() => format!("{} is not responding", wm.name()),
+        (Some(wm), _) => match wm.last_error() {
+            Some(error) => error,
+            None => return CompositorHealth::Connected,
+        },
+    };
+    CompositorHealth::Disconnected {
+        reason,
+        expected: session.is_some(),
+    }
+}
+
+/// Floating state to give drun's window back in the launcher, if any
+///
+/// With `compositor.float_on_idle` the window goes back to how it was before
+/// the command (`captured`), so a window tiled on purpose stays tiled. If
+/// that isn't known it is floated, as configured.
+fn idle_floating(captured: Option<bool>, float_on_idle: bool) -> Option<bool> {
+    float_on_idle.then(|| captured.unwrap_or(true))
+}
+
+/// Start `[hooks] post_exec` for a command that exited
+fn spawn_post_exec_hooks(
+    config: &Config,
+    entry_id: Option<String>,
+    command: &str,
+    status: &CommandStatus,
+    duration: Duration,
+) {
+    let event = HookEvent {
+        entry_id,
+        command: command.to_string(),
+        status: Some(status.clone()),
+        duration: Some(duration),
+    };
+    hooks::spawn(
+        "post_exec",
+        config.hooks.post_exec.clone(),
+        event,
+        Duration::from_secs(config.hooks.timeout_secs),
+    );
+}
+
+/// Write the output in `terminal` to the configured output directory
+fn save_output(config: &Config, command: &str, terminal: &EmbeddedTerminal) -> Result<PathBuf> {
+    let dir = config
+        .output_dir()
+        .ok_or_else(|| anyhow::anyhow!("No output directory (set output.dir)"))?;
+    output_file::save(&dir, command, &terminal.content_as_text(), SystemTime::now())
+}
+
+/// Scrollback size for command output
+fn scrollback_lines(config: &Config) -> usize {
+    config.behavior.preserve_output_lines.max(1000)
+}
+
+/// Terminal emulator for a new command (resized when the command starts)
+fn new_terminal(scrollback: usize) -> EmbeddedTerminal {
+    EmbeddedTerminal::new(TerminalConfig {
+        cols: 80,
+        rows: 24,
+        scrollback,
+        cell_pixels: host_cell_pixels(),
+        ..Default::default()
+    })
+}
+
+/// Character cell size of the terminal drun runs in, if it reports its
+/// size in pixels (many don't, and none do over some SSH setups)
+fn host_cell_pixels() -> Option<(usize, usize)> {
+    let size = crossterm::terminal::window_size().ok()?;
+    if size.width == 0 || size.height == 0 || size.columns == 0 || size.rows == 0 {
+        return None;
+    }
+    Some((
+        (size.width / size.columns) as usize,
+        (size.height / size.rows) as usize,
+    ))
+}
+
+impl App {
+    pub fn new(entries: Vec<Entry>, config: Config, use_compositor: bool) -> Self {
+        let entries = with_config_entries(entries, &config);
 
-        let scrollback_lines = config.behavior.preserve_output_lines.max(1000);
-        
         // TEAM_001: Initialize history
-        let mut history = History::new(
-            config.history.max_entries,
-            config.history.decay_after_days,
+        let history = load_history(
+            History::new(config.history.max_entries, config.history.decay_after_days),
+            &config,
         );
-        if config.history.enabled {
-            if let Err(e) = history.load() {
-                tracing::warn!("Failed to load history: {}", e);
-            }
-        }
-        let frecency_weight = config.history.frecency_weight;
+
+        // Previously typed commands are searchable, but only listed
+        // once there is filter text
+        let entries = with_typed_commands(entries, &history);
+        Self::with_entries(entries, config, use_compositor, history)
+    }
+
+    /// App picking one of the lines of `dmenu` (`--dmenu`)
+    ///
+    /// No desktop or config entries, icons or compositor; history only
+    /// with a dmenu history file (`--dmenu-history`).
+    pub fn new_dmenu(dmenu: Dmenu, mut config: Config, history_path: Option<PathBuf>) -> Self {
+        config.icons.enabled = false;
+        config.history.enabled = history_path.is_some();
+        let (max_entries, decay) = (config.history.max_entries, config.history.decay_after_days);
+        let history = match history_path {
+            Some(path) => load_history(History::with_path(path, max_entries, decay), &config),
+            None => History::new(max_entries, decay),
+        };
+
+        let mut app = Self::with_entries(dmenu.entries(), config, false, history);
+        app.dmenu = Some(dmenu);
+        // Most picked lines first; input order otherwise
+        app.update_filtered();
+        app
+    }
+
+    fn with_entries(entries: Vec<Entry>, config: Config, use_compositor: bool, history: History) -> Self {
+        // Compositor IPC: gracefully disabled if socket not found (e.g., over SSH)
+        let compositor_enabled = use_compositor && config.compositor.enabled;
+        let compositor = if compositor_enabled {
+            compositor::connect(&config)
+        } else {
+            None
+        };
+
+        let compositor_events = compositor.as_ref().and_then(|wm| wm.spawn_event_listener());
+
+        let scrollback_lines = scrollback_lines(&config);
         
-        // TEAM_004: Initialize grid layout from config
-        let grid_layout = config.grid_layout();
+        // TEAM_004: Initialize grid layout from config (automatic columns
+        // are fitted to the width once it's known)
+        let grid_layout = config.grid_layout(0);
+        let mode_rules = config.mode_rules();
+        let preview = config.appearance.show_preview;
+        let glyph_icons = config.glyph_icons();
+
+        let filtered: Vec<usize> = (0..entries.len())
+            .filter(|&i| !entries[i].is_typed_command())
+            .collect();
         
         Self {
             mode: AppMode::Launcher,
-            entries,
+            entries: Arc::new(entries),
             filtered,
             selected: 0,
+            picked: None,
+            match_indices: HashMap::new(),
             filter: String::new(),
             filtering: false,
             config,
-            niri,
+            compositor,
             pty_session: None,
-            terminal: EmbeddedTerminal::new(TerminalConfig {
-                cols: 80,
-                rows: 24,
-                scrollback: scrollback_lines,
-                ..Default::default()
-            }),
+            terminal: new_terminal(scrollback_lines),
             matcher: Matcher::new(nucleo_matcher::Config::DEFAULT),
             history,
-            frecency_weight,
             grid_layout,
+            wakeup: Wakeup::new()
+                .inspect_err(|e| tracing::warn!("No wakeup for command output: {}", e))
+                .ok(),
+            elevation_armed: false,
+            category_list: None,
+            last_run: None,
+            output_search: None,
+            link_picker: None,
+            status_message: None,
+            notifier: Box::new(NotifySend),
+            mode_rules,
+            jobs: Vec::new(),
+            selected_job: 0,
+            last_click: None,
+            help_scroll: None,
+            output_area: Rect::default(),
+            preview,
+            glyph_icons,
+            compositor_events,
+            awaiting_window: None,
+            pending_launch: None,
+            compositor_list: None,
+            floating_before_command: None,
+            compositor_enabled,
+            compositor_health: CompositorHealth::Off,
+            compositor_health_checked: None,
+            dmenu: None,
+            launched: false,
+            last_status: None,
+            propagate_exit: false,
+            keyboard_enhancement: false,
+            executables: Vec::new(),
+            executable_scan: None,
+            run_mode: false,
+            hidden: false,
+            execution: ExecutionPolicy::Run,
+            printed: None,
+            dirty: true,
+            last_draw: None,
+            match_worker: None,
+            match_generation: 0,
+            ranking_pending: false,
+            entries_replaced: false,
+            forgotten: None,
+        }
+    }
+
+    /// Swap in a config reloaded from disk
+    ///
+    /// Theme, layout, keys and behavior apply right away; settings only
+    /// read at startup keep their old values until drun is restarted.
+    pub fn reload_config(&mut self, mut config: Config) {
+        let restart = config.keep_startup_settings(&self.config);
+        if !restart.is_empty() {
+            tracing::warn!("Config reloaded; restart drun to apply {}", restart.join(", "));
+        }
+        if config.appearance.show_preview != self.config.appearance.show_preview {
+            self.preview = config.appearance.show_preview;
+        }
+        self.mode_rules = config.mode_rules();
+        self.glyph_icons = config.glyph_icons();
+        // Refitted to the width by the caller
+        self.grid_layout = config.grid_layout(0);
+        self.config = config;
+        self.update_filtered();
+        tracing::info!("Config reloaded");
+    }
+
+    /// Keep the running config and say why the reloaded one was rejected
+    pub fn reject_config(&mut self, error: &anyhow::Error) {
+        tracing::warn!("Config not reloaded: {:#}", error);
+        // The status bar has one line; TOML errors go on with a snippet
+        let message = format!("{:#}", error);
+        let first_line = message.lines().next().unwrap_or_default();
+        self.show_status(format!("Config not reloaded: {}", first_line), true);
+    }
+
+    /// Replace the loaded entries after a re-scan
+    ///
+    /// Keeps the current filter, and the picked entry selected if it still
+    /// exists. History is keyed by id, so frecency scores carry over
+    /// unchanged.
+    ///
+    /// Returns the ids of entries whose icon changed.
+    pub fn reload_entries(&mut self, entries: Vec<Entry>) -> Vec<String> {
+        let old_icons: HashMap<&str, Option<&str>> = self
+            .app_entries()
+            .iter()
+            .map(|e| (e.id.as_str(), e.icon.as_deref()))
+            .collect();
+
+        let mut entries = with_typed_commands(with_config_entries(entries, &self.config), &self.history);
+        entries.extend(self.executables.iter().cloned());
+        let changed_icons = entries
+            .iter()
+            .filter(|e| old_icons.get(e.id.as_str()).is_some_and(|icon| *icon != e.icon.as_deref()))
+            .map(|e| e.id.clone())
+            .collect();
+        // A compositor list stays up; the new entries show when it closes
+        if let Some(ref mut listing) = self.compositor_list {
+            listing.apps = entries;
+            return changed_icons;
         }
+        self.entries = Arc::new(entries);
+        self.entries_replaced = true;
+        self.update_filtered();
+        changed_icons
     }
 
     /// Get currently visible entries
@@ -129,11 +736,33 @@ impl App {
         self.filtered.iter().map(|&i| &self.entries[i]).collect()
     }
 
+    /// Entries on the current page of the grid, the selected one first
+    pub fn page_entries(&self) -> Vec<&Entry> {
+        let range = self.grid_layout.visible_range(self.selected, self.filtered.len());
+        let mut entries: Vec<_> = self.filtered[range.clone()]
+            .iter()
+            .map(|&i| &self.entries[i])
+            .collect();
+        if range.contains(&self.selected) {
+            entries.swap(0, self.selected - range.start);
+        }
+        entries
+    }
+
     /// Get the currently selected entry
     pub fn selected_entry(&self) -> Option<&Entry> {
         self.filtered.get(self.selected).map(|&i| &self.entries[i])
     }
 
+    /// Characters of the name of the visible entry at `index` that matched
+    /// the filter, as char positions
+    pub fn name_match_indices(&self, index: usize) -> &[u32] {
+        self.filtered
+            .get(index)
+            .and_then(|i| self.match_indices.get(i))
+            .map_or(&[], Vec::as_slice)
+    }
+
     /// Get selected index
     pub fn selected_index(&self) -> usize {
         self.selected
@@ -141,52 +770,157 @@ impl App {
 
     /// Move selection up
     pub fn previous(&mut self) {
-        self.selected = self.grid_layout.move_up(self.selected);
+        self.pick(self.grid_layout.move_up(self.selected));
     }
 
     /// Move selection down
     pub fn next(&mut self) {
-        self.selected = self.grid_layout.move_down(self.selected, self.filtered.len());
+        self.pick(self.grid_layout.move_down(self.selected, self.filtered.len()));
     }
 
     /// TEAM_004: Move selection left (previous column)
     pub fn move_left(&mut self) {
-        self.selected = self.grid_layout.move_left(self.selected);
+        self.pick(self.grid_layout.move_left(self.selected));
     }
 
     /// TEAM_004: Move selection right (next column)
     pub fn move_right(&mut self) {
-        self.selected = self.grid_layout.move_right(self.selected, self.filtered.len());
+        self.pick(self.grid_layout.move_right(self.selected, self.filtered.len()));
     }
 
     /// TEAM_004: Tab navigation (next with wrap)
     pub fn tab_next(&mut self) {
-        self.selected = self.grid_layout.tab_next(self.selected, self.filtered.len());
+        self.pick(self.grid_layout.tab_next(self.selected, self.filtered.len()));
     }
 
     /// TEAM_004: Shift+Tab navigation (previous with wrap)
     pub fn tab_prev(&mut self) {
-        self.selected = self.grid_layout.tab_prev(self.selected, self.filtered.len());
+        self.pick(self.grid_layout.tab_prev(self.selected, self.filtered.len()));
     }
 
     /// TEAM_004: Page up
     pub fn page_up(&mut self) {
-        self.selected = self.grid_layout.page_up(self.selected);
+        self.pick(self.grid_layout.page_up(self.selected));
     }
 
     /// TEAM_004: Page down
     pub fn page_down(&mut self) {
-        self.selected = self.grid_layout.page_down(self.selected, self.filtered.len());
+        self.pick(self.grid_layout.page_down(self.selected, self.filtered.len()));
+    }
+
+    /// Jump to the first entry of `page` (a click on the scrollbar)
+    pub fn go_to_page(&mut self, page: usize) {
+        let page_size = self.grid_layout.visible_count();
+        if page != self.selected / page_size {
+            self.pick((page * page_size).min(self.filtered.len().saturating_sub(1)));
+        }
     }
 
     /// TEAM_004: Move to first entry
     pub fn move_home(&mut self) {
-        self.selected = self.grid_layout.move_home();
+        self.pick(self.grid_layout.move_home());
     }
 
     /// TEAM_004: Move to last entry
     pub fn move_end(&mut self) {
-        self.selected = self.grid_layout.move_end(self.filtered.len());
+        self.pick(self.grid_layout.move_end(self.filtered.len()));
+    }
+
+    /// Select the card in `slot` of the current page (a mouse click)
+    ///
+    /// Returns true for a second click on the same card within the
+    /// double-click time, which runs it.
+    pub fn click_card(&mut self, slot: usize) -> bool {
+        let page_start = self
+            .grid_layout
+            .visible_range(self.selected, self.filtered.len())
+            .start;
+        let index = page_start + slot;
+        if index >= self.filtered.len() {
+            return false;
+        }
+        self.elevation_armed = false;
+        self.pick(index);
+
+        let now = Instant::now();
+        let double = self
+            .last_click
+            .is_some_and(|(at, clicked)| clicked == index && now.duration_since(at) <= DOUBLE_CLICK);
+        // A third click starts over rather than running it again
+        self.last_click = (!double).then_some((now, index));
+        double
+    }
+
+    /// Whether the detail pane is shown
+    pub fn show_preview(&self) -> bool {
+        self.preview
+    }
+
+    /// Show or hide the detail pane
+    pub fn toggle_preview(&mut self) {
+        self.preview = !self.preview;
+    }
+
+    /// Terminal mode `entry` would run in
+    pub fn entry_mode(&self, entry: &Entry) -> Option<TerminalMode> {
+        let cmd = entry.command()?;
+        let detect_entry = (!entry.is_typed_command()).then_some(entry);
+        Some(TerminalMode::detect(&cmd, detect_entry, &self.mode_rules))
+    }
+
+    /// How often and when `entry_id` was launched (None if never, or with
+    /// history disabled)
+    pub fn usage_stats(&self, entry_id: &str) -> Option<UsageStats> {
+        self.history.stats(entry_id)
+    }
+
+    /// Forget the selected entry's history, so frecency no longer ranks it
+    /// (Ctrl+Delete)
+    ///
+    /// Pressed again within `FORGET_UNDO_WINDOW`, brings back what was just
+    /// forgotten instead, whatever is selected by then.
+    pub fn toggle_forget(&mut self) {
+        if let Some(forgotten) = self.forgotten.take().filter(|f| f.at.elapsed() < FORGET_UNDO_WINDOW) {
+            self.history.restore(&forgotten.entry_id, forgotten.stats);
+            self.show_status(format!("Restored the history of {}", forgotten.name), false);
+            self.update_filtered();
+            return;
+        }
+        let Some(entry) = self.selected_entry() else {
+            return;
+        };
+        let (entry_id, name) = (entry.id.clone(), entry.name.clone());
+        let Some(stats) = self.history.forget(&entry_id) else {
+            self.show_status(format!("{} has no history", name), false);
+            return;
+        };
+        tracing::info!("Forgot the history of {}", entry_id);
+        self.show_status(
+            format!("Forgot {} launches of {} | Ctrl+Delete: undo", stats.count, name),
+            false,
+        );
+        self.forgotten = Some(ForgottenHistory {
+            entry_id,
+            name,
+            stats,
+            at: Instant::now(),
+        });
+        self.update_filtered();
+    }
+
+    /// Refit the grid to an entry list `list_width` cells wide
+    ///
+    /// Only changes anything with `appearance.columns = "auto"`. The
+    /// selection is an index into the filtered entries, independent of the
+    /// column count, so the same entry stays selected; only the page it is
+    /// on changes.
+    pub fn fit_grid(&mut self, list_width: u16) {
+        self.grid_layout = self.config.grid_layout(list_width);
+    }
+
+    /// Text icons (`icons.fallback`), None if disabled
+    pub fn glyph_icons(&self) -> Option<&GlyphIcons> {
+        self.glyph_icons.as_ref()
     }
 
     /// TEAM_004: Get grid layout reference
@@ -222,6 +956,98 @@ impl App {
         self.update_filtered();
     }
 
+    /// Add pasted text to the filter
+    ///
+    /// The filter is a single line: line breaks and tabs become spaces and
+    /// other control characters are dropped.
+    pub fn paste_into_filter(&mut self, text: &str) {
+        self.elevation_armed = false;
+        self.filtering = true;
+        self.filter.extend(text.chars().filter_map(|c| match c {
+            '\n' | '\r' | '\t' => Some(' '),
+            c if c.is_control() => None,
+            c => Some(c),
+        }));
+        self.update_filtered();
+    }
+
+    /// Look for programs on `$PATH` in the background, for run mode
+    pub fn start_executable_scan(&mut self, dirs: Vec<PathBuf>, cache_path: Option<PathBuf>) {
+        self.executable_scan = Some(executables::spawn_scan(dirs, cache_path));
+    }
+
+    /// Add the programs the `$PATH` scan has found since the last call;
+    /// whether there were any
+    pub fn poll_executables(&mut self) -> bool {
+        let Some(ref mut scan) = self.executable_scan else {
+            return false;
+        };
+        let mut found = Vec::new();
+        loop {
+            match scan.try_recv() {
+                Ok(entries) => found.extend(entries),
+                Err(mpsc::error::TryRecvError::Empty) => break,
+                Err(mpsc::error::TryRecvError::Disconnected) => {
+                    self.executable_scan = None;
+                    break;
+                }
+            }
+        }
+        if found.is_empty() {
+            return false;
+        }
+        self.add_executables(found);
+        true
+    }
+
+    /// Add programs from `$PATH`, keeping the selection in run mode
+    pub fn add_executables(&mut self, found: Vec<Entry>) {
+        self.executables.extend(found.iter().cloned());
+        // Under a compositor list they join the applications behind it
+        if let Some(ref mut listing) = self.compositor_list {
+            listing.apps.extend(found);
+            return;
+        }
+        Arc::make_mut(&mut self.entries).extend(found);
+        if self.showing_executables() {
+            self.update_filtered();
+        }
+    }
+
+    /// List programs on `$PATH` instead of applications (`--mode run`)
+    pub fn set_run_mode(&mut self, run_mode: bool) {
+        self.run_mode = run_mode;
+        self.select_first();
+        self.update_filtered();
+    }
+
+    /// Programs on `$PATH` are listed, by `--mode run` or a `>` filter
+    pub fn showing_executables(&self) -> bool {
+        self.compositor_list.is_none()
+            && self.dmenu.is_none()
+            && (self.run_mode || search::parse_query(&self.filter).run)
+    }
+
+    /// Start with `text` in the filter, filtering (`--filter`)
+    pub fn set_initial_filter(&mut self, text: &str) {
+        self.filter.clear();
+        self.paste_into_filter(text);
+    }
+
+    /// Select the listed entry with `id` (`--select`)
+    ///
+    /// An id that isn't listed (unknown, or filtered out) leaves the first
+    /// entry selected.
+    pub fn select_id(&mut self, id: &str) {
+        match self.filtered.iter().position(|&i| self.entries[i].id == id) {
+            Some(pos) => self.pick(pos),
+            None => {
+                tracing::warn!("No entry {} to select", id);
+                self.select_first();
+            }
+        }
+    }
+
     /// Remove last character from filter
     pub fn pop_filter_char(&mut self) {
         self.filter.pop();
@@ -233,162 +1059,705 @@ impl App {
 
     /// Update filtered list based on current filter
     /// TEAM_001: Integrated frecency scoring
+    ///
+    /// An entry the user picked stays selected wherever it moves, as long
+    /// as it's listed, so typing on doesn't launch something else. Until
+    /// then, the best match at the top is.
     fn update_filtered(&mut self) {
-        if self.filter.is_empty() {
-            // No filter: sort by frecency only
-            let mut scored: Vec<(usize, f64)> = self
-                .entries
-                .iter()
-                .enumerate()
-                .map(|(i, entry)| {
-                    let frecency = self.history.frecency_score(&entry.id);
-                    (i, frecency)
-                })
-                .collect();
-
-            // Sort by frecency descending, then alphabetically for ties
-            scored.sort_by(|a, b| {
-                b.1.partial_cmp(&a.1)
-                    .unwrap_or(std::cmp::Ordering::Equal)
-                    .then_with(|| {
-                        self.entries[a.0].name.cmp(&self.entries[b.0].name)
-                    })
-            });
-            self.filtered = scored.into_iter().map(|(i, _)| i).collect();
-        } else {
-            let pattern = Pattern::parse(&self.filter, CaseMatching::Ignore, Normalization::Smart);
-
-            // Combine fuzzy score with frecency
-            let mut scored: Vec<(usize, f64)> = self
-                .entries
-                .iter()
-                .enumerate()
-                .filter_map(|(i, entry)| {
-                    let haystack = entry.search_text();
-                    let mut buf = Vec::new();
-                    pattern
-                        .score(nucleo_matcher::Utf32Str::new(&haystack, &mut buf), &mut self.matcher)
-                        .map(|fuzzy_score| {
-                            let frecency = self.history.frecency_score(&entry.id);
-                            // Weighted combination: fuzzy_score normalized + frecency weight
-                            // Fuzzy scores are typically 0-1000+, frecency is 0-~500
-                            let fuzzy_norm = fuzzy_score as f64;
-                            let combined = fuzzy_norm * (1.0 - self.frecency_weight)
-                                + frecency * self.frecency_weight * 10.0; // Scale frecency
-                            (i, combined)
-                        })
-                })
-                .collect();
-
-            // Sort by combined score descending
-            scored.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
-            self.filtered = scored.into_iter().map(|(i, _)| i).collect();
-        }
-
-        // Reset selection if out of bounds
-        if self.selected >= self.filtered.len() {
-            self.selected = 0;
+        // Pending rankings are for an older filter now
+        self.match_generation += 1;
+        let ranker = self.ranker();
+        match self.match_worker {
+            // The current list stays up until the worker's ranking arrives
+            // (see `poll_matches`)
+            Some(ref worker) if self.entries.len() >= BACKGROUND_MATCH_MIN && !self.entries_replaced => {
+                worker.send(MatchJob {
+                    generation: self.match_generation,
+                    entries: Arc::clone(&self.entries),
+                    ranker,
+                });
+                self.ranking_pending = true;
+            }
+            _ => self.rank_now(ranker),
         }
     }
 
-    /// Get current application mode
-    pub fn mode(&self) -> &AppMode {
-        &self.mode
+    /// Ranker for the current filter
+    fn ranker(&self) -> Ranker {
+        // Compositor lists keep the compositor's order, dmenu lines the input order
+        let keep_order = self.compositor_list.is_some() || self.dmenu.is_some();
+        Ranker::new(
+            &self.filter,
+            self.showing_executables(),
+            keep_order,
+            Arc::new(self.history.frecency_scores()),
+            self.config.history.frecency_weight,
+        )
     }
 
-    /// Check if we're in launcher mode
-    /// NOTE: Currently unused but kept for API completeness - may be useful for plugins/extensions
-    #[allow(dead_code)]
-    pub fn is_launcher_mode(&self) -> bool {
-        matches!(self.mode, AppMode::Launcher)
+    /// Rank all entries with `ranker` on this thread
+    fn rank_now(&mut self, mut ranker: Ranker) {
+        ranker.score(&self.entries, 0..self.entries.len(), &mut self.matcher);
+        (self.filtered, self.match_indices) = ranker.finish(&self.entries);
+        self.ranking_pending = false;
+        self.entries_replaced = false;
+        self.reselect_picked(true);
     }
 
-    /// Check if we're executing a command
-    pub fn is_executing(&self) -> bool {
-        matches!(self.mode, AppMode::Executing { .. })
+    /// Complete the ranking the match worker is still on, so what's listed
+    /// and selected is for the current filter (before running the
+    /// selected entry)
+    pub fn finish_ranking(&mut self) {
+        if !self.ranking_pending {
+            return;
+        }
+        self.match_generation += 1;
+        let ranker = self.ranker();
+        self.rank_now(ranker);
     }
 
-    /// Check if we're in post-execution mode
-    /// NOTE: Currently unused but kept for API completeness - may be useful for plugins/extensions
-    #[allow(dead_code)]
-    pub fn is_post_execution(&self) -> bool {
-        matches!(self.mode, AppMode::PostExecution { .. })
+    /// Show the rankings the match worker sent since the last call;
+    /// whether there was one for the current filter
+    fn poll_matches(&mut self) -> bool {
+        let Some(ref worker) = self.match_worker else {
+            return false;
+        };
+        let mut latest = None;
+        while let Some(ranking) = worker.try_recv() {
+            if ranking.generation == self.match_generation {
+                latest = Some(ranking);
+            }
+        }
+        let Some(ranking) = latest else {
+            return false;
+        };
+        self.filtered = ranking.ranked;
+        self.match_indices = ranking.match_indices;
+        self.ranking_pending = !ranking.complete;
+        self.reselect_picked(ranking.complete);
+        true
     }
 
-    /// Get terminal reference
-    pub fn terminal(&self) -> &EmbeddedTerminal {
-        &self.terminal
+    /// Rank long lists on a worker thread from now on, so typing never
+    /// waits for the ranking
+    pub fn start_match_worker(&mut self) {
+        match MatchWorker::spawn() {
+            Ok(worker) => self.match_worker = Some(worker),
+            Err(e) => tracing::warn!("Failed to start matcher thread: {:#}", e),
+        }
     }
 
-    /// Get mutable terminal reference
-    pub fn terminal_mut(&mut self) -> &mut EmbeddedTerminal {
-        &mut self.terminal
+    /// The picked entry where it is now, or the first if it's gone
+    ///
+    /// A partial ranking may not have reached the picked entry yet: then it
+    /// stays picked for the complete one.
+    fn reselect_picked(&mut self, complete: bool) {
+        let picked = self
+            .picked
+            .as_ref()
+            .and_then(|id| self.filtered.iter().position(|&i| self.entries[i].id == *id));
+        match picked {
+            Some(pos) => self.selected = pos,
+            None if complete => self.select_first(),
+            None => self.selected = 0,
+        }
     }
 
-    /// Start executing the selected entry
-    /// TEAM_000: Phase 2 - In-place execution with PTY
-    /// TEAM_001: Records usage for frecency
-    pub async fn execute_entry(&mut self, entry: Entry, cols: u16, rows: u16) -> Result<()> {
-        let Some(cmd) = entry.command() else {
-            tracing::warn!("Entry {} has no command", entry.id);
-            return Ok(());
-        };
+    /// Select the entry at `index` the user chose, keeping it selected
+    /// while it's listed
+    fn pick(&mut self, index: usize) {
+        self.selected = index;
+        self.picked = self.selected_entry().map(|entry| entry.id.clone());
+    }
 
-        tracing::info!("Executing: {}", cmd);
+    /// Select the first entry, the best match
+    fn select_first(&mut self) {
+        self.selected = 0;
+        self.picked = None;
+    }
 
-        // TEAM_001: Record usage for frecency sorting
-        if self.config.history.enabled {
-            self.history.record_usage(&entry.id);
-            // Re-sort entries so next time this entry appears higher
-            self.update_filtered();
-        }
+    /// The listed entries in order, with their scores (`--list`)
+    pub fn listed_entries(&mut self) -> Vec<ListedEntry> {
+        let text = search::parse_query(&self.filter).pattern_text();
+        let pattern = Pattern::parse(&text, CaseMatching::Ignore, Normalization::Smart);
+        self.filtered
+            .iter()
+            .map(|&i| {
+                let entry = &self.entries[i];
+                let score = (!text.is_empty())
+                    .then(|| search::score_entry(entry, &text, &pattern, &mut self.matcher))
+                    .flatten();
+                ListedEntry::new(entry, &self.mode_rules, self.history.frecency_score(&entry.id), score)
+            })
+            .collect()
+    }
 
-        // Detect terminal mode
-        let terminal_mode = TerminalMode::detect(&cmd, Some(&entry));
-        tracing::debug!("Terminal mode: {:?}", terminal_mode);
+    /// Categories named by `#category` operators, for the breadcrumb
+    ///
+    /// Resolves each typed prefix to a main category when it's unambiguous.
+    pub fn active_category(&self) -> Option<String> {
+        let categories: Vec<&str> = search::parse_query(&self.filter)
+            .categories
+            .into_iter()
+            .filter(|category| !category.is_empty())
+            .map(|category| {
+                let prefix = category.to_lowercase();
+                let mut matching = search::MAIN_CATEGORIES
+                    .iter()
+                    .filter(|c| c.to_lowercase().starts_with(&prefix));
+                match (matching.next(), matching.next()) {
+                    (Some(main), None) => main,
+                    _ => category,
+                }
+            })
+            .collect();
+        (!categories.is_empty()).then(|| categories.join(" + "))
+    }
 
-        // Handle TUI apps specially - they need full terminal control
-        if terminal_mode == TerminalMode::Tui {
-            self.mode = AppMode::TuiHandover { command: cmd };
-            return Ok(());
-        }
+    /// Operators of the filter beyond fuzzy text, for the status bar
+    pub fn search_operators(&self) -> Vec<&'static str> {
+        search::parse_query(&self.filter).operators()
+    }
 
-        // Handle GUI apps - launch detached and exit
-        if terminal_mode == TerminalMode::Gui {
-            tracing::info!("Launching GUI app detached: {}", cmd);
-            std::process::Command::new("sh")
-                .arg("-c")
-                .arg(&cmd)
-                .stdin(std::process::Stdio::null())
-                .stdout(std::process::Stdio::null())
-                .stderr(std::process::Stdio::null())
-                .spawn()?;
-            // Signal exit - GUI app runs independently
-            self.mode = AppMode::Exit;
-            return Ok(());
+    /// Open the category browser (not for compositor lists)
+    pub fn open_categories(&mut self) {
+        if self.compositor_list.is_some() {
+            return;
         }
+        self.category_list = Some(CategoryList {
+            categories: search::category_counts(&self.entries),
+            selected: 0,
+        });
+    }
 
-        // Unfloat window if configured
-        if self.config.niri.unfloat_on_execute {
-            if let Some(ref niri) = self.niri {
-                niri.set_floating(false).await.ok();
-            }
-        }
+    /// Close the category browser without choosing
+    pub fn close_categories(&mut self) {
+        self.category_list = None;
+    }
 
-        // Clear terminal and filter for new command
-        self.terminal.clear();
-        self.terminal.resize(cols as usize, rows as usize);
-        self.filter.clear();
-        self.update_filtered();
+    /// Get the category browser, if open
+    pub fn category_list(&self) -> Option<&CategoryList> {
+        self.category_list.as_ref()
+    }
 
-        // Spawn PTY session
-        let session = PtySession::spawn(&cmd, cols, rows)?;
-        self.pty_session = Some(session);
+    /// Check if the category browser is open
+    pub fn is_browsing_categories(&self) -> bool {
+        self.category_list.is_some()
+    }
 
-        // Enter executing mode
+    /// Move the category selection by `delta` rows (clamped)
+    pub fn move_category(&mut self, delta: isize) {
+        if let Some(ref mut list) = self.category_list {
+            let last = list.categories.len().saturating_sub(1);
+            list.selected = list.selected.saturating_add_signed(delta).min(last);
+        }
+    }
+
+    /// Filter by the selected category and close the browser
+    pub fn choose_category(&mut self) {
+        let Some(list) = self.category_list.take() else {
+            return;
+        };
+        if let Some((name, _)) = list.categories.get(list.selected) {
+            self.filter = format!("#{} ", name);
+            self.filtering = true;
+            self.select_first();
+            self.update_filtered();
+        }
+    }
+
+    /// Compositor list shown instead of applications, if any
+    pub fn compositor_list(&self) -> Option<CompositorList> {
+        self.compositor_list.as_ref().map(|listing| listing.kind)
+    }
+
+    /// Why the compositor list is empty, when it couldn't be asked
+    pub fn compositor_list_error(&self) -> Option<&str> {
+        self.compositor_list.as_ref()?.error.as_deref()
+    }
+
+    /// Switch between the window list and applications (Ctrl+W)
+    pub async fn toggle_windows(&mut self) {
+        if self.compositor_list() == Some(CompositorList::Windows) {
+            self.show_applications();
+        } else {
+            self.show_windows().await;
+        }
+    }
+
+    /// List the compositor's open windows (also refreshes an open list)
+    pub async fn show_windows(&mut self) {
+        let windows = self.fetch_windows().await;
+        self.set_compositor_list(CompositorList::Windows, windows);
+    }
+
+    /// List the compositor's workspaces (also refreshes an open list)
+    pub async fn show_workspaces(&mut self) {
+        let workspaces = match self.connected_compositor() {
+            Ok(wm) => wm.workspaces().await.map(|ws| windows::workspace_entries(&ws)),
+            Err(e) => Err(e),
+        };
+        self.set_compositor_list(CompositorList::Workspaces, workspaces);
+    }
+
+    async fn fetch_windows(&self) -> Result<Vec<Entry>> {
+        let wm = self.connected_compositor()?;
+        let windows = wm.windows().await?;
+        // Without workspace names windows are still listed
+        let workspaces = wm.workspaces().await.unwrap_or_else(|e| {
+            tracing::debug!("Failed to get {} workspaces: {}", wm.name(), e);
+            Vec::new()
+        });
+        Ok(windows::entries(&windows, &workspaces, self.app_entries()))
+    }
+
+    fn connected_compositor(&self) -> Result<&dyn Compositor> {
+        self.compositor
+            .as_deref()
+            .ok_or_else(|| anyhow::anyhow!("No compositor connected"))
+    }
+
+    /// Application entries, also while a compositor list is shown
+    fn app_entries(&self) -> &[Entry] {
+        self.compositor_list.as_ref().map_or(&self.entries, |listing| &listing.apps)
+    }
+
+    /// Show `entries` as a compositor list, or an empty list with the error
+    fn set_compositor_list(&mut self, kind: CompositorList, entries: Result<Vec<Entry>>) {
+        let (entries, error) = match entries {
+            Ok(entries) => {
+                tracing::debug!("Listing {} {:?} from the compositor", entries.len(), kind);
+                (entries, None)
+            }
+            Err(e) => {
+                tracing::warn!("Can't list {:?} from the compositor: {:#}", kind, e);
+                (Vec::new(), Some(format!("{:#}", e)))
+            }
+        };
+        let shown = std::mem::replace(&mut self.entries, Arc::new(entries));
+        self.entries_replaced = true;
+        let apps = match self.compositor_list.take() {
+            Some(listing) => listing.apps,
+            None => Arc::unwrap_or_clone(shown),
+        };
+        self.compositor_list = Some(CompositorListing { kind, apps, error });
+        self.category_list = None;
+        self.elevation_armed = false;
+        self.filter.clear();
+        self.filtering = false;
+        self.select_first();
+        self.update_filtered();
+    }
+
+    /// Back from a compositor list to applications
+    pub fn show_applications(&mut self) {
+        let Some(listing) = self.compositor_list.take() else {
+            return;
+        };
+        self.entries = Arc::new(listing.apps);
+        self.entries_replaced = true;
+        self.filter.clear();
+        self.filtering = false;
+        self.select_first();
+        self.update_filtered();
+    }
+
+    /// Focus a listed window and exit
+    async fn focus_window(&mut self, id: u64) {
+        let result = match self.connected_compositor() {
+            Ok(wm) => wm.focus_window(id).await,
+            Err(e) => Err(e),
+        };
+        self.finish_compositor_action(result, "focus window").await;
+    }
+
+    /// Switch to a listed workspace and exit
+    async fn focus_workspace(&mut self, id: u64) {
+        let result = match self.connected_compositor() {
+            Ok(wm) => wm.focus_workspace(WorkspaceReference::Id(id)).await,
+            Err(e) => Err(e),
+        };
+        self.finish_compositor_action(result, "switch workspace").await;
+    }
+
+    /// Move the focused window to the workspace of `entry` and follow it
+    /// there (Shift+Enter in the workspace list)
+    pub async fn move_focused_window(&mut self, entry: &Entry) {
+        let Some(id) = windows::workspace_id(entry) else {
+            return;
+        };
+        let result = match self.connected_compositor() {
+            Ok(wm) => wm.move_window_to_workspace(None, WorkspaceReference::Id(id), true).await,
+            Err(e) => Err(e),
+        };
+        self.finish_compositor_action(result, "move window").await;
+    }
+
+    /// Exit after a compositor action; when it failed (the window or workspace
+    /// is gone), refresh the list instead
+    async fn finish_compositor_action(&mut self, result: Result<()>, what: &str) {
+        match result {
+            Ok(()) => {
+                self.launched = true;
+                self.mode = AppMode::Exit;
+            }
+            Err(e) => {
+                tracing::warn!("Failed to {}: {:#}", what, e);
+                match self.compositor_list() {
+                    Some(CompositorList::Windows) => self.show_windows().await,
+                    Some(CompositorList::Workspaces) => self.show_workspaces().await,
+                    None => {}
+                }
+            }
+        }
+    }
+
+    /// Get current application mode
+    pub fn mode(&self) -> &AppMode {
+        &self.mode
+    }
+
+    /// Check if we're in launcher mode
+    /// NOTE: Currently unused but kept for API completeness - may be useful for plugins/extensions
+    #[allow(dead_code)]
+    pub fn is_launcher_mode(&self) -> bool {
+        matches!(self.mode, AppMode::Launcher)
+    }
+
+    /// Check if we're executing a command
+    pub fn is_executing(&self) -> bool {
+        matches!(self.mode, AppMode::Executing { .. })
+    }
+
+    /// Check if we're in post-execution mode
+    pub fn is_post_execution(&self) -> bool {
+        matches!(self.mode, AppMode::PostExecution { .. })
+    }
+
+    /// Get terminal reference
+    pub fn terminal(&self) -> &EmbeddedTerminal {
+        &self.terminal
+    }
+
+    /// Get mutable terminal reference
+    pub fn terminal_mut(&mut self) -> &mut EmbeddedTerminal {
+        &mut self.terminal
+    }
+
+    /// Start executing the selected entry
+    /// TEAM_000: Phase 2 - In-place execution with PTY
+    /// TEAM_001: Records usage for frecency
+    pub async fn execute_entry(&mut self, entry: Entry, cols: u16, rows: u16) -> Result<()> {
+        self.run_entry(entry, false, cols, rows).await
+    }
+
+    /// Execute an entry as root through `behavior.elevate_command`
+    pub async fn execute_entry_elevated(&mut self, entry: Entry, cols: u16, rows: u16) -> Result<()> {
+        self.run_entry(entry, true, cols, rows).await
+    }
+
+    async fn run_entry(&mut self, entry: Entry, elevate: bool, cols: u16, rows: u16) -> Result<()> {
+        self.elevation_armed = false;
+        if self.pending_launch.is_some() {
+            return Ok(());
+        }
+        // dmenu mode picks; nothing is ever run
+        if let Some(ref mut dmenu) = self.dmenu {
+            dmenu.pick_entry(&entry);
+            self.history.record_usage(&entry.id);
+            self.mode = AppMode::Exit;
+            return Ok(());
+        }
+        if let Some(id) = windows::window_id(&entry) {
+            self.focus_window(id).await;
+            return Ok(());
+        }
+        if let Some(id) = windows::workspace_id(&entry) {
+            self.focus_workspace(id).await;
+            return Ok(());
+        }
+        let Some(cmd) = entry.command() else {
+            tracing::warn!("Entry {} has no command", entry.id);
+            return Ok(());
+        };
+
+        // TEAM_001: Record usage for frecency sorting
+        if self.config.history.enabled {
+            self.history.record_usage(&entry.id);
+            // Re-sort entries so next time this entry appears higher
+            self.update_filtered();
+        }
+
+        // --print-only: no terminal mode, hooks, PTY or compositor
+        if self.execution == ExecutionPolicy::Print {
+            let command = if elevate {
+                format!("{} {}", self.config.behavior.elevate_command.trim(), cmd)
+            } else {
+                cmd
+            };
+            self.printed = Some(PrintedCommand { id: entry.id, command });
+            self.mode = AppMode::Exit;
+            return Ok(());
+        }
+
+        // Detect terminal mode (typed commands are detected like raw
+        // commands, from the command line alone)
+        let detect_entry = (!entry.is_typed_command()).then_some(&entry);
+        let terminal_mode = TerminalMode::detect(&cmd, detect_entry, &self.mode_rules);
+
+        let (cmd, terminal_mode) = if elevate {
+            let prefix = self.config.behavior.elevate_command.trim();
+            (format!("{} {}", prefix, cmd), terminal_mode.elevated(prefix))
+        } else {
+            (cmd, terminal_mode)
+        };
+        tracing::info!("Executing: {}", cmd);
+        tracing::debug!("Terminal mode: {:?}", terminal_mode);
+
+        // Shell, Path= working directory and environment for the child
+        let launch = self.config.launch_options(Some(&entry));
+
+        self.launched = true;
+        let run = LastRun {
+            command: cmd,
+            mode: terminal_mode,
+            launch,
+            entry_id: Some(entry.id.clone()),
+            elevated: elevate,
+        };
+        self.last_run = Some(run.clone());
+        self.launch_after_hooks(Some(entry), run, cols, rows).await
+    }
+
+    /// Launch `run` once `[hooks] pre_exec` are done
+    ///
+    /// The hooks run on their own thread while the launcher stays up and
+    /// says so; [`App::poll_launch`] launches when they finish.
+    async fn launch_after_hooks(&mut self, entry: Option<Entry>, run: LastRun, cols: u16, rows: u16) -> Result<()> {
+        if self.config.hooks.pre_exec.is_empty() {
+            return self.launch(entry, run, cols, rows).await;
+        }
+        let event = HookEvent {
+            entry_id: run.entry_id.clone(),
+            command: run.command.clone(),
+            ..Default::default()
+        };
+        let pre_exec = self.config.hooks.pre_exec.clone();
+        let timeout = Duration::from_secs(self.config.hooks.timeout_secs);
+        let wakeup = self.wakeup.clone();
+        let hooks = std::thread::spawn(move || {
+            hooks::run("pre_exec", &pre_exec, &event, timeout);
+            if let Some(wakeup) = wakeup {
+                wakeup.wake();
+            }
+        });
+        self.pending_launch = Some(PendingLaunch {
+            hooks,
+            entry,
+            run,
+            cols,
+            rows,
+        });
+        Ok(())
+    }
+
+    /// Launch the command whose pre_exec hooks finished; whether there
+    /// was one
+    pub async fn poll_launch(&mut self) -> Result<bool> {
+        if !self.pending_launch.as_ref().is_some_and(|pending| pending.hooks.is_finished()) {
+            return Ok(false);
+        }
+        let Some(PendingLaunch { entry, run, cols, rows, .. }) = self.pending_launch.take() else {
+            return Ok(false);
+        };
+        self.launch(entry, run, cols, rows).await?;
+        Ok(true)
+    }
+
+    /// Command line waiting for its pre_exec hooks, if any
+    pub fn pending_launch(&self) -> Option<&str> {
+        self.pending_launch.as_ref().map(|pending| pending.run.command.as_str())
+    }
+
+    /// Start `run`: GUI entries detached, everything else in the
+    /// embedded terminal
+    async fn launch(&mut self, entry: Option<Entry>, run: LastRun, cols: u16, rows: u16) -> Result<()> {
+        let LastRun {
+            command: cmd,
+            mode: terminal_mode,
+            launch,
+            elevated: elevate,
+            ..
+        } = run;
+
+        // Handle GUI apps - launch detached and exit
+        if let (TerminalMode::Gui, Some(entry)) = (terminal_mode, entry) {
+            // Terminal=true apps forced to GUI mode get an external terminal
+            let prefix = if entry.terminal {
+                self.config.terminal_command()
+            } else {
+                Vec::new()
+            };
+            let compositor_argv = match self.compositor {
+                Some(_) if self.config.compositor.spawn_via_ipc && !elevate => {
+                    compositor_spawn_argv(&entry, &prefix, &launch)
+                }
+                _ => None,
+            };
+            if !self.spawn_via_compositor(compositor_argv).await {
+                tracing::info!("Launching detached: {} {}", prefix.join(" "), cmd);
+                let mut command = launch.command(&prefix, &cmd);
+                command
+                    .stdin(std::process::Stdio::null())
+                    .stdout(std::process::Stdio::null())
+                    .stderr(std::process::Stdio::null());
+                command.spawn()?;
+            }
+            // Signal exit - GUI app runs independently. Under a compositor, wait
+            // for its window first so drun doesn't vanish before it shows
+            let wait = Duration::from_secs(self.config.compositor.wait_for_window_secs);
+            if self.compositor_events.is_some() && !wait.is_zero() {
+                let program = match prefix.first() {
+                    Some(terminal) => Some(terminal.as_str()),
+                    None => cmd.split_whitespace().next(),
+                };
+                self.awaiting_window = Some(AwaitedWindow::new(&entry, program, wait));
+            } else {
+                self.mode = AppMode::Exit;
+            }
+            return Ok(());
+        }
+
+        self.start_command(cmd, terminal_mode, launch, cols, rows).await
+    }
+
+    /// Have the compositor start `argv`; false if there is nothing to
+    /// start or it refused, to launch it locally instead
+    async fn spawn_via_compositor(&self, argv: Option<Vec<String>>) -> bool {
+        let (Some(wm), Some(argv)) = (self.compositor.as_deref(), argv) else {
+            return false;
+        };
+        tracing::info!("Launching through {}: {:?}", wm.name(), argv);
+        match wm.spawn(argv).await {
+            Ok(()) => true,
+            Err(e) => {
+                tracing::warn!("{} couldn't spawn the app, launching it directly: {:#}", wm.name(), e);
+                false
+            }
+        }
+    }
+
+    /// Ask to run the selected entry elevated; the next Enter confirms
+    pub fn arm_elevation(&mut self) {
+        self.elevation_armed = self.selected_entry().is_some() && self.compositor_list.is_none();
+    }
+
+    /// Cancel a pending elevated run
+    pub fn cancel_elevation(&mut self) {
+        self.elevation_armed = false;
+    }
+
+    /// Whether Enter will run the selected entry elevated
+    pub fn is_elevation_armed(&self) -> bool {
+        self.elevation_armed
+    }
+
+    /// Whether the last (or current) command was run elevated
+    pub fn last_run_elevated(&self) -> bool {
+        self.last_run.as_ref().is_some_and(|last| last.elevated)
+    }
+
+    /// Command line typed into the filter, if it can be run as a command
+    ///
+    /// `#category` filters and searches in compositor lists are never treated
+    /// as commands.
+    pub fn typed_command(&self) -> Option<&str> {
+        let cmd = self.filter.trim();
+        let cmd = cmd.strip_prefix('>').unwrap_or(cmd).trim_start();
+        (!cmd.is_empty() && !cmd.starts_with('#') && self.compositor_list.is_none()).then_some(cmd)
+    }
+
+    /// Run the filter text as a shell command (Shift+Enter)
+    ///
+    /// Goes through the same pipeline as entries; the command is recorded
+    /// in history under `cmd:<text>` so it shows up in later searches.
+    pub async fn execute_typed_command(&mut self, cols: u16, rows: u16) -> Result<()> {
+        let Some(cmd) = self.typed_command().map(str::to_string) else {
+            return Ok(());
+        };
+        if let Some(ref mut dmenu) = self.dmenu {
+            dmenu.pick_typed(&cmd);
+            self.mode = AppMode::Exit;
+            return Ok(());
+        }
+        let entry = Entry::typed_command(&cmd);
+        if !self.entries.iter().any(|e| e.id == entry.id) {
+            Arc::make_mut(&mut self.entries).push(entry.clone());
+        }
+        self.execute_entry(entry, cols, rows).await
+    }
+
+    /// Run the last executed command again (from post-execution view)
+    ///
+    /// Uses the same shell, working directory and environment, and records
+    /// another use in history if the command came from an entry.
+    pub async fn rerun_last(&mut self, cols: u16, rows: u16) -> Result<()> {
+        let Some(last) = self.last_run.clone().filter(|_| self.pending_launch.is_none()) else {
+            return Ok(());
+        };
+
+        tracing::info!("Re-running: {}", last.command);
+
+        if let Some(ref id) = last.entry_id {
+            if self.config.history.enabled {
+                self.history.record_usage(id);
+                self.update_filtered();
+            }
+        }
+
+        self.launched = true;
+        self.launch_after_hooks(None, last, cols, rows).await
+    }
+
+    /// Start a command in the embedded terminal, or hand over the terminal
+    /// for TUI apps
+    async fn start_command(
+        &mut self,
+        cmd: String,
+        terminal_mode: TerminalMode,
+        launch: LaunchOptions,
+        cols: u16,
+        rows: u16,
+    ) -> Result<()> {
+        // Handle TUI apps specially - they need full terminal control
+        if terminal_mode == TerminalMode::Tui {
+            self.mode = AppMode::TuiHandover {
+                command: cmd,
+                launch,
+            };
+            return Ok(());
+        }
+
+        self.unfloat_for_command().await;
+
+        // Clear terminal and filter for new command
+        self.terminal.clear();
+        self.output_search = None;
+        self.link_picker = None;
+        self.terminal.resize(cols as usize, rows as usize);
+        self.filter.clear();
+        self.update_filtered();
+
+        // Spawn PTY session
+        let session = PtySession::spawn(&cmd, cols, rows, &launch, self.wakeup.clone())?;
+        self.pty_session = Some(session);
+
+        // Enter executing mode
         self.mode = AppMode::Executing {
             command: cmd,
             mode: terminal_mode,
+            started: Instant::now(),
+            terminating: None,
         };
 
         Ok(())
@@ -396,51 +1765,84 @@ impl App {
 
     /// Execute a TUI app with full terminal handover
     /// Returns the exit code when the app exits
-    pub fn execute_tui(&mut self, cmd: &str) -> Result<Option<i32>> {
+    pub fn execute_tui(&mut self, cmd: &str, launch: &LaunchOptions) -> Result<Option<i32>> {
         use crossterm::{
+            event::{
+                DisableBracketedPaste, DisableFocusChange, EnableBracketedPaste, EnableFocusChange,
+                KeyboardEnhancementFlags, PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+            },
             execute,
             terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
         };
         use std::io;
+        use std::os::unix::process::ExitStatusExt;
 
-        // 1. Disable our TUI
+        // 1. Disable our TUI (the app enables bracketed paste, focus
+        // reports and keyboard protocols itself if it wants them)
         disable_raw_mode()?;
-        execute!(io::stdout(), LeaveAlternateScreen)?;
+        if self.keyboard_enhancement {
+            execute!(io::stdout(), PopKeyboardEnhancementFlags)?;
+        }
+        execute!(io::stdout(), LeaveAlternateScreen, DisableBracketedPaste, DisableFocusChange)?;
 
         // 2. Run the command directly
-        let status = std::process::Command::new("sh")
-            .arg("-c")
-            .arg(cmd)
-            .status()?;
+        let started = Instant::now();
+        let status = launch.command(&[], cmd).status()?;
+        let exit_status = match (status.code(), status.signal()) {
+            (Some(code), _) => CommandStatus::Exited(code),
+            (None, Some(signal)) => CommandStatus::Signaled(signal),
+            (None, None) => CommandStatus::Unknown,
+        };
+        let entry_id = self.last_run.as_ref().and_then(|last| last.entry_id.clone());
+        spawn_post_exec_hooks(&self.config, entry_id, cmd, &exit_status, started.elapsed());
 
         // 3. Restore our TUI
         enable_raw_mode()?;
-        execute!(io::stdout(), EnterAlternateScreen)?;
+        execute!(io::stdout(), EnterAlternateScreen, EnableBracketedPaste, EnableFocusChange)?;
+        if self.keyboard_enhancement {
+            execute!(
+                io::stdout(),
+                PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::DISAMBIGUATE_ESCAPE_CODES)
+            )?;
+        }
 
-        // Return to launcher mode
-        self.mode = AppMode::Launcher;
+        // Return to launcher mode (or exit with its status)
+        self.mode = if self.propagate_exit {
+            AppMode::Exit
+        } else {
+            AppMode::Launcher
+        };
+        self.last_status = Some(exit_status);
 
         Ok(status.code())
     }
 
     /// Poll PTY for output and check if command has exited
-    /// Returns true if command is still running
+    ///
+    /// Returns whether there was output or the command exited.
     pub fn poll_execution(&mut self) -> Result<bool> {
+        if self.is_timed_out() && self.config.behavior.oneshot_timeout_action == TimeoutAction::Kill {
+            tracing::warn!("Oneshot command timed out, stopping it");
+            self.kill_execution();
+        }
+
         let Some(ref mut session) = self.pty_session else {
             return Ok(false);
         };
 
         // Read available output and feed to terminal emulator
-        let mut buf = [0u8; 4096];
-        loop {
-            match session.try_read(&mut buf) {
-                Ok(Some(n)) if n > 0 => {
-                    self.terminal.write(&buf[..n]);
-                }
-                Ok(_) => break, // No more data or EOF
-                Err(e) => {
-                    tracing::warn!("PTY read error: {}", e);
-                    break;
+        let output = jobs::read_output(session, &mut self.terminal) > 0;
+
+        // Force-kill once the grace period after Ctrl+C is over
+        if let AppMode::Executing {
+            terminating: Some(since),
+            ..
+        } = self.mode
+        {
+            let grace = Duration::from_secs(self.config.behavior.kill_grace_secs);
+            if since.elapsed() >= grace {
+                if let Err(e) = session.force_kill() {
+                    tracing::warn!("Failed to kill command: {}", e);
                 }
             }
         }
@@ -451,36 +1853,51 @@ impl App {
                 // Process exited
                 let exit_status = CommandStatus::from_exit_status(status);
                 
-                // Extract command from current mode
-                let command = match &self.mode {
-                    AppMode::Executing { command, .. } => command.clone(),
-                    _ => String::new(),
+                // Extract command and run time from current mode
+                let (command, duration) = match &self.mode {
+                    AppMode::Executing { command, started, .. } => {
+                        (command.clone(), started.elapsed())
+                    }
+                    _ => (String::new(), Duration::ZERO),
                 };
 
+                if self.config.output.auto_save {
+                    let result = save_output(&self.config, &command, &self.terminal);
+                    self.report_save(result);
+                }
+                notify::command_finished(
+                    self.notifier.as_ref(),
+                    Duration::from_secs(self.config.behavior.notify_after_secs),
+                    &command,
+                    &exit_status,
+                    duration,
+                );
+                let entry_id = self.last_run.as_ref().and_then(|last| last.entry_id.clone());
+                spawn_post_exec_hooks(&self.config, entry_id, &command, &exit_status, duration);
+
+                let auto_dismiss_secs = self.config.behavior.auto_dismiss_success_secs;
+                let auto_dismiss_at = (matches!(exit_status, CommandStatus::Exited(0))
+                    && auto_dismiss_secs > 0)
+                    .then(|| Instant::now() + Duration::from_secs(auto_dismiss_secs));
+
+                self.last_status = Some(exit_status.clone());
                 // Transition to post-execution (terminal state is preserved)
                 self.mode = AppMode::PostExecution {
                     command,
                     exit_status,
+                    duration,
                     copy_feedback: None,
+                    auto_dismiss_at,
                 };
 
                 // Clean up PTY
                 self.pty_session = None;
 
-                // Re-float window if configured
-                if self.config.niri.float_on_idle {
-                    if let Some(ref niri) = self.niri {
-                        // Fire and forget - don't block on this
-                        let niri = niri.clone();
-                        tokio::spawn(async move {
-                            niri.set_floating(true).await.ok();
-                        });
-                    }
-                }
+                self.restore_floating();
 
-                Ok(false)
+                Ok(true)
             }
-            None => Ok(true), // Still running
+            None => Ok(output), // Still running
         }
     }
 
@@ -492,6 +1909,64 @@ impl App {
         Ok(())
     }
 
+    /// Poll everything that runs in the background: the command, jobs, the
+    /// `$PATH` scan, the matcher and the compositor
+    ///
+    /// Whatever brought news is drawn on the next frame.
+    pub fn poll(&mut self) -> Result<()> {
+        let mut changed = self.is_executing() && self.poll_execution()?;
+        changed |= self.poll_jobs();
+        changed |= self.poll_executables();
+        changed |= self.poll_matches();
+        changed |= self.poll_compositor_events();
+        changed |= self.poll_compositor_health();
+        changed |= self.poll_auto_dismiss();
+        self.dirty |= changed;
+        Ok(())
+    }
+
+    /// Redraw on the next frame, after input, control commands, reloads
+    /// and icons that finished loading
+    pub fn mark_dirty(&mut self) {
+        self.dirty = true;
+    }
+
+    /// Whether to draw now: something changed since the last draw, or
+    /// the heartbeat is due
+    ///
+    /// Counts as drawn, so call it only right before drawing.
+    pub fn take_redraw(&mut self) -> bool {
+        let due = self.last_draw.is_none_or(|at| at.elapsed() >= REDRAW_HEARTBEAT);
+        if !(self.dirty || due) || self.frame_pending() {
+            return false;
+        }
+        self.dirty = false;
+        self.last_draw = Some(Instant::now());
+        true
+    }
+
+    /// Changes are waiting for the next frame of a running command
+    fn frame_pending(&self) -> bool {
+        self.is_executing() && self.last_draw.is_some_and(|at| at.elapsed() < FRAME)
+    }
+
+    /// How long the main loop may wait for input before polling again
+    ///
+    /// Command output doesn't need to be polled for: it signals
+    /// [`App::wakeup`]. Only a draw held back for the frame rate is due
+    /// sooner.
+    pub fn poll_interval(&self) -> Duration {
+        match self.last_draw {
+            Some(at) if self.dirty && self.frame_pending() => FRAME.saturating_sub(at.elapsed()),
+            _ => IDLE_POLL,
+        }
+    }
+
+    /// Signaled when the running command prints or exits
+    pub fn wakeup(&self) -> Option<&Wakeup> {
+        self.wakeup.as_ref()
+    }
+
     /// Resize the PTY and terminal emulator (call on terminal resize)
     pub fn resize_pty(&mut self, cols: u16, rows: u16) -> Result<()> {
         // Resize the terminal emulator
@@ -508,38 +1983,904 @@ impl App {
     pub fn dismiss_output(&mut self) {
         if matches!(self.mode, AppMode::PostExecution { .. }) {
             self.terminal.clear();
+            self.output_search = None;
+            self.link_picker = None;
             self.filter.clear();
             self.update_filtered();
-            self.mode = AppMode::Launcher;
+            self.mode = if self.propagate_exit {
+                AppMode::Exit
+            } else {
+                AppMode::Launcher
+            };
         }
     }
 
-    /// Kill the current execution
-    pub fn kill_execution(&mut self) {
-        self.pty_session = None; // Drop will kill the process
-        self.mode = AppMode::Launcher;
+    /// Exit once the output is dismissed, with the command's exit code
+    /// (`--propagate-exit`)
+    pub fn set_propagate_exit(&mut self, propagate_exit: bool) {
+        self.propagate_exit = propagate_exit;
     }
 
-    /// Copy terminal output to clipboard using wl-copy
-    pub fn copy_output_to_clipboard(&mut self) -> Result<()> {
-        use std::process::{Command, Stdio};
-        use std::io::Write;
+    /// The terminal got kitty keyboard protocol flags pushed
+    pub fn set_keyboard_enhancement(&mut self, keyboard_enhancement: bool) {
+        self.keyboard_enhancement = keyboard_enhancement;
+    }
 
-        let content = self.terminal.content_as_text();
-        
-        let mut child = Command::new("wl-copy")
-            .stdin(Stdio::piped())
-            .stdout(Stdio::null())
-            .stderr(Stdio::null())
-            .spawn()
-            .map_err(|e| anyhow::anyhow!("Failed to spawn wl-copy: {}", e))?;
-        
-        if let Some(mut stdin) = child.stdin.take() {
-            stdin.write_all(content.as_bytes())?;
+    /// drun's exit code: 0 after a launch, 1 when the launcher was left
+    /// without one
+    ///
+    /// With `--propagate-exit` it is the exit code of the last command run
+    /// in the terminal instead (128 + signal when it was killed).
+    pub fn exit_code(&self) -> u8 {
+        match self.last_status {
+            Some(ref status) if self.propagate_exit => match *status {
+                CommandStatus::Exited(code) => code as u8,
+                CommandStatus::Signaled(signal) => (128 + signal) as u8,
+                CommandStatus::Running | CommandStatus::Unknown => 1,
+            },
+            _ if self.launched => 0,
+            _ => 1,
         }
-        
-        child.wait()?;
-        
+    }
+
+    /// Dismiss the output once the auto-dismiss countdown has elapsed;
+    /// whether it did
+    pub fn poll_auto_dismiss(&mut self) -> bool {
+        match self.mode {
+            AppMode::PostExecution {
+                auto_dismiss_at: Some(at),
+                ..
+            } if Instant::now() >= at => {
+                self.dismiss_output();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Time left before the output is dismissed automatically
+    pub fn auto_dismiss_remaining(&self) -> Option<Duration> {
+        match self.mode {
+            AppMode::PostExecution {
+                auto_dismiss_at: Some(at),
+                ..
+            } => Some(at.saturating_duration_since(Instant::now())),
+            _ => None,
+        }
+    }
+
+    /// Keep the output on screen (the user pressed a key)
+    pub fn cancel_auto_dismiss(&mut self) {
+        if let AppMode::PostExecution {
+            auto_dismiss_at, ..
+        } = &mut self.mode
+        {
+            *auto_dismiss_at = None;
+        }
+    }
+
+    /// Where the command output was last drawn
+    pub fn output_area(&self) -> Rect {
+        self.output_area
+    }
+
+    /// Remember where the command output was drawn, relative to the
+    /// launcher area, to place mouse events on it
+    pub fn set_output_area(&mut self, area: Rect) {
+        self.output_area = area;
+    }
+
+    /// Show the keybindings over the current mode
+    ///
+    /// The mode keeps running underneath; a running command keeps its
+    /// output coming in.
+    pub fn open_help(&mut self) {
+        self.cancel_auto_dismiss();
+        self.help_scroll = Some(0);
+    }
+
+    /// Hide the keybindings
+    pub fn close_help(&mut self) {
+        self.help_scroll = None;
+    }
+
+    /// Scroll offset of the help overlay, if it is open
+    pub fn help_scroll(&self) -> Option<usize> {
+        self.help_scroll
+    }
+
+    /// Scroll the help overlay by `delta` lines, drawn over `rows` rows
+    pub fn scroll_help(&mut self, delta: isize, rows: u16) {
+        if let Some(scroll) = &mut self.help_scroll {
+            *scroll = scroll.saturating_add_signed(delta).min(keymap::max_help_scroll(rows));
+        }
+    }
+
+    /// Which keybindings are active right now
+    pub fn key_context(&self) -> KeyContext {
+        match self.mode {
+            AppMode::Launcher if self.category_list.is_some() => KeyContext::Categories,
+            AppMode::Executing { .. } | AppMode::PostExecution { .. } => match &self.output_search {
+                Some(search) if search.editing => KeyContext::SearchInput,
+                Some(_) => KeyContext::Search,
+                None if matches!(self.mode, AppMode::Executing { .. }) => KeyContext::Executing,
+                None if self.link_picker.is_some() => KeyContext::Links,
+                None if self.is_selecting() => KeyContext::Selecting,
+                None => KeyContext::PostExecution,
+            },
+            AppMode::Jobs { .. } => KeyContext::Jobs,
+            _ => KeyContext::Launcher,
+        }
+    }
+
+    /// Stop the current execution (Ctrl+C)
+    ///
+    /// The first call asks the command to exit: interactive commands get ^C
+    /// written to the PTY, everything else receives SIGTERM. A second call,
+    /// or `behavior.kill_grace_secs` passing, sends SIGKILL. Either way the
+    /// session is kept so `poll_execution` moves to post-execution with the
+    /// output and the signal that ended the command.
+    pub fn kill_execution(&mut self) {
+        let AppMode::Executing {
+            mode, terminating, ..
+        } = &mut self.mode
+        else {
+            return;
+        };
+        let Some(ref mut session) = self.pty_session else {
+            return;
+        };
+
+        let result = if terminating.is_some() {
+            session.force_kill()
+        } else {
+            *terminating = Some(Instant::now());
+            if *mode == TerminalMode::Interactive {
+                session.write(b"\x03")
+            } else {
+                session.terminate()
+            }
+        };
+        if let Err(e) = result {
+            tracing::warn!("Failed to stop command: {}", e);
+        }
+    }
+
+    /// Whether the running oneshot command has exceeded
+    /// `behavior.oneshot_timeout_secs` (and hasn't been asked to stop yet)
+    ///
+    /// Other terminal modes are expected to run indefinitely and never time out.
+    pub fn is_timed_out(&self) -> bool {
+        let AppMode::Executing {
+            mode: TerminalMode::Oneshot,
+            started,
+            terminating: None,
+            ..
+        } = self.mode
+        else {
+            return false;
+        };
+        self.config
+            .oneshot_timeout()
+            .is_some_and(|timeout| started.elapsed() >= timeout)
+    }
+
+    /// Save the command output to a file and report the path (or the
+    /// error) in the status bar
+    pub fn save_output(&mut self) {
+        let command = match self.mode {
+            AppMode::Executing { ref command, .. } | AppMode::PostExecution { ref command, .. } => {
+                command.clone()
+            }
+            _ => return,
+        };
+        let result = save_output(&self.config, &command, &self.terminal);
+        self.report_save(result);
+    }
+
+    /// Open the link picker with the hyperlinks in the output
+    pub fn open_link_picker(&mut self) {
+        if !matches!(self.mode, AppMode::PostExecution { .. }) {
+            return;
+        }
+        let links = self.terminal.hyperlinks();
+        if links.is_empty() {
+            self.show_status("No links in the output".to_string(), true);
+            return;
+        }
+        self.link_picker = Some(LinkPicker { links, selected: 0 });
+    }
+
+    /// Close the link picker without opening a link
+    pub fn close_link_picker(&mut self) {
+        self.link_picker = None;
+    }
+
+    /// Get the link picker, if open
+    pub fn link_picker(&self) -> Option<&LinkPicker> {
+        self.link_picker.as_ref()
+    }
+
+    /// Move the link selection by `delta` rows (clamped)
+    pub fn move_link(&mut self, delta: isize) {
+        if let Some(ref mut picker) = self.link_picker {
+            let last = picker.links.len().saturating_sub(1);
+            picker.selected = picker.selected.saturating_add_signed(delta).min(last);
+        }
+    }
+
+    /// Open the link at `index` (0-based) and close the picker
+    ///
+    /// Links are opened with `behavior.open_link_command`. Over SSH a
+    /// program started here would open on the remote host, so the URL is
+    /// copied to the clipboard instead.
+    pub fn open_link(&mut self, index: usize) {
+        let Some(url) = self
+            .link_picker
+            .as_ref()
+            .and_then(|picker| picker.links.get(index).cloned())
+        else {
+            return;
+        };
+        self.link_picker = None;
+
+        let result = if std::env::var("SSH_CONNECTION").is_ok() {
+            clipboard::copy(&url).map(|()| format!("Copied {}", url))
+        } else {
+            self.spawn_link_opener(&url).map(|()| format!("Opened {}", url))
+        };
+        match result {
+            Ok(text) => self.show_status(text, false),
+            Err(e) => {
+                tracing::warn!("Failed to open link {}: {:#}", url, e);
+                self.show_status(format!("Failed to open link: {:#}", e), true);
+            }
+        }
+    }
+
+    /// Open the selected link
+    pub fn open_selected_link(&mut self) {
+        if let Some(index) = self.link_picker.as_ref().map(|picker| picker.selected) {
+            self.open_link(index);
+        }
+    }
+
+    /// Start `behavior.open_link_command` for `url` without waiting for it
+    fn spawn_link_opener(&self, url: &str) -> Result<()> {
+        use std::process::{Command, Stdio};
+
+        let opener = self.config.behavior.open_link_command.clone();
+        let mut words = opener.split_whitespace();
+        let program = words
+            .next()
+            .ok_or_else(|| anyhow::anyhow!("behavior.open_link_command is empty"))?;
+        let mut child = Command::new(program)
+            .args(words)
+            .arg(url)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| anyhow::anyhow!("Failed to spawn {}: {}", program, e))?;
+        // Reap the opener once it exits
+        std::thread::spawn(move || child.wait());
+        Ok(())
+    }
+
+    /// Status bar message, while still fresh
+    pub fn status_message(&self) -> Option<&StatusMessage> {
+        self.status_message
+            .as_ref()
+            .filter(|m| m.since.elapsed() < STATUS_MESSAGE_TIMEOUT)
+    }
+
+    /// Show where output was saved, or why saving failed
+    fn report_save(&mut self, result: Result<PathBuf>) {
+        let (text, error) = match result {
+            Ok(path) => (format!("Saved to {}", path.display()), false),
+            Err(e) => {
+                tracing::warn!("Failed to save output: {:#}", e);
+                (format!("Save failed: {:#}", e), true)
+            }
+        };
+        self.show_status(text, error);
+    }
+
+    /// Show `text` in the output view status bar for a few seconds
+    fn show_status(&mut self, text: String, error: bool) {
+        self.status_message = Some(StatusMessage {
+            text,
+            error,
+            since: Instant::now(),
+        });
+    }
+
+    /// Replace the desktop notifier
+    #[cfg(test)]
+    fn set_notifier(&mut self, notifier: Box<dyn Notifier>) {
+        self.notifier = notifier;
+    }
+
+    /// Search in the command output, while active
+    pub fn output_search(&self) -> Option<&OutputSearch> {
+        self.output_search.as_ref()
+    }
+
+    /// Open the output search prompt (keeps the previous case setting)
+    pub fn start_output_search(&mut self) {
+        let case_sensitive = self
+            .output_search
+            .as_ref()
+            .is_some_and(|s| s.query.case_sensitive);
+        self.output_search = Some(OutputSearch {
+            query: SearchQuery {
+                text: String::new(),
+                case_sensitive,
+            },
+            editing: true,
+            ..Default::default()
+        });
+    }
+
+    /// Close the output search and remove highlights
+    pub fn cancel_output_search(&mut self) {
+        self.output_search = None;
+    }
+
+    /// Add a character to the output search query
+    pub fn push_search_char(&mut self, c: char) {
+        if let Some(ref mut search) = self.output_search {
+            search.query.text.push(c);
+        }
+    }
+
+    /// Add pasted text to the output search query (first line only)
+    pub fn paste_into_search(&mut self, text: &str) {
+        if let Some(ref mut search) = self.output_search {
+            let line = text.lines().next().unwrap_or("");
+            search.query.text.extend(line.chars().filter(|c| !c.is_control()));
+        }
+    }
+
+    /// Remove the last character from the output search query
+    pub fn pop_search_char(&mut self) {
+        if let Some(ref mut search) = self.output_search {
+            search.query.text.pop();
+        }
+    }
+
+    /// Toggle case-sensitive output search
+    pub fn toggle_search_case(&mut self) {
+        if let Some(ref mut search) = self.output_search {
+            search.query.case_sensitive = !search.query.case_sensitive;
+            search.position = None;
+        }
+    }
+
+    /// Close the prompt and jump to the first match from the top of the view
+    ///
+    /// An empty query closes the search.
+    pub fn confirm_output_search(&mut self) {
+        match self.output_search {
+            Some(ref mut search) if !search.query.text.is_empty() => {
+                search.editing = false;
+                search.current = None;
+                self.next_search_match(true);
+            }
+            _ => self.output_search = None,
+        }
+    }
+
+    /// Jump to the next (or previous) match, wrapping around
+    ///
+    /// Without a current match, searches from the top of the view. Scrolls
+    /// the match into view, which stops following new output.
+    pub fn next_search_match(&mut self, forward: bool) {
+        let Some(ref mut search) = self.output_search else {
+            return;
+        };
+        let matches = self.terminal.search(&search.query);
+        if matches.is_empty() {
+            search.current = None;
+            search.position = Some((0, 0));
+            return;
+        }
+
+        let key = |m: &SearchMatch| (m.line, m.cols.start);
+        let top = self.terminal.viewport_top_line();
+        let index = match (&search.current, forward) {
+            (Some(current), true) => matches.iter().position(|m| key(m) > key(current)),
+            (Some(current), false) => matches.iter().rposition(|m| key(m) < key(current)),
+            (None, true) => matches.iter().position(|m| m.line >= top),
+            (None, false) => matches.iter().rposition(|m| m.line < top),
+        };
+        let index = index.unwrap_or(if forward { 0 } else { matches.len() - 1 });
+
+        let found = matches[index].clone();
+        self.terminal.reveal_line(found.line);
+        search.current = Some(found);
+        search.position = Some((index + 1, matches.len()));
+    }
+
+    /// Move the running command to the background and return to the launcher
+    pub fn detach_execution(&mut self) {
+        let AppMode::Executing {
+            ref command,
+            mode,
+            started,
+            ..
+        } = self.mode
+        else {
+            return;
+        };
+        let Some(session) = self.pty_session.take() else {
+            return;
+        };
+
+        let (launch, entry_id, elevated) = match self.last_run {
+            Some(ref last) => (last.launch.clone(), last.entry_id.clone(), last.elevated),
+            None => (LaunchOptions::default(), None, false),
+        };
+        let terminal = std::mem::replace(
+            &mut self.terminal,
+            new_terminal(scrollback_lines(&self.config)),
+        );
+        self.output_search = None;
+        tracing::info!("Moved to background: {}", command);
+        self.jobs.push(Job {
+            command: command.clone(),
+            mode,
+            launch,
+            entry_id,
+            elevated,
+            started,
+            session: Some(session),
+            terminal,
+            finished: None,
+        });
+        self.mode = AppMode::Launcher;
+        self.restore_floating();
+    }
+
+    /// Print chosen commands instead of running them (`--print-only`)
+    pub fn set_execution_policy(&mut self, policy: ExecutionPolicy) {
+        self.execution = policy;
+    }
+
+    pub fn execution_policy(&self) -> ExecutionPolicy {
+        self.execution
+    }
+
+    /// The command chosen under `ExecutionPolicy::Print`
+    pub fn printed(&self) -> Option<&PrintedCommand> {
+        self.printed.as_ref()
+    }
+
+    /// The dmenu run, in `--dmenu` mode
+    pub fn dmenu(&self) -> Option<&Dmenu> {
+        self.dmenu.as_ref()
+    }
+
+    /// GUI app whose window is awaited before exiting
+    pub fn awaiting_window(&self) -> Option<&AwaitedWindow> {
+        self.awaiting_window.as_ref()
+    }
+
+    /// Handle events from the compositor's event stream
+    ///
+    /// Exits once the window of a launched GUI app opened, or when it
+    /// didn't within `compositor.wait_for_window_secs`; returns whether it
+    /// exits.
+    pub fn poll_compositor_events(&mut self) -> bool {
+        let mut exits = false;
+        while let Some(events) = self.compositor_events.as_mut() {
+            match events.try_recv() {
+                Ok(event) => exits |= self.handle_compositor_event(event),
+                Err(mpsc::error::TryRecvError::Empty) => break,
+                Err(mpsc::error::TryRecvError::Disconnected) => {
+                    tracing::debug!("Compositor event stream closed");
+                    self.compositor_events = None;
+                }
+            }
+        }
+
+        let Some(ref awaited) = self.awaiting_window else {
+            return exits;
+        };
+        if self.compositor_events.is_none() || Instant::now() >= awaited.deadline {
+            tracing::info!("No window of {} appeared, exiting anyway", awaited.name);
+            self.awaiting_window = None;
+            self.mode = AppMode::Exit;
+            return true;
+        }
+        exits
+    }
+
+    /// Whether `event` is the awaited window opening, to exit
+    fn handle_compositor_event(&mut self, event: Event) -> bool {
+        let Event::WindowOpenedOrChanged { window } = event else {
+            return false;
+        };
+        if !self.awaiting_window.as_ref().is_some_and(|awaited| awaited.matches(&window)) {
+            return false;
+        }
+        tracing::info!("Window of {} opened ({})", window.app_id, window.id);
+        self.awaiting_window = None;
+        self.mode = AppMode::Exit;
+        true
+    }
+
+    /// Poll background jobs for output and exit; whether any had news
+    pub fn poll_jobs(&mut self) -> bool {
+        let mut changed = false;
+        for job in &mut self.jobs {
+            let was_running = job.is_running();
+            match job.poll() {
+                Ok(polled) => changed |= polled,
+                Err(e) => tracing::warn!("Failed to poll job \"{}\": {}", job.command, e),
+            }
+            if !was_running {
+                continue;
+            }
+            if let Some((ref status, duration)) = job.finished {
+                if self.config.output.auto_save {
+                    match save_output(&self.config, &job.command, &job.terminal) {
+                        Ok(path) => tracing::info!("Saved job output to {}", path.display()),
+                        Err(e) => tracing::warn!("Failed to save job output: {:#}", e),
+                    }
+                }
+                notify::command_finished(
+                    self.notifier.as_ref(),
+                    Duration::from_secs(self.config.behavior.notify_after_secs),
+                    &job.command,
+                    status,
+                    duration,
+                );
+                spawn_post_exec_hooks(
+                    &self.config,
+                    job.entry_id.clone(),
+                    &job.command,
+                    status,
+                    duration,
+                );
+            }
+        }
+        changed
+    }
+
+    /// Background jobs, oldest first
+    pub fn jobs(&self) -> &[Job] {
+        &self.jobs
+    }
+
+    /// Number of background jobs that are still running
+    pub fn running_job_count(&self) -> usize {
+        self.jobs.iter().filter(|job| job.is_running()).count()
+    }
+
+    /// Show the jobs view
+    pub fn open_jobs(&mut self) {
+        self.selected_job = self.selected_job.min(self.jobs.len().saturating_sub(1));
+        self.mode = AppMode::Jobs {
+            confirm_exit: false,
+        };
+    }
+
+    /// Leave the jobs view
+    pub fn close_jobs(&mut self) {
+        if matches!(self.mode, AppMode::Jobs { .. }) {
+            self.mode = AppMode::Launcher;
+        }
+    }
+
+    /// Selected index in the jobs view
+    pub fn selected_job(&self) -> usize {
+        self.selected_job
+    }
+
+    /// Move the jobs view selection by `delta`, clamped to the list
+    pub fn move_job(&mut self, delta: isize) {
+        let last = self.jobs.len().saturating_sub(1);
+        self.selected_job = self.selected_job.saturating_add_signed(delta).min(last);
+    }
+
+    /// Bring the selected job back to the foreground
+    ///
+    /// Running jobs continue in executing mode; finished ones open in
+    /// post-execution mode with their preserved output.
+    pub async fn attach_job(&mut self, cols: u16, rows: u16) -> Result<()> {
+        if self.selected_job >= self.jobs.len() {
+            return Ok(());
+        }
+        let job = self.jobs.remove(self.selected_job);
+        self.move_job(0);
+
+        self.last_run = Some(LastRun {
+            command: job.command.clone(),
+            mode: job.mode,
+            launch: job.launch,
+            entry_id: job.entry_id,
+            elevated: job.elevated,
+        });
+        self.terminal = job.terminal;
+        self.output_search = None;
+
+        match (job.session, job.finished) {
+            (Some(session), None) => {
+                self.unfloat_for_command().await;
+                self.pty_session = Some(session);
+                self.resize_pty(cols, rows)?;
+                self.mode = AppMode::Executing {
+                    command: job.command,
+                    mode: job.mode,
+                    started: job.started,
+                    terminating: None,
+                };
+            }
+            (_, finished) => {
+                let (exit_status, duration) =
+                    finished.unwrap_or((CommandStatus::Unknown, Duration::ZERO));
+                self.mode = AppMode::PostExecution {
+                    command: job.command,
+                    exit_status,
+                    duration,
+                    copy_feedback: None,
+                    // Attached on purpose to look at the output
+                    auto_dismiss_at: None,
+                };
+            }
+        }
+        Ok(())
+    }
+
+    /// Remove the selected job if it has finished
+    pub fn dismiss_job(&mut self) {
+        if self
+            .jobs
+            .get(self.selected_job)
+            .is_some_and(|job| !job.is_running())
+        {
+            self.jobs.remove(self.selected_job);
+            self.move_job(0);
+        }
+    }
+
+    /// Check whether drun may quit now
+    ///
+    /// With running background jobs and `behavior.jobs_on_exit = "prompt"`,
+    /// this opens the jobs view asking for confirmation and returns false;
+    /// quitting again from there confirms. Jobs still running on exit are
+    /// killed when their sessions are dropped.
+    pub fn confirm_exit(&mut self) -> bool {
+        if matches!(self.mode, AppMode::Jobs { confirm_exit: true })
+            || self.running_job_count() == 0
+            || self.config.behavior.jobs_on_exit != JobsOnExit::Prompt
+        {
+            return true;
+        }
+        self.open_jobs();
+        if let AppMode::Jobs { confirm_exit } = &mut self.mode {
+            *confirm_exit = true;
+        }
+        false
+    }
+
+    /// Hide instead of exiting (`--daemon`): back to a fresh launcher
+    /// behind the idle screen
+    ///
+    /// A running command is never hidden.
+    pub fn hide(&mut self) {
+        if self.is_executing() {
+            tracing::info!("Not hiding while a command runs");
+            return;
+        }
+        self.show_applications();
+        self.mode = AppMode::Launcher;
+        self.terminal.clear();
+        self.output_search = None;
+        self.link_picker = None;
+        self.category_list = None;
+        self.help_scroll = None;
+        self.status_message = None;
+        self.elevation_armed = false;
+        self.awaiting_window = None;
+        self.pending_launch = None;
+        self.filter.clear();
+        self.filtering = false;
+        self.select_first();
+        self.update_filtered();
+        // A daemon may run for weeks; don't keep its history in memory only
+        self.save_history();
+        self.hidden = true;
+    }
+
+    /// Show the launcher again, focusing drun's window under a compositor
+    pub async fn show(&mut self) {
+        self.hidden = false;
+        let Some(ref wm) = self.compositor else {
+            return;
+        };
+        let result = match wm.windows().await {
+            Ok(windows) => match windows::own_window(&windows) {
+                Some(id) => wm.focus_window(id).await,
+                None => Ok(()),
+            },
+            Err(e) => Err(e),
+        };
+        if let Err(e) = result {
+            tracing::warn!("Failed to focus drun's window: {:#}", e);
+        }
+    }
+
+    /// Hidden by `--daemon` after a launch or Esc
+    pub fn is_hidden(&self) -> bool {
+        self.hidden
+    }
+
+    /// Compositor connection state, as of the last refresh
+    pub fn compositor_health(&self) -> &CompositorHealth {
+        &self.compositor_health
+    }
+
+    /// Refresh the compositor indicator, at most once a second; whether
+    /// it changed
+    pub fn poll_compositor_health(&mut self) -> bool {
+        if self.compositor_health_checked.is_some_and(|at| at.elapsed() < COMPOSITOR_HEALTH_INTERVAL) {
+            return false;
+        }
+        self.compositor_health_checked = Some(Instant::now());
+        let session = match self.compositor_enabled {
+            true => self.config.compositor.backend.resolve().filter(|b| b.in_session()),
+            false => None,
+        };
+        let health = compositor_health(self.compositor.as_deref(), session);
+        if health == self.compositor_health {
+            return false;
+        }
+        tracing::debug!("Compositor health: {:?}", health);
+        self.compositor_health = health;
+        true
+    }
+
+    /// Explain the compositor indicator in the status bar (Ctrl+N)
+    pub fn show_compositor_status(&mut self) {
+        self.poll_compositor_health();
+        let (text, error) = match (&self.compositor_health, &self.compositor) {
+            (CompositorHealth::Connected, Some(wm)) => {
+                (format!("{} connected ({})", wm.name(), wm.socket_path().display()), false)
+            }
+            (CompositorHealth::Disconnected { reason, .. }, Some(wm)) => {
+                (format!("{} not connected: {}", wm.name(), reason), true)
+            }
+            (CompositorHealth::Disconnected { reason, .. }, None) => (format!("Not connected: {}", reason), true),
+            _ if !self.compositor_enabled => ("Compositor integration is disabled".to_string(), false),
+            _ => ("Not running under niri, sway or Hyprland".to_string(), false),
+        };
+        self.show_status(text, error);
+    }
+
+    /// Remember whether drun's window floats, then tile it if configured
+    async fn unfloat_for_command(&mut self) {
+        let Some(ref wm) = self.compositor else {
+            return;
+        };
+        if self.config.compositor.float_on_idle {
+            self.floating_before_command = match wm.focused_window().await {
+                Ok(window) => window.map(|w| w.is_floating),
+                Err(e) => {
+                    tracing::debug!("Failed to get the focused window: {}", e);
+                    None
+                }
+            };
+        }
+        if self.config.compositor.unfloat_on_execute {
+            wm.set_floating(false).await.ok();
+        }
+    }
+
+    /// Put drun's window back the way it was before the command, or float
+    /// it if that isn't known
+    fn restore_floating(&mut self) {
+        let captured = self.floating_before_command.take();
+        if let Some(floating) = idle_floating(captured, self.config.compositor.float_on_idle) {
+            self.set_floating_in_background(floating);
+        }
+    }
+
+    /// Float or tile drun's window (Ctrl+F)
+    pub async fn toggle_floating(&self) {
+        if let Some(ref wm) = self.compositor {
+            if let Err(e) = wm.toggle_floating().await {
+                tracing::warn!("Failed to toggle floating: {}", e);
+            }
+        }
+    }
+
+    /// Change window floating without blocking the UI
+    fn set_floating_in_background(&self, floating: bool) {
+        if let Some(ref wm) = self.compositor {
+            let wm = wm.boxed_clone();
+            tokio::spawn(async move {
+                wm.set_floating(floating).await.ok();
+            });
+        }
+    }
+
+    /// Start selecting output (`v`) at the start of the last row on screen
+    /// with text
+    pub fn start_selection(&mut self) {
+        let top = self.terminal.viewport_top_line();
+        let (_, rows) = self.terminal.size();
+        let line = (top..top + rows)
+            .rev()
+            .find(|&index| {
+                self.terminal
+                    .line(index)
+                    .is_some_and(|cells| cells.iter().any(|cell| cell.str() != " "))
+            })
+            .unwrap_or(top);
+        self.terminal.start_selection(SelectionPoint { line, col: 0 });
+    }
+
+    /// Whether part of the output is selected
+    pub fn is_selecting(&self) -> bool {
+        self.terminal.selection().is_some()
+    }
+
+    /// Move the moving end of the selection by `lines` and `cols`
+    /// (clamped), scrolling to keep it in view
+    pub fn move_selection(&mut self, lines: isize, cols: isize) {
+        let Some(head) = self.terminal.selection().map(|s| s.head) else {
+            return;
+        };
+        let head = SelectionPoint {
+            line: head.line.saturating_add_signed(lines),
+            col: head.col.saturating_add_signed(cols),
+        };
+        self.terminal.extend_selection(head);
+        if let Some(selection) = self.terminal.selection() {
+            let line = selection.head.line;
+            self.terminal.scroll_line_into_view(line);
+        }
+    }
+
+    /// Select with the mouse: start at (or with `extend`, drag to) the cell
+    /// at `col`, `row` of the output view
+    pub fn select_output_cell(&mut self, col: u16, row: u16, extend: bool) {
+        let point = SelectionPoint {
+            line: self.terminal.viewport_top_line() + row as usize,
+            col: col as usize,
+        };
+        if extend {
+            self.terminal.extend_selection(point);
+        } else {
+            self.terminal.start_selection(point);
+        }
+    }
+
+    /// Drop the selection
+    pub fn cancel_selection(&mut self) {
+        self.terminal.clear_selection();
+    }
+
+    /// Copy the selected text to the clipboard and end the selection
+    pub fn copy_selection(&mut self) -> Result<()> {
+        let Some(text) = self.terminal.selected_text() else {
+            return Ok(());
+        };
+        self.terminal.clear_selection();
+        clipboard::copy(&text)?;
+
+        if let AppMode::PostExecution { copy_feedback, .. } = &mut self.mode {
+            *copy_feedback = Some(std::time::Instant::now());
+        }
+        Ok(())
+    }
+
+    /// Copy terminal output to clipboard using wl-copy
+    pub fn copy_output_to_clipboard(&mut self) -> Result<()> {
+        clipboard::copy(&self.terminal.content_as_text())?;
+
         // Set feedback timestamp
         if let AppMode::PostExecution { copy_feedback, .. } = &mut self.mode {
             *copy_feedback = Some(std::time::Instant::now());
@@ -562,3 +2903,1049 @@ impl App {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+    use crate::niri::NiriClient;
+    use crate::notify::Recorder;
+
+    /// Config that leaves the user's history alone
+    fn test_config() -> Config {
+        toml::from_str("[history]\nenabled = false").unwrap()
+    }
+
+    /// Entry named and run after its `id`
+    fn entry(id: &str) -> Entry {
+        Entry {
+            id: id.to_string(),
+            name: id.to_string(),
+            exec: Some(id.to_string()),
+            ..Default::default()
+        }
+    }
+
+    /// App with a single "Hang" entry running `sleep 60` in `mode`
+    fn hanging_app(mode: &str, action: &str) -> App {
+        let config: Config = toml::from_str(&format!(
+            r#"
+            [behavior]
+            oneshot_timeout_secs = 1
+            oneshot_timeout_action = "{action}"
+            kill_grace_secs = 1
+
+            [history]
+            enabled = false
+
+            [[custom_entries]]
+            name = "Hang"
+            exec = "sleep 60"
+            terminal_mode = "{mode}"
+            "#
+        ))
+        .unwrap();
+        App::new(Vec::new(), config, false)
+    }
+
+    async fn run_hang(app: &mut App) {
+        let entry = app.selected_entry().cloned().unwrap();
+        app.execute_entry(entry, 80, 24).await.unwrap();
+        assert!(app.is_executing());
+        assert!(!app.is_timed_out());
+    }
+
+    #[tokio::test]
+    async fn test_oneshot_timeout_warns() {
+        let mut app = hanging_app("oneshot", "warn");
+        run_hang(&mut app).await;
+
+        tokio::time::sleep(Duration::from_millis(1100)).await;
+        app.poll_execution().unwrap();
+        assert!(app.is_executing());
+        assert!(app.is_timed_out());
+
+        // Killing clears the warning
+        app.kill_execution();
+        assert!(!app.is_timed_out());
+    }
+
+    #[tokio::test]
+    async fn test_oneshot_timeout_kills() {
+        let mut app = hanging_app("oneshot", "kill");
+        run_hang(&mut app).await;
+
+        tokio::time::sleep(Duration::from_millis(1100)).await;
+        let start = Instant::now();
+        while app.is_executing() && start.elapsed() < Duration::from_secs(5) {
+            app.poll_execution().unwrap();
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        assert!(matches!(
+            app.mode(),
+            AppMode::PostExecution {
+                exit_status: CommandStatus::Signaled(15),
+                ..
+            }
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_elevated_run_uses_prefix() {
+        let config: Config = toml::from_str(
+            r#"
+            [behavior]
+            elevate_command = "env"
+
+            [history]
+            enabled = false
+
+            [[custom_entries]]
+            name = "Hang"
+            exec = "sleep 60"
+            terminal_mode = "oneshot"
+            "#,
+        )
+        .unwrap();
+        let mut app = App::new(Vec::new(), config, false);
+
+        app.arm_elevation();
+        assert!(app.is_elevation_armed());
+        app.cancel_elevation();
+        assert!(!app.is_elevation_armed());
+
+        app.arm_elevation();
+        let entry = app.selected_entry().cloned().unwrap();
+        app.execute_entry_elevated(entry, 80, 24).await.unwrap();
+        assert!(!app.is_elevation_armed());
+        assert!(app.last_run_elevated());
+        // A prefix other than pkexec may prompt on the terminal
+        assert!(matches!(
+            app.mode(),
+            AppMode::Executing { command, mode: TerminalMode::Interactive, .. } if command == "env sleep 60"
+        ));
+        app.kill_execution();
+    }
+
+    /// App with one oneshot entry running `exec` and a 1s auto-dismiss
+    fn auto_dismiss_app(exec: &str) -> App {
+        let config: Config = toml::from_str(&format!(
+            r#"
+            [behavior]
+            auto_dismiss_success_secs = 1
+
+            [history]
+            enabled = false
+
+            [[custom_entries]]
+            name = "Run"
+            exec = "{exec}"
+            terminal_mode = "oneshot"
+            "#
+        ))
+        .unwrap();
+        App::new(Vec::new(), config, false)
+    }
+
+    async fn run_to_completion(app: &mut App) {
+        let entry = app.selected_entry().cloned().unwrap();
+        app.execute_entry(entry, 80, 24).await.unwrap();
+        let start = Instant::now();
+        while app.pending_launch().is_some() && start.elapsed() < Duration::from_secs(5) {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            app.poll_launch().await.unwrap();
+        }
+        while app.is_executing() && start.elapsed() < Duration::from_secs(5) {
+            app.poll_execution().unwrap();
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        assert!(app.is_post_execution());
+    }
+
+    #[tokio::test]
+    async fn test_auto_dismiss_success_only() {
+        let mut app = auto_dismiss_app("true");
+        run_to_completion(&mut app).await;
+        assert!(app.auto_dismiss_remaining().is_some());
+        app.poll_auto_dismiss();
+        assert!(app.is_post_execution());
+        tokio::time::sleep(Duration::from_millis(1100)).await;
+        app.poll_auto_dismiss();
+        assert!(matches!(app.mode(), AppMode::Launcher));
+
+        // A key press cancels the countdown
+        run_to_completion(&mut app).await;
+        app.cancel_auto_dismiss();
+        assert!(app.auto_dismiss_remaining().is_none());
+
+        // Failures stay on screen
+        let mut app = auto_dismiss_app("false");
+        run_to_completion(&mut app).await;
+        assert!(app.auto_dismiss_remaining().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_exit_code() {
+        let mut app = auto_dismiss_app("exit 3");
+        // Leaving without a launch
+        assert_eq!(app.exit_code(), 1);
+
+        run_to_completion(&mut app).await;
+        assert_eq!(app.exit_code(), 0);
+        app.dismiss_output();
+        assert!(matches!(app.mode(), AppMode::Launcher));
+
+        // --propagate-exit: dismissing exits with the command's code
+        app.set_propagate_exit(true);
+        run_to_completion(&mut app).await;
+        app.dismiss_output();
+        assert!(matches!(app.mode(), AppMode::Exit));
+        assert_eq!(app.exit_code(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_link_picker_lists_output_links() {
+        let mut app = auto_dismiss_app("true");
+        run_to_completion(&mut app).await;
+
+        // No links: the picker stays closed and says why
+        app.open_link_picker();
+        assert!(app.link_picker().is_none());
+        assert!(app.status_message().is_some_and(|m| m.error));
+
+        app.terminal_mut().write(
+            b"\x1b]8;;https://a.example/\x1b\\a\x1b]8;;\x1b\\ \x1b]8;;https://b.example/\x1b\\b\x1b]8;;\x1b\\",
+        );
+        app.open_link_picker();
+        let picker = app.link_picker().unwrap();
+        assert_eq!(picker.links, ["https://a.example/", "https://b.example/"]);
+        app.move_link(5);
+        assert_eq!(app.link_picker().unwrap().selected, 1);
+        app.move_link(-1);
+        assert_eq!(app.link_picker().unwrap().selected, 0);
+
+        app.close_link_picker();
+        assert!(app.link_picker().is_none());
+        app.open_link_picker();
+        app.dismiss_output();
+        assert!(app.link_picker().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_exec_hooks_run_around_command() {
+        let dir = TempDir::new("app-hooks");
+        let config: Config = toml::from_str(&format!(
+            r#"
+            [history]
+            enabled = false
+
+            [hooks]
+            pre_exec = ["sleep 0.2", "echo \"pre $DRUN_ENTRY_ID $DRUN_COMMAND\" > {dir}/pre", "exit 1"]
+            post_exec = ["echo \"post $DRUN_EXIT_CODE\" > {dir}/post"]
+
+            [[custom_entries]]
+            name = "Fail"
+            exec = "exit 4"
+            terminal_mode = "oneshot"
+            "#,
+            dir = dir.display()
+        ))
+        .unwrap();
+        let mut app = App::new(Vec::new(), config, false);
+
+        // The launcher stays up while pre_exec hooks run
+        let entry = app.selected_entry().cloned().unwrap();
+        app.execute_entry(entry.clone(), 80, 24).await.unwrap();
+        assert_eq!(app.pending_launch(), Some("exit 4"));
+        assert!(matches!(app.mode(), AppMode::Launcher));
+        app.poll_launch().await.unwrap();
+        assert!(!app.is_executing());
+        // Another Enter meanwhile doesn't launch twice
+        app.execute_entry(entry, 80, 24).await.unwrap();
+        app.hide();
+        assert!(app.pending_launch().is_none());
+
+        // A failing pre_exec hook does not stop the launch
+        run_to_completion(&mut app).await;
+        assert_eq!(
+            std::fs::read_to_string(dir.join("pre")).unwrap(),
+            "pre custom:Fail exit 4\n"
+        );
+
+        // post_exec runs in the background
+        let post = dir.join("post");
+        let start = Instant::now();
+        while !post.exists() && start.elapsed() < Duration::from_secs(5) {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(std::fs::read_to_string(&post).unwrap(), "post 4\n");
+    }
+
+    #[tokio::test]
+    async fn test_typed_command() {
+        let mut app = hanging_app("oneshot", "warn");
+        for c in "echo %f 'a b'".chars() {
+            app.push_filter_char(c);
+        }
+        assert!(app.selected_entry().is_none());
+        assert_eq!(app.typed_command(), Some("echo %f 'a b'"));
+
+        app.execute_typed_command(80, 24).await.unwrap();
+        // Run verbatim, detected like a raw command
+        assert!(matches!(
+            app.mode(),
+            AppMode::Executing { command, mode: TerminalMode::Oneshot, .. } if command == "echo %f 'a b'"
+        ));
+
+        // Searchable afterwards, but not listed without filter text
+        app.kill_execution();
+        while app.is_executing() {
+            app.poll_execution().unwrap();
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        app.dismiss_output();
+        assert!(app.visible_entries().iter().all(|e| !e.is_typed_command()));
+        for c in "echo".chars() {
+            app.push_filter_char(c);
+        }
+        assert_eq!(app.selected_entry().unwrap().id, "cmd:echo %f 'a b'");
+
+        // Category filters are never run
+        app.clear_filter();
+        app.push_filter_char('#');
+        assert_eq!(app.typed_command(), None);
+    }
+
+    #[test]
+    fn test_forget_history_and_undo() {
+        let config = test_config();
+        let named = |name: &str| Entry {
+            name: name.to_string(),
+            ..entry(&name.to_lowercase())
+        };
+        let mut app = App::new(vec![named("Alpha"), named("Beta"), named("Gamma")], config, false);
+        for _ in 0..3 {
+            app.history.record_usage("gamma");
+        }
+        app.update_filtered();
+        let first = |app: &App| app.selected_entry().unwrap().id.clone();
+        assert_eq!(first(&app), "gamma");
+
+        app.toggle_forget();
+        assert!(app.history.stats("gamma").is_none());
+        assert_eq!(first(&app), "alpha");
+        assert!(app.status_message().unwrap().text.starts_with("Forgot 3 launches of Gamma"));
+
+        // Again: undo, whatever is selected now
+        app.toggle_forget();
+        assert_eq!(app.history.stats("gamma").unwrap().count, 3);
+        assert_eq!(first(&app), "gamma");
+        assert!(app.history.stats("alpha").is_none());
+
+        // Once the undo window is over, it forgets the selected entry
+        app.toggle_forget();
+        app.forgotten.as_mut().unwrap().at -= FORGET_UNDO_WINDOW;
+        app.toggle_forget();
+        assert_eq!(app.status_message().unwrap().text, "Alpha has no history");
+        assert!(app.history.stats("gamma").is_none());
+    }
+
+    #[test]
+    fn test_search_operators() {
+        let config = test_config();
+        let described = |name: &str, keywords: &[&str], categories: &[&str], comment: &str| Entry {
+            name: name.to_string(),
+            comment: Some(comment.to_string()),
+            keywords: keywords.iter().map(|k| k.to_string()).collect(),
+            categories: categories.iter().map(|c| c.to_string()).collect(),
+            ..entry(&name.to_lowercase())
+        };
+        let entries = vec![
+            described("Firefox", &["Browser", "Web"], &["Network", "WebBrowser"], "Browse the web"),
+            described("Chromium", &["Browser"], &["Network", "WebBrowser"], "Access the Internet"),
+            described("Thunderbird", &["Mail"], &["Network", "Email"], "Read your mail"),
+            described("Gedit", &["Text"], &["Utility", "TextEditor"], "Edit text files"),
+        ];
+        let mut app = App::new(entries, config, false);
+        let listed = |app: &App| app.visible_entries().iter().map(|e| e.id.clone()).collect::<Vec<_>>();
+
+        app.set_initial_filter("#net @brows");
+        assert_eq!(listed(&app), ["chromium", "firefox"]);
+        assert_eq!(app.active_category().as_deref(), Some("Network"));
+        assert_eq!(app.search_operators(), ["#category", "@keyword"]);
+
+        app.set_initial_filter("#net !internet");
+        assert_eq!(listed(&app), ["firefox", "thunderbird"]);
+
+        app.set_initial_filter("\"the web\"");
+        assert_eq!(listed(&app), ["firefox"]);
+        app.set_initial_filter("\"the wb\"");
+        assert!(listed(&app).is_empty());
+
+        // Bare words match as before, operators or not
+        app.set_initial_filter("edit #util");
+        assert_eq!(listed(&app), ["gedit"]);
+        assert_eq!(app.active_category().as_deref(), Some("Utility"));
+        app.set_initial_filter("fire !");
+        assert_eq!(listed(&app), ["firefox"]);
+        assert!(app.search_operators().is_empty());
+    }
+
+    #[test]
+    fn test_output_search_navigation() {
+        let mut app = hanging_app("oneshot", "warn");
+        app.terminal_mut().resize(20, 3);
+        for i in 0..10 {
+            let line = if i % 3 == 0 { format!("error {}\r\n", i) } else { format!("ok {}\r\n", i) };
+            app.terminal_mut().write(line.as_bytes());
+        }
+
+        app.start_output_search();
+        for c in "ERROR".chars() {
+            app.push_search_char(c);
+        }
+        app.confirm_output_search();
+        // The first match from the top of the view is the visible "error 9"
+        let search = app.output_search().unwrap();
+        assert!(!search.editing);
+        assert_eq!(search.position, Some((4, 4)));
+        assert_eq!(search.current.as_ref().unwrap().line, 9);
+        assert!(!app.terminal().is_following());
+
+        // Wraps around to the top of the scrollback
+        app.next_search_match(true);
+        assert_eq!(app.output_search().unwrap().position, Some((1, 4)));
+        app.next_search_match(true);
+        assert_eq!(app.output_search().unwrap().current.as_ref().unwrap().line, 3);
+        app.next_search_match(false);
+        app.next_search_match(false);
+        assert_eq!(app.output_search().unwrap().position, Some((4, 4)));
+
+        // Case-sensitive search finds nothing
+        app.toggle_search_case();
+        app.next_search_match(true);
+        assert_eq!(app.output_search().unwrap().position, Some((0, 0)));
+
+        app.cancel_output_search();
+        assert!(app.output_search().is_none());
+    }
+
+    #[test]
+    fn test_click_card() {
+        let config: Config = toml::from_str(
+            r#"
+            [history]
+            enabled = false
+
+            [[custom_entries]]
+            name = "One"
+            exec = "true"
+
+            [[custom_entries]]
+            name = "Two"
+            exec = "true"
+            "#,
+        )
+        .unwrap();
+        let mut app = App::new(Vec::new(), config, false);
+
+        // Slots past the last entry hit nothing
+        assert!(!app.click_card(5));
+        assert_eq!(app.selected_index(), 0);
+
+        assert!(!app.click_card(1));
+        assert_eq!(app.selected_index(), 1);
+        assert!(app.click_card(1));
+        // A third click is a new first click
+        assert!(!app.click_card(1));
+
+        // Clicking another card in between breaks the double click
+        assert!(!app.click_card(0));
+        assert!(!app.click_card(1));
+    }
+
+    #[test]
+    fn test_reload_reports_changed_icons() {
+        let config = test_config();
+        let with_icon = |id: &str, icon: Option<&str>| Entry {
+            icon: icon.map(String::from),
+            ..entry(id)
+        };
+        let mut app = App::new(vec![with_icon("a", Some("a")), with_icon("b", None), with_icon("c", Some("c"))], config, false);
+
+        let changed = app.reload_entries(vec![
+            with_icon("a", Some("a")),
+            with_icon("b", Some("b")),
+            with_icon("c", Some("c-new")),
+            with_icon("d", Some("d")),
+        ]);
+        assert_eq!(changed, ["b", "c"]);
+    }
+
+    #[test]
+    fn test_idle_polls_do_not_draw() {
+        use ratatui::{backend::TestBackend, Terminal};
+
+        let config = test_config();
+        let mut app = App::new(vec![entry("firefox")], config, false);
+        let mut terminal = Terminal::new(TestBackend::new(80, 24)).unwrap();
+        let mut draws = 0;
+        // One pass of the main loop, without input
+        let mut cycle = |app: &mut App| {
+            app.poll().unwrap();
+            if app.take_redraw() {
+                terminal.draw(|f| crate::ui::draw(f, app, None)).unwrap();
+                draws += 1;
+            }
+        };
+
+        // The first frame, then nothing while idle
+        for _ in 0..100 {
+            cycle(&mut app);
+        }
+        app.mark_dirty();
+        cycle(&mut app);
+        cycle(&mut app);
+        // News from the background redraws by itself
+        app.compositor_health_checked = None;
+        app.compositor_enabled = true;
+        app.compositor_health = CompositorHealth::Connected;
+        cycle(&mut app);
+        cycle(&mut app);
+        // The heartbeat
+        app.last_draw = Some(Instant::now() - REDRAW_HEARTBEAT);
+        cycle(&mut app);
+        cycle(&mut app);
+        assert_eq!(draws, 4);
+    }
+
+    #[test]
+    fn test_long_lists_ranked_in_background() {
+        let config = test_config();
+        let entries: Vec<Entry> = (0..BACKGROUND_MATCH_MIN * 2)
+            .map(|n| entry(&format!("prog{}", n)))
+            .collect();
+        // Ranked on the spot, for comparison
+        let mut expected = App::new(entries.clone(), config.clone(), false);
+        let mut app = App::new(entries, config, false);
+        app.start_match_worker();
+
+        app.push_filter_char('1');
+        expected.push_filter_char('1');
+        let start = Instant::now();
+        while app.filtered != expected.filtered {
+            assert!(start.elapsed() < Duration::from_secs(10), "never ranked");
+            std::thread::sleep(Duration::from_millis(1));
+            app.poll().unwrap();
+        }
+        app.next();
+        expected.next();
+
+        // Typing doesn't wait for the ranking: the last one stays up
+        let shown = app.filtered.clone();
+        app.push_filter_char('2');
+        expected.push_filter_char('2');
+        assert_eq!(app.filtered, shown);
+        let start = Instant::now();
+        while app.filtered != expected.filtered {
+            assert!(start.elapsed() < Duration::from_secs(10), "never ranked");
+            std::thread::sleep(Duration::from_millis(1));
+            app.poll().unwrap();
+        }
+        assert_eq!(app.match_indices, expected.match_indices);
+        // Still picked, at its new position
+        assert_eq!(app.selected_entry().unwrap().id, expected.selected_entry().unwrap().id);
+
+        // Enter doesn't wait for the worker, nor run what was listed for
+        // the previous filter
+        app.push_filter_char('3');
+        expected.push_filter_char('3');
+        app.finish_ranking();
+        assert_eq!(app.filtered, expected.filtered);
+        assert_eq!(app.selected_entry().unwrap().id, expected.selected_entry().unwrap().id);
+        // The worker's late ranking is dropped
+        std::thread::sleep(Duration::from_millis(50));
+        app.poll().unwrap();
+        assert_eq!(app.filtered, expected.filtered);
+    }
+
+    #[test]
+    fn test_selection_kept_while_typing() {
+        let config = test_config();
+        let entries = vec![entry("fileroller"), entry("files"), entry("firefox"), entry("foot"), entry("vim")];
+        let mut app = App::new(entries, config, false);
+        let selected = |app: &App| app.selected_entry().unwrap().id.clone();
+
+        app.push_filter_char('f');
+        app.next();
+        app.next();
+        assert_eq!(selected(&app), "firefox");
+        // Still listed, at a new position
+        app.push_filter_char('i');
+        assert_eq!(selected(&app), "firefox");
+        app.push_filter_char('r');
+        assert_eq!(selected(&app), "firefox");
+        app.pop_filter_char();
+        assert_eq!(selected(&app), "firefox");
+
+        // Filtered out: the first entry
+        app.push_filter_char('l');
+        assert_eq!(app.selected_index(), 0);
+        // and it's no longer picked when it's listed again
+        app.pop_filter_char();
+        app.pop_filter_char();
+        assert_eq!(app.selected_index(), 0);
+
+        // Kept across a re-scan too
+        app.clear_filter();
+        app.select_id("foot");
+        app.reload_entries(vec![entry("foot"), entry("alacritty"), entry("vim")]);
+        assert_eq!(selected(&app), "foot");
+    }
+
+    #[test]
+    fn test_initial_filter_and_selection() {
+        let config = test_config();
+        let entries = vec![entry("firefox"), entry("foot"), entry("files"), entry("vim")];
+        let mut app = App::new(entries, config, false);
+
+        app.set_initial_filter("f\to");
+        assert!(app.is_filtering());
+        assert_eq!(app.filter_text(), "f o");
+        let names: Vec<_> = app.visible_entries().iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names.len(), 2);
+
+        app.select_id("foot");
+        assert_eq!(app.selected_entry().unwrap().id, "foot");
+        // Filtered out or unknown: back to the first entry
+        app.select_id("vim");
+        assert_eq!(app.selected_index(), 0);
+        app.set_initial_filter("");
+        app.select_id("vim");
+        assert_eq!(app.selected_entry().unwrap().id, "vim");
+
+        // --list shows the same order, scored when filtering
+        let listed = app.listed_entries();
+        assert_eq!(listed.len(), 4);
+        assert!(listed.iter().all(|e| e.score.is_none()));
+        app.set_initial_filter("foo");
+        let listed = app.listed_entries();
+        assert_eq!(listed[0].id, "foot");
+        assert!(listed[0].score.is_some_and(|score| score > 0.0));
+    }
+
+    #[test]
+    fn test_reload_config() {
+        let config = test_config();
+        let mut app = App::new(Vec::new(), config, false);
+        let dirs = app.config().desktop_entry_dirs.clone();
+
+        let reloaded: Config = toml::from_str(
+            "desktop_entry_dirs = [\"/nowhere\"]\n\
+             [appearance]\ncolumns = 3\nprompt = \"> \"\nshow_preview = true\n\
+             [history]\nenabled = false",
+        )
+        .unwrap();
+        app.reload_config(reloaded);
+        assert_eq!(app.grid_layout().columns, 3);
+        assert_eq!(app.config().appearance.prompt, "> ");
+        assert!(app.show_preview());
+        // Only read at startup
+        assert_eq!(app.config().desktop_entry_dirs, dirs);
+
+        let error = anyhow::anyhow!("TOML parse error at line 2\n  |\n2 | columns =\n")
+            .context("Failed to parse config from config.toml");
+        app.reject_config(&error);
+        let status = app.status_message().unwrap();
+        assert!(status.error);
+        assert_eq!(
+            status.text,
+            "Config not reloaded: Failed to parse config from config.toml: TOML parse error at line 2"
+        );
+        assert_eq!(app.grid_layout().columns, 3);
+    }
+
+    #[test]
+    fn test_compositor_health() {
+        assert_eq!(compositor_health(None, None), CompositorHealth::Off);
+        assert_eq!(
+            compositor_health(None, Some(Backend::Sway)),
+            CompositorHealth::Disconnected {
+                reason: "$SWAYSOCK is set but the socket doesn't exist".to_string(),
+                expected: true,
+            }
+        );
+
+        let dir = TempDir::new("health");
+        let socket = dir.join("health.sock");
+        std::fs::write(&socket, "").unwrap();
+        let niri = NiriClient::new(Some(&socket), Duration::from_millis(50)).unwrap();
+        assert_eq!(compositor_health(Some(&niri), Some(Backend::Niri)), CompositorHealth::Connected);
+
+        std::fs::remove_file(&socket).unwrap();
+        assert_eq!(
+            compositor_health(Some(&niri), None),
+            CompositorHealth::Disconnected {
+                reason: "the niri socket is gone".to_string(),
+                expected: false,
+            }
+        );
+    }
+
+    #[test]
+    fn test_idle_floating() {
+        // The state from before the command comes back
+        assert_eq!(idle_floating(Some(false), true), Some(false));
+        assert_eq!(idle_floating(Some(true), true), Some(true));
+        // Unknown: float as configured
+        assert_eq!(idle_floating(None, true), Some(true));
+        // float_on_idle = false leaves the window alone
+        assert_eq!(idle_floating(Some(true), false), None);
+        assert_eq!(idle_floating(None, false), None);
+    }
+
+    #[test]
+    fn test_compositor_spawn_argv() {
+        let entry = Entry {
+            id: "firefox".to_string(),
+            exec: Some(r#""/opt/fire fox/firefox" --new-window %u"#.to_string()),
+            ..Default::default()
+        };
+        let launch = LaunchOptions::default();
+        assert_eq!(
+            compositor_spawn_argv(&entry, &[], &launch).unwrap(),
+            ["/opt/fire fox/firefox", "--new-window"]
+        );
+        let terminal = ["kitty".to_string(), "-e".to_string()];
+        assert_eq!(compositor_spawn_argv(&entry, &terminal, &launch).unwrap()[..3], ["kitty", "-e", "/opt/fire fox/firefox"]);
+
+        // Shell command lines, working directories and environment need a
+        // local launch
+        assert!(compositor_spawn_argv(&Entry::typed_command("firefox"), &[], &launch).is_none());
+        let in_dir = LaunchOptions {
+            working_dir: Some(PathBuf::from("/tmp")),
+            ..LaunchOptions::default()
+        };
+        assert!(compositor_spawn_argv(&entry, &[], &in_dir).is_none());
+        let with_env = LaunchOptions {
+            env: vec![("MOZ_ENABLE_WAYLAND".to_string(), "1".to_string())],
+            ..LaunchOptions::default()
+        };
+        assert!(compositor_spawn_argv(&entry, &[], &with_env).is_none());
+    }
+
+    #[test]
+    fn test_window_list() {
+        let config = test_config();
+        let mut app = App::new(vec![entry("firefox")], config, false);
+        app.push_filter_char('f');
+
+        let window = |id: u64, title: &str| WindowInfo {
+            id,
+            app_id: "firefox".to_string(),
+            title: title.to_string(),
+            ..Default::default()
+        };
+        let windows = windows::entries(&[window(1, "Mail"), window(2, "Docs")], &[], app.app_entries());
+        app.set_compositor_list(CompositorList::Windows, Ok(windows));
+        assert_eq!(app.compositor_list(), Some(CompositorList::Windows));
+        assert_eq!(app.visible_entries()[0].name, "Mail");
+        assert!(app.filter_text().is_empty());
+        assert_eq!(app.visible_entries().len(), 2);
+        // Filtering finds windows; the text is never a command
+        app.push_filter_char('m');
+        assert_eq!(app.selected_entry().unwrap().name, "Mail");
+        assert_eq!(app.typed_command(), None);
+
+        // Reloads update the applications behind the list
+        app.reload_entries(vec![entry("firefox"), entry("foot")]);
+        assert_eq!(app.visible_entries()[0].name, "Mail");
+
+        app.show_applications();
+        assert_eq!(app.compositor_list(), None);
+        assert_eq!(app.visible_entries().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_dmenu_picks_without_running() {
+        let lines = ["reboot", "shutdown", "suspend"].map(String::from).to_vec();
+        let mut app = App::new_dmenu(Dmenu::new(lines), Config::default(), None);
+        // Input order, nothing from desktop files or config
+        let names: Vec<_> = app.visible_entries().iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, ["reboot", "shutdown", "suspend"]);
+
+        app.next();
+        let entry = app.selected_entry().unwrap().clone();
+        app.execute_entry(entry, 80, 24).await.unwrap();
+        assert!(matches!(app.mode(), AppMode::Exit));
+        assert!(!app.is_executing());
+        assert_eq!(app.dmenu().unwrap().output(false).as_deref(), Some("shutdown"));
+        assert_eq!(app.dmenu().unwrap().output(true).as_deref(), Some("1"));
+
+        // Text matching nothing is picked as typed
+        let mut app = App::new_dmenu(Dmenu::new(vec!["a".to_string()]), Config::default(), None);
+        app.start_filter();
+        app.push_filter_char('z');
+        app.execute_typed_command(80, 24).await.unwrap();
+        assert_eq!(app.dmenu().unwrap().output(false).as_deref(), Some("z"));
+    }
+
+    #[test]
+    fn test_run_mode() {
+        let config = test_config();
+        let program = |id: &str, name: &str| Entry {
+            name: name.to_string(),
+            exec: Some(name.to_string()),
+            custom: id.starts_with(executables::RUN_PREFIX),
+            ..entry(id)
+        };
+        let mut app = App::new(vec![program("btop", "Btop")], config, false);
+        app.add_executables(vec![program("run:btop", "btop"), program("run:wev", "wev")]);
+        // Programs stay out of the application list and its search
+        assert_eq!(app.visible_entries().len(), 1);
+        app.set_initial_filter("wev");
+        assert!(app.visible_entries().is_empty());
+
+        app.set_initial_filter("> bt");
+        assert!(app.showing_executables());
+        let ids: Vec<_> = app.visible_entries().iter().map(|e| e.id.as_str()).collect();
+        assert_eq!(ids, ["run:btop"]);
+        assert_eq!(app.typed_command(), Some("bt"));
+
+        // Streamed-in programs keep the selection
+        app.set_initial_filter("");
+        app.set_run_mode(true);
+        app.select_id("run:wev");
+        app.add_executables(vec![program("run:aerc", "aerc")]);
+        assert_eq!(app.visible_entries().len(), 3);
+        assert_eq!(app.selected_entry().unwrap().id, "run:wev");
+
+        // and survive a reload of the desktop entries
+        app.reload_entries(vec![program("btop", "Btop")]);
+        assert_eq!(app.visible_entries().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_hide_resets_launcher() {
+        let config = test_config();
+        let mut app = App::new(vec![entry("firefox"), entry("foot")], config, false);
+        app.set_initial_filter("foot");
+        app.open_help();
+        app.mode = AppMode::Exit;
+
+        app.hide();
+        assert!(app.is_hidden());
+        assert!(matches!(app.mode(), AppMode::Launcher));
+        assert!(app.filter_text().is_empty());
+        assert_eq!(app.help_scroll(), None);
+        assert_eq!(app.visible_entries().len(), 2);
+
+        app.show().await;
+        assert!(!app.is_hidden());
+    }
+
+    #[tokio::test]
+    async fn test_print_only() {
+        let config = test_config();
+        let entry = Entry {
+            id: "org.gnome.Nautilus".to_string(),
+            name: "Files".to_string(),
+            exec: Some("nautilus --new-window %U".to_string()),
+            ..Default::default()
+        };
+        let mut app = App::new(vec![entry.clone()], config, false);
+        app.set_execution_policy(ExecutionPolicy::Print);
+
+        app.execute_entry(entry, 80, 24).await.unwrap();
+        assert!(matches!(app.mode(), AppMode::Exit));
+        assert!(!app.is_executing());
+        let printed = app.printed().unwrap();
+        assert_eq!(printed.command, "nautilus --new-window");
+        assert_eq!(printed.format(false), "org.gnome.Nautilus\tnautilus --new-window");
+        assert_eq!(
+            printed.format(true),
+            r#"{"command":"nautilus --new-window","id":"org.gnome.Nautilus"}"#
+        );
+    }
+
+    #[tokio::test]
+    async fn test_compositor_list_without_compositor() {
+        let config = test_config();
+        let mut app = App::new(Vec::new(), config, false);
+
+        app.show_workspaces().await;
+        assert_eq!(app.compositor_list(), Some(CompositorList::Workspaces));
+        assert_eq!(app.compositor_list_error(), Some("No compositor connected"));
+        assert!(app.visible_entries().is_empty());
+
+        // Ctrl+W goes to windows, then back to applications
+        app.toggle_windows().await;
+        assert_eq!(app.compositor_list(), Some(CompositorList::Windows));
+        assert_eq!(app.compositor_list_error(), Some("No compositor connected"));
+        app.toggle_windows().await;
+        assert_eq!(app.compositor_list(), None);
+    }
+
+    #[test]
+    fn test_exit_when_launched_window_opens() {
+        let config = test_config();
+        let mut app = App::new(Vec::new(), config, false);
+        let (tx, rx) = mpsc::unbounded_channel();
+        app.compositor_events = Some(rx);
+
+        let entry = Entry {
+            id: "org.mozilla.firefox".to_string(),
+            name: "Firefox".to_string(),
+            ..Default::default()
+        };
+        app.awaiting_window = Some(AwaitedWindow::new(&entry, Some("/usr/bin/firefox-esr"), Duration::from_secs(60)));
+        let window = |app_id: &str| Event::WindowOpenedOrChanged {
+            window: WindowInfo {
+                id: 1,
+                app_id: app_id.to_string(),
+                ..Default::default()
+            },
+        };
+
+        tx.send(window("kitty")).unwrap();
+        app.poll_compositor_events();
+        assert!(app.awaiting_window().is_some());
+        assert!(matches!(app.mode(), AppMode::Launcher));
+
+        // The last part of the Flatpak id, any case
+        tx.send(window("Firefox")).unwrap();
+        app.poll_compositor_events();
+        assert!(app.awaiting_window().is_none());
+        assert!(matches!(app.mode(), AppMode::Exit));
+
+        // The program name matches too; timing out or losing niri exits
+        let awaited = AwaitedWindow::new(&entry, Some("/usr/bin/firefox-esr"), Duration::ZERO);
+        assert!(awaited.matches(&WindowInfo { id: 2, app_id: "firefox-esr".to_string(), ..Default::default() }));
+        app.mode = AppMode::Launcher;
+        app.awaiting_window = Some(awaited);
+        app.poll_compositor_events();
+        assert!(matches!(app.mode(), AppMode::Exit));
+
+        app.mode = AppMode::Launcher;
+        app.awaiting_window = Some(AwaitedWindow::new(&entry, None, Duration::from_secs(60)));
+        drop(tx);
+        app.poll_compositor_events();
+        assert!(matches!(app.mode(), AppMode::Exit));
+    }
+
+    #[test]
+    fn test_name_match_indices() {
+        let config: Config = toml::from_str(
+            r#"
+            [history]
+            enabled = false
+
+            [[custom_entries]]
+            name = "Firefox"
+            exec = "firefox"
+            categories = ["WebBrowser"]
+            "#,
+        )
+        .unwrap();
+        let mut app = App::new(Vec::new(), config, false);
+        assert_eq!(app.name_match_indices(0), &[] as &[u32]);
+
+        for c in "ffx".chars() {
+            app.push_filter_char(c);
+        }
+        assert_eq!(app.name_match_indices(0), &[0, 4, 6]);
+
+        // Matched only in the categories: nothing to highlight in the name
+        app.clear_filter();
+        for c in "browser".chars() {
+            app.push_filter_char(c);
+        }
+        assert_eq!(app.selected_entry().unwrap().name, "Firefox");
+        assert_eq!(app.name_match_indices(0), &[] as &[u32]);
+    }
+
+    #[test]
+    fn test_fit_grid_keeps_selection() {
+        let mut config: Config = toml::from_str(
+            "[history]\nenabled = false\n[appearance]\ncolumns = \"auto\"\nvisible_rows = 2",
+        )
+        .unwrap();
+        for i in 0..12 {
+            config.custom_entries.push(toml::from_str(&format!("name = \"E{:02}\"\nexec = \"true\"", i)).unwrap());
+        }
+        let mut app = App::new(Vec::new(), config, false);
+        app.fit_grid(200);
+        assert_eq!(app.grid_layout().columns, 6);
+        app.move_end();
+        assert_eq!(app.selected_entry().unwrap().name, "E11");
+
+        // Narrower: fewer columns, more pages, same entry
+        app.fit_grid(40);
+        assert_eq!(app.grid_layout().columns, 1);
+        assert_eq!(app.selected_entry().unwrap().name, "E11");
+        assert_eq!(app.grid_layout().visible_range(app.selected_index(), 12), 10..12);
+    }
+
+    #[test]
+    fn test_help_overlay() {
+        let config = test_config();
+        let mut app = App::new(Vec::new(), config, false);
+        assert_eq!(app.help_scroll(), None);
+
+        app.open_categories();
+        assert_eq!(app.key_context(), KeyContext::Categories);
+        app.open_help();
+        app.scroll_help(-1, 30);
+        assert_eq!(app.help_scroll(), Some(0));
+        // Until the last line is at the bottom of the overlay
+        app.scroll_help(isize::MAX, 30);
+        assert_eq!(app.help_scroll(), Some(keymap::help_lines() - 28));
+
+        // The mode underneath is untouched
+        app.close_help();
+        assert_eq!(app.help_scroll(), None);
+        assert!(app.is_browsing_categories());
+    }
+
+    async fn run_typed(app: &mut App, cmd: &str) {
+        app.clear_filter();
+        for c in cmd.chars() {
+            app.push_filter_char(c);
+        }
+        app.execute_typed_command(80, 24).await.unwrap();
+        while app.is_executing() {
+            app.poll_execution().unwrap();
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        app.dismiss_output();
+    }
+
+    #[tokio::test]
+    async fn test_notifies_only_long_commands() {
+        let config: Config =
+            toml::from_str("[behavior]\nnotify_after_secs = 1\n[history]\nenabled = false").unwrap();
+        let mut app = App::new(Vec::new(), config, false);
+        let recorder = Recorder::default();
+        app.set_notifier(Box::new(recorder.clone()));
+
+        run_typed(&mut app, "true").await;
+        assert!(recorder.notifications().is_empty());
+
+        run_typed(&mut app, "sleep 1.1").await;
+        let notifications = recorder.notifications();
+        assert_eq!(notifications.len(), 1);
+        assert!(notifications[0].1.starts_with("sleep 1.1\n"));
+    }
+
+    #[tokio::test]
+    async fn test_timeout_exempts_other_modes() {
+        let mut app = hanging_app("long-running", "kill");
+        run_hang(&mut app).await;
+
+        tokio::time::sleep(Duration::from_millis(1100)).await;
+        app.poll_execution().unwrap();
+        assert!(app.is_executing());
+        assert!(!app.is_timed_out());
+    }
+}
@@ -0,0 +1,66 @@
+//! System clipboard access.
+//!
+//! Locally the clipboard is set with `wl-copy`. Text that commands copy
+//! with OSC 52 is sent the same way, except over SSH: there the clipboard
+//! that matters is the one of the user's own terminal, so the OSC 52
+//! sequence is passed on to it through stdout.
+
+use anyhow::Result;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use termwiz::escape::osc::{OperatingSystemCommand, Selection};
+
+/// Copy `text` to the clipboard using wl-copy
+pub fn copy(text: &str) -> Result<()> {
+    let mut child = Command::new("wl-copy")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| anyhow::anyhow!("Failed to spawn wl-copy: {}", e))?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(text.as_bytes())?;
+    }
+
+    child.wait()?;
+    Ok(())
+}
+
+/// Copy text a command wrote with OSC 52
+///
+/// Best-effort: failures are only logged, the command never hears back.
+pub fn copy_from_command(text: &str) {
+    let result = if std::env::var("SSH_CONNECTION").is_ok() {
+        forward(text)
+    } else {
+        copy(text)
+    };
+    if let Err(e) = result {
+        tracing::warn!("Failed to copy OSC 52 clipboard data: {:#}", e);
+    }
+}
+
+/// Pass `text` to the outer terminal as an OSC 52 clipboard write
+fn forward(text: &str) -> Result<()> {
+    let mut stdout = std::io::stdout();
+    stdout.write_all(osc52(text).as_bytes())?;
+    stdout.flush()?;
+    Ok(())
+}
+
+/// OSC 52 sequence setting the clipboard to `text`
+fn osc52(text: &str) -> String {
+    OperatingSystemCommand::SetSelection(Selection::CLIPBOARD, text.to_string()).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_osc52_encodes_text() {
+        assert_eq!(osc52("hello"), "\x1b]52;c;aGVsbG8=\x1b\\");
+    }
+}
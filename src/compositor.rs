@@ -0,0 +1,471 @@
+//! Window management through the compositor's IPC.
+//!
+//! drun floats its window while the launcher shows and tiles it for
+//! commands, lists windows and workspaces, and can have the compositor
+//! start GUI apps. The `Compositor` trait covers those calls; niri, sway
+//! (and i3) and Hyprland implement it over their sockets.
+//!
+//! # Backend Selection
+//!
+//! `compositor.backend` picks the backend. With `auto` (the default) it
+//! follows the session: `$NIRI_SOCKET`, then `$SWAYSOCK` / `$I3SOCK`, then
+//! `$HYPRLAND_INSTANCE_SIGNATURE`. Without any of them niri is still tried,
+//! as it may be found through `niri.socket_path` or `$XDG_RUNTIME_DIR`.
+//!
+//! # Graceful Degradation
+//!
+//! Every backend is optional: over SSH or in another session no socket is
+//! found and `connect()` returns None. Requests time out after
+//! `compositor.request_timeout_ms`; the backend is then left alone until its
+//! socket is recreated (see `IpcSocket`).
+
+use anyhow::Result;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+use crate::config::Config;
+use crate::hyprland::HyprlandClient;
+use crate::niri::NiriClient;
+use crate::sway::SwayClient;
+
+/// Future returned by `Compositor` calls
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T>> + Send + 'a>>;
+
+/// Window management calls drun makes, whatever the compositor
+///
+/// Calls without a window argument act on the focused window, which is
+/// drun's own while it is in use.
+pub trait Compositor: Send + Sync {
+    /// Name for the status bar and log, e.g. "niri"
+    fn name(&self) -> &'static str;
+
+    /// Socket the client talks to
+    fn socket_path(&self) -> &Path;
+
+    /// Whether the socket is there and didn't time out
+    fn is_available(&self) -> bool;
+
+    /// Why the last request failed, if it did
+    fn last_error(&self) -> Option<String>;
+
+    /// Set the focused window's floating state
+    fn set_floating(&self, floating: bool) -> BoxFuture<'_, ()>;
+
+    /// Float a tiled focused window, tile a floating one
+    fn toggle_floating(&self) -> BoxFuture<'_, ()>;
+
+    /// The focused window; None if no window has focus
+    fn focused_window(&self) -> BoxFuture<'_, Option<WindowInfo>>;
+
+    /// All open windows
+    fn windows(&self) -> BoxFuture<'_, Vec<WindowInfo>>;
+
+    /// All workspaces, on every output
+    fn workspaces(&self) -> BoxFuture<'_, Vec<WorkspaceInfo>>;
+
+    /// Focus the window with `id`, switching workspace if needed
+    fn focus_window(&self, id: u64) -> BoxFuture<'_, ()>;
+
+    /// Switch to a workspace
+    fn focus_workspace(&self, reference: WorkspaceReference) -> BoxFuture<'_, ()>;
+
+    /// Move a window (None: the focused one) to a workspace, following it
+    /// there if `focus` is set
+    fn move_window_to_workspace(
+        &self,
+        window_id: Option<u64>,
+        reference: WorkspaceReference,
+        focus: bool,
+    ) -> BoxFuture<'_, ()>;
+
+    /// Start a program as the compositor's child, with its environment
+    fn spawn(&self, command: Vec<String>) -> BoxFuture<'_, ()>;
+
+    /// Listen to window events on a background task
+    ///
+    /// Returns None outside a tokio runtime. The channel closes when the
+    /// stream ends (the compositor exited).
+    fn spawn_event_listener(&self) -> Option<mpsc::UnboundedReceiver<Event>>;
+
+    /// A client sharing this one's state, for background tasks
+    fn boxed_clone(&self) -> Box<dyn Compositor>;
+}
+
+/// `compositor.backend`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+    /// Follow the session's environment
+    #[default]
+    Auto,
+    Niri,
+    /// sway, or i3 which speaks the same protocol
+    Sway,
+    Hyprland,
+    /// No compositor integration
+    None,
+}
+
+impl Backend {
+    /// The backend whose session drun runs in, by environment variable
+    pub fn of_session() -> Option<Backend> {
+        [Backend::Niri, Backend::Sway, Backend::Hyprland]
+            .into_iter()
+            .find(|backend| backend.in_session())
+    }
+
+    /// Whether the session variable of this backend is set
+    pub fn in_session(self) -> bool {
+        self.session_vars().iter().any(|var| std::env::var_os(var).is_some())
+    }
+
+    /// Environment variables a session of this backend sets
+    pub fn session_vars(self) -> &'static [&'static str] {
+        match self {
+            Backend::Niri => &["NIRI_SOCKET"],
+            Backend::Sway => &["SWAYSOCK", "I3SOCK"],
+            Backend::Hyprland => &["HYPRLAND_INSTANCE_SIGNATURE"],
+            Backend::Auto | Backend::None => &[],
+        }
+    }
+
+    /// The backend to connect to; None for `none`
+    pub fn resolve(self) -> Option<Backend> {
+        match self {
+            Backend::Auto => Some(Backend::of_session().unwrap_or(Backend::Niri)),
+            Backend::None => None,
+            backend => Some(backend),
+        }
+    }
+}
+
+/// Connect to the compositor picked by `compositor.backend`
+///
+/// Returns None if its socket isn't found (e.g., over SSH); drun works
+/// fine without.
+pub fn connect(config: &Config) -> Option<Box<dyn Compositor>> {
+    let timeout = Duration::from_millis(config.compositor.request_timeout_ms);
+    let client: Result<Box<dyn Compositor>> = match config.compositor.backend.resolve()? {
+        Backend::Niri => NiriClient::new(config.niri_socket_path().as_deref(), timeout)
+            .map(|client| Box::new(client) as Box<dyn Compositor>),
+        Backend::Sway => SwayClient::new(timeout).map(|client| Box::new(client) as Box<dyn Compositor>),
+        Backend::Hyprland => {
+            HyprlandClient::new(timeout).map(|client| Box::new(client) as Box<dyn Compositor>)
+        }
+        Backend::Auto | Backend::None => return None,
+    };
+    match client {
+        Ok(client) => Some(client),
+        Err(e) => {
+            tracing::debug!("Compositor IPC not available: {}", e);
+            None
+        }
+    }
+}
+
+/// Window events drun reacts to
+///
+/// The variants follow niri's event stream, which is parsed into them
+/// directly; other backends translate their events.
+#[derive(Debug, Clone, Deserialize)]
+#[allow(dead_code)] // Focus and workspace events: Public API for future use
+pub enum Event {
+    /// A window was opened, or its title, app_id or state changed
+    WindowOpenedOrChanged { window: WindowInfo },
+    /// Focus moved to another window (None: no window focused)
+    WindowFocusChanged { id: Option<u64> },
+    /// A workspace was activated
+    WorkspaceActivated { id: u64, focused: bool },
+}
+
+/// Open event stream of a backend
+pub trait EventSource: Send {
+    /// Next event drun knows about; None when the stream closed
+    fn next_event(&mut self) -> BoxFuture<'_, Option<Event>>;
+}
+
+/// Forward the events of the stream `open` connects to to a channel
+///
+/// Returns None outside a tokio runtime.
+pub fn spawn_event_listener<S: EventSource + 'static>(
+    name: &'static str,
+    open: impl Future<Output = Result<S>> + Send + 'static,
+) -> Option<mpsc::UnboundedReceiver<Event>> {
+    let runtime = tokio::runtime::Handle::try_current().ok()?;
+    let (tx, rx) = mpsc::unbounded_channel();
+    runtime.spawn(async move {
+        let mut stream = match open.await {
+            Ok(stream) => stream,
+            Err(e) => {
+                tracing::debug!("{} event stream unavailable: {}", name, e);
+                return;
+            }
+        };
+        loop {
+            match stream.next_event().await {
+                Ok(Some(event)) => {
+                    if tx.send(event).is_err() {
+                        break;
+                    }
+                }
+                Ok(None) => break,
+                Err(e) => {
+                    tracing::debug!("{} event stream failed: {}", name, e);
+                    break;
+                }
+            }
+        }
+    });
+    Some(rx)
+}
+
+/// Compositor socket with the timeout state shared by a client's clones
+///
+/// A request that times out marks the socket stalled: calls fail right
+/// away until the socket is replaced (the compositor restarted), so a
+/// hung compositor never blocks a launch twice.
+#[derive(Clone)]
+pub struct IpcSocket {
+    /// Compositor name for messages
+    name: &'static str,
+    path: PathBuf,
+    /// Longest a request may take
+    timeout: Duration,
+    /// Socket a request timed out on
+    stalled: Arc<Mutex<Option<SocketId>>>,
+    /// Why the last request failed; None after a success
+    last_error: Arc<Mutex<Option<String>>>,
+}
+
+impl IpcSocket {
+    pub fn new(name: &'static str, path: PathBuf, timeout: Duration) -> Self {
+        tracing::info!("Using {} socket: {}", name, path.display());
+        Self {
+            name,
+            path,
+            timeout,
+            stalled: Arc::new(Mutex::new(None)),
+            last_error: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Whether the socket file exists and isn't the one that stalled
+    ///
+    /// A quick filesystem check, not a connection test.
+    pub fn is_available(&self) -> bool {
+        let Ok(meta) = std::fs::metadata(&self.path) else {
+            return false;
+        };
+        let mut stalled = self.stalled.lock();
+        match *stalled {
+            Some(id) if id == SocketId::of(&meta) => false,
+            Some(_) => {
+                tracing::info!("{} socket was replaced, using it again", self.name);
+                *stalled = None;
+                true
+            }
+            None => true,
+        }
+    }
+
+    pub fn last_error(&self) -> Option<String> {
+        self.last_error.lock().clone()
+    }
+
+    /// Run a request, remembering whether it failed
+    ///
+    /// Fails right away while the socket is stalled, and after the
+    /// timeout if the compositor doesn't answer.
+    pub async fn request<T>(&self, io: impl Future<Output = Result<T>>) -> Result<T> {
+        if !self.is_available() {
+            anyhow::bail!("{} is not responding", self.name);
+        }
+        let result = self.with_timeout(io).await;
+        *self.last_error.lock() = result.as_ref().err().map(|e| format!("{:#}", e));
+        result
+    }
+
+    /// Run `io` against the socket, marking it stalled if it doesn't
+    /// finish in time
+    pub async fn with_timeout<T>(&self, io: impl Future<Output = Result<T>>) -> Result<T> {
+        match tokio::time::timeout(self.timeout, io).await {
+            Ok(result) => result,
+            Err(_) => {
+                let id = std::fs::metadata(&self.path)
+                    .map(|meta| SocketId::of(&meta))
+                    .unwrap_or_default();
+                if self.stalled.lock().replace(id).is_none() {
+                    tracing::warn!(
+                        "{} didn't answer within {:?}, not using it until it restarts",
+                        self.name,
+                        self.timeout
+                    );
+                }
+                anyhow::bail!("{} request timed out", self.name)
+            }
+        }
+    }
+}
+
+/// Identity of a socket file: inode numbers alone are reused right away
+/// when a socket is recreated
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct SocketId {
+    inode: u64,
+    ctime: (i64, i64),
+}
+
+impl SocketId {
+    fn of(meta: &std::fs::Metadata) -> Self {
+        Self {
+            inode: meta.ino(),
+            ctime: (meta.ctime(), meta.ctime_nsec()),
+        }
+    }
+}
+
+/// Workspace argument of workspace calls
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[allow(dead_code)] // Index and Name: Public API for future use
+pub enum WorkspaceReference {
+    /// Workspace id, stable while it exists
+    Id(u64),
+    /// Position on the focused output, counting from 1
+    Index(u8),
+    /// Named workspace from the compositor's config
+    Name(String),
+}
+
+/// Information about a window.
+///
+/// # Fields
+///
+/// - `id`: Unique window identifier (stable for window lifetime)
+/// - `app_id`: Wayland app_id (e.g., "firefox", "org.gnome.Nautilus"),
+///   or the X11 class of XWayland windows
+/// - `title`: Current window title (may change dynamically)
+/// - `is_floating`: Whether window is in floating state
+///
+/// # Use Cases
+///
+/// ```ignore
+/// let info = client.focused_window().await?.unwrap();
+///
+/// // Check if this is our own window
+/// if info.app_id == "darkwall-drun" {
+///     // We're focused, good
+/// }
+///
+/// // Remember float state for later restoration
+/// let was_floating = info.is_floating;
+///
+/// // Log for debugging
+/// tracing::debug!("Focused: {} ({})", info.title, info.app_id);
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct WindowInfo {
+    /// Unique window identifier assigned by the compositor.
+    /// Stable for the lifetime of the window.
+    pub id: u64,
+
+    /// Wayland app_id (similar to X11 WM_CLASS).
+    /// Set by the application, e.g., "firefox", "kitty".
+    #[serde(default)]
+    pub app_id: String,
+
+    /// Current window title.
+    /// May change dynamically (e.g., browser tab changes).
+    #[serde(default)]
+    pub title: String,
+
+    /// Whether the window is currently floating.
+    /// `false` means tiled in the layout.
+    #[serde(default)]
+    pub is_floating: bool,
+
+    /// Whether the window has keyboard focus.
+    #[serde(default)]
+    pub is_focused: bool,
+
+    /// Process that created the window, if the compositor knows it.
+    #[serde(default)]
+    pub pid: Option<i32>,
+
+    /// Workspace the window is on (None while it is being moved).
+    #[serde(default)]
+    pub workspace_id: Option<u64>,
+}
+
+/// A workspace
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct WorkspaceInfo {
+    pub id: u64,
+    /// Position on its output, counting from 1
+    pub idx: u64,
+    /// Named workspaces from the compositor's config
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Connector of the output it is on, e.g. `DP-1`
+    #[serde(default)]
+    pub output: Option<String>,
+    /// Shown on its output
+    #[serde(default)]
+    pub is_active: bool,
+    /// Active on the focused output
+    #[serde(default)]
+    pub is_focused: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+
+    #[test]
+    fn test_backend_config() {
+        let backend = |toml: &str| toml::from_str::<Config>(toml).unwrap().compositor.backend;
+        assert_eq!(backend(""), Backend::Auto);
+        assert_eq!(backend("[compositor]\nbackend = \"sway\""), Backend::Sway);
+        assert_eq!(backend("[compositor]\nbackend = \"none\""), Backend::None);
+        assert!(toml::from_str::<Config>("[compositor]\nbackend = \"kwin\"").is_err());
+
+        assert_eq!(Backend::None.resolve(), None);
+        assert_eq!(Backend::Hyprland.resolve(), Some(Backend::Hyprland));
+        // Without a session variable auto still tries niri's socket
+        assert!(Backend::Auto.resolve().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_request_timeout_stalls_socket() {
+        let dir = TempDir::new("ipc");
+        let path = dir.join("wm.sock");
+        let _listener = tokio::net::UnixListener::bind(&path).unwrap();
+
+        let socket = IpcSocket::new("sway", path.clone(), Duration::from_millis(50));
+        let err = socket.request(std::future::pending::<Result<()>>()).await.unwrap_err();
+        assert_eq!(err.to_string(), "sway request timed out");
+        assert_eq!(socket.last_error().as_deref(), Some("sway request timed out"));
+        assert!(!socket.is_available());
+        let err = socket.clone().request(async { Ok(()) }).await.unwrap_err();
+        assert_eq!(err.to_string(), "sway is not responding");
+
+        // A new socket (the compositor restarted) is used again
+        drop(_listener);
+        std::fs::remove_file(&path).unwrap();
+        let _listener = tokio::net::UnixListener::bind(&path).unwrap();
+        assert!(socket.is_available());
+        socket.request(async { Ok(()) }).await.unwrap();
+        assert_eq!(socket.last_error(), None);
+    }
+}
@@ -1,10 +1,22 @@
 use anyhow::{Context, Result};
+use ratatui::layout::Rect;
+use regex::Regex;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 
-use crate::ui::theme::{parse_hex_color, Theme};
-use crate::ui::layout::GridLayout;
-use crate::ui::entry_card::EntryDisplayConfig;
+use crate::compositor::Backend;
+use crate::config_check;
+use crate::desktop_entry::{self, Entry, LoadOptions};
+use crate::executor::{self, LaunchOptions, ModeRules, TerminalMode};
+use crate::output_file;
+use crate::ui::theme::{self, parse_hex_color, Background, Theme};
+use crate::glyphs::{GlyphIcons, IconFallback};
+use crate::icons::{IconOptions, SymbolicColors};
+use crate::ui::layout::{self, BorderStyle, Extent, GridLayout, GridOrder};
+use crate::ui::card_format::CardFormat;
+use crate::ui::entry_card::{EntryDisplayConfig, EntryLayout};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(default)]
@@ -12,10 +24,183 @@ pub struct Config {
     pub desktop_entry_dirs: Vec<PathBuf>,
     pub appearance: AppearanceConfig,
     pub theme: ThemeConfig,
+    /// Window management through the compositor's IPC
+    pub compositor: CompositorConfig,
     pub niri: NiriConfig,
     pub behavior: BehaviorConfig,
     pub history: HistoryConfig,
     pub icons: IconsConfig,
+    /// Extra environment variables for every launched command
+    /// (values support $VAR expansion)
+    pub env: BTreeMap<String, String>,
+    /// Launcher entries defined in config instead of .desktop files
+    #[serde(deserialize_with = "deserialize_custom_entries")]
+    pub custom_entries: Vec<CustomEntryConfig>,
+    /// Per-entry overrides keyed by desktop file id
+    pub entries: BTreeMap<String, EntryOverride>,
+    /// Terminal mode rules consulted before the builtin app lists
+    pub terminal_mode: TerminalModeConfig,
+    /// Saving command output to files
+    pub output: OutputConfig,
+    /// User scripts run around command execution
+    pub hooks: HooksConfig,
+}
+
+/// `[hooks]`: shell commands run before and after every launch
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HooksConfig {
+    /// Run before a command is launched; the launch waits for them
+    pub pre_exec: Vec<String>,
+    /// Run in the background after a command exits
+    pub post_exec: Vec<String>,
+    /// Seconds each hook may run before it is killed
+    pub timeout_secs: u64,
+}
+
+impl Default for HooksConfig {
+    fn default() -> Self {
+        Self {
+            pre_exec: Vec::new(),
+            post_exec: Vec::new(),
+            timeout_secs: 5,
+        }
+    }
+}
+
+/// `[output]`: saving command output to files
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OutputConfig {
+    /// Directory for saved output (default ~/.local/share/darkwall-drun/output)
+    pub dir: Option<String>,
+    /// Save the output of every command when it finishes
+    pub auto_save: bool,
+}
+
+/// `[terminal_mode]`: extra rules for terminal mode detection
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TerminalModeConfig {
+    /// Full screen apps (matched against the command name)
+    pub tui: Vec<String>,
+    /// REPL-style apps (matched against the command name)
+    pub interactive: Vec<String>,
+    /// Substrings marking a command line as long-running
+    pub long_running_patterns: Vec<String>,
+    /// Regex rules matched against the whole command line, checked first
+    pub rules: Vec<ModeRuleConfig>,
+}
+
+/// A `[[terminal_mode.rules]]` entry
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ModeRuleConfig {
+    /// Regular expression matched against the command line
+    pub pattern: String,
+    /// Mode for matching commands (gui, oneshot, interactive, tui, long-running)
+    pub mode: String,
+}
+
+/// Override for a single entry in `[entries."<desktop file id>"]`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EntryOverride {
+    /// Never show this entry
+    pub hidden: bool,
+    /// Replacement display name
+    pub name: Option<String>,
+    /// Replacement icon name or path
+    pub icon: Option<String>,
+    /// Override terminal mode detection (like X-DarkwallTerminalMode)
+    pub terminal_mode: Option<String>,
+}
+
+/// A launcher entry defined in `[[custom_entries]]`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CustomEntryConfig {
+    pub name: String,
+    /// Shell command line, run with `sh -c`
+    pub exec: String,
+    pub icon: Option<String>,
+    /// Override terminal mode detection (gui, oneshot, interactive, tui, long-running)
+    pub terminal_mode: Option<String>,
+    #[serde(default)]
+    pub categories: Vec<String>,
+}
+
+impl CustomEntryConfig {
+    /// Stable id used for history, independent of list position
+    pub fn id(&self) -> String {
+        format!("custom:{}", self.name)
+    }
+
+    /// Check fields that serde can't, returning a reason if invalid
+    fn validate(&self) -> std::result::Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("name is empty".to_string());
+        }
+        if self.exec.trim().is_empty() {
+            return Err("exec is empty".to_string());
+        }
+        if let Some(ref mode) = self.terminal_mode {
+            if mode.parse::<TerminalMode>().is_err() {
+                return Err(format!("unknown terminal_mode \"{}\"", mode));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl From<&CustomEntryConfig> for Entry {
+    fn from(custom: &CustomEntryConfig) -> Self {
+        let mut entry = Entry {
+            id: custom.id(),
+            name: custom.name.clone(),
+            exec: Some(custom.exec.clone()),
+            icon: custom.icon.clone(),
+            categories: custom.categories.clone(),
+            // Treated like a terminal command so the usual TUI/interactive
+            // detection applies; use terminal_mode = "gui" for GUI apps
+            terminal: true,
+            custom: true,
+            ..Default::default()
+        };
+        if let Some(ref mode) = custom.terminal_mode {
+            entry.custom_fields.insert("TerminalMode".to_string(), mode.clone());
+        }
+        entry
+    }
+}
+
+/// Deserialize `[[custom_entries]]`, skipping malformed ones with a warning
+/// instead of failing the whole config
+fn deserialize_custom_entries<'de, D>(deserializer: D) -> std::result::Result<Vec<CustomEntryConfig>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let values = Vec::<toml::Value>::deserialize(deserializer)?;
+    let mut entries: Vec<CustomEntryConfig> = Vec::new();
+    for (i, value) in values.into_iter().enumerate() {
+        let entry = match value.try_into::<CustomEntryConfig>() {
+            Ok(entry) => entry,
+            Err(e) => {
+                tracing::warn!("Skipping custom entry #{}: {}", i + 1, e.message());
+                continue;
+            }
+        };
+        if let Err(reason) = entry.validate() {
+            tracing::warn!("Skipping custom entry #{} ({}): {}", i + 1, entry.name, reason);
+            continue;
+        }
+        if entries.iter().any(|e| e.name == entry.name) {
+            tracing::warn!("Skipping duplicate custom entry \"{}\"", entry.name);
+            continue;
+        }
+        entries.push(entry);
+    }
+    Ok(entries)
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -24,12 +209,44 @@ pub struct AppearanceConfig {
     pub prompt: String,
     pub selected_prefix: String,
     pub unselected_prefix: String,
-    /// Number of columns in the grid layout
-    pub columns: u16,
+    /// Number of columns in the grid layout, or "auto" to fit the width
+    /// (default: 2 cards, or a single column of compact lines)
+    pub columns: Option<Columns>,
+    /// Narrowest column with `columns = "auto"`
+    pub min_column_width: u16,
     /// Number of visible rows in the grid layout
     pub visible_rows: u16,
+    /// Fill the grid column by column ("column") or row by row ("row")
+    pub grid_order: GridOrder,
+    /// Entries as cards or single lines
+    pub layout: EntryLayout,
     /// Entry display configuration
     pub entry: EntryDisplayConfigToml,
+    /// Show the title a running command sets (OSC 0/2) in the output header
+    pub show_window_title: bool,
+    /// Show the detail pane for the selected entry next to the grid
+    pub show_preview: bool,
+    /// Lines the boxes are drawn with
+    pub border: BorderStyle,
+    /// Width of the launcher, centered in the terminal (default: all of it)
+    pub width: Option<Extent>,
+    /// Height the launcher is limited to, centered in the terminal
+    pub max_height: Option<Extent>,
+}
+
+/// `appearance.columns`: a fixed count or "auto"
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Columns {
+    Fixed(u16),
+    Auto(AutoColumns),
+}
+
+/// The "auto" keyword of `appearance.columns`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AutoColumns {
+    Auto,
 }
 
 /// TEAM_004: Entry display configuration (TOML-friendly)
@@ -42,6 +259,8 @@ pub struct EntryDisplayConfigToml {
     pub show_comment: bool,
     /// Show Categories line
     pub show_categories: bool,
+    /// Template of the card's lines, instead of the switches above
+    pub format: Option<CardFormat>,
 }
 
 impl Default for EntryDisplayConfigToml {
@@ -50,6 +269,7 @@ impl Default for EntryDisplayConfigToml {
             show_generic: true,
             show_comment: true,
             show_categories: true,
+            format: None,
         }
     }
 }
@@ -60,18 +280,57 @@ impl From<&EntryDisplayConfigToml> for EntryDisplayConfig {
             show_generic: toml.show_generic,
             show_comment: toml.show_comment,
             show_categories: toml.show_categories,
+            format: toml.format.clone(),
+            // appearance.layout, outside the [appearance.entry] table
+            ..Default::default()
         }
     }
 }
 
+impl AppearanceConfig {
+    /// The launcher's part of the terminal `screen`, per `width` and
+    /// `max_height`
+    pub fn area(&self, screen: Rect) -> Rect {
+        layout::launcher_area(screen, self.width, self.max_height)
+    }
+}
+
 /// TEAM_004: Theme configuration
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 #[serde(default)]
 pub struct ThemeConfig {
-    /// Use a preset theme as base (darkwall, catppuccin-mocha, catppuccin-latte, nord, gruvbox)
+    /// Use a preset theme as base (one of `theme::PRESETS`, or the name of
+    /// a file in the themes directory)
     pub preset: Option<String>,
+    /// Preset on a dark terminal background, instead of `preset`
+    pub preset_dark: Option<String>,
+    /// Preset on a light terminal background, instead of `preset`
+    pub preset_light: Option<String>,
     /// Custom color overrides
     pub colors: ThemeColors,
+    /// The theme file the active preset names, read by `Config::load`
+    #[serde(skip)]
+    pub file: Option<Theme>,
+    /// The terminal's background, when it was detected
+    #[serde(skip)]
+    pub background: Option<Background>,
+}
+
+impl ThemeConfig {
+    /// Whether the preset depends on the terminal's background
+    pub fn follows_background(&self) -> bool {
+        self.preset_dark.is_some() || self.preset_light.is_some()
+    }
+
+    /// The preset in use, after `preset_dark`/`preset_light`
+    pub fn active_preset(&self) -> Option<&str> {
+        let by_background = match self.background {
+            Some(Background::Dark) => self.preset_dark.as_deref(),
+            Some(Background::Light) => self.preset_light.as_deref(),
+            None => None,
+        };
+        by_background.or(self.preset.as_deref())
+    }
 }
 
 /// TEAM_004: Custom theme color overrides
@@ -90,15 +349,43 @@ pub struct ThemeColors {
     pub exit_failure: Option<String>,
 }
 
+/// `[compositor]`: which compositor drun talks to and what it asks of it
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(default)]
-pub struct NiriConfig {
+pub struct CompositorConfig {
+    /// "auto", "niri", "sway", "hyprland" or "none"
+    pub backend: Backend,
     pub enabled: bool,
-    pub socket_path: Option<PathBuf>,
     pub float_on_idle: bool,
     pub unfloat_on_execute: bool,
+    /// After launching a GUI app, wait this long for its window before
+    /// exiting (0 exits right away)
+    pub wait_for_window_secs: u64,
+    /// Have the compositor start GUI apps (niri's `Spawn` action, sway's
+    /// and Hyprland's `exec`) rather than drun
+    pub spawn_via_ipc: bool,
+    /// Give up on a compositor request after this many milliseconds
+    pub request_timeout_ms: u64,
+}
+
+/// `[niri]`: settings only niri has
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NiriConfig {
+    pub socket_path: Option<PathBuf>,
 }
 
+/// `[compositor]` keys that used to be in `[niri]`, where they still work
+/// unless `[compositor]` sets them too
+pub const NIRI_ALIASES: &[&str] = &[
+    "enabled",
+    "float_on_idle",
+    "unfloat_on_execute",
+    "wait_for_window_secs",
+    "spawn_via_ipc",
+    "request_timeout_ms",
+];
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(default)]
 pub struct BehaviorConfig {
@@ -110,6 +397,69 @@ pub struct BehaviorConfig {
     pub show_categories: bool,
     /// Show generic name below entry name
     pub show_generic_name: bool,
+    /// Use localized Name/Comment from .desktop files based on LANG
+    /// (set to false to always show the untranslated name)
+    pub localized_names: bool,
+    /// Also list entries with NoDisplay/Hidden or excluded by
+    /// OnlyShowIn/NotShowIn (rendered dimmed)
+    pub show_hidden: bool,
+    /// Hide entries whose TryExec binary is missing
+    /// (when false they are shown greyed out with a "(missing)" suffix)
+    pub hide_missing: bool,
+    /// Also load Flatpak and Snap exported applications when installed
+    pub discover_sandboxed_apps: bool,
+    /// Shell used to run commands, e.g. ["zsh", "-ic"]; the command line is
+    /// appended as the last argument. Defaults to sh -c for entries and
+    /// $SHELL -c for typed commands.
+    pub shell: Option<Vec<String>>,
+    /// External terminal for Terminal=true entries forced to GUI mode,
+    /// e.g. "kitty -e", quoted like an Exec line
+    pub terminal_command: Option<String>,
+    /// Plain Enter runs the filter text as a command when nothing matches
+    /// (Alt+Enter always does)
+    pub enter_runs_typed_command: bool,
+    /// Seconds to wait after Ctrl+C before force-killing a command
+    pub kill_grace_secs: u64,
+    /// What to do with running background jobs on quit
+    pub jobs_on_exit: JobsOnExit,
+    /// Seconds a oneshot command may run before it counts as hung
+    /// (0 disables the timeout)
+    pub oneshot_timeout_secs: u64,
+    /// What to do with a hung oneshot command
+    pub oneshot_timeout_action: TimeoutAction,
+    /// Send a desktop notification when a command that ran at least this
+    /// many seconds finishes (0 disables notifications)
+    pub notify_after_secs: u64,
+    /// Return to the launcher this many seconds after a command exits
+    /// successfully (0 keeps the output until dismissed)
+    pub auto_dismiss_success_secs: u64,
+    /// Prefix for running an entry as root (Ctrl+E), e.g. "pkexec" or "sudo -A"
+    pub elevate_command: String,
+    /// Program that opens links picked from the output (`o`); the URL is
+    /// appended as the last argument. Over SSH links are copied instead.
+    pub open_link_command: String,
+}
+
+/// Running background jobs when quitting (`behavior.jobs_on_exit`)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JobsOnExit {
+    /// Open the jobs view and ask first
+    #[default]
+    Prompt,
+    /// Kill them without asking
+    Kill,
+}
+
+/// A oneshot command that ran too long (`behavior.oneshot_timeout_action`)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TimeoutAction {
+    /// Show a warning and offer a kill key
+    #[default]
+    Warn,
+    /// Stop it
+    Kill,
 }
 
 /// TEAM_001: History/frecency configuration
@@ -145,11 +495,18 @@ pub struct IconsConfig {
     pub enabled: bool,
     /// Icon size in pixels (for graphics protocols)
     pub size: u16,
-    /// Fallback mode: "none" (graphics only)
-    #[allow(dead_code)]
-    pub fallback: String,
+    /// Icon theme to use instead of the one in GTK settings
+    pub theme: Option<String>,
+    /// Icon directories searched before the standard ones
+    pub extra_search_paths: Vec<PathBuf>,
+    /// Text icons when the terminal can't show images
+    pub fallback: IconFallback,
+    /// Fallback glyphs by entry id or category, before the builtin ones
+    pub glyphs: BTreeMap<String, String>,
     /// Force icons over SSH (normally disabled)
     pub force_over_ssh: bool,
+    /// How long to wait for the terminal to answer the graphics query
+    pub detect_timeout_ms: u64,
 }
 
 impl Default for IconsConfig {
@@ -157,27 +514,38 @@ impl Default for IconsConfig {
         Self {
             enabled: true,
             size: 32,
-            fallback: "none".to_string(),
+            theme: None,
+            extra_search_paths: Vec::new(),
+            fallback: IconFallback::None,
+            glyphs: BTreeMap::new(),
             force_over_ssh: false,
+            detect_timeout_ms: 500,
         }
     }
 }
 
 impl Default for Config {
     fn default() -> Self {
-        let home = dirs::home_dir().unwrap_or_else(|| PathBuf::from("."));
         Self {
+            // Expanded by `entry_dirs`; written like this by --init-config
             desktop_entry_dirs: vec![
-                home.join(".local/share/applications"),
+                PathBuf::from("~/.local/share/applications"),
                 PathBuf::from("/run/current-system/sw/share/applications"),
                 PathBuf::from("/usr/share/applications"),
             ],
             appearance: AppearanceConfig::default(),
             theme: ThemeConfig::default(),
+            compositor: CompositorConfig::default(),
             niri: NiriConfig::default(),
             behavior: BehaviorConfig::default(),
             history: HistoryConfig::default(),
             icons: IconsConfig::default(),
+            env: BTreeMap::new(),
+            custom_entries: Vec::new(),
+            entries: BTreeMap::new(),
+            terminal_mode: TerminalModeConfig::default(),
+            output: OutputConfig::default(),
+            hooks: HooksConfig::default(),
         }
     }
 }
@@ -188,20 +556,31 @@ impl Default for AppearanceConfig {
             prompt: "❯ ".to_string(),
             selected_prefix: "● ".to_string(),
             unselected_prefix: "  ".to_string(),
-            columns: 2,
+            columns: None,
+            min_column_width: 30,
             visible_rows: 5,
+            grid_order: GridOrder::Column,
+            layout: EntryLayout::Cards,
             entry: EntryDisplayConfigToml::default(),
+            show_window_title: true,
+            show_preview: false,
+            border: BorderStyle::Plain,
+            width: None,
+            max_height: None,
         }
     }
 }
 
-impl Default for NiriConfig {
+impl Default for CompositorConfig {
     fn default() -> Self {
         Self {
+            backend: Backend::default(),
             enabled: true,
-            socket_path: None,
             float_on_idle: true,
             unfloat_on_execute: true,
+            wait_for_window_secs: 5,
+            spawn_via_ipc: true,
+            request_timeout_ms: 500,
         }
     }
 }
@@ -213,6 +592,21 @@ impl Default for BehaviorConfig {
             preserve_output_lines: 10,
             show_categories: true,
             show_generic_name: true,
+            localized_names: true,
+            show_hidden: false,
+            hide_missing: true,
+            discover_sandboxed_apps: true,
+            shell: None,
+            terminal_command: None,
+            enter_runs_typed_command: false,
+            kill_grace_secs: 3,
+            jobs_on_exit: JobsOnExit::Prompt,
+            oneshot_timeout_secs: 0,
+            oneshot_timeout_action: TimeoutAction::Warn,
+            notify_after_secs: 0,
+            auto_dismiss_success_secs: 0,
+            elevate_command: "pkexec".to_string(),
+            open_link_command: "xdg-open".to_string(),
         }
     }
 }
@@ -225,22 +619,198 @@ impl Config {
         if path.exists() {
             let content = std::fs::read_to_string(path)
                 .with_context(|| format!("Failed to read config from {}", path.display()))?;
-            toml::from_str(&content)
-                .with_context(|| format!("Failed to parse config from {}", path.display()))
+            let mut config = Self::parse(&content)
+                .with_context(|| format!("Failed to parse config from {}", path.display()))?;
+            for key in config_check::unknown_keys(&content) {
+                tracing::warn!("{} in {} (see drun --check-config)", key, path.display());
+            }
+            config
+                .validate()
+                .with_context(|| format!("Invalid config in {}", path.display()))?;
+            config.theme.file = config.load_theme_file()?;
+            Ok(config)
         } else {
             tracing::info!("Config file not found, using defaults");
             Ok(Self::default())
         }
     }
 
+    /// Parse the text of a config file
+    ///
+    /// `[compositor]` settings still in `[niri]`, their old place, apply
+    /// unless `[compositor]` sets them too.
+    pub fn parse(text: &str) -> Result<Self, toml::de::Error> {
+        let mut config: Self = toml::from_str(text)?;
+        let table: toml::Table = toml::from_str(text)?;
+        let Some(niri) = table.get("niri").and_then(toml::Value::as_table) else {
+            return Ok(config);
+        };
+        let mut compositor = table
+            .get("compositor")
+            .and_then(toml::Value::as_table)
+            .cloned()
+            .unwrap_or_default();
+        let mut moved = false;
+        for &key in NIRI_ALIASES {
+            if let Some(value) = niri.get(key).filter(|_| !compositor.contains_key(key)) {
+                tracing::warn!("niri.{} is deprecated, use compositor.{}", key, key);
+                compositor.insert(key.to_string(), value.clone());
+                moved = true;
+            }
+        }
+        if moved {
+            config.compositor = compositor.try_into()?;
+        }
+        Ok(config)
+    }
+
+    /// Check constraints that serde can't express
+    pub fn validate(&self) -> Result<()> {
+        if let Some(ref shell) = self.behavior.shell {
+            if shell.first().is_none_or(|program| program.trim().is_empty()) {
+                anyhow::bail!("behavior.shell must name a program, e.g. [\"sh\", \"-c\"]");
+            }
+        }
+        if let Some(ref terminal) = self.behavior.terminal_command {
+            if desktop_entry::split_exec(terminal).is_none() {
+                anyhow::bail!("behavior.terminal_command has an unterminated quote: {}", terminal);
+            }
+        }
+        if self.behavior.elevate_command.trim().is_empty() {
+            anyhow::bail!("behavior.elevate_command must name a program, e.g. \"pkexec\"");
+        }
+        if self.behavior.open_link_command.trim().is_empty() {
+            anyhow::bail!("behavior.open_link_command must name a program, e.g. \"xdg-open\"");
+        }
+        for rule in &self.terminal_mode.rules {
+            Regex::new(&rule.pattern).with_context(|| {
+                format!("Invalid terminal_mode rule pattern \"{}\"", rule.pattern)
+            })?;
+            if rule.mode.parse::<TerminalMode>().is_err() {
+                anyhow::bail!(
+                    "Unknown mode \"{}\" in terminal_mode rule \"{}\"",
+                    rule.mode,
+                    rule.pattern
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Compiled `[terminal_mode]` rules for `TerminalMode::detect`
+    ///
+    /// Rules that fail to compile are skipped (`load` rejects them already).
+    pub fn mode_rules(&self) -> ModeRules {
+        let config = &self.terminal_mode;
+        ModeRules {
+            patterns: config
+                .rules
+                .iter()
+                .filter_map(|rule| Some((Regex::new(&rule.pattern).ok()?, rule.mode.parse().ok()?)))
+                .collect(),
+            tui: config.tui.clone(),
+            interactive: config.interactive.clone(),
+            long_running_patterns: config.long_running_patterns.clone(),
+        }
+    }
+
+    /// Directory for saved command output (tilde-expanded)
+    pub fn output_dir(&self) -> Option<PathBuf> {
+        match self.output.dir {
+            Some(ref dir) => Some(PathBuf::from(shellexpand::tilde(dir).as_ref())),
+            None => output_file::default_dir(),
+        }
+    }
+
+    /// `niri.socket_path` with `~` expanded
+    pub fn niri_socket_path(&self) -> Option<PathBuf> {
+        self.niri
+            .socket_path
+            .as_ref()
+            .map(|p| PathBuf::from(shellexpand::tilde(&p.to_string_lossy()).as_ref()))
+    }
+
+    /// How long a oneshot command may run, None if unlimited
+    pub fn oneshot_timeout(&self) -> Option<Duration> {
+        match self.behavior.oneshot_timeout_secs {
+            0 => None,
+            secs => Some(Duration::from_secs(secs)),
+        }
+    }
+
+    /// Shell command used to run command lines
+    ///
+    /// `behavior.shell` if set. Otherwise command lines the user typed run
+    /// in `$SHELL -c` (else `sh -c`), as at their prompt, and the Exec
+    /// lines of entries in `sh -c`: they are written for a POSIX shell,
+    /// not fish or nu.
+    pub fn shell(&self, typed: bool) -> Vec<String> {
+        if let Some(ref shell) = self.behavior.shell {
+            return shell.clone();
+        }
+        let program = std::env::var("SHELL")
+            .ok()
+            .filter(|s| typed && !s.is_empty())
+            .unwrap_or_else(|| "sh".to_string());
+        vec![program, "-c".to_string()]
+    }
+
+    /// External terminal command split into arguments like an Exec line
+    /// (empty if unset)
+    pub fn terminal_command(&self) -> Vec<String> {
+        self.behavior
+            .terminal_command
+            .as_deref()
+            .and_then(desktop_entry::split_exec)
+            .unwrap_or_default()
+    }
+
+    /// Shell, working directory and environment for launching `entry`
+    /// (or a typed command when None)
+    pub fn launch_options(&self, entry: Option<&Entry>) -> LaunchOptions {
+        LaunchOptions {
+            shell: self.shell(entry.is_none_or(Entry::is_typed_command)),
+            working_dir: executor::resolve_working_dir(entry.and_then(|e| e.working_dir.as_deref())),
+            env: executor::launch_env(&self.env, entry),
+        }
+    }
+
+    /// The user theme the active preset names, when it isn't built in
+    ///
+    /// An unknown name only logs a warning, as it always has; a theme file
+    /// with mistakes is an error.
+    pub fn load_theme_file(&self) -> Result<Option<Theme>> {
+        let Some(name) = self.theme.active_preset().filter(|name| Theme::from_preset(name).is_none()) else {
+            return Ok(None);
+        };
+        match theme::theme_file(name).filter(|path| path.exists()) {
+            Some(path) => Theme::from_file(&path).map(Some),
+            None => {
+                tracing::warn!("Unknown theme \"{}\": not a preset or a file in the themes directory", name);
+                Ok(None)
+            }
+        }
+    }
+
+    /// Use `theme.preset_dark` or `preset_light` for `background`
+    ///
+    /// A broken theme file for it is logged and the built-in default used.
+    pub fn set_background(&mut self, background: Background) {
+        self.theme.background = Some(background);
+        self.theme.file = self.load_theme_file().unwrap_or_else(|e| {
+            tracing::warn!("{:#}", e);
+            None
+        });
+    }
+
     /// TEAM_004: Resolve theme from preset + color overrides
     pub fn resolve_theme(&self) -> Theme {
         use ratatui::style::Color;
 
         // Start with preset or default
-        let mut theme = self.theme.preset
-            .as_ref()
-            .and_then(|name| Theme::from_preset(name))
+        let mut theme = self.theme.active_preset()
+            .and_then(Theme::from_preset)
+            .or_else(|| self.theme.file.clone())
             .unwrap_or_default();
 
         // Helper to parse and apply a color override
@@ -269,12 +839,480 @@ impl Config {
     }
 
     /// TEAM_004: Get grid layout from config
-    pub fn grid_layout(&self) -> GridLayout {
-        GridLayout::new(self.appearance.columns, self.appearance.visible_rows)
+    ///
+    /// `list_width` is the width inside the entry list's border, used to
+    /// pick the column count with `columns = "auto"`.
+    pub fn grid_layout(&self, list_width: u16) -> GridLayout {
+        let columns = match self.appearance.columns {
+            Some(Columns::Fixed(columns)) => columns,
+            Some(Columns::Auto(_)) => layout::auto_columns(list_width, self.appearance.min_column_width),
+            None if self.appearance.layout == EntryLayout::Compact => 1,
+            None => 2,
+        };
+        GridLayout::new(columns, self.appearance.visible_rows).with_order(self.appearance.grid_order)
+    }
+
+    /// Directories to load desktop entries from, in precedence order
+    ///
+    /// The configured `desktop_entry_dirs` (with `~` expanded) come first,
+    /// followed by Flatpak/Snap exports, so a native install with the same
+    /// desktop file id wins.
+    pub fn entry_dirs(&self) -> Vec<PathBuf> {
+        let mut dirs: Vec<PathBuf> = self
+            .desktop_entry_dirs
+            .iter()
+            .map(|d| PathBuf::from(shellexpand::tilde(&d.to_string_lossy()).as_ref()))
+            .collect();
+
+        if self.behavior.discover_sandboxed_apps {
+            for data_dir in desktop_entry::sandboxed_data_dirs() {
+                let apps = data_dir.join("applications");
+                if apps.is_dir() && !dirs.contains(&apps) {
+                    tracing::debug!("Discovered {}", apps.display());
+                    dirs.push(apps);
+                }
+            }
+        }
+
+        dirs
+    }
+
+    /// Entries from `[[custom_entries]]`
+    pub fn custom_entries(&self) -> Vec<Entry> {
+        self.custom_entries.iter().map(Entry::from).collect()
+    }
+
+    /// Apply `[entries]` overrides: drop hidden entries, rename and re-icon others
+    ///
+    /// Keys may be given with or without the `.desktop` suffix.
+    pub fn apply_overrides(&self, entries: Vec<Entry>) -> Vec<Entry> {
+        if self.entries.is_empty() {
+            return entries;
+        }
+
+        let overrides: BTreeMap<&str, &EntryOverride> = self
+            .entries
+            .iter()
+            .map(|(id, o)| (id.strip_suffix(".desktop").unwrap_or(id), o))
+            .collect();
+
+        entries
+            .into_iter()
+            .filter_map(|mut entry| {
+                let Some(o) = overrides.get(entry.id.as_str()) else {
+                    return Some(entry);
+                };
+                if o.hidden {
+                    return None;
+                }
+                if let Some(ref name) = o.name {
+                    entry.name = name.clone();
+                }
+                if let Some(ref icon) = o.icon {
+                    entry.icon = Some(icon.clone());
+                }
+                if let Some(ref mode) = o.terminal_mode {
+                    if mode.parse::<TerminalMode>().is_ok() {
+                        entry.custom_fields.insert("TerminalMode".to_string(), mode.clone());
+                    } else {
+                        tracing::warn!("Ignoring unknown terminal_mode \"{}\" for {}", mode, entry.id);
+                    }
+                }
+                Some(entry)
+            })
+            .collect()
+    }
+
+    /// Keep the settings of `running` that only take effect at startup,
+    /// for a config reloaded while drun runs
+    ///
+    /// Returns the names of those that differ, so the user can be told to
+    /// restart.
+    pub fn keep_startup_settings(&mut self, running: &Config) -> Vec<&'static str> {
+        fn differ<T: Serialize>(a: &T, b: &T) -> bool {
+            serde_json::to_value(a).ok() != serde_json::to_value(b).ok()
+        }
+        let mut changed = Vec::new();
+        if self.desktop_entry_dirs != running.desktop_entry_dirs {
+            changed.push("desktop_entry_dirs");
+            self.desktop_entry_dirs = running.desktop_entry_dirs.clone();
+        }
+        if differ(&self.compositor, &running.compositor) || differ(&self.niri, &running.niri) {
+            changed.push("[compositor] and [niri]");
+            self.compositor = running.compositor.clone();
+            self.niri = running.niri.clone();
+        }
+        // The frecency weight only ranks, so it applies right away
+        let frecency_weight = self.history.frecency_weight;
+        self.history.frecency_weight = running.history.frecency_weight;
+        if differ(&self.history, &running.history) {
+            changed.push("[history]");
+            self.history = running.history.clone();
+        }
+        self.history.frecency_weight = frecency_weight;
+        if differ(&self.icons, &running.icons) {
+            changed.push("[icons]");
+            self.icons = running.icons.clone();
+        }
+        changed
+    }
+
+    /// Get desktop entry loading options
+    pub fn load_options(&self) -> LoadOptions {
+        let locales = if self.behavior.localized_names {
+            desktop_entry::user_locales()
+        } else {
+            Vec::new()
+        };
+        LoadOptions {
+            locales,
+            desktops: desktop_entry::current_desktops(),
+            show_hidden: self.behavior.show_hidden,
+            hide_missing: self.behavior.hide_missing,
+        }
+    }
+
+    /// Where to look for icons
+    pub fn icon_options(&self) -> IconOptions {
+        IconOptions {
+            size: self.icons.size,
+            theme: self.icons.theme.clone().filter(|theme| !theme.is_empty()),
+            extra_search_paths: self
+                .icons
+                .extra_search_paths
+                .iter()
+                .map(|d| PathBuf::from(shellexpand::tilde(&d.to_string_lossy()).as_ref()))
+                .collect(),
+            symbolic_colors: SymbolicColors::from_theme(&self.resolve_theme()),
+        }
+    }
+
+    /// Text icons for terminals without graphics, None if disabled
+    pub fn glyph_icons(&self) -> Option<GlyphIcons> {
+        if !self.icons.enabled {
+            return None;
+        }
+        let style = self.icons.fallback.style()?;
+        Some(GlyphIcons::new(style, self.icons.glyphs.clone()))
     }
 
     /// TEAM_004: Get entry display config
     pub fn entry_display_config(&self) -> EntryDisplayConfig {
-        EntryDisplayConfig::from(&self.appearance.entry)
+        EntryDisplayConfig {
+            layout: self.appearance.layout,
+            ..EntryDisplayConfig::from(&self.appearance.entry)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_custom_entries() {
+        let config: Config = toml::from_str(
+            r#"
+            [[custom_entries]]
+            name = "SSH to build box"
+            exec = "ssh build"
+            terminal_mode = "interactive"
+
+            [[custom_entries]]
+            name = "Notes"
+            exec = "nvim ~/notes.md"
+            icon = "accessories-text-editor"
+            categories = ["Utility"]
+            "#,
+        )
+        .unwrap();
+
+        let entries = config.custom_entries();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].id, "custom:SSH to build box");
+        assert_eq!(entries[0].get_darkwall_field("TerminalMode"), Some("interactive"));
+        // Custom exec is a shell command line, not re-quoted
+        assert_eq!(entries[1].command().as_deref(), Some("nvim ~/notes.md"));
+        assert_eq!(entries[1].categories, vec!["Utility"]);
+    }
+
+    #[test]
+    fn test_entry_dirs_expand_tilde_and_keep_order() {
+        let mut config = Config {
+            desktop_entry_dirs: vec![
+                PathBuf::from("~/.local/share/applications"),
+                PathBuf::from("/usr/share/applications"),
+            ],
+            ..Default::default()
+        };
+        config.behavior.discover_sandboxed_apps = false;
+
+        let home = dirs::home_dir().unwrap();
+        assert_eq!(
+            config.entry_dirs(),
+            vec![
+                home.join(".local/share/applications"),
+                PathBuf::from("/usr/share/applications"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_entry_overrides() {
+        let config: Config = toml::from_str(
+            r#"
+            [entries."avahi-discover.desktop"]
+            hidden = true
+
+            [entries.firefox]
+            name = "Web"
+            icon = "web-browser"
+            terminal_mode = "tui"
+            "#,
+        )
+        .unwrap();
+
+        let entry = |id: &str| Entry {
+            id: id.to_string(),
+            name: id.to_string(),
+            ..Default::default()
+        };
+        let entries = config.apply_overrides(vec![entry("avahi-discover"), entry("firefox"), entry("gimp")]);
+
+        let ids: Vec<_> = entries.iter().map(|e| e.id.as_str()).collect();
+        assert_eq!(ids, vec!["firefox", "gimp"]);
+        assert_eq!(entries[0].name, "Web");
+        assert_eq!(entries[0].icon.as_deref(), Some("web-browser"));
+        assert_eq!(entries[0].get_darkwall_field("TerminalMode"), Some("tui"));
+        assert_eq!(entries[1].name, "gimp");
+    }
+
+    #[test]
+    fn test_shell_validation() {
+        let config: Config = toml::from_str("[behavior]\nshell = [\"zsh\", \"-ic\"]").unwrap();
+        assert!(config.validate().is_ok());
+        assert_eq!(config.shell(false), vec!["zsh", "-ic"]);
+        assert_eq!(config.shell(true), vec!["zsh", "-ic"]);
+        // Exec lines are POSIX shell whatever the login shell is
+        assert_eq!(Config::default().shell(false), vec!["sh", "-c"]);
+
+        let config: Config = toml::from_str("[behavior]\nshell = []").unwrap();
+        assert!(config.validate().is_err());
+        let config: Config = toml::from_str("[behavior]\nshell = [\"\"]").unwrap();
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_terminal_command_quoting() {
+        let config: Config =
+            toml::from_str(r#"behavior.terminal_command = '"/opt/My Term/term" --title "drun app" -e'"#).unwrap();
+        assert!(config.validate().is_ok());
+        assert_eq!(config.terminal_command(), vec!["/opt/My Term/term", "--title", "drun app", "-e"]);
+
+        let config: Config = toml::from_str(r#"behavior.terminal_command = '"kitty -e'"#).unwrap();
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_terminal_mode_rules() {
+        let config: Config = toml::from_str(
+            r#"
+            [terminal_mode]
+            tui = ["zellij", "yazi"]
+            long_running_patterns = ["npm run dev"]
+
+            [[terminal_mode.rules]]
+            pattern = "^ssh "
+            mode = "interactive"
+            "#,
+        )
+        .unwrap();
+        assert!(config.validate().is_ok());
+        let rules = config.mode_rules();
+        assert_eq!(rules.tui, vec!["zellij", "yazi"]);
+        assert_eq!(rules.patterns.len(), 1);
+        assert_eq!(rules.patterns[0].1, TerminalMode::Interactive);
+
+        let config: Config =
+            toml::from_str("[[terminal_mode.rules]]\npattern = \"(\"\nmode = \"tui\"").unwrap();
+        assert!(config.validate().is_err());
+        let config: Config =
+            toml::from_str("[[terminal_mode.rules]]\npattern = \"x\"\nmode = \"fullscreen\"").unwrap();
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_columns() {
+        let config: Config = toml::from_str("[appearance]\ncolumns = 3").unwrap();
+        assert_eq!(config.appearance.columns, Some(Columns::Fixed(3)));
+        assert_eq!(config.grid_layout(200).columns, 3);
+
+        let config: Config =
+            toml::from_str("[appearance]\ncolumns = \"auto\"\nmin_column_width = 40").unwrap();
+        assert_eq!(config.appearance.columns, Some(Columns::Auto(AutoColumns::Auto)));
+        assert_eq!(config.grid_layout(200).columns, 4);
+        assert_eq!(config.grid_layout(60).columns, 1);
+
+        assert!(toml::from_str::<Config>("[appearance]\ncolumns = \"many\"").is_err());
+
+        // Two columns of cards, or one of compact lines, unless set
+        assert_eq!(Config::default().grid_layout(200).columns, 2);
+        let config: Config = toml::from_str("[appearance]\nlayout = \"compact\"").unwrap();
+        assert_eq!(config.grid_layout(200).columns, 1);
+        assert_eq!(config.entry_display_config().card_height(), 1);
+        let config: Config = toml::from_str("[appearance]\nlayout = \"compact\"\ncolumns = 2").unwrap();
+        assert_eq!(config.grid_layout(200).columns, 2);
+    }
+
+    #[test]
+    fn test_grid_order() {
+        assert_eq!(Config::default().grid_layout(80).order, GridOrder::Column);
+        let config: Config = toml::from_str("[appearance]\ngrid_order = \"row\"").unwrap();
+        assert_eq!(config.grid_layout(80).order, GridOrder::Row);
+        assert!(toml::from_str::<Config>("[appearance]\ngrid_order = \"diagonal\"").is_err());
+    }
+
+    #[test]
+    fn test_jobs_on_exit() {
+        assert_eq!(Config::default().behavior.jobs_on_exit, JobsOnExit::Prompt);
+        let config: Config = toml::from_str("[behavior]\njobs_on_exit = \"kill\"").unwrap();
+        assert_eq!(config.behavior.jobs_on_exit, JobsOnExit::Kill);
+        assert!(toml::from_str::<Config>("[behavior]\njobs_on_exit = \"detach\"").is_err());
+        assert!(toml::from_str::<Config>("[behavior]\noneshot_timeout_action = \"ignore\"").is_err());
+    }
+
+    #[test]
+    fn test_icon_fallback() {
+        assert!(Config::default().glyph_icons().is_none());
+        let config: Config = toml::from_str("[icons]\nfallback = \"nerdfont\"").unwrap();
+        assert_eq!(config.icons.fallback, IconFallback::NerdFont);
+        assert!(config.glyph_icons().is_some());
+        // Icons turned off turn off the fallback too
+        let config: Config =
+            toml::from_str("[icons]\nenabled = false\nfallback = \"unicode\"").unwrap();
+        assert!(config.glyph_icons().is_none());
+        assert!(toml::from_str::<Config>("[icons]\nfallback = \"emoji\"").is_err());
+    }
+
+    #[test]
+    fn test_icon_options() {
+        let options = Config::default().icon_options();
+        assert_eq!(options.theme, None);
+        assert!(options.extra_search_paths.is_empty());
+
+        let config: Config = toml::from_str(
+            "[icons]\ntheme = \"Papirus-Dark\"\nextra_search_paths = [\"~/icons\", \"/opt/icons\"]",
+        )
+        .unwrap();
+        let options = config.icon_options();
+        assert_eq!(options.theme.as_deref(), Some("Papirus-Dark"));
+        assert_eq!(options.extra_search_paths[0], dirs::home_dir().unwrap().join("icons"));
+        assert_eq!(options.extra_search_paths[1], PathBuf::from("/opt/icons"));
+
+        // An empty theme means the GTK one
+        let config: Config = toml::from_str("[icons]\ntheme = \"\"").unwrap();
+        assert_eq!(config.icon_options().theme, None);
+    }
+
+    #[test]
+    fn test_malformed_custom_entries_are_skipped() {
+        let config: Config = toml::from_str(
+            r#"
+            [[custom_entries]]
+            name = "No exec"
+
+            [[custom_entries]]
+            name = "Bad mode"
+            exec = "true"
+            terminal_mode = "fullscreen"
+
+            [[custom_entries]]
+            name = ""
+            exec = "true"
+
+            [[custom_entries]]
+            name = "Typo"
+            exec = "true"
+            termnal_mode = "tui"
+
+            [[custom_entries]]
+            name = "Good"
+            exec = "true"
+
+            [[custom_entries]]
+            name = "Good"
+            exec = "false"
+            "#,
+        )
+        .unwrap();
+
+        let names: Vec<_> = config.custom_entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, vec!["Good"]);
+        assert_eq!(config.custom_entries[0].exec, "true");
+    }
+
+    #[test]
+    fn test_keep_startup_settings() {
+        let running = Config::default();
+        let mut reloaded: Config = toml::from_str(
+            r#"
+            desktop_entry_dirs = ["/opt/apps"]
+
+            [appearance]
+            prompt = "> "
+
+            [history]
+            max_entries = 5
+            frecency_weight = 0.5
+            "#,
+        )
+        .unwrap();
+        let changed = reloaded.keep_startup_settings(&running);
+        assert_eq!(changed, ["desktop_entry_dirs", "[history]"]);
+        assert_eq!(reloaded.desktop_entry_dirs, running.desktop_entry_dirs);
+        assert_eq!(reloaded.history.max_entries, running.history.max_entries);
+        // Everything else is taken as reloaded
+        assert_eq!(reloaded.appearance.prompt, "> ");
+        assert_eq!(reloaded.history.frecency_weight, 0.5);
+        assert!(Config::default().keep_startup_settings(&running).is_empty());
+    }
+
+    #[test]
+    fn test_niri_aliases() {
+        let config = Config::parse("[niri]\nenabled = false\nrequest_timeout_ms = 50\n").unwrap();
+        assert!(!config.compositor.enabled);
+        assert_eq!(config.compositor.request_timeout_ms, 50);
+        // Other settings keep their defaults
+        assert!(config.compositor.float_on_idle);
+
+        // [compositor] wins over the old place
+        let config = Config::parse(
+            "[compositor]\nenabled = true\nbackend = \"sway\"\n[niri]\nenabled = false\nspawn_via_ipc = false\n",
+        )
+        .unwrap();
+        assert!(config.compositor.enabled);
+        assert!(!config.compositor.spawn_via_ipc);
+        assert_eq!(config.compositor.backend, crate::compositor::Backend::Sway);
+    }
+
+    #[test]
+    fn test_preset_follows_background() {
+        let mut config: Config = toml::from_str(
+            r#"
+            [theme]
+            preset = "nord"
+            preset_light = "solarized-light"
+            "#,
+        )
+        .unwrap();
+        assert!(config.theme.follows_background());
+        // Undetected, or no preset for the background: `preset`
+        assert_eq!(config.theme.active_preset(), Some("nord"));
+        config.set_background(Background::Dark);
+        assert_eq!(config.resolve_theme(), Theme::nord());
+        config.set_background(Background::Light);
+        assert_eq!(config.resolve_theme(), Theme::solarized_light());
+
+        assert!(!Config::default().theme.follows_background());
     }
 }
@@ -0,0 +1,374 @@
+//! `--check-config`: everything wrong with a config file, for dotfile CI
+//!
+//! Loading is lenient: unknown keys are ignored, a bad color falls back to
+//! the theme's and a missing directory is skipped, so a typo goes unnoticed.
+//! The check reports all of these at once, with line numbers from the
+//! file's spans, and fails when the config would be rejected or a setting
+//! ignored.
+//!
+//! Unknown keys are found by walking the file alongside `Config::default()`
+//! serialized to JSON, whose objects list every known key.
+
+use std::fmt;
+use std::ops::Range;
+use std::path::Path;
+
+use serde_json::{json, Value as Schema};
+use toml_edit::{ImDocument, TableLike};
+
+use crate::config::{Config, CustomEntryConfig, EntryOverride, ModeRuleConfig, NIRI_ALIASES};
+use crate::ui::theme::{self, parse_hex_color, Theme};
+
+/// Schema key standing for any key, in tables keyed by the user
+const ANY_KEY: &str = "*";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// The config is rejected or a setting is ignored
+    Error,
+    /// Worth a look, but harmless (e.g. a directory that isn't there yet)
+    Warning,
+}
+
+/// One finding of the check
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// 1-based line in the file, when known
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl Diagnostic {
+    fn error(line: Option<usize>, message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Error,
+            line,
+            message: message.into(),
+        }
+    }
+
+    fn warning(line: Option<usize>, message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Warning,
+            line,
+            message: message.into(),
+        }
+    }
+
+    /// `path:line: error: message`, like a compiler
+    pub fn format(&self, path: &Path) -> String {
+        let location = match self.line {
+            Some(line) => format!("{}:{}", path.display(), line),
+            None => path.display().to_string(),
+        };
+        format!("{}: {}: {}", location, self.severity, self.message)
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Error => "error",
+            Self::Warning => "warning",
+        })
+    }
+}
+
+/// Check the config file at `path` (`~` is expanded)
+pub fn check_file(path: &str) -> Vec<Diagnostic> {
+    let path = shellexpand::tilde(path);
+    match std::fs::read_to_string(path.as_ref()) {
+        Ok(source) => check(&source),
+        Err(e) => vec![Diagnostic::error(None, format!("cannot read config: {}", e))],
+    }
+}
+
+/// Check the config text `source`
+pub fn check(source: &str) -> Vec<Diagnostic> {
+    let doc = match ImDocument::parse(source) {
+        Ok(doc) => doc,
+        Err(e) => {
+            let line = e.span().map(|span| line_of(source, span.start));
+            return vec![Diagnostic::error(line, e.message().trim())];
+        }
+    };
+
+    let mut found = Vec::new();
+    walk(&schema(), doc.as_table(), "", source, &mut found);
+
+    let config = match Config::parse(source) {
+        Ok(config) => config,
+        Err(e) => {
+            let line = e.span().map(|span| line_of(source, span.start));
+            found.push(Diagnostic::error(line, e.message().trim()));
+            return found;
+        }
+    };
+    if let Err(e) = config.validate() {
+        found.push(Diagnostic::error(None, format!("{:#}", e)));
+    }
+
+    let line = |keys: &[&str]| key_span(&doc, keys).map(|span| line_of(source, span.start));
+    for key in NIRI_ALIASES {
+        if let Some(at) = line(&["niri", key]) {
+            found.push(Diagnostic::warning(
+                Some(at),
+                format!("niri.{} is deprecated, use compositor.{}", key, key),
+            ));
+        }
+    }
+    let presets = [
+        ("preset", &config.theme.preset),
+        ("preset_dark", &config.theme.preset_dark),
+        ("preset_light", &config.theme.preset_light),
+    ];
+    for (key, preset) in presets {
+        let Some(preset) = preset.as_deref().filter(|name| Theme::from_preset(name).is_none()) else {
+            continue;
+        };
+        let at = line(&["theme", key]);
+        match theme::theme_file(preset).filter(|path| path.exists()) {
+            Some(path) => {
+                if let Err(e) = Theme::from_file(&path) {
+                    found.push(Diagnostic::error(at, format!("{:#}", e)));
+                }
+            }
+            None => found.push(Diagnostic::error(
+                at,
+                format!(
+                    "unknown theme.{} \"{}\" (not in the themes directory or one of {})",
+                    key,
+                    preset,
+                    theme::PRESETS.join(", ")
+                ),
+            )),
+        }
+    }
+    if let Ok(Schema::Object(colors)) = serde_json::to_value(&config.theme.colors) {
+        for (name, hex) in colors.iter().filter_map(|(name, value)| Some((name, value.as_str()?))) {
+            if let Err(e) = parse_hex_color(hex) {
+                found.push(Diagnostic::error(
+                    line(&["theme", "colors", name]),
+                    format!("theme.colors.{} = \"{}\": {}", name, hex, e),
+                ));
+            }
+        }
+    }
+    if let Some(ref format) = config.appearance.entry.format {
+        for placeholder in format.unknown() {
+            found.push(Diagnostic::warning(
+                line(&["appearance", "entry", "format"]),
+                format!("appearance.entry.format: unknown placeholder {} is shown as written", placeholder),
+            ));
+        }
+    }
+    // Only directories the file names: the defaults cover every distro
+    let dir_settings = [
+        (&["desktop_entry_dirs"][..], &config.desktop_entry_dirs),
+        (&["icons", "extra_search_paths"][..], &config.icons.extra_search_paths),
+    ];
+    for (keys, dirs) in dir_settings {
+        let Some(at) = line(keys) else {
+            continue;
+        };
+        for dir in dirs {
+            let expanded = shellexpand::tilde(&dir.to_string_lossy()).into_owned();
+            if !Path::new(&expanded).is_dir() {
+                found.push(Diagnostic::warning(
+                    Some(at),
+                    format!("{}: {} is not a directory", keys.join("."), dir.display()),
+                ));
+            }
+        }
+    }
+
+    found.sort_by_key(|d| d.line.unwrap_or(usize::MAX));
+    found
+}
+
+/// Keys of `source` that the config doesn't know, as `section.key` paths
+///
+/// Used when loading, so typos at least show up in the log. A file that
+/// doesn't parse has none.
+pub fn unknown_keys(source: &str) -> Vec<String> {
+    let Ok(doc) = ImDocument::parse(source) else {
+        return Vec::new();
+    };
+    let mut found = Vec::new();
+    walk(&schema(), doc.as_table(), "", source, &mut found);
+    found.into_iter().map(|d| d.message).collect()
+}
+
+/// Every known key, with the user-keyed tables marked by `ANY_KEY`
+fn schema() -> Schema {
+    let mut schema = serde_json::to_value(Config::default()).unwrap_or_default();
+    schema["env"] = json!({ ANY_KEY: "" });
+    for key in NIRI_ALIASES {
+        schema["niri"][key] = schema["compositor"][key].clone();
+    }
+    schema["icons"]["glyphs"] = json!({ ANY_KEY: "" });
+    schema["entries"] = json!({ ANY_KEY: serde_json::to_value(EntryOverride::default()).unwrap_or_default() });
+    // Arrays of tables hold the schema of their tables
+    let custom_entry = CustomEntryConfig {
+        name: String::new(),
+        exec: String::new(),
+        icon: None,
+        terminal_mode: None,
+        categories: Vec::new(),
+    };
+    let mode_rule = ModeRuleConfig {
+        pattern: String::new(),
+        mode: String::new(),
+    };
+    schema["custom_entries"] = json!([custom_entry]);
+    schema["terminal_mode"]["rules"] = json!([mode_rule]);
+    schema
+}
+
+/// Report the keys of `table` (at `path`) missing from `schema`
+///
+/// The tables of an array (`[[custom_entries]]`, mode rules) are checked
+/// against the schema's only element: malformed custom entries are skipped
+/// when loading rather than failing the config, so a typo there would go
+/// unnoticed otherwise.
+fn walk(schema: &Schema, table: &dyn TableLike, path: &str, source: &str, found: &mut Vec<Diagnostic>) {
+    let Some(known) = schema.as_object() else {
+        return;
+    };
+    for (key, item) in table.iter() {
+        let name = if path.is_empty() {
+            key.to_string()
+        } else {
+            format!("{}.{}", path, key)
+        };
+        match known.get(ANY_KEY).or_else(|| known.get(key)) {
+            Some(schema) => {
+                if let Some(nested) = item.as_table_like() {
+                    walk(schema, nested, &name, source, found);
+                } else if let Some(element) = schema.get(0) {
+                    for nested in array_tables(item) {
+                        walk(element, nested, &name, source, found);
+                    }
+                }
+            }
+            None => {
+                let line = table.key(key).and_then(|k| k.span()).map(|span| line_of(source, span.start));
+                found.push(Diagnostic::error(line, format!("unknown key {}", name)));
+            }
+        }
+    }
+}
+
+/// The tables of `item`, as `[[section]]`s or an array of inline tables
+fn array_tables(item: &toml_edit::Item) -> Vec<&dyn TableLike> {
+    match item.as_array_of_tables() {
+        Some(tables) => tables.iter().map(|table| table as &dyn TableLike).collect(),
+        None => item
+            .as_array()
+            .into_iter()
+            .flat_map(|array| array.iter())
+            .filter_map(|value| value.as_inline_table())
+            .map(|table| table as &dyn TableLike)
+            .collect(),
+    }
+}
+
+/// Span of the last key of the path `keys`
+fn key_span<S>(doc: &ImDocument<S>, keys: &[&str]) -> Option<Range<usize>> {
+    let (last, parents) = keys.split_last()?;
+    let mut table: &dyn TableLike = doc.as_table();
+    for key in parents {
+        table = table.get(key)?.as_table_like()?;
+    }
+    table.key(last)?.span()
+}
+
+/// 1-based line of the byte `offset` in `source`
+fn line_of(source: &str, offset: usize) -> usize {
+    source[..offset.min(source.len())].matches('\n').count() + 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn messages(found: &[Diagnostic]) -> Vec<(Severity, Option<usize>, &str)> {
+        found.iter().map(|d| (d.severity, d.line, d.message.as_str())).collect()
+    }
+
+    #[test]
+    fn test_check_reports_with_lines() {
+        let source = "\
+desktop_entry_dirs = [\"/nonexistent/drun\"]
+[appearance]
+colums = 3
+
+[theme]
+preset = \"nord\"
+colors = { accent = \"#zzzzzz\", dimmed = \"#808080\" }
+
+[appearance.entry]
+format = \"{name}\\n{keywords}\"
+
+[env]
+EDITOR = \"nvim\"
+
+[entries.\"firefox.desktop\"]
+hiden = true
+";
+        let found = check(source);
+        assert_eq!(
+            messages(&found),
+            [
+                (Severity::Warning, Some(1), "desktop_entry_dirs: /nonexistent/drun is not a directory"),
+                (Severity::Error, Some(3), "unknown key appearance.colums"),
+                (Severity::Error, Some(7), "theme.colors.accent = \"#zzzzzz\": invalid hex character"),
+                (Severity::Warning, Some(10), "appearance.entry.format: unknown placeholder {keywords} is shown as written"),
+                (Severity::Error, Some(16), "unknown key entries.firefox.desktop.hiden"),
+            ]
+        );
+        assert_eq!(found[1].format(Path::new("config.toml")), "config.toml:3: error: unknown key appearance.colums");
+        assert_eq!(unknown_keys(source).len(), 2);
+    }
+
+    #[test]
+    fn test_check_rejected_configs() {
+        // Syntax errors stop the check
+        let found = check("[appearance]\nprompt = \n");
+        assert_eq!(found.len(), 1);
+        assert_eq!((found[0].severity, found[0].line), (Severity::Error, Some(2)));
+
+        // Wrong types and failed validation are errors too
+        let found = check("[appearance]\nvisible_rows = \"five\"\n");
+        assert_eq!(found[0].line, Some(2));
+        let found = check("[behavior]\njobs_on_exit = \"later\"\n");
+        assert_eq!(found[0].line, Some(2));
+        assert!(found[0].message.contains("unknown variant `later`"));
+        let found = check("[behavior]\nelevate_command = \"\"\n[theme]\npreset_dark = \"monokai\"\n");
+        assert_eq!(found.len(), 2);
+        assert!(found[0].message.contains("unknown theme.preset_dark \"monokai\""));
+        assert!(found[1].message.contains("elevate_command"));
+
+        // So are unknown keys in arrays of tables, though a malformed
+        // custom entry is only skipped when loading
+        let found = check("[[custom_entries]]\nname = \"x\"\nexec = \"y\"\nbogus = 1\n");
+        assert_eq!(
+            messages(&found),
+            [(Severity::Error, Some(4), "unknown key custom_entries.bogus")]
+        );
+        let found = check("[terminal_mode]\nrules = [{ pattern = \"x\", mode = \"tui\", mdoe = 1 }]\n");
+        assert_eq!(found[0].line, Some(2));
+        assert_eq!(found[0].message, "unknown key terminal_mode.rules.mdoe");
+
+        assert!(check("").is_empty());
+        // Compositor settings in their old place still pass
+        assert_eq!(
+            messages(&check("[niri]\nenabled = false\nsocket_path = \"/tmp/niri\"\n")),
+            [(Severity::Warning, Some(2), "niri.enabled is deprecated, use compositor.enabled")]
+        );
+        assert!(check(include_str!("../config.example.toml"))
+            .iter()
+            .all(|d| d.severity == Severity::Warning));
+    }
+}
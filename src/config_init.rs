@@ -0,0 +1,407 @@
+//! `--init-config`: write a commented config with every default
+//!
+//! The values come from `Config::default()` serialized to TOML, so the file
+//! always matches the code; only the comments live here. Settings that are
+//! unset or empty by default are written commented out with an example.
+//! A test fails when a setting has no comment.
+
+use std::fmt::Write as _;
+use std::path::Path;
+
+use anyhow::{Context, Result};
+
+use crate::config::Config;
+
+/// Comment for one key or table, by dotted path
+struct Doc {
+    path: &'static str,
+    comment: &'static str,
+    /// Written commented out when the default is unset or empty
+    example: Option<&'static str>,
+}
+
+const fn doc(path: &'static str, comment: &'static str) -> Doc {
+    Doc {
+        path,
+        comment,
+        example: None,
+    }
+}
+
+const fn example(path: &'static str, comment: &'static str, example: &'static str) -> Doc {
+    Doc {
+        path,
+        comment,
+        example: Some(example),
+    }
+}
+
+const HEADER: &str = "\
+# darkwall-drun configuration, generated by `drun --init-config`
+# Every setting shows its default. See docs/USAGE.md for details.
+
+";
+
+/// In the order written; keys before the tables inside them
+const DOCS: &[Doc] = &[
+    doc("desktop_entry_dirs", "Directories to search for .desktop files"),
+    example(
+        "custom_entries",
+        "Launcher entries that don't come from .desktop files; exec is a shell\n\
+         command line, terminal_mode overrides detection",
+        "[[custom_entries]]\nname = \"SSH to build box\"\nexec = \"ssh build\"\n\
+         icon = \"utilities-terminal\"\nterminal_mode = \"interactive\"\ncategories = [\"Network\"]",
+    ),
+    doc("appearance", "Layout and look of the launcher"),
+    doc("appearance.prompt", "Prompt in front of the filter text"),
+    doc("appearance.selected_prefix", "Prefix of the selected entry"),
+    doc("appearance.unselected_prefix", "Prefix of the other entries"),
+    example(
+        "appearance.columns",
+        "Columns in the grid (1-10), or \"auto\" to fit as many columns of at\n\
+         least min_column_width cells as the terminal is wide (default: 2, or 1\n\
+         with layout = \"compact\")",
+        "columns = 2",
+    ),
+    doc("appearance.min_column_width", "Narrowest column with columns = \"auto\""),
+    doc("appearance.visible_rows", "Visible rows in the grid (1-20)"),
+    doc("appearance.grid_order", "Fill the grid column by column (\"column\") or row by row (\"row\")"),
+    doc(
+        "appearance.layout",
+        "\"cards\" of up to four lines per entry, or \"compact\": one line each, with\n\
+         visible_rows lines per column",
+    ),
+    doc("appearance.show_window_title", "Show the title a running command sets next to it in the output header"),
+    doc("appearance.show_preview", "Show a detail pane for the selected entry (Ctrl+P toggles it)"),
+    doc("appearance.border", "Lines of the boxes: \"plain\", \"rounded\", \"thick\" or \"none\""),
+    example(
+        "appearance.width",
+        "Width of the launcher in cells, or a percentage like \"80%\", centered in the terminal",
+        "width = \"80%\"",
+    ),
+    example("appearance.max_height", "Most rows the launcher takes, in cells or percent", "max_height = 30"),
+    doc("appearance.entry", "Lines shown in each entry card"),
+    doc("appearance.entry.show_generic", "Show the GenericName line"),
+    doc("appearance.entry.show_comment", "Show the Comment line"),
+    doc("appearance.entry.show_categories", "Show the Categories line"),
+    example(
+        "appearance.entry.format",
+        "Lines of a card instead of the switches above: {name}, {generic}, {comment},\n\
+         {categories}, {exec} and {uses} (launch count); {a|b} is the first one the\n\
+         entry has, {{ and }} are literal braces",
+        "format = \"{name}\\n{comment|generic}\\n{categories}\"",
+    ),
+    doc("theme", "Colors"),
+    example(
+        "theme.preset",
+        "Preset to start from: \"darkwall\" (default), \"catppuccin-mocha\",\n\
+         \"catppuccin-latte\", \"nord\", \"gruvbox\", \"tokyonight\", \"tokyonight-storm\",\n\
+         \"dracula\", \"solarized-dark\", \"solarized-light\", \"rose-pine\",\n\
+         \"everforest\", or the name of a file in ~/.config/darkwall-drun/themes/",
+        "preset = \"nord\"",
+    ),
+    example(
+        "theme.preset_dark",
+        "Preset on a dark terminal background, asked with OSC 11 at startup",
+        "preset_dark = \"tokyonight\"",
+    ),
+    example(
+        "theme.preset_light",
+        "Preset on a light terminal background",
+        "preset_light = \"solarized-light\"",
+    ),
+    doc("theme.colors", "Hex colors overriding the preset's"),
+    example("theme.colors.background", "Background", "background = \"#0d1116\""),
+    example("theme.colors.foreground", "Text", "foreground = \"#e5eaf1\""),
+    example("theme.colors.selection_bg", "Selected entry background", "selection_bg = \"#141c2a\""),
+    example("theme.colors.selection_fg", "Selected entry text", "selection_fg = \"#e5eaf1\""),
+    example("theme.colors.accent", "Borders and highlights", "accent = \"#b45309\""),
+    example("theme.colors.dimmed", "Secondary text", "dimmed = \"#9ca3af\""),
+    example("theme.colors.dimmed_alt", "Tertiary text", "dimmed_alt = \"#6b7280\""),
+    example("theme.colors.search_highlight", "Matched characters", "search_highlight = \"#b45309\""),
+    example("theme.colors.exit_success", "Exit status 0", "exit_success = \"#22c55e\""),
+    example("theme.colors.exit_failure", "Other exit statuses", "exit_failure = \"#ef4444\""),
+    doc("compositor", "Which compositor drun manages its window through"),
+    doc("compositor.backend", "\"auto\" follows the session, or \"niri\", \"sway\", \"hyprland\", \"none\""),
+    doc("compositor.enabled", "Enable compositor IPC integration"),
+    doc("compositor.float_on_idle", "Float the window while the launcher shows"),
+    doc("compositor.unfloat_on_execute", "Tile the window while a command runs"),
+    doc(
+        "compositor.wait_for_window_secs",
+        "Seconds to wait for a launched GUI app's window before exiting (0: don't)",
+    ),
+    doc("compositor.spawn_via_ipc", "Have the compositor start GUI apps, so they aren't drun's children"),
+    doc("compositor.request_timeout_ms", "Give up on a compositor request after this many milliseconds"),
+    doc("niri", "niri only; the other keys once here now live in [compositor]"),
+    example(
+        "niri.socket_path",
+        "Socket path, tried before $NIRI_SOCKET and $XDG_RUNTIME_DIR/niri-socket",
+        "socket_path = \"/run/user/1000/niri-socket\"",
+    ),
+    doc("behavior", "What drun does around commands"),
+    doc("behavior.after_command", "After a command exits: \"return\", \"close\" or \"prompt\""),
+    doc("behavior.preserve_output_lines", "Output lines kept when returning to the launcher"),
+    doc("behavior.show_categories", "Show categories in the entry list"),
+    doc("behavior.show_generic_name", "Show the generic name below the entry name"),
+    doc("behavior.localized_names", "Use the Name/Comment translated for LC_MESSAGES/LANG"),
+    doc("behavior.show_hidden", "Also list NoDisplay/Hidden entries and those for other desktops (dimmed)"),
+    doc("behavior.hide_missing", "Hide entries whose TryExec binary is missing (false greys them out)"),
+    doc("behavior.discover_sandboxed_apps", "Also load apps exported by Flatpak and Snap"),
+    example(
+        "behavior.shell",
+        "Shell running commands, with the command line appended; when unset,\n\
+         entries run in sh -c and typed commands in $SHELL -c",
+        "shell = [\"zsh\", \"-ic\"]",
+    ),
+    example(
+        "behavior.terminal_command",
+        "External terminal for Terminal=true entries forced to GUI mode, quoted\n\
+         like an Exec line",
+        "terminal_command = \"kitty -e\"",
+    ),
+    doc(
+        "behavior.enter_runs_typed_command",
+        "Plain Enter runs the filter text as a command when nothing matches\n\
+         (Alt+Enter always does)",
+    ),
+    doc("behavior.kill_grace_secs", "Seconds after Ctrl+C before a command is killed"),
+    doc("behavior.jobs_on_exit", "Running background jobs when quitting: \"prompt\" or \"kill\""),
+    doc("behavior.oneshot_timeout_secs", "Seconds a oneshot command may run before it counts as hung (0: never)"),
+    doc("behavior.oneshot_timeout_action", "Hung oneshot commands: \"warn\" (offer a kill key) or \"kill\""),
+    doc("behavior.notify_after_secs", "Notify when a command that ran this many seconds finishes (0: never)"),
+    doc("behavior.auto_dismiss_success_secs", "Return to the launcher this many seconds after a success (0: never)"),
+    doc("behavior.elevate_command", "Prefix for running an entry as root (Ctrl+E)"),
+    doc("behavior.open_link_command", "Program opening links picked from the output, with the URL appended"),
+    doc("history", "Launch history and frecency sorting"),
+    doc("history.enabled", "Record launches and sort by frecency"),
+    doc("history.max_entries", "Entries remembered"),
+    doc("history.decay_after_days", "Forget launches older than this many days"),
+    doc("history.frecency_weight", "Weight of frecency against the fuzzy match score (0.0 - 1.0)"),
+    doc("icons", "Entry icons"),
+    doc("icons.enabled", "Show icons"),
+    doc("icons.size", "Icon size in pixels for graphics protocols"),
+    example(
+        "icons.theme",
+        "Icon theme; the one in GTK settings when unset",
+        "theme = \"Papirus-Dark\"",
+    ),
+    example(
+        "icons.extra_search_paths",
+        "Icon directories searched before the standard ones",
+        "extra_search_paths = [\"~/my-icons\"]",
+    ),
+    doc("icons.fallback", "Text icons without image support: \"none\", \"nerdfont\" or \"unicode\""),
+    doc("icons.force_over_ssh", "Show image icons over SSH too"),
+    doc("icons.detect_timeout_ms", "How long to wait for the terminal to answer the graphics query"),
+    example(
+        "icons.glyphs",
+        "Fallback glyphs by desktop file id or category",
+        "[icons.glyphs]\nfirefox = \"\\uf269\"\nDevelopment = \"λ\"",
+    ),
+    example(
+        "env",
+        "Extra environment variables for launched commands ($VAR is expanded)",
+        "[env]\nMOZ_ENABLE_WAYLAND = \"1\"",
+    ),
+    example(
+        "entries",
+        "Per-entry overrides by desktop file id: hidden, name, icon, terminal_mode",
+        "[entries.firefox]\nname = \"Web Browser\"",
+    ),
+    doc("terminal_mode", "Terminal mode rules, checked before the builtin app lists"),
+    example("terminal_mode.tui", "Full screen apps, by command name", "tui = [\"zellij\", \"yazi\"]"),
+    example("terminal_mode.interactive", "REPL-style apps, by command name", "interactive = [\"clojure\"]"),
+    example(
+        "terminal_mode.long_running_patterns",
+        "Substrings marking a command line as long-running",
+        "long_running_patterns = [\"npm run dev\"]",
+    ),
+    example(
+        "terminal_mode.rules",
+        "Regex rules against the whole command line, first match wins",
+        "[[terminal_mode.rules]]\npattern = \"^ssh\\\\s\"\nmode = \"interactive\"",
+    ),
+    doc("output", "Saving command output (s in the output view)"),
+    example(
+        "output.dir",
+        "Directory for saved output",
+        "dir = \"~/.local/share/darkwall-drun/output\"",
+    ),
+    doc("output.auto_save", "Save the output of every command when it finishes"),
+    doc(
+        "hooks",
+        "Shell commands run around every launch; see docs/USAGE.md for the\n\
+         variables they get",
+    ),
+    example(
+        "hooks.pre_exec",
+        "Run before launching; the launch waits for them",
+        "pre_exec = [\"echo \\\"$DRUN_COMMAND\\\" >> ~/.local/state/drun.log\"]",
+    ),
+    example(
+        "hooks.post_exec",
+        "Run in the background after the command exits",
+        "post_exec = [\"notify-send \\\"$DRUN_COMMAND exited $DRUN_EXIT_CODE\\\"\"]",
+    ),
+    doc("hooks.timeout_secs", "Seconds each hook may run before it is killed"),
+];
+
+/// The commented default config
+pub fn generate() -> Result<String> {
+    let defaults = toml::Table::try_from(Config::default()).context("Failed to serialize the default config")?;
+    let mut out = HEADER.to_string();
+    write_table("", &defaults, &mut out);
+    Ok(out)
+}
+
+/// Write the default config to `path`, which must not exist unless `force`
+pub fn write(path: &Path, force: bool) -> Result<()> {
+    if path.exists() && !force {
+        anyhow::bail!("{} already exists (pass --force to overwrite it)", path.display());
+    }
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    std::fs::write(path, generate()?).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+fn join(path: &str, name: &str) -> String {
+    if path.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}.{}", path, name)
+    }
+}
+
+fn find(path: &str) -> Option<&'static Doc> {
+    DOCS.iter().find(|doc| doc.path == path)
+}
+
+/// Names in the table at `path`: documented ones in order, then the rest
+fn children(path: &str, table: &toml::Table) -> Vec<String> {
+    let mut names: Vec<String> = DOCS
+        .iter()
+        .filter_map(|doc| {
+            let name = match doc.path.rsplit_once('.') {
+                Some((parent, name)) if parent == path => name,
+                None if path.is_empty() => doc.path,
+                _ => return None,
+            };
+            Some(name.to_string())
+        })
+        .collect();
+    let undocumented: Vec<String> = table.keys().filter(|key| !names.contains(key)).cloned().collect();
+    names.extend(undocumented);
+    names
+}
+
+/// Write the keys of `table`, then its tables, which TOML needs last
+fn write_table(path: &str, table: &toml::Table, out: &mut String) {
+    let names = children(path, table);
+    let is_table = |name: &str| matches!(table.get(name), Some(toml::Value::Table(_)));
+
+    for name in names.iter().filter(|name| !is_table(name)) {
+        let doc = find(&join(path, name));
+        write_comment(doc.map(|d| d.comment), out);
+        match (table.get(name.as_str()), doc.and_then(|d| d.example)) {
+            (Some(value), Some(example)) if is_empty(value) => write_commented(example, out),
+            (Some(value), _) => {
+                let _ = writeln!(out, "{} = {}", name, value);
+            }
+            (None, Some(example)) => write_commented(example, out),
+            (None, None) => {}
+        }
+    }
+
+    for name in names.iter().filter(|name| is_table(name)) {
+        let Some(toml::Value::Table(nested)) = table.get(name.as_str()) else {
+            continue;
+        };
+        let full = join(path, name);
+        let doc = find(&full);
+        out.push('\n');
+        write_comment(doc.map(|d| d.comment), out);
+        match doc.and_then(|d| d.example) {
+            // A table of the user's own keys
+            Some(example) if nested.is_empty() => write_commented(example, out),
+            _ => {
+                let _ = writeln!(out, "[{}]", full);
+                write_table(&full, nested, out);
+            }
+        }
+    }
+}
+
+fn is_empty(value: &toml::Value) -> bool {
+    match value {
+        toml::Value::Array(items) => items.is_empty(),
+        toml::Value::Table(table) => table.is_empty(),
+        toml::Value::String(text) => text.is_empty(),
+        _ => false,
+    }
+}
+
+fn write_comment(comment: Option<&str>, out: &mut String) {
+    for line in comment.into_iter().flat_map(str::lines) {
+        let _ = writeln!(out, "# {}", line);
+    }
+}
+
+fn write_commented(text: &str, out: &mut String) {
+    for line in text.lines() {
+        let _ = writeln!(out, "# {}", line);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+
+    #[test]
+    fn test_generated_config_loads_as_defaults() {
+        let dir = TempDir::new("init-config");
+        let path = dir.join("drun").join("config.toml");
+
+        write(&path, false).unwrap();
+        let loaded = Config::load(path.to_str().unwrap()).unwrap();
+        assert_eq!(
+            serde_json::to_value(&loaded).unwrap(),
+            serde_json::to_value(Config::default()).unwrap()
+        );
+        // Examples are left commented out
+        let text = std::fs::read_to_string(&path).unwrap();
+        assert!(text.contains("\n[theme.colors]\n# Background\n# background = \"#0d1116\"\n"));
+        assert!(text.contains("\n# [[custom_entries]]\n"));
+        // Home paths stay portable
+        assert!(text.contains("desktop_entry_dirs = [\"~/.local/share/applications\""));
+
+        // Nothing is overwritten without --force
+        std::fs::write(&path, "# mine\n").unwrap();
+        assert!(write(&path, false).unwrap_err().to_string().contains("--force"));
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "# mine\n");
+        write(&path, true).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), text);
+    }
+
+    #[test]
+    fn test_every_setting_is_documented() {
+        fn walk(path: &str, value: &serde_json::Value, found: &mut Vec<String>) {
+            if let Some(object) = value.as_object() {
+                for (key, value) in object {
+                    let full = join(path, key);
+                    walk(&full, value, found);
+                    found.push(full);
+                }
+            }
+        }
+        let mut settings = Vec::new();
+        walk("", &serde_json::to_value(Config::default()).unwrap(), &mut settings);
+
+        let undocumented: Vec<_> = settings.iter().filter(|path| find(path).is_none()).collect();
+        assert!(undocumented.is_empty(), "no comment for {:?}", undocumented);
+        let stale: Vec<_> = DOCS.iter().map(|d| d.path).filter(|path| !settings.iter().any(|s| s == path)).collect();
+        assert!(stale.is_empty(), "no such setting: {:?}", stale);
+    }
+}
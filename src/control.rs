@@ -0,0 +1,227 @@
+//! Control socket of a resident drun (`--daemon`)
+//!
+//! A daemon keeps its entries, history and icons loaded and hides instead
+//! of exiting, so showing it again is instant. It listens on
+//! `$XDG_RUNTIME_DIR/darkwall-drun.sock` for newline-delimited JSON
+//! commands such as `{"command":"toggle"}` and answers each line with
+//! `{"ok":true}` or `{"ok":false,"error":"..."}`. `drun --toggle` and
+//! `drun --send <command>` are the client side.
+//!
+//! A socket file nobody answers on is left over from a crashed daemon and
+//! is replaced; one that answers means a daemon is already running.
+
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt};
+use tokio::net::{UnixListener, UnixStream};
+use tokio::sync::mpsc;
+
+/// File name of the socket in `$XDG_RUNTIME_DIR`
+const SOCKET_NAME: &str = "darkwall-drun.sock";
+
+/// How long the client waits for the daemon to answer
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// A command for the daemon
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "kebab-case")]
+pub enum Command {
+    /// Show the launcher (and focus its window, with a compositor)
+    Show,
+    /// Hide the launcher, as after a launch
+    Hide,
+    /// Show when hidden, hide when shown
+    Toggle,
+    /// Exit the daemon
+    Quit,
+    /// Re-scan the desktop entries
+    ReloadEntries,
+}
+
+impl Command {
+    /// The command called `name` (`reload-entries`), for `--send`
+    pub fn from_name(name: &str) -> Result<Self> {
+        parse_command(&serde_json::json!({ "command": name }).to_string())
+    }
+}
+
+/// Answer to a command line
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct Reply {
+    ok: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// Parse one line of the protocol
+pub fn parse_command(line: &str) -> Result<Command> {
+    serde_json::from_str(line.trim())
+        .with_context(|| format!("Invalid command {}", line.trim()))
+}
+
+/// `$XDG_RUNTIME_DIR/darkwall-drun.sock`
+pub fn socket_path() -> Result<PathBuf> {
+    let dir = std::env::var_os("XDG_RUNTIME_DIR")
+        .filter(|dir| !dir.is_empty())
+        .context("XDG_RUNTIME_DIR is not set, so there is no place for the control socket")?;
+    Ok(PathBuf::from(dir).join(SOCKET_NAME))
+}
+
+/// The daemon's end: commands received on the socket
+///
+/// The socket file is removed when this is dropped.
+pub struct ControlSocket {
+    path: PathBuf,
+    commands: mpsc::UnboundedReceiver<Command>,
+    task: tokio::task::JoinHandle<()>,
+}
+
+impl ControlSocket {
+    /// Listen on `path`, replacing a stale socket file
+    ///
+    /// Fails when another daemon answers on `path`. Must be called within
+    /// the tokio runtime.
+    pub fn bind(path: &Path) -> Result<Self> {
+        if path.exists() {
+            if std::os::unix::net::UnixStream::connect(path).is_ok() {
+                anyhow::bail!("drun is already running as a daemon ({} is in use)", path.display());
+            }
+            tracing::info!("Replacing stale control socket {}", path.display());
+            std::fs::remove_file(path)
+                .with_context(|| format!("Failed to remove stale socket {}", path.display()))?;
+        }
+        let listener = UnixListener::bind(path)
+            .with_context(|| format!("Failed to listen on {}", path.display()))?;
+        tracing::info!("Listening for commands on {}", path.display());
+
+        let (tx, commands) = mpsc::unbounded_channel();
+        let task = tokio::spawn(async move {
+            loop {
+                match listener.accept().await {
+                    Ok((stream, _)) => {
+                        tokio::spawn(serve(stream, tx.clone()));
+                    }
+                    Err(e) => {
+                        tracing::warn!("Control socket accept failed: {}", e);
+                        return;
+                    }
+                }
+            }
+        });
+        Ok(Self {
+            path: path.to_path_buf(),
+            commands,
+            task,
+        })
+    }
+
+    /// Next received command, without waiting
+    pub fn try_recv(&mut self) -> Option<Command> {
+        self.commands.try_recv().ok()
+    }
+}
+
+impl Drop for ControlSocket {
+    fn drop(&mut self) {
+        self.task.abort();
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Answer the command lines of one client until it hangs up
+async fn serve(stream: UnixStream, commands: mpsc::UnboundedSender<Command>) {
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = tokio::io::BufReader::new(reader).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let reply = match parse_command(&line) {
+            Ok(command) => {
+                tracing::debug!("Control command: {:?}", command);
+                let _ = commands.send(command);
+                Reply { ok: true, error: None }
+            }
+            Err(e) => Reply {
+                ok: false,
+                error: Some(format!("{:#}", e)),
+            },
+        };
+        let mut out = serde_json::to_string(&reply).unwrap_or_default();
+        out.push('\n');
+        if writer.write_all(out.as_bytes()).await.is_err() {
+            return;
+        }
+    }
+}
+
+/// Send `command` to the daemon listening on `path` and wait for its answer
+pub fn send(path: &Path, command: Command) -> Result<()> {
+    let mut stream = std::os::unix::net::UnixStream::connect(path).with_context(|| {
+        format!("No drun daemon is listening on {} (start one with --daemon)", path.display())
+    })?;
+    stream.set_read_timeout(Some(CLIENT_TIMEOUT))?;
+    stream.set_write_timeout(Some(CLIENT_TIMEOUT))?;
+
+    let mut line = serde_json::to_string(&command)?;
+    line.push('\n');
+    stream.write_all(line.as_bytes())?;
+
+    let mut answer = String::new();
+    BufReader::new(&stream)
+        .read_line(&mut answer)
+        .context("The drun daemon didn't answer")?;
+    let reply: Reply = serde_json::from_str(answer.trim())
+        .with_context(|| format!("Unexpected answer from the drun daemon: {}", answer.trim()))?;
+    match reply.error {
+        Some(error) if !reply.ok => anyhow::bail!("{}", error),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+
+    #[test]
+    fn test_parse_command() {
+        assert_eq!(parse_command(r#"{"command":"toggle"}"#).unwrap(), Command::Toggle);
+        assert_eq!(
+            parse_command(" {\"command\": \"reload-entries\"}\r\n").unwrap(),
+            Command::ReloadEntries
+        );
+        assert!(parse_command(r#"{"command":"launch"}"#).is_err());
+        assert!(parse_command("toggle").is_err());
+        assert_eq!(Command::from_name("quit").unwrap(), Command::Quit);
+        assert_eq!(serde_json::to_string(&Command::Show).unwrap(), r#"{"command":"show"}"#);
+    }
+
+    #[tokio::test]
+    async fn test_stale_socket_is_replaced() {
+        let dir = TempDir::new("control");
+        let path = dir.join("control.sock");
+        // A crashed daemon leaves its socket file behind
+        drop(std::os::unix::net::UnixListener::bind(&path).unwrap());
+        assert!(path.exists());
+
+        let mut control = ControlSocket::bind(&path).unwrap();
+        let err = ControlSocket::bind(&path).err().unwrap();
+        assert!(err.to_string().contains("already running"));
+
+        let client = path.clone();
+        tokio::task::spawn_blocking(move || send(&client, Command::Toggle))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(control.try_recv(), Some(Command::Toggle));
+        assert_eq!(control.try_recv(), None);
+
+        drop(control);
+        assert!(!path.exists());
+    }
+}
@@ -1,11 +1,29 @@
 use anyhow::Result;
-use freedesktop_desktop_entry::{DesktopEntry, Iter};
+use freedesktop_desktop_entry::{DecodeError, DesktopEntry, Iter};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 
+use crate::entry_cache::EntryCache;
+
+/// Options controlling how desktop entries are loaded
+#[derive(Debug, Clone, Default)]
+pub struct LoadOptions {
+    /// Locale candidates for localized keys, most specific first.
+    /// Empty means always use the unlocalized values.
+    pub locales: Vec<String>,
+    /// Desktop names from `XDG_CURRENT_DESKTOP`, for OnlyShowIn/NotShowIn
+    pub desktops: Vec<String>,
+    /// Keep entries that would normally be hidden (marked `suppressed`)
+    pub show_hidden: bool,
+    /// Drop entries whose TryExec binary is missing instead of marking them
+    pub hide_missing: bool,
+}
+
 /// Parsed desktop entry with fields we care about
 /// TEAM_000: Phase 4 - Added custom_fields for X-Darkwall* support
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Entry {
     pub id: String,
     pub name: String,
@@ -19,26 +37,67 @@ pub struct Entry {
     pub keywords: Vec<String>,
     pub terminal: bool,
     pub no_display: bool,
-    /// NOTE: Stored for debugging and potential "open containing folder" feature
-    #[allow(dead_code)]
+    /// Hidden=true (the entry is considered deleted)
+    pub hidden: bool,
+    /// OnlyShowIn= desktop names
+    pub only_show_in: Vec<String>,
+    /// NotShowIn= desktop names
+    pub not_show_in: Vec<String>,
+    /// Entry would normally not be listed (NoDisplay, Hidden, OnlyShowIn/NotShowIn)
+    /// but was kept because `behavior.show_hidden` is enabled
+    pub suppressed: bool,
+    /// TryExec= value, checked at load time
+    pub try_exec: Option<String>,
+    /// Path= working directory for the launched command
+    pub working_dir: Option<PathBuf>,
+    /// StartupWMClass=: the app_id / WM class of the windows it opens
+    #[serde(default)]
+    pub startup_wm_class: Option<String>,
+    /// TryExec binary could not be found (entry kept because
+    /// `behavior.hide_missing` is disabled)
+    pub missing: bool,
+    /// Desktop file the entry was read from, shown in the detail pane
     pub path: PathBuf,
     /// Custom X-Darkwall* fields from the desktop entry
     pub custom_fields: HashMap<String, String>,
+    /// Defined in `[[custom_entries]]` or typed into the filter rather than
+    /// read from a .desktop file; `exec` is then a plain shell command line
+    pub custom: bool,
 }
 
+/// Id (and history key) prefix of commands typed into the filter
+pub const TYPED_COMMAND_PREFIX: &str = "cmd:";
+
 impl Entry {
+    /// Entry for a shell command typed into the filter
+    ///
+    /// The command is run verbatim: no field code expansion or quoting.
+    pub fn typed_command(cmd: &str) -> Self {
+        Self {
+            id: format!("{}{}", TYPED_COMMAND_PREFIX, cmd),
+            name: cmd.to_string(),
+            comment: Some("Command".to_string()),
+            exec: Some(cmd.to_string()),
+            terminal: true,
+            custom: true,
+            ..Default::default()
+        }
+    }
+
+    /// Whether this entry is a command typed into the filter
+    pub fn is_typed_command(&self) -> bool {
+        self.id.starts_with(TYPED_COMMAND_PREFIX)
+    }
+
     /// Create from freedesktop DesktopEntry
-    fn from_desktop_entry(de: &DesktopEntry, path: &Path) -> Option<Self> {
-        // The API takes a slice of locale strings, use empty slice for default
-        let locales: &[&str] = &[];
-        
-        let name = de.name(locales)?.to_string();
-        let exec = de.exec().map(|s| s.to_string());
+    ///
+    /// `locales` are tried in order for localized keys (Name, GenericName,
+    /// Comment, Keywords) before falling back to the unlocalized value.
+    fn from_desktop_entry(de: &DesktopEntry, path: &Path, locales: &[String]) -> Option<Self> {
+        let name = localized_entry(de, "Name", locales)?.to_string();
 
         // Skip entries without exec
-        if exec.is_none() {
-            return None;
-        }
+        let exec = Some(de.exec()?.to_string());
 
         let id = path
             .file_stem()
@@ -46,22 +105,39 @@ impl Entry {
             .unwrap_or("unknown")
             .to_string();
 
-        let generic_name = de.generic_name(locales).map(|s| s.to_string());
-        let comment = de.comment(locales).map(|s| s.to_string());
+        let generic_name = localized_entry(de, "GenericName", locales).map(|s| s.to_string());
+        let comment = localized_entry(de, "Comment", locales).map(|s| s.to_string());
         let icon = de.icon().map(|s| s.to_string());
+        let try_exec = de.try_exec().filter(|s| !s.is_empty()).map(|s| s.to_string());
+        let working_dir = de.path().filter(|s| !s.is_empty()).map(PathBuf::from);
+        let startup_wm_class = de.startup_wm_class().filter(|s| !s.is_empty()).map(|s| s.to_string());
 
         let categories = de
             .categories()
             .map(|cats| cats.iter().map(|c| c.to_string()).collect())
             .unwrap_or_default();
 
-        let keywords = de
-            .keywords(locales)
-            .map(|kws| kws.iter().map(|k| k.to_string()).collect())
+        let keywords = localized_entry(de, "Keywords", locales)
+            .map(|kws| {
+                kws.split(';')
+                    .filter(|k| !k.is_empty())
+                    .map(|k| k.to_string())
+                    .collect()
+            })
             .unwrap_or_default();
 
         let terminal = de.terminal();
         let no_display = de.no_display();
+        let hidden = de.hidden();
+        let to_strings = |list: Option<Vec<&str>>| -> Vec<String> {
+            list.unwrap_or_default()
+                .into_iter()
+                .filter(|s| !s.is_empty())
+                .map(|s| s.to_string())
+                .collect()
+        };
+        let only_show_in = to_strings(de.only_show_in());
+        let not_show_in = to_strings(de.not_show_in());
 
         // Extract X-Darkwall* custom fields
         let mut custom_fields = HashMap::new();
@@ -71,6 +147,7 @@ impl Entry {
             "KeepOutput",
             "UnfloatOnRun",
             "PreserveLines",
+            "Env",
         ];
         for field in DARKWALL_FIELDS {
             let key = format!("X-Darkwall{}", field);
@@ -90,11 +167,42 @@ impl Entry {
             keywords,
             terminal,
             no_display,
+            hidden,
+            only_show_in,
+            not_show_in,
+            suppressed: false,
+            try_exec,
+            working_dir,
+            startup_wm_class,
+            missing: false,
             path: path.to_path_buf(),
             custom_fields,
+            custom: false,
         })
     }
 
+    /// Check whether the entry should be listed on the given desktops
+    ///
+    /// Follows the Desktop Entry spec: `NoDisplay` and `Hidden` always hide,
+    /// `OnlyShowIn` requires one of `desktops` to be listed, and `NotShowIn`
+    /// hides the entry if any of `desktops` is listed.
+    pub fn should_show(&self, desktops: &[String]) -> bool {
+        if self.no_display || self.hidden {
+            return false;
+        }
+
+        let listed = |list: &[String]| {
+            desktops
+                .iter()
+                .any(|d| list.iter().any(|l| l.eq_ignore_ascii_case(d)))
+        };
+
+        if !self.only_show_in.is_empty() && !listed(&self.only_show_in) {
+            return false;
+        }
+        !listed(&self.not_show_in)
+    }
+
     /// Get display text for filtering/matching
     pub fn search_text(&self) -> String {
         let mut parts = vec![self.name.clone()];
@@ -109,15 +217,94 @@ impl Entry {
         parts.join(" ")
     }
 
-    /// Get the command to execute (with field codes stripped)
+    /// Get the command to execute as a shell command line
+    ///
+    /// Custom entries from config are used as-is. Otherwise the Exec value
+    /// is split using the Desktop Entry quoting rules, field codes are
+    /// expanded (no files or URLs are ever passed), and the resulting
+    /// arguments are re-quoted for `sh -c`.
     pub fn command(&self) -> Option<String> {
-        self.exec.as_ref().map(|e| {
-            // Strip field codes like %f, %F, %u, %U, etc.
-            e.split_whitespace()
-                .filter(|s| !s.starts_with('%'))
-                .collect::<Vec<_>>()
-                .join(" ")
-        })
+        let exec = self.exec.as_ref()?;
+        if self.custom {
+            return Some(exec.clone());
+        }
+
+        let Some(args) = self.argv() else {
+            tracing::warn!("Malformed Exec in {}: {}", self.id, exec);
+            // Fall back to naive stripping of field codes
+            return Some(
+                exec.split_whitespace()
+                    .filter(|s| !s.starts_with('%'))
+                    .collect::<Vec<_>>()
+                    .join(" "),
+            );
+        };
+
+        let args: Vec<String> = args
+            .iter()
+            .map(|arg| shell_quote(arg).into_owned())
+            .collect();
+
+        Some(args.join(" "))
+    }
+
+    /// The command as program and arguments, to run without a shell
+    ///
+    /// Split and expanded like `command()`. None for custom entries (their
+    /// Exec is a shell command line) and malformed Exec values.
+    pub fn argv(&self) -> Option<Vec<String>> {
+        if self.custom {
+            return None;
+        }
+        let args = split_exec(self.exec.as_ref()?)?;
+        let argv: Vec<String> = args
+            .iter()
+            .flat_map(|arg| self.expand_field_codes(arg))
+            .collect();
+        (!argv.is_empty()).then_some(argv)
+    }
+
+    /// Expand field codes in a single Exec argument
+    ///
+    /// A standalone file/URL code (`%f`, `%F`, `%u`, `%U`, and the deprecated
+    /// ones) removes the argument entirely, `%i` becomes `--icon <Icon>`,
+    /// `%c` the (localized) name and `%k` the desktop file path. Unknown
+    /// codes are kept literally since real-world files contain things like
+    /// `date +%Y` inside quoted scripts.
+    fn expand_field_codes(&self, arg: &str) -> Vec<String> {
+        match arg {
+            "%f" | "%F" | "%u" | "%U" | "%d" | "%D" | "%n" | "%N" | "%v" | "%m" => {
+                return Vec::new();
+            }
+            "%i" => {
+                return match self.icon {
+                    Some(ref icon) => vec!["--icon".to_string(), icon.clone()],
+                    None => Vec::new(),
+                };
+            }
+            _ => {}
+        }
+
+        let mut out = String::with_capacity(arg.len());
+        let mut chars = arg.chars();
+        while let Some(c) = chars.next() {
+            if c != '%' {
+                out.push(c);
+                continue;
+            }
+            match chars.next() {
+                Some('%') => out.push('%'),
+                Some('c') => out.push_str(&self.name),
+                Some('k') => out.push_str(&self.path.to_string_lossy()),
+                Some('f' | 'F' | 'u' | 'U' | 'd' | 'D' | 'n' | 'N' | 'v' | 'm' | 'i') => {}
+                Some(other) => {
+                    out.push('%');
+                    out.push(other);
+                }
+                None => out.push('%'),
+            }
+        }
+        vec![out]
     }
 
     /// Get a custom X-Darkwall field value
@@ -147,10 +334,308 @@ impl Entry {
 
 }
 
+/// Data directories exported by Flatpak and Snap that exist on this system
+///
+/// Each contains `applications/` and `icons/` like a regular XDG data dir.
+/// User Flatpak installs come first so they shadow system-wide ones.
+pub fn sandboxed_data_dirs() -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+    if let Some(data) = dirs::data_dir() {
+        dirs.push(data.join("flatpak/exports/share"));
+    }
+    dirs.push(PathBuf::from("/var/lib/flatpak/exports/share"));
+    dirs.push(PathBuf::from("/var/lib/snapd/desktop"));
+    dirs.retain(|d| d.is_dir());
+    dirs
+}
+
+/// Compute the desktop file id of `path` relative to the data dir `base`
+///
+/// Per the XDG spec the id is the path below the applications directory
+/// with `/` replaced by `-` (`kde4/konsole.desktop` → `kde4-konsole`). The
+/// `.desktop` suffix is dropped to keep ids stable in the history file.
+pub fn desktop_file_id(base: &Path, path: &Path) -> String {
+    let relative = path.strip_prefix(base).unwrap_or(path);
+    let relative = relative.with_extension("");
+    let parts: Vec<_> = relative
+        .components()
+        .filter_map(|c| match c {
+            std::path::Component::Normal(part) => part.to_str(),
+            _ => None,
+        })
+        .collect();
+    if parts.is_empty() {
+        return "unknown".to_string();
+    }
+    parts.join("-")
+}
+
+/// Split an Exec value into arguments following the Desktop Entry quoting rules
+///
+/// Arguments are separated by spaces. Double-quoted arguments may contain
+/// spaces, and inside them a backslash escapes `"`, `` ` ``, `$` and `\`.
+/// Returns None for unterminated quotes.
+pub fn split_exec(exec: &str) -> Option<Vec<String>> {
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut in_arg = false;
+    let mut chars = exec.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            ' ' | '\t' | '\n' => {
+                if in_arg {
+                    args.push(std::mem::take(&mut current));
+                    in_arg = false;
+                }
+            }
+            '"' => {
+                in_arg = true;
+                loop {
+                    match chars.next()? {
+                        '"' => break,
+                        '\\' => {
+                            let escaped = chars.next()?;
+                            if !matches!(escaped, '"' | '`' | '$' | '\\') {
+                                current.push('\\');
+                            }
+                            current.push(escaped);
+                        }
+                        c => current.push(c),
+                    }
+                }
+            }
+            c => {
+                in_arg = true;
+                current.push(c);
+            }
+        }
+    }
+
+    if in_arg {
+        args.push(current);
+    }
+    Some(args)
+}
+
+/// Quote an argument for `sh -c` if it contains anything but safe characters
+pub fn shell_quote(arg: &str) -> Cow<'_, str> {
+    let safe = !arg.is_empty()
+        && arg
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./=:,+@%".contains(c));
+    if safe {
+        Cow::Borrowed(arg)
+    } else {
+        Cow::Owned(format!("'{}'", arg.replace('\'', "'\\''")))
+    }
+}
+
+/// Resolves executable names against `PATH`, caching results
+///
+/// Many entries share the same TryExec (or none at all), so caching keeps
+/// startup cheap even with hundreds of entries.
+pub struct ExecResolver {
+    path_dirs: Vec<PathBuf>,
+    cache: HashMap<String, bool>,
+}
+
+impl ExecResolver {
+    /// Create a resolver using the current `PATH`
+    pub fn from_env() -> Self {
+        let path_dirs = std::env::var_os("PATH")
+            .map(|p| std::env::split_paths(&p).collect())
+            .unwrap_or_default();
+        Self::new(path_dirs)
+    }
+
+    /// Create a resolver searching the given directories
+    pub fn new(path_dirs: Vec<PathBuf>) -> Self {
+        Self {
+            path_dirs,
+            cache: HashMap::new(),
+        }
+    }
+
+    /// Check whether `program` exists as an executable file
+    ///
+    /// Absolute paths are checked directly, bare names are searched in `PATH`.
+    pub fn exists(&mut self, program: &str) -> bool {
+        if let Some(&found) = self.cache.get(program) {
+            return found;
+        }
+
+        let found = if program.contains('/') {
+            is_executable(Path::new(program))
+        } else {
+            self.path_dirs.iter().any(|dir| is_executable(&dir.join(program)))
+        };
+
+        self.cache.insert(program.to_string(), found);
+        found
+    }
+}
+
+/// Check that a path is a regular file with an executable bit set
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+
+    std::fs::metadata(path)
+        .map(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+        .unwrap_or(false)
+}
+
+/// Look up a `[Desktop Entry]` key, preferring the first matching locale
+///
+/// Unlike `DesktopEntry::desktop_entry_localized`, candidates are matched
+/// exactly and in order, so `de_DE@euro` does not short-circuit to `de`
+/// before `de_DE` has been tried.
+fn localized_entry<'a>(de: &'a DesktopEntry, key: &str, locales: &[String]) -> Option<&'a str> {
+    let (default, localized) = de.groups.desktop_entry()?.0.get(key)?;
+    locales
+        .iter()
+        .find_map(|locale| localized.get(locale))
+        .map(|s| s.as_str())
+        .or(Some(default.as_str()))
+}
+
+/// Get the user's message locale from `LC_ALL`, `LC_MESSAGES` or `LANG`
+fn message_locale() -> Option<String> {
+    ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find(|value| !value.is_empty())
+        .filter(|value| value != "C" && value != "POSIX" && !value.starts_with("C."))
+}
+
+/// Desktop names from `XDG_CURRENT_DESKTOP` (colon separated)
+pub fn current_desktops() -> Vec<String> {
+    std::env::var("XDG_CURRENT_DESKTOP")
+        .map(|value| {
+            value
+                .split(':')
+                .filter(|d| !d.is_empty())
+                .map(|d| d.to_string())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Locale candidates for the current user, most specific first
+pub fn user_locales() -> Vec<String> {
+    message_locale()
+        .map(|locale| locale_candidates(&locale))
+        .unwrap_or_default()
+}
+
+/// Expand a POSIX locale (`lang_COUNTRY.ENCODING@MODIFIER`) into the lookup
+/// order from the Desktop Entry spec:
+/// `lang_COUNTRY@MODIFIER`, `lang_COUNTRY`, `lang@MODIFIER`, `lang`.
+/// The encoding part is ignored.
+pub fn locale_candidates(locale: &str) -> Vec<String> {
+    let (rest, modifier) = match locale.split_once('@') {
+        Some((rest, modifier)) => (rest, Some(modifier)),
+        None => (locale, None),
+    };
+    let rest = rest.split('.').next().unwrap_or(rest);
+    let (lang, country) = match rest.split_once('_') {
+        Some((lang, country)) => (lang, Some(country)),
+        None => (rest, None),
+    };
+
+    if lang.is_empty() {
+        return Vec::new();
+    }
+
+    let mut candidates = Vec::new();
+    if let (Some(country), Some(modifier)) = (country, modifier) {
+        candidates.push(format!("{}_{}@{}", lang, country, modifier));
+    }
+    if let Some(country) = country {
+        candidates.push(format!("{}_{}", lang, country));
+    }
+    if let Some(modifier) = modifier {
+        candidates.push(format!("{}@{}", lang, modifier));
+    }
+    candidates.push(lang.to_string());
+    candidates
+}
+
+/// Parse desktop file content, tolerating localized keys without a default
+///
+/// The parser rejects files where e.g. `Name[de]=` appears without a plain
+/// `Name=`. Such files are retried with the first localized value promoted
+/// to the default.
+fn parse_desktop_entry(path: &Path, content: &str) -> Option<DesktopEntry> {
+    match DesktopEntry::from_str(path, content, None::<&[&str]>) {
+        Ok(de) => Some(de),
+        Err(DecodeError::KeyDoesNotExist) => {
+            let patched = fill_missing_defaults(content);
+            DesktopEntry::from_str(path, &patched, None::<&[&str]>).ok()
+        }
+        Err(_) => None,
+    }
+}
+
+/// Keys seen in one group of a desktop file (see `fill_missing_defaults`)
+struct GroupKeys {
+    /// Index of the `[Group]` header line
+    header: usize,
+    /// Keys with an unlocalized value
+    plain: Vec<String>,
+    /// First localized value seen for each key
+    localized: Vec<(String, String)>,
+}
+
+/// Insert a default `Key=` line for every `Key[locale]=` that lacks one
+fn fill_missing_defaults(content: &str) -> String {
+    let mut out = Vec::new();
+    let mut groups: Vec<GroupKeys> = Vec::new();
+
+    for line in content.lines() {
+        if line.starts_with('[') {
+            groups.push(GroupKeys {
+                header: out.len(),
+                plain: Vec::new(),
+                localized: Vec::new(),
+            });
+        } else if let (Some(group), Some((key, value))) = (groups.last_mut(), line.split_once('=')) {
+            match key.strip_suffix(']').and_then(|k| k.split_once('[')) {
+                Some((base, _locale)) => {
+                    if !group.localized.iter().any(|(k, _)| k == base) {
+                        group.localized.push((base.to_string(), value.to_string()));
+                    }
+                }
+                None => group.plain.push(key.trim().to_string()),
+            }
+        }
+        out.push(line.to_string());
+    }
+
+    // Insert from the last group backwards so earlier indices stay valid
+    for group in groups.into_iter().rev() {
+        for (key, value) in group.localized.into_iter().rev() {
+            if !group.plain.contains(&key) {
+                out.insert(group.header + 1, format!("{}={}", key, value));
+            }
+        }
+    }
+
+    out.join("\n")
+}
+
 /// Load all desktop entries from the given directories
-pub fn load_all(dirs: &[PathBuf]) -> Result<Vec<Entry>> {
+///
+/// With a `cache_path`, unchanged files are taken from the entry cache
+/// instead of being parsed again (see `entry_cache`).
+pub fn load_all(dirs: &[PathBuf], options: &LoadOptions, cache_path: Option<&Path>) -> Result<Vec<Entry>> {
+    let start = std::time::Instant::now();
     let mut entries = Vec::new();
-    let mut seen_ids = std::collections::HashSet::new();
+    // Desktop file id -> path of the file that won
+    let mut seen_ids: HashMap<String, PathBuf> = HashMap::new();
+    let mut shadowed = 0;
+    let mut resolver = ExecResolver::from_env();
+    let mut cache = EntryCache::load(cache_path, &options.locales);
 
     for dir in dirs {
         if !dir.exists() {
@@ -159,31 +644,424 @@ pub fn load_all(dirs: &[PathBuf]) -> Result<Vec<Entry>> {
         }
 
         for path in Iter::new(std::iter::once(dir.to_path_buf())) {
-            match std::fs::read_to_string(&path) {
-                Ok(content) => {
-                    if let Ok(de) = DesktopEntry::from_str(&path, &content, None::<&[&str]>) {
-                        if let Some(entry) = Entry::from_desktop_entry(&de, &path) {
-                            // Skip NoDisplay entries
-                            if entry.no_display {
-                                continue;
-                            }
-
-                            // Deduplicate by ID (first one wins)
-                            if seen_ids.insert(entry.id.clone()) {
-                                entries.push(entry);
-                            }
-                        }
-                    }
-                }
+            let parsed = cache.get_or_parse(&path, |content| {
+                let de = parse_desktop_entry(&path, content)?;
+                Entry::from_desktop_entry(&de, &path, &options.locales)
+            });
+            let mut entry = match parsed {
+                Ok(Some(entry)) => entry,
+                Ok(None) => continue,
                 Err(e) => {
                     tracing::warn!("Failed to read {}: {}", path.display(), e);
+                    continue;
                 }
+            };
+            entry.id = desktop_file_id(dir, &path);
+
+            // Deduplicate by desktop file id: the first directory
+            // wins, as in the XDG spec. This happens before
+            // visibility checks so a user entry with Hidden=true or
+            // NoDisplay=true shadows the system one.
+            if let Some(winner) = seen_ids.get(&entry.id) {
+                tracing::debug!(
+                    "{} shadowed by {}",
+                    path.display(),
+                    winner.display()
+                );
+                shadowed += 1;
+                continue;
             }
+            seen_ids.insert(entry.id.clone(), path.clone());
+
+            if !entry.should_show(&options.desktops) {
+                if !options.show_hidden {
+                    continue;
+                }
+                entry.suppressed = true;
+            }
+
+            if let Some(ref try_exec) = entry.try_exec {
+                if !resolver.exists(try_exec) {
+                    tracing::debug!("TryExec not found for {}: {}", entry.id, try_exec);
+                    if options.hide_missing {
+                        continue;
+                    }
+                    entry.missing = true;
+                }
+            }
+
+            entries.push(entry);
         }
     }
 
+    tracing::info!(
+        "Scanned desktop entries in {:.1?} ({} cached, {} parsed)",
+        start.elapsed(),
+        cache.hits,
+        cache.parsed
+    );
+    cache.save();
+
+    if shadowed > 0 {
+        tracing::info!(
+            "{} duplicate desktop entries shadowed by earlier directories (RUST_LOG=debug lists them)",
+            shadowed
+        );
+    }
+
     // Sort by name
-    entries.sort_by(|a, b| a.name.to_lowercase().cmp(&b.name.to_lowercase()));
+    entries.sort_by_key(|e| e.name.to_lowercase());
 
     Ok(entries)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+
+    fn parse(content: &str, locales: &[&str]) -> Option<Entry> {
+        let path = Path::new("/test/applications/test.desktop");
+        let locales: Vec<String> = locales.iter().map(|l| l.to_string()).collect();
+        let de = parse_desktop_entry(path, content)?;
+        Entry::from_desktop_entry(&de, path, &locales)
+    }
+
+    const LOCALIZED: &str = "[Desktop Entry]
+Type=Application
+Name=Files
+Name[de]=Dateien
+Name[de_AT]=Dateien (AT)
+Comment=Browse files
+Comment[de]=Dateien durchsuchen
+Exec=files
+";
+
+    #[test]
+    fn test_locale_candidates() {
+        assert_eq!(
+            locale_candidates("de_DE.UTF-8@euro"),
+            vec!["de_DE@euro", "de_DE", "de@euro", "de"]
+        );
+        assert_eq!(locale_candidates("de_DE.UTF-8"), vec!["de_DE", "de"]);
+        assert_eq!(locale_candidates("sr@latin"), vec!["sr@latin", "sr"]);
+        assert_eq!(locale_candidates("fr"), vec!["fr"]);
+        assert!(locale_candidates("").is_empty());
+    }
+
+    #[test]
+    fn test_localized_name_and_comment() {
+        let entry = parse(LOCALIZED, &["de_DE", "de"]).unwrap();
+        assert_eq!(entry.name, "Dateien");
+        assert_eq!(entry.comment.as_deref(), Some("Dateien durchsuchen"));
+
+        // Full locale wins over language
+        let entry = parse(LOCALIZED, &["de_AT", "de"]).unwrap();
+        assert_eq!(entry.name, "Dateien (AT)");
+        assert_eq!(entry.comment.as_deref(), Some("Dateien durchsuchen"));
+    }
+
+    #[test]
+    fn test_unlocalized_fallback() {
+        let entry = parse(LOCALIZED, &["fr_FR", "fr"]).unwrap();
+        assert_eq!(entry.name, "Files");
+
+        // No locales forces the untranslated name
+        let entry = parse(LOCALIZED, &[]).unwrap();
+        assert_eq!(entry.name, "Files");
+        assert_eq!(entry.comment.as_deref(), Some("Browse files"));
+    }
+
+    #[test]
+    fn test_search_text_uses_localized_name() {
+        let entry = parse(LOCALIZED, &["de"]).unwrap();
+        assert!(entry.search_text().contains("Dateien"));
+    }
+
+    #[test]
+    fn test_localized_only_name() {
+        let content = "[Desktop Entry]
+Type=Application
+Name[de]=Rechner
+Exec=calc
+";
+        let entry = parse(content, &["de"]).unwrap();
+        assert_eq!(entry.name, "Rechner");
+
+        // Still loads for other locales, using the promoted value
+        let entry = parse(content, &[]).unwrap();
+        assert_eq!(entry.name, "Rechner");
+    }
+
+    fn desktops(names: &[&str]) -> Vec<String> {
+        names.iter().map(|d| d.to_string()).collect()
+    }
+
+    fn with_keys(keys: &str) -> Entry {
+        let content = format!("[Desktop Entry]\nType=Application\nName=App\nExec=app\n{}\n", keys);
+        parse(&content, &[]).unwrap()
+    }
+
+    #[test]
+    fn test_no_display_and_hidden() {
+        let gnome = desktops(&["GNOME"]);
+        assert!(with_keys("").should_show(&gnome));
+        assert!(!with_keys("NoDisplay=true").should_show(&gnome));
+        assert!(!with_keys("Hidden=true").should_show(&gnome));
+        assert!(with_keys("NoDisplay=false\nHidden=false").should_show(&gnome));
+    }
+
+    #[test]
+    fn test_only_show_in() {
+        let entry = with_keys("OnlyShowIn=KDE;LXQt;");
+        assert!(entry.should_show(&desktops(&["KDE"])));
+        assert!(entry.should_show(&desktops(&["ubuntu", "LXQt"])));
+        assert!(!entry.should_show(&desktops(&["GNOME"])));
+        // Unknown desktop: OnlyShowIn entries are not shown
+        assert!(!entry.should_show(&[]));
+    }
+
+    #[test]
+    fn test_not_show_in() {
+        let entry = with_keys("NotShowIn=GNOME;");
+        assert!(!entry.should_show(&desktops(&["GNOME"])));
+        assert!(!entry.should_show(&desktops(&["ubuntu", "GNOME"])));
+        assert!(entry.should_show(&desktops(&["niri"])));
+        assert!(entry.should_show(&[]));
+    }
+
+    #[test]
+    fn test_combined_show_in_rules() {
+        let entry = with_keys("OnlyShowIn=GNOME;KDE;\nNotShowIn=KDE;");
+        assert!(entry.should_show(&desktops(&["GNOME"])));
+        assert!(!entry.should_show(&desktops(&["KDE"])));
+
+        // NoDisplay wins over a matching OnlyShowIn
+        let entry = with_keys("OnlyShowIn=GNOME;\nNoDisplay=true");
+        assert!(!entry.should_show(&desktops(&["GNOME"])));
+    }
+
+    fn write_entry(dir: &Path, id: &str, keys: &str) {
+        let content = format!("[Desktop Entry]\nType=Application\nName={}\nExec={}\n{}\n", id, id, keys);
+        std::fs::write(dir.join(format!("{}.desktop", id)), content).unwrap();
+    }
+
+    #[test]
+    fn test_load_all_visibility() {
+        let dir = TempDir::new("visibility");
+        write_entry(&dir, "visible", "");
+        write_entry(&dir, "nodisplay", "NoDisplay=true");
+        write_entry(&dir, "hidden", "Hidden=true");
+        write_entry(&dir, "kdeonly", "OnlyShowIn=KDE;");
+        write_entry(&dir, "notgnome", "NotShowIn=GNOME;");
+
+        let mut options = LoadOptions {
+            desktops: desktops(&["GNOME"]),
+            ..Default::default()
+        };
+        let entries = load_all(&[dir.to_path_buf()], &options, None).unwrap();
+        let ids: Vec<_> = entries.iter().map(|e| e.id.as_str()).collect();
+        assert_eq!(ids, vec!["visible"]);
+
+        // show_hidden keeps everything, marking the normally hidden ones
+        options.show_hidden = true;
+        let entries = load_all(&[dir.to_path_buf()], &options, None).unwrap();
+        assert_eq!(entries.len(), 5);
+        for entry in &entries {
+            assert_eq!(entry.suppressed, entry.id != "visible", "{}", entry.id);
+        }
+    }
+
+    #[test]
+    fn test_hidden_user_entry_shadows_system_entry() {
+        let user = TempDir::new("shadow-user");
+        let system = TempDir::new("shadow-system");
+        write_entry(&user, "app", "Hidden=true");
+        write_entry(&system, "app", "");
+
+        let entries = load_all(&[user.to_path_buf(), system.to_path_buf()], &LoadOptions::default(), None).unwrap();
+        assert!(entries.is_empty());
+    }
+
+    #[test]
+    fn test_desktop_file_id() {
+        let base = Path::new("/usr/share/applications");
+        assert_eq!(desktop_file_id(base, &base.join("firefox.desktop")), "firefox");
+        assert_eq!(desktop_file_id(base, &base.join("kde4/konsole.desktop")), "kde4-konsole");
+        assert_eq!(
+            desktop_file_id(base, &base.join("org.gnome.Nautilus.desktop")),
+            "org.gnome.Nautilus"
+        );
+    }
+
+    #[test]
+    fn test_first_directory_wins() {
+        let user = TempDir::new("precedence-user");
+        let system = TempDir::new("precedence-system");
+        std::fs::write(
+            user.join("firefox.desktop"),
+            "[Desktop Entry]\nType=Application\nName=Firefox (user)\nExec=firefox --user\n",
+        )
+        .unwrap();
+        write_entry(&system, "firefox", "Comment=Web Browser");
+        std::fs::create_dir_all(system.join("kde4")).unwrap();
+        write_entry(&system.join("kde4"), "konsole", "");
+        write_entry(&system, "konsole", "");
+
+        let entries = load_all(&[user.to_path_buf(), system.to_path_buf()], &LoadOptions::default(), None).unwrap();
+        let firefox: Vec<_> = entries.iter().filter(|e| e.id == "firefox").collect();
+        assert_eq!(firefox.len(), 1);
+        // The user copy wins as a whole, keys are not merged
+        assert_eq!(firefox[0].name, "Firefox (user)");
+        assert_eq!(firefox[0].comment, None);
+
+        // Same file name in a subdirectory is a different id
+        let mut ids: Vec<_> = entries.iter().map(|e| e.id.as_str()).collect();
+        ids.sort();
+        assert_eq!(ids, vec!["firefox", "kde4-konsole", "konsole"]);
+    }
+
+    #[test]
+    fn test_exec_resolver() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = TempDir::new("resolver");
+        let bin = dir.join("mytool");
+        std::fs::write(&bin, "#!/bin/sh\n").unwrap();
+        std::fs::set_permissions(&bin, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let plain = dir.join("notexec");
+        std::fs::write(&plain, "").unwrap();
+        std::fs::set_permissions(&plain, std::fs::Permissions::from_mode(0o644)).unwrap();
+
+        let mut resolver = ExecResolver::new(vec![dir.to_path_buf()]);
+        assert!(resolver.exists("mytool"));
+        assert!(resolver.exists(bin.to_str().unwrap()));
+        assert!(!resolver.exists("notexec"));
+        assert!(!resolver.exists("does-not-exist"));
+
+        // Cached result is reused even after the file disappears
+        std::fs::remove_file(&bin).unwrap();
+        assert!(resolver.exists("mytool"));
+    }
+
+    #[test]
+    fn test_load_all_try_exec() {
+        let dir = TempDir::new("tryexec");
+        write_entry(&dir, "present", "TryExec=/bin/sh");
+        write_entry(&dir, "absent", "TryExec=/nonexistent/drun-test-binary");
+
+        let mut options = LoadOptions {
+            hide_missing: true,
+            ..Default::default()
+        };
+        let entries = load_all(&[dir.to_path_buf()], &options, None).unwrap();
+        let ids: Vec<_> = entries.iter().map(|e| e.id.as_str()).collect();
+        assert_eq!(ids, vec!["present"]);
+
+        options.hide_missing = false;
+        let entries = load_all(&[dir.to_path_buf()], &options, None).unwrap();
+        assert_eq!(entries.len(), 2);
+        let absent = entries.iter().find(|e| e.id == "absent").unwrap();
+        assert!(absent.missing);
+        let present = entries.iter().find(|e| e.id == "present").unwrap();
+        assert!(!present.missing);
+    }
+
+    fn exec_entry(exec: &str) -> Entry {
+        Entry {
+            id: "app".to_string(),
+            name: "My App".to_string(),
+            exec: Some(exec.to_string()),
+            icon: Some("app-icon".to_string()),
+            path: PathBuf::from("/usr/share/applications/app.desktop"),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_command_strips_file_codes() {
+        assert_eq!(exec_entry("gimp %U").command().unwrap(), "gimp");
+        assert_eq!(exec_entry("firefox %u").command().unwrap(), "firefox");
+        assert_eq!(
+            exec_entry("code --new-window %F").command().unwrap(),
+            "code --new-window"
+        );
+        assert_eq!(exec_entry("evince %f").command().unwrap(), "evince");
+    }
+
+    #[test]
+    fn test_command_expands_icon_name_and_path() {
+        assert_eq!(
+            exec_entry("app %i --name %c").command().unwrap(),
+            "app --icon app-icon --name 'My App'"
+        );
+        assert_eq!(
+            exec_entry("app --desktop-file=%k").command().unwrap(),
+            "app --desktop-file=/usr/share/applications/app.desktop"
+        );
+
+        // %i without an Icon key expands to nothing
+        let mut entry = exec_entry("app %i");
+        entry.icon = None;
+        assert_eq!(entry.command().unwrap(), "app");
+    }
+
+    #[test]
+    fn test_command_quoted_arguments() {
+        // Quoted path with spaces
+        assert_eq!(
+            exec_entry(r#""/opt/My App/bin/app" --flag %f"#).command().unwrap(),
+            "'/opt/My App/bin/app' --flag"
+        );
+
+        // Field code inside a quoted shell script expands to nothing
+        assert_eq!(
+            exec_entry(r#"sh -c "foo %U""#).command().unwrap(),
+            "sh -c 'foo '"
+        );
+
+        // Backslash escapes inside quotes
+        assert_eq!(
+            exec_entry(r#"sh -c "echo \"hi\" \$HOME it's""#).command().unwrap(),
+            r#"sh -c 'echo "hi" $HOME it'\''s'"#
+        );
+    }
+
+    #[test]
+    fn test_command_percent_literals() {
+        assert_eq!(exec_entry("echo 100%%").command().unwrap(), "echo 100%");
+        // Unknown codes are kept (common in embedded scripts)
+        assert_eq!(
+            exec_entry(r#"sh -c "date +%Y""#).command().unwrap(),
+            "sh -c 'date +%Y'"
+        );
+    }
+
+    #[test]
+    fn test_argv() {
+        assert_eq!(
+            exec_entry(r#""/opt/My App/bin/app" --name "%c" %U"#).argv().unwrap(),
+            ["/opt/My App/bin/app", "--name", "My App"]
+        );
+        assert_eq!(
+            exec_entry(r#"sh -c "echo \"hi\" it's""#).argv().unwrap(),
+            ["sh", "-c", r#"echo "hi" it's"#]
+        );
+        assert_eq!(exec_entry(r#"app "unterminated"#).argv(), None);
+
+        let custom = Entry {
+            custom: true,
+            ..exec_entry("ls -la")
+        };
+        assert_eq!(custom.argv(), None);
+    }
+
+    #[test]
+    fn test_command_malformed_exec() {
+        // Unterminated quote falls back to whitespace splitting
+        assert_eq!(
+            exec_entry(r#"app "unterminated %f"#).command().unwrap(),
+            r#"app "unterminated"#
+        );
+    }
+}
@@ -0,0 +1,140 @@
+//! dmenu mode (`--dmenu`): pick one of the lines read from stdin
+//!
+//! Like `rofi -dmenu`, drun becomes a generic picker for scripts. The
+//! lines become entries in the usual grid and fuzzy filter, with no
+//! desktop entries, icons or compositor around. Enter picks instead of
+//! running anything; `main` prints the pick to stdout once the terminal is
+//! restored. The UI is drawn on `/dev/tty`, so stdout carries only the
+//! result.
+
+use std::io::BufRead;
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+
+use crate::desktop_entry::Entry;
+
+/// Id prefix of dmenu entries; the rest is the line, so history follows
+/// the text rather than its position in the input
+pub const DMENU_PREFIX: &str = "dmenu:";
+
+/// A dmenu run: the input and what was picked
+#[derive(Debug, Default)]
+pub struct Dmenu {
+    /// Input lines, in order
+    lines: Vec<String>,
+    picked: Option<Pick>,
+}
+
+/// What Enter picked
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Pick {
+    /// Index of an input line
+    Line(usize),
+    /// Filter text that matched nothing (or Alt+Enter)
+    Typed(String),
+}
+
+impl Dmenu {
+    pub fn new(lines: Vec<String>) -> Self {
+        Self { lines, picked: None }
+    }
+
+    /// An entry for each line, in input order
+    pub fn entries(&self) -> Vec<Entry> {
+        self.lines
+            .iter()
+            .map(|line| Entry {
+                id: format!("{}{}", DMENU_PREFIX, line),
+                name: line.clone(),
+                custom: true,
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    /// Pick the line of `entry` (the first one, for repeated lines)
+    pub fn pick_entry(&mut self, entry: &Entry) {
+        let line = entry.id.strip_prefix(DMENU_PREFIX).unwrap_or(&entry.name);
+        self.picked = match self.lines.iter().position(|l| l == line) {
+            Some(index) => Some(Pick::Line(index)),
+            None => Some(Pick::Typed(line.to_string())),
+        };
+    }
+
+    pub fn pick_typed(&mut self, text: &str) {
+        self.picked = Some(Pick::Typed(text.to_string()));
+    }
+
+    /// What to print for the pick: the line, or with `index` its
+    /// zero-based index (-1 for typed text, as rofi does)
+    pub fn output(&self, index: bool) -> Option<String> {
+        Some(match (self.picked.as_ref()?, index) {
+            (Pick::Line(i), false) => self.lines[*i].clone(),
+            (Pick::Line(i), true) => i.to_string(),
+            (Pick::Typed(text), false) => text.clone(),
+            (Pick::Typed(_), true) => "-1".to_string(),
+        })
+    }
+}
+
+/// Options from `reader`, one per line; blank lines are skipped
+pub fn read_lines(reader: impl BufRead) -> Result<Vec<String>> {
+    let mut lines = Vec::new();
+    for line in reader.lines() {
+        let line = line.context("Failed to read options from stdin")?;
+        let line = line.trim_end_matches('\r');
+        if !line.trim().is_empty() {
+            lines.push(line.to_string());
+        }
+    }
+    Ok(lines)
+}
+
+/// History file of `--dmenu-history <name>`
+pub fn history_path(name: &str) -> Result<PathBuf> {
+    if name.is_empty() || name.contains('/') || name.starts_with('.') {
+        anyhow::bail!("Invalid dmenu history name \"{}\"", name);
+    }
+    Ok(crate::history::state_dir().join("dmenu").join(format!("{}.json", name)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_lines() {
+        let input = "firefox\r\n\n  \nfoot --server\nfirefox\n";
+        let lines = read_lines(input.as_bytes()).unwrap();
+        assert_eq!(lines, ["firefox", "foot --server", "firefox"]);
+    }
+
+    #[test]
+    fn test_pick_output() {
+        let mut dmenu = Dmenu::new(vec!["a".to_string(), "b b".to_string(), "a".to_string()]);
+        assert_eq!(dmenu.output(false), None);
+
+        let entries = dmenu.entries();
+        assert_eq!(entries[1].name, "b b");
+        dmenu.pick_entry(&entries[1]);
+        assert_eq!(dmenu.output(false).as_deref(), Some("b b"));
+        assert_eq!(dmenu.output(true).as_deref(), Some("1"));
+
+        // Repeated lines give the first index
+        dmenu.pick_entry(&entries[2]);
+        assert_eq!(dmenu.output(true).as_deref(), Some("0"));
+
+        dmenu.pick_typed("c");
+        assert_eq!(dmenu.output(false).as_deref(), Some("c"));
+        assert_eq!(dmenu.output(true).as_deref(), Some("-1"));
+    }
+
+    #[test]
+    fn test_history_path() {
+        let path = history_path("power-menu").unwrap();
+        assert!(path.ends_with("darkwall-drun/dmenu/power-menu.json"));
+        assert!(history_path("../history").is_err());
+        assert!(history_path("").is_err());
+    }
+}
@@ -0,0 +1,240 @@
+//! On-disk cache of parsed desktop entries.
+//!
+//! Parsing ~1000 desktop files dominates cold start. After a scan the
+//! parsed entries are written to `~/.cache/darkwall-drun/entries.json`,
+//! keyed by path and validated by each file's mtime and size, so the next
+//! launch only re-parses files that changed.
+//!
+//! The cache stores entries as they come out of the parser, before
+//! visibility or TryExec filtering, so those are re-evaluated on every load.
+//! Unreadable, corrupted or version-mismatched cache files are discarded
+//! and rebuilt.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use crate::desktop_entry::Entry;
+
+/// Bump when the cache format or `Entry` changes meaning
+const CACHE_VERSION: u32 = 2;
+
+/// Default cache location
+pub fn default_path() -> Option<PathBuf> {
+    dirs::cache_dir().map(|d| d.join("darkwall-drun").join("entries.json"))
+}
+
+/// Modification time and size used to detect changed files
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+struct FileStamp {
+    mtime_secs: u64,
+    mtime_nanos: u32,
+    size: u64,
+}
+
+impl FileStamp {
+    fn of(path: &Path) -> Option<Self> {
+        let meta = std::fs::metadata(path).ok()?;
+        let mtime = meta.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+        Some(Self {
+            mtime_secs: mtime.as_secs(),
+            mtime_nanos: mtime.subsec_nanos(),
+            size: meta.len(),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CachedFile {
+    stamp: FileStamp,
+    /// None for files that didn't produce an entry (e.g. no Exec)
+    entry: Option<Entry>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CacheFile {
+    version: u32,
+    /// Locales the entries were localized with
+    locales: Vec<String>,
+    files: HashMap<PathBuf, CachedFile>,
+}
+
+/// Cache used during a single `load_all` scan
+#[derive(Debug, Default)]
+pub struct EntryCache {
+    path: Option<PathBuf>,
+    locales: Vec<String>,
+    /// Entries read from disk
+    previous: HashMap<PathBuf, CachedFile>,
+    /// Entries seen during this scan (written back on save)
+    current: HashMap<PathBuf, CachedFile>,
+    /// Number of entries served from the cache
+    pub hits: usize,
+    /// Number of files parsed
+    pub parsed: usize,
+}
+
+impl EntryCache {
+    /// Load the cache at `path`, or start empty if it's missing or invalid
+    ///
+    /// `path` of None disables caching entirely.
+    pub fn load(path: Option<&Path>, locales: &[String]) -> Self {
+        let previous = path
+            .and_then(|p| std::fs::read(p).ok())
+            .and_then(|data| match serde_json::from_slice::<CacheFile>(&data) {
+                Ok(file) if file.version == CACHE_VERSION && file.locales == locales => {
+                    Some(file.files)
+                }
+                Ok(_) => {
+                    tracing::debug!("Entry cache is stale, rebuilding");
+                    None
+                }
+                Err(e) => {
+                    tracing::debug!("Discarding corrupted entry cache: {}", e);
+                    None
+                }
+            })
+            .unwrap_or_default();
+
+        Self {
+            path: path.map(Path::to_path_buf),
+            locales: locales.to_vec(),
+            previous,
+            ..Default::default()
+        }
+    }
+
+    /// Get the entry for `path` from the cache, or parse it with `parse`
+    ///
+    /// `parse` receives the file contents. Read errors are returned so the
+    /// caller can log them.
+    pub fn get_or_parse(
+        &mut self,
+        path: &Path,
+        parse: impl FnOnce(&str) -> Option<Entry>,
+    ) -> std::io::Result<Option<Entry>> {
+        let stamp = FileStamp::of(path);
+
+        if let (Some(stamp), Some(cached)) = (stamp, self.previous.remove(path)) {
+            if cached.stamp == stamp {
+                self.hits += 1;
+                let entry = cached.entry.clone();
+                self.current.insert(path.to_path_buf(), cached);
+                return Ok(entry);
+            }
+        }
+
+        let content = std::fs::read_to_string(path)?;
+        let entry = parse(&content);
+        self.parsed += 1;
+
+        if let Some(stamp) = stamp {
+            self.current.insert(
+                path.to_path_buf(),
+                CachedFile {
+                    stamp,
+                    entry: entry.clone(),
+                },
+            );
+        }
+        Ok(entry)
+    }
+
+    /// Write the cache back if anything changed
+    ///
+    /// Failures are logged and otherwise ignored; the cache is only an
+    /// optimization.
+    pub fn save(self) {
+        let Some(path) = self.path else {
+            return;
+        };
+        // Nothing parsed and no files removed: the file on disk is current
+        if self.parsed == 0 && self.previous.is_empty() {
+            return;
+        }
+
+        let file = CacheFile {
+            version: CACHE_VERSION,
+            locales: self.locales,
+            files: self.current,
+        };
+        if let Err(e) = write_atomic(&path, &file) {
+            tracing::warn!("Failed to write entry cache {}: {}", path.display(), e);
+        }
+    }
+}
+
+/// Write via a temporary file so a crash never leaves a truncated cache
+fn write_atomic(path: &Path, file: &CacheFile) -> anyhow::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let tmp = path.with_extension("json.tmp");
+    std::fs::write(&tmp, serde_json::to_vec(file)?)?;
+    std::fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+
+    fn parse_name(content: &str) -> Option<Entry> {
+        let name = content.lines().find_map(|l| l.strip_prefix("Name="))?;
+        Some(Entry {
+            name: name.to_string(),
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_cache_reuses_unchanged_files() {
+        let dir = TempDir::new("cache-reuse");
+        let cache_path = dir.join("entries.json");
+        let desktop = dir.join("app.desktop");
+        std::fs::write(&desktop, "Name=App\n").unwrap();
+
+        let mut cache = EntryCache::load(Some(&cache_path), &[]);
+        let entry = cache.get_or_parse(&desktop, parse_name).unwrap().unwrap();
+        assert_eq!(entry.name, "App");
+        assert_eq!((cache.hits, cache.parsed), (0, 1));
+        cache.save();
+
+        let mut cache = EntryCache::load(Some(&cache_path), &[]);
+        let entry = cache
+            .get_or_parse(&desktop, |_| panic!("should not re-parse"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(entry.name, "App");
+        assert_eq!((cache.hits, cache.parsed), (1, 0));
+
+        // A different size invalidates the cached entry
+        std::fs::write(&desktop, "Name=Renamed\n").unwrap();
+        let mut cache = EntryCache::load(Some(&cache_path), &[]);
+        let entry = cache.get_or_parse(&desktop, parse_name).unwrap().unwrap();
+        assert_eq!(entry.name, "Renamed");
+        assert_eq!(cache.parsed, 1);
+    }
+
+    #[test]
+    fn test_corrupted_or_mismatched_cache_is_discarded() {
+        let dir = TempDir::new("cache-corrupt");
+        let cache_path = dir.join("entries.json");
+        let desktop = dir.join("app.desktop");
+        std::fs::write(&desktop, "Name=App\n").unwrap();
+
+        std::fs::write(&cache_path, b"\x00not json").unwrap();
+        let mut cache = EntryCache::load(Some(&cache_path), &[]);
+        assert!(cache.get_or_parse(&desktop, parse_name).unwrap().is_some());
+        assert_eq!(cache.parsed, 1);
+        cache.save();
+
+        // Entries localized for another locale are not reused
+        let locales = vec!["de".to_string()];
+        let mut cache = EntryCache::load(Some(&cache_path), &locales);
+        cache.get_or_parse(&desktop, parse_name).unwrap();
+        assert_eq!((cache.hits, cache.parsed), (0, 1));
+    }
+}
@@ -0,0 +1,249 @@
+//! Programs on `$PATH` as launcher entries (run mode)
+//!
+//! Like rofi's `run` mode: `--mode run`, or a filter starting with `>`,
+//! lists the executables on `$PATH` instead of applications. Each becomes
+//! an `Entry` with a `run:<name>` id, so history keeps them apart from
+//! desktop entries, and runs through the usual terminal mode detection
+//! like a typed command.
+//!
+//! A cold scan stats thousands of files, so it runs on a background thread
+//! that sends each directory's programs as soon as they are known. The
+//! names found in each directory are cached in
+//! `~/.cache/darkwall-drun/executables.json` and only re-read when the
+//! directory's mtime changes (a program was added, removed or renamed).
+
+use std::collections::{HashMap, HashSet};
+use std::ffi::OsStr;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+
+use crate::desktop_entry::{shell_quote, Entry};
+
+/// Id prefix of executable entries
+pub const RUN_PREFIX: &str = "run:";
+
+/// Bump when the cache format changes
+const CACHE_VERSION: u32 = 1;
+
+/// Whether `entry` is a program from `$PATH`
+pub fn is_executable(entry: &Entry) -> bool {
+    entry.id.starts_with(RUN_PREFIX)
+}
+
+/// Default cache location
+pub fn default_cache_path() -> Option<PathBuf> {
+    dirs::cache_dir().map(|d| d.join("darkwall-drun").join("executables.json"))
+}
+
+/// Entry for the program `name` in `dir`
+fn entry(name: &str, dir: &Path) -> Entry {
+    Entry {
+        id: format!("{}{}", RUN_PREFIX, name),
+        name: name.to_string(),
+        comment: Some(dir.join(name).display().to_string()),
+        exec: Some(shell_quote(name).into_owned()),
+        // Detected from the command line alone, like typed commands
+        terminal: true,
+        custom: true,
+        ..Default::default()
+    }
+}
+
+/// Directories of `path` (a `$PATH` value) in order, without duplicates
+pub fn path_dirs(path: &OsStr) -> Vec<PathBuf> {
+    let mut seen = HashSet::new();
+    std::env::split_paths(path)
+        .filter(|dir| !dir.as_os_str().is_empty() && seen.insert(dir.clone()))
+        .collect()
+}
+
+/// Scan `dirs` on a background thread
+///
+/// Each message holds the programs of one directory that no earlier
+/// directory had; the channel closes when the scan is done.
+pub fn spawn_scan(dirs: Vec<PathBuf>, cache_path: Option<PathBuf>) -> mpsc::UnboundedReceiver<Vec<Entry>> {
+    let (tx, rx) = mpsc::unbounded_channel();
+    std::thread::spawn(move || {
+        let count = scan(&dirs, cache_path.as_deref(), |entries| {
+            let _ = tx.send(entries);
+        });
+        tracing::info!("Found {} programs on PATH", count);
+    });
+    rx
+}
+
+/// Scan `dirs` in order, passing each directory's new programs to `found`
+///
+/// A name only counts in the first directory that has it, as in a `$PATH`
+/// lookup. Returns the number of programs found.
+pub fn scan(dirs: &[PathBuf], cache_path: Option<&Path>, mut found: impl FnMut(Vec<Entry>)) -> usize {
+    let mut cache = cache_path.map(load_cache).unwrap_or_default();
+    let mut changed = false;
+    let mut seen = HashSet::new();
+
+    for dir in dirs {
+        let Some(mtime) = dir_mtime(dir) else {
+            continue;
+        };
+        let names = match cache.dirs.get(dir) {
+            Some(cached) if cached.mtime == mtime => cached.names.clone(),
+            _ => {
+                let names = list_dir(dir);
+                cache.dirs.insert(dir.clone(), CachedDir { mtime, names: names.clone() });
+                changed = true;
+                names
+            }
+        };
+        let entries: Vec<Entry> = names
+            .iter()
+            .filter(|name| seen.insert(name.to_string()))
+            .map(|name| entry(name, dir))
+            .collect();
+        if !entries.is_empty() {
+            found(entries);
+        }
+    }
+
+    // Directories no longer on PATH are dropped from the cache
+    let before = cache.dirs.len();
+    cache.dirs.retain(|dir, _| dirs.contains(dir));
+    if let Some(path) = cache_path.filter(|_| changed || cache.dirs.len() != before) {
+        if let Err(e) = save_cache(path, &cache) {
+            tracing::warn!("Failed to write executable cache {}: {}", path.display(), e);
+        }
+    }
+    seen.len()
+}
+
+/// Executable files in `dir` (following symlinks), sorted by name
+fn list_dir(dir: &Path) -> Vec<String> {
+    let Ok(read_dir) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut names: Vec<String> = read_dir
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| {
+            std::fs::metadata(entry.path())
+                .is_ok_and(|meta| meta.is_file() && meta.permissions().mode() & 0o111 != 0)
+        })
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .filter(|name| !name.starts_with('.'))
+        .collect();
+    names.sort();
+    names
+}
+
+/// Modification time of `dir` in nanoseconds since the epoch
+fn dir_mtime(dir: &Path) -> Option<u128> {
+    let modified = std::fs::metadata(dir).ok()?.modified().ok()?;
+    Some(modified.duration_since(UNIX_EPOCH).ok()?.as_nanos())
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CacheFile {
+    version: u32,
+    dirs: HashMap<PathBuf, CachedDir>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CachedDir {
+    mtime: u128,
+    names: Vec<String>,
+}
+
+/// The cache at `path`; empty when missing, corrupted or outdated
+fn load_cache(path: &Path) -> CacheFile {
+    std::fs::read(path)
+        .ok()
+        .and_then(|data| serde_json::from_slice::<CacheFile>(&data).ok())
+        .filter(|cache| cache.version == CACHE_VERSION)
+        .unwrap_or_default()
+}
+
+/// Write via a temporary file so a crash never leaves a truncated cache
+fn save_cache(path: &Path, cache: &CacheFile) -> anyhow::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let file = CacheFile {
+        version: CACHE_VERSION,
+        dirs: cache.dirs.clone(),
+    };
+    let tmp = path.with_extension("json.tmp");
+    std::fs::write(&tmp, serde_json::to_vec(&file)?)?;
+    std::fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+
+    fn program(dir: &Path, name: &str, mode: u32) {
+        let path = dir.join(name);
+        std::fs::write(&path, "#!/bin/sh\n").unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(mode)).unwrap();
+    }
+
+    fn scan_names(dirs: &[PathBuf], cache: Option<&Path>) -> Vec<(String, String)> {
+        let mut found = Vec::new();
+        scan(dirs, cache, |entries| {
+            found.extend(entries.into_iter().map(|e| (e.name, e.comment.unwrap())));
+        });
+        found
+    }
+
+    #[test]
+    fn test_scan_path() {
+        let root = TempDir::new("path-scan");
+        let (bin, local) = (root.join("bin"), root.join("local"));
+        std::fs::create_dir_all(&bin).unwrap();
+        std::fs::create_dir_all(&local).unwrap();
+        program(&local, "btop", 0o755);
+        program(&bin, "btop", 0o755);
+        program(&bin, "wev", 0o700);
+        program(&bin, "README", 0o644);
+        program(&bin, ".hidden", 0o755);
+        std::fs::create_dir(bin.join("subdir")).unwrap();
+
+        let path = std::env::join_paths([&local, &bin, &local, &root.join("missing")]).unwrap();
+        let dirs = path_dirs(&path);
+        assert_eq!(dirs.len(), 3);
+
+        // The first directory on PATH wins
+        let found = scan_names(&dirs, None);
+        let btop = local.join("btop").display().to_string();
+        let wev = bin.join("wev").display().to_string();
+        assert_eq!(found, [("btop".to_string(), btop), ("wev".to_string(), wev)]);
+
+        let entry = entry("my tool", &bin);
+        assert!(is_executable(&entry));
+        assert_eq!(entry.id, "run:my tool");
+        assert_eq!(entry.command().as_deref(), Some("'my tool'"));
+    }
+
+    #[test]
+    fn test_scan_cache_follows_dir_mtime() {
+        let root = TempDir::new("path-cache");
+        let bin = root.join("bin");
+        std::fs::create_dir_all(&bin).unwrap();
+        program(&bin, "btop", 0o755);
+        let cache = root.join("executables.json");
+        let dirs = vec![bin.clone()];
+        assert_eq!(scan_names(&dirs, Some(&cache)).len(), 1);
+
+        // Changing a file doesn't touch the directory: the cache is used
+        std::fs::set_permissions(bin.join("btop"), std::fs::Permissions::from_mode(0o644)).unwrap();
+        assert_eq!(scan_names(&dirs, Some(&cache)).len(), 1);
+
+        // Adding a file does, so the directory is read again
+        program(&bin, "wev", 0o755);
+        let names: Vec<_> = scan_names(&dirs, Some(&cache)).into_iter().map(|(name, _)| name).collect();
+        assert_eq!(names, ["wev"]);
+    }
+}
@@ -2,9 +2,129 @@
 //!
 //! TEAM_000: Phase 2, Units 2.2-2.4
 
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use regex::Regex;
+
 use crate::desktop_entry::Entry;
 use crate::pty::ExitStatus;
 
+/// Resolve the working directory for a launched command
+///
+/// Returns the directory if it exists. A missing directory is logged and
+/// replaced with the home directory so the launch still succeeds.
+pub fn resolve_working_dir(dir: Option<&Path>) -> Option<PathBuf> {
+    let dir = dir?;
+    if dir.is_dir() {
+        return Some(dir.to_path_buf());
+    }
+    tracing::warn!(
+        "Working directory {} does not exist, using home directory",
+        dir.display()
+    );
+    dirs::home_dir()
+}
+
+/// Parse an `X-DarkwallEnv` value of the form `KEY=VAL;KEY2=VAL2`
+///
+/// Empty segments are ignored and an empty value (`KEY=`) is kept. Pairs
+/// without `=` or with an empty key are malformed and skipped with a warning.
+pub fn parse_env_pairs(spec: &str) -> Vec<(String, String)> {
+    let mut pairs = Vec::new();
+    for segment in spec.split(';') {
+        let segment = segment.trim();
+        if segment.is_empty() {
+            continue;
+        }
+        match segment.split_once('=') {
+            Some((key, value)) if !key.trim().is_empty() => {
+                pairs.push((key.trim().to_string(), value.to_string()));
+            }
+            _ => tracing::warn!("Ignoring malformed environment pair: {}", segment),
+        }
+    }
+    pairs
+}
+
+/// Expand `$VAR` and `${VAR}` against drun's own environment
+///
+/// Unset variables expand to an empty string, like in a shell.
+pub fn expand_env_value(value: &str) -> String {
+    shellexpand::env_with_context_no_errors(value, |var| {
+        Some(std::env::var(var).unwrap_or_default())
+    })
+    .into_owned()
+}
+
+/// Build the extra environment for a launch
+///
+/// The global `[env]` table from config is applied first, then the entry's
+/// `X-DarkwallEnv` pairs, so entries can override global values.
+pub fn launch_env(global: &BTreeMap<String, String>, entry: Option<&Entry>) -> Vec<(String, String)> {
+    let mut env: Vec<(String, String)> = global
+        .iter()
+        .map(|(key, value)| (key.clone(), expand_env_value(value)))
+        .collect();
+
+    if let Some(spec) = entry.and_then(|e| e.get_darkwall_field("Env")) {
+        for (key, value) in parse_env_pairs(spec) {
+            let value = expand_env_value(&value);
+            env.retain(|(k, _)| *k != key);
+            env.push((key, value));
+        }
+    }
+
+    env
+}
+
+/// How to launch a command: shell, working directory and extra environment
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LaunchOptions {
+    /// Shell and its arguments; the command line is appended as the last argument
+    pub shell: Vec<String>,
+    /// Working directory (None inherits drun's)
+    pub working_dir: Option<PathBuf>,
+    /// Extra environment from config [env] and X-DarkwallEnv
+    pub env: Vec<(String, String)>,
+}
+
+impl Default for LaunchOptions {
+    fn default() -> Self {
+        Self {
+            shell: vec!["sh".to_string(), "-c".to_string()],
+            working_dir: None,
+            env: Vec::new(),
+        }
+    }
+}
+
+impl LaunchOptions {
+    /// Full argv for running `cmd` through the shell
+    pub fn argv(&self, cmd: &str) -> Vec<String> {
+        let mut argv = self.shell.clone();
+        argv.push(cmd.to_string());
+        argv
+    }
+
+    /// Build a `std::process::Command` for `cmd`
+    ///
+    /// `prefix` is prepended to the shell (e.g. an external terminal's
+    /// `kitty -e`).
+    pub fn command(&self, prefix: &[String], cmd: &str) -> std::process::Command {
+        let mut argv = prefix.iter().cloned().chain(self.argv(cmd));
+        // shell is validated non-empty at config load
+        let program = argv.next().unwrap_or_else(|| "sh".to_string());
+        let mut command = std::process::Command::new(program);
+        command.args(argv);
+        if let Some(ref dir) = self.working_dir {
+            command.current_dir(dir);
+        }
+        command.envs(self.env.iter().cloned());
+        command
+    }
+}
+
 /// Terminal mode determines how a command should be executed
 /// TEAM_000: Phase 4, Unit 4.1 - Terminal Mode Schema
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -36,18 +156,75 @@ impl std::str::FromStr for TerminalMode {
     }
 }
 
+/// Name as written in config (`terminal_mode = "long-running"`)
+impl std::fmt::Display for TerminalMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            TerminalMode::Gui => "gui",
+            TerminalMode::Oneshot => "oneshot",
+            TerminalMode::Interactive => "interactive",
+            TerminalMode::Tui => "tui",
+            TerminalMode::LongRunning => "long-running",
+        })
+    }
+}
+
+/// User-configured terminal mode rules (`[terminal_mode]` in config)
+///
+/// Consulted by `TerminalMode::detect` before the builtin app lists.
+#[derive(Debug, Clone, Default)]
+pub struct ModeRules {
+    /// Regex rules matched against the whole command line, first match wins
+    pub patterns: Vec<(Regex, TerminalMode)>,
+    /// Extra full screen apps
+    pub tui: Vec<String>,
+    /// Extra REPL-style apps
+    pub interactive: Vec<String>,
+    /// Substrings marking a command as long-running
+    pub long_running_patterns: Vec<String>,
+}
+
+impl ModeRules {
+    /// Mode for `cmd` (with base command `base_cmd`) if a user rule matches
+    fn detect(&self, cmd: &str, base_cmd: &str) -> Option<TerminalMode> {
+        if let Some((_, mode)) = self.patterns.iter().find(|(re, _)| re.is_match(cmd)) {
+            return Some(*mode);
+        }
+        if self.tui.iter().any(|app| is_app(base_cmd, app)) {
+            return Some(TerminalMode::Tui);
+        }
+        if self.interactive.iter().any(|app| is_app(base_cmd, app)) {
+            return Some(TerminalMode::Interactive);
+        }
+        if self.long_running_patterns.iter().any(|p| cmd.contains(p.as_str())) {
+            return Some(TerminalMode::LongRunning);
+        }
+        None
+    }
+}
+
+/// Check exact match OR if base_cmd ends with the app name (for Nix wrapper
+/// scripts like "desktop-btop")
+fn is_app(base_cmd: &str, app: &str) -> bool {
+    base_cmd == app
+        || base_cmd
+            .strip_suffix(app)
+            .is_some_and(|prefix| prefix.ends_with('-'))
+}
+
 impl TerminalMode {
     /// Detect terminal mode from command and optional desktop entry
     /// 
     /// Detection priority:
     /// 1. X-DarkwallTerminalMode custom field (explicit override)
     /// 2. Terminal=false in desktop entry → Gui (launch detached)
-    /// 3. Known TUI apps → Tui
-    /// 4. Known interactive apps → Interactive
-    /// 5. Long-running patterns → LongRunning
-    /// 6. Terminal=true fallback → Interactive
-    /// 7. Default (raw command, no entry) → Oneshot
-    pub fn detect(cmd: &str, entry: Option<&Entry>) -> Self {
+    /// 3. User rules from `[terminal_mode]` config
+    /// 4. Known TUI apps → Tui
+    /// 5. Known interactive apps → Interactive
+    /// 6. Long-running patterns → LongRunning
+    /// 7. Terminal=true fallback → Interactive
+    /// 8. Default (raw command, no entry) → Oneshot
+    pub fn detect(cmd: &str, entry: Option<&Entry>, rules: &ModeRules) -> Self {
         // 1. Check custom X-DarkwallTerminalMode field first (explicit override)
         if let Some(entry) = entry {
             if let Some(mode) = entry.get_darkwall_field("TerminalMode") {
@@ -76,7 +253,12 @@ impl TerminalMode {
             .next()
             .unwrap_or("");
 
-        // 4. Known TUI apps (full screen)
+        // 4. User rules take precedence over the builtin lists
+        if let Some(mode) = rules.detect(cmd, base_cmd) {
+            return mode;
+        }
+
+        // 5. Known TUI apps (full screen)
         const TUI_APPS: &[&str] = &[
             "htop", "btop", "top", "vim", "nvim", "neovim", "nano", "less", "man",
             "mc", "ranger", "nnn", "lf", "vifm", "tmux", "screen",
@@ -85,12 +267,11 @@ impl TerminalMode {
             // Nested TUI launchers - need full terminal handover
             "drun", "rofi", "dmenu", "wofi", "fuzzel", "tofi",
         ];
-        // Check exact match OR if base_cmd ends with the app name (for Nix wrapper scripts like "desktop-btop")
-        if TUI_APPS.iter().any(|&app| base_cmd == app || base_cmd.ends_with(&format!("-{}", app))) {
+        if TUI_APPS.iter().any(|app| is_app(base_cmd, app)) {
             return TerminalMode::Tui;
         }
 
-        // 5. Known interactive commands (REPL-style)
+        // 6. Known interactive commands (REPL-style)
         const INTERACTIVE: &[&str] = &[
             "bash", "zsh", "fish", "sh", "dash",
             "python", "python3", "ipython", "bpython",
@@ -102,27 +283,49 @@ impl TerminalMode {
             "psql", "mysql", "sqlite3",
             "redis-cli", "mongosh",
         ];
-        if INTERACTIVE.iter().any(|&app| base_cmd == app) {
+        if INTERACTIVE.contains(&base_cmd) {
             return TerminalMode::Interactive;
         }
 
-        // 6. Long-running patterns
+        // 7. Long-running patterns
         if cmd.contains("watch ") || cmd.contains("tail -f") || cmd.contains("journalctl -f") {
             return TerminalMode::LongRunning;
         }
 
-        // 7. If we have an entry with Terminal=true but didn't match above, default to Interactive
+        // 8. If we have an entry with Terminal=true but didn't match above, default to Interactive
         if let Some(entry) = entry {
             if entry.terminal {
                 return TerminalMode::Interactive;
             }
         }
 
-        // 8. Default for raw commands (no entry) → Oneshot
+        // 9. Default for raw commands (no entry) → Oneshot
         TerminalMode::Oneshot
     }
 }
 
+impl TerminalMode {
+    /// Mode for a command run through the elevation prefix `prefix`
+    ///
+    /// pkexec asks for the password through the polkit agent, so the
+    /// detected mode is kept. Other tools (sudo, doas, ...) prompt on the
+    /// terminal: TUI apps already get the real terminal, everything else
+    /// runs Interactive in the PTY so the prompt is visible and typeable.
+    pub fn elevated(self, prefix: &str) -> Self {
+        let program = prefix
+            .split_whitespace()
+            .next()
+            .unwrap_or("")
+            .rsplit('/')
+            .next()
+            .unwrap_or("");
+        match (program, self) {
+            ("pkexec", mode) | (_, mode @ TerminalMode::Tui) => mode,
+            _ => TerminalMode::Interactive,
+        }
+    }
+}
+
 /// Status of a command execution
 #[derive(Debug, Clone)]
 #[allow(dead_code)] // Variants used in pattern matching in ui.rs
@@ -139,14 +342,31 @@ pub enum CommandStatus {
 
 impl CommandStatus {
     /// Create from portable_pty::ExitStatus
+    ///
+    /// On unix a child killed by a signal becomes `Signaled`, either directly
+    /// or via the shell's `128 + signal` exit code convention.
     pub fn from_exit_status(status: ExitStatus) -> Self {
         if status.success() {
-            CommandStatus::Exited(0)
-        } else {
-            // portable_pty doesn't expose the exact code easily
-            // We can only tell success vs failure
-            CommandStatus::Exited(1)
+            return CommandStatus::Exited(0);
         }
+
+        #[cfg(unix)]
+        {
+            // portable_pty only keeps the signal's strsignal() description
+            let description = status.to_string();
+            if let Some(name) = description.strip_prefix("Terminated by ") {
+                return signal_number(name)
+                    .map(CommandStatus::Signaled)
+                    .unwrap_or(CommandStatus::Unknown);
+            }
+
+            let code = status.exit_code();
+            if (129..=128 + 64).contains(&code) {
+                return CommandStatus::Signaled(code as i32 - 128);
+            }
+        }
+
+        CommandStatus::Exited(status.exit_code() as i32)
     }
     
     /// Create from std::process::ExitStatus (for TUI handover)
@@ -175,27 +395,29 @@ impl CommandStatus {
     }
 }
 
+/// Map a strsignal() description (e.g. "Terminated") back to its number
+#[cfg(unix)]
+fn signal_number(description: &str) -> Option<i32> {
+    (1..=64).find(|&sig| {
+        // SAFETY: strsignal returns a valid C string (or null) for any input
+        let name = unsafe { libc::strsignal(sig) };
+        !name.is_null() && unsafe { std::ffi::CStr::from_ptr(name) }.to_string_lossy() == description
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::collections::HashMap;
-    use std::path::PathBuf;
 
     /// Create a mock Entry for testing
     fn mock_entry(terminal: bool) -> Entry {
         Entry {
             id: "test".to_string(),
             name: "Test".to_string(),
-            generic_name: None,
-            comment: None,
             exec: Some("test-cmd".to_string()),
-            icon: None,
-            categories: vec![],
-            keywords: vec![],
             terminal,
-            no_display: false,
             path: PathBuf::from("/test.desktop"),
-            custom_fields: HashMap::new(),
+            ..Default::default()
         }
     }
 
@@ -207,54 +429,197 @@ mod tests {
 
     #[test]
     fn test_terminal_mode_detection_raw_commands() {
+        let rules = ModeRules::default();
         // Raw commands (no entry) default to Oneshot
-        assert_eq!(TerminalMode::detect("ls -la", None), TerminalMode::Oneshot);
-        assert_eq!(TerminalMode::detect("echo hello", None), TerminalMode::Oneshot);
+        assert_eq!(TerminalMode::detect("ls -la", None, &rules), TerminalMode::Oneshot);
+        assert_eq!(TerminalMode::detect("echo hello", None, &rules), TerminalMode::Oneshot);
         
         // Known TUI apps
-        assert_eq!(TerminalMode::detect("htop", None), TerminalMode::Tui);
-        assert_eq!(TerminalMode::detect("vim test.txt", None), TerminalMode::Tui);
+        assert_eq!(TerminalMode::detect("htop", None, &rules), TerminalMode::Tui);
+        assert_eq!(TerminalMode::detect("vim test.txt", None, &rules), TerminalMode::Tui);
         
         // Known interactive apps
-        assert_eq!(TerminalMode::detect("python", None), TerminalMode::Interactive);
-        assert_eq!(TerminalMode::detect("bash", None), TerminalMode::Interactive);
+        assert_eq!(TerminalMode::detect("python", None, &rules), TerminalMode::Interactive);
+        assert_eq!(TerminalMode::detect("bash", None, &rules), TerminalMode::Interactive);
         
         // Long-running patterns
-        assert_eq!(TerminalMode::detect("watch ls", None), TerminalMode::LongRunning);
+        assert_eq!(TerminalMode::detect("watch ls", None, &rules), TerminalMode::LongRunning);
         
         // Nix store paths with wrapper scripts
-        assert_eq!(TerminalMode::detect("/nix/store/abc123-desktop-btop", None), TerminalMode::Tui);
-        assert_eq!(TerminalMode::detect("/nix/store/xyz789-desktop-neovim", None), TerminalMode::Tui);
-        assert_eq!(TerminalMode::detect("/nix/store/def456-desktop-nvim", None), TerminalMode::Tui);
+        assert_eq!(TerminalMode::detect("/nix/store/abc123-desktop-btop", None, &rules), TerminalMode::Tui);
+        assert_eq!(TerminalMode::detect("/nix/store/xyz789-desktop-neovim", None, &rules), TerminalMode::Tui);
+        assert_eq!(TerminalMode::detect("/nix/store/def456-desktop-nvim", None, &rules), TerminalMode::Tui);
     }
 
     #[test]
     fn test_gui_detection_from_desktop_entry() {
+        let rules = ModeRules::default();
         // Terminal=false means GUI app
         let gui_entry = mock_entry(false);
-        assert_eq!(TerminalMode::detect("firefox", Some(&gui_entry)), TerminalMode::Gui);
-        assert_eq!(TerminalMode::detect("code", Some(&gui_entry)), TerminalMode::Gui);
-        assert_eq!(TerminalMode::detect("gimp", Some(&gui_entry)), TerminalMode::Gui);
+        assert_eq!(TerminalMode::detect("firefox", Some(&gui_entry), &rules), TerminalMode::Gui);
+        assert_eq!(TerminalMode::detect("code", Some(&gui_entry), &rules), TerminalMode::Gui);
+        assert_eq!(TerminalMode::detect("gimp", Some(&gui_entry), &rules), TerminalMode::Gui);
         
         // Terminal=true means CLI app (falls through to other detection)
         let cli_entry = mock_entry(true);
-        assert_eq!(TerminalMode::detect("htop", Some(&cli_entry)), TerminalMode::Tui);
-        assert_eq!(TerminalMode::detect("python", Some(&cli_entry)), TerminalMode::Interactive);
+        assert_eq!(TerminalMode::detect("htop", Some(&cli_entry), &rules), TerminalMode::Tui);
+        assert_eq!(TerminalMode::detect("python", Some(&cli_entry), &rules), TerminalMode::Interactive);
         // Unknown CLI command with Terminal=true defaults to Interactive
-        assert_eq!(TerminalMode::detect("my-custom-cli", Some(&cli_entry)), TerminalMode::Interactive);
+        assert_eq!(TerminalMode::detect("my-custom-cli", Some(&cli_entry), &rules), TerminalMode::Interactive);
     }
 
     #[test]
     fn test_explicit_mode_override() {
+        let rules = ModeRules::default();
         // X-DarkwallTerminalMode overrides everything
         let gui_entry_forced_tui = mock_entry_with_mode(false, "tui");
-        assert_eq!(TerminalMode::detect("firefox", Some(&gui_entry_forced_tui)), TerminalMode::Tui);
+        assert_eq!(TerminalMode::detect("firefox", Some(&gui_entry_forced_tui), &rules), TerminalMode::Tui);
         
         let cli_entry_forced_gui = mock_entry_with_mode(true, "gui");
-        assert_eq!(TerminalMode::detect("htop", Some(&cli_entry_forced_gui)), TerminalMode::Gui);
+        assert_eq!(TerminalMode::detect("htop", Some(&cli_entry_forced_gui), &rules), TerminalMode::Gui);
         
         let entry_forced_oneshot = mock_entry_with_mode(true, "oneshot");
-        assert_eq!(TerminalMode::detect("python", Some(&entry_forced_oneshot)), TerminalMode::Oneshot);
+        assert_eq!(TerminalMode::detect("python", Some(&entry_forced_oneshot), &rules), TerminalMode::Oneshot);
+    }
+
+    #[test]
+    fn test_user_mode_rules() {
+        let rules = ModeRules {
+            patterns: vec![(Regex::new(r"^ssh\s").unwrap(), TerminalMode::Interactive)],
+            tui: vec!["yazi".to_string()],
+            interactive: vec!["python".to_string()],
+            long_running_patterns: vec!["npm run dev".to_string()],
+        };
+
+        assert_eq!(TerminalMode::detect("yazi ~/src", None, &rules), TerminalMode::Tui);
+        assert_eq!(TerminalMode::detect("desktop-yazi", None, &rules), TerminalMode::Tui);
+        assert_eq!(TerminalMode::detect("npm run dev", None, &rules), TerminalMode::LongRunning);
+        assert_eq!(TerminalMode::detect("ssh host", None, &rules), TerminalMode::Interactive);
+        // Regex rules win over lists and builtins
+        let rules = ModeRules {
+            patterns: vec![(Regex::new(r"^htop").unwrap(), TerminalMode::Oneshot)],
+            ..Default::default()
+        };
+        assert_eq!(TerminalMode::detect("htop -n 1", None, &rules), TerminalMode::Oneshot);
+        // Explicit overrides and GUI entries are unaffected
+        let entry = mock_entry_with_mode(true, "tui");
+        assert_eq!(TerminalMode::detect("htop -n 1", Some(&entry), &rules), TerminalMode::Tui);
+        assert_eq!(TerminalMode::detect("htop", Some(&mock_entry(false)), &rules), TerminalMode::Gui);
+        // Builtins still apply when nothing matches
+        assert_eq!(TerminalMode::detect("vim", None, &rules), TerminalMode::Tui);
+    }
+
+    #[test]
+    fn test_elevated_mode() {
+        assert_eq!(TerminalMode::Gui.elevated("pkexec"), TerminalMode::Gui);
+        assert_eq!(TerminalMode::Oneshot.elevated("/usr/bin/pkexec"), TerminalMode::Oneshot);
+        // sudo prompts on the terminal
+        assert_eq!(TerminalMode::Gui.elevated("sudo -A"), TerminalMode::Interactive);
+        assert_eq!(TerminalMode::Oneshot.elevated("doas"), TerminalMode::Interactive);
+        assert_eq!(TerminalMode::Tui.elevated("sudo"), TerminalMode::Tui);
+    }
+
+    #[test]
+    fn test_resolve_working_dir() {
+        assert_eq!(resolve_working_dir(None), None);
+        assert_eq!(resolve_working_dir(Some(Path::new("/"))), Some(PathBuf::from("/")));
+        assert_eq!(
+            resolve_working_dir(Some(Path::new("/nonexistent/drun-test-dir"))),
+            dirs::home_dir()
+        );
+    }
+
+    #[test]
+    fn test_parse_env_pairs() {
+        assert_eq!(
+            parse_env_pairs("MOZ_ENABLE_WAYLAND=1;GDK_BACKEND=wayland"),
+            vec![
+                ("MOZ_ENABLE_WAYLAND".to_string(), "1".to_string()),
+                ("GDK_BACKEND".to_string(), "wayland".to_string()),
+            ]
+        );
+        // Values may contain '=' and trailing separators are ignored
+        assert_eq!(
+            parse_env_pairs("OPTS=a=b;"),
+            vec![("OPTS".to_string(), "a=b".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_parse_env_pairs_malformed() {
+        // Missing '=' and empty keys are skipped
+        assert_eq!(
+            parse_env_pairs("NOVALUE;=orphan;GOOD=1"),
+            vec![("GOOD".to_string(), "1".to_string())]
+        );
+        // Empty values are kept
+        assert_eq!(
+            parse_env_pairs("EMPTY="),
+            vec![("EMPTY".to_string(), String::new())]
+        );
+        assert!(parse_env_pairs("").is_empty());
+        assert!(parse_env_pairs(";;").is_empty());
+    }
+
+    #[test]
+    fn test_expand_env_value() {
+        let home = std::env::var("HOME").unwrap_or_default();
+        assert_eq!(expand_env_value("$HOME/bin"), format!("{}/bin", home));
+        assert_eq!(expand_env_value("${HOME}"), home);
+        assert_eq!(expand_env_value("$DRUN_TEST_UNSET_VAR"), "");
+        assert_eq!(expand_env_value("plain"), "plain");
+    }
+
+    #[test]
+    fn test_launch_env_entry_overrides_global() {
+        let mut global = BTreeMap::new();
+        global.insert("A".to_string(), "global".to_string());
+        global.insert("B".to_string(), "kept".to_string());
+
+        let mut entry = mock_entry(false);
+        entry.custom_fields.insert("Env".to_string(), "A=entry".to_string());
+
+        let env = launch_env(&global, Some(&entry));
+        assert_eq!(
+            env,
+            vec![
+                ("B".to_string(), "kept".to_string()),
+                ("A".to_string(), "entry".to_string()),
+            ]
+        );
+        assert_eq!(launch_env(&global, None).len(), 2);
+    }
+
+    #[test]
+    fn test_command_status_from_exit_status() {
+        assert!(matches!(
+            CommandStatus::from_exit_status(ExitStatus::with_exit_code(0)),
+            CommandStatus::Exited(0)
+        ));
+        assert!(matches!(
+            CommandStatus::from_exit_status(ExitStatus::with_exit_code(42)),
+            CommandStatus::Exited(42)
+        ));
+        // Shell convention for a child killed by SIGINT
+        assert!(matches!(
+            CommandStatus::from_exit_status(ExitStatus::with_exit_code(130)),
+            CommandStatus::Signaled(2)
+        ));
+    }
+
+    #[test]
+    fn test_launch_options_argv() {
+        let options = LaunchOptions {
+            shell: vec!["zsh".to_string(), "-ic".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(options.argv("echo hi"), vec!["zsh", "-ic", "echo hi"]);
+        assert_eq!(LaunchOptions::default().argv("ls"), vec!["sh", "-c", "ls"]);
+
+        let command = options.command(&["kitty".to_string(), "-e".to_string()], "htop");
+        assert_eq!(command.get_program(), "kitty");
+        let args: Vec<_> = command.get_args().collect();
+        assert_eq!(args, vec!["-e", "zsh", "-ic", "htop"]);
     }
 
     #[test]
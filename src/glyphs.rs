@@ -0,0 +1,222 @@
+//! Text icons for terminals without a graphics protocol
+//!
+//! With `icons.fallback = "nerdfont"` or `"unicode"`, entries get a glyph
+//! in place of the image icon when the terminal can't show images (over
+//! SSH, or without kitty/sixel/iTerm2 support). The glyph is picked from
+//! `[icons.glyphs]` in config first, then from the builtin tables: by
+//! application id, then by category.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use unicode_width::UnicodeWidthChar;
+
+use crate::desktop_entry::Entry;
+
+/// Cells the glyph column takes in a card: a glyph of width 1 or 2 and a
+/// space before the name
+pub const GLYPH_COLUMN_WIDTH: u16 = 3;
+
+/// Which glyph set to use
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GlyphStyle {
+    /// Nerd Font private use glyphs
+    NerdFont,
+    /// Plain Unicode symbols that any font has
+    Unicode,
+}
+
+/// Text icons when the terminal can't show images (`icons.fallback`)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IconFallback {
+    /// No icons
+    #[default]
+    None,
+    NerdFont,
+    Unicode,
+}
+
+impl IconFallback {
+    /// Glyph set to draw, None for no icons
+    pub fn style(self) -> Option<GlyphStyle> {
+        match self {
+            Self::None => None,
+            Self::NerdFont => Some(GlyphStyle::NerdFont),
+            Self::Unicode => Some(GlyphStyle::Unicode),
+        }
+    }
+}
+
+/// Nerd Font glyphs of well-known applications, by desktop file id
+const NERDFONT_APPS: &[(&str, &str)] = &[
+    ("firefox", "\u{f269}"),
+    ("chromium", "\u{f268}"),
+    ("google-chrome", "\u{f268}"),
+    ("code", "\u{e70c}"),
+    ("nvim", "\u{e62b}"),
+    ("vim", "\u{e62b}"),
+    ("gvim", "\u{e62b}"),
+    ("spotify", "\u{f1bc}"),
+    ("discord", "\u{f392}"),
+    ("steam", "\u{f1b6}"),
+    ("thunderbird", "\u{f0e0}"),
+    ("gimp", "\u{f1fc}"),
+    ("kitty", "\u{f120}"),
+    ("alacritty", "\u{f120}"),
+    ("foot", "\u{f120}"),
+];
+
+/// Nerd Font glyphs by category; specific categories come before the
+/// main ones they belong to
+const NERDFONT_CATEGORIES: &[(&str, &str)] = &[
+    ("TerminalEmulator", "\u{f120}"),
+    ("WebBrowser", "\u{f0ac}"),
+    ("Email", "\u{f0e0}"),
+    ("TextEditor", "\u{f044}"),
+    ("Development", "\u{f121}"),
+    ("Game", "\u{f11b}"),
+    ("Audio", "\u{f001}"),
+    ("Video", "\u{f03d}"),
+    ("AudioVideo", "\u{f001}"),
+    ("Graphics", "\u{f03e}"),
+    ("Network", "\u{f0ac}"),
+    ("Office", "\u{f15c}"),
+    ("Education", "\u{f19d}"),
+    ("Science", "\u{f0c3}"),
+    ("Settings", "\u{f1de}"),
+    ("System", "\u{f013}"),
+    ("Utility", "\u{f0ad}"),
+];
+
+/// Unicode symbols by category
+const UNICODE_CATEGORIES: &[(&str, &str)] = &[
+    ("TerminalEmulator", "❯"),
+    ("WebBrowser", "🌐"),
+    ("Email", "✉"),
+    ("TextEditor", "✎"),
+    ("Development", "⌨"),
+    ("Game", "♟"),
+    ("Audio", "♫"),
+    ("Video", "▶"),
+    ("AudioVideo", "♫"),
+    ("Graphics", "🎨"),
+    ("Network", "🌐"),
+    ("Office", "✍"),
+    ("Education", "✎"),
+    ("Science", "⚗"),
+    ("Settings", "⚙"),
+    ("System", "⚙"),
+    ("Utility", "✂"),
+];
+
+/// Glyph for entries that match nothing
+fn default_glyph(style: GlyphStyle) -> &'static str {
+    match style {
+        GlyphStyle::NerdFont => "\u{f2d0}",
+        GlyphStyle::Unicode => "◆",
+    }
+}
+
+/// Picks a glyph for each entry
+#[derive(Debug, Clone)]
+pub struct GlyphIcons {
+    style: GlyphStyle,
+    /// `[icons.glyphs]`: entry id or category to glyph
+    custom: BTreeMap<String, String>,
+}
+
+impl GlyphIcons {
+    pub fn new(style: GlyphStyle, custom: BTreeMap<String, String>) -> Self {
+        Self { style, custom }
+    }
+
+    /// Glyph for `entry`, at most two cells wide
+    pub fn glyph(&self, entry: &Entry) -> &str {
+        let glyph = self
+            .custom
+            .get(&entry.id)
+            .or_else(|| entry.categories.iter().find_map(|c| self.custom.get(c)))
+            .map(String::as_str)
+            .or_else(|| self.builtin(entry))
+            .unwrap_or(default_glyph(self.style));
+        fit_width(glyph, 2)
+    }
+
+    /// Glyph from the builtin tables
+    fn builtin(&self, entry: &Entry) -> Option<&'static str> {
+        let (apps, categories) = match self.style {
+            GlyphStyle::NerdFont => (NERDFONT_APPS, NERDFONT_CATEGORIES),
+            GlyphStyle::Unicode => (&[][..], UNICODE_CATEGORIES),
+        };
+        // Flatpak ids end in the app name: org.mozilla.firefox
+        let id = entry.id.to_lowercase();
+        let app = id.rsplit('.').next().unwrap_or(&id);
+        apps.iter()
+            .find(|(name, _)| *name == app)
+            .or_else(|| categories.iter().find(|(name, _)| entry.categories.iter().any(|c| c == name)))
+            .map(|(_, glyph)| *glyph)
+    }
+}
+
+/// The leading characters of `glyph` that fit in `max_width` cells
+pub fn fit_width(glyph: &str, max_width: usize) -> &str {
+    let mut width = 0;
+    for (index, c) in glyph.char_indices() {
+        width += c.width().unwrap_or(0);
+        if width > max_width {
+            return &glyph[..index];
+        }
+    }
+    glyph
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use unicode_width::UnicodeWidthStr;
+
+    fn entry(id: &str, categories: &[&str]) -> Entry {
+        Entry {
+            id: id.to_string(),
+            name: id.to_string(),
+            categories: categories.iter().map(|c| c.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_nerdfont_lookup() {
+        let glyphs = GlyphIcons::new(GlyphStyle::NerdFont, BTreeMap::new());
+        // By id, also the last part of a Flatpak id
+        assert_eq!(glyphs.glyph(&entry("firefox", &["Network"])), "\u{f269}");
+        assert_eq!(glyphs.glyph(&entry("org.mozilla.firefox", &[])), "\u{f269}");
+        // The specific category wins over the main one listed first
+        assert_eq!(glyphs.glyph(&entry("qutebrowser", &["Network", "WebBrowser"])), "\u{f0ac}");
+        assert_eq!(glyphs.glyph(&entry("ghostty", &["System", "TerminalEmulator"])), "\u{f120}");
+        assert_eq!(glyphs.glyph(&entry("unknown", &[])), "\u{f2d0}");
+    }
+
+    #[test]
+    fn test_custom_glyphs_win() {
+        let custom = BTreeMap::from([
+            ("firefox".to_string(), "F".to_string()),
+            ("Game".to_string(), "G".to_string()),
+        ]);
+        let glyphs = GlyphIcons::new(GlyphStyle::Unicode, custom);
+        assert_eq!(glyphs.glyph(&entry("firefox", &["Network"])), "F");
+        assert_eq!(glyphs.glyph(&entry("chess", &["Game", "BoardGame"])), "G");
+        assert_eq!(glyphs.glyph(&entry("calc", &["Utility"])), "✂");
+    }
+
+    #[test]
+    fn test_glyph_width() {
+        let glyphs = GlyphIcons::new(GlyphStyle::Unicode, BTreeMap::new());
+        assert_eq!(glyphs.glyph(&entry("chess", &["Game"])).width(), 1);
+        assert_eq!(glyphs.glyph(&entry("browser", &["WebBrowser"])).width(), 2);
+
+        // Glyphs from config are cut to two cells
+        assert_eq!(fit_width("abc", 2), "ab");
+        assert_eq!(fit_width("🌐🌐", 2), "🌐");
+        assert_eq!(fit_width("a🌐", 2), "a");
+    }
+}
@@ -0,0 +1,216 @@
+//! Terminal graphics protocol detection
+//!
+//! Icons need to know which image protocol the terminal speaks (kitty,
+//! sixel, iTerm2) and its cell size in pixels, which means asking the
+//! terminal with escape sequences. Terminals that never answer (old
+//! urxvt, some multiplexer setups) would stall startup, so the query is
+//! given a timeout, after which drun goes on without graphics. An answer
+//! is cached per `$TERM` and `$TERMINFO` in
+//! `~/.cache/darkwall-drun/graphics.json`, so later starts skip the query;
+//! a timeout isn't, as the terminal may just have been slow that once.
+
+use ratatui_image::picker::{Picker, ProtocolType};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::tty::{self, RawTty};
+
+/// Kitty graphics support and cell size in pixels; DA1 (sixel) is added
+/// by [`RawTty::query`]
+const QUERY: &[u8] = b"\x1b_Gi=31,s=1,v=1,a=q,t=d,f=24;AAAA\x1b\\\x1b[16t";
+
+/// Default cache location
+pub fn default_cache_path() -> Option<PathBuf> {
+    dirs::cache_dir().map(|d| d.join("darkwall-drun").join("graphics.json"))
+}
+
+/// Image protocol, as cached
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Protocol {
+    Kitty,
+    Sixel,
+    Iterm2,
+    Halfblocks,
+}
+
+/// What the terminal answered
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+struct Detection {
+    protocol: Protocol,
+    /// Cell size in pixels
+    font_size: (u16, u16),
+}
+
+impl Detection {
+    /// What to use, from the answer to [`QUERY`]; None without a cell size
+    ///
+    /// Hints from the environment (tmux, iTerm2 and friends) win over the
+    /// answer, as with [`Picker::from_fontsize`].
+    fn of(reply: &[u8], window: Option<libc::winsize>) -> Option<Self> {
+        let (answered, font_size) = parse_reply(reply, window);
+        let picker = Picker::from_fontsize(font_size?);
+        let protocol = match picker.protocol_type() {
+            ProtocolType::Kitty => Protocol::Kitty,
+            ProtocolType::Sixel => Protocol::Sixel,
+            ProtocolType::Iterm2 => Protocol::Iterm2,
+            ProtocolType::Halfblocks => answered.unwrap_or(Protocol::Halfblocks),
+        };
+        Some(Self {
+            protocol,
+            font_size: picker.font_size(),
+        })
+    }
+
+    fn picker(self) -> Picker {
+        let mut picker = Picker::from_fontsize(self.font_size);
+        picker.set_protocol_type(match self.protocol {
+            Protocol::Kitty => ProtocolType::Kitty,
+            Protocol::Sixel => ProtocolType::Sixel,
+            Protocol::Iterm2 => ProtocolType::Iterm2,
+            Protocol::Halfblocks => ProtocolType::Halfblocks,
+        });
+        picker
+    }
+}
+
+/// Cached outcomes by terminal; None for terminals without graphics
+type DetectionCache = BTreeMap<String, Option<Detection>>;
+
+/// Picker for this terminal, None without graphics
+///
+/// MUST be called before entering raw mode / alternate screen.
+pub fn detect(timeout: Duration, cache_path: Option<&Path>) -> Option<Picker> {
+    let key = terminal_key();
+    let mut cache = cache_path.map(read_cache).unwrap_or_default();
+    if let Some(&detection) = cache.get(&key) {
+        tracing::debug!("Graphics detection for {} from cache: {:?}", key, detection);
+        return detection.map(Detection::picker);
+    }
+
+    let Some((reply, window)) = query(timeout) else {
+        tracing::info!("Terminal didn't answer the graphics query within {:?}, icons disabled", timeout);
+        return None;
+    };
+    let detection = Detection::of(&reply, window);
+    tracing::info!("Graphics detection: {:?}", detection);
+    cache.insert(key, detection);
+    if let Some(path) = cache_path {
+        if let Err(e) = write_cache(path, &cache) {
+            tracing::debug!("Failed to write {}: {}", path.display(), e);
+        }
+    }
+    detection.map(Detection::picker)
+}
+
+/// Cache key of the terminal drun runs in
+fn terminal_key() -> String {
+    let var = |name| std::env::var(name).unwrap_or_default();
+    format!("{}:{}", var("TERM"), var("TERMINFO"))
+}
+
+/// The terminal's answer to [`QUERY`] and its size; None if it didn't
+/// answer within `timeout`
+fn query(timeout: Duration) -> Option<(Vec<u8>, Option<libc::winsize>)> {
+    let mut tty = RawTty::open()?;
+    let reply = tty.query(QUERY, timeout)?;
+    Some((reply, tty.window_size()))
+}
+
+/// Protocol the terminal claims to support and its cell size in pixels,
+/// from an answer to [`QUERY`]
+///
+/// Terminals that don't report the cell size (`CSI 6 ; height ; width t`)
+/// may still report the window size in pixels.
+fn parse_reply(reply: &[u8], window: Option<libc::winsize>) -> (Option<Protocol>, Option<(u16, u16)>) {
+    let text = String::from_utf8_lossy(reply);
+    let protocol = if text.contains("\x1b_Gi=31;OK\x1b\\") {
+        Some(Protocol::Kitty)
+    } else if tty::da1_answer(reply).is_some_and(|params| params.split(|&b| b == b';').any(|p| p == b"4")) {
+        Some(Protocol::Sixel)
+    } else {
+        None
+    };
+
+    let font_size = text
+        .split('\x1b')
+        .filter_map(|seq| seq.strip_prefix("[6;")?.strip_suffix('t'))
+        .find_map(|size| {
+            let (height, width) = size.split_once(';')?;
+            Some((width.parse::<u16>().ok()?, height.parse::<u16>().ok()?))
+        })
+        .or_else(|| {
+            let w = window?;
+            (w.ws_col > 0 && w.ws_row > 0).then(|| (w.ws_xpixel / w.ws_col, w.ws_ypixel / w.ws_row))
+        })
+        .filter(|&(width, height)| width > 0 && height > 0);
+    (protocol, font_size)
+}
+
+fn read_cache(path: &Path) -> DetectionCache {
+    std::fs::read_to_string(path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn write_cache(path: &Path, cache: &DetectionCache) -> anyhow::Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(path, serde_json::to_string_pretty(cache)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+
+    #[test]
+    fn test_detection_from_cache() {
+        let dir = TempDir::new("graphics");
+        let path = dir.join("graphics.json");
+        let kitty = Detection {
+            protocol: Protocol::Kitty,
+            font_size: (9, 18),
+        };
+        let cache = DetectionCache::from([(terminal_key(), Some(kitty))]);
+        write_cache(&path, &cache).unwrap();
+
+        // Answered from the cache without querying (no terminal in tests)
+        let picker = detect(Duration::ZERO, Some(&path)).unwrap();
+        assert_eq!(picker.protocol_type(), ProtocolType::Kitty);
+        assert_eq!(picker.font_size(), (9, 18));
+
+        let cache = DetectionCache::from([(terminal_key(), None)]);
+        write_cache(&path, &cache).unwrap();
+        assert!(detect(Duration::ZERO, Some(&path)).is_none());
+
+        // Unreadable caches are ignored
+        std::fs::write(&path, "{").unwrap();
+        assert!(read_cache(&path).is_empty());
+    }
+
+    #[test]
+    fn test_parse_reply() {
+        let kitty = b"\x1b_Gi=31;OK\x1b\\\x1b[6;18;9t\x1b[?62;22c";
+        assert_eq!(parse_reply(kitty, None), (Some(Protocol::Kitty), Some((9, 18))));
+
+        let sixel = b"\x1b[6;20;10t\x1b[?62;4;22c";
+        assert_eq!(parse_reply(sixel, None), (Some(Protocol::Sixel), Some((10, 20))));
+
+        // No cell size report: from the window size in pixels, if known
+        let plain = b"\x1b[?1;2c";
+        assert_eq!(parse_reply(plain, None), (None, None));
+        let window = libc::winsize {
+            ws_row: 30,
+            ws_col: 100,
+            ws_xpixel: 800,
+            ws_ypixel: 480,
+        };
+        assert_eq!(parse_reply(plain, Some(window)), (None, Some((8, 16))));
+    }
+}
@@ -45,6 +45,21 @@ impl Default for HistoryFile {
     }
 }
 
+/// drun's state directory
+/// TEAM_001: Use XDG_STATE_HOME per spec (not DATA_HOME)
+pub fn state_dir() -> PathBuf {
+    // XDG_STATE_HOME is for state data that persists between restarts
+    // but isn't important enough to sync (like history, logs, etc.)
+    if let Ok(state_home) = std::env::var("XDG_STATE_HOME") {
+        return PathBuf::from(state_home).join("darkwall-drun");
+    }
+
+    // Fall back to ~/.local/state (XDG default)
+    dirs::home_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join(".local/state/darkwall-drun")
+}
+
 /// Usage history manager
 pub struct History {
     entries: HashMap<String, UsageStats>,
@@ -56,7 +71,11 @@ pub struct History {
 impl History {
     /// Create a new history manager
     pub fn new(max_entries: usize, decay_after_days: u64) -> Self {
-        let path = Self::default_path();
+        Self::with_path(state_dir().join("history.json"), max_entries, decay_after_days)
+    }
+
+    /// History manager for the file at `path`
+    pub fn with_path(path: PathBuf, max_entries: usize, decay_after_days: u64) -> Self {
         Self {
             entries: HashMap::new(),
             path,
@@ -65,23 +84,6 @@ impl History {
         }
     }
 
-    /// Get the default history file path
-    /// TEAM_001: Use XDG_STATE_HOME per spec (not DATA_HOME)
-    fn default_path() -> PathBuf {
-        // XDG_STATE_HOME is for state data that persists between restarts
-        // but isn't important enough to sync (like history, logs, etc.)
-        if let Ok(state_home) = std::env::var("XDG_STATE_HOME") {
-            return PathBuf::from(state_home)
-                .join("darkwall-drun")
-                .join("history.json");
-        }
-
-        // Fall back to ~/.local/state (XDG default)
-        dirs::home_dir()
-            .unwrap_or_else(|| PathBuf::from("."))
-            .join(".local/state/darkwall-drun/history.json")
-    }
-
     /// Load history from disk
     pub fn load(&mut self) -> Result<()> {
         if !self.path.exists() {
@@ -158,6 +160,11 @@ impl History {
         frequency * recency
     }
 
+    /// Frecency scores of all entries with history, by id
+    pub fn frecency_scores(&self) -> HashMap<String, f64> {
+        self.entries.keys().map(|id| (id.clone(), self.frecency_score(id))).collect()
+    }
+
     /// Calculate recency weight based on last use time
     fn recency_weight(&self, last_used: u64) -> f64 {
         let now = current_timestamp();
@@ -205,6 +212,36 @@ impl History {
         }
     }
 
+    /// Drop the usage records of an entry, returning them for
+    /// [`History::restore`]
+    pub fn forget(&mut self, entry_id: &str) -> Option<UsageStats> {
+        self.entries.remove(entry_id)
+    }
+
+    /// Put back records taken by [`History::forget`]
+    ///
+    /// Launches since then are added to them.
+    pub fn restore(&mut self, entry_id: &str, stats: UsageStats) {
+        let stats = match self.entries.remove(entry_id) {
+            Some(since) => UsageStats {
+                count: stats.count.saturating_add(since.count),
+                last_used: stats.last_used.max(since.last_used),
+            },
+            None => stats,
+        };
+        self.entries.insert(entry_id.to_string(), stats);
+    }
+
+    /// Usage statistics of an entry, if it was ever launched
+    pub fn stats(&self, entry_id: &str) -> Option<UsageStats> {
+        self.entries.get(entry_id).cloned()
+    }
+
+    /// Ids of all tracked entries
+    pub fn ids(&self) -> impl Iterator<Item = &str> {
+        self.entries.keys().map(String::as_str)
+    }
+
     /// Get the number of tracked entries
     #[allow(dead_code)]
     pub fn len(&self) -> usize {
@@ -239,6 +276,33 @@ mod tests {
         assert!(history.frecency_score("test.desktop") > 0.0);
     }
 
+    #[test]
+    fn test_forget_and_restore() {
+        let mut history = History::with_path(PathBuf::from("/nonexistent/history.json"), 100, 90);
+        for _ in 0..3 {
+            history.record_usage("once.desktop");
+        }
+        history.record_usage("other.desktop");
+        let score = history.frecency_score("once.desktop");
+
+        let stats = history.forget("once.desktop").unwrap();
+        assert_eq!(stats.count, 3);
+        assert!(history.stats("once.desktop").is_none());
+        assert_eq!(history.frecency_score("once.desktop"), 0.0);
+        assert!(history.forget("once.desktop").is_none());
+        assert_eq!(history.len(), 1);
+
+        history.restore("once.desktop", stats.clone());
+        assert_eq!(history.frecency_score("once.desktop"), score);
+        assert_eq!(history.len(), 2);
+
+        // A launch in between counts on top
+        history.forget("once.desktop");
+        history.record_usage("once.desktop");
+        history.restore("once.desktop", stats);
+        assert_eq!(history.stats("once.desktop").unwrap().count, 4);
+    }
+
     #[test]
     fn test_frecency_recent_boost() {
         let mut history = History::new(100, 90);
@@ -283,4 +347,16 @@ mod tests {
         
         assert_eq!(history.entries.get("test.desktop").unwrap().count, 3);
     }
+
+    #[test]
+    fn test_stats() {
+        let mut history = History::new(100, 90);
+        assert!(history.stats("test.desktop").is_none());
+
+        history.record_usage("test.desktop");
+        history.record_usage("test.desktop");
+        let stats = history.stats("test.desktop").unwrap();
+        assert_eq!(stats.count, 2);
+        assert!(stats.last_used > 0);
+    }
 }
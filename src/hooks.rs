@@ -0,0 +1,157 @@
+//! User hooks run around command execution.
+//!
+//! `[hooks] pre_exec` commands run before a command is launched and
+//! `post_exec` commands after it exits. Each hook is a shell command line
+//! (`sh -c`) that gets details about the launch in `DRUN_*` environment
+//! variables. Hooks are best-effort: a failing, missing or hung hook is
+//! logged and never stops the launch.
+
+use std::os::unix::process::CommandExt;
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+use crate::executor::CommandStatus;
+
+/// What a hook is told about the command
+#[derive(Debug, Clone, Default)]
+pub struct HookEvent {
+    /// Entry the command came from (`DRUN_ENTRY_ID`)
+    pub entry_id: Option<String>,
+    /// Command line (`DRUN_COMMAND`)
+    pub command: String,
+    /// Exit status, for post_exec hooks
+    pub status: Option<CommandStatus>,
+    /// Run time, for post_exec hooks (`DRUN_DURATION_SECS`)
+    pub duration: Option<Duration>,
+}
+
+impl HookEvent {
+    /// Environment variables describing the event to a hook named `hook`
+    ///
+    /// `DRUN_EXIT_CODE` is only set for commands that exited, and
+    /// `DRUN_SIGNAL` for commands killed by a signal.
+    fn env(&self, hook: &str) -> Vec<(&'static str, String)> {
+        let mut env = vec![
+            ("DRUN_HOOK", hook.to_string()),
+            ("DRUN_ENTRY_ID", self.entry_id.clone().unwrap_or_default()),
+            ("DRUN_COMMAND", self.command.clone()),
+        ];
+        match self.status {
+            Some(CommandStatus::Exited(code)) => env.push(("DRUN_EXIT_CODE", code.to_string())),
+            Some(CommandStatus::Signaled(signal)) => env.push(("DRUN_SIGNAL", signal.to_string())),
+            _ => {}
+        }
+        if let Some(duration) = self.duration {
+            env.push(("DRUN_DURATION_SECS", duration.as_secs().to_string()));
+        }
+        env
+    }
+}
+
+/// Run `hooks` one after another, waiting at most `timeout` for each
+///
+/// Hooks get no terminal: stdin, stdout and stderr are /dev/null, since
+/// drun owns the screen. Each hook runs in its own process group; a hook
+/// still running at the timeout is killed along with everything it
+/// started.
+pub fn run(hook: &str, hooks: &[String], event: &HookEvent, timeout: Duration) {
+    let env = event.env(hook);
+    for line in hooks {
+        let child = Command::new("sh")
+            .args(["-c", line])
+            .envs(env.iter().map(|(k, v)| (k, v)))
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .process_group(0)
+            .spawn();
+        let mut child = match child {
+            Ok(child) => child,
+            Err(e) => {
+                tracing::warn!("Failed to run {} hook \"{}\": {}", hook, line, e);
+                continue;
+            }
+        };
+
+        let start = Instant::now();
+        loop {
+            match child.try_wait() {
+                Ok(Some(status)) if status.success() => break,
+                Ok(Some(status)) => {
+                    tracing::warn!("{} hook \"{}\" failed: {}", hook, line, status);
+                    break;
+                }
+                Ok(None) if start.elapsed() >= timeout => {
+                    tracing::warn!("{} hook \"{}\" timed out, killing it", hook, line);
+                    // The hook leads its process group, so this reaches
+                    // what it started; until it is reaped, the id is still
+                    // its own, so the kill can't hit a reused pid
+                    // SAFETY: kill has no memory safety preconditions
+                    unsafe { libc::kill(-(child.id() as libc::pid_t), libc::SIGKILL) };
+                    child.wait().ok();
+                    break;
+                }
+                Ok(None) => std::thread::sleep(Duration::from_millis(10)),
+                Err(e) => {
+                    tracing::warn!("Failed to wait for {} hook \"{}\": {}", hook, line, e);
+                    break;
+                }
+            }
+        }
+    }
+}
+
+/// Run `hooks` on a background thread, so slow hooks never block the UI
+pub fn spawn(hook: &'static str, hooks: Vec<String>, event: HookEvent, timeout: Duration) {
+    if hooks.is_empty() {
+        return;
+    }
+    std::thread::spawn(move || run(hook, &hooks, &event, timeout));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+
+    #[test]
+    fn test_hook_gets_event_env_and_times_out() {
+        let dir = TempDir::new("hooks");
+        let out = dir.join("post");
+
+        let event = HookEvent {
+            entry_id: Some("firefox.desktop".to_string()),
+            command: "firefox --new-window".to_string(),
+            status: Some(CommandStatus::Exited(2)),
+            duration: Some(Duration::from_secs(61)),
+        };
+        let sleeper = dir.join("sleeper");
+        let hooks = vec![
+            // A failing or hung hook does not stop the ones after it
+            "exit 1".to_string(),
+            format!("sleep 10 & echo $! > {}; wait", sleeper.display()),
+            format!(
+                "echo \"$DRUN_HOOK $DRUN_ENTRY_ID $DRUN_EXIT_CODE ${{DRUN_SIGNAL:-none}} \
+                 $DRUN_DURATION_SECS $DRUN_COMMAND\" > {}",
+                out.display()
+            ),
+        ];
+
+        let start = Instant::now();
+        run("post_exec", &hooks, &event, Duration::from_millis(200));
+        assert!(start.elapsed() < Duration::from_secs(5));
+        assert_eq!(
+            std::fs::read_to_string(&out).unwrap(),
+            "post_exec firefox.desktop 2 none 61 firefox --new-window\n"
+        );
+
+        // What the hung hook started was killed with it
+        let pid = std::fs::read_to_string(&sleeper).unwrap();
+        let stat = format!("/proc/{}/stat", pid.trim());
+        let start = Instant::now();
+        while std::fs::read_to_string(&stat).is_ok_and(|stat| !stat.contains(") Z ")) {
+            assert!(start.elapsed() < Duration::from_secs(5), "sleep outlived its hook");
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
+}
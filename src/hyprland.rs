@@ -0,0 +1,490 @@
+//! Hyprland IPC client.
+//!
+//! # Protocol
+//!
+//! Hyprland answers one plain-text request per connection on
+//! `$XDG_RUNTIME_DIR/hypr/$HYPRLAND_INSTANCE_SIGNATURE/.socket.sock`:
+//! `j/<query>` (e.g. `j/clients`) replies with JSON, `dispatch <dispatcher>
+//! <args>` with `ok` or an error message. Events are lines like
+//! `openwindow>>ADDRESS,WORKSPACE,CLASS,TITLE` on `.socket2.sock` in the
+//! same directory.
+//!
+//! Windows are known by their address (`0x55d0c3a1e2f0`), which drun uses
+//! as window id. Special workspaces (the scratchpad) have negative ids and
+//! are left out.
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader, Lines};
+use tokio::net::UnixStream;
+use tokio::sync::mpsc;
+
+use crate::compositor::{
+    self, BoxFuture, Compositor, Event, EventSource, IpcSocket, WindowInfo, WorkspaceInfo,
+    WorkspaceReference,
+};
+use crate::desktop_entry::shell_quote;
+
+/// Client for Hyprland IPC
+#[derive(Clone)]
+pub struct HyprlandClient {
+    socket: IpcSocket,
+}
+
+impl HyprlandClient {
+    /// Connect to the instance in `$HYPRLAND_INSTANCE_SIGNATURE`
+    pub fn new(timeout: Duration) -> Result<Self> {
+        let signature = std::env::var("HYPRLAND_INSTANCE_SIGNATURE")
+            .context("Hyprland socket not found (normal if not running under Hyprland)")?;
+        // Hyprland before 0.40 kept its sockets in /tmp
+        let dirs = std::env::var_os("XDG_RUNTIME_DIR")
+            .map(|dir| PathBuf::from(dir).join("hypr"))
+            .into_iter()
+            .chain([PathBuf::from("/tmp/hypr")]);
+        let path = dirs
+            .map(|dir| dir.join(&signature).join(".socket.sock"))
+            .find(|path| path.exists())
+            .context("Hyprland socket not found")?;
+        Ok(Self::with_socket(path, timeout))
+    }
+
+    fn with_socket(path: PathBuf, timeout: Duration) -> Self {
+        Self {
+            socket: IpcSocket::new("Hyprland", path, timeout),
+        }
+    }
+
+    async fn request(&self, msg: &str) -> Result<String> {
+        self.socket.request(self.send(msg)).await
+    }
+
+    async fn send(&self, msg: &str) -> Result<String> {
+        let mut stream = UnixStream::connect(self.socket.path())
+            .await
+            .context("Failed to connect to Hyprland socket")?;
+        stream
+            .write_all(msg.as_bytes())
+            .await
+            .context("Failed to write to Hyprland socket")?;
+        stream.shutdown().await?;
+
+        let mut response = String::new();
+        stream
+            .read_to_string(&mut response)
+            .await
+            .context("Failed to read from Hyprland socket")?;
+        tracing::debug!("Hyprland response: {}", response);
+        Ok(response)
+    }
+
+    /// Send a `j/<query>` and parse the JSON reply
+    async fn query<T: serde::de::DeserializeOwned>(&self, query: &str) -> Result<T> {
+        let response = self.request(&format!("j/{}", query)).await?;
+        serde_json::from_str(&response).with_context(|| format!("Failed to parse {}", query))
+    }
+
+    /// Run a dispatcher, failing unless Hyprland answers `ok`
+    async fn dispatch(&self, dispatch: &str) -> Result<()> {
+        tracing::debug!("Hyprland dispatch: {}", dispatch);
+        let response = self.request(&format!("dispatch {}", dispatch)).await?;
+        dispatch_result(&response)
+    }
+
+    pub async fn focused_window(&self) -> Result<Option<WindowInfo>> {
+        // An empty object when no window has focus
+        let window: serde_json::Value = self.query("activewindow").await?;
+        if window.as_object().is_none_or(|window| window.is_empty()) {
+            return Ok(None);
+        }
+        let window: HyprClient =
+            serde_json::from_value(window).context("Failed to parse activewindow")?;
+        Ok(window.window_info().map(|window| WindowInfo {
+            is_focused: true,
+            ..window
+        }))
+    }
+
+    pub async fn windows(&self) -> Result<Vec<WindowInfo>> {
+        let clients: Vec<HyprClient> = self.query("clients").await?;
+        Ok(clients.iter().filter_map(HyprClient::window_info).collect())
+    }
+
+    pub async fn workspaces(&self) -> Result<Vec<WorkspaceInfo>> {
+        let workspaces: Vec<HyprWorkspace> = self.query("workspaces").await?;
+        let monitors: Vec<HyprMonitor> = self.query("monitors").await?;
+        Ok(workspace_infos(workspaces, &monitors))
+    }
+
+    /// Set the focused window's floating state (Hyprland 0.36 and later)
+    pub async fn set_floating(&self, floating: bool) -> Result<()> {
+        self.dispatch(if floating { "setfloating" } else { "settiled" }).await
+    }
+
+    /// Connect to the event socket next to the request socket
+    pub async fn event_stream(&self) -> Result<EventStream> {
+        if !self.socket.is_available() {
+            anyhow::bail!("Hyprland is not responding");
+        }
+        let path = self.socket.path().with_file_name(".socket2.sock");
+        let stream = self
+            .socket
+            .with_timeout(async {
+                UnixStream::connect(&path)
+                    .await
+                    .context("Failed to connect to Hyprland event socket")
+            })
+            .await?;
+        Ok(EventStream {
+            lines: BufReader::new(stream).lines(),
+        })
+    }
+}
+
+impl Compositor for HyprlandClient {
+    fn name(&self) -> &'static str {
+        "Hyprland"
+    }
+
+    fn socket_path(&self) -> &Path {
+        self.socket.path()
+    }
+
+    fn is_available(&self) -> bool {
+        self.socket.is_available()
+    }
+
+    fn last_error(&self) -> Option<String> {
+        self.socket.last_error()
+    }
+
+    fn set_floating(&self, floating: bool) -> BoxFuture<'_, ()> {
+        Box::pin(HyprlandClient::set_floating(self, floating))
+    }
+
+    fn toggle_floating(&self) -> BoxFuture<'_, ()> {
+        Box::pin(self.dispatch("togglefloating"))
+    }
+
+    fn focused_window(&self) -> BoxFuture<'_, Option<WindowInfo>> {
+        Box::pin(HyprlandClient::focused_window(self))
+    }
+
+    fn windows(&self) -> BoxFuture<'_, Vec<WindowInfo>> {
+        Box::pin(HyprlandClient::windows(self))
+    }
+
+    fn workspaces(&self) -> BoxFuture<'_, Vec<WorkspaceInfo>> {
+        Box::pin(HyprlandClient::workspaces(self))
+    }
+
+    fn focus_window(&self, id: u64) -> BoxFuture<'_, ()> {
+        Box::pin(async move { self.dispatch(&format!("focuswindow address:0x{:x}", id)).await })
+    }
+
+    fn focus_workspace(&self, reference: WorkspaceReference) -> BoxFuture<'_, ()> {
+        let workspace = workspace_arg(&reference);
+        Box::pin(async move { self.dispatch(&format!("workspace {}", workspace)).await })
+    }
+
+    fn move_window_to_workspace(
+        &self,
+        window_id: Option<u64>,
+        reference: WorkspaceReference,
+        focus: bool,
+    ) -> BoxFuture<'_, ()> {
+        let dispatcher = if focus { "movetoworkspace" } else { "movetoworkspacesilent" };
+        let mut dispatch = format!("{} {}", dispatcher, workspace_arg(&reference));
+        if let Some(id) = window_id {
+            dispatch.push_str(&format!(",address:0x{:x}", id));
+        }
+        Box::pin(async move { self.dispatch(&dispatch).await })
+    }
+
+    fn spawn(&self, command: Vec<String>) -> BoxFuture<'_, ()> {
+        // exec hands the rest of the line to sh -c
+        let argv: Vec<_> = command.iter().map(|arg| shell_quote(arg)).collect();
+        let dispatch = format!("exec {}", argv.join(" "));
+        Box::pin(async move { self.dispatch(&dispatch).await })
+    }
+
+    fn spawn_event_listener(&self) -> Option<mpsc::UnboundedReceiver<Event>> {
+        let client = self.clone();
+        compositor::spawn_event_listener("Hyprland", async move { client.event_stream().await })
+    }
+
+    fn boxed_clone(&self) -> Box<dyn Compositor> {
+        Box::new(self.clone())
+    }
+}
+
+/// Open event socket connection
+pub struct EventStream {
+    lines: Lines<BufReader<UnixStream>>,
+}
+
+impl EventSource for EventStream {
+    fn next_event(&mut self) -> BoxFuture<'_, Option<Event>> {
+        Box::pin(async move {
+            while let Some(line) = self.lines.next_line().await? {
+                if let Some(event) = parse_event(&line) {
+                    return Ok(Some(event));
+                }
+            }
+            Ok(None)
+        })
+    }
+}
+
+/// Parse one event line, None for events drun doesn't handle
+fn parse_event(line: &str) -> Option<Event> {
+    let (name, data) = line.split_once(">>")?;
+    match name {
+        "openwindow" => {
+            // The title comes last and may hold commas
+            let mut fields = data.splitn(4, ',');
+            let id = parse_address(fields.next()?)?;
+            let _workspace = fields.next()?;
+            Some(Event::WindowOpenedOrChanged {
+                window: WindowInfo {
+                    id,
+                    app_id: fields.next()?.to_string(),
+                    title: fields.next().unwrap_or_default().to_string(),
+                    ..Default::default()
+                },
+            })
+        }
+        "activewindowv2" => Some(Event::WindowFocusChanged {
+            id: parse_address(data),
+        }),
+        "workspacev2" => {
+            let (id, _name) = data.split_once(',')?;
+            Some(Event::WorkspaceActivated {
+                id: id.parse().ok()?,
+                focused: true,
+            })
+        }
+        _ => {
+            tracing::trace!("Skipping Hyprland event: {}", line);
+            None
+        }
+    }
+}
+
+/// Window address, with or without `0x`
+fn parse_address(address: &str) -> Option<u64> {
+    let address = address.trim();
+    u64::from_str_radix(address.strip_prefix("0x").unwrap_or(address), 16).ok()
+}
+
+/// Fail unless a dispatch was answered with `ok`
+fn dispatch_result(response: &str) -> Result<()> {
+    match response.trim() {
+        "ok" => Ok(()),
+        error => anyhow::bail!("Hyprland error: {}", error),
+    }
+}
+
+/// Workspace argument of dispatchers for `reference`
+///
+/// Numbered workspaces have their number as id.
+fn workspace_arg(reference: &WorkspaceReference) -> String {
+    match reference {
+        WorkspaceReference::Id(id) => id.to_string(),
+        WorkspaceReference::Index(index) => index.to_string(),
+        WorkspaceReference::Name(name) => format!("name:{}", name),
+    }
+}
+
+/// Workspaces from `j/workspaces`, active as `j/monitors` says
+fn workspace_infos(workspaces: Vec<HyprWorkspace>, monitors: &[HyprMonitor]) -> Vec<WorkspaceInfo> {
+    workspaces
+        .into_iter()
+        .filter(|ws| ws.id > 0)
+        .map(|ws| {
+            let shown_on = monitors.iter().find(|m| m.active_workspace.id == ws.id);
+            WorkspaceInfo {
+                id: ws.id as u64,
+                idx: ws.id as u64,
+                name: (ws.name != ws.id.to_string()).then_some(ws.name),
+                output: ws.monitor,
+                is_active: shown_on.is_some(),
+                is_focused: shown_on.is_some_and(|m| m.focused),
+            }
+        })
+        .collect()
+}
+
+/// Window from `j/clients` or `j/activewindow`
+#[derive(Debug, Deserialize)]
+struct HyprClient {
+    address: String,
+    #[serde(default)]
+    class: String,
+    #[serde(default)]
+    title: String,
+    #[serde(default)]
+    floating: bool,
+    #[serde(default)]
+    pid: Option<i32>,
+    workspace: HyprWorkspaceRef,
+    /// 0 for the focused window, then in order of last focus
+    #[serde(rename = "focusHistoryID", default = "unfocused")]
+    focus_history_id: i64,
+}
+
+fn unfocused() -> i64 {
+    -1
+}
+
+impl HyprClient {
+    /// None for windows on special workspaces
+    fn window_info(&self) -> Option<WindowInfo> {
+        if self.workspace.id <= 0 {
+            return None;
+        }
+        Some(WindowInfo {
+            id: parse_address(&self.address)?,
+            app_id: self.class.clone(),
+            title: self.title.clone(),
+            is_floating: self.floating,
+            is_focused: self.focus_history_id == 0,
+            pid: self.pid.filter(|&pid| pid > 0),
+            workspace_id: Some(self.workspace.id as u64),
+        })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct HyprWorkspaceRef {
+    id: i64,
+}
+
+/// Workspace from `j/workspaces`
+#[derive(Debug, Deserialize)]
+struct HyprWorkspace {
+    id: i64,
+    name: String,
+    #[serde(default)]
+    monitor: Option<String>,
+}
+
+/// Monitor from `j/monitors`
+#[derive(Debug, Deserialize)]
+struct HyprMonitor {
+    #[serde(default)]
+    focused: bool,
+    #[serde(rename = "activeWorkspace")]
+    active_workspace: HyprWorkspaceRef,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+
+    #[test]
+    fn test_parse_clients() {
+        // Trimmed from `hyprctl clients -j`
+        let json = r#"[{"address":"0x55d0c3a1e2f0","mapped":true,"hidden":false,"at":[10,40],"size":[940,1030],
+            "workspace":{"id":1,"name":"1"},"floating":false,"monitor":0,"class":"firefox","title":"Rust docs",
+            "pid":1200,"xwayland":false,"pinned":false,"fullscreen":0,"focusHistoryID":1},
+            {"address":"0x55d0c3b00010","workspace":{"id":1,"name":"1"},"floating":true,"class":"foot",
+            "title":"drun","pid":1400,"focusHistoryID":0},
+            {"address":"0x55d0c3c00020","workspace":{"id":-98,"name":"special:magic"},"floating":false,
+            "class":"foot","title":"scratch","pid":1500,"focusHistoryID":2}]"#;
+        let clients: Vec<HyprClient> = serde_json::from_str(json).unwrap();
+        let windows: Vec<WindowInfo> = clients.iter().filter_map(HyprClient::window_info).collect();
+
+        assert_eq!(windows.len(), 2);
+        assert_eq!(windows[0].id, 0x55d0c3a1e2f0);
+        assert_eq!(windows[0].app_id, "firefox");
+        assert_eq!(windows[0].title, "Rust docs");
+        assert_eq!(windows[0].workspace_id, Some(1));
+        assert!(!windows[0].is_focused);
+        assert!(windows[1].is_focused && windows[1].is_floating);
+        assert_eq!(windows[1].pid, Some(1400));
+    }
+
+    #[test]
+    fn test_parse_workspaces() {
+        // Trimmed from `hyprctl workspaces -j` and `hyprctl monitors -j`
+        let workspaces = r#"[{"id":1,"name":"1","monitor":"eDP-1","windows":2},
+            {"id":4,"name":"chat","monitor":"DP-1","windows":1},
+            {"id":5,"name":"5","monitor":"DP-1","windows":0},
+            {"id":-98,"name":"special:magic","monitor":"eDP-1","windows":1}]"#;
+        let monitors = r#"[{"id":0,"name":"eDP-1","focused":true,"activeWorkspace":{"id":1,"name":"1"}},
+            {"id":1,"name":"DP-1","focused":false,"activeWorkspace":{"id":4,"name":"chat"}}]"#;
+        let workspaces = workspace_infos(
+            serde_json::from_str(workspaces).unwrap(),
+            &serde_json::from_str::<Vec<HyprMonitor>>(monitors).unwrap(),
+        );
+
+        assert_eq!(workspaces.len(), 3);
+        assert!(workspaces[0].is_active && workspaces[0].is_focused);
+        assert_eq!(workspaces[0].name, None);
+        assert_eq!(workspaces[1].name.as_deref(), Some("chat"));
+        assert_eq!(workspaces[1].output.as_deref(), Some("DP-1"));
+        assert!(workspaces[1].is_active && !workspaces[1].is_focused);
+        assert!(!workspaces[2].is_active);
+    }
+
+    #[test]
+    fn test_parse_events() {
+        match parse_event("openwindow>>55d0c3a1e2f0,2,firefox,Rust docs, the book") {
+            Some(Event::WindowOpenedOrChanged { window }) => {
+                assert_eq!(window.id, 0x55d0c3a1e2f0);
+                assert_eq!(window.app_id, "firefox");
+                assert_eq!(window.title, "Rust docs, the book");
+            }
+            other => panic!("unexpected event: {:?}", other),
+        }
+        assert!(matches!(
+            parse_event("activewindowv2>>55d0c3a1e2f0"),
+            Some(Event::WindowFocusChanged { id: Some(0x55d0c3a1e2f0) })
+        ));
+        assert!(matches!(
+            parse_event("activewindowv2>>,"),
+            Some(Event::WindowFocusChanged { id: None })
+        ));
+        assert!(matches!(
+            parse_event("workspacev2>>4,chat"),
+            Some(Event::WorkspaceActivated { id: 4, .. })
+        ));
+        assert!(parse_event("activelayout>>AT Translated Set 2 keyboard,us").is_none());
+    }
+
+    #[test]
+    fn test_dispatch_args() {
+        assert!(dispatch_result("ok").is_ok());
+        assert_eq!(
+            dispatch_result("Invalid dispatcher").unwrap_err().to_string(),
+            "Hyprland error: Invalid dispatcher"
+        );
+        assert_eq!(workspace_arg(&WorkspaceReference::Id(4)), "4");
+        assert_eq!(workspace_arg(&WorkspaceReference::Name("chat".to_string())), "name:chat");
+    }
+
+    #[tokio::test]
+    async fn test_dispatch_round_trip() {
+        let dir = TempDir::new("hypr");
+        let path = dir.join(".socket.sock");
+        let listener = tokio::net::UnixListener::bind(&path).unwrap();
+
+        // Answers one request like Hyprland, handing back what it got
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = String::new();
+            stream.read_to_string(&mut request).await.unwrap();
+            stream.write_all(b"ok").await.unwrap();
+            request
+        });
+
+        let client = HyprlandClient::with_socket(path, Duration::from_secs(1));
+        Compositor::move_window_to_workspace(&client, Some(0x55d0), WorkspaceReference::Id(3), false)
+            .await
+            .unwrap();
+        assert_eq!(server.await.unwrap(), "dispatch movetoworkspacesilent 3,address:0x55d0");
+    }
+}
@@ -0,0 +1,295 @@
+//! On-disk cache of rasterized icons.
+//!
+//! Rasterizing SVGs with resvg is the slowest part of showing icons. The
+//! loader thread keeps each decoded icon, scaled down to `icons.size`, as
+//! raw RGBA in `~/.cache/darkwall-drun/icons/`. A file's name is a hash of
+//! the source path, its mtime and size, the icon size and the symbolic
+//! icon colors, so a changed icon file or theme is simply a miss. The
+//! cache is emptied when `icons.size` changes; unreadable cache files
+//! count as misses.
+//!
+//! Misses leave the stale files behind, so the cache keeps only the
+//! [`MAX_ICONS`] most recently used icons: a hit touches its file and
+//! opening the cache deletes the least recently touched ones.
+
+use anyhow::Result;
+use image::{DynamicImage, RgbaImage};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// File in the cache dir holding the icon size the cache was built for
+const SIZE_FILE: &str = "size";
+
+/// Start of every cached icon file
+const MAGIC: &[u8; 4] = b"DRI1";
+
+/// Icons kept on disk; at 48px that is under 20 MB
+const MAX_ICONS: usize = 2000;
+
+/// Default cache location
+pub fn default_dir() -> Option<PathBuf> {
+    dirs::cache_dir().map(|d| d.join("darkwall-drun").join("icons"))
+}
+
+/// Delete every cached icon (`--clear-icon-cache`)
+pub fn clear(dir: &Path) -> Result<()> {
+    match std::fs::remove_dir_all(dir) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+/// Cached icons of one icon size
+#[derive(Debug)]
+pub struct IconCache {
+    /// None disables the cache
+    dir: Option<PathBuf>,
+    size: u16,
+    /// Anything else the pixels depend on (symbolic icon colors); part
+    /// of every key
+    variant: String,
+    /// Icons served from the cache
+    pub hits: usize,
+    /// Icons that had to be decoded
+    pub misses: usize,
+}
+
+impl IconCache {
+    /// Open the cache in `dir` for icons of `size` pixels, emptying it if
+    /// it was built for another size
+    pub fn open(dir: Option<PathBuf>, size: u16, variant: &str) -> Self {
+        let dir = dir.filter(|dir| match prepare_dir(dir, size) {
+            Ok(()) => true,
+            Err(e) => {
+                tracing::warn!("Icon cache disabled: {}: {}", dir.display(), e);
+                false
+            }
+        });
+        if let Some(dir) = &dir {
+            if let Err(e) = evict(dir, MAX_ICONS) {
+                tracing::debug!("Failed to trim icon cache {}: {}", dir.display(), e);
+            }
+        }
+        Self {
+            dir,
+            size,
+            variant: variant.to_string(),
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    /// Icon size in pixels
+    pub fn size(&self) -> u16 {
+        self.size
+    }
+
+    /// The cached icon for the file at `source`
+    pub fn get(&mut self, source: &Path) -> Option<DynamicImage> {
+        let path = self.entry_path(source);
+        let image = path.as_deref().and_then(read_icon);
+        if let (Some(path), Some(_)) = (&path, &image) {
+            self.hits += 1;
+            // Recently used icons survive the next eviction
+            let touched = std::fs::File::options()
+                .append(true)
+                .open(path)
+                .and_then(|file| file.set_modified(SystemTime::now()));
+            if let Err(e) = touched {
+                tracing::debug!("Failed to touch cached icon {}: {}", path.display(), e);
+            }
+        } else {
+            self.misses += 1;
+        }
+        image
+    }
+
+    /// Scale a decoded icon down to the icon size and cache it
+    pub fn insert(&self, source: &Path, image: DynamicImage) -> DynamicImage {
+        let size = u32::from(self.size);
+        let image = if image.width() > size || image.height() > size {
+            image.resize(size, size, image::imageops::FilterType::Lanczos3)
+        } else {
+            image
+        };
+        if let Some(path) = self.entry_path(source) {
+            if let Err(e) = write_icon(&path, &image.to_rgba8()) {
+                tracing::debug!("Failed to cache icon {}: {}", source.display(), e);
+            }
+        }
+        image
+    }
+
+    /// Cache file for `source` as it is now; None if it can't be read
+    fn entry_path(&self, source: &Path) -> Option<PathBuf> {
+        let dir = self.dir.as_ref()?;
+        let meta = std::fs::metadata(source).ok()?;
+        let mtime = meta.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+
+        let mut hash = Fnv::default();
+        hash.write(source.as_os_str().as_encoded_bytes());
+        hash.write(&mtime.as_secs().to_le_bytes());
+        hash.write(&mtime.subsec_nanos().to_le_bytes());
+        hash.write(&meta.len().to_le_bytes());
+        hash.write(&self.size.to_le_bytes());
+        hash.write(self.variant.as_bytes());
+        Some(dir.join(format!("{:016x}.rgba", hash.0)))
+    }
+}
+
+/// Create `dir`, emptying it if it holds icons of another size
+fn prepare_dir(dir: &Path, size: u16) -> Result<()> {
+    let size_file = dir.join(SIZE_FILE);
+    let cached_size = std::fs::read_to_string(&size_file).ok();
+    if cached_size.as_deref().map(str::trim) != Some(size.to_string().as_str()) {
+        if cached_size.is_some() {
+            tracing::info!("Icon size changed, clearing icon cache");
+        }
+        clear(dir)?;
+        std::fs::create_dir_all(dir)?;
+        std::fs::write(&size_file, size.to_string())?;
+    }
+    Ok(())
+}
+
+/// Delete all but the `keep` most recently touched icons in `dir`
+fn evict(dir: &Path, keep: usize) -> Result<()> {
+    let mut icons = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        if path.extension().is_some_and(|ext| ext == "rgba") {
+            icons.push((entry.metadata()?.modified()?, path));
+        }
+    }
+    if icons.len() <= keep {
+        return Ok(());
+    }
+    icons.sort_by_key(|(modified, _)| std::cmp::Reverse(*modified));
+    tracing::debug!("Evicting {} icons from the icon cache", icons.len() - keep);
+    for (_, path) in &icons[keep..] {
+        std::fs::remove_file(path)?;
+    }
+    Ok(())
+}
+
+/// Read a cached icon: magic, width and height (u32 LE), RGBA pixels
+fn read_icon(path: &Path) -> Option<DynamicImage> {
+    let data = std::fs::read(path).ok()?;
+    let rest = data.strip_prefix(MAGIC)?;
+    let width = u32::from_le_bytes(rest.get(0..4)?.try_into().ok()?);
+    let height = u32::from_le_bytes(rest.get(4..8)?.try_into().ok()?);
+    RgbaImage::from_raw(width, height, rest[8..].to_vec()).map(DynamicImage::ImageRgba8)
+}
+
+/// Write a cached icon; through a temporary file so a concurrent reader
+/// never sees half of it
+fn write_icon(path: &Path, image: &RgbaImage) -> Result<()> {
+    let tmp = path.with_extension("tmp");
+    let mut file = std::fs::File::create(&tmp)?;
+    file.write_all(MAGIC)?;
+    file.write_all(&image.width().to_le_bytes())?;
+    file.write_all(&image.height().to_le_bytes())?;
+    file.write_all(image.as_raw())?;
+    drop(file);
+    std::fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+/// 64-bit FNV-1a; unlike `DefaultHasher` it is the same in every build,
+/// so cache file names stay valid across upgrades
+struct Fnv(u64);
+
+impl Default for Fnv {
+    fn default() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Fnv {
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 ^= u64::from(byte);
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+
+    fn write_source(dir: &Path) -> PathBuf {
+        let source = dir.join("app.png");
+        std::fs::write(&source, "not really a png").unwrap();
+        source
+    }
+
+    #[test]
+    fn test_round_trip_scaled_to_icon_size() {
+        let dir = TempDir::new("icon-cache-round-trip");
+        let source = write_source(&dir);
+        let mut cache = IconCache::open(Some(dir.join("cache")), 32, "");
+
+        assert!(cache.get(&source).is_none());
+        let image = DynamicImage::ImageRgba8(RgbaImage::from_pixel(128, 64, image::Rgba([1, 2, 3, 4])));
+        let stored = cache.insert(&source, image);
+        assert_eq!((stored.width(), stored.height()), (32, 16));
+
+        let cached = cache.get(&source).unwrap();
+        assert_eq!(cached.to_rgba8(), stored.to_rgba8());
+        assert_eq!((cache.hits, cache.misses), (1, 1));
+    }
+
+    #[test]
+    fn test_size_change_empties_cache() {
+        let dir = TempDir::new("icon-cache-size-change");
+        let source = write_source(&dir);
+        let image = DynamicImage::ImageRgba8(RgbaImage::new(16, 16));
+
+        IconCache::open(Some(dir.join("cache")), 32, "").insert(&source, image);
+        let files = |dir: &Path| std::fs::read_dir(dir).unwrap().count();
+        assert_eq!(files(&dir.join("cache")), 2);
+
+        let mut cache = IconCache::open(Some(dir.join("cache")), 48, "");
+        assert_eq!(files(&dir.join("cache")), 1);
+        assert!(cache.get(&source).is_none());
+    }
+
+    #[test]
+    fn test_changed_source_misses() {
+        let dir = TempDir::new("icon-cache-changed");
+        let source = write_source(&dir);
+        let mut cache = IconCache::open(Some(dir.join("cache")), 32, "");
+        cache.insert(&source, DynamicImage::ImageRgba8(RgbaImage::new(16, 16)));
+
+        std::fs::write(&source, "a different icon").unwrap();
+        assert!(cache.get(&source).is_none());
+    }
+
+    #[test]
+    fn test_evicts_least_recently_used() {
+        let dir = TempDir::new("icon-cache-evict");
+        let mut cache = IconCache::open(Some(dir.join("cache")), 32, "");
+        let sources: Vec<_> = (0..3)
+            .map(|n| {
+                let source = dir.join(format!("app{}.png", n));
+                std::fs::write(&source, format!("icon {}", n)).unwrap();
+                cache.insert(&source, DynamicImage::ImageRgba8(RgbaImage::new(16, 16)));
+                // Apart in mtime even on coarse filesystems
+                let file = std::fs::File::options().append(true).open(cache.entry_path(&source).unwrap()).unwrap();
+                file.set_modified(UNIX_EPOCH + std::time::Duration::from_secs(1000 * (n + 1))).unwrap();
+                source
+            })
+            .collect();
+
+        // Using the oldest makes the second the least recently used
+        assert!(cache.get(&sources[0]).is_some());
+        evict(&dir.join("cache"), 2).unwrap();
+        assert!(cache.get(&sources[0]).is_some());
+        assert!(cache.get(&sources[1]).is_none());
+        assert!(cache.get(&sources[2]).is_some());
+    }
+}
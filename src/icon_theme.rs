@@ -0,0 +1,256 @@
+//! Icon theme `index.theme` files
+//!
+//! Per the freedesktop icon theme spec, a theme lists its parents in
+//! `Inherits=` and describes each icon directory (nominal size, scale,
+//! how it may be scaled, context) in a section of its own. Icons are
+//! looked up in the directory that matches the wanted size, or the
+//! closest one.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// How the icons of a directory may be scaled
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DirKind {
+    /// Only at the nominal size
+    Fixed,
+    /// Anywhere from MinSize to MaxSize
+    Scalable,
+    /// Within Threshold of the nominal size
+    Threshold,
+}
+
+/// One `Directories=` entry of a theme
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ThemeDir {
+    /// Path relative to the theme directory, e.g. `48x48/apps`
+    pub path: String,
+    pub size: u16,
+    pub scale: u16,
+    pub kind: DirKind,
+    pub min_size: u16,
+    pub max_size: u16,
+    pub threshold: u16,
+    /// `Applications`, `MimeTypes`, ...
+    pub context: Option<String>,
+}
+
+impl ThemeDir {
+    /// Whether icons here can be shown at `size` without scaling
+    pub fn matches_size(&self, size: u16, scale: u16) -> bool {
+        if self.scale != scale {
+            return false;
+        }
+        match self.kind {
+            DirKind::Fixed => self.size == size,
+            DirKind::Scalable => (self.min_size..=self.max_size).contains(&size),
+            DirKind::Threshold => {
+                self.size.saturating_sub(self.threshold) <= size && size <= self.size + self.threshold
+            }
+        }
+    }
+
+    /// How far icons here are from `size`, in pixels
+    pub fn size_distance(&self, size: u16, scale: u16) -> u32 {
+        let wanted = u32::from(size) * u32::from(scale);
+        let (min, max) = match self.kind {
+            DirKind::Fixed => (self.size, self.size),
+            DirKind::Scalable => (self.min_size, self.max_size),
+            DirKind::Threshold => (
+                self.size.saturating_sub(self.threshold),
+                self.size + self.threshold,
+            ),
+        };
+        let min = u32::from(min) * u32::from(self.scale);
+        let max = u32::from(max) * u32::from(self.scale);
+        if wanted < min {
+            min - wanted
+        } else {
+            wanted.saturating_sub(max)
+        }
+    }
+}
+
+/// A parsed `index.theme`
+#[derive(Debug, Clone, Default)]
+pub struct IconTheme {
+    /// Parent themes, in lookup order
+    pub inherits: Vec<String>,
+    /// Icon directories; the `Applications` context first, otherwise in
+    /// the order the file lists them
+    pub dirs: Vec<ThemeDir>,
+}
+
+impl IconTheme {
+    /// Load `<base>/<name>/index.theme` from the first search path that has it
+    pub fn find(name: &str, search_paths: &[PathBuf]) -> Option<Self> {
+        search_paths.iter().find_map(|base| Self::load(&base.join(name).join("index.theme")))
+    }
+
+    fn load(path: &Path) -> Option<Self> {
+        let content = std::fs::read_to_string(path).ok()?;
+        let theme = Self::parse(&content);
+        if theme.is_none() {
+            tracing::debug!("No [Icon Theme] section in {}", path.display());
+        }
+        theme
+    }
+
+    /// Parse the content of an `index.theme`; None without an
+    /// `[Icon Theme]` section
+    pub fn parse(content: &str) -> Option<Self> {
+        let sections = parse_sections(content);
+        let main = sections.get("Icon Theme")?;
+        let list = |key: &str| -> Vec<String> {
+            main.get(key)
+                .map(|value| {
+                    value
+                        .split(',')
+                        .map(str::trim)
+                        .filter(|s| !s.is_empty())
+                        .map(String::from)
+                        .collect()
+                })
+                .unwrap_or_default()
+        };
+
+        let mut dirs: Vec<ThemeDir> = list("Directories")
+            .into_iter()
+            .chain(list("ScaledDirectories"))
+            .filter_map(|path| {
+                let keys = sections.get(path.as_str())?;
+                let number = |key: &str| keys.get(key).and_then(|v| v.trim().parse::<u16>().ok());
+                let size = number("Size")?;
+                let kind = match keys.get("Type").map(|t| t.trim()) {
+                    Some("Fixed") => DirKind::Fixed,
+                    Some("Scalable") => DirKind::Scalable,
+                    _ => DirKind::Threshold,
+                };
+                Some(ThemeDir {
+                    size,
+                    scale: number("Scale").unwrap_or(1),
+                    kind,
+                    min_size: number("MinSize").unwrap_or(size),
+                    max_size: number("MaxSize").unwrap_or(size),
+                    threshold: number("Threshold").unwrap_or(2),
+                    context: keys.get("Context").map(|c| c.trim().to_string()),
+                    path,
+                })
+            })
+            .collect();
+        // Launcher icons are nearly always application icons
+        dirs.sort_by_key(|dir| dir.context.as_deref() != Some("Applications"));
+
+        Some(Self {
+            inherits: list("Inherits"),
+            dirs,
+        })
+    }
+}
+
+/// Sections of an ini-style file: section name to key/value pairs
+fn parse_sections(content: &str) -> HashMap<&str, HashMap<&str, &str>> {
+    let mut sections: HashMap<&str, HashMap<&str, &str>> = HashMap::new();
+    let mut current = None;
+    for line in content.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            current = Some(name);
+            sections.entry(name).or_default();
+        } else if let (Some(section), Some((key, value))) = (current, line.split_once('=')) {
+            sections
+                .entry(section)
+                .or_default()
+                .entry(key.trim())
+                .or_insert(value);
+        }
+    }
+    sections
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INDEX: &str = "[Icon Theme]
+Name=Papirus-Dark
+Inherits=Papirus, breeze-dark,hicolor
+Directories=16x16/actions,48x48/apps,scalable/apps,64x64/mimetypes
+ScaledDirectories=48x48@2x/apps
+
+# A comment
+[16x16/actions]
+Size=16
+Context=Actions
+Type=Fixed
+
+[48x48/apps]
+Size=48
+Context=Applications
+Type=Threshold
+
+[scalable/apps]
+Size=64
+MinSize=8
+MaxSize=512
+Context=Applications
+Type=Scalable
+
+[64x64/mimetypes]
+Size=64
+Context=MimeTypes
+Type=Fixed
+
+[48x48@2x/apps]
+Size=48
+Scale=2
+Context=Applications
+Type=Fixed
+";
+
+    #[test]
+    fn test_parse_index_theme() {
+        let theme = IconTheme::parse(INDEX).unwrap();
+        assert_eq!(theme.inherits, ["Papirus", "breeze-dark", "hicolor"]);
+
+        let paths: Vec<_> = theme.dirs.iter().map(|d| d.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            ["48x48/apps", "scalable/apps", "48x48@2x/apps", "16x16/actions", "64x64/mimetypes"]
+        );
+
+        let scalable = &theme.dirs[1];
+        assert_eq!(scalable.kind, DirKind::Scalable);
+        assert_eq!((scalable.min_size, scalable.max_size), (8, 512));
+        assert_eq!(theme.dirs[2].scale, 2);
+        assert_eq!(theme.dirs[0].threshold, 2);
+
+        assert!(IconTheme::parse("[Desktop Entry]\nName=x\n").is_none());
+    }
+
+    #[test]
+    fn test_size_matching() {
+        let theme = IconTheme::parse(INDEX).unwrap();
+        let dir = |path: &str| theme.dirs.iter().find(|d| d.path == path).unwrap();
+
+        // Threshold: 48 ± 2
+        assert!(dir("48x48/apps").matches_size(50, 1));
+        assert!(!dir("48x48/apps").matches_size(51, 1));
+        assert_eq!(dir("48x48/apps").size_distance(64, 1), 14);
+        assert_eq!(dir("48x48/apps").size_distance(32, 1), 14);
+
+        assert!(dir("scalable/apps").matches_size(256, 1));
+        assert_eq!(dir("scalable/apps").size_distance(256, 1), 0);
+
+        assert!(dir("16x16/actions").matches_size(16, 1));
+        assert_eq!(dir("16x16/actions").size_distance(64, 1), 48);
+
+        // Scaled directories only match at their scale
+        assert!(!dir("48x48@2x/apps").matches_size(48, 1));
+        assert!(dir("48x48@2x/apps").matches_size(48, 2));
+        assert_eq!(dir("48x48@2x/apps").size_distance(64, 1), 32);
+    }
+}
@@ -7,52 +7,169 @@
 //! - Sixel graphics (foot, mlterm, xterm)
 //! - iTerm2 protocol (iTerm2, WezTerm)
 //!
-//! NO FALLBACKS. Either real images or nothing.
+//! Without a graphics protocol, `icons.fallback` can show glyphs instead
+//! (see `glyphs.rs`); this module only deals with real images.
 
 use image::DynamicImage;
+use ratatui::style::Color;
 use ratatui_image::picker::Picker;
 use ratatui_image::protocol::StatefulProtocol;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
-use std::sync::Arc;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Weak};
+use std::thread;
+
+use crate::icon_cache::IconCache;
+use crate::ui::theme::Theme;
+use crate::icon_theme::IconTheme;
 use parking_lot::Mutex;
 
+/// Where icons come from (`[icons]` in config)
+#[derive(Debug, Clone, Default)]
+pub struct IconOptions {
+    /// Icon size in pixels
+    pub size: u16,
+    /// Icon theme; None reads it from GTK settings
+    pub theme: Option<String>,
+    /// Icon directories searched before the standard ones
+    pub extra_search_paths: Vec<PathBuf>,
+    /// Colors for symbolic icons; None draws them as they are
+    pub symbolic_colors: Option<SymbolicColors>,
+}
+
+/// Colors symbolic icons are drawn in, taken from the UI theme
+///
+/// Symbolic icons are single-color shapes meant to be recolored; as
+/// shipped they are dark gray, nearly invisible on a dark background.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SymbolicColors {
+    pub foreground: [u8; 3],
+    /// For shapes with class="success", "warning" and "error"
+    pub success: [u8; 3],
+    pub warning: [u8; 3],
+    pub error: [u8; 3],
+}
+
+impl SymbolicColors {
+    /// Colors from the UI theme: its foreground, exit status colors and
+    /// accent; None if the foreground isn't an RGB color
+    pub fn from_theme(theme: &Theme) -> Option<Self> {
+        let rgb = |color: Color| match color {
+            Color::Rgb(r, g, b) => Some([r, g, b]),
+            _ => None,
+        };
+        let foreground = rgb(theme.foreground)?;
+        Some(Self {
+            foreground,
+            success: rgb(theme.exit_success).unwrap_or(foreground),
+            warning: rgb(theme.accent).unwrap_or(foreground),
+            error: rgb(theme.exit_failure).unwrap_or(foreground),
+        })
+    }
+
+    /// Style sheet that recolors the SVG `data` from `path`, None if it
+    /// isn't symbolic
+    ///
+    /// Icons named `*-symbolic` or using the state classes get every
+    /// shape filled like GTK does; other icons only get `currentColor`.
+    fn style_sheet(&self, path: &Path, data: &[u8]) -> Option<String> {
+        let text = String::from_utf8_lossy(data);
+        let named_symbolic = path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .is_some_and(|stem| stem.ends_with("-symbolic"));
+        let state_classes = ["success", "warning", "error"]
+            .iter()
+            .any(|class| text.contains(&format!("class=\"{}\"", class)));
+
+        let color = format!("svg {{ color: {}; }}", hex(self.foreground));
+        if named_symbolic || state_classes {
+            Some(format!(
+                "{} rect, circle, path {{ fill: {} !important; }} \
+                 .success {{ fill: {} !important; }} \
+                 .warning {{ fill: {} !important; }} \
+                 .error {{ fill: {} !important; }}",
+                color,
+                hex(self.foreground),
+                hex(self.success),
+                hex(self.warning),
+                hex(self.error),
+            ))
+        } else if text.contains("currentColor") {
+            Some(color)
+        } else {
+            None
+        }
+    }
+
+    /// Part of the disk cache key: icons drawn in other colors are
+    /// different icons
+    fn cache_key(&self) -> String {
+        [self.foreground, self.success, self.warning, self.error].map(hex).join(",")
+    }
+}
+
+fn hex([r, g, b]: [u8; 3]) -> String {
+    format!("#{:02x}{:02x}{:02x}", r, g, b)
+}
+
 /// Icon manager - handles icon loading and caching
-/// Must be initialized once at startup before entering raw mode
+///
+/// Icons are resolved and rasterized on a loader thread (see
+/// [`IconManager::start_loader`]); the draw path only reads the cache.
 pub struct IconManager {
     /// The picker determines the graphics protocol and font size
     picker: Option<Picker>,
     /// Cache of loaded icon protocols by entry ID
     cache: HashMap<String, Arc<Mutex<StatefulProtocol>>>,
     /// Failed icon lookups (don't retry)
-    failed: std::collections::HashSet<String>,
+    failed: HashSet<String>,
     /// Icon size in pixels
     icon_size: u16,
     /// Whether graphics are supported
     graphics_supported: bool,
     /// Icon theme search paths and themes
     icon_lookup: IconLookup,
+    /// Where the loader caches rasterized icons (None: no disk cache)
+    cache_dir: Option<PathBuf>,
+    /// Colors for symbolic icons
+    symbolic_colors: Option<SymbolicColors>,
+    /// Requests to the loader thread, once it runs
+    loader: Option<Sender<IconRequest>>,
+    /// Entries sent to the loader and not loaded yet, with the icon name
+    /// asked for
+    queued: HashMap<String, String>,
+    /// Entries on screen at the last request; the loader skips queued
+    /// requests for the others
+    wanted: HashSet<String>,
+    /// Icons were added to the cache since the last [`IconManager::take_arrived`]
+    arrived: bool,
+}
+
+/// An icon for the loader thread to load
+struct IconRequest {
+    entry_id: String,
+    icon_name: String,
 }
 
 impl IconManager {
-    /// Create a new icon manager by querying the terminal
-    /// MUST be called before entering raw mode / alternate screen
-    pub fn new(icon_size: u16) -> Self {
-        // Try to create a picker by querying the terminal
-        let picker = match Picker::from_query_stdio() {
-            Ok(p) => {
-                tracing::info!("Graphics protocol detected: {:?}", p.protocol_type());
-                Some(p)
-            }
-            Err(e) => {
-                tracing::debug!("No graphics protocol available: {}", e);
-                None
-            }
-        };
+    /// Create a new icon manager; `picker` comes from
+    /// [`crate::graphics::detect`], None without graphics
+    pub fn new(picker: Option<Picker>, options: &IconOptions, cache_dir: Option<PathBuf>) -> Self {
+        let mut manager = Self::with_picker(picker, IconLookup::new(options), options.size, cache_dir);
+        manager.symbolic_colors = options.symbolic_colors;
+        manager
+    }
 
+    fn with_picker(
+        picker: Option<Picker>,
+        icon_lookup: IconLookup,
+        icon_size: u16,
+        cache_dir: Option<PathBuf>,
+    ) -> Self {
         let graphics_supported = picker.is_some();
-        let icon_lookup = IconLookup::new();
-        
+
         tracing::info!("Icon theme: {}, search paths: {}", 
             icon_lookup.theme, 
             icon_lookup.search_paths.len()
@@ -61,10 +178,43 @@ impl IconManager {
         Self {
             picker,
             cache: HashMap::new(),
-            failed: std::collections::HashSet::new(),
+            failed: HashSet::new(),
             icon_size,
             graphics_supported,
             icon_lookup,
+            cache_dir,
+            symbolic_colors: None,
+            loader: None,
+            queued: HashMap::new(),
+            wanted: HashSet::new(),
+            arrived: false,
+        }
+    }
+
+    /// Start the thread that loads requested icons into the cache
+    ///
+    /// The thread only holds a weak reference to the manager and stops
+    /// when the manager is dropped. Does nothing without graphics.
+    pub fn start_loader(manager: &Arc<Mutex<IconManager>>) {
+        let mut mgr = manager.lock();
+        let Some(picker) = mgr.picker else {
+            return;
+        };
+        let lookup = mgr.icon_lookup.clone();
+        let size = mgr.icon_size;
+        let cache_dir = mgr.cache_dir.clone();
+        let symbolic_colors = mgr.symbolic_colors;
+        let weak = Arc::downgrade(manager);
+        let (tx, rx) = mpsc::channel();
+        let spawned = thread::Builder::new()
+            .name("icon-loader".to_string())
+            .spawn(move || {
+                let loader = IconLoader::new(lookup, cache_dir, size, symbolic_colors);
+                run_loader(rx, weak, picker, loader)
+            });
+        match spawned {
+            Ok(_) => mgr.loader = Some(tx),
+            Err(e) => tracing::warn!("Failed to start icon loader: {}", e),
         }
     }
 
@@ -78,56 +228,69 @@ impl IconManager {
         self.cache.get(entry_id).cloned()
     }
 
-    /// Try to load ONE icon that isn't cached yet (call once per frame to avoid blocking)
-    /// Returns true if an icon was loaded, false if nothing to load
-    pub fn try_load_one<'a>(&mut self, entries: impl Iterator<Item = (&'a str, Option<&'a str>)>) -> bool {
-        let picker = match self.picker.as_mut() {
-            Some(p) => p,
-            None => return false,
+    /// Ask the loader for the icons of the entries on screen, in order
+    ///
+    /// Entries from an earlier call that are not in this one have
+    /// scrolled away; their queued requests are dropped.
+    pub fn request_visible<'a>(&mut self, entries: impl Iterator<Item = (&'a str, Option<&'a str>)>) {
+        let Some(loader) = self.loader.as_ref() else {
+            return;
         };
+        self.wanted.clear();
 
         for (entry_id, icon_name) in entries {
-            // Skip if already cached or failed
-            if self.cache.contains_key(entry_id) || self.failed.contains(entry_id) {
+            self.wanted.insert(entry_id.to_string());
+            // Skip if already cached, failed or on its way
+            if self.cache.contains_key(entry_id)
+                || self.failed.contains(entry_id)
+                || self.queued.contains_key(entry_id)
+            {
                 continue;
             }
 
-            let icon_name = match icon_name {
-                Some(n) => n,
-                None => {
-                    self.failed.insert(entry_id.to_string());
-                    continue;
-                }
+            let Some(icon_name) = icon_name else {
+                self.failed.insert(entry_id.to_string());
+                continue;
             };
-
-            // Resolve icon path
-            let icon_path = match self.icon_lookup.find_icon(icon_name, self.icon_size) {
-                Some(p) => p,
-                None => {
-                    tracing::debug!("Icon not found: {}", icon_name);
-                    self.failed.insert(entry_id.to_string());
-                    continue;
-                }
+            let request = IconRequest {
+                entry_id: entry_id.to_string(),
+                icon_name: icon_name.to_string(),
             };
+            if loader.send(request).is_ok() {
+                self.queued.insert(entry_id.to_string(), icon_name.to_string());
+            }
+        }
+    }
 
-            // Load the image with transparency support
-            let dyn_img = match load_icon_image(&icon_path) {
-                Some(img) => img,
-                None => {
-                    self.failed.insert(entry_id.to_string());
-                    continue;
-                }
-            };
+    /// Forget the icon of `entry_id` (loaded, failed or queued), so the
+    /// next request loads it again
+    ///
+    /// A request already sent to the loader is dropped when it gets to it.
+    pub fn invalidate(&mut self, entry_id: &str) {
+        self.cache.remove(entry_id);
+        self.failed.remove(entry_id);
+        self.queued.remove(entry_id);
+    }
 
-            // Create the protocol and cache it
-            let protocol = picker.new_resize_protocol(dyn_img);
-            let arc = Arc::new(Mutex::new(protocol));
-            self.cache.insert(entry_id.to_string(), arc);
-            
-            return true; // Only load one per call
-        }
+    /// Try failed icons again on their next request (e.g. after icons
+    /// were installed)
+    pub fn retry_failed(&mut self) {
+        self.failed.clear();
+    }
+
+    /// Whether `request` is still the one queued for its entry
+    fn is_queued(&self, request: &IconRequest) -> bool {
+        self.queued.get(&request.entry_id) == Some(&request.icon_name)
+    }
+
+    /// Whether requested icons are still loading (redraw soon)
+    pub fn is_loading(&self) -> bool {
+        !self.queued.is_empty()
+    }
 
-        false
+    /// Whether icons were loaded since the last call (redraw to show them)
+    pub fn take_arrived(&mut self) -> bool {
+        std::mem::take(&mut self.arrived)
     }
 
     /// Clear the icon cache
@@ -137,12 +300,97 @@ impl IconManager {
     }
 }
 
+/// Loader thread: resolve and rasterize icons without holding the lock,
+/// then put them in the cache
+fn run_loader(
+    requests: Receiver<IconRequest>,
+    manager: Weak<Mutex<IconManager>>,
+    mut picker: Picker,
+    mut loader: IconLoader,
+) {
+    for request in requests {
+        let Some(manager) = manager.upgrade() else {
+            break;
+        };
+        {
+            let mut mgr = manager.lock();
+            if !mgr.is_queued(&request) {
+                // Invalidated while queued
+                continue;
+            }
+            if !mgr.wanted.contains(&request.entry_id) {
+                // Scrolled away while queued; asked again when back
+                mgr.queued.remove(&request.entry_id);
+                continue;
+            }
+        }
+
+        let protocol = loader
+            .load(&request.icon_name)
+            .map(|img| picker.new_resize_protocol(img));
+
+        let mut mgr = manager.lock();
+        if !mgr.is_queued(&request) {
+            // Invalidated while loading
+            continue;
+        }
+        mgr.queued.remove(&request.entry_id);
+        match protocol {
+            Some(protocol) => {
+                mgr.cache.insert(request.entry_id, Arc::new(Mutex::new(protocol)));
+                mgr.arrived = true;
+            }
+            None => {
+                mgr.failed.insert(request.entry_id);
+            }
+        }
+    }
+}
+
+/// What the loader thread needs to turn an icon name into an image
+struct IconLoader {
+    lookup: IconLookup,
+    cache: IconCache,
+    symbolic_colors: Option<SymbolicColors>,
+}
+
+impl IconLoader {
+    fn new(
+        lookup: IconLookup,
+        cache_dir: Option<PathBuf>,
+        size: u16,
+        symbolic_colors: Option<SymbolicColors>,
+    ) -> Self {
+        let variant = symbolic_colors.map(|colors| colors.cache_key()).unwrap_or_default();
+        Self {
+            lookup,
+            cache: IconCache::open(cache_dir, size, &variant),
+            symbolic_colors,
+        }
+    }
+
+    /// Resolve an icon name and load its image, from the disk cache when
+    /// it has it
+    fn load(&mut self, icon_name: &str) -> Option<DynamicImage> {
+        let Some(icon_path) = self.lookup.find_icon(icon_name, self.cache.size()) else {
+            tracing::debug!("Icon not found: {}", icon_name);
+            return None;
+        };
+        if let Some(image) = self.cache.get(&icon_path) {
+            return Some(image);
+        }
+        // Load the image with transparency support
+        load_icon_image(&icon_path, self.symbolic_colors.as_ref())
+            .map(|image| self.cache.insert(&icon_path, image))
+    }
+}
+
 /// Load an icon image with proper format handling
-fn load_icon_image(path: &Path) -> Option<DynamicImage> {
+fn load_icon_image(path: &Path, symbolic_colors: Option<&SymbolicColors>) -> Option<DynamicImage> {
     let ext = path.extension()?.to_str()?.to_lowercase();
     
     match ext.as_str() {
-        "svg" => load_svg(path),
+        "svg" => load_svg(path, symbolic_colors),
         "png" | "jpg" | "jpeg" | "gif" | "bmp" | "ico" | "webp" => {
             match image::open(path) {
                 Ok(img) => Some(img),
@@ -159,14 +407,17 @@ fn load_icon_image(path: &Path) -> Option<DynamicImage> {
     }
 }
 
-/// Load SVG and rasterize it
-fn load_svg(path: &Path) -> Option<DynamicImage> {
+/// Load SVG and rasterize it, recoloring symbolic icons
+fn load_svg(path: &Path, symbolic_colors: Option<&SymbolicColors>) -> Option<DynamicImage> {
     use std::fs;
     
     let svg_data = fs::read(path).ok()?;
     
     // Use resvg for SVG rendering
-    let options = usvg::Options::default();
+    let options = usvg::Options {
+        style_sheet: symbolic_colors.and_then(|colors| colors.style_sheet(path, &svg_data)),
+        ..Default::default()
+    };
     let tree = usvg::Tree::from_data(&svg_data, &options).ok()?;
     
     let size = tree.size();
@@ -196,21 +447,95 @@ fn load_svg(path: &Path) -> Option<DynamicImage> {
 }
 
 /// Icon lookup following freedesktop spec
+#[derive(Clone)]
 struct IconLookup {
     /// Icon theme name (from GTK settings)
     theme: String,
     /// Search paths for icons
     search_paths: Vec<PathBuf>,
+    /// Themes to search, in order: the current theme, its parents
+    /// (depth first), then hicolor
+    themes: Vec<ThemeLookup>,
+}
+
+/// A theme of the hierarchy and where it is installed
+#[derive(Clone)]
+struct ThemeLookup {
+    name: String,
+    /// `<search path>/<name>` directories that exist
+    dirs: Vec<PathBuf>,
+    /// Its index.theme; without one, common directory names are tried
+    index: Option<IconTheme>,
 }
 
 impl IconLookup {
-    fn new() -> Self {
-        let theme = detect_icon_theme().unwrap_or_else(|| "hicolor".to_string());
-        let search_paths = get_icon_search_paths();
+    fn new(options: &IconOptions) -> Self {
+        let theme = options
+            .theme
+            .clone()
+            .or_else(detect_icon_theme)
+            .unwrap_or_else(|| "hicolor".to_string());
+        let mut search_paths = options.extra_search_paths.clone();
+        for path in get_icon_search_paths() {
+            if !search_paths.contains(&path) {
+                search_paths.push(path);
+            }
+        }
         
         tracing::debug!("Icon search paths: {:?}", search_paths);
         
-        Self { theme, search_paths }
+        Self::with_theme(theme, search_paths)
+    }
+
+    fn with_theme(theme: String, search_paths: Vec<PathBuf>) -> Self {
+        let mut lookup = Self {
+            theme,
+            search_paths,
+            themes: Vec::new(),
+        };
+        // Without an index.theme the parents can only be guessed
+        let roots = if IconTheme::find(&lookup.theme, &lookup.search_paths).is_some() {
+            vec![lookup.theme.clone()]
+        } else {
+            guess_theme_hierarchy(&lookup.theme)
+        };
+        for name in &roots {
+            lookup.add_theme(name);
+        }
+        lookup.add_theme_last("hicolor");
+
+        tracing::debug!(
+            "Icon theme hierarchy: {:?}",
+            lookup.themes.iter().map(|t| t.name.as_str()).collect::<Vec<_>>()
+        );
+        lookup
+    }
+
+    /// Add `name` and, recursively, the themes it inherits from; hicolor
+    /// is left for the end
+    fn add_theme(&mut self, name: &str) {
+        if name == "hicolor" || self.themes.iter().any(|t| t.name == name) {
+            return;
+        }
+        self.add_theme_last(name);
+        let parents = self.themes.last().and_then(|t| t.index.as_ref()).map(|i| i.inherits.clone());
+        for parent in parents.unwrap_or_default() {
+            self.add_theme(&parent);
+        }
+    }
+
+    fn add_theme_last(&mut self, name: &str) {
+        let dirs = self
+            .search_paths
+            .iter()
+            .map(|base| base.join(name))
+            .filter(|dir| dir.is_dir())
+            .collect();
+        self.themes.push(ThemeLookup {
+            name: name.to_string(),
+            dirs,
+            index: IconTheme::find(name, &self.search_paths),
+        });
     }
     
     /// Find an icon by name, searching theme hierarchy
@@ -224,78 +549,33 @@ impl IconLookup {
             return None;
         }
         
-        // Build theme search order: current theme -> parent themes -> hicolor
-        let themes = self.get_theme_hierarchy();
-        tracing::trace!("Looking for icon '{}' in themes: {:?}", name, themes);
-        
-        // Preferred sizes in order
-        let sizes = [
-            size.to_string(),
-            "scalable".to_string(),
-            "64".to_string(),
-            "48".to_string(),
-            "32".to_string(),
-            "24".to_string(),
-            "22".to_string(),
-            "16".to_string(),
-        ];
-        
-        // Extensions in preference order
-        let extensions = ["svg", "png", "xpm"];
+        tracing::trace!("Looking for icon '{}' in themes: {:?}", name, self.theme_names());
         
         // Search each theme
-        for theme in &themes {
-            for base_path in &self.search_paths {
-                let theme_path = base_path.join(theme);
-                if !theme_path.exists() {
-                    continue;
-                }
-                
-                // Try each size directory
-                for size_str in &sizes {
-                    // Common subdirectory patterns
-                    let subdirs = [
-                        format!("{}/apps", size_str),
-                        format!("{}x{}/apps", size_str, size_str),
-                        format!("{}/categories", size_str),
-                        format!("{}x{}/categories", size_str, size_str),
-                        format!("{}/mimetypes", size_str),
-                        format!("{}x{}/mimetypes", size_str, size_str),
-                        format!("{}/places", size_str),
-                        format!("{}x{}/places", size_str, size_str),
-                        format!("{}/devices", size_str),
-                        format!("{}x{}/devices", size_str, size_str),
-                        format!("{}/actions", size_str),
-                        format!("{}x{}/actions", size_str, size_str),
-                        format!("{}/status", size_str),
-                        format!("{}x{}/status", size_str, size_str),
-                        // Papirus-style paths
-                        format!("{}x{}", size_str, size_str),
-                        size_str.clone(),
-                    ];
-                    
-                    for subdir in &subdirs {
-                        let dir = theme_path.join(subdir);
-                        if !dir.exists() {
-                            continue;
-                        }
-                        
-                        for ext in &extensions {
-                            let icon_path = dir.join(format!("{}.{}", name, ext));
-                            if icon_path.exists() {
-                                return Some(icon_path);
-                            }
-                        }
-                    }
-                }
+        for theme in &self.themes {
+            let found = match &theme.index {
+                Some(index) => lookup_in_index(theme, index, name, size),
+                None => lookup_by_dir_names(theme, name, size),
+            };
+            if found.is_some() {
+                return found;
             }
         }
         
-        // Fallback: search pixmaps directories
+        // Fallback: unthemed icons directly in a base dir (e.g. Snap's
+        // flat icons dir), then pixmaps directories
+        for base_path in &self.search_paths {
+            for ext in ICON_EXTENSIONS {
+                let icon_path = base_path.join(format!("{}.{}", name, ext));
+                if icon_path.exists() {
+                    return Some(icon_path);
+                }
+            }
+        }
         for base_path in &self.search_paths {
             let pixmaps = base_path.parent()?.join("pixmaps");
             if pixmaps.exists() {
-                for ext in &extensions {
+                for ext in ICON_EXTENSIONS {
                     let icon_path = pixmaps.join(format!("{}.{}", name, ext));
                     if icon_path.exists() {
                         return Some(icon_path);
@@ -306,35 +586,123 @@ impl IconLookup {
         
         None
     }
-    
-    /// Get theme hierarchy (current theme + inherited themes + hicolor)
-    fn get_theme_hierarchy(&self) -> Vec<String> {
-        let mut themes = vec![self.theme.clone()];
-        
-        // Add parent themes based on common patterns
-        // Papirus-Dark -> Papirus -> hicolor
-        if self.theme.ends_with("-Dark") || self.theme.ends_with("-dark") {
-            let base = self.theme.trim_end_matches("-Dark").trim_end_matches("-dark");
-            if !themes.contains(&base.to_string()) {
-                themes.push(base.to_string());
+
+    /// Names of the themes searched, in order
+    fn theme_names(&self) -> Vec<&str> {
+        self.themes.iter().map(|t| t.name.as_str()).collect()
+    }
+}
+
+/// Extensions in preference order
+const ICON_EXTENSIONS: [&str; 3] = ["svg", "png", "xpm"];
+
+/// Look an icon up in the directories an index.theme lists: one that
+/// matches `size`, else the closest size
+fn lookup_in_index(theme: &ThemeLookup, index: &IconTheme, name: &str, size: u16) -> Option<PathBuf> {
+    let candidates = || {
+        index.dirs.iter().flat_map(move |dir| {
+            theme.dirs.iter().flat_map(move |base| {
+                ICON_EXTENSIONS
+                    .iter()
+                    .map(move |ext| (dir, base.join(&dir.path).join(format!("{}.{}", name, ext))))
+            })
+        })
+    };
+
+    if let Some((_, path)) = candidates().find(|(dir, path)| dir.matches_size(size, 1) && path.exists()) {
+        return Some(path);
+    }
+    candidates()
+        .filter(|(_, path)| path.exists())
+        .min_by_key(|(dir, _)| dir.size_distance(size, 1))
+        .map(|(_, path)| path)
+}
+
+/// Look an icon up in common directory layouts, for themes without an
+/// index.theme
+fn lookup_by_dir_names(theme: &ThemeLookup, name: &str, size: u16) -> Option<PathBuf> {
+    // Preferred sizes in order
+    let sizes = [
+        size.to_string(),
+        "scalable".to_string(),
+        "64".to_string(),
+        "48".to_string(),
+        "32".to_string(),
+        "24".to_string(),
+        "22".to_string(),
+        "16".to_string(),
+    ];
+
+    for theme_path in &theme.dirs {
+        // Try each size directory
+        for size_str in &sizes {
+            // Common subdirectory patterns
+            let subdirs = [
+                format!("{}/apps", size_str),
+                format!("{}x{}/apps", size_str, size_str),
+                format!("{}/categories", size_str),
+                format!("{}x{}/categories", size_str, size_str),
+                format!("{}/mimetypes", size_str),
+                format!("{}x{}/mimetypes", size_str, size_str),
+                format!("{}/places", size_str),
+                format!("{}x{}/places", size_str, size_str),
+                format!("{}/devices", size_str),
+                format!("{}x{}/devices", size_str, size_str),
+                format!("{}/actions", size_str),
+                format!("{}x{}/actions", size_str, size_str),
+                format!("{}/status", size_str),
+                format!("{}x{}/status", size_str, size_str),
+                // Papirus-style paths
+                format!("{}x{}", size_str, size_str),
+                size_str.clone(),
+            ];
+
+            for subdir in &subdirs {
+                let dir = theme_path.join(subdir);
+                if !dir.exists() {
+                    continue;
+                }
+
+                for ext in ICON_EXTENSIONS {
+                    let icon_path = dir.join(format!("{}.{}", name, ext));
+                    if icon_path.exists() {
+                        return Some(icon_path);
+                    }
+                }
             }
         }
-        if self.theme.ends_with("-Light") || self.theme.ends_with("-light") {
-            let base = self.theme.trim_end_matches("-Light").trim_end_matches("-light");
-            if !themes.contains(&base.to_string()) {
-                themes.push(base.to_string());
-            }
+    }
+    None
+}
+
+/// Guess the parents of a theme that has no index.theme from its name
+/// (Papirus-Dark -> Papirus), then common fallbacks
+fn guess_theme_hierarchy(theme: &str) -> Vec<String> {
+    let mut themes = vec![theme.to_string()];
+    
+    // Add parent themes based on common patterns
+    // Papirus-Dark -> Papirus -> hicolor
+    if theme.ends_with("-Dark") || theme.ends_with("-dark") {
+        let base = theme.trim_end_matches("-Dark").trim_end_matches("-dark");
+        if !themes.contains(&base.to_string()) {
+            themes.push(base.to_string());
         }
-        
-        // Always include these fallbacks
-        for fallback in &["Adwaita", "breeze", "hicolor"] {
-            if !themes.contains(&fallback.to_string()) {
-                themes.push(fallback.to_string());
-            }
+    }
+    if theme.ends_with("-Light") || theme.ends_with("-light") {
+        let base = theme.trim_end_matches("-Light").trim_end_matches("-light");
+        if !themes.contains(&base.to_string()) {
+            themes.push(base.to_string());
+        }
+    }
+    
+    // Always include these fallbacks
+    for fallback in &["Adwaita", "breeze", "hicolor"] {
+        if !themes.contains(&fallback.to_string()) {
+            themes.push(fallback.to_string());
         }
-        
-        themes
     }
+    
+    themes
 }
 
 /// Detect icon theme from GTK settings
@@ -387,6 +755,11 @@ fn get_icon_search_paths() -> Vec<PathBuf> {
     paths.push(PathBuf::from("/usr/share/icons"));
     paths.push(PathBuf::from("/usr/local/share/icons"));
     paths.push(PathBuf::from("/run/current-system/sw/share/icons")); // NixOS
+
+    // Flatpak/Snap exports (not always in XDG_DATA_DIRS)
+    for data_dir in crate::desktop_entry::sandboxed_data_dirs() {
+        paths.push(data_dir.join("icons"));
+    }
     
     // Deduplicate while preserving order
     let mut seen = std::collections::HashSet::new();
@@ -398,6 +771,308 @@ fn get_icon_search_paths() -> Vec<PathBuf> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_util::TempDir;
+
+    /// A hicolor theme with `count` SVG icons named icon-0, icon-1, ...,
+    /// in a directory that lives as long as the returned one
+    fn test_lookup(name: &str, count: usize) -> (TempDir, IconLookup) {
+        let dir = TempDir::new(name);
+        let apps = dir.join("hicolor/scalable/apps");
+        std::fs::create_dir_all(&apps).unwrap();
+        for n in 0..count {
+            let svg = format!(
+                r##"<svg xmlns="http://www.w3.org/2000/svg" width="64" height="64">
+                    <defs><linearGradient id="g"><stop offset="0" stop-color="#{:06x}"/>
+                    <stop offset="1" stop-color="#202020"/></linearGradient></defs>
+                    <circle cx="32" cy="32" r="30" fill="url(#g)"/>
+                    <path d="M12 40 Q32 {} 52 40 T60 20" stroke="#fff" stroke-width="4" fill="none"/>
+                </svg>"##,
+                n * 0x040404,
+                n % 40
+            );
+            std::fs::write(apps.join(format!("icon-{}.svg", n)), svg).unwrap();
+        }
+        let lookup = IconLookup::with_theme("hicolor".to_string(), vec![dir.to_path_buf()]);
+        (dir, lookup)
+    }
+
+    /// Write `files` (path, content) under a fresh icons dir
+    fn fixture(name: &str, files: &[(&str, &str)]) -> TempDir {
+        let dir = TempDir::new(name);
+        for (path, content) in files {
+            let path = dir.join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, content).unwrap();
+        }
+        dir
+    }
+
+    fn index(inherits: &str, dirs: &[(&str, u16)]) -> String {
+        let mut index = format!(
+            "[Icon Theme]\nInherits={}\nDirectories={}\n",
+            inherits,
+            dirs.iter().map(|(path, _)| *path).collect::<Vec<_>>().join(",")
+        );
+        for (path, size) in dirs {
+            index.push_str(&format!("\n[{}]\nSize={}\nType=Fixed\nContext=Applications\n", path, size));
+        }
+        index
+    }
+
+    #[test]
+    fn test_theme_hierarchy_from_index() {
+        let papirus_dark = index("Papirus,breeze", &[]);
+        let papirus = index("hicolor", &[("16x16/apps", 16), ("64x64/apps", 64)]);
+        let dir = fixture(
+            "inherits",
+            &[
+                ("Papirus-Dark/index.theme", &papirus_dark),
+                ("Papirus/index.theme", &papirus),
+                ("Papirus/16x16/apps/app.png", ""),
+                ("Papirus/64x64/apps/app.png", ""),
+                // No index.theme: found by directory name
+                ("breeze/48x48/apps/only-breeze.svg", ""),
+                ("hicolor/index.theme", &index("", &[("48x48/apps", 48)])),
+                ("hicolor/48x48/apps/app.png", ""),
+            ],
+        );
+        let lookup = IconLookup::with_theme("Papirus-Dark".to_string(), vec![dir.to_path_buf()]);
+        assert_eq!(lookup.theme_names(), ["Papirus-Dark", "Papirus", "breeze", "hicolor"]);
+
+        // The closest size in the first theme that has the icon wins over
+        // an exact size further up the hierarchy
+        assert_eq!(lookup.find_icon("app", 48), Some(dir.join("Papirus/64x64/apps/app.png")));
+        assert_eq!(lookup.find_icon("app", 16), Some(dir.join("Papirus/16x16/apps/app.png")));
+        assert_eq!(lookup.find_icon("only-breeze", 48), Some(dir.join("breeze/48x48/apps/only-breeze.svg")));
+        assert_eq!(lookup.find_icon("missing", 48), None);
+    }
+
+    #[test]
+    fn test_theme_hierarchy_guessed_without_index() {
+        let dir = fixture(
+            "guessed",
+            &[
+                ("Qogir/index.theme", &index("Moka", &[("scalable/apps", 48)])),
+                ("Qogir/scalable/apps/app.svg", ""),
+            ],
+        );
+        let lookup = IconLookup::with_theme("Qogir-dark".to_string(), vec![dir.to_path_buf()]);
+        assert_eq!(lookup.theme_names(), ["Qogir-dark", "Qogir", "Moka", "Adwaita", "breeze", "hicolor"]);
+        assert_eq!(lookup.find_icon("app", 64), Some(dir.join("Qogir/scalable/apps/app.svg")));
+    }
+
+    fn halfblocks() -> Picker {
+        Picker::from_fontsize((8, 16))
+    }
+
+    #[test]
+    fn test_loader_fills_cache() {
+        let (_dir, lookup) = test_lookup("loader", 2);
+        let manager = Arc::new(Mutex::new(IconManager::with_picker(Some(halfblocks()), lookup, 64, None)));
+        IconManager::start_loader(&manager);
+
+        let entries = [("a", Some("icon-0")), ("b", Some("missing")), ("c", None), ("d", Some("icon-1"))];
+        manager.lock().request_visible(entries.iter().map(|&(id, icon)| (id, icon)));
+
+        let start = std::time::Instant::now();
+        while manager.lock().is_loading() {
+            assert!(start.elapsed() < std::time::Duration::from_secs(10), "icons never loaded");
+            thread::sleep(std::time::Duration::from_millis(1));
+        }
+        let mut mgr = manager.lock();
+        assert!(mgr.get_cached("a").is_some());
+        assert!(mgr.get_cached("d").is_some());
+        assert!(mgr.failed.contains("b"));
+        assert!(mgr.failed.contains("c"));
+        assert!(mgr.take_arrived());
+        assert!(!mgr.take_arrived());
+    }
+
+    #[test]
+    fn test_loader_skips_scrolled_away_entries() {
+        let (_dir, lookup) = test_lookup("cancel", 2);
+        let manager = Arc::new(Mutex::new(IconManager::with_picker(Some(halfblocks()), lookup.clone(), 64, None)));
+        let (tx, rx) = mpsc::channel();
+        manager.lock().loader = Some(tx);
+
+        // Page one is requested, then the user moves on to page two
+        manager.lock().request_visible([("a", Some("icon-0"))].into_iter());
+        manager.lock().request_visible([("b", Some("icon-1"))].into_iter());
+        manager.lock().loader = None;
+        run_loader(rx, Arc::downgrade(&manager), halfblocks(), IconLoader::new(lookup, None, 64, None));
+
+        let mut mgr = manager.lock();
+        assert!(mgr.get_cached("a").is_none());
+        assert!(!mgr.failed.contains("a"));
+        assert!(mgr.get_cached("b").is_some());
+        assert!(!mgr.is_loading());
+
+        // Scrolling back asks for it again
+        let (tx, _rx) = mpsc::channel();
+        mgr.loader = Some(tx);
+        mgr.request_visible([("a", Some("icon-0"))].into_iter());
+        assert!(mgr.queued.contains_key("a"));
+    }
+
+    #[test]
+    fn test_invalidate_and_retry() {
+        let (_dir, lookup) = test_lookup("retry", 0);
+        let mut mgr = IconManager::with_picker(Some(halfblocks()), lookup, 64, None);
+        let (tx, _rx) = mpsc::channel();
+        mgr.loader = Some(tx);
+        mgr.failed.insert("a".to_string());
+        mgr.failed.insert("b".to_string());
+
+        mgr.request_visible([("a", Some("icon")), ("b", Some("icon"))].into_iter());
+        assert!(!mgr.is_loading());
+
+        mgr.invalidate("a");
+        mgr.request_visible([("a", Some("icon")), ("b", Some("icon"))].into_iter());
+        assert!(mgr.queued.contains_key("a") && !mgr.queued.contains_key("b"));
+
+        mgr.retry_failed();
+        mgr.request_visible([("b", Some("icon"))].into_iter());
+        assert!(mgr.queued.contains_key("b"));
+    }
+
+    #[test]
+    fn test_invalidate_drops_queued_request() {
+        let (_dir, lookup) = test_lookup("invalidate-queued", 2);
+        let manager = Arc::new(Mutex::new(IconManager::with_picker(Some(halfblocks()), lookup.clone(), 64, None)));
+        let (tx, rx) = mpsc::channel();
+        manager.lock().loader = Some(tx);
+
+        // The entry's icon changes while its old icon is queued
+        manager.lock().request_visible([("a", Some("missing"))].into_iter());
+        manager.lock().invalidate("a");
+        assert!(!manager.lock().is_loading());
+        manager.lock().request_visible([("a", Some("icon-1"))].into_iter());
+        manager.lock().loader = None;
+        run_loader(rx, Arc::downgrade(&manager), halfblocks(), IconLoader::new(lookup, None, 64, None));
+
+        let mgr = manager.lock();
+        assert!(!mgr.failed.contains("a"));
+        assert!(mgr.get_cached("a").is_some());
+        assert!(!mgr.is_loading());
+    }
+
+    #[test]
+    fn test_second_load_hits_disk_cache() {
+        let (_dir, lookup) = test_lookup("disk-cache", 1);
+        let cache = TempDir::new("disk-cache-icons");
+        let cache_dir = cache.join("icons");
+
+        let mut loader = IconLoader::new(lookup.clone(), Some(cache_dir.clone()), 32, None);
+        let first = loader.load("icon-0").unwrap();
+        assert_eq!((loader.cache.hits, loader.cache.misses), (0, 1));
+        // Rasterized at 128px, cached at the icon size
+        assert_eq!((first.width(), first.height()), (32, 32));
+
+        // A later run reads it back without touching resvg
+        let mut loader = IconLoader::new(lookup, Some(cache_dir), 32, None);
+        let second = loader.load("icon-0").unwrap();
+        assert_eq!((loader.cache.hits, loader.cache.misses), (1, 0));
+        assert_eq!(first.to_rgba8(), second.to_rgba8());
+    }
+
+    const COLORS: SymbolicColors = SymbolicColors {
+        foreground: [200, 210, 220],
+        success: [0, 200, 0],
+        warning: [200, 200, 0],
+        error: [200, 0, 0],
+    };
+
+    /// Pixels of an image that aren't fully transparent
+    fn opaque_pixels(image: &DynamicImage) -> Vec<[u8; 4]> {
+        image.to_rgba8().pixels().map(|p| p.0).filter(|p| p[3] == 255).collect()
+    }
+
+    #[test]
+    fn test_current_color_svg_uses_foreground() {
+        let dir = fixture(
+            "current-color",
+            &[(
+                "app.svg",
+                r#"<svg xmlns="http://www.w3.org/2000/svg" width="16" height="16">
+                    <rect width="16" height="16" fill="currentColor"/></svg>"#,
+            )],
+        );
+        let image = load_svg(&dir.join("app.svg"), Some(&COLORS)).unwrap();
+        let pixels = opaque_pixels(&image);
+        assert!(!pixels.is_empty());
+        assert!(pixels.iter().all(|p| p[..3] == COLORS.foreground));
+
+        // Without colors currentColor is black
+        let image = load_svg(&dir.join("app.svg"), None).unwrap();
+        assert!(opaque_pixels(&image).iter().all(|p| p[..3] == [0, 0, 0]));
+    }
+
+    #[test]
+    fn test_symbolic_svg_recolored() {
+        let dir = fixture(
+            "symbolic",
+            &[
+                (
+                    "app-symbolic.svg",
+                    r##"<svg xmlns="http://www.w3.org/2000/svg" width="16" height="16">
+                        <path d="M0 0h8v16H0z" fill="#2e3436"/>
+                        <rect x="8" width="8" height="16" class="error" fill="#2e3436"/></svg>"##,
+                ),
+                (
+                    "app.svg",
+                    r##"<svg xmlns="http://www.w3.org/2000/svg" width="16" height="16">
+                        <path d="M0 0h16v16H0z" fill="#2e3436"/></svg>"##,
+                ),
+            ],
+        );
+        let image = load_svg(&dir.join("app-symbolic.svg"), Some(&COLORS)).unwrap();
+        let pixels = opaque_pixels(&image);
+        assert!(pixels.iter().any(|p| p[..3] == COLORS.foreground));
+        assert!(pixels.iter().any(|p| p[..3] == COLORS.error));
+        assert!(pixels.iter().all(|p| p[..3] == COLORS.foreground || p[..3] == COLORS.error));
+
+        // Full-color icons keep their colors
+        let image = load_svg(&dir.join("app.svg"), Some(&COLORS)).unwrap();
+        assert!(opaque_pixels(&image).iter().all(|p| p[..3] == [0x2e, 0x34, 0x36]));
+    }
+
+    /// Time until a full page of 60 SVG icons is cached: one icon per
+    /// frame on the render loop (the old way, idle frames 100ms apart)
+    /// against the loader thread
+    #[test]
+    #[ignore]
+    fn bench_first_page_icons() {
+        use std::time::{Duration, Instant};
+
+        let count = 60;
+        let (_dir, lookup) = test_lookup("bench", count);
+        let names: Vec<_> = (0..count).map(|n| (format!("e{}", n), format!("icon-{}", n))).collect();
+
+        let mut picker = halfblocks();
+        let mut loader = IconLoader::new(lookup.clone(), None, 64, None);
+        let start = Instant::now();
+        let mut busy = Duration::ZERO;
+        for (_, icon) in &names {
+            let frame = Instant::now();
+            let img = loader.load(icon).unwrap();
+            let _ = picker.new_resize_protocol(img);
+            busy = busy.max(frame.elapsed());
+            thread::sleep(Duration::from_millis(100));
+        }
+        println!("one per frame: {:?} (longest blocked frame {:?})", start.elapsed(), busy);
+
+        let manager = Arc::new(Mutex::new(IconManager::with_picker(Some(halfblocks()), lookup, 64, None)));
+        IconManager::start_loader(&manager);
+        let start = Instant::now();
+        manager
+            .lock()
+            .request_visible(names.iter().map(|(id, icon)| (id.as_str(), Some(icon.as_str()))));
+        while manager.lock().is_loading() {
+            thread::sleep(Duration::from_millis(1));
+        }
+        println!("loader thread: {:?}", start.elapsed());
+        assert_eq!(manager.lock().cache.len(), count);
+    }
 
     #[test]
     fn test_detect_icon_theme() {
@@ -417,9 +1092,9 @@ mod tests {
     
     #[test]
     fn test_icon_lookup() {
-        let lookup = IconLookup::new();
+        let lookup = IconLookup::new(&IconOptions::default());
         println!("Theme: {}", lookup.theme);
-        println!("Theme hierarchy: {:?}", lookup.get_theme_hierarchy());
+        println!("Theme hierarchy: {:?}", lookup.theme_names());
         
         // Test some common icons
         for icon in &["firefox", "chromium", "org.kde.ark", "utilities-terminal"] {
@@ -430,10 +1105,10 @@ mod tests {
     
     #[test]
     fn test_svg_loading() {
-        let lookup = IconLookup::new();
+        let lookup = IconLookup::new(&IconOptions::default());
         if let Some(path) = lookup.find_icon("firefox", 64) {
             println!("Loading SVG: {}", path.display());
-            match load_icon_image(&path) {
+            match load_icon_image(&path, None) {
                 Some(img) => {
                     println!("  Loaded! Size: {}x{}", img.width(), img.height());
                 }
@@ -0,0 +1,138 @@
+//! Background jobs: commands detached from the executing view.
+//!
+//! Pressing Ctrl+B while a command runs moves its PTY session and terminal
+//! emulator into a `Job` and returns to the launcher. Jobs keep being polled
+//! from the main loop, so their output and exit status are up to date when
+//! they are re-attached from the jobs view.
+
+use anyhow::Result;
+use std::time::{Duration, Instant};
+
+use crate::clipboard;
+use crate::executor::{CommandStatus, LaunchOptions, TerminalMode};
+use crate::pty::PtySession;
+use crate::terminal::EmbeddedTerminal;
+
+/// A command running (or finished) in the background
+pub struct Job {
+    pub command: String,
+    pub mode: TerminalMode,
+    /// Shell, working directory and environment, for re-running
+    pub launch: LaunchOptions,
+    /// Entry the command came from, for history
+    pub entry_id: Option<String>,
+    /// Run through `behavior.elevate_command`
+    pub elevated: bool,
+    /// When the command was started
+    pub started: Instant,
+    /// PTY session, None once the command has exited
+    pub session: Option<PtySession>,
+    /// Terminal emulator holding the job's output
+    pub terminal: EmbeddedTerminal,
+    /// Exit status and run time once the command has exited
+    pub finished: Option<(CommandStatus, Duration)>,
+}
+
+impl Job {
+    /// Whether the command is still running
+    pub fn is_running(&self) -> bool {
+        self.finished.is_none()
+    }
+
+    /// Read pending output into the job's terminal and check for exit
+    ///
+    /// Returns whether there was output or the command exited.
+    pub fn poll(&mut self) -> Result<bool> {
+        let Some(ref mut session) = self.session else {
+            return Ok(false);
+        };
+
+        let output = read_output(session, &mut self.terminal) > 0;
+
+        if let Some(status) = session.try_wait()? {
+            // Pick up output written just before the exit
+            read_output(session, &mut self.terminal);
+            self.finished = Some((
+                CommandStatus::from_exit_status(status),
+                self.started.elapsed(),
+            ));
+            self.session = None;
+            return Ok(true);
+        }
+        Ok(output)
+    }
+}
+
+/// Most output fed into a terminal per poll, so a flood of output never
+/// keeps the UI from drawing
+const MAX_OUTPUT_PER_POLL: usize = 1024 * 1024;
+
+/// Feed output currently available from `session` into `terminal`, send
+/// the terminal's replies to queries back to the session and put text the
+/// command copied (OSC 52) on the clipboard
+///
+/// Reads at most `MAX_OUTPUT_PER_POLL` bytes; the rest is picked up by the
+/// next poll. Returns the number of bytes read.
+pub fn read_output(session: &mut PtySession, terminal: &mut EmbeddedTerminal) -> usize {
+    let mut buf = [0u8; 16 * 1024];
+    let mut total = 0;
+    while total < MAX_OUTPUT_PER_POLL {
+        match session.try_read(&mut buf) {
+            Ok(Some(n)) if n > 0 => {
+                terminal.write(&buf[..n]);
+                total += n;
+                // Answer cursor position and device attribute queries
+                let responses = terminal.take_responses();
+                if !responses.is_empty() {
+                    if let Err(e) = session.write(&responses) {
+                        tracing::warn!("Failed to answer terminal query: {}", e);
+                    }
+                }
+                if let Some(text) = terminal.take_clipboard() {
+                    clipboard::copy_from_command(&text);
+                }
+            }
+            Ok(_) => break, // No more data or EOF
+            Err(e) => {
+                tracing::warn!("PTY read error: {}", e);
+                break;
+            }
+        }
+    }
+    total
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::terminal::TerminalConfig;
+
+    #[test]
+    fn test_job_collects_output_and_status() {
+        let session = PtySession::spawn("echo background; exit 3", 80, 24, &LaunchOptions::default(), None)
+            .unwrap();
+        let mut job = Job {
+            command: "echo background; exit 3".to_string(),
+            mode: TerminalMode::Oneshot,
+            launch: LaunchOptions::default(),
+            entry_id: None,
+            elevated: false,
+            started: Instant::now(),
+            session: Some(session),
+            terminal: EmbeddedTerminal::new(TerminalConfig::default()),
+            finished: None,
+        };
+
+        let start = Instant::now();
+        while job.is_running() && start.elapsed() < Duration::from_secs(5) {
+            job.poll().unwrap();
+            std::thread::sleep(Duration::from_millis(10));
+        }
+
+        assert!(matches!(job.finished, Some((CommandStatus::Exited(3), _))));
+        assert!(job.session.is_none());
+        assert!(job.terminal.content_as_text().contains("background"));
+        // Nothing changes after the exit
+        assert!(!job.poll().unwrap());
+    }
+}
@@ -0,0 +1,548 @@
+//! Table of keybindings, grouped by mode
+//!
+//! The key handlers in main.rs look up what a key does here and the help
+//! overlay (`?` / F1) is rendered from the same table, so the two can't
+//! disagree. When a binding changes, change docs/USAGE.md too.
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+/// Where a group of bindings applies
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyContext {
+    Launcher,
+    Categories,
+    Executing,
+    PostExecution,
+    Selecting,
+    Links,
+    /// Typing a search of the output
+    SearchInput,
+    /// Going through the matches of a search
+    Search,
+    Jobs,
+}
+
+/// A key a binding reacts to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Key {
+    /// This key, pressed with at least these modifiers
+    Code(KeyCode, KeyModifiers),
+    /// Any character typed without Ctrl
+    Text,
+    /// `1` to `9`
+    Digit,
+}
+
+/// What a key does; the handler of the mode decides what that means there
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Up,
+    Down,
+    Left,
+    Right,
+    Next,
+    Previous,
+    PageUp,
+    PageDown,
+    Home,
+    End,
+    /// Run, choose or find, depending on the mode
+    Confirm,
+    /// Close what's open, or clear the filter
+    Back,
+    Quit,
+    /// Add the character to the filter or search
+    Type,
+    DeleteChar,
+    RunTyped,
+    Elevate,
+    Categories,
+    Windows,
+    Forget,
+    Preview,
+    Float,
+    CompositorStatus,
+    Jobs,
+    /// Stop the running command
+    Stop,
+    Background,
+    /// Kill a timed-out oneshot command
+    Kill,
+    Search,
+    Save,
+    Rerun,
+    Copy,
+    Select,
+    Links,
+    /// Open the link with the digit's number
+    OpenNumber,
+    NextMatch,
+    PreviousMatch,
+    ToggleCase,
+    Dismiss,
+    /// Confirm exiting with jobs running
+    Yes,
+}
+
+/// Keys doing related things, shown as one line of the help
+pub struct Binding {
+    pub keys: &'static [(Key, Action)],
+    pub description: &'static str,
+}
+
+/// Bindings of one mode
+pub struct Section {
+    pub context: KeyContext,
+    pub title: &'static str,
+    pub bindings: &'static [Binding],
+}
+
+const fn bind(keys: &'static [(Key, Action)], description: &'static str) -> Binding {
+    Binding { keys, description }
+}
+
+const fn code(code: KeyCode) -> Key {
+    Key::Code(code, KeyModifiers::NONE)
+}
+
+const fn key(c: char) -> Key {
+    code(KeyCode::Char(c))
+}
+
+const fn ctrl(c: char) -> Key {
+    Key::Code(KeyCode::Char(c), KeyModifiers::CONTROL)
+}
+
+const fn with(modifiers: KeyModifiers, code: KeyCode) -> Key {
+    Key::Code(code, modifiers)
+}
+
+const UP: Key = code(KeyCode::Up);
+const DOWN: Key = code(KeyCode::Down);
+const LEFT: Key = code(KeyCode::Left);
+const RIGHT: Key = code(KeyCode::Right);
+const PAGE_UP: Key = code(KeyCode::PageUp);
+const PAGE_DOWN: Key = code(KeyCode::PageDown);
+const HOME: Key = code(KeyCode::Home);
+const END: Key = code(KeyCode::End);
+const ENTER: Key = code(KeyCode::Enter);
+const ESC: Key = code(KeyCode::Esc);
+const TAB: Key = code(KeyCode::Tab);
+const BACK_TAB: Key = code(KeyCode::BackTab);
+
+/// Every keybinding, in the order the help shows them
+pub const KEYMAP: &[Section] = &[
+    Section {
+        context: KeyContext::Launcher,
+        title: "Launcher",
+        bindings: &[
+            bind(
+                &[(UP, Action::Up), (DOWN, Action::Down), (LEFT, Action::Left), (RIGHT, Action::Right)],
+                "Move selection",
+            ),
+            bind(
+                &[
+                    (TAB, Action::Next),
+                    (with(KeyModifiers::SHIFT, KeyCode::Tab), Action::Previous),
+                    (BACK_TAB, Action::Previous),
+                ],
+                "Next / previous entry",
+            ),
+            bind(&[(PAGE_UP, Action::PageUp), (PAGE_DOWN, Action::PageDown)], "Previous / next page"),
+            bind(&[(HOME, Action::Home), (END, Action::End)], "First / last entry"),
+            bind(&[(ENTER, Action::Confirm)], "Run selected entry"),
+            bind(
+                &[
+                    (with(KeyModifiers::ALT, KeyCode::Enter), Action::RunTyped),
+                    (with(KeyModifiers::SHIFT, KeyCode::Enter), Action::RunTyped),
+                    (with(KeyModifiers::CONTROL, KeyCode::Enter), Action::RunTyped),
+                ],
+                "Run the filter text as a command; on a workspace, move the focused window there",
+            ),
+            bind(
+                &[
+                    (ctrl('e'), Action::Elevate),
+                    (
+                        with(KeyModifiers::CONTROL.union(KeyModifiers::SHIFT), KeyCode::Enter),
+                        Action::Elevate,
+                    ),
+                ],
+                "Run selected entry as root",
+            ),
+            bind(&[(Key::Text, Action::Type)], "Filter entries (#category @keyword !exclude \"phrase\", > for programs on PATH)"),
+            bind(&[(code(KeyCode::Backspace), Action::DeleteChar)], "Delete filter character"),
+            bind(&[(ESC, Action::Back)], "Clear filter / exit"),
+            bind(&[(ctrl('g'), Action::Categories)], "Browse categories"),
+            bind(&[(ctrl('w'), Action::Windows)], "Switch to open windows and back (compositor)"),
+            bind(
+                &[(with(KeyModifiers::CONTROL, KeyCode::Delete), Action::Forget)],
+                "Forget the selected entry's history (again to undo)",
+            ),
+            bind(&[(ctrl('p'), Action::Preview)], "Show / hide entry details"),
+            bind(&[(ctrl('f'), Action::Float)], "Float / tile the window (compositor)"),
+            bind(&[(ctrl('n'), Action::CompositorStatus)], "Show the compositor connection status"),
+            bind(&[(ctrl('b'), Action::Jobs)], "Show background jobs"),
+            bind(&[(ctrl('c'), Action::Quit)], "Exit"),
+        ],
+    },
+    Section {
+        context: KeyContext::Categories,
+        title: "Categories",
+        bindings: &[
+            bind(
+                &[(UP, Action::Up), (DOWN, Action::Down), (TAB, Action::Down), (BACK_TAB, Action::Up)],
+                "Move selection",
+            ),
+            bind(&[(PAGE_UP, Action::PageUp), (PAGE_DOWN, Action::PageDown)], "Move ten categories"),
+            bind(&[(HOME, Action::Home), (END, Action::End)], "First / last category"),
+            bind(&[(ENTER, Action::Confirm)], "Filter by category"),
+            bind(&[(ESC, Action::Back), (ctrl('g'), Action::Back)], "Back to entries"),
+            bind(&[(ctrl('c'), Action::Quit)], "Exit"),
+        ],
+    },
+    Section {
+        context: KeyContext::Executing,
+        title: "Executing",
+        bindings: &[
+            bind(&[(ctrl('c'), Action::Stop)], "Stop process (twice to force kill)"),
+            bind(&[(ctrl('b'), Action::Background)], "Move process to the background (not interactive)"),
+            bind(&[(key('K'), Action::Kill)], "Kill a timed-out oneshot command"),
+            bind(
+                &[(UP, Action::Up), (DOWN, Action::Down), (key('k'), Action::Up), (key('j'), Action::Down)],
+                "Scroll output",
+            ),
+            bind(&[(ctrl('u'), Action::PageUp), (ctrl('d'), Action::PageDown)], "Scroll ten lines"),
+            bind(&[(key('g'), Action::Home), (key('G'), Action::End)], "Scroll to top / bottom"),
+            bind(&[(key('/'), Action::Search)], "Search output (not interactive)"),
+            bind(&[(key('s'), Action::Save)], "Save output (not interactive)"),
+        ],
+    },
+    Section {
+        context: KeyContext::PostExecution,
+        title: "Finished output",
+        bindings: &[
+            bind(&[(ENTER, Action::Back), (ESC, Action::Back)], "Back to launcher"),
+            bind(&[(key('r'), Action::Rerun)], "Re-run the command"),
+            bind(&[(key('y'), Action::Copy)], "Copy output"),
+            bind(&[(key('v'), Action::Select)], "Select part of the output"),
+            bind(&[(key('o'), Action::Links)], "Open a link"),
+            bind(&[(key('/'), Action::Search)], "Search output"),
+            bind(&[(key('s'), Action::Save)], "Save output to a file"),
+            bind(
+                &[(UP, Action::Up), (DOWN, Action::Down), (key('k'), Action::Up), (key('j'), Action::Down)],
+                "Scroll output",
+            ),
+            bind(&[(ctrl('u'), Action::PageUp), (ctrl('d'), Action::PageDown)], "Scroll ten lines"),
+            bind(&[(key('g'), Action::Home), (key('G'), Action::End)], "Scroll to top / bottom"),
+            bind(&[(key('q'), Action::Quit), (ctrl('c'), Action::Quit)], "Exit"),
+        ],
+    },
+    Section {
+        context: KeyContext::Selecting,
+        title: "Selecting output",
+        bindings: &[
+            bind(
+                &[
+                    (UP, Action::Up),
+                    (DOWN, Action::Down),
+                    (LEFT, Action::Left),
+                    (RIGHT, Action::Right),
+                    (key('h'), Action::Left),
+                    (key('j'), Action::Down),
+                    (key('k'), Action::Up),
+                    (key('l'), Action::Right),
+                ],
+                "Move selection end",
+            ),
+            bind(&[(ctrl('u'), Action::PageUp), (ctrl('d'), Action::PageDown)], "Move ten lines"),
+            bind(
+                &[(key('0'), Action::Home), (key('$'), Action::End), (HOME, Action::Home), (END, Action::End)],
+                "Start / end of line",
+            ),
+            bind(&[(key('y'), Action::Copy), (ENTER, Action::Copy)], "Copy selection"),
+            bind(&[(ESC, Action::Back), (key('v'), Action::Back)], "Cancel"),
+        ],
+    },
+    Section {
+        context: KeyContext::Links,
+        title: "Links",
+        bindings: &[
+            bind(&[(Key::Digit, Action::OpenNumber)], "Open link by number"),
+            bind(
+                &[(UP, Action::Up), (DOWN, Action::Down), (key('k'), Action::Up), (key('j'), Action::Down)],
+                "Move selection",
+            ),
+            bind(
+                &[(key('g'), Action::Home), (key('G'), Action::End), (HOME, Action::Home), (END, Action::End)],
+                "First / last link",
+            ),
+            bind(&[(ENTER, Action::Confirm)], "Open selected link"),
+            bind(&[(ESC, Action::Back), (key('o'), Action::Back), (key('q'), Action::Back)], "Close"),
+        ],
+    },
+    Section {
+        context: KeyContext::SearchInput,
+        title: "Searching output",
+        bindings: &[
+            bind(&[(Key::Text, Action::Type)], "Search for the text"),
+            bind(&[(code(KeyCode::Backspace), Action::DeleteChar)], "Delete search character"),
+            bind(&[(ENTER, Action::Confirm)], "Find"),
+            bind(&[(TAB, Action::ToggleCase)], "Toggle case sensitivity"),
+            bind(&[(ESC, Action::Back)], "Close search"),
+        ],
+    },
+    Section {
+        context: KeyContext::Search,
+        title: "Search matches",
+        bindings: &[
+            bind(&[(key('n'), Action::NextMatch), (key('N'), Action::PreviousMatch)], "Next / previous match"),
+            bind(&[(key('/'), Action::Search)], "New search"),
+            bind(&[(ESC, Action::Back)], "Close search"),
+        ],
+    },
+    Section {
+        context: KeyContext::Jobs,
+        title: "Jobs",
+        bindings: &[
+            bind(
+                &[(UP, Action::Up), (DOWN, Action::Down), (key('k'), Action::Up), (key('j'), Action::Down)],
+                "Move selection",
+            ),
+            bind(
+                &[(key('g'), Action::Home), (key('G'), Action::End), (HOME, Action::Home), (END, Action::End)],
+                "First / last job",
+            ),
+            bind(&[(ENTER, Action::Confirm)], "Attach"),
+            bind(&[(key('d'), Action::Dismiss)], "Dismiss a finished job"),
+            bind(&[(ESC, Action::Back), (ctrl('b'), Action::Back)], "Back to launcher"),
+            bind(&[(key('y'), Action::Yes), (key('n'), Action::Back)], "Answer the exit prompt"),
+            bind(&[(key('q'), Action::Quit), (ctrl('c'), Action::Quit)], "Exit"),
+        ],
+    },
+];
+
+/// What `key` does in `context`, if anything
+///
+/// The binding needing the most of the pressed modifiers wins, so
+/// Ctrl+Shift+Enter isn't taken for Ctrl+Enter, nor that for Enter.
+/// Characters bound without Ctrl don't match with it: Ctrl+G isn't `g`.
+pub fn action(context: KeyContext, key: &KeyEvent) -> Option<Action> {
+    let mut best: Option<(u32, Action)> = None;
+    let bindings = KEYMAP.iter().filter(|section| section.context == context);
+    for &(bound, action) in bindings.flat_map(|section| section.bindings).flat_map(|binding| binding.keys) {
+        let Some(specificity) = matches(bound, key) else {
+            continue;
+        };
+        if best.is_none_or(|(best, _)| specificity > best) {
+            best = Some((specificity, action));
+        }
+    }
+    best.map(|(_, action)| action)
+}
+
+/// How many modifiers of `key` the `bound` key uses, if it matches
+fn matches(bound: Key, key: &KeyEvent) -> Option<u32> {
+    let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+    match (bound, key.code) {
+        (Key::Text, KeyCode::Char(_)) if !ctrl => Some(0),
+        (Key::Digit, KeyCode::Char('1'..='9')) => Some(0),
+        (Key::Code(code, modifiers), pressed) if code == pressed && key.modifiers.contains(modifiers) => {
+            let typed = matches!(code, KeyCode::Char(_)) && !modifiers.contains(KeyModifiers::CONTROL);
+            (!(typed && ctrl)).then(|| modifiers.bits().count_ones())
+        }
+        _ => None,
+    }
+}
+
+/// The keys of `binding` as the help shows them, like `↑ ↓ / k / j` or
+/// `Alt/Shift/Ctrl+Enter`
+pub fn keys_label(binding: &Binding) -> String {
+    let mut names: Vec<String> = Vec::new();
+    for &(key, _) in binding.keys {
+        let name = key_name(key);
+        if !names.contains(&name) {
+            names.push(name);
+        }
+    }
+
+    let mut label = String::new();
+    let mut previous: Option<&str> = None;
+    for name in &names {
+        if let Some(previous) = previous {
+            if same_key_other_modifiers(previous, name) {
+                // The same key with other modifiers: Alt/Shift+Enter
+                let (modifiers, _) = name.rsplit_once('+').unwrap_or_default();
+                let at = label.rfind('+').unwrap_or(label.len());
+                label.insert_str(at, &format!("/{}", modifiers));
+                continue;
+            }
+            label.push_str(if is_arrow(previous) && is_arrow(name) { " " } else { " / " });
+        }
+        label.push_str(name);
+        previous = Some(name);
+    }
+    label
+}
+
+/// Whether `a` and `b` are the same key with one different modifier each
+fn same_key_other_modifiers(a: &str, b: &str) -> bool {
+    match (a.rsplit_once('+'), b.rsplit_once('+')) {
+        (Some((a_modifiers, a_key)), Some((b_modifiers, b_key))) => {
+            !a_key.is_empty() && a_key == b_key && !a_modifiers.contains('+') && !b_modifiers.contains('+')
+        }
+        _ => false,
+    }
+}
+
+fn is_arrow(name: &str) -> bool {
+    matches!(name, "↑" | "↓" | "←" | "→")
+}
+
+/// How the help names `key`
+fn key_name(key: Key) -> String {
+    let (code, modifiers) = match key {
+        Key::Text => return "any character".to_string(),
+        Key::Digit => return "1-9".to_string(),
+        // Terminals report Shift+Tab as a key of its own
+        Key::Code(KeyCode::BackTab, _) => return "Shift+Tab".to_string(),
+        Key::Code(code, modifiers) => (code, modifiers),
+    };
+    let mut name = String::new();
+    for (modifier, prefix) in [
+        (KeyModifiers::CONTROL, "Ctrl+"),
+        (KeyModifiers::SHIFT, "Shift+"),
+        (KeyModifiers::ALT, "Alt+"),
+    ] {
+        if modifiers.contains(modifier) {
+            name.push_str(prefix);
+        }
+    }
+    match code {
+        KeyCode::Up => name.push('↑'),
+        KeyCode::Down => name.push('↓'),
+        KeyCode::Left => name.push('←'),
+        KeyCode::Right => name.push('→'),
+        KeyCode::PageUp => name.push_str("PgUp"),
+        KeyCode::PageDown => name.push_str("PgDn"),
+        KeyCode::Esc => name.push_str("Esc"),
+        KeyCode::Delete => name.push_str("Delete"),
+        KeyCode::Char(c) if modifiers.contains(KeyModifiers::CONTROL) => name.push(c.to_ascii_uppercase()),
+        KeyCode::Char(c) => name.push(c),
+        code => name.push_str(&code.to_string()),
+    }
+    name
+}
+
+/// Sections with the one for `current` first
+pub fn sections_for(current: KeyContext) -> impl Iterator<Item = &'static Section> {
+    let first = KEYMAP.iter().filter(move |section| section.context == current);
+    let rest = KEYMAP.iter().filter(move |section| section.context != current);
+    first.chain(rest)
+}
+
+/// Number of lines the help takes: a title and a blank line per section,
+/// plus its bindings
+pub fn help_lines() -> usize {
+    KEYMAP.iter().map(|section| section.bindings.len() + 2).sum()
+}
+
+/// Lines of the help an overlay over `rows` rows shows inside its border
+pub fn visible_help_lines(rows: u16) -> usize {
+    help_lines().min((rows as usize).saturating_sub(2))
+}
+
+/// Furthest the help scrolls over `rows` rows: until its last line is at
+/// the bottom
+pub fn max_help_scroll(rows: u16) -> usize {
+    help_lines() - visible_help_lines(rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_current_section_first() {
+        let titles: Vec<_> = sections_for(KeyContext::Jobs).map(|s| s.title).collect();
+        assert_eq!(titles[0], "Jobs");
+        assert_eq!(titles[1], "Launcher");
+        assert_eq!(titles.len(), KEYMAP.len());
+    }
+
+    #[test]
+    fn test_every_context_has_a_section() {
+        for context in [
+            KeyContext::Launcher,
+            KeyContext::Categories,
+            KeyContext::Executing,
+            KeyContext::PostExecution,
+            KeyContext::Selecting,
+            KeyContext::Links,
+            KeyContext::SearchInput,
+            KeyContext::Search,
+            KeyContext::Jobs,
+        ] {
+            assert!(KEYMAP.iter().any(|section| section.context == context));
+        }
+    }
+
+    fn press(code: KeyCode, modifiers: KeyModifiers) -> KeyEvent {
+        KeyEvent::new(code, modifiers)
+    }
+
+    #[test]
+    fn test_most_modifiers_win() {
+        let enter = |modifiers| action(KeyContext::Launcher, &press(KeyCode::Enter, modifiers));
+        assert_eq!(enter(KeyModifiers::NONE), Some(Action::Confirm));
+        assert_eq!(enter(KeyModifiers::ALT), Some(Action::RunTyped));
+        assert_eq!(enter(KeyModifiers::CONTROL), Some(Action::RunTyped));
+        assert_eq!(enter(KeyModifiers::CONTROL | KeyModifiers::SHIFT), Some(Action::Elevate));
+
+        let tab = |modifiers| action(KeyContext::Launcher, &press(KeyCode::Tab, modifiers));
+        assert_eq!(tab(KeyModifiers::NONE), Some(Action::Next));
+        assert_eq!(tab(KeyModifiers::SHIFT), Some(Action::Previous));
+    }
+
+    #[test]
+    fn test_ctrl_isnt_typed() {
+        let launcher = |c, modifiers| action(KeyContext::Launcher, &press(KeyCode::Char(c), modifiers));
+        assert_eq!(launcher('e', KeyModifiers::NONE), Some(Action::Type));
+        assert_eq!(launcher('e', KeyModifiers::CONTROL), Some(Action::Elevate));
+        assert_eq!(launcher('x', KeyModifiers::CONTROL), None);
+
+        // Uppercase letters come with Shift; Ctrl+G goes to the command
+        let executing = |c, modifiers| action(KeyContext::Executing, &press(KeyCode::Char(c), modifiers));
+        assert_eq!(executing('G', KeyModifiers::SHIFT), Some(Action::End));
+        assert_eq!(executing('g', KeyModifiers::NONE), Some(Action::Home));
+        assert_eq!(executing('g', KeyModifiers::CONTROL), None);
+    }
+
+    #[test]
+    fn test_no_key_bound_twice() {
+        for section in KEYMAP {
+            let keys: Vec<Key> = section.bindings.iter().flat_map(|b| b.keys).map(|&(key, _)| key).collect();
+            for (i, key) in keys.iter().enumerate() {
+                assert!(!keys[..i].contains(key), "{:?} bound twice in {}", key, section.title);
+            }
+            assert!(section.bindings.iter().all(|binding| !binding.keys.is_empty()));
+        }
+    }
+
+    #[test]
+    fn test_keys_label() {
+        let label = |context, description| {
+            let section = KEYMAP.iter().find(|section| section.context == context).unwrap();
+            keys_label(section.bindings.iter().find(|b| b.description == description).unwrap())
+        };
+        assert_eq!(label(KeyContext::Launcher, "Move selection"), "↑ ↓ ← →");
+        assert_eq!(label(KeyContext::Launcher, "Next / previous entry"), "Tab / Shift+Tab");
+        assert_eq!(
+            label(KeyContext::Launcher, "Run the filter text as a command; on a workspace, move the focused window there"),
+            "Alt/Shift/Ctrl+Enter"
+        );
+        assert_eq!(label(KeyContext::Launcher, "Run selected entry as root"), "Ctrl+E / Ctrl+Shift+Enter");
+        assert_eq!(label(KeyContext::Launcher, "Forget the selected entry's history (again to undo)"), "Ctrl+Delete");
+        assert_eq!(label(KeyContext::Executing, "Scroll output"), "↑ ↓ / k / j");
+        assert_eq!(label(KeyContext::Links, "Open link by number"), "1-9");
+    }
+}
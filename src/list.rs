@@ -0,0 +1,78 @@
+//! `--list` and `--list-filter`: the launcher's entries as JSON
+//!
+//! The entries are printed in the order the launcher would show them,
+//! after the same config, blacklist and history handling, so scripts can
+//! look at drun's ranking without a terminal.
+
+use serde::Serialize;
+
+use crate::desktop_entry::Entry;
+use crate::executor::{ModeRules, TerminalMode};
+
+/// One listed entry
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ListedEntry {
+    pub id: String,
+    pub name: String,
+    pub generic_name: Option<String>,
+    pub comment: Option<String>,
+    pub categories: Vec<String>,
+    pub exec: Option<String>,
+    pub icon: Option<String>,
+    /// Detected terminal mode (`gui`, `tui`, ...); None without a command
+    pub terminal_mode: Option<String>,
+    /// Frecency score from history
+    pub frecency: f64,
+    /// Fuzzy match score, with `--list-filter`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub score: Option<f64>,
+}
+
+impl ListedEntry {
+    pub fn new(entry: &Entry, mode_rules: &ModeRules, frecency: f64, score: Option<f64>) -> Self {
+        let detect_entry = (!entry.is_typed_command()).then_some(entry);
+        let terminal_mode = entry
+            .command()
+            .map(|cmd| TerminalMode::detect(&cmd, detect_entry, mode_rules).to_string());
+        Self {
+            id: entry.id.clone(),
+            name: entry.name.clone(),
+            generic_name: entry.generic_name.clone(),
+            comment: entry.comment.clone(),
+            categories: entry.categories.clone(),
+            exec: entry.exec.clone(),
+            icon: entry.icon.clone(),
+            terminal_mode,
+            frecency,
+            score,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_listed_entry_json() {
+        let entry = Entry {
+            id: "htop".to_string(),
+            name: "Htop".to_string(),
+            exec: Some("htop".to_string()),
+            terminal: true,
+            categories: vec!["System".to_string()],
+            ..Default::default()
+        };
+        let listed = ListedEntry::new(&entry, &ModeRules::default(), 1.5, None);
+        assert_eq!(listed.terminal_mode.as_deref(), Some("tui"));
+
+        let json = serde_json::to_value(&listed).unwrap();
+        assert_eq!(json["categories"][0], "System");
+        assert_eq!(json["frecency"], 1.5);
+        assert!(json["generic_name"].is_null());
+        // The match score only shows up when filtering
+        assert!(json.get("score").is_none());
+        let json = serde_json::to_value(ListedEntry::new(&entry, &ModeRules::default(), 0.0, Some(42.0))).unwrap();
+        assert_eq!(json["score"], 42.0);
+    }
+}
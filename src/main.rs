@@ -1,30 +1,82 @@
 mod app;
+mod clipboard;
+mod compositor;
 mod config;
+mod config_check;
+mod config_init;
+mod control;
 mod desktop_entry;
+mod dmenu;
+mod entry_cache;
+mod executables;
 mod executor;
+mod glyphs;
+mod graphics;
 mod history;
+mod hooks;
+mod hyprland;
+mod icon_cache;
+mod icon_theme;
 mod icons;
+mod jobs;
+mod keymap;
+mod list;
+mod matching;
 mod niri;
+mod notify;
+mod output_file;
 mod pty;
+mod search;
+mod sway;
 mod terminal;
+mod tty;
 mod ui;
+mod util;
+mod wakeup;
+mod watcher;
+mod windows;
 
-use anyhow::Result;
+#[cfg(test)]
+mod test_util;
+
+use anyhow::{Context, Result};
 use clap::Parser;
 use crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEventKind},
+    event::{
+        self, DisableBracketedPaste, DisableFocusChange, DisableMouseCapture, EnableBracketedPaste,
+        EnableFocusChange, EnableMouseCapture, KeyboardEnhancementFlags, PopKeyboardEnhancementFlags,
+        PushKeyboardEnhancementFlags,
+        Event, KeyCode, KeyEventKind, MouseButton, MouseEventKind,
+    },
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
-use ratatui::{backend::CrosstermBackend, Terminal};
+use ratatui::{
+    backend::CrosstermBackend,
+    layout::{Position, Rect, Size},
+    Terminal,
+};
 use std::io;
+use std::process::ExitCode;
 use std::sync::Arc;
 use parking_lot::Mutex;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
-use app::App;
+use app::{App, ExecutionPolicy};
+use keymap::{Action, KeyContext};
 use config::Config;
+use dmenu::Dmenu;
 use icons::IconManager;
+use watcher::{ConfigWatcher, EntryWatcher};
+
+/// What the launcher lists (`--mode`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum Mode {
+    /// Applications from desktop entries
+    Drun,
+    /// Programs on $PATH
+    Run,
+}
 
 #[derive(Parser, Debug)]
 #[command(name = "drun")]
@@ -35,21 +87,124 @@ struct Cli {
     #[arg(long, default_value = "~/.config/darkwall-drun/config.toml")]
     config: String,
 
-    /// Run in daemon mode (stay open after command execution)
+    /// Check the config file, print what's wrong with it and exit (1 if
+    /// there are errors)
+    #[arg(long)]
+    check_config: bool,
+
+    /// Write a commented config with every default to PATH (the --config
+    /// path if omitted) and exit
+    #[arg(long, value_name = "PATH", conflicts_with = "check_config")]
+    init_config: Option<Option<String>>,
+
+    /// Overwrite an existing file (with --init-config)
+    #[arg(long, requires = "init_config")]
+    force: bool,
+
+    /// Stay resident: hide instead of exiting and take commands on
+    /// $XDG_RUNTIME_DIR/darkwall-drun.sock
     #[arg(long, short)]
     daemon: bool,
 
-    /// Disable niri IPC integration (auto-disabled when NIRI_SOCKET is absent)
-    #[arg(long)]
-    no_niri: bool,
+    /// Show or hide the running daemon instead of starting drun
+    #[arg(long, conflicts_with = "daemon")]
+    toggle: bool,
+
+    /// Send COMMAND (show, hide, toggle, quit, reload-entries) to the
+    /// running daemon instead of starting drun
+    #[arg(long, value_name = "COMMAND", conflicts_with_all = ["daemon", "toggle"])]
+    send: Option<String>,
+
+    /// Disable compositor IPC integration (niri, sway, Hyprland; auto-disabled
+    /// when no compositor socket is found)
+    #[arg(long, alias = "no-niri")]
+    no_compositor: bool,
 
     /// Enable mouse support (may not work well over SSH)
     #[arg(long)]
     mouse: bool,
+
+    /// Don't read or write the desktop entry and $PATH caches
+    #[arg(long)]
+    no_cache: bool,
+
+    /// Delete cached icons and graphics detection before starting
+    #[arg(long)]
+    clear_icon_cache: bool,
+
+    /// Don't show image icons and skip the terminal graphics query
+    #[arg(long)]
+    no_icons: bool,
+
+    /// Start with the open windows listed, to switch to one (needs niri,
+    /// sway or Hyprland)
+    #[arg(long, conflicts_with = "workspaces")]
+    windows: bool,
+
+    /// Start with the workspaces listed, to switch to one or move the
+    /// focused window there
+    #[arg(long)]
+    workspaces: bool,
+
+    /// Pick one of the lines read from stdin and print it, like dmenu
+    #[arg(long, conflicts_with_all = ["windows", "workspaces", "daemon"])]
+    dmenu: bool,
+
+    /// Remember picks under NAME and list them first (with --dmenu)
+    #[arg(long, value_name = "NAME", requires = "dmenu")]
+    dmenu_history: Option<String>,
+
+    /// Print the zero-based index of the pick instead of the line (with
+    /// --dmenu; -1 for typed text)
+    #[arg(long, requires = "dmenu")]
+    index: bool,
+
+    /// Print the chosen entry's id and command instead of running it
+    #[arg(long, conflicts_with_all = ["windows", "workspaces", "daemon", "dmenu"])]
+    print_only: bool,
+
+    /// Print JSON instead of `id<TAB>command` (with --print-only)
+    #[arg(long, requires = "print_only")]
+    json: bool,
+
+    /// Don't record launches in history
+    #[arg(long)]
+    no_history: bool,
+
+    /// Prompt in front of the filter text
+    #[arg(short, long)]
+    prompt: Option<String>,
+
+    /// Exit with the exit code of the command run in the terminal, once
+    /// its output is dismissed
+    #[arg(long, conflicts_with_all = ["daemon", "dmenu", "print_only"])]
+    propagate_exit: bool,
+
+    /// Print the entries as a JSON array, in launcher order, and exit
+    #[arg(long, conflicts_with_all = ["daemon", "dmenu", "print_only", "windows", "workspaces"])]
+    list: bool,
+
+    /// Like --list, but only the entries matching QUERY, with their match
+    /// scores
+    #[arg(long, value_name = "QUERY", conflicts_with_all = ["daemon", "dmenu", "print_only", "windows", "workspaces"])]
+    list_filter: Option<String>,
+
+    /// List applications (drun) or the programs on $PATH (run; a filter
+    /// starting with > does the same)
+    #[arg(long, value_enum, default_value_t = Mode::Drun, conflicts_with_all = ["dmenu", "windows", "workspaces"])]
+    mode: Mode,
+
+    /// Start with TEXT in the filter
+    #[arg(long, value_name = "TEXT")]
+    filter: Option<String>,
+
+    /// Start with the entry with desktop file id ID selected
+    #[arg(long, value_name = "ID", conflicts_with = "dmenu")]
+    select: Option<String>,
 }
 
 #[tokio::main]
-async fn main() -> Result<()> {
+async fn main() -> Result<ExitCode> {
     // Initialize logging
     tracing_subscriber::registry()
         .with(
@@ -61,25 +216,107 @@ async fn main() -> Result<()> {
 
     let cli = Cli::parse();
 
+    // Clients of a daemon only talk to its socket
+    if cli.toggle || cli.send.is_some() {
+        let command = match cli.send {
+            Some(ref name) => control::Command::from_name(name)?,
+            None => control::Command::Toggle,
+        };
+        control::send(&control::socket_path()?, command)?;
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    if cli.check_config {
+        return Ok(check_config(&cli.config));
+    }
+    if let Some(ref path) = cli.init_config {
+        let path = shellexpand::tilde(path.as_deref().unwrap_or(&cli.config)).into_owned();
+        config_init::write(std::path::Path::new(&path), cli.force)?;
+        println!("Wrote {}", path);
+        return Ok(ExitCode::SUCCESS);
+    }
+
     // Load config
-    let config = Config::load(&cli.config)?;
+    let mut config = Config::load(&cli.config)?;
+    apply_cli_overrides(&cli, &mut config);
+    if cli.dmenu {
+        return run_dmenu(&cli, config).await;
+    }
+
+    if cli.clear_icon_cache {
+        if let Some(dir) = icon_cache::default_dir() {
+            icon_cache::clear(&dir)?;
+            tracing::info!("Cleared icon cache {}", dir.display());
+        }
+        if let Some(path) = graphics::default_cache_path() {
+            let _ = std::fs::remove_file(path);
+        }
+    }
 
-    // Load desktop entries
-    let entries = desktop_entry::load_all(&config.desktop_entry_dirs)?;
+    // Claim the control socket before anything else, so a second daemon
+    // fails right away
+    let mut control = if cli.daemon {
+        Some(control::ControlSocket::bind(&control::socket_path()?)?)
+    } else {
+        None
+    };
+
+    // Load desktop entries (unchanged files come from the cache)
+    let cache_path = if cli.no_cache {
+        None
+    } else {
+        entry_cache::default_path()
+    };
+    let entries = desktop_entry::load_all(
+        &config.entry_dirs(),
+        &config.load_options(),
+        cache_path.as_deref(),
+    )?;
     tracing::info!("Loaded {} desktop entries", entries.len());
 
+    let path_dirs = executables::path_dirs(&std::env::var_os("PATH").unwrap_or_default());
+    let path_cache = if cli.no_cache {
+        None
+    } else {
+        executables::default_cache_path()
+    };
+
+    if cli.list || cli.list_filter.is_some() {
+        let mut app = App::new(entries, config, false);
+        if cli.mode == Mode::Run {
+            let mut found = Vec::new();
+            executables::scan(&path_dirs, path_cache.as_deref(), |entries| found.extend(entries));
+            app.add_executables(found);
+            app.set_run_mode(true);
+        }
+        if let Some(ref query) = cli.list_filter {
+            app.set_initial_filter(query);
+        }
+        println!("{}", serde_json::to_string_pretty(&app.listed_entries())?);
+        return Ok(ExitCode::SUCCESS);
+    }
+
     // TEAM_002: Initialize icon manager BEFORE entering raw mode
     // This queries the terminal for graphics protocol support
     // Skip over SSH to avoid hanging on terminal queries
-    let icon_manager = if config.icons.enabled && std::env::var("SSH_CONNECTION").is_err() {
+    let icon_manager = if cli.no_icons || cli.print_only {
+        tracing::info!("Icons disabled with --no-icons or --print-only");
+        None
+    } else if config.icons.enabled && std::env::var("SSH_CONNECTION").is_err() {
         // Use a timeout to avoid hanging if terminal doesn't respond
-        let mgr = IconManager::new(config.icons.size);
+        let picker = graphics::detect(
+            std::time::Duration::from_millis(config.icons.detect_timeout_ms),
+            graphics::default_cache_path().as_deref(),
+        );
+        let mgr = IconManager::new(picker, &config.icon_options(), icon_cache::default_dir());
         if mgr.supports_graphics() {
             tracing::info!("Graphics icons enabled");
         } else {
             tracing::info!("No graphics protocol detected, icons disabled");
         }
-        Some(Arc::new(Mutex::new(mgr)))
+        let mgr = Arc::new(Mutex::new(mgr));
+        IconManager::start_loader(&mgr);
+        Some(mgr)
     } else {
         if std::env::var("SSH_CONNECTION").is_ok() {
             tracing::info!("Icons disabled over SSH");
@@ -89,35 +326,194 @@ async fn main() -> Result<()> {
         None
     };
 
+    // Like the graphics query, before the event reader can swallow the answer
+    let background = detect_background(&mut config);
+
     // Setup terminal
     // NOTE: DRUN is terminal-agnostic. It uses stdin/stdout/stderr only.
     // No assumptions about specific terminal emulators (kitty, foot, etc.)
-    enable_raw_mode()?;
-    let mut stdout = io::stdout();
-    
-    // Mouse support is off by default for SSH compatibility
-    if cli.mouse {
-        execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
+    // With --print-only, stdout carries the result and the UI goes to the
+    // terminal itself, as in dmenu mode
+    let out: Box<dyn io::Write> = if cli.print_only {
+        Box::new(open_tty("--print-only")?)
     } else {
-        execute!(stdout, EnterAlternateScreen)?;
-    }
-    
-    let backend = CrosstermBackend::new(stdout);
-    let mut terminal = Terminal::new(backend)?;
+        Box::new(io::stdout())
+    };
+    let (mut terminal, modes) = setup_terminal(out, cli.mouse)?;
 
     // Create app state
-    // Niri IPC is auto-disabled if socket doesn't exist (common over SSH)
-    let mut app = App::new(entries, config, !cli.no_niri);
+    // Compositor IPC is auto-disabled if no socket exists (common over SSH);
+    // --print-only never talks to the compositor
+    let mut app = App::new(entries, config, !cli.no_compositor && !cli.print_only);
+    if cli.print_only {
+        app.set_execution_policy(ExecutionPolicy::Print);
+    }
+    app.set_propagate_exit(cli.propagate_exit);
+    app.set_keyboard_enhancement(modes.keyboard_enhancement);
+    app.start_executable_scan(path_dirs, path_cache);
+    if cli.mode == Mode::Run {
+        app.set_run_mode(true);
+    }
+    if cli.windows {
+        app.show_windows().await;
+    } else if cli.workspaces {
+        app.show_workspaces().await;
+    }
+    apply_initial_selection(&cli, &mut app);
+    // From here on, long lists are ranked in the background while typing
+    app.start_match_worker();
+    let area = launcher_area(&app, terminal.size()?);
+    fit_grid(&mut app, area.width, area.height);
+
+    // Reload entries when applications are installed or removed
+    let watcher = EntryWatcher::try_new(&app.config().entry_dirs());
+    // Reload the config when it's edited or on SIGUSR1
+    let config_reload = ConfigReload {
+        cli: &cli,
+        background,
+        watcher: ConfigWatcher::new(
+            std::path::Path::new(shellexpand::tilde(&cli.config).as_ref()),
+            ui::theme::themes_dir().as_deref(),
+        ),
+    };
 
     // Run main loop
-    let result = run_app(&mut terminal, &mut app, icon_manager).await;
+    let result = run_app(
+        &mut terminal,
+        &mut app,
+        icon_manager,
+        watcher.as_ref(),
+        cache_path.as_deref(),
+        control.as_mut(),
+        Some(&config_reload),
+    )
+    .await;
 
     // TEAM_001: Save history before exit
     app.save_history();
 
-    // Restore terminal
+    restore_terminal(&mut terminal, modes)?;
+    result?;
+
+    if app.execution_policy() == ExecutionPolicy::Print {
+        // Nothing chosen (Esc, Ctrl+C): fail without output, like --dmenu
+        return Ok(match app.printed() {
+            Some(printed) => {
+                println!("{}", printed.format(cli.json));
+                ExitCode::SUCCESS
+            }
+            None => ExitCode::FAILURE,
+        });
+    }
+    // A daemon only ends when asked to
+    if cli.daemon {
+        return Ok(ExitCode::SUCCESS);
+    }
+    Ok(ExitCode::from(app.exit_code()))
+}
+
+/// `--dmenu`: pick one of the lines on stdin, print it and exit 0, or
+/// exit 1 without output when cancelled
+///
+/// Only the launcher runs: no desktop entries, icons, watcher or PTY.
+async fn run_dmenu(cli: &Cli, mut config: Config) -> Result<ExitCode> {
+    let lines = dmenu::read_lines(io::stdin().lock())?;
+    let history = cli.dmenu_history.as_deref().map(dmenu::history_path).transpose()?;
+    detect_background(&mut config);
+
+    // stdout carries the pick, so the UI goes to the terminal itself
+    let (mut terminal, modes) = setup_terminal(open_tty("--dmenu")?, cli.mouse)?;
+
+    let mut app = App::new_dmenu(Dmenu::new(lines), config, history);
+    apply_initial_selection(cli, &mut app);
+    app.start_match_worker();
+    let area = launcher_area(&app, terminal.size()?);
+    fit_grid(&mut app, area.width, area.height);
+
+    let result = run_app(&mut terminal, &mut app, None, None, None, None, None).await;
+    app.save_history();
+    restore_terminal(&mut terminal, modes)?;
+    result?;
+
+    match app.dmenu().and_then(|dmenu| dmenu.output(cli.index)) {
+        Some(output) => {
+            println!("{}", output);
+            Ok(ExitCode::SUCCESS)
+        }
+        None => Ok(ExitCode::FAILURE),
+    }
+}
+
+/// `--filter` and `--select`, once history has sorted the entries
+fn apply_initial_selection(cli: &Cli, app: &mut App) {
+    if let Some(ref text) = cli.filter {
+        app.set_initial_filter(text);
+    }
+    if let Some(ref id) = cli.select {
+        app.select_id(id);
+    }
+}
+
+/// The controlling terminal, for the UI when stdout carries a result
+/// (crossterm reads keys from /dev/tty when stdin isn't one)
+fn open_tty(flag: &str) -> Result<std::fs::File> {
+    std::fs::OpenOptions::new()
+        .write(true)
+        .open("/dev/tty")
+        .with_context(|| format!("{} needs a terminal (/dev/tty)", flag))
+}
+
+/// What `setup_terminal` turned on, for `restore_terminal` and handing the
+/// terminal over to TUI commands
+#[derive(Debug, Clone, Copy)]
+struct TerminalModes {
+    mouse: bool,
+    /// Kitty keyboard protocol flags pushed, so keypad keys are reported
+    /// as such
+    keyboard_enhancement: bool,
+}
+
+/// Enter raw mode and the alternate screen on `out`
+///
+/// Like the graphics query, asks about the keyboard protocol before the
+/// event reader can swallow the answer; not over SSH, where a slow link
+/// could outlast the timeout and the answer arrive as keys.
+fn setup_terminal<W: io::Write>(mut out: W, mouse: bool) -> Result<(Terminal<CrosstermBackend<W>>, TerminalModes)> {
+    let keyboard_enhancement = std::env::var("SSH_CONNECTION").is_err()
+        && tty::supports_keyboard_enhancement(tty::KEYBOARD_QUERY_TIMEOUT);
+    enable_raw_mode()?;
+
+    // Mouse support is off by default for SSH compatibility
+    if mouse {
+        execute!(out, EnterAlternateScreen, EnableMouseCapture)?;
+    } else {
+        execute!(out, EnterAlternateScreen)?;
+    }
+    // Pastes arrive as one event instead of a burst of key presses; focus
+    // changes are passed on to commands that ask for them
+    execute!(out, EnableBracketedPaste, EnableFocusChange)?;
+    if keyboard_enhancement {
+        execute!(
+            out,
+            PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::DISAMBIGUATE_ESCAPE_CODES)
+        )?;
+    }
+
+    let modes = TerminalModes {
+        mouse,
+        keyboard_enhancement,
+    };
+    Ok((Terminal::new(CrosstermBackend::new(out))?, modes))
+}
+
+/// Undo `setup_terminal`
+fn restore_terminal<W: io::Write>(terminal: &mut Terminal<CrosstermBackend<W>>, modes: TerminalModes) -> Result<()> {
     disable_raw_mode()?;
-    if cli.mouse {
+    if modes.keyboard_enhancement {
+        execute!(terminal.backend_mut(), PopKeyboardEnhancementFlags)?;
+    }
+    execute!(terminal.backend_mut(), DisableBracketedPaste, DisableFocusChange)?;
+    if modes.mouse {
         execute!(
             terminal.backend_mut(),
             LeaveAlternateScreen,
@@ -127,63 +523,162 @@ async fn main() -> Result<()> {
         execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
     }
     terminal.show_cursor()?;
-
-    result
+    Ok(())
 }
 
+/// Poll timeout while the icon loader has icons on the way, so they show
+/// up as they arrive
+const ICON_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(16);
+
 async fn run_app<B: ratatui::backend::Backend>(
     terminal: &mut Terminal<B>,
     app: &mut App,
     icon_manager: Option<Arc<Mutex<IconManager>>>,
+    watcher: Option<&EntryWatcher>,
+    cache_path: Option<&std::path::Path>,
+    mut control: Option<&mut control::ControlSocket>,
+    config_reload: Option<&ConfigReload<'_>>,
 ) -> Result<()> {
+    // A daemon hides where drun would exit
+    let daemon = control.is_some();
+    // Waited on for input along with command output (stdin may be a pipe)
+    let input = std::fs::File::open("/dev/tty").ok();
     loop {
-        // Get terminal size for PTY
-        let size = terminal.size()?;
+        // Commands from `drun --toggle` and friends
+        while let Some(command) = control.as_mut().and_then(|c| c.try_recv()) {
+            match command {
+                control::Command::Show => app.show().await,
+                control::Command::Hide => app.hide(),
+                control::Command::Toggle if app.is_hidden() => app.show().await,
+                control::Command::Toggle => app.hide(),
+                control::Command::Quit => {
+                    app.show().await;
+                    if app.confirm_exit() {
+                        return Ok(());
+                    }
+                }
+                control::Command::ReloadEntries => reload_entries(app, icon_manager.as_ref(), cache_path),
+            }
+            app.mark_dirty();
+        }
+
+        // Re-scan desktop entries after a (debounced) directory change
+        if watcher.is_some_and(|w| w.poll_changed()) {
+            reload_entries(app, icon_manager.as_ref(), cache_path);
+            app.mark_dirty();
+        }
+
+        // Get the launcher's size for the grid and PTY
+        let mut area = launcher_area(app, terminal.size()?);
+
+        if let Some(reload) = config_reload.filter(|r| r.watcher.poll_changed()) {
+            match Config::load(&reload.cli.config) {
+                Ok(mut config) => {
+                    apply_cli_overrides(reload.cli, &mut config);
+                    if let Some(background) = reload.background {
+                        config.set_background(background);
+                    }
+                    app.reload_config(config);
+                    // Custom entries and overrides may have changed too
+                    reload_entries(app, icon_manager.as_ref(), cache_path);
+                    // The launcher may have a new size
+                    area = launcher_area(app, terminal.size()?);
+                    fit_grid(app, area.width, area.height);
+                    app.resize_pty(area.width.saturating_sub(2), area.height.saturating_sub(6)).ok();
+                }
+                Err(e) => app.reject_config(&e),
+            }
+            app.mark_dirty();
+        }
         
-        // Preload one icon per frame (non-blocking gradual loading)
-        if let Some(ref mgr) = icon_manager {
-            let entries = app.visible_entries();
+        // Ask the loader thread for the icons on this page
+        let icons_loading = icon_manager.as_ref().is_some_and(|mgr| {
+            let entries = app.page_entries();
             let icon_iter = entries.iter().map(|e| (e.id.as_str(), e.icon.as_deref()));
-            mgr.lock().try_load_one(icon_iter);
+            let mut mgr = mgr.lock();
+            mgr.request_visible(icon_iter);
+            mgr.is_loading()
+        });
+        if icon_manager.as_ref().is_some_and(|mgr| mgr.lock().take_arrived()) {
+            app.mark_dirty();
+        }
+
+        // Only when something changed, or for the heartbeat
+        if app.take_redraw() {
+            terminal.draw(|f| ui::draw(f, app, icon_manager.as_ref()))?;
         }
-        
-        terminal.draw(|f| ui::draw(f, app, icon_manager.as_ref()))?;
 
         // Handle TUI handover mode
-        if let app::AppMode::TuiHandover { command } = app.mode() {
+        if let app::AppMode::TuiHandover { command, launch } = app.mode() {
             let cmd = command.clone();
-            app.execute_tui(&cmd)?;
+            let launch = launch.clone();
+            app.execute_tui(&cmd, &launch)?;
+            app.mark_dirty();
             continue;
         }
 
         // Handle exit mode (GUI app launched)
         if matches!(app.mode(), app::AppMode::Exit) {
-            return Ok(());
+            if daemon {
+                app.hide();
+                app.mark_dirty();
+                continue;
+            }
+            if app.confirm_exit() {
+                return Ok(());
+            }
         }
 
-        // Poll PTY if executing
-        if app.is_executing() {
-            app.poll_execution()?;
+        app.poll()?;
+        if app.poll_launch().await? {
+            app.mark_dirty();
         }
 
-        // Use shorter poll timeout when executing to be responsive
-        let poll_timeout = if app.is_executing() {
-            std::time::Duration::from_millis(16) // ~60fps
+        // Short poll timeout only while a frame is held back or icons are
+        // arriving; command output wakes the loop itself
+        let timeout = if icons_loading {
+            app.poll_interval().min(ICON_POLL_INTERVAL)
         } else {
-            std::time::Duration::from_millis(100)
+            app.poll_interval()
         };
-
-        if event::poll(poll_timeout)? {
-            match event::read()? {
-                Event::Key(key) if key.kind == KeyEventKind::Press => {
-                    if handle_key_event(app, key, size.width, size.height).await? {
+        if wait_for_input(app.wakeup(), input.as_ref(), timeout)? {
+            let event = event::read()?;
+            app.mark_dirty();
+            match event {
+                // Hidden, any key shows the launcher again; Ctrl+C quits
+                Event::Key(key) if key.kind == KeyEventKind::Press && app.is_hidden() => {
+                    let ctrl_c = key.code == KeyCode::Char('c')
+                        && key.modifiers.contains(event::KeyModifiers::CONTROL);
+                    app.show().await;
+                    if ctrl_c && app.confirm_exit() {
                         return Ok(());
                     }
                 }
+                Event::Key(key)
+                    if key.kind == KeyEventKind::Press
+                        && handle_key_event(app, key, area.width, area.height).await? =>
+                {
+                    if daemon {
+                        app.hide();
+                    } else if app.confirm_exit() {
+                        return Ok(());
+                    }
+                }
+                // Relative to the launcher; around it the mouse does nothing
+                Event::Mouse(mut mouse) if area.contains(Position::new(mouse.column, mouse.row)) => {
+                    mouse.column -= area.x;
+                    mouse.row -= area.y;
+                    handle_mouse_event(app, mouse, area.width, area.height).await?
+                }
+                Event::Paste(text) => handle_paste(app, &text)?,
+                Event::FocusGained => handle_focus(app, true)?,
+                Event::FocusLost => handle_focus(app, false)?,
                 Event::Resize(cols, rows) => {
+                    let area = launcher_area(app, Size::new(cols, rows));
+                    fit_grid(app, area.width, area.height);
                     // Propagate resize to PTY (adjusted for UI chrome)
-                    let output_cols = cols.saturating_sub(2);
-                    let output_rows = rows.saturating_sub(6);
+                    let output_cols = area.width.saturating_sub(2);
+                    let output_rows = area.height.saturating_sub(6);
                     app.resize_pty(output_cols, output_rows).ok();
                 }
                 _ => {}
@@ -192,6 +687,247 @@ async fn run_app<B: ratatui::backend::Backend>(
     }
 }
 
+/// Wait up to `timeout` for an event, or until the running command prints
+///
+/// Returns whether an event can be read without blocking.
+fn wait_for_input(
+    wakeup: Option<&wakeup::Wakeup>,
+    input: Option<&std::fs::File>,
+    timeout: std::time::Duration,
+) -> Result<bool> {
+    let (Some(wakeup), Some(input)) = (wakeup, input) else {
+        return Ok(event::poll(timeout)?);
+    };
+    // Events crossterm already read ahead don't make the terminal readable
+    if event::poll(std::time::Duration::ZERO)? {
+        return Ok(true);
+    }
+    if !wakeup.wait(input, timeout) {
+        return Ok(false);
+    }
+    // Readable doesn't mean a whole event is there yet
+    Ok(event::poll(std::time::Duration::ZERO)?)
+}
+
+/// `--check-config`: print the findings and a summary
+fn check_config(path: &str) -> ExitCode {
+    let found = config_check::check_file(path);
+    let path = std::path::PathBuf::from(shellexpand::tilde(path).as_ref());
+    for diagnostic in &found {
+        println!("{}", diagnostic.format(&path));
+    }
+    let errors = found.iter().filter(|d| d.severity == config_check::Severity::Error).count();
+    let warnings = found.len() - errors;
+    if found.is_empty() {
+        println!("{}: OK", path.display());
+    } else {
+        println!("{} error(s), {} warning(s)", errors, warnings);
+    }
+    if errors > 0 {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+/// Ask the terminal whether its background is dark or light, and pick
+/// `theme.preset_dark` or `preset_light` accordingly
+///
+/// Only when the config has either; not over SSH, where a slow link could
+/// outlast the timeout and the answer arrive as keys.
+fn detect_background(config: &mut Config) -> Option<ui::theme::Background> {
+    if !config.theme.follows_background() || std::env::var("SSH_CONNECTION").is_ok() {
+        return None;
+    }
+    let background = ui::theme::detect_background(ui::theme::BACKGROUND_QUERY_TIMEOUT)?;
+    config.set_background(background);
+    Some(background)
+}
+
+/// Command-line settings that win over the config file
+fn apply_cli_overrides(cli: &Cli, config: &mut Config) {
+    if let Some(ref prompt) = cli.prompt {
+        config.appearance.prompt = prompt.clone();
+    }
+    if cli.no_history {
+        config.history.enabled = false;
+    }
+}
+
+/// The config file being watched, and the flags to re-apply on reload
+struct ConfigReload<'a> {
+    cli: &'a Cli,
+    /// Detected at startup, for `theme.preset_dark`/`preset_light`
+    background: Option<ui::theme::Background>,
+    watcher: ConfigWatcher,
+}
+
+/// Re-scan the desktop entries
+fn reload_entries(
+    app: &mut App,
+    icon_manager: Option<&Arc<Mutex<IconManager>>>,
+    cache_path: Option<&std::path::Path>,
+) {
+    let config = app.config();
+    match desktop_entry::load_all(&config.entry_dirs(), &config.load_options(), cache_path) {
+        Ok(entries) => {
+            tracing::info!("Reloaded {} desktop entries", entries.len());
+            let changed_icons = app.reload_entries(entries);
+            // Icons may have been installed along with the entries
+            if let Some(mgr) = icon_manager {
+                let mut mgr = mgr.lock();
+                mgr.retry_failed();
+                for id in &changed_icons {
+                    mgr.invalidate(id);
+                }
+            }
+        }
+        Err(e) => tracing::warn!("Failed to reload desktop entries: {}", e),
+    }
+}
+
+/// The launcher's part of a terminal of `size`
+/// (`appearance.width`/`max_height`)
+fn launcher_area(app: &App, size: Size) -> Rect {
+    app.config().appearance.area(Rect::new(0, 0, size.width, size.height))
+}
+
+/// Fit the launcher grid to a `cols` x `rows` screen
+/// (for `appearance.columns = "auto"`)
+fn fit_grid(app: &mut App, cols: u16, rows: u16) {
+    let areas = ui::LauncherAreas::new(Rect::new(0, 0, cols, rows), app.show_preview());
+    app.fit_grid(areas.list_inner().width);
+}
+
+/// Handle mouse events (only delivered with --mouse)
+///
+/// A running command that enabled mouse reporting gets the events in the
+/// encoding it asked for; otherwise the wheel scrolls the output. In the launcher a
+/// click selects a card, a double click runs it and the wheel pages.
+async fn handle_mouse_event(app: &mut App, mouse: event::MouseEvent, cols: u16, rows: u16) -> Result<()> {
+    use app::AppMode;
+
+    match app.mode() {
+        AppMode::Launcher if !app.is_browsing_categories() => match mouse.kind {
+            MouseEventKind::ScrollUp => app.page_up(),
+            MouseEventKind::ScrollDown => app.page_down(),
+            MouseEventKind::Down(MouseButton::Left) | MouseEventKind::Drag(MouseButton::Left) => {
+                let areas = ui::LauncherAreas::new(Rect::new(0, 0, cols, rows), app.show_preview());
+                let position = Position::new(mouse.column, mouse.row);
+                let pages = app.grid_layout().page_count(app.visible_entries().len());
+                // Clicking or dragging on the scrollbar jumps pages
+                if let Some(page) = areas.scrollbar_page(mouse.column, mouse.row, pages).filter(|_| pages > 1) {
+                    app.go_to_page(page);
+                    return Ok(());
+                }
+                if matches!(mouse.kind, MouseEventKind::Drag(_)) {
+                    return Ok(());
+                }
+                let card_height = app.config().entry_display_config().card_height();
+                if areas.search.contains(position) {
+                    app.start_filter();
+                } else if let Some(slot) =
+                    app.grid_layout()
+                        .hit_test(mouse.column, mouse.row, areas.list_inner(), card_height)
+                {
+                    // A double click runs the entry, like Enter
+                    if app.click_card(slot) {
+                        if let Some(entry) = app.selected_entry() {
+                            let (output_cols, output_rows) = (cols.saturating_sub(2), rows.saturating_sub(6));
+                            app.execute_entry(entry.clone(), output_cols, output_rows).await?;
+                        }
+                    }
+                }
+            }
+            _ => {}
+        },
+        AppMode::Executing { .. } if app.terminal().mouse_enabled() => {
+            // Only events over the output area, relative to it
+            let (cols, rows) = app.terminal().size();
+            let output = app.output_area();
+            let (Some(col), Some(row)) = (
+                mouse.column.checked_sub(output.x),
+                mouse.row.checked_sub(output.y),
+            ) else {
+                return Ok(());
+            };
+            if col as usize >= cols || row as usize >= rows {
+                return Ok(());
+            }
+            if let Some(seq) = crate::terminal::encode_crossterm_mouse(app.terminal(), &mouse, col, row) {
+                app.send_input(&seq)?;
+            }
+        }
+        AppMode::Executing { .. } | AppMode::PostExecution { .. } => {
+            // Using the output keeps it on screen, like a key
+            if mouse.kind != MouseEventKind::Moved {
+                app.cancel_auto_dismiss();
+            }
+            match mouse.kind {
+                MouseEventKind::ScrollUp => app.terminal_mut().scroll_up(3),
+                MouseEventKind::ScrollDown => app.terminal_mut().scroll_down(3),
+                // Dragging selects output after the command has finished
+                MouseEventKind::Down(MouseButton::Left) | MouseEventKind::Drag(MouseButton::Left)
+                    if app.is_post_execution() =>
+                {
+                    let (cols, rows) = app.terminal().size();
+                    let output = app.output_area();
+                    let col = mouse.column.saturating_sub(output.x).min((cols as u16).saturating_sub(1));
+                    let row = mouse.row.saturating_sub(output.y).min((rows as u16).saturating_sub(1));
+                    let extend = matches!(mouse.kind, MouseEventKind::Drag(_));
+                    app.select_output_cell(col, row, extend);
+                }
+                // A click without dragging selects nothing
+                MouseEventKind::Up(MouseButton::Left)
+                    if app
+                        .terminal()
+                        .selection()
+                        .is_some_and(|selection| selection.anchor == selection.head) =>
+                {
+                    app.cancel_selection();
+                }
+                _ => {}
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Handle pasted text
+///
+/// Running commands get it as input (bracketed if they asked for it); in
+/// the launcher it is added to the filter.
+fn handle_paste(app: &mut App, text: &str) -> Result<()> {
+    use app::AppMode;
+
+    match app.mode() {
+        AppMode::Launcher if !app.is_browsing_categories() => app.paste_into_filter(text),
+        AppMode::Executing { .. } | AppMode::PostExecution { .. }
+            if app.output_search().is_some_and(|search| search.editing) =>
+        {
+            app.paste_into_search(text);
+        }
+        AppMode::Executing { .. } => {
+            let data = app.terminal().encode_paste(text);
+            app.send_input(data.as_bytes())?;
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Tell a running command that asked for focus reports (mode 1004) that
+/// drun's terminal gained or lost focus
+fn handle_focus(app: &mut App, focused: bool) -> Result<()> {
+    if let app::AppMode::Executing { .. } = app.mode() {
+        if let Some(report) = app.terminal().encode_focus(focused) {
+            app.send_input(report.as_bytes())?;
+        }
+    }
+    Ok(())
+}
+
 /// Handle key events based on current app mode
 /// Returns true if the app should exit
 async fn handle_key_event(
@@ -202,15 +938,64 @@ async fn handle_key_event(
 ) -> Result<bool> {
     use app::AppMode;
 
+    if app.help_scroll().is_some() {
+        handle_help_keys(app, key, rows);
+        return Ok(false);
+    }
+    if opens_help(app, &key) {
+        app.open_help();
+        return Ok(false);
+    }
+
     match app.mode() {
         AppMode::Launcher => handle_launcher_keys(app, key, cols, rows).await,
         AppMode::Executing { .. } => handle_executing_keys(app, key),
-        AppMode::PostExecution { .. } => handle_post_execution_keys(app, key),
+        AppMode::PostExecution { .. } => handle_post_execution_keys(app, key, cols, rows).await,
+        AppMode::Jobs { confirm_exit } => {
+            let confirm_exit = *confirm_exit;
+            handle_jobs_keys(app, key, confirm_exit, cols, rows).await
+        }
         AppMode::TuiHandover { .. } => Ok(false), // Handled in main loop
         AppMode::Exit => Ok(true), // Exit immediately
     }
 }
 
+/// Whether `key` opens the help overlay
+///
+/// F1 works in every mode and `?` wherever it isn't typed as text; an
+/// interactive command gets both as input.
+fn opens_help(app: &App, key: &event::KeyEvent) -> bool {
+    use app::AppMode;
+
+    let typing = match app.mode() {
+        AppMode::Launcher => app.is_filtering() || !app.filter_text().is_empty(),
+        AppMode::Executing {
+            mode: executor::TerminalMode::Interactive,
+            ..
+        } => return false,
+        _ => false,
+    } || app.output_search().is_some_and(|search| search.editing);
+    match key.code {
+        KeyCode::F(1) => true,
+        KeyCode::Char('?') => !typing && !key.modifiers.contains(event::KeyModifiers::CONTROL),
+        _ => false,
+    }
+}
+
+/// Handle keys while the help overlay is open: scroll keys scroll it and
+/// any other key closes it
+fn handle_help_keys(app: &mut App, key: event::KeyEvent, rows: u16) {
+    match key.code {
+        KeyCode::Up | KeyCode::Char('k') => app.scroll_help(-1, rows),
+        KeyCode::Down | KeyCode::Char('j') => app.scroll_help(1, rows),
+        KeyCode::PageUp => app.scroll_help(-10, rows),
+        KeyCode::PageDown => app.scroll_help(10, rows),
+        KeyCode::Home => app.scroll_help(isize::MIN, rows),
+        KeyCode::End => app.scroll_help(isize::MAX, rows),
+        _ => app.close_help(),
+    }
+}
+
 /// Handle keys in launcher mode
 /// TEAM_004: Added grid navigation (left/right/tab/page)
 async fn handle_launcher_keys(
@@ -219,101 +1004,294 @@ async fn handle_launcher_keys(
     cols: u16,
     rows: u16,
 ) -> Result<bool> {
-    match key.code {
-        // Ctrl+C always exits
-        KeyCode::Char('c') if key.modifiers.contains(event::KeyModifiers::CONTROL) => {
-            return Ok(true);
+    if app.is_browsing_categories() {
+        return Ok(handle_category_keys(app, key));
+    }
+
+    // Adjust size for UI chrome: header(3) + output borders(2) + status(1) = 6 rows
+    // And 2 columns for left/right borders
+    let output_cols = cols.saturating_sub(2);
+    let output_rows = rows.saturating_sub(6);
+
+    // Enter runs what's selected for the filter as typed, even if the
+    // match worker is still ranking it
+    if key.code == KeyCode::Enter {
+        app.finish_ranking();
+    }
+
+    let action = keymap::action(KeyContext::Launcher, &key);
+    // An armed elevated run is confirmed with Enter; any other key cancels it
+    if app.is_elevation_armed() {
+        app.cancel_elevation();
+        match action {
+            Some(Action::Confirm) => {
+                if let Some(entry) = app.selected_entry() {
+                    app.execute_entry_elevated(entry.clone(), output_cols, output_rows)
+                        .await?;
+                }
+                return Ok(false);
+            }
+            Some(Action::Back) => return Ok(false),
+            _ => {}
         }
+    }
+
+    if app.dmenu().is_some() {
+        match action {
+            // Esc cancels right away, like dmenu
+            Some(Action::Back) => return Ok(true),
+            // Running as root, jobs, windows and categories don't apply
+            // to plain lines
+            Some(Action::Elevate | Action::Jobs | Action::Windows | Action::Categories) => return Ok(false),
+            _ => {}
+        }
+    }
+    if app.execution_policy() == ExecutionPolicy::Print {
+        // Nothing runs, so there are no jobs, and no compositor for windows
+        if let Some(Action::Jobs | Action::Windows | Action::Float) = action {
+            return Ok(false);
+        }
+    }
+    let Some(action) = action else {
+        return Ok(false);
+    };
+    match action {
+        Action::Quit => return Ok(true),
+        Action::Elevate => app.arm_elevation(),
+        Action::Jobs => app.open_jobs(),
+        Action::Preview => {
+            app.toggle_preview();
+            fit_grid(app, cols, rows);
+        }
+        Action::Float => app.toggle_floating().await,
+        Action::Forget => app.toggle_forget(),
+        Action::CompositorStatus => app.show_compositor_status(),
+        Action::Windows => app.toggle_windows().await,
+        // Only with an empty filter
+        Action::Categories if app.filter_text().is_empty() => app.open_categories(),
         // Esc clears filter or exits
-        KeyCode::Esc => {
+        Action::Back => {
             if app.is_filtering() || !app.filter_text().is_empty() {
                 app.clear_filter();
             } else {
                 return Ok(true); // Exit
             }
         }
-        // Enter executes selected entry
-        KeyCode::Enter => {
-            if let Some(entry) = app.selected_entry() {
-                // Adjust size for UI chrome: header(3) + output borders(2) + status(1) = 6 rows
-                // And 2 columns for left/right borders
-                let output_cols = cols.saturating_sub(2);
-                let output_rows = rows.saturating_sub(6);
+        // Enter executes selected entry; with no matches, if enabled, it
+        // runs the filter text as a shell command
+        Action::Confirm => match app.selected_entry() {
+            Some(entry) => {
                 app.execute_entry(entry.clone(), output_cols, output_rows).await?;
             }
-        }
+            None if !app.config().behavior.enter_runs_typed_command => {}
+            None => app.execute_typed_command(output_cols, output_rows).await?,
+        },
+        Action::RunTyped => match app.selected_entry() {
+            // On a workspace, moves the focused window there
+            Some(entry) if app.compositor_list() == Some(app::CompositorList::Workspaces) => {
+                let entry = entry.clone();
+                app.move_focused_window(&entry).await;
+            }
+            _ => app.execute_typed_command(output_cols, output_rows).await?,
+        },
         // Navigation - arrows always work
-        KeyCode::Up => app.previous(),
-        KeyCode::Down => app.next(),
-        KeyCode::Left => app.move_left(),
-        KeyCode::Right => app.move_right(),
+        Action::Up => app.previous(),
+        Action::Down => app.next(),
+        Action::Left => app.move_left(),
+        Action::Right => app.move_right(),
         // TEAM_004: Page navigation
-        KeyCode::PageUp => app.page_up(),
-        KeyCode::PageDown => app.page_down(),
-        KeyCode::Home => app.move_home(),
-        KeyCode::End => app.move_end(),
+        Action::PageUp => app.page_up(),
+        Action::PageDown => app.page_down(),
+        Action::Home => app.move_home(),
+        Action::End => app.move_end(),
         // TEAM_004: Tab navigation (wraps around)
-        KeyCode::Tab => {
-            if key.modifiers.contains(event::KeyModifiers::SHIFT) {
-                app.tab_prev();
-            } else {
-                app.tab_next();
+        Action::Next => app.tab_next(),
+        Action::Previous => app.tab_prev(),
+        // Backspace in filter mode
+        Action::DeleteChar if app.is_filtering() || !app.filter_text().is_empty() => {
+            app.pop_filter_char();
+        }
+        // Any printable char starts/continues filtering
+        Action::Type => {
+            if let KeyCode::Char(c) = key.code {
+                if !app.is_filtering() {
+                    app.start_filter();
+                }
+                app.push_filter_char(c);
             }
         }
-        KeyCode::BackTab => app.tab_prev(),
-        // Backspace in filter mode
-        KeyCode::Backspace => {
-            if app.is_filtering() || !app.filter_text().is_empty() {
-                app.pop_filter_char();
+        _ => {}
+    }
+    Ok(false)
+}
+
+/// Handle keys while selecting output
+fn handle_selection_keys(app: &mut App, key: event::KeyEvent) {
+    match keymap::action(KeyContext::Selecting, &key) {
+        Some(Action::Back) => app.cancel_selection(),
+        Some(Action::Copy) => {
+            if let Err(e) = app.copy_selection() {
+                tracing::warn!("Failed to copy to clipboard: {}", e);
             }
         }
-        // Any printable char starts/continues filtering
-        KeyCode::Char(c) => {
-            if !app.is_filtering() {
-                app.start_filter();
+        Some(Action::Up) => app.move_selection(-1, 0),
+        Some(Action::Down) => app.move_selection(1, 0),
+        Some(Action::Left) => app.move_selection(0, -1),
+        Some(Action::Right) => app.move_selection(0, 1),
+        Some(Action::PageUp) => app.move_selection(-10, 0),
+        Some(Action::PageDown) => app.move_selection(10, 0),
+        Some(Action::Home) => app.move_selection(0, isize::MIN),
+        Some(Action::End) => app.move_selection(0, isize::MAX),
+        _ => {}
+    }
+}
+
+/// Handle keys while the link picker is open
+fn handle_link_picker_keys(app: &mut App, key: event::KeyEvent) {
+    match keymap::action(KeyContext::Links, &key) {
+        Some(Action::Back) => app.close_link_picker(),
+        Some(Action::Confirm) => app.open_selected_link(),
+        // Number keys open the link with that number
+        Some(Action::OpenNumber) => {
+            if let KeyCode::Char(c) = key.code {
+                app.open_link(c as usize - '1' as usize);
             }
-            app.push_filter_char(c);
         }
+        Some(Action::Up) => app.move_link(-1),
+        Some(Action::Down) => app.move_link(1),
+        Some(Action::Home) => app.move_link(isize::MIN),
+        Some(Action::End) => app.move_link(isize::MAX),
+        _ => {}
+    }
+}
+
+/// Handle keys while the category browser is open
+/// Returns true if the app should exit
+fn handle_category_keys(app: &mut App, key: event::KeyEvent) -> bool {
+    match keymap::action(KeyContext::Categories, &key) {
+        Some(Action::Quit) => return true,
+        Some(Action::Back) => app.close_categories(),
+        Some(Action::Confirm) => app.choose_category(),
+        Some(Action::Up) => app.move_category(-1),
+        Some(Action::Down) => app.move_category(1),
+        Some(Action::PageUp) => app.move_category(-10),
+        Some(Action::PageDown) => app.move_category(10),
+        Some(Action::Home) => app.move_category(isize::MIN),
+        Some(Action::End) => app.move_category(isize::MAX),
+        _ => {}
+    }
+    false
+}
+
+/// Handle keys in the jobs view
+/// Returns true if the app should exit
+async fn handle_jobs_keys(
+    app: &mut App,
+    key: event::KeyEvent,
+    confirm_exit: bool,
+    cols: u16,
+    rows: u16,
+) -> Result<bool> {
+    match keymap::action(KeyContext::Jobs, &key) {
+        Some(Action::Quit) => return Ok(true),
+        Some(Action::Yes) if confirm_exit => return Ok(true),
+        Some(Action::Back) => app.close_jobs(),
+        // Attach (same size handling as launching an entry)
+        Some(Action::Confirm) => {
+            let output_cols = cols.saturating_sub(2);
+            let output_rows = rows.saturating_sub(6);
+            app.attach_job(output_cols, output_rows).await?;
+        }
+        Some(Action::Dismiss) => app.dismiss_job(),
+        Some(Action::Up) => app.move_job(-1),
+        Some(Action::Down) => app.move_job(1),
+        Some(Action::Home) => app.move_job(isize::MIN),
+        Some(Action::End) => app.move_job(isize::MAX),
         _ => {}
     }
     Ok(false)
 }
 
+/// Handle keys while searching command output
+/// Returns true if the key was consumed
+fn handle_output_search_keys(app: &mut App, key: event::KeyEvent) -> bool {
+    let Some(search) = app.output_search() else {
+        return false;
+    };
+    // Ctrl combinations (Ctrl+C etc.) keep working while searching
+    if key.modifiers.contains(event::KeyModifiers::CONTROL) {
+        return false;
+    }
+    if search.editing {
+        match keymap::action(KeyContext::SearchInput, &key) {
+            Some(Action::Back) => app.cancel_output_search(),
+            Some(Action::Confirm) => app.confirm_output_search(),
+            Some(Action::ToggleCase) => app.toggle_search_case(),
+            Some(Action::DeleteChar) => app.pop_search_char(),
+            Some(Action::Type) => {
+                if let KeyCode::Char(c) = key.code {
+                    app.push_search_char(c);
+                }
+            }
+            _ => {}
+        }
+        return true;
+    }
+    match keymap::action(KeyContext::Search, &key) {
+        Some(Action::NextMatch) => app.next_search_match(true),
+        Some(Action::PreviousMatch) => app.next_search_match(false),
+        Some(Action::Search) => app.start_output_search(),
+        Some(Action::Back) => app.cancel_output_search(),
+        _ => return false,
+    }
+    true
+}
+
 /// Handle keys in executing mode
 fn handle_executing_keys(app: &mut App, key: event::KeyEvent) -> Result<bool> {
-    use crate::terminal::{convert_keycode, convert_modifiers};
+    use crate::terminal::encode_crossterm_key;
 
-    match key.code {
+    // Interactive commands get '/' and 's' as input; output search and
+    // saving are for the rest
+    let output_keys = !matches!(
+        app.mode(),
+        app::AppMode::Executing {
+            mode: executor::TerminalMode::Interactive,
+            ..
+        }
+    );
+    if handle_output_search_keys(app, key) {
+        return Ok(false);
+    }
+
+    match keymap::action(KeyContext::Executing, &key) {
         // Ctrl+C kills the process
-        KeyCode::Char('c') if key.modifiers.contains(event::KeyModifiers::CONTROL) => {
-            app.kill_execution();
+        Some(Action::Stop) => app.kill_execution(),
+        Some(Action::Search) if output_keys => app.start_output_search(),
+        // Save output to a file
+        Some(Action::Save) if output_keys => app.save_output(),
+        // K kills a oneshot command that exceeded its timeout
+        Some(Action::Kill) if app.is_timed_out() => app.kill_execution(),
+        // Ctrl+B moves the process to the background; interactive and
+        // full-screen programs get it as input (readline, tmux prefix)
+        Some(Action::Background) if output_keys && !app.terminal().in_alternate_screen() => {
+            app.detach_execution();
         }
         // Scroll output (only when not following/at bottom)
-        KeyCode::Up | KeyCode::Char('k') if !app.terminal().is_at_bottom() => {
-            app.terminal_mut().scroll_up(1);
-        }
-        KeyCode::Down | KeyCode::Char('j') if !app.terminal().is_at_bottom() => {
-            app.terminal_mut().scroll_down(1);
-        }
-        KeyCode::Char('u') if key.modifiers.contains(event::KeyModifiers::CONTROL) => {
-            app.terminal_mut().scroll_up(10);
-        }
-        KeyCode::Char('d') if key.modifiers.contains(event::KeyModifiers::CONTROL) => {
-            app.terminal_mut().scroll_down(10);
-        }
-        KeyCode::Char('g') => {
+        Some(Action::Up) if !app.terminal().is_at_bottom() => app.terminal_mut().scroll_up(1),
+        Some(Action::Down) if !app.terminal().is_at_bottom() => app.terminal_mut().scroll_down(1),
+        Some(Action::PageUp) => app.terminal_mut().scroll_up(10),
+        Some(Action::PageDown) => app.terminal_mut().scroll_down(10),
+        Some(Action::Home) => {
             // Scroll to top of scrollback
             let max_offset = app.terminal().scrollback().len();
             app.terminal_mut().set_scroll_offset(max_offset);
         }
-        KeyCode::Char('G') => {
-            app.terminal_mut().scroll_to_bottom();
-        }
-        // Forward other input to the process using proper key encoding
+        Some(Action::End) => app.terminal_mut().scroll_to_bottom(),
+        // Forward other input to the process using proper key encoding;
+        // keys without a terminal encoding are dropped
         _ => {
-            let tw_key = convert_keycode(key.code);
-            let tw_mods = convert_modifiers(key.modifiers);
-            let encoded = app.terminal().encode_key(tw_key, tw_mods);
+            let encoded = encode_crossterm_key(app.terminal(), &key);
             if !encoded.is_empty() {
                 app.send_input(encoded.as_bytes())?;
             }
@@ -324,49 +1302,65 @@ fn handle_executing_keys(app: &mut App, key: event::KeyEvent) -> Result<bool> {
 
 /// Handle keys in post-execution mode
 /// Uses same scroll handling as Executing mode via terminal
-fn handle_post_execution_keys(app: &mut App, key: event::KeyEvent) -> Result<bool> {
-    match key.code {
+async fn handle_post_execution_keys(
+    app: &mut App,
+    key: event::KeyEvent,
+    cols: u16,
+    rows: u16,
+) -> Result<bool> {
+    // Any key keeps the output on screen
+    app.cancel_auto_dismiss();
+
+    if app.link_picker().is_some() {
+        handle_link_picker_keys(app, key);
+        return Ok(false);
+    }
+    if app.is_selecting() {
+        handle_selection_keys(app, key);
+        return Ok(false);
+    }
+
+    if handle_output_search_keys(app, key) {
+        return Ok(false);
+    }
+
+    match keymap::action(KeyContext::PostExecution, &key) {
         // Enter or Esc dismisses output and returns to launcher
-        KeyCode::Enter | KeyCode::Esc => {
-            app.dismiss_output();
-        }
+        Some(Action::Back) => app.dismiss_output(),
         // Ctrl+C or q exits
-        KeyCode::Char('c') if key.modifiers.contains(event::KeyModifiers::CONTROL) => {
-            return Ok(true);
-        }
-        KeyCode::Char('q') => {
-            return Ok(true);
+        Some(Action::Quit) => return Ok(true),
+        // Re-run the same command (same size handling as launching an entry)
+        Some(Action::Rerun) => {
+            let output_cols = cols.saturating_sub(2);
+            let output_rows = rows.saturating_sub(6);
+            app.rerun_last(output_cols, output_rows).await?;
         }
+        Some(Action::Search) => app.start_output_search(),
+        // Save output to a file
+        Some(Action::Save) => app.save_output(),
+        // Pick a link from the output to open
+        Some(Action::Links) => app.open_link_picker(),
+        // Select part of the output to copy
+        Some(Action::Select) => app.start_selection(),
         // Copy output to clipboard
-        KeyCode::Char('y') => {
+        Some(Action::Copy) => {
             if let Err(e) = app.copy_output_to_clipboard() {
                 tracing::warn!("Failed to copy to clipboard: {}", e);
             }
         }
-        // Scroll up (into scrollback history)
-        KeyCode::Up | KeyCode::Char('k') => {
-            app.terminal_mut().scroll_up(1);
-        }
-        // Scroll down (toward current output)
-        KeyCode::Down | KeyCode::Char('j') => {
-            app.terminal_mut().scroll_down(1);
-        }
+        // Scroll up (into scrollback history) and down (toward current output)
+        Some(Action::Up) => app.terminal_mut().scroll_up(1),
+        Some(Action::Down) => app.terminal_mut().scroll_down(1),
         // Page up/down
-        KeyCode::Char('u') if key.modifiers.contains(event::KeyModifiers::CONTROL) => {
-            app.terminal_mut().scroll_up(10);
-        }
-        KeyCode::Char('d') if key.modifiers.contains(event::KeyModifiers::CONTROL) => {
-            app.terminal_mut().scroll_down(10);
-        }
+        Some(Action::PageUp) => app.terminal_mut().scroll_up(10),
+        Some(Action::PageDown) => app.terminal_mut().scroll_down(10),
         // Go to top of scrollback
-        KeyCode::Char('g') => {
+        Some(Action::Home) => {
             let max_offset = app.terminal().scrollback().len();
             app.terminal_mut().set_scroll_offset(max_offset);
         }
         // Go to bottom
-        KeyCode::Char('G') => {
-            app.terminal_mut().scroll_to_bottom();
-        }
+        Some(Action::End) => app.terminal_mut().scroll_to_bottom(),
         _ => {}
     }
     Ok(false)
@@ -0,0 +1,354 @@
+//! Ranking entries against the filter, on a worker thread for long lists
+//!
+//! Scoring thousands of entries (run mode with a big `$PATH`) takes longer
+//! than a frame. With a [`MatchWorker`] running, a keystroke only sends the
+//! query: the worker scores the candidates in chunks, sends the ranking so
+//! far about once a frame and drops the query as soon as a newer one
+//! arrives. The launcher shows the latest ranking it got meanwhile.
+
+use std::collections::HashMap;
+use std::ops::Range;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use nucleo_matcher::pattern::{CaseMatching, Normalization, Pattern};
+use nucleo_matcher::Matcher;
+
+use crate::desktop_entry::Entry;
+use crate::executables;
+use crate::search::{self, Filter};
+
+/// Entries scored between checks for a newer query
+const CHUNK: usize = 512;
+
+/// How often the worker sends the ranking so far
+const PARTIAL_INTERVAL: Duration = Duration::from_millis(16);
+
+/// Frecency scores by entry id, a snapshot of the history
+pub type Frecency = Arc<HashMap<String, f64>>;
+
+/// The entries seen so far that pass the filter, scored against its fuzzy
+/// text and combined with frecency
+pub struct Ranker {
+    text: String,
+    pattern: Pattern,
+    filter: Filter,
+    /// List programs on `$PATH` rather than applications
+    run: bool,
+    /// Rank entries with equal scores in list order rather than by name
+    /// (compositor lists, dmenu lines)
+    keep_order: bool,
+    frecency: Frecency,
+    frecency_weight: f64,
+    /// Matching entries (index into the entries) with their score, in
+    /// list order
+    scored: Vec<(usize, f64)>,
+    /// Matched character positions in the names of the scored entries
+    match_indices: HashMap<usize, Vec<u32>>,
+}
+
+impl Ranker {
+    pub fn new(filter: &str, run: bool, keep_order: bool, frecency: Frecency, frecency_weight: f64) -> Self {
+        let query = search::parse_query(filter);
+        let text = query.pattern_text();
+        Self {
+            pattern: Pattern::parse(&text, CaseMatching::Ignore, Normalization::Smart),
+            filter: Filter::new(&query),
+            text,
+            run,
+            keep_order,
+            frecency,
+            frecency_weight,
+            scored: Vec::new(),
+            match_indices: HashMap::new(),
+        }
+    }
+
+    /// Score `entries[range]`
+    pub fn score(&mut self, entries: &[Entry], range: Range<usize>, matcher: &mut Matcher) {
+        for i in range {
+            let entry = &entries[i];
+            // Typed commands only show up as search results
+            if self.text.is_empty() && entry.is_typed_command() {
+                continue;
+            }
+            if executables::is_executable(entry) != self.run || !self.filter.matches(entry, matcher) {
+                continue;
+            }
+            let frecency = self.frecency.get(&entry.id).copied().unwrap_or(0.0);
+            if self.text.is_empty() {
+                self.scored.push((i, frecency));
+                continue;
+            }
+            let Some(fuzzy_score) = search::score_entry(entry, &self.text, &self.pattern, matcher) else {
+                continue;
+            };
+            // Weighted combination: fuzzy_score normalized + frecency weight
+            // Fuzzy scores are typically 0-1000+, frecency is 0-~500
+            let combined = fuzzy_score * (1.0 - self.frecency_weight)
+                + frecency * self.frecency_weight * 10.0; // Scale frecency
+            self.scored.push((i, combined));
+
+            // Highlight positions against the displayed name (entries that
+            // only matched in other fields get none)
+            if let Some(indices) = search::name_match_indices(&entry.name, &self.pattern, matcher) {
+                self.match_indices.insert(i, indices);
+            }
+        }
+    }
+
+    /// The scored entries, best first
+    ///
+    /// Without fuzzy text that's by frecency, then alphabetically; ties
+    /// otherwise stay in list order.
+    pub fn ranked(&self, entries: &[Entry]) -> Vec<usize> {
+        let by_name = self.text.is_empty() && !self.keep_order;
+        let mut scored = self.scored.clone();
+        scored.sort_by(|a, b| {
+            b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal).then_with(|| {
+                if by_name {
+                    entries[a.0].name.cmp(&entries[b.0].name)
+                } else {
+                    std::cmp::Ordering::Equal
+                }
+            })
+        });
+        scored.into_iter().map(|(i, _)| i).collect()
+    }
+
+    /// Highlight positions of the scored entries
+    pub fn match_indices(&self) -> &HashMap<usize, Vec<u32>> {
+        &self.match_indices
+    }
+
+    /// The ranking and its highlights
+    pub fn finish(self, entries: &[Entry]) -> (Vec<usize>, HashMap<usize, Vec<u32>>) {
+        (self.ranked(entries), self.match_indices)
+    }
+}
+
+/// Entries to rank against a query
+pub struct MatchJob {
+    /// Tells the rankings of successive queries apart
+    pub generation: u64,
+    pub entries: Arc<Vec<Entry>>,
+    pub ranker: Ranker,
+}
+
+/// Entries matching a query, best first
+pub struct Ranking {
+    pub generation: u64,
+    pub ranked: Vec<usize>,
+    pub match_indices: HashMap<usize, Vec<u32>>,
+    /// All candidates are scored (otherwise more rankings follow)
+    pub complete: bool,
+}
+
+/// Handle to the thread ranking entries; it stops when this is dropped
+pub struct MatchWorker {
+    jobs: Sender<MatchJob>,
+    rankings: Receiver<Ranking>,
+}
+
+impl MatchWorker {
+    pub fn spawn() -> Result<Self> {
+        let (jobs, job_rx) = mpsc::channel();
+        let (ranking_tx, rankings) = mpsc::channel();
+        thread::Builder::new()
+            .name("matcher".to_string())
+            .spawn(move || run_worker(job_rx, ranking_tx))?;
+        Ok(Self { jobs, rankings })
+    }
+
+    /// Rank `job`, replacing the query being ranked
+    pub fn send(&self, job: MatchJob) {
+        if self.jobs.send(job).is_err() {
+            tracing::warn!("Matcher thread is gone");
+        }
+    }
+
+    /// A ranking sent since the last call, oldest first
+    pub fn try_recv(&self) -> Option<Ranking> {
+        self.rankings.try_recv().ok()
+    }
+}
+
+fn run_worker(jobs: Receiver<MatchJob>, rankings: Sender<Ranking>) {
+    let mut matcher = Matcher::new(nucleo_matcher::Config::DEFAULT);
+    let mut next = jobs.recv().ok();
+    while let Some(job) = next.take() {
+        match rank(job, &jobs, &rankings, &mut matcher) {
+            Ok(Some(newer)) => next = Some(newer),
+            Ok(None) => next = jobs.recv().ok(),
+            // The app is gone
+            Err(_) => break,
+        }
+    }
+}
+
+/// Rank `job`, sending the ranking so far once a frame
+///
+/// Returns the newest of the jobs that arrived meanwhile, which make this
+/// one moot.
+fn rank(
+    job: MatchJob,
+    jobs: &Receiver<MatchJob>,
+    rankings: &Sender<Ranking>,
+    matcher: &mut Matcher,
+) -> Result<Option<MatchJob>, mpsc::SendError<Ranking>> {
+    let MatchJob {
+        generation,
+        entries,
+        mut ranker,
+    } = job;
+    let mut sent = Instant::now();
+    for start in (0..entries.len()).step_by(CHUNK) {
+        if let Some(newer) = jobs.try_iter().last() {
+            return Ok(Some(newer));
+        }
+        ranker.score(&entries, start..(start + CHUNK).min(entries.len()), matcher);
+        if sent.elapsed() >= PARTIAL_INTERVAL {
+            rankings.send(Ranking {
+                generation,
+                ranked: ranker.ranked(&entries),
+                match_indices: ranker.match_indices().clone(),
+                complete: false,
+            })?;
+            sent = Instant::now();
+        }
+    }
+    let (ranked, match_indices) = ranker.finish(&entries);
+    rankings.send(Ranking {
+        generation,
+        ranked,
+        match_indices,
+        complete: true,
+    })?;
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::App;
+    use crate::config::Config;
+
+    /// `count` entries named after their number, like programs on `$PATH`
+    fn corpus(count: usize) -> Vec<Entry> {
+        (0..count)
+            .map(|n| Entry {
+                id: format!("prog{}", n),
+                name: format!("prog-{}-{}", ["fire", "term", "edit", "view"][n % 4], n),
+                keywords: vec![format!("tool{}", n % 7)],
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    fn ranker(filter: &str) -> Ranker {
+        let frecency = HashMap::from([("prog12".to_string(), 4.0)]);
+        Ranker::new(filter, false, false, Arc::new(frecency), 0.3)
+    }
+
+    fn job(generation: u64, entries: &Arc<Vec<Entry>>, filter: &str) -> MatchJob {
+        MatchJob {
+            generation,
+            entries: Arc::clone(entries),
+            ranker: ranker(filter),
+        }
+    }
+
+    /// Rankings up to the complete one
+    fn collect(worker: &MatchWorker) -> Vec<Ranking> {
+        let mut rankings = Vec::new();
+        let start = Instant::now();
+        while !rankings.last().is_some_and(|r: &Ranking| r.complete) {
+            assert!(start.elapsed() < Duration::from_secs(10), "no complete ranking");
+            match worker.try_recv() {
+                Some(ranking) => rankings.push(ranking),
+                None => thread::sleep(Duration::from_millis(1)),
+            }
+        }
+        rankings
+    }
+
+    #[test]
+    fn test_worker_ranks_like_the_ranker() {
+        let entries = Arc::new(corpus(3000));
+        let worker = MatchWorker::spawn().unwrap();
+        let mut matcher = Matcher::new(nucleo_matcher::Config::DEFAULT);
+        // Fuzzy text, keyword and excluded terms, and none at all
+        for (generation, filter) in ["fire 12", "fire 12 @tool5 !prog-fire-12", ""].into_iter().enumerate() {
+            let mut ranker = ranker(filter);
+            ranker.score(&entries, 0..entries.len(), &mut matcher);
+            let (expected, expected_indices) = ranker.finish(&entries);
+            assert!(!expected.is_empty());
+
+            worker.send(job(generation as u64, &entries, filter));
+            let ranking = collect(&worker).pop().unwrap();
+            assert_eq!(ranking.generation, generation as u64);
+            assert_eq!(ranking.ranked, expected, "{}", filter);
+            assert_eq!(ranking.match_indices, expected_indices);
+        }
+    }
+
+    #[test]
+    fn test_worker_skips_superseded_queries() {
+        let entries = Arc::new(corpus(20_000));
+        let worker = MatchWorker::spawn().unwrap();
+        for (generation, text) in ["f", "fi", "fir", "fire"].into_iter().enumerate() {
+            worker.send(job(generation as u64, &entries, text));
+        }
+        let rankings = collect(&worker);
+        // The last query is always ranked completely, earlier ones at most
+        // partially
+        assert_eq!(rankings.last().unwrap().generation, 3);
+        assert_eq!(rankings.iter().filter(|r| r.complete).count(), 1);
+        assert_eq!(rankings.last().unwrap().ranked.len(), 5000);
+    }
+
+    /// Typing a query into 10k entries, ranked on the spot and by the
+    /// worker: the longest keystroke and the time until the ranking is
+    /// complete
+    ///
+    /// Run with `cargo test --release -- --ignored --nocapture bench_`.
+    #[test]
+    #[ignore]
+    fn bench_keystroke_latency() {
+        let config: Config = toml::from_str("[history]\nenabled = false").unwrap();
+        let query = "fire 42";
+        let ids = |app: &App| app.visible_entries().iter().map(|e| e.id.clone()).collect::<Vec<_>>();
+        let mut expected = App::new(corpus(10_000), config.clone(), false);
+        for c in query.chars() {
+            expected.push_filter_char(c);
+        }
+
+        for background in [false, true] {
+            let mut app = App::new(corpus(10_000), config.clone(), false);
+            if background {
+                app.start_match_worker();
+            }
+            let start = Instant::now();
+            let mut longest = Duration::ZERO;
+            for c in query.chars() {
+                let key = Instant::now();
+                app.push_filter_char(c);
+                app.poll().unwrap();
+                longest = longest.max(key.elapsed());
+            }
+            while ids(&app) != ids(&expected) {
+                thread::sleep(Duration::from_millis(1));
+                app.poll().unwrap();
+            }
+            println!(
+                "{}: longest keystroke {:?}, ranked after {:?}",
+                if background { "worker" } else { "on the spot" },
+                longest,
+                start.elapsed()
+            );
+        }
+    }
+}
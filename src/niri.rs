@@ -9,23 +9,38 @@
 //!
 //! # Connection Lifecycle
 //!
-//! 1. On startup, `NiriClient::try_new()` attempts to find the socket
-//! 2. If found, the client is stored in `App.niri`
+//! 1. On startup, `compositor::connect()` attempts to find the socket
+//! 2. If found, the client is stored in `App.compositor`
 //! 3. Each IPC call opens a new connection (niri doesn't support persistent connections)
 //! 4. If the socket disappears (niri crash), calls will fail gracefully
 //!
+//! The event stream is the exception: `event_stream()` keeps its
+//! connection open and niri pushes window and workspace events over it.
+//! `spawn_event_listener()` forwards them to a channel that the main loop
+//! drains through `App::poll_compositor_events`.
+//!
 //! # Graceful Degradation
 //!
 //! All niri features are optional. When unavailable:
-//! - Over SSH: Socket doesn't exist, `try_new()` returns None
+//! - Over SSH: Socket doesn't exist, `new()` fails
 //! - Niri crash: `is_available()` returns false, calls return errors
+//! - Niri hung: requests time out after `compositor.request_timeout_ms`; the
+//!   client is then marked stalled and calls fail right away until the
+//!   socket is replaced (niri restarted)
 //! - Non-niri session: Same as SSH case
 
 use anyhow::{Context, Result};
-use serde::Deserialize;
-use std::path::PathBuf;
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader, Lines};
 use tokio::net::UnixStream;
+use tokio::sync::mpsc;
+
+use crate::compositor::{
+    self, BoxFuture, Compositor, Event, EventSource, IpcSocket, WindowInfo, WorkspaceInfo,
+    WorkspaceReference,
+};
 
 /// Niri IPC response format
 #[derive(Debug, Deserialize)]
@@ -33,9 +48,13 @@ use tokio::net::UnixStream;
 pub enum NiriResponse {
     Ok {
         #[allow(dead_code)] // Used for parsing, value accessed via pattern matching
+        #[serde(alias = "Ok")]
         ok: serde_json::Value,
     },
-    Err { err: String },
+    Err {
+        #[serde(alias = "Err")]
+        err: String,
+    },
 }
 
 impl NiriResponse {
@@ -74,32 +93,20 @@ impl NiriResponse {
 /// TEAM_000: Phase 3, Unit 3.1 - IPC Protocol
 #[derive(Clone)]
 pub struct NiriClient {
-    socket_path: PathBuf,
+    socket: IpcSocket,
 }
 
 impl NiriClient {
-    /// Create a new niri client, auto-detecting socket path.
+    /// Create a new niri client, using `socket_path` from config if it
+    /// exists and auto-detecting the socket otherwise.
     /// 
     /// Returns None if niri socket is not found (e.g., over SSH or non-niri session).
     /// This is expected behavior - DRUN works fine without niri.
-    pub fn new() -> Result<Self> {
-        let socket_path = Self::find_socket()?;
-        tracing::info!("Using niri socket: {}", socket_path.display());
-        Ok(Self { socket_path })
-    }
-
-    /// Try to create a niri client, returning None if unavailable.
-    /// 
-    /// This is the preferred way to create a client when niri is optional.
-    /// Common case: running over SSH where niri socket doesn't exist.
-    pub fn try_new() -> Option<Self> {
-        match Self::new() {
-            Ok(client) => Some(client),
-            Err(e) => {
-                tracing::debug!("Niri IPC not available: {}", e);
-                None
-            }
-        }
+    pub fn new(socket_path: Option<&Path>, timeout: Duration) -> Result<Self> {
+        let socket_path = Self::find_socket(socket_path)?;
+        Ok(Self {
+            socket: IpcSocket::new("niri", socket_path, timeout),
+        })
     }
 
     /// Check if niri IPC is currently available.
@@ -107,7 +114,8 @@ impl NiriClient {
     /// # Behavior
     ///
     /// Returns `true` if the socket file exists. This is a quick filesystem check,
-    /// not a full connection test.
+    /// not a full connection test. After a request timed out it is `false`
+    /// until the socket is replaced by a new one (niri restarted).
     ///
     /// # Use Cases
     ///
@@ -126,14 +134,21 @@ impl NiriClient {
     /// // In status bar rendering
     /// let indicator = if client.is_available() { "◉" } else { "◎" };
     /// ```
-    #[allow(dead_code)] // Phase 9: Will be used for health indicator
     pub fn is_available(&self) -> bool {
-        self.socket_path.exists()
+        self.socket.is_available()
     }
 
     /// Find the niri socket path
-    fn find_socket() -> Result<PathBuf> {
-        // Check NIRI_SOCKET env var first
+    fn find_socket(configured: Option<&Path>) -> Result<PathBuf> {
+        // niri.socket_path from config first
+        if let Some(path) = configured {
+            if path.exists() {
+                return Ok(path.to_path_buf());
+            }
+            tracing::warn!("niri.socket_path doesn't exist: {}", path.display());
+        }
+
+        // Check NIRI_SOCKET env var next
         if let Ok(path) = std::env::var("NIRI_SOCKET") {
             let path = PathBuf::from(path);
             if path.exists() {
@@ -156,8 +171,20 @@ impl NiriClient {
     }
 
     /// Send a request to niri and get parsed response
+    ///
+    /// Fails right away while the client is stalled, and after the
+    /// timeout if niri doesn't answer.
     async fn request(&self, msg: &str) -> Result<NiriResponse> {
-        let mut stream = UnixStream::connect(&self.socket_path)
+        self.socket.request(self.send(msg)).await
+    }
+
+    /// Why the last request failed, if it did
+    pub fn last_error(&self) -> Option<String> {
+        self.socket.last_error()
+    }
+
+    async fn send(&self, msg: &str) -> Result<NiriResponse> {
+        let mut stream = UnixStream::connect(self.socket.path())
             .await
             .context("Failed to connect to niri socket")?;
 
@@ -238,7 +265,6 @@ impl NiriClient {
     ///
     /// Each call opens a new socket connection. For frequent polling,
     /// consider caching with a refresh interval (e.g., 1 second).
-    #[allow(dead_code)] // Phase 9: Will be used for smart float/unfloat
     pub async fn focused_window(&self) -> Result<Option<WindowInfo>> {
         let msg = r#"{"Request":"FocusedWindow"}"#;
         let response = self.request(msg).await?;
@@ -287,7 +313,6 @@ impl NiriClient {
     ///
     /// Use `toggle_floating()` for user-triggered actions.
     /// Use `set_floating()` for programmatic state management.
-    #[allow(dead_code)] // Phase 9: Will be used for Ctrl+F keybind
     pub async fn toggle_floating(&self) -> Result<()> {
         let msg = r#"{"Action":{"ToggleWindowFloating":{"id":null}}}"#;
         let response = self.request(msg).await?;
@@ -298,59 +323,231 @@ impl NiriClient {
 
         Ok(())
     }
+
+    /// All open windows
+    pub async fn windows(&self) -> Result<Vec<WindowInfo>> {
+        self.list(r#"{"Request":"Windows"}"#, "Windows").await
+    }
+
+    /// All workspaces, on every output
+    pub async fn workspaces(&self) -> Result<Vec<WorkspaceInfo>> {
+        self.list(r#"{"Request":"Workspaces"}"#, "Workspaces").await
+    }
+
+    /// Send a request answered with `{"Ok":{"<kind>":[...]}}`
+    async fn list<T: serde::de::DeserializeOwned>(&self, msg: &str, kind: &str) -> Result<Vec<T>> {
+        match self.request(msg).await? {
+            NiriResponse::Ok { mut ok } => {
+                let list = ok.get_mut(kind).map(serde_json::Value::take).unwrap_or(ok);
+                serde_json::from_value(list).with_context(|| format!("Failed to parse {}", kind))
+            }
+            NiriResponse::Err { err } => anyhow::bail!("niri error: {}", err),
+        }
+    }
+
+    /// Focus the window with `id`, switching workspace if needed
+    pub async fn focus_window(&self, id: u64) -> Result<()> {
+        self.action(Action::FocusWindow { id }).await
+    }
+
+    /// Switch to a workspace
+    pub async fn focus_workspace(&self, reference: WorkspaceReference) -> Result<()> {
+        self.action(Action::FocusWorkspace { reference }).await
+    }
+
+    /// Move a window (None: the focused one) to a workspace, following it
+    /// there if `focus` is set
+    pub async fn move_window_to_workspace(
+        &self,
+        window_id: Option<u64>,
+        reference: WorkspaceReference,
+        focus: bool,
+    ) -> Result<()> {
+        self.action(Action::MoveWindowToWorkspace {
+            window_id,
+            reference,
+            focus,
+        })
+        .await
+    }
+
+    /// Start a program as niri's child, with niri's environment
+    pub async fn spawn(&self, command: Vec<String>) -> Result<()> {
+        self.action(Action::Spawn { command }).await
+    }
+
+    /// Run an action, failing if niri refuses it
+    async fn action(&self, action: Action) -> Result<()> {
+        let msg = serde_json::to_string(&Request::Action(action))?;
+        let response = self.request(&msg).await?;
+
+        if let Some(err) = response.error() {
+            anyhow::bail!("niri error: {}", err);
+        }
+
+        Ok(())
+    }
+
+    /// Subscribe to window and workspace events
+    ///
+    /// niri answers the request once, then writes one event per line for
+    /// as long as the connection stays open.
+    pub async fn event_stream(&self) -> Result<EventStream> {
+        if !self.is_available() {
+            anyhow::bail!("niri is not responding");
+        }
+        self.socket.with_timeout(self.subscribe()).await
+    }
+
+    async fn subscribe(&self) -> Result<EventStream> {
+        let mut stream = UnixStream::connect(self.socket.path())
+            .await
+            .context("Failed to connect to niri socket")?;
+        stream
+            .write_all(br#"{"Request":"EventStream"}"#)
+            .await
+            .context("Failed to write to niri socket")?;
+        stream.write_all(b"\n").await?;
+
+        let mut lines = BufReader::new(stream).lines();
+        let reply = lines
+            .next_line()
+            .await
+            .context("Failed to read from niri socket")?
+            .context("niri closed the event stream")?;
+        if let Ok(NiriResponse::Err { err }) = serde_json::from_str(&reply) {
+            anyhow::bail!("niri error: {}", err);
+        }
+        Ok(EventStream { lines })
+    }
+}
+
+impl Compositor for NiriClient {
+    fn name(&self) -> &'static str {
+        "niri"
+    }
+
+    fn socket_path(&self) -> &Path {
+        self.socket.path()
+    }
+
+    fn is_available(&self) -> bool {
+        NiriClient::is_available(self)
+    }
+
+    fn last_error(&self) -> Option<String> {
+        NiriClient::last_error(self)
+    }
+
+    fn set_floating(&self, floating: bool) -> BoxFuture<'_, ()> {
+        Box::pin(NiriClient::set_floating(self, floating))
+    }
+
+    fn toggle_floating(&self) -> BoxFuture<'_, ()> {
+        Box::pin(NiriClient::toggle_floating(self))
+    }
+
+    fn focused_window(&self) -> BoxFuture<'_, Option<WindowInfo>> {
+        Box::pin(NiriClient::focused_window(self))
+    }
+
+    fn windows(&self) -> BoxFuture<'_, Vec<WindowInfo>> {
+        Box::pin(NiriClient::windows(self))
+    }
+
+    fn workspaces(&self) -> BoxFuture<'_, Vec<WorkspaceInfo>> {
+        Box::pin(NiriClient::workspaces(self))
+    }
+
+    fn focus_window(&self, id: u64) -> BoxFuture<'_, ()> {
+        Box::pin(NiriClient::focus_window(self, id))
+    }
+
+    fn focus_workspace(&self, reference: WorkspaceReference) -> BoxFuture<'_, ()> {
+        Box::pin(NiriClient::focus_workspace(self, reference))
+    }
+
+    fn move_window_to_workspace(
+        &self,
+        window_id: Option<u64>,
+        reference: WorkspaceReference,
+        focus: bool,
+    ) -> BoxFuture<'_, ()> {
+        Box::pin(NiriClient::move_window_to_workspace(self, window_id, reference, focus))
+    }
+
+    fn spawn(&self, command: Vec<String>) -> BoxFuture<'_, ()> {
+        Box::pin(NiriClient::spawn(self, command))
+    }
+
+    fn spawn_event_listener(&self) -> Option<mpsc::UnboundedReceiver<Event>> {
+        let client = self.clone();
+        compositor::spawn_event_listener("niri", async move { client.event_stream().await })
+    }
+
+    fn boxed_clone(&self) -> Box<dyn Compositor> {
+        Box::new(self.clone())
+    }
+}
+
+/// Request to niri, for the typed ones
+#[derive(Debug, Serialize)]
+enum Request {
+    Action(Action),
 }
 
-/// Information about a niri window.
-///
-/// # Fields
-///
-/// - `id`: Unique window identifier (stable for window lifetime)
-/// - `app_id`: Wayland app_id (e.g., "firefox", "org.gnome.Nautilus")
-/// - `title`: Current window title (may change dynamically)
-/// - `is_floating`: Whether window is in floating state
-///
-/// # Use Cases
-///
-/// ```ignore
-/// let info = client.focused_window().await?.unwrap();
-///
-/// // Check if this is our own window
-/// if info.app_id == "darkwall-drun" {
-///     // We're focused, good
-/// }
-///
-/// // Remember float state for later restoration
-/// let was_floating = info.is_floating;
-///
-/// // Log for debugging
-/// tracing::debug!("Focused: {} ({})", info.title, info.app_id);
-/// ```
-#[derive(Debug, Clone, Deserialize)]
-#[allow(dead_code)] // Phase 9: Will be used with focused_window()
-pub struct WindowInfo {
-    /// Unique window identifier assigned by niri.
-    /// Stable for the lifetime of the window.
-    pub id: u64,
-    
-    /// Wayland app_id (similar to X11 WM_CLASS).
-    /// Set by the application, e.g., "firefox", "kitty".
-    #[serde(default)]
-    pub app_id: String,
-    
-    /// Current window title.
-    /// May change dynamically (e.g., browser tab changes).
-    #[serde(default)]
-    pub title: String,
-    
-    /// Whether the window is currently floating.
-    /// `false` means tiled in the layout.
-    #[serde(default)]
-    pub is_floating: bool,
+/// niri actions drun sends as typed values
+#[derive(Debug, Serialize)]
+enum Action {
+    Spawn {
+        command: Vec<String>,
+    },
+    FocusWindow {
+        id: u64,
+    },
+    FocusWorkspace {
+        reference: WorkspaceReference,
+    },
+    MoveWindowToWorkspace {
+        window_id: Option<u64>,
+        reference: WorkspaceReference,
+        focus: bool,
+    },
+}
+
+/// Open event stream connection
+pub struct EventStream {
+    lines: Lines<BufReader<UnixStream>>,
+}
+
+impl EventSource for EventStream {
+    fn next_event(&mut self) -> BoxFuture<'_, Option<Event>> {
+        Box::pin(async move {
+            while let Some(line) = self.lines.next_line().await? {
+                if let Some(event) = parse_event(&line) {
+                    return Ok(Some(event));
+                }
+            }
+            Ok(None)
+        })
+    }
+}
+
+/// Parse one event line, None for events drun doesn't handle
+fn parse_event(line: &str) -> Option<Event> {
+    match serde_json::from_str(line) {
+        Ok(event) => Some(event),
+        Err(_) => {
+            tracing::trace!("Skipping niri event: {}", line);
+            None
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_util::TempDir;
 
     #[test]
     fn test_parse_ok_response() {
@@ -368,6 +565,118 @@ mod tests {
         assert_eq!(resp.error(), Some("window not found"));
     }
 
+    #[test]
+    fn test_parse_events() {
+        let event = parse_event(
+            r#"{"WindowOpenedOrChanged":{"window":{"id":7,"title":"Mozilla Firefox","app_id":"firefox","workspace_id":1,"is_focused":true,"is_floating":false}}}"#,
+        );
+        match event {
+            Some(Event::WindowOpenedOrChanged { window }) => {
+                assert_eq!(window.id, 7);
+                assert_eq!(window.app_id, "firefox");
+            }
+            other => panic!("unexpected event: {:?}", other),
+        }
+
+        assert!(matches!(
+            parse_event(r#"{"WindowFocusChanged":{"id":null}}"#),
+            Some(Event::WindowFocusChanged { id: None })
+        ));
+        assert!(matches!(
+            parse_event(r#"{"WorkspaceActivated":{"id":2,"focused":true}}"#),
+            Some(Event::WorkspaceActivated { id: 2, focused: true })
+        ));
+        // Events drun doesn't know are skipped
+        assert!(parse_event(r#"{"KeyboardLayoutSwitched":{"idx":1}}"#).is_none());
+    }
+
+    #[test]
+    fn test_parse_niri_reply() {
+        // niri wraps replies in Ok / Err
+        let json = r#"{"Ok":{"Windows":[{"id":3,"title":"~","app_id":"foot","pid":412,"workspace_id":1,"is_focused":true,"is_floating":false}]}}"#;
+        let NiriResponse::Ok { mut ok } = serde_json::from_str(json).unwrap() else {
+            panic!("not an Ok reply");
+        };
+        let windows: Vec<WindowInfo> = serde_json::from_value(ok["Windows"].take()).unwrap();
+        assert_eq!(windows[0].pid, Some(412));
+        assert_eq!(windows[0].workspace_id, Some(1));
+        assert!(windows[0].is_focused);
+
+        let resp: NiriResponse = serde_json::from_str(r#"{"Err":"no such window"}"#).unwrap();
+        assert_eq!(resp.error(), Some("no such window"));
+    }
+
+    #[test]
+    fn test_parse_workspaces() {
+        // Captured from `niri msg --json workspaces`
+        let json = r#"{"Ok":{"Workspaces":[{"id":1,"idx":1,"name":null,"output":"eDP-1","is_urgent":false,"is_active":true,"is_focused":true,"active_window_id":4},{"id":5,"idx":2,"name":"chat","output":"eDP-1","is_urgent":false,"is_active":false,"is_focused":false,"active_window_id":null}]}}"#;
+        let NiriResponse::Ok { mut ok } = serde_json::from_str(json).unwrap() else {
+            panic!("not an Ok reply");
+        };
+        let workspaces: Vec<WorkspaceInfo> = serde_json::from_value(ok["Workspaces"].take()).unwrap();
+        assert_eq!(workspaces.len(), 2);
+        assert!(workspaces[0].is_focused && workspaces[0].is_active);
+        assert_eq!(workspaces[0].name, None);
+        assert_eq!(workspaces[1].name.as_deref(), Some("chat"));
+        assert_eq!(workspaces[1].output.as_deref(), Some("eDP-1"));
+        assert_eq!((workspaces[1].id, workspaces[1].idx), (5, 2));
+    }
+
+    #[test]
+    fn test_serialize_actions() {
+        let json = |action| serde_json::to_string(&Request::Action(action)).unwrap();
+        assert_eq!(json(Action::FocusWindow { id: 7 }), r#"{"Action":{"FocusWindow":{"id":7}}}"#);
+        assert_eq!(
+            json(Action::Spawn {
+                command: vec!["/opt/My App/app".to_string(), "--new-window".to_string()]
+            }),
+            r#"{"Action":{"Spawn":{"command":["/opt/My App/app","--new-window"]}}}"#
+        );
+        assert_eq!(
+            json(Action::FocusWorkspace {
+                reference: WorkspaceReference::Id(5)
+            }),
+            r#"{"Action":{"FocusWorkspace":{"reference":{"Id":5}}}}"#
+        );
+        assert_eq!(
+            json(Action::MoveWindowToWorkspace {
+                window_id: None,
+                reference: WorkspaceReference::Name("chat".to_string()),
+                focus: true,
+            }),
+            r#"{"Action":{"MoveWindowToWorkspace":{"window_id":null,"reference":{"Name":"chat"},"focus":true}}}"#
+        );
+        assert_eq!(
+            serde_json::to_string(&WorkspaceReference::Index(2)).unwrap(),
+            r#"{"Index":2}"#
+        );
+    }
+
+    #[tokio::test]
+    async fn test_request_timeout_stalls_client() {
+        let dir = TempDir::new("niri");
+        let path = dir.join("niri.sock");
+        // Accepts connections but never answers
+        let _listener = tokio::net::UnixListener::bind(&path).unwrap();
+
+        let client = NiriClient::new(Some(&path), Duration::from_millis(50)).unwrap();
+        assert!(client.is_available());
+        let err = client.focused_window().await.unwrap_err();
+        assert_eq!(err.to_string(), "niri request timed out");
+        assert_eq!(client.last_error().as_deref(), Some("niri request timed out"));
+        assert!(!client.is_available());
+        // Clones share the state; calls fail without waiting
+        let started = std::time::Instant::now();
+        assert!(client.clone().set_floating(true).await.is_err());
+        assert!(started.elapsed() < Duration::from_millis(50));
+
+        // A new socket (niri restarted) is used again
+        drop(_listener);
+        std::fs::remove_file(&path).unwrap();
+        let _listener = tokio::net::UnixListener::bind(&path).unwrap();
+        assert!(client.is_available());
+    }
+
     #[test]
     fn test_parse_window_info() {
         let json = r#"{"id": 123, "app_id": "darkwall-drun", "title": "Test"}"#;
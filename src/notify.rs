@@ -0,0 +1,122 @@
+//! Desktop notifications for finished long-running commands.
+//!
+//! Notifications go through `notify-send`, which talks to the
+//! org.freedesktop.Notifications D-Bus service. Everything is best-effort:
+//! a missing `notify-send` or notification daemon (common over SSH) is only
+//! logged at debug level.
+
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+use crate::executor::CommandStatus;
+use crate::util::format_duration;
+
+/// Something that can show a desktop notification
+pub trait Notifier {
+    fn notify(&self, summary: &str, body: &str);
+}
+
+/// Notifier using the `notify-send` command
+pub struct NotifySend;
+
+impl Notifier for NotifySend {
+    fn notify(&self, summary: &str, body: &str) {
+        let child = Command::new("notify-send")
+            .args(["--app-name", "darkwall-drun", summary, body])
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn();
+        match child {
+            // Reap in the background so a slow daemon never blocks the UI
+            Ok(mut child) => {
+                std::thread::spawn(move || child.wait());
+            }
+            Err(e) => tracing::debug!("Cannot send notification: {}", e),
+        }
+    }
+}
+
+/// Notifier recording the summary and body of each notification
+#[cfg(test)]
+#[derive(Clone, Default)]
+pub struct Recorder(std::rc::Rc<std::cell::RefCell<Vec<(String, String)>>>);
+
+#[cfg(test)]
+impl Recorder {
+    /// Summary and body of the notifications so far
+    pub fn notifications(&self) -> Vec<(String, String)> {
+        self.0.borrow().clone()
+    }
+}
+
+#[cfg(test)]
+impl Notifier for Recorder {
+    fn notify(&self, summary: &str, body: &str) {
+        self.0.borrow_mut().push((summary.to_string(), body.to_string()));
+    }
+}
+
+/// Notify that `command` finished if it ran for at least `threshold`
+///
+/// A zero threshold disables notifications.
+pub fn command_finished(
+    notifier: &dyn Notifier,
+    threshold: Duration,
+    command: &str,
+    status: &CommandStatus,
+    duration: Duration,
+) {
+    if threshold.is_zero() || duration < threshold {
+        return;
+    }
+
+    let outcome = match status {
+        CommandStatus::Exited(0) => "finished".to_string(),
+        CommandStatus::Exited(code) => format!("failed (exit {})", code),
+        CommandStatus::Signaled(signal) => format!("killed (signal {})", signal),
+        CommandStatus::Running | CommandStatus::Unknown => "ended".to_string(),
+    };
+    let summary = format!("Command {}", outcome);
+    let body = format!("{}\nRan for {}", command, format_duration(duration));
+    notifier.notify(&summary, &body);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_notifies_only_long_commands() {
+        let recorder = Recorder::default();
+        let threshold = Duration::from_secs(30);
+
+        command_finished(&recorder, threshold, "ls", &CommandStatus::Exited(0), Duration::from_secs(2));
+        assert!(recorder.notifications().is_empty());
+
+        command_finished(
+            &recorder,
+            threshold,
+            "cargo build",
+            &CommandStatus::Exited(101),
+            Duration::from_secs(602),
+        );
+        assert_eq!(
+            recorder.notifications(),
+            [(
+                "Command failed (exit 101)".to_string(),
+                "cargo build\nRan for 10:02".to_string()
+            )]
+        );
+
+        // A zero threshold disables notifications
+        command_finished(
+            &recorder,
+            Duration::ZERO,
+            "sleep 600",
+            &CommandStatus::Exited(0),
+            Duration::from_secs(600),
+        );
+        assert_eq!(recorder.notifications().len(), 1);
+    }
+}
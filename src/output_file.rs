@@ -0,0 +1,159 @@
+//! Save command output to files.
+//!
+//! Output is written as plain text (no escape sequences) to
+//! `~/.local/share/darkwall-drun/output/` by default, one file per save,
+//! named after the local time and the command:
+//! `2026-10-15_14-03-22_cargo.log`.
+
+use anyhow::{Context, Result};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Longest command part of a file name
+const MAX_SLUG_LEN: usize = 32;
+
+/// Default output directory
+pub fn default_dir() -> Option<PathBuf> {
+    dirs::data_dir().map(|d| d.join("darkwall-drun").join("output"))
+}
+
+/// Format broken-down time as `YYYY-MM-DD_HH-MM-SS`
+fn format_timestamp(tm: &libc::tm) -> String {
+    format!(
+        "{:04}-{:02}-{:02}_{:02}-{:02}-{:02}",
+        tm.tm_year + 1900,
+        tm.tm_mon + 1,
+        tm.tm_mday,
+        tm.tm_hour,
+        tm.tm_min,
+        tm.tm_sec
+    )
+}
+
+/// Local time for `time`
+fn local_time(time: SystemTime) -> libc::tm {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as libc::time_t)
+        .unwrap_or(0);
+    // SAFETY: tm is plain data; localtime_r only writes to the provided struct
+    unsafe {
+        let mut tm: libc::tm = std::mem::zeroed();
+        libc::localtime_r(&secs, &mut tm);
+        tm
+    }
+}
+
+/// File name part describing `command`: its program name, reduced to
+/// characters that are safe in file names
+fn command_slug(command: &str) -> String {
+    let program = command
+        .split_whitespace()
+        .next()
+        .unwrap_or("")
+        .rsplit('/')
+        .next()
+        .unwrap_or("");
+    let slug: String = program
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .take(MAX_SLUG_LEN)
+        .collect();
+    let slug = slug.trim_matches('.');
+    if slug.is_empty() {
+        "output".to_string()
+    } else {
+        slug.to_string()
+    }
+}
+
+/// File name for the output of `command` saved at local time `tm`
+fn file_name(command: &str, tm: &libc::tm) -> String {
+    format!("{}_{}.log", format_timestamp(tm), command_slug(command))
+}
+
+/// Write `content` to a new file in `dir`, creating the directory
+///
+/// Never overwrites: saving twice within a second adds a `-1`, `-2`, ...
+/// suffix. Returns the path written.
+pub fn save(dir: &Path, command: &str, content: &str, time: SystemTime) -> Result<PathBuf> {
+    std::fs::create_dir_all(dir)
+        .with_context(|| format!("Failed to create {}", dir.display()))?;
+
+    let name = file_name(command, &local_time(time));
+    let stem = name.trim_end_matches(".log");
+    for attempt in 0.. {
+        let path = if attempt == 0 {
+            dir.join(&name)
+        } else {
+            dir.join(format!("{}-{}.log", stem, attempt))
+        };
+        let mut file = match std::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)
+        {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+            Err(e) => {
+                return Err(e).with_context(|| format!("Failed to create {}", path.display()))
+            }
+        };
+        file.write_all(content.as_bytes())
+            .and_then(|_| file.write_all(b"\n"))
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        return Ok(path);
+    }
+    unreachable!("attempt counter is unbounded")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+
+    #[test]
+    fn test_file_name_formatting() {
+        // SAFETY: tm is plain data
+        let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+        tm.tm_year = 126;
+        tm.tm_mon = 9;
+        tm.tm_mday = 5;
+        tm.tm_hour = 7;
+        tm.tm_min = 3;
+        tm.tm_sec = 9;
+
+        assert_eq!(format_timestamp(&tm), "2026-10-05_07-03-09");
+        assert_eq!(
+            file_name("/usr/bin/cargo build --release", &tm),
+            "2026-10-05_07-03-09_cargo.log"
+        );
+        assert_eq!(command_slug("my script;rm -rf /"), "my");
+        assert_eq!(command_slug("a:b*c"), "a_b_c");
+        assert_eq!(command_slug(".."), "output");
+        assert_eq!(command_slug(""), "output");
+        assert_eq!(command_slug(&"x".repeat(100)).len(), MAX_SLUG_LEN);
+    }
+
+    #[test]
+    fn test_save_creates_dir_and_never_overwrites() {
+        let root = TempDir::new("output");
+        let dir = root.join("nested");
+        let time = SystemTime::now();
+
+        let first = save(&dir, "echo hi", "hi", time).unwrap();
+        let second = save(&dir, "echo hi", "again", time).unwrap();
+
+        assert_ne!(first, second);
+        assert!(second.to_string_lossy().ends_with("_echo-1.log"));
+        assert_eq!(std::fs::read_to_string(&first).unwrap(), "hi\n");
+        assert_eq!(std::fs::read_to_string(&second).unwrap(), "again\n");
+    }
+}
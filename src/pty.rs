@@ -1,30 +1,76 @@
 //! PTY (pseudo-terminal) handling for in-place command execution.
 //!
 //! TEAM_000: Phase 2, Unit 2.1 - PTY Allocation
+//!
+//! Two background threads serve each session: a reader that forwards PTY
+//! output through a bounded channel, and a waiter that blocks on the child
+//! and sends its exit status. Both signal a [`Wakeup`] if given one, so the
+//! UI notices at once. The UI drains both without blocking, and only
+//! reports the exit once the output has been read to the end, so nothing
+//! written just before exiting is lost.
 
 use anyhow::{Context, Result};
-use portable_pty::{native_pty_system, Child, CommandBuilder, MasterPty, PtySize};
+use portable_pty::{native_pty_system, ChildKiller, CommandBuilder, MasterPty, PtySize};
 use std::io::{Read, Write};
 use std::sync::mpsc::{self, Receiver, TryRecvError};
-use std::thread::{self, JoinHandle};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::executor::LaunchOptions;
+use crate::wakeup::Wakeup;
 
 // Re-export ExitStatus for use by other modules
 pub use portable_pty::ExitStatus;
 
+/// Size of a single read from the PTY
+const READ_CHUNK: usize = 64 * 1024;
+
+/// Output chunks buffered between the reader thread and the UI; the reader
+/// waits when the UI falls behind, which bounds memory use
+const OUTPUT_CHANNEL_CHUNKS: usize = 256;
+
+/// How long to wait for the end of output after the child exited
+///
+/// Background processes started by the command can keep the PTY open, so
+/// the exit is reported once the output goes quiet for this long.
+const EXIT_DRAIN: Duration = Duration::from_millis(250);
+
 /// A PTY session for running commands
 pub struct PtySession {
     master: Box<dyn MasterPty + Send>,
-    child: Box<dyn Child + Send + Sync>,
+    /// Child process id, for signalling its process group
+    pid: Option<u32>,
+    /// Kills the child on drop (the waiter thread owns the child itself)
+    killer: Box<dyn ChildKiller + Send + Sync>,
     /// Receiver for output data from the reader thread
     output_rx: Receiver<Vec<u8>>,
-    /// Handle to the reader thread (for cleanup)
-    _reader_thread: JoinHandle<()>,
+    /// Rest of the last chunk that did not fit the caller's buffer
+    pending: Vec<u8>,
+    /// The reader thread hit EOF and all its output was consumed
+    output_done: bool,
+    /// The last read found no output waiting
+    output_idle: bool,
+    /// Receiver for the exit status from the waiter thread
+    exit_rx: Receiver<ExitStatus>,
+    /// Exit status and when it arrived
+    exit: Option<(ExitStatus, Instant)>,
     writer: Box<dyn Write + Send>,
 }
 
 impl PtySession {
     /// Spawn a new PTY session with the given command
-    pub fn spawn(cmd: &str, cols: u16, rows: u16) -> Result<Self> {
+    ///
+    /// The command runs through `options.shell` in `options.working_dir`
+    /// (None inherits drun's) with `options.env` added to the environment.
+    ///
+    /// `wakeup` is signaled when output arrives and when the command exits.
+    pub fn spawn(
+        cmd: &str,
+        cols: u16,
+        rows: u16,
+        options: &LaunchOptions,
+        wakeup: Option<Wakeup>,
+    ) -> Result<Self> {
         let pty_system = native_pty_system();
 
         let size = PtySize {
@@ -38,9 +84,12 @@ impl PtySession {
             .openpty(size)
             .context("Failed to open PTY")?;
 
-        let mut command = CommandBuilder::new("sh");
-        command.arg("-c");
-        command.arg(cmd);
+        let command_argv = options.argv(cmd);
+        let mut command = CommandBuilder::from_argv(command_argv.into_iter().map(Into::into).collect());
+
+        if let Some(ref dir) = options.working_dir {
+            command.cwd(dir);
+        }
 
         // Inherit environment
         for (key, value) in std::env::vars() {
@@ -52,10 +101,17 @@ impl PtySession {
             command.env("TERM", "xterm-256color");
         }
 
-        let child = pair
+        // Extra variables from config [env] and X-DarkwallEnv
+        for (key, value) in &options.env {
+            command.env(key, value);
+        }
+
+        let mut child = pair
             .slave
             .spawn_command(command)
             .context("Failed to spawn command in PTY")?;
+        let pid = child.process_id();
+        let killer = child.clone_killer();
 
         let mut reader = pair
             .master
@@ -67,33 +123,59 @@ impl PtySession {
             .take_writer()
             .context("Failed to take PTY writer")?;
 
-        // Spawn a background thread to read from PTY and send via channel
-        // This provides truly non-blocking reads in the main thread
-        let (tx, rx) = mpsc::channel();
-        let reader_thread = thread::spawn(move || {
-            let mut buf = [0u8; 4096];
+        // Reader thread: forward output until EOF (the channel closing
+        // tells the UI that all output has arrived)
+        let (output_tx, output_rx) = mpsc::sync_channel(OUTPUT_CHANNEL_CHUNKS);
+        let output_wakeup = wakeup.clone();
+        thread::spawn(move || {
+            let mut buf = vec![0u8; READ_CHUNK];
             loop {
                 match reader.read(&mut buf) {
                     Ok(0) => break, // EOF
                     Ok(n) => {
-                        if tx.send(buf[..n].to_vec()).is_err() {
+                        if output_tx.send(buf[..n].to_vec()).is_err() {
                             break; // Receiver dropped
                         }
+                        if let Some(ref wakeup) = output_wakeup {
+                            wakeup.wake();
+                        }
                     }
                     Err(e) => {
-                        // Log error and exit thread
+                        // EIO once the child side is closed
                         tracing::debug!("PTY reader thread error: {}", e);
                         break;
                     }
                 }
             }
+            drop(output_tx);
+            if let Some(ref wakeup) = output_wakeup {
+                wakeup.wake();
+            }
+        });
+
+        // Waiter thread: reap the child and report its exit status
+        let (exit_tx, exit_rx) = mpsc::channel();
+        thread::spawn(move || {
+            let status = child.wait().unwrap_or_else(|e| {
+                tracing::warn!("Failed to wait for child process: {}", e);
+                ExitStatus::with_exit_code(1)
+            });
+            exit_tx.send(status).ok();
+            if let Some(ref wakeup) = wakeup {
+                wakeup.wake();
+            }
         });
 
         Ok(Self {
             master: pair.master,
-            child,
-            output_rx: rx,
-            _reader_thread: reader_thread,
+            pid,
+            killer,
+            output_rx,
+            pending: Vec::new(),
+            output_done: false,
+            output_idle: false,
+            exit_rx,
+            exit: None,
             writer,
         })
     }
@@ -113,15 +195,26 @@ impl PtySession {
     /// Read available data from the PTY (non-blocking)
     /// Returns Ok(None) if no data available, Ok(Some(data)) if data read
     pub fn try_read(&mut self, buf: &mut [u8]) -> Result<Option<usize>> {
-        match self.output_rx.try_recv() {
-            Ok(data) => {
-                let len = data.len().min(buf.len());
-                buf[..len].copy_from_slice(&data[..len]);
-                Ok(Some(len))
+        if self.pending.is_empty() {
+            match self.output_rx.try_recv() {
+                Ok(data) => self.pending = data,
+                Err(TryRecvError::Empty) => {
+                    self.output_idle = true;
+                    return Ok(None);
+                }
+                Err(TryRecvError::Disconnected) => {
+                    // Reader thread exited: all output has been read
+                    self.output_done = true;
+                    self.output_idle = true;
+                    return Ok(None);
+                }
             }
-            Err(TryRecvError::Empty) => Ok(None),
-            Err(TryRecvError::Disconnected) => Ok(None), // Reader thread exited
         }
+        self.output_idle = false;
+        let len = self.pending.len().min(buf.len());
+        buf[..len].copy_from_slice(&self.pending[..len]);
+        self.pending.drain(..len);
+        Ok(Some(len))
     }
 
     /// Write data to the PTY (for user input)
@@ -132,30 +225,88 @@ impl PtySession {
         self.writer.flush().context("Failed to flush PTY writer")
     }
 
+    /// Pick up the exit status if the waiter thread has sent it
+    fn poll_exit(&mut self) {
+        if self.exit.is_none() {
+            if let Ok(status) = self.exit_rx.try_recv() {
+                self.exit = Some((status, Instant::now()));
+            }
+        }
+    }
+
     /// Check if the child process is still running
     pub fn is_alive(&mut self) -> bool {
-        self.child.try_wait().ok().flatten().is_none()
+        self.poll_exit();
+        self.exit.is_none()
     }
 
     /// Wait for the child process to exit and return the exit status
     /// NOTE: Used in tests; main code uses try_wait() for non-blocking behavior
     #[allow(dead_code)]
     pub fn wait(&mut self) -> Result<ExitStatus> {
-        self.child.wait().context("Failed to wait for child process")
+        self.poll_exit();
+        if let Some((ref status, _)) = self.exit {
+            return Ok(status.clone());
+        }
+        let status = self
+            .exit_rx
+            .recv()
+            .context("Failed to wait for child process")?;
+        self.exit = Some((status.clone(), Instant::now()));
+        Ok(status)
     }
 
     /// Try to get exit status without blocking
+    ///
+    /// The status is only returned once the output has been read to the
+    /// end (or has stayed quiet for `EXIT_DRAIN` after the exit), so
+    /// reading output before and after this call collects all of it.
     pub fn try_wait(&mut self) -> Result<Option<ExitStatus>> {
-        self.child
-            .try_wait()
-            .context("Failed to check child status")
+        self.poll_exit();
+        Ok(match self.exit {
+            Some((ref status, at))
+                if self.output_done || (self.output_idle && at.elapsed() >= EXIT_DRAIN) =>
+            {
+                Some(status.clone())
+            }
+            _ => None,
+        })
+    }
+
+    /// Ask the child to exit by sending SIGTERM
+    ///
+    /// Unlike dropping the session, the reader keeps running so the final
+    /// output and exit status can still be collected.
+    pub fn terminate(&mut self) -> Result<()> {
+        self.signal(libc::SIGTERM)
+    }
+
+    /// Force the child to exit by sending SIGKILL
+    pub fn force_kill(&mut self) -> Result<()> {
+        self.signal(libc::SIGKILL)
+    }
+
+    /// Send a signal to the child's process group (falls back to the child)
+    fn signal(&mut self, signal: i32) -> Result<()> {
+        // The pid may already be reused once the child has been reaped
+        if !self.is_alive() {
+            return Ok(());
+        }
+        let pid = self.pid.context("Child has no process id")? as i32;
+        // The child is a session leader, so its pid is also its process
+        // group; signalling the group reaches pipelines started by `sh -c`
+        // SAFETY: kill has no memory safety preconditions
+        if unsafe { libc::kill(-pid, signal) } == 0 || unsafe { libc::kill(pid, signal) } == 0 {
+            return Ok(());
+        }
+        Err(std::io::Error::last_os_error()).context("Failed to signal child process")
     }
 
     /// Drain all available output from the channel
     /// Returns all data that's currently buffered
     #[allow(dead_code)] // Used in tests, available for future use
     pub fn drain_output(&mut self) -> Vec<u8> {
-        let mut output = Vec::new();
+        let mut output = std::mem::take(&mut self.pending);
         while let Ok(data) = self.output_rx.try_recv() {
             output.extend(data);
         }
@@ -167,7 +318,7 @@ impl Drop for PtySession {
     fn drop(&mut self) {
         // Try to kill the child if still running
         if self.is_alive() {
-            let _ = self.child.kill();
+            let _ = self.killer.kill();
         }
     }
 }
@@ -175,10 +326,12 @@ impl Drop for PtySession {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::executor::CommandStatus;
+    use std::path::PathBuf;
 
     #[test]
     fn test_pty_spawn_simple() {
-        let mut session = PtySession::spawn("echo hello", 80, 24).unwrap();
+        let mut session = PtySession::spawn("echo hello", 80, 24, &LaunchOptions::default(), None).unwrap();
         
         // Wait for command to complete
         let status = session.wait().unwrap();
@@ -187,7 +340,7 @@ mod tests {
 
     #[test]
     fn test_pty_read_output() {
-        let mut session = PtySession::spawn("echo hello", 80, 24).unwrap();
+        let mut session = PtySession::spawn("echo hello", 80, 24, &LaunchOptions::default(), None).unwrap();
         
         let mut buf = [0u8; 1024];
         let mut output = Vec::new();
@@ -200,10 +353,8 @@ mod tests {
             match session.try_read(&mut buf) {
                 Ok(Some(n)) => output.extend_from_slice(&buf[..n]),
                 Ok(None) => {
-                    // No data yet, check if process exited
-                    if !session.is_alive() {
-                        // Drain any remaining buffered output
-                        output.extend(session.drain_output());
+                    // No data yet; the exit is only reported after all output
+                    if session.try_wait().unwrap().is_some() {
                         break;
                     }
                     // Brief sleep before polling again
@@ -217,23 +368,158 @@ mod tests {
         assert!(output_str.contains("hello"), "Output was: {}", output_str);
     }
 
+    /// Read all output until the exit is reported
+    fn read_to_exit(session: &mut PtySession) -> (Vec<u8>, ExitStatus) {
+        let mut buf = [0u8; 1024];
+        let mut output = Vec::new();
+        let start = std::time::Instant::now();
+        loop {
+            assert!(start.elapsed() < std::time::Duration::from_secs(10), "no exit");
+            match session.try_read(&mut buf).unwrap() {
+                Some(n) => output.extend_from_slice(&buf[..n]),
+                None => match session.try_wait().unwrap() {
+                    Some(status) => return (output, status),
+                    None => std::thread::sleep(std::time::Duration::from_millis(5)),
+                },
+            }
+        }
+    }
+
+    #[test]
+    fn test_pty_large_output_is_complete() {
+        // Larger than the output channel holds, with the exit right after
+        let mut session = PtySession::spawn(
+            "head -c 3000000 /dev/zero | tr '\\0' x; exit 7",
+            80,
+            24,
+            &LaunchOptions::default(),
+            None,
+        )
+        .unwrap();
+        let (output, status) = read_to_exit(&mut session);
+        assert_eq!(output.iter().filter(|&&b| b == b'x').count(), 3_000_000);
+        assert!(matches!(
+            CommandStatus::from_exit_status(status),
+            CommandStatus::Exited(7)
+        ));
+    }
+
+    #[test]
+    fn test_pty_exit_with_background_process_holding_pty() {
+        let mut session =
+            PtySession::spawn("sleep 5 & echo started", 80, 24, &LaunchOptions::default(), None).unwrap();
+        let start = std::time::Instant::now();
+        let (output, status) = read_to_exit(&mut session);
+        assert!(start.elapsed() < std::time::Duration::from_secs(3));
+        assert!(String::from_utf8_lossy(&output).contains("started"));
+        assert!(status.success());
+    }
+
+    #[test]
+    fn test_pty_working_dir() {
+        let mut session = PtySession::spawn("pwd", 80, 24, &LaunchOptions {
+            working_dir: Some(PathBuf::from("/tmp")),
+            ..Default::default()
+        }, None).unwrap();
+        session.wait().unwrap();
+
+        // Poll until the reader thread has forwarded the output
+        let mut output = Vec::new();
+        let start = std::time::Instant::now();
+        while start.elapsed() < std::time::Duration::from_secs(2) {
+            output.extend(session.drain_output());
+            if String::from_utf8_lossy(&output).contains("/tmp") {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+
+        let output_str = String::from_utf8_lossy(&output);
+        assert!(output_str.contains("/tmp"), "Output was: {}", output_str);
+    }
+
+    #[test]
+    fn test_pty_extra_env() {
+        let options = LaunchOptions {
+            env: vec![("DRUN_TEST_VAR".to_string(), "from-drun".to_string())],
+            ..Default::default()
+        };
+        let mut session = PtySession::spawn("echo $DRUN_TEST_VAR", 80, 24, &options, None).unwrap();
+        session.wait().unwrap();
+
+        let mut output = Vec::new();
+        let start = std::time::Instant::now();
+        while start.elapsed() < std::time::Duration::from_secs(2) {
+            output.extend(session.drain_output());
+            if String::from_utf8_lossy(&output).contains("from-drun") {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+
+        let output_str = String::from_utf8_lossy(&output);
+        assert!(output_str.contains("from-drun"), "Output was: {}", output_str);
+    }
+
     #[test]
     fn test_pty_resize() {
-        let session = PtySession::spawn("sleep 0.1", 80, 24).unwrap();
+        let session = PtySession::spawn("sleep 0.1", 80, 24, &LaunchOptions::default(), None).unwrap();
         assert!(session.resize(120, 40).is_ok());
     }
 
     #[test]
     fn test_pty_exit_code() {
-        let mut session = PtySession::spawn("exit 42", 80, 24).unwrap();
+        let mut session = PtySession::spawn("exit 42", 80, 24, &LaunchOptions::default(), None).unwrap();
         let status = session.wait().unwrap();
-        // portable_pty::ExitStatus only exposes success()
         assert!(!status.success());
+        assert!(matches!(
+            CommandStatus::from_exit_status(status),
+            CommandStatus::Exited(42)
+        ));
+    }
+
+    #[test]
+    fn test_pty_signal_exit() {
+        let mut session = PtySession::spawn("kill -TERM $$", 80, 24, &LaunchOptions::default(), None).unwrap();
+        let status = session.wait().unwrap();
+        assert!(matches!(
+            CommandStatus::from_exit_status(status),
+            CommandStatus::Signaled(15)
+        ));
+    }
+
+    #[test]
+    fn test_pty_terminate() {
+        let mut session = PtySession::spawn("sleep 10", 80, 24, &LaunchOptions::default(), None).unwrap();
+        session.terminate().unwrap();
+        let status = session.wait().unwrap();
+        assert!(matches!(
+            CommandStatus::from_exit_status(status),
+            CommandStatus::Signaled(15)
+        ));
+    }
+
+    #[test]
+    fn test_pty_force_kill_when_term_is_ignored() {
+        let mut session =
+            PtySession::spawn("trap '' TERM; sleep 10", 80, 24, &LaunchOptions::default(), None).unwrap();
+        // Let the shell install the trap
+        std::thread::sleep(std::time::Duration::from_millis(100));
+        session.terminate().unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(100));
+        assert!(session.is_alive());
+
+        session.force_kill().unwrap();
+        let status = session.wait().unwrap();
+        assert!(matches!(
+            CommandStatus::from_exit_status(status),
+            CommandStatus::Signaled(9)
+        ));
     }
 
     #[test]
     fn test_pty_success() {
-        let mut session = PtySession::spawn("exit 0", 80, 24).unwrap();
+        let mut session = PtySession::spawn("exit 0", 80, 24, &LaunchOptions::default(), None).unwrap();
         let status = session.wait().unwrap();
         assert!(status.success());
     }
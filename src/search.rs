@@ -0,0 +1,551 @@
+//! Fuzzy matching and per-field scoring of entries.
+//!
+//! Each searchable field of an entry is matched separately and weighted so
+//! that a hit in the name outranks the same hit in keywords or categories.
+//! The concatenated `search_text()` is still used as a gate (and lowest
+//! weighted fallback) so queries spanning several fields keep matching.
+//!
+//! Operators narrow the results before matching: `#dev vim` only
+//! fuzzy-matches "vim" among entries in a category starting with "dev"
+//! (e.g. Development), `@web` among those with a keyword matching "web",
+//! `!beta` leaves out entries mentioning "beta" and `"text editor"` keeps
+//! only those containing the phrase. See [`Query`].
+
+use nucleo_matcher::{
+    pattern::{CaseMatching, Normalization, Pattern},
+    Matcher, Utf32Str,
+};
+
+use crate::desktop_entry::Entry;
+
+/// Weight of a match in the entry name
+const NAME_WEIGHT: f64 = 1.0;
+/// Weight of a match in GenericName
+const GENERIC_NAME_WEIGHT: f64 = 0.8;
+/// Weight of a match in Keywords
+const KEYWORDS_WEIGHT: f64 = 0.6;
+/// Weight of a match in Comment
+const COMMENT_WEIGHT: f64 = 0.5;
+/// Weight of a fuzzy match in Categories
+const CATEGORIES_WEIGHT: f64 = 0.5;
+/// Weight of a category equal to the whole query (e.g. "game" vs "Game")
+const CATEGORY_EXACT_WEIGHT: f64 = 0.9;
+/// Weight of a match only found across fields in the concatenated text
+const COMBINED_WEIGHT: f64 = 0.4;
+
+/// Freedesktop main categories, listed in the category browser
+pub const MAIN_CATEGORIES: &[&str] = &[
+    "AudioVideo",
+    "Audio",
+    "Video",
+    "Development",
+    "Education",
+    "Game",
+    "Graphics",
+    "Network",
+    "Office",
+    "Science",
+    "Settings",
+    "System",
+    "Utility",
+];
+
+/// A filter split into its operators and the fuzzy text
+///
+/// Tokens starting with `#` restrict to a category, `@` fuzzy-match
+/// Keywords only, `!` leave out entries containing the term (or `!"a
+/// phrase"`), and `"quoted phrases"` must appear as written, ignoring case.
+/// Everything else is fuzzy text, with nucleo's own `^`, `'` and `$`.
+/// `\#`, `\@` and `\"` start a word with that character; a `!` on its
+/// own does nothing.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Query<'a> {
+    /// Category prefixes without the `#` (may be empty while typing)
+    pub categories: Vec<&'a str>,
+    /// `@` terms, fuzzy-matched against Keywords
+    pub keywords: Vec<&'a str>,
+    /// `!` terms: entries containing one are left out
+    pub excluded: Vec<&'a str>,
+    /// Quoted phrases, without the quotes
+    pub phrases: Vec<&'a str>,
+    /// The bare words, for fuzzy matching
+    pub text: String,
+    /// `>` prefix: programs on `$PATH` instead of applications
+    pub run: bool,
+}
+
+impl Query<'_> {
+    /// The fuzzy text with the phrases as nucleo substring atoms, so they
+    /// rank and highlight like the words
+    pub fn pattern_text(&self) -> String {
+        let mut atoms: Vec<String> = self
+            .phrases
+            .iter()
+            .map(|phrase| {
+                let mut atom = format!("'{}", phrase.replace(' ', "\\ "));
+                // A trailing `$` would anchor the phrase at the end
+                if atom.ends_with('$') {
+                    atom.insert(atom.len() - 1, '\\');
+                }
+                atom
+            })
+            .collect();
+        if !self.text.is_empty() {
+            atoms.insert(0, self.text.clone());
+        }
+        atoms.join(" ")
+    }
+
+    /// Names of the operators in use beyond fuzzy text, for the status bar
+    pub fn operators(&self) -> Vec<&'static str> {
+        [
+            (!self.categories.is_empty(), "#category"),
+            (!self.keywords.is_empty(), "@keyword"),
+            (!self.excluded.is_empty(), "!exclude"),
+            (!self.phrases.is_empty(), "\"phrase\""),
+        ]
+        .into_iter()
+        .filter_map(|(used, name)| used.then_some(name))
+        .collect()
+    }
+}
+
+/// Parse the filter text into operators and fuzzy text
+pub fn parse_query(filter: &str) -> Query<'_> {
+    let (filter, run) = match filter.strip_prefix('>') {
+        Some(rest) => (rest, true),
+        None => (filter, false),
+    };
+    let mut query = Query {
+        run,
+        ..Default::default()
+    };
+    let mut words = Vec::new();
+    let mut rest = filter.trim_start();
+    while let Some(first) = rest.chars().next() {
+        let after_first = &rest[first.len_utf8()..];
+        rest = match first {
+            '"' => {
+                let (phrase, after) = split_phrase(after_first);
+                if !phrase.is_empty() {
+                    query.phrases.push(phrase);
+                }
+                after
+            }
+            '!' => {
+                let (term, after) = match after_first.strip_prefix('"') {
+                    Some(quoted) => split_phrase(quoted),
+                    None => split_word(after_first),
+                };
+                if !term.is_empty() {
+                    query.excluded.push(term);
+                }
+                after
+            }
+            '#' => {
+                let (category, after) = split_word(after_first);
+                query.categories.push(category);
+                after
+            }
+            '@' => {
+                let (keyword, after) = split_word(after_first);
+                if !keyword.is_empty() {
+                    query.keywords.push(keyword);
+                }
+                after
+            }
+            _ => {
+                let (word, after) = split_word(rest);
+                // Nucleo doesn't know these escapes; it handles `\!` itself
+                let word = match word.strip_prefix('\\') {
+                    Some(escaped) if escaped.starts_with(['#', '@', '"']) => escaped,
+                    _ => word,
+                };
+                words.push(word);
+                after
+            }
+        }
+        .trim_start();
+    }
+    query.text = words.join(" ");
+    query
+}
+
+/// The word `s` starts with and the rest; a backslash escapes a space
+fn split_word(s: &str) -> (&str, &str) {
+    let mut escaped = false;
+    for (i, c) in s.char_indices() {
+        if c.is_whitespace() && !escaped {
+            return (&s[..i], &s[i..]);
+        }
+        escaped = c == '\\' && !escaped;
+    }
+    (s, "")
+}
+
+/// The phrase up to the closing quote (or the end, while typing) and the
+/// rest after the quote
+fn split_phrase(s: &str) -> (&str, &str) {
+    match s.split_once('"') {
+        Some((phrase, rest)) => (phrase, rest),
+        None => (s, ""),
+    }
+}
+
+/// The operators of a query other than the fuzzy text, to test entries
+/// against
+pub struct Filter {
+    categories: Vec<String>,
+    keywords: Vec<Pattern>,
+    /// Lowercase
+    excluded: Vec<String>,
+}
+
+impl Filter {
+    pub fn new(query: &Query) -> Self {
+        Self {
+            categories: query.categories.iter().map(|c| c.to_string()).collect(),
+            keywords: query
+                .keywords
+                .iter()
+                .map(|k| Pattern::parse(k, CaseMatching::Ignore, Normalization::Smart))
+                .collect(),
+            excluded: query.excluded.iter().map(|t| t.to_lowercase()).collect(),
+        }
+    }
+
+    /// Whether `entry` is in every category, matches every keyword term
+    /// and contains no excluded term
+    pub fn matches(&self, entry: &Entry, matcher: &mut Matcher) -> bool {
+        if !self.categories.iter().all(|category| in_category(entry, category)) {
+            return false;
+        }
+        if !self.keywords.is_empty() {
+            let keywords = entry.keywords.join(" ");
+            let mut buf = Vec::new();
+            if !self
+                .keywords
+                .iter()
+                .all(|pattern| score_text(&keywords, pattern, matcher, &mut buf).is_some())
+            {
+                return false;
+            }
+        }
+        if !self.excluded.is_empty() {
+            let text = entry.search_text().to_lowercase();
+            if self.excluded.iter().any(|term| text.contains(term.as_str())) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// Check whether an entry has a category starting with `prefix` (case-insensitive)
+///
+/// An empty prefix matches everything.
+pub fn in_category(entry: &Entry, prefix: &str) -> bool {
+    if prefix.is_empty() {
+        return true;
+    }
+    let prefix = prefix.to_lowercase();
+    entry
+        .categories
+        .iter()
+        .any(|c| c.to_lowercase().starts_with(&prefix))
+}
+
+/// Main categories that have entries, with their entry counts
+pub fn category_counts(entries: &[Entry]) -> Vec<(String, usize)> {
+    MAIN_CATEGORIES
+        .iter()
+        .map(|&cat| {
+            let count = entries
+                .iter()
+                .filter(|e| e.categories.iter().any(|c| c == cat))
+                .count();
+            (cat.to_string(), count)
+        })
+        .filter(|(_, count)| *count > 0)
+        .collect()
+}
+
+/// Score a single haystack, returning None if the pattern doesn't match
+fn score_text(
+    text: &str,
+    pattern: &Pattern,
+    matcher: &mut Matcher,
+    buf: &mut Vec<char>,
+) -> Option<f64> {
+    pattern
+        .score(Utf32Str::new(text, buf), matcher)
+        .map(|score| score as f64)
+}
+
+/// Score an entry against a pattern
+///
+/// Returns None if the entry doesn't match. `query` is the raw filter text,
+/// used to detect exact category matches.
+pub fn score_entry(
+    entry: &Entry,
+    query: &str,
+    pattern: &Pattern,
+    matcher: &mut Matcher,
+) -> Option<f64> {
+    let mut buf = Vec::new();
+
+    // Gate on the full text so multi-word queries can span fields
+    let combined = score_text(&entry.search_text(), pattern, matcher, &mut buf)?;
+    let mut best = combined * COMBINED_WEIGHT;
+
+    let mut consider = |text: &str, weight: f64, buf: &mut Vec<char>, matcher: &mut Matcher| {
+        if let Some(score) = score_text(text, pattern, matcher, buf) {
+            best = best.max(score * weight);
+        }
+    };
+
+    consider(&entry.name, NAME_WEIGHT, &mut buf, matcher);
+    if let Some(ref generic) = entry.generic_name {
+        consider(generic, GENERIC_NAME_WEIGHT, &mut buf, matcher);
+    }
+    if !entry.keywords.is_empty() {
+        consider(&entry.keywords.join(" "), KEYWORDS_WEIGHT, &mut buf, matcher);
+    }
+    if let Some(ref comment) = entry.comment {
+        consider(comment, COMMENT_WEIGHT, &mut buf, matcher);
+    }
+
+    let query = query.trim();
+    for category in &entry.categories {
+        let weight = if category.eq_ignore_ascii_case(query) {
+            CATEGORY_EXACT_WEIGHT
+        } else {
+            CATEGORIES_WEIGHT
+        };
+        consider(category, weight, &mut buf, matcher);
+    }
+
+    Some(best)
+}
+
+/// Positions (in chars) of the characters in `name` the pattern matched,
+/// sorted, or None if the name alone doesn't match
+///
+/// Computed against the name as displayed, not the combined search text,
+/// so the positions can be used to highlight it.
+pub fn name_match_indices(name: &str, pattern: &Pattern, matcher: &mut Matcher) -> Option<Vec<u32>> {
+    let mut buf = Vec::new();
+    let mut indices = Vec::new();
+    pattern.indices(Utf32Str::new(name, &mut buf), matcher, &mut indices)?;
+    indices.sort_unstable();
+    indices.dedup();
+    Some(indices)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(name: &str, keywords: &[&str], categories: &[&str]) -> Entry {
+        Entry {
+            id: name.to_lowercase(),
+            name: name.to_string(),
+            keywords: keywords.iter().map(|k| k.to_string()).collect(),
+            categories: categories.iter().map(|c| c.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    fn score(entry: &Entry, query: &str) -> Option<f64> {
+        let pattern = Pattern::parse(query, CaseMatching::Ignore, Normalization::Smart);
+        let mut matcher = Matcher::new(nucleo_matcher::Config::DEFAULT);
+        score_entry(entry, query, &pattern, &mut matcher)
+    }
+
+    #[test]
+    fn test_keywords_are_searched() {
+        let firefox = entry("Firefox", &["Internet", "WWW", "Browser"], &["Network"]);
+        assert!(score(&firefox, "browser").is_some());
+        assert!(score(&firefox, "zzz").is_none());
+    }
+
+    #[test]
+    fn test_name_match_outranks_keyword_match() {
+        let by_name = entry("Browser", &[], &[]);
+        let by_keyword = entry("Firefox", &["Browser"], &[]);
+
+        let name_score = score(&by_name, "browser").unwrap();
+        let keyword_score = score(&by_keyword, "browser").unwrap();
+        assert!(name_score > keyword_score, "{} <= {}", name_score, keyword_score);
+    }
+
+    #[test]
+    fn test_exact_category_boost() {
+        let exact = entry("Chess", &[], &["Game"]);
+        let fuzzy = entry("Level Editor", &[], &["Development", "GameDev"]);
+
+        let exact_score = score(&exact, "game").unwrap();
+        let fuzzy_score = score(&fuzzy, "game").unwrap();
+        assert!(exact_score > fuzzy_score, "{} <= {}", exact_score, fuzzy_score);
+    }
+
+    fn text(text: &str) -> Query<'_> {
+        Query {
+            text: text.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_parse_query() {
+        assert_eq!(parse_query("firefox"), text("firefox"));
+        assert_eq!(parse_query("  text   editor "), text("text editor"));
+        assert_eq!(
+            parse_query("#dev"),
+            Query { categories: vec!["dev"], ..Default::default() }
+        );
+        assert_eq!(
+            parse_query("#dev  vim"),
+            Query { categories: vec!["dev"], ..text("vim") }
+        );
+        assert_eq!(parse_query("#"), Query { categories: vec![""], ..Default::default() });
+        assert_eq!(parse_query("> bt"), Query { run: true, ..text("bt") });
+        // Nucleo's own syntax is left to nucleo
+        assert_eq!(parse_query("^fire fox$ 'term"), text("^fire fox$ 'term"));
+    }
+
+    #[test]
+    fn test_parse_query_operators() {
+        assert_eq!(
+            parse_query("vim #dev @edit !gtk \"text editor\" neo"),
+            Query {
+                categories: vec!["dev"],
+                keywords: vec!["edit"],
+                excluded: vec!["gtk"],
+                phrases: vec!["text editor"],
+                text: "vim neo".to_string(),
+                run: false,
+            }
+        );
+        assert_eq!(
+            parse_query("#Network #web !\"web browser\" !beta"),
+            Query {
+                categories: vec!["Network", "web"],
+                excluded: vec!["web browser", "beta"],
+                ..Default::default()
+            }
+        );
+        // Mid-word they are ordinary characters
+        assert_eq!(parse_query("user@host a#b c!d"), text("user@host a#b c!d"));
+        // Unclosed while typing
+        assert_eq!(parse_query("\"text ed"), Query { phrases: vec!["text ed"], ..Default::default() });
+        assert_eq!(parse_query("!\"web"), Query { excluded: vec!["web"], ..Default::default() });
+    }
+
+    #[test]
+    fn test_parse_query_escapes() {
+        assert_eq!(parse_query("\\#1 \\@home \\\"quoted"), text("#1 @home \"quoted"));
+        // Nucleo unescapes `\!` and spaces itself
+        assert_eq!(parse_query("\\!important a\\ b"), text("\\!important a\\ b"));
+        assert!(parse_query("\\#1").operators().is_empty());
+    }
+
+    #[test]
+    fn test_parse_query_trailing_operators() {
+        // Nothing to apply yet while typing
+        assert_eq!(parse_query("vim !"), text("vim"));
+        assert_eq!(parse_query("vim ! @"), text("vim"));
+        assert_eq!(parse_query("vim \"\""), text("vim"));
+        assert!(parse_query("vim !").operators().is_empty());
+        assert_eq!(parse_query("vim #").operators(), ["#category"]);
+        assert_eq!(parse_query("@web !x \"a b\"").operators(), ["@keyword", "!exclude", "\"phrase\""]);
+    }
+
+    #[test]
+    fn test_pattern_text() {
+        assert_eq!(parse_query("vim \"text editor\"").pattern_text(), "vim 'text\\ editor");
+        assert_eq!(parse_query("\"costs $\"").pattern_text(), "'costs\\ \\$");
+        assert_eq!(parse_query("#dev").pattern_text(), "");
+    }
+
+    #[test]
+    fn test_filter() {
+        let filter = |query: &str, entry: &Entry| {
+            let mut matcher = Matcher::new(nucleo_matcher::Config::DEFAULT);
+            Filter::new(&parse_query(query)).matches(entry, &mut matcher)
+        };
+        let firefox = entry("Firefox", &["Internet", "WWW", "Browser"], &["Network", "WebBrowser"]);
+        assert!(filter("#net", &firefox));
+        assert!(filter("#net #web", &firefox));
+        assert!(!filter("#net #game", &firefox));
+        assert!(filter("@brwsr", &firefox));
+        // Keywords only: the name doesn't count
+        assert!(!filter("@firefox", &firefox));
+        assert!(!filter("!browser", &firefox));
+        assert!(!filter("!FIRE", &firefox));
+        assert!(filter("!chrome", &firefox));
+        assert!(filter("\"no such phrase\"", &firefox), "phrases are matched by the pattern");
+    }
+
+    #[test]
+    fn test_phrases_match_exactly() {
+        let editor = entry("Text Editor", &[], &[]);
+        let query = parse_query("\"text edit\"");
+        let pattern = Pattern::parse(&query.pattern_text(), CaseMatching::Ignore, Normalization::Smart);
+        let mut matcher = Matcher::new(nucleo_matcher::Config::DEFAULT);
+        assert!(score_entry(&editor, &query.text, &pattern, &mut matcher).is_some());
+        let query = parse_query("\"txt edit\"");
+        let pattern = Pattern::parse(&query.pattern_text(), CaseMatching::Ignore, Normalization::Smart);
+        assert!(score_entry(&editor, &query.text, &pattern, &mut matcher).is_none());
+        assert_eq!(name_match_indices("Text Editor", &pattern, &mut matcher), None);
+    }
+
+    #[test]
+    fn test_in_category() {
+        let ide = entry("IDE", &[], &["Development", "IDE"]);
+        assert!(in_category(&ide, "dev"));
+        assert!(in_category(&ide, "DEVELOPMENT"));
+        assert!(in_category(&ide, ""));
+        assert!(!in_category(&ide, "game"));
+        assert!(!in_category(&ide, "opment"));
+    }
+
+    #[test]
+    fn test_category_counts() {
+        let entries = vec![
+            entry("IDE", &[], &["Development", "IDE"]),
+            entry("Editor", &[], &["Development", "Utility"]),
+            entry("Chess", &[], &["Game", "BoardGame"]),
+        ];
+        assert_eq!(
+            category_counts(&entries),
+            vec![
+                ("Development".to_string(), 2),
+                ("Game".to_string(), 1),
+                ("Utility".to_string(), 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_name_match_indices() {
+        let indices = |name: &str, query: &str| {
+            let pattern = Pattern::parse(query, CaseMatching::Ignore, Normalization::Smart);
+            let mut matcher = Matcher::new(nucleo_matcher::Config::DEFAULT);
+            name_match_indices(name, &pattern, &mut matcher)
+        };
+        assert_eq!(indices("Firefox", "ffx"), Some(vec![0, 4, 6]));
+        assert_eq!(indices("GNOME Terminal", "term"), Some(vec![6, 7, 8, 9]));
+        // Several words highlight each, in order
+        assert_eq!(indices("Text Editor", "edit text"), Some(vec![0, 1, 2, 3, 5, 6, 7, 8]));
+        // Counted in chars, not bytes
+        assert_eq!(indices("Café Noir", "noir"), Some(vec![5, 6, 7, 8]));
+        assert_eq!(indices("Firefox", "browser"), None);
+    }
+
+    #[test]
+    fn test_query_spanning_fields_still_matches() {
+        let firefox = entry("Firefox", &["Browser"], &[]);
+        assert!(score(&firefox, "firefox browser").is_some());
+    }
+}
@@ -0,0 +1,614 @@
+//! sway IPC client, also used for i3.
+//!
+//! # Protocol
+//!
+//! Messages are JSON in a binary frame: the magic string `i3-ipc`, the
+//! payload length and the message type as native-endian u32s, then the
+//! payload. Replies use the same frame and type; events have the high bit
+//! of the type set. Unlike niri, one connection can carry many requests,
+//! but drun opens one per request like it does for niri.
+//!
+//! Commands (`RUN_COMMAND`) act on the focused window unless criteria
+//! like `[con_id=7]` pick another. Windows come from `GET_TREE`: they are
+//! the leaves below the workspaces, tiled in `nodes` and floating in
+//! `floating_nodes`.
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::UnixStream;
+use tokio::sync::mpsc;
+
+use crate::compositor::{
+    self, BoxFuture, Compositor, Event, EventSource, IpcSocket, WindowInfo, WorkspaceInfo,
+    WorkspaceReference,
+};
+use crate::desktop_entry::shell_quote;
+
+/// Start of every message
+const MAGIC: &[u8; 6] = b"i3-ipc";
+/// Magic, payload length and message type
+const HEADER_LEN: usize = 14;
+
+const RUN_COMMAND: u32 = 0;
+const GET_WORKSPACES: u32 = 1;
+const SUBSCRIBE: u32 = 2;
+const GET_TREE: u32 = 4;
+/// Event sent for window changes after subscribing to `window`
+const WINDOW_EVENT: u32 = 0x8000_0003;
+
+/// Workspace sway keeps scratchpad windows on
+const SCRATCHPAD: &str = "__i3_scratch";
+
+/// Client for sway IPC
+#[derive(Clone)]
+pub struct SwayClient {
+    socket: IpcSocket,
+}
+
+impl SwayClient {
+    /// Connect to the socket in `$SWAYSOCK`, or `$I3SOCK` under i3
+    pub fn new(timeout: Duration) -> Result<Self> {
+        let path = ["SWAYSOCK", "I3SOCK"]
+            .into_iter()
+            .filter_map(|var| std::env::var_os(var).map(PathBuf::from))
+            .find(|path| path.exists())
+            .context("sway socket not found (normal if not running under sway)")?;
+        Ok(Self::with_socket(path, timeout))
+    }
+
+    fn with_socket(path: PathBuf, timeout: Duration) -> Self {
+        Self {
+            socket: IpcSocket::new("sway", path, timeout),
+        }
+    }
+
+    async fn request(&self, kind: u32, payload: &str) -> Result<serde_json::Value> {
+        self.socket.request(self.send(kind, payload)).await
+    }
+
+    async fn send(&self, kind: u32, payload: &str) -> Result<serde_json::Value> {
+        let mut stream = UnixStream::connect(self.socket.path())
+            .await
+            .context("Failed to connect to sway socket")?;
+        stream
+            .write_all(&encode(kind, payload))
+            .await
+            .context("Failed to write to sway socket")?;
+
+        let (reply_kind, reply) = read_message(&mut stream).await?;
+        if reply_kind != kind {
+            anyhow::bail!("sway answered with message type {} to {}", reply_kind, kind);
+        }
+        tracing::debug!("sway response: {}", String::from_utf8_lossy(&reply));
+        serde_json::from_slice(&reply).context("Failed to parse sway response")
+    }
+
+    /// Run sway commands, failing if one of them fails
+    async fn command(&self, command: &str) -> Result<()> {
+        tracing::debug!("sway command: {}", command);
+        command_result(self.request(RUN_COMMAND, command).await?)
+    }
+
+    pub async fn focused_window(&self) -> Result<Option<WindowInfo>> {
+        Ok(self.windows().await?.into_iter().find(|window| window.is_focused))
+    }
+
+    pub async fn windows(&self) -> Result<Vec<WindowInfo>> {
+        let tree = serde_json::from_value(self.request(GET_TREE, "").await?)
+            .context("Failed to parse sway tree")?;
+        Ok(tree_windows(&tree))
+    }
+
+    pub async fn workspaces(&self) -> Result<Vec<WorkspaceInfo>> {
+        let workspaces: Vec<SwayWorkspace> =
+            serde_json::from_value(self.request(GET_WORKSPACES, "").await?)
+                .context("Failed to parse Workspaces")?;
+        Ok(workspaces.into_iter().map(WorkspaceInfo::from).collect())
+    }
+
+    /// Argument of `workspace` commands for `reference`
+    ///
+    /// sway has no command taking a workspace id, so ids are looked up.
+    async fn workspace_arg(&self, reference: WorkspaceReference) -> Result<String> {
+        match reference {
+            WorkspaceReference::Id(id) => {
+                let workspaces: Vec<SwayWorkspace> =
+                    serde_json::from_value(self.request(GET_WORKSPACES, "").await?)
+                        .context("Failed to parse Workspaces")?;
+                let workspace = workspaces
+                    .into_iter()
+                    .find(|ws| ws.id == id)
+                    .with_context(|| format!("No sway workspace with id {}", id))?;
+                Ok(quote(&workspace.name))
+            }
+            WorkspaceReference::Index(index) => Ok(format!("number {}", index)),
+            WorkspaceReference::Name(name) => Ok(quote(&name)),
+        }
+    }
+
+    pub async fn focus_workspace(&self, reference: WorkspaceReference) -> Result<()> {
+        let workspace = self.workspace_arg(reference).await?;
+        self.command(&format!("workspace {}", workspace)).await
+    }
+
+    pub async fn move_window_to_workspace(
+        &self,
+        window_id: Option<u64>,
+        reference: WorkspaceReference,
+        focus: bool,
+    ) -> Result<()> {
+        let workspace = self.workspace_arg(reference).await?;
+        let mut command = format!(
+            "{}move container to workspace {}",
+            window_id.map(|id| format!("[con_id={}] ", id)).unwrap_or_default(),
+            workspace
+        );
+        if focus {
+            command.push_str(&format!("; workspace {}", workspace));
+        }
+        self.command(&command).await
+    }
+
+    /// Subscribe to window events
+    pub async fn event_stream(&self) -> Result<EventStream> {
+        if !self.socket.is_available() {
+            anyhow::bail!("sway is not responding");
+        }
+        self.socket.with_timeout(self.subscribe()).await
+    }
+
+    async fn subscribe(&self) -> Result<EventStream> {
+        let mut stream = UnixStream::connect(self.socket.path())
+            .await
+            .context("Failed to connect to sway socket")?;
+        stream
+            .write_all(&encode(SUBSCRIBE, r#"["window"]"#))
+            .await
+            .context("Failed to write to sway socket")?;
+        let (_, reply) = read_message(&mut stream).await?;
+        let reply: serde_json::Value =
+            serde_json::from_slice(&reply).context("Failed to parse sway response")?;
+        if reply["success"] != true {
+            anyhow::bail!("sway refused the event subscription");
+        }
+        Ok(EventStream { stream })
+    }
+}
+
+impl Compositor for SwayClient {
+    fn name(&self) -> &'static str {
+        "sway"
+    }
+
+    fn socket_path(&self) -> &Path {
+        self.socket.path()
+    }
+
+    fn is_available(&self) -> bool {
+        self.socket.is_available()
+    }
+
+    fn last_error(&self) -> Option<String> {
+        self.socket.last_error()
+    }
+
+    fn set_floating(&self, floating: bool) -> BoxFuture<'_, ()> {
+        let command = if floating { "floating enable" } else { "floating disable" };
+        Box::pin(self.command(command))
+    }
+
+    fn toggle_floating(&self) -> BoxFuture<'_, ()> {
+        Box::pin(self.command("floating toggle"))
+    }
+
+    fn focused_window(&self) -> BoxFuture<'_, Option<WindowInfo>> {
+        Box::pin(SwayClient::focused_window(self))
+    }
+
+    fn windows(&self) -> BoxFuture<'_, Vec<WindowInfo>> {
+        Box::pin(SwayClient::windows(self))
+    }
+
+    fn workspaces(&self) -> BoxFuture<'_, Vec<WorkspaceInfo>> {
+        Box::pin(SwayClient::workspaces(self))
+    }
+
+    fn focus_window(&self, id: u64) -> BoxFuture<'_, ()> {
+        Box::pin(async move { self.command(&format!("[con_id={}] focus", id)).await })
+    }
+
+    fn focus_workspace(&self, reference: WorkspaceReference) -> BoxFuture<'_, ()> {
+        Box::pin(SwayClient::focus_workspace(self, reference))
+    }
+
+    fn move_window_to_workspace(
+        &self,
+        window_id: Option<u64>,
+        reference: WorkspaceReference,
+        focus: bool,
+    ) -> BoxFuture<'_, ()> {
+        Box::pin(SwayClient::move_window_to_workspace(self, window_id, reference, focus))
+    }
+
+    fn spawn(&self, command: Vec<String>) -> BoxFuture<'_, ()> {
+        // exec hands the rest of the line to sh -c
+        let argv: Vec<_> = command.iter().map(|arg| shell_quote(arg)).collect();
+        let command = format!("exec {}", argv.join(" "));
+        Box::pin(async move { self.command(&command).await })
+    }
+
+    fn spawn_event_listener(&self) -> Option<mpsc::UnboundedReceiver<Event>> {
+        let client = self.clone();
+        compositor::spawn_event_listener("sway", async move { client.event_stream().await })
+    }
+
+    fn boxed_clone(&self) -> Box<dyn Compositor> {
+        Box::new(self.clone())
+    }
+}
+
+/// Open event subscription
+pub struct EventStream {
+    stream: UnixStream,
+}
+
+impl EventSource for EventStream {
+    fn next_event(&mut self) -> BoxFuture<'_, Option<Event>> {
+        Box::pin(async move {
+            loop {
+                let (kind, payload) = match read_message(&mut self.stream).await {
+                    Ok(message) => message,
+                    // sway closed the connection
+                    Err(e) if is_eof(&e) => return Ok(None),
+                    Err(e) => return Err(e),
+                };
+                if kind != WINDOW_EVENT {
+                    continue;
+                }
+                match serde_json::from_slice(&payload) {
+                    Ok(event) => {
+                        if let Some(event) = window_event(event) {
+                            return Ok(Some(event));
+                        }
+                    }
+                    Err(e) => tracing::trace!("Skipping sway event: {}", e),
+                }
+            }
+        })
+    }
+}
+
+/// Frame `payload` as a message of type `kind`
+fn encode(kind: u32, payload: &str) -> Vec<u8> {
+    let mut message = Vec::with_capacity(HEADER_LEN + payload.len());
+    message.extend_from_slice(MAGIC);
+    message.extend_from_slice(&(payload.len() as u32).to_ne_bytes());
+    message.extend_from_slice(&kind.to_ne_bytes());
+    message.extend_from_slice(payload.as_bytes());
+    message
+}
+
+/// Payload length and message type from a message header
+fn decode_header(header: &[u8; HEADER_LEN]) -> Result<(usize, u32)> {
+    if &header[..MAGIC.len()] != MAGIC {
+        anyhow::bail!("Not a sway IPC message");
+    }
+    let len = u32::from_ne_bytes(header[6..10].try_into()?);
+    let kind = u32::from_ne_bytes(header[10..14].try_into()?);
+    Ok((len as usize, kind))
+}
+
+/// Read one message: its type and payload
+async fn read_message(stream: &mut UnixStream) -> Result<(u32, Vec<u8>)> {
+    let mut header = [0; HEADER_LEN];
+    stream
+        .read_exact(&mut header)
+        .await
+        .context("Failed to read from sway socket")?;
+    let (len, kind) = decode_header(&header)?;
+    let mut payload = vec![0; len];
+    stream
+        .read_exact(&mut payload)
+        .await
+        .context("Failed to read from sway socket")?;
+    Ok((kind, payload))
+}
+
+/// Whether reading failed because the connection was closed
+fn is_eof(error: &anyhow::Error) -> bool {
+    error
+        .downcast_ref::<std::io::Error>()
+        .is_some_and(|e| e.kind() == std::io::ErrorKind::UnexpectedEof)
+}
+
+/// Fail if a command in a `RUN_COMMAND` reply failed
+fn command_result(reply: serde_json::Value) -> Result<()> {
+    let results: Vec<CommandResult> =
+        serde_json::from_value(reply).context("Failed to parse sway command reply")?;
+    match results.into_iter().find(|result| !result.success) {
+        Some(failed) => anyhow::bail!(
+            "sway error: {}",
+            failed.error.as_deref().unwrap_or("command failed")
+        ),
+        None => Ok(()),
+    }
+}
+
+/// Workspace name in double quotes for a command
+fn quote(name: &str) -> String {
+    format!("\"{}\"", name.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Windows in `tree`, with the workspace they are on
+fn tree_windows(tree: &Node) -> Vec<WindowInfo> {
+    fn walk(node: &Node, workspace: Option<u64>, windows: &mut Vec<WindowInfo>) {
+        let workspace = match node.kind.as_str() {
+            "workspace" if node.name.as_deref() == Some(SCRATCHPAD) => return,
+            "workspace" => Some(node.id),
+            _ => workspace,
+        };
+        if node.is_window() {
+            windows.push(WindowInfo {
+                workspace_id: workspace,
+                ..node.window_info()
+            });
+        }
+        for child in node.nodes.iter().chain(&node.floating_nodes) {
+            walk(child, workspace, windows);
+        }
+    }
+
+    let mut windows = Vec::new();
+    walk(tree, None, &mut windows);
+    windows
+}
+
+/// Event for a `window` event from sway, None for closed windows
+fn window_event(event: WindowEvent) -> Option<Event> {
+    match event.change.as_str() {
+        "close" => None,
+        "focus" => Some(Event::WindowFocusChanged {
+            id: Some(event.container.id),
+        }),
+        _ => Some(Event::WindowOpenedOrChanged {
+            window: event.container.window_info(),
+        }),
+    }
+}
+
+/// Node of sway's layout tree
+#[derive(Debug, Default, Deserialize)]
+struct Node {
+    id: u64,
+    #[serde(rename = "type", default)]
+    kind: String,
+    /// Window title, or the workspace / output name
+    #[serde(default)]
+    name: Option<String>,
+    /// Wayland app_id; None for XWayland windows
+    #[serde(default)]
+    app_id: Option<String>,
+    /// X11 properties of XWayland (and i3) windows
+    #[serde(default)]
+    window_properties: Option<WindowProperties>,
+    #[serde(default)]
+    pid: Option<i32>,
+    #[serde(default)]
+    focused: bool,
+    #[serde(default)]
+    nodes: Vec<Node>,
+    #[serde(default)]
+    floating_nodes: Vec<Node>,
+}
+
+impl Node {
+    /// Containers are windows when they hold no other containers
+    fn is_window(&self) -> bool {
+        matches!(self.kind.as_str(), "con" | "floating_con")
+            && self.nodes.is_empty()
+            && self.floating_nodes.is_empty()
+            && (self.app_id.is_some() || self.window_properties.is_some())
+    }
+
+    fn window_info(&self) -> WindowInfo {
+        let app_id = self
+            .app_id
+            .clone()
+            .or_else(|| self.window_properties.as_ref().and_then(|props| props.class.clone()))
+            .unwrap_or_default();
+        WindowInfo {
+            id: self.id,
+            app_id,
+            title: self.name.clone().unwrap_or_default(),
+            is_floating: self.kind == "floating_con",
+            is_focused: self.focused,
+            pid: self.pid,
+            workspace_id: None,
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct WindowProperties {
+    #[serde(default)]
+    class: Option<String>,
+}
+
+/// Workspace from `GET_WORKSPACES`
+#[derive(Debug, Deserialize)]
+struct SwayWorkspace {
+    id: u64,
+    /// Number at the start of the name; -1 if there is none
+    #[serde(default)]
+    num: i64,
+    name: String,
+    #[serde(default)]
+    output: Option<String>,
+    #[serde(default)]
+    visible: bool,
+    #[serde(default)]
+    focused: bool,
+}
+
+impl From<SwayWorkspace> for WorkspaceInfo {
+    fn from(ws: SwayWorkspace) -> Self {
+        // Numbered workspaces are named after their number
+        let name = (ws.name != ws.num.to_string()).then_some(ws.name);
+        WorkspaceInfo {
+            id: ws.id,
+            idx: ws.num.max(0) as u64,
+            name,
+            output: ws.output,
+            is_active: ws.visible,
+            is_focused: ws.focused,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct CommandResult {
+    success: bool,
+    #[serde(default)]
+    error: Option<String>,
+}
+
+/// Payload of a `window` event
+#[derive(Debug, Deserialize)]
+struct WindowEvent {
+    change: String,
+    container: Node,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+
+    #[test]
+    fn test_message_framing() {
+        let message = encode(RUN_COMMAND, "floating enable");
+        assert_eq!(&message[..6], b"i3-ipc");
+        assert_eq!(message.len(), HEADER_LEN + 15);
+
+        let header: [u8; HEADER_LEN] = message[..HEADER_LEN].try_into().unwrap();
+        assert_eq!(decode_header(&header).unwrap(), (15, RUN_COMMAND));
+        assert_eq!(&message[HEADER_LEN..], b"floating enable");
+
+        let mut bad = header;
+        bad[0] = b'x';
+        assert!(decode_header(&bad).is_err());
+    }
+
+    #[test]
+    fn test_command_result() {
+        let reply = serde_json::from_str(r#"[{"success":true}]"#).unwrap();
+        assert!(command_result(reply).is_ok());
+
+        let reply = serde_json::from_str(
+            r#"[{"success":true},{"success":false,"parse_error":false,"error":"No matching node."}]"#,
+        )
+        .unwrap();
+        assert_eq!(command_result(reply).unwrap_err().to_string(), "sway error: No matching node.");
+    }
+
+    #[test]
+    fn test_tree_windows() {
+        // Trimmed from `swaymsg -t get_tree`
+        let json = r#"{"id":1,"type":"root","name":"root","nodes":[
+            {"id":2147483647,"type":"output","name":"__i3","nodes":[
+                {"id":2147483646,"type":"workspace","name":"__i3_scratch","nodes":[],"floating_nodes":[
+                    {"id":20,"type":"floating_con","name":"scratch","app_id":"foot","pid":900,"nodes":[],"floating_nodes":[]}]}]},
+            {"id":3,"type":"output","name":"eDP-1","nodes":[
+                {"id":4,"type":"workspace","name":"1","nodes":[
+                    {"id":8,"type":"con","name":null,"layout":"splitv","nodes":[
+                        {"id":9,"type":"con","name":"Rust docs","app_id":"firefox","pid":1200,"focused":false,"nodes":[],"floating_nodes":[]},
+                        {"id":10,"type":"con","name":"Spotify","app_id":null,"window_properties":{"class":"Spotify","instance":"spotify"},"pid":1300,"nodes":[],"floating_nodes":[]}]}],
+                 "floating_nodes":[
+                    {"id":11,"type":"floating_con","name":"drun","app_id":"foot","pid":1400,"focused":true,"nodes":[],"floating_nodes":[]}]},
+                {"id":5,"type":"workspace","name":"2","nodes":[],"floating_nodes":[]}]}]}"#;
+        let tree: Node = serde_json::from_str(json).unwrap();
+        let windows = tree_windows(&tree);
+
+        let ids: Vec<u64> = windows.iter().map(|w| w.id).collect();
+        assert_eq!(ids, [9, 10, 11]);
+        assert_eq!(windows[0].app_id, "firefox");
+        assert_eq!(windows[0].workspace_id, Some(4));
+        // XWayland windows go by their class
+        assert_eq!(windows[1].app_id, "Spotify");
+        assert!(!windows[1].is_floating);
+        assert!(windows[2].is_floating && windows[2].is_focused);
+        assert_eq!(windows[2].pid, Some(1400));
+    }
+
+    #[test]
+    fn test_parse_workspaces() {
+        // Captured from `swaymsg -t get_workspaces --raw`
+        let json = r#"[{"id":4,"type":"workspace","name":"1","num":1,"output":"eDP-1","visible":true,"focused":true,"urgent":false},
+            {"id":6,"type":"workspace","name":"3: chat","num":3,"output":"DP-1","visible":true,"focused":false,"urgent":false},
+            {"id":7,"type":"workspace","name":"music","num":-1,"output":"DP-1","visible":false,"focused":false,"urgent":false}]"#;
+        let workspaces: Vec<SwayWorkspace> = serde_json::from_str(json).unwrap();
+        let workspaces: Vec<WorkspaceInfo> = workspaces.into_iter().map(WorkspaceInfo::from).collect();
+
+        assert_eq!((workspaces[0].id, workspaces[0].idx), (4, 1));
+        assert_eq!(workspaces[0].name, None);
+        assert!(workspaces[0].is_focused && workspaces[0].is_active);
+        assert_eq!(workspaces[1].name.as_deref(), Some("3: chat"));
+        assert_eq!(workspaces[1].output.as_deref(), Some("DP-1"));
+        assert!(workspaces[1].is_active && !workspaces[1].is_focused);
+        assert_eq!(workspaces[2].idx, 0);
+        assert_eq!(workspaces[2].name.as_deref(), Some("music"));
+    }
+
+    #[test]
+    fn test_window_events() {
+        let event = |json: &str| window_event(serde_json::from_str(json).unwrap());
+        match event(
+            r#"{"change":"new","container":{"id":12,"type":"con","name":"Mozilla Firefox","app_id":"firefox","pid":1500,"focused":false,"nodes":[],"floating_nodes":[]}}"#,
+        ) {
+            Some(Event::WindowOpenedOrChanged { window }) => {
+                assert_eq!(window.id, 12);
+                assert_eq!(window.app_id, "firefox");
+            }
+            other => panic!("unexpected event: {:?}", other),
+        }
+        assert!(matches!(
+            event(r#"{"change":"focus","container":{"id":12,"type":"con","nodes":[]}}"#),
+            Some(Event::WindowFocusChanged { id: Some(12) })
+        ));
+        assert!(event(r#"{"change":"close","container":{"id":12,"type":"con"}}"#).is_none());
+    }
+
+    #[test]
+    fn test_quote() {
+        assert_eq!(quote("3: chat"), r#""3: chat""#);
+        assert_eq!(quote(r#"say "hi""#), r#""say \"hi\"""#);
+    }
+
+    #[tokio::test]
+    async fn test_command_round_trip() {
+        let dir = TempDir::new("sway");
+        let path = dir.join("sway.sock");
+        let listener = tokio::net::UnixListener::bind(&path).unwrap();
+
+        // Answers one command like sway, handing back what it got
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let (kind, payload) = read_message(&mut stream).await.unwrap();
+            stream
+                .write_all(&encode(kind, r#"[{"success":true}]"#))
+                .await
+                .unwrap();
+            String::from_utf8(payload).unwrap()
+        });
+
+        let client = SwayClient::with_socket(path, Duration::from_secs(1));
+        Compositor::spawn(&client, vec!["/opt/My App/app".to_string(), "--new".to_string()])
+            .await
+            .unwrap();
+        assert_eq!(server.await.unwrap(), "exec '/opt/My App/app' --new");
+        assert_eq!(client.last_error(), None);
+    }
+}
@@ -0,0 +1,66 @@
+//! Character sets selectable with SCS (`ESC ( 0`, `ESC ) B`, ...)
+//!
+//! Programs from before Unicode line drawing designate the DEC special
+//! graphics set and then print ASCII letters: `lqqk` for the top of a box.
+//! The emulator keeps the G0 and G1 designations and translates printed
+//! characters through the one selected with SI / SO.
+
+/// A character set that can be designated as G0 or G1
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Charset {
+    #[default]
+    Ascii,
+    /// British: `#` is the pound sign
+    Uk,
+    /// DEC special graphics: lowercase letters and a few symbols are
+    /// line drawing characters
+    DecSpecialGraphics,
+}
+
+impl Charset {
+    /// `c` as printed in this character set
+    pub fn translate(self, c: char) -> char {
+        match self {
+            Charset::Ascii => c,
+            Charset::Uk => match c {
+                '#' => '£',
+                _ => c,
+            },
+            Charset::DecSpecialGraphics => match c {
+                '_' => ' ',
+                '`' => '◆',
+                'a' => '▒',
+                'b' => '␉',
+                'c' => '␌',
+                'd' => '␍',
+                'e' => '␊',
+                'f' => '°',
+                'g' => '±',
+                'h' => '␤',
+                'i' => '␋',
+                'j' => '┘',
+                'k' => '┐',
+                'l' => '┌',
+                'm' => '└',
+                'n' => '┼',
+                'o' => '⎺',
+                'p' => '⎻',
+                'q' => '─',
+                'r' => '⎼',
+                's' => '⎽',
+                't' => '├',
+                'u' => '┤',
+                'v' => '┴',
+                'w' => '┬',
+                'x' => '│',
+                'y' => '≤',
+                'z' => '≥',
+                '{' => 'π',
+                '|' => '≠',
+                '}' => '£',
+                '~' => '·',
+                _ => c,
+            },
+        }
+    }
+}
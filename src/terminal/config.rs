@@ -12,6 +12,9 @@ pub struct TerminalConfig {
     /// Whether to enable alternate screen buffer
     #[allow(dead_code)] // Config option for future use
     pub alternate_screen: bool,
+    /// Size of a character cell in pixels (width, height), if known;
+    /// without it pixel size queries (XTWINOPS 14/16) go unanswered
+    pub cell_pixels: Option<(usize, usize)>,
 }
 
 impl Default for TerminalConfig {
@@ -21,12 +24,13 @@ impl Default for TerminalConfig {
             rows: 24,
             scrollback: 10000,
             alternate_screen: true,
+            cell_pixels: None,
         }
     }
 }
 
 /// Cursor position (column, row)
-#[derive(Debug, Clone, Copy, Default)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
 pub struct CursorPosition {
     pub col: usize,
     pub row: usize,
@@ -38,3 +42,12 @@ impl CursorPosition {
         Self { col, row }
     }
 }
+
+/// Cursor shape requested by the child with DECSCUSR
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CursorShape {
+    #[default]
+    Block,
+    Underline,
+    Bar,
+}
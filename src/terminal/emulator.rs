@@ -3,69 +3,193 @@
 //! This module contains the main `EmbeddedTerminal` struct and its implementation,
 //! delegating escape sequence handling to the `escape_handlers` module.
 
+use std::collections::VecDeque;
+
 use termwiz::cell::{Cell, CellAttributes};
-use termwiz::color::ColorAttribute;
 use termwiz::input::{KeyCode, KeyCodeEncodeModes, KeyboardEncoding, Modifiers};
 use termwiz::escape::parser::Parser;
-use termwiz::surface::Surface;
+use termwiz::surface::{Line, Surface};
+
+use super::charset::Charset;
+use super::config::{CursorPosition, CursorShape, TerminalConfig};
+use super::reflow;
+use super::selection::{Selection, SelectionPoint};
 
-use super::config::{CursorPosition, TerminalConfig};
+/// Most text a child can copy with one OSC 52 sequence
+const MAX_CLIPBOARD_BYTES: usize = 1024 * 1024;
 
 /// Embedded terminal emulator
 pub struct EmbeddedTerminal {
     /// The terminal surface (screen buffer)
     surface: Surface,
+    /// Copy of the surface's rows, one cell per column, refreshed after
+    /// each change so drawing can borrow rows instead of rebuilding them
+    screen: Vec<Vec<Cell>>,
     /// Escape sequence parser
     parser: Parser,
     /// Configuration
     config: TerminalConfig,
     /// Current cursor position
     cursor: CursorPosition,
-    /// Scrollback buffer (lines that scrolled off top)
-    scrollback: Vec<Vec<Cell>>,
+    /// Cell of the character printed last, where zero-width characters
+    /// that follow go
+    last_printed: Option<LastPrinted>,
+    /// Scrollback buffer (lines that scrolled off top), oldest first and
+    /// without trailing blanks
+    scrollback: VecDeque<Vec<Cell>>,
     /// Scroll offset for viewing (0 = bottom)
     scroll_offset: usize,
     /// Whether in alternate screen mode
     in_alternate_screen: bool,
     /// Saved primary screen (when in alternate)
     saved_primary: Option<Surface>,
+    /// Alternate screen kept while the primary is shown, for mode 47
+    /// which switches back to it without clearing
+    saved_alternate: Option<Surface>,
     /// Whether to auto-scroll when new content arrives
     follow_mode: bool,
     /// Current text attributes (colors, bold, etc.)
     current_attrs: CellAttributes,
-    /// Saved cursor position (for save/restore)
-    saved_cursor: Option<CursorPosition>,
+    /// Character sets designated as G0 and G1
+    charsets: [Charset; 2],
+    /// Whether SO switched printing to G1 (SI switches back to G0)
+    shift_out: bool,
+    /// Cursor saved by DECSC / CSI s on the screen in use
+    saved_cursor: Option<SavedCursorState>,
+    /// The primary screen's saved cursor while the alternate screen is
+    /// shown; each screen has its own
+    primary_saved_cursor: Option<SavedCursorState>,
     /// Whether application cursor keys mode is enabled
     application_cursor_keys: bool,
+    /// Whether keypad keys send application sequences (DECKPAM)
+    application_keypad: bool,
     /// Whether newline mode is enabled
     newline_mode: bool,
     /// Keyboard encoding mode
     keyboard_encoding: KeyboardEncoding,
     /// Mouse reporting mode
-    mouse_reporting: bool,
+    mouse_tracking: MouseTracking,
+    /// How mouse reports are encoded
+    mouse_encoding: MouseEncoding,
+    /// Whether the child asked for pastes wrapped in ESC[200~ ... ESC[201~
+    bracketed_paste: bool,
+    /// Whether the child wants focus changes reported (mode 1004)
+    focus_reporting: bool,
+    /// Replies to queries (cursor position, device attributes) waiting to
+    /// be written back to the child
+    responses: Vec<u8>,
+    /// Text the child copied with OSC 52, waiting to be put on the
+    /// clipboard
+    clipboard: Option<String>,
+    /// Window title set by the child (OSC 0/2)
+    title: Option<String>,
+    /// Text selected for copying
+    selection: Option<Selection>,
+    /// Scrolling region set by DECSTBM: first and last row (0-based,
+    /// inclusive); None scrolls the whole screen
+    margins: Option<(usize, usize)>,
+    /// Tab stops, one flag per column (set by HTS, cleared by TBC)
+    tab_stops: Vec<bool>,
+    /// Whether printing past the right margin wraps to the next line
+    /// (DECAWM); when off, the last column is overwritten instead
+    autowrap: bool,
+    /// Whether the cursor is shown (DECTCEM)
+    cursor_visible: bool,
+    /// Cursor shape and blinking requested with DECSCUSR
+    cursor_shape: CursorShape,
+    cursor_blink: bool,
+}
+
+/// Cursor state saved by DECSC and restored by DECRC
+///
+/// Besides the position this holds the SGR attributes, so a prompt that
+/// saves, recolors and restores doesn't leak its colors into later output,
+/// and the character sets. There is no pending-wrap flag or origin mode to
+/// save: printing wraps as soon as the last column is written, and DECOM
+/// isn't supported.
+#[derive(Debug, Clone)]
+struct SavedCursorState {
+    pos: CursorPosition,
+    attrs: CellAttributes,
+    charsets: [Charset; 2],
+    shift_out: bool,
+}
+
+/// Where the character printed last went
+#[derive(Debug, Clone, Copy)]
+struct LastPrinted {
+    cell: CursorPosition,
+    /// The cursor right after printing it; once the cursor moves on,
+    /// zero-width characters no longer belong to it
+    after: CursorPosition,
+}
+
+/// Which mouse events the child asked for (DEC private modes 1000-1003)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum MouseTracking {
+    Off,
+    /// Button presses, releases and the wheel (1000, 1001)
+    Click,
+    /// Also motion while a button is held (1002)
+    Drag,
+    /// Also motion without a button (1003)
+    Motion,
+}
+
+/// How the child wants mouse reports encoded (DEC private modes 1005, 1006)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MouseEncoding {
+    /// `CSI M` and three bytes offset by 32, for coordinates up to 223
+    X10,
+    /// Like X10 with coordinates as UTF-8 characters, up to 2015 (1005)
+    Utf8,
+    /// `CSI < b;x;y M`, any coordinates, releases tell the button (1006)
+    Sgr,
 }
 
 impl EmbeddedTerminal {
     /// Create a new embedded terminal
     pub fn new(config: TerminalConfig) -> Self {
         let surface = Surface::new(config.cols, config.rows);
+        let screen = vec![vec![Cell::default(); config.cols]; config.rows];
+        let tab_stops = default_tab_stops(config.cols);
 
         Self {
             surface,
+            screen,
             parser: Parser::new(),
             config,
             cursor: CursorPosition::default(),
-            scrollback: Vec::new(),
+            last_printed: None,
+            scrollback: VecDeque::new(),
             scroll_offset: 0,
             in_alternate_screen: false,
             saved_primary: None,
+            saved_alternate: None,
             follow_mode: true,
             current_attrs: CellAttributes::default(),
+            charsets: [Charset::Ascii; 2],
+            shift_out: false,
             saved_cursor: None,
+            primary_saved_cursor: None,
             application_cursor_keys: false,
+            application_keypad: false,
             newline_mode: false,
             keyboard_encoding: KeyboardEncoding::Xterm,
-            mouse_reporting: false,
+            mouse_tracking: MouseTracking::Off,
+            mouse_encoding: MouseEncoding::X10,
+            bracketed_paste: false,
+            focus_reporting: false,
+            responses: Vec::new(),
+            clipboard: None,
+            title: None,
+            selection: None,
+            margins: None,
+            tab_stops,
+            autowrap: true,
+            cursor_visible: true,
+            cursor_shape: CursorShape::Block,
+            cursor_blink: true,
         }
     }
 
@@ -78,10 +202,44 @@ impl EmbeddedTerminal {
     // ========== Basic Accessors ==========
 
     /// Resize the terminal
+    ///
+    /// The screen and scrollback are re-wrapped to the new width. Shrinking
+    /// the height moves rows above the cursor into the scrollback, growing
+    /// it brings them back.
     pub fn resize(&mut self, cols: usize, rows: usize) {
+        if (cols, rows) == (self.config.cols, self.config.rows) {
+            return;
+        }
         self.config.cols = cols;
         self.config.rows = rows;
-        self.surface.resize(cols, rows);
+        if self.in_alternate_screen {
+            // Full-screen programs redraw themselves on SIGWINCH; only the
+            // primary screen underneath is re-wrapped
+            self.surface.resize(cols, rows);
+            self.cursor.row = self.cursor.row.min(rows.saturating_sub(1));
+            self.cursor.col = self.cursor.col.min(cols.saturating_sub(1));
+            if let Some(primary) = self.saved_primary.take() {
+                self.saved_primary = Some(self.reflow_screen(&primary, None).0);
+            }
+        } else {
+            if let Some(ref mut alternate) = self.saved_alternate {
+                alternate.resize(cols, rows);
+            }
+            let screen = std::mem::replace(&mut self.surface, Surface::new(cols, rows));
+            let (surface, cursor) = self.reflow_screen(&screen, Some(self.cursor));
+            self.surface = surface;
+            self.cursor = cursor.unwrap_or_default();
+        }
+        self.scroll_offset = self.scroll_offset.min(self.scrollback.len());
+        // Rewrapping moves the text out from under the selection
+        self.selection = None;
+        self.margins = None;
+        // Keep the stops that still fit; new columns get the defaults
+        let old_cols = self.tab_stops.len();
+        self.tab_stops.truncate(cols);
+        self.tab_stops
+            .extend(default_tab_stops(cols).into_iter().skip(old_cols));
+        self.sync_screen();
     }
 
     /// Get terminal dimensions
@@ -89,6 +247,27 @@ impl EmbeddedTerminal {
         (self.config.cols, self.config.rows)
     }
 
+    /// Window title the child set last, if any
+    pub fn title(&self) -> Option<&str> {
+        self.title.as_deref()
+    }
+
+    /// Whether the child wants the cursor shown
+    pub fn cursor_visible(&self) -> bool {
+        self.cursor_visible
+    }
+
+    /// Cursor shape requested by the child
+    pub fn cursor_shape(&self) -> CursorShape {
+        self.cursor_shape
+    }
+
+    /// Whether the child asked for a blinking cursor
+    #[allow(dead_code)] // Public API for future use
+    pub fn cursor_blink(&self) -> bool {
+        self.cursor_blink
+    }
+
     /// Get a reference to the surface
     #[allow(dead_code)] // Public API for future use
     pub fn surface(&self) -> &Surface {
@@ -107,7 +286,7 @@ impl EmbeddedTerminal {
     }
 
     /// Get the scrollback buffer
-    pub fn scrollback(&self) -> &[Vec<Cell>] {
+    pub fn scrollback(&self) -> &VecDeque<Vec<Cell>> {
         &self.scrollback
     }
 
@@ -117,12 +296,6 @@ impl EmbeddedTerminal {
         self.in_alternate_screen
     }
 
-    /// Get mutable reference to surface
-    #[allow(dead_code)] // Public API for future use
-    pub fn surface_mut(&mut self) -> &mut Surface {
-        &mut self.surface
-    }
-
     /// Get current attributes (for tests)
     #[cfg(test)]
     pub(crate) fn current_attrs(&self) -> &CellAttributes {
@@ -131,52 +304,145 @@ impl EmbeddedTerminal {
 
     // ========== Scrollback Management ==========
 
+    /// Re-wrap the scrollback and `screen` (still at the old size) to the
+    /// current size, returning the new screen and where `cursor` moved
+    ///
+    /// The new screen shows the last rows, moved up as far as needed to
+    /// keep the cursor on it; rows above it go to the scrollback.
+    fn reflow_screen(
+        &mut self,
+        screen: &Surface,
+        cursor: Option<CursorPosition>,
+    ) -> (Surface, Option<CursorPosition>) {
+        let (cols, rows) = (self.config.cols, self.config.rows);
+        let (old_cols, _) = screen.dimensions();
+        let screen_rows: Vec<Vec<Cell>> = screen
+            .screen_lines()
+            .iter()
+            .map(|line| line_cells(line, old_cols))
+            .collect();
+        // Blank rows below the output and the cursor are not part of it
+        let used = screen_rows
+            .iter()
+            .rposition(|row| !row.iter().all(reflow::is_blank))
+            .map_or(0, |row| row + 1)
+            .max(cursor.map_or(0, |c| c.row + 1));
+
+        let scrollback_len = self.scrollback.len();
+        let mut all = Vec::from(std::mem::take(&mut self.scrollback));
+        all.extend(screen_rows.into_iter().take(used));
+        let cursor = cursor.map(|c| (scrollback_len + c.row, c.col));
+        let (mut all, cursor) = reflow::reflow(all, cols, cursor);
+
+        let mut top = all.len().saturating_sub(rows);
+        if let Some((row, _)) = cursor {
+            top = top.min(row);
+        }
+        let visible = all.split_off(top);
+        let mut surface = Surface::new(cols, rows);
+        for (dst, src) in surface.screen_cells().into_iter().zip(visible) {
+            let n = dst.len().min(src.len());
+            dst[..n].clone_from_slice(&src[..n]);
+        }
+
+        let excess = all.len().saturating_sub(self.config.scrollback);
+        self.scrollback = all.into_iter().skip(excess).map(trim_row).collect();
+
+        let cursor = cursor.map(|(row, col)| CursorPosition {
+            row: row - top,
+            col: col.min(cols.saturating_sub(1)),
+        });
+        (surface, cursor)
+    }
+
     /// Add a line to scrollback
     fn push_to_scrollback(&mut self, line: Vec<Cell>) {
-        self.scrollback.push(line);
+        self.scrollback.push_back(trim_row(line));
 
         // Enforce max scrollback
         while self.scrollback.len() > self.config.scrollback {
-            self.scrollback.remove(0);
+            self.scrollback.pop_front();
         }
     }
 
-    /// Scroll the screen up by n lines, saving to scrollback
-    pub fn scroll_screen_up(&mut self, n: usize) {
-        use termwiz::surface::{Change, Position};
+    /// First and last row (inclusive) of the scrolling region
+    fn scroll_region(&self) -> (usize, usize) {
+        let last = self.config.rows.saturating_sub(1);
+        match self.margins {
+            Some((top, bottom)) => (top.min(last), bottom.min(last)),
+            None => (0, last),
+        }
+    }
 
+    /// Scroll the scrolling region up by n lines
+    ///
+    /// Lines scrolled off the top go to scrollback only when the region is
+    /// the whole primary screen; a status line or footer kept out of the
+    /// region by DECSTBM must not be copied into the history, and neither
+    /// must the frames of a full-screen program.
+    fn scroll_screen_up(&mut self, n: usize) {
+        use termwiz::surface::Change;
+
+        let (top, bottom) = self.scroll_region();
+        let full_screen = self.margins.is_none() && !self.in_alternate_screen;
         for _ in 0..n {
             // Save top line to scrollback
             let lines = self.surface.screen_lines();
-            if let Some(top_line) = lines.first() {
-                let cells: Vec<Cell> = (0..self.config.cols)
-                    .map(|i| {
-                        top_line
-                            .get_cell(i)
-                            .map(|cr| cr.as_cell())
-                            .unwrap_or_default()
-                    })
-                    .collect();
+            if let (true, Some(top_line)) = (full_screen, lines.first()) {
+                let cells = line_cells(top_line, self.config.cols);
                 self.push_to_scrollback(cells);
             }
 
             // Use termwiz's scroll region to scroll up
             self.surface.add_change(Change::ScrollRegionUp {
-                first_row: 0,
-                region_size: self.config.rows,
+                first_row: top,
+                region_size: bottom + 1 - top,
                 scroll_count: 1,
             });
 
             // Clear the bottom line
+            self.clear_rows(bottom..bottom + 1);
+
+            // The character printed last moves up with its row
+            if let Some(ref mut last) = self.last_printed {
+                if (top..=bottom).contains(&last.cell.row) {
+                    self.last_printed = last.cell.row.checked_sub(1).filter(|&row| row >= top).map(|row| {
+                        LastPrinted {
+                            cell: CursorPosition { row, ..last.cell },
+                            after: last.after,
+                        }
+                    });
+                }
+            }
+        }
+    }
+
+    /// Blank `rows` of the screen in the current background
+    ///
+    /// Erased, inserted and scrolled-in cells take the background of the
+    /// current SGR attributes (background color erase), so full-screen
+    /// programs with a colored background don't leave default stripes.
+    fn clear_rows(&mut self, rows: std::ops::Range<usize>) {
+        use termwiz::surface::{Change, Position};
+
+        let background = self.current_attrs.background();
+        for y in rows {
             self.surface.add_change(Change::CursorPosition {
                 x: Position::Absolute(0),
-                y: Position::Absolute(self.config.rows - 1),
+                y: Position::Absolute(y),
             });
-            self.surface
-                .add_change(Change::ClearToEndOfLine(ColorAttribute::Default));
+            self.surface.add_change(Change::ClearToEndOfLine(background));
         }
     }
 
+    /// A blank cell for erasing: default attributes except the current
+    /// background
+    fn blank_cell(&self) -> Cell {
+        let mut attrs = CellAttributes::default();
+        attrs.set_background(self.current_attrs.background());
+        Cell::new(' ', attrs)
+    }
+
     /// Get total scrollable lines (scrollback + visible)
     pub fn total_lines(&self) -> usize {
         self.scrollback.len() + self.config.rows
@@ -221,79 +487,138 @@ impl EmbeddedTerminal {
     // ========== Visible Content Retrieval ==========
 
     /// Get a row of cells for rendering
-    /// row 0 is the top of the viewport
-    pub fn get_row(&self, viewport_row: usize) -> Vec<Cell> {
-        let total_scrollback = self.scrollback.len();
-
-        // Calculate which actual row this maps to
-        let actual_row = if self.scroll_offset > 0 {
-            // Scrolled up - may be in scrollback
-            let scrollback_row = total_scrollback.saturating_sub(self.scroll_offset) + viewport_row;
-
-            if scrollback_row < total_scrollback {
-                // In scrollback buffer
-                return self.scrollback[scrollback_row].clone();
-            } else {
-                // In visible surface
-                scrollback_row - total_scrollback
+    /// row 0 is the top of the viewport; rows past the screen are empty
+    pub fn get_row(&self, viewport_row: usize) -> &[Cell] {
+        self.line(self.viewport_top_line() + viewport_row)
+            .unwrap_or_default()
+    }
+
+    /// Get a line by absolute index (0 = oldest scrollback line, the
+    /// surface follows the scrollback)
+    ///
+    /// Scrollback lines end at their last non-blank cell, so they can be
+    /// shorter than the terminal is wide.
+    pub fn line(&self, index: usize) -> Option<&[Cell]> {
+        match index.checked_sub(self.scrollback.len()) {
+            None => Some(&self.scrollback[index]),
+            Some(row) => self.screen.get(row).map(Vec::as_slice),
+        }
+    }
+
+    /// Copy the surface's rows into `screen` after they changed
+    fn sync_screen(&mut self) {
+        let cols = self.config.cols;
+        self.screen = self
+            .surface
+            .screen_lines()
+            .iter()
+            .map(|line| line_cells(line, cols))
+            .collect();
+        // Nothing reads the surface's change log
+        self.surface
+            .flush_changes_older_than(self.surface.current_seqno());
+    }
+
+    /// Absolute index of the line shown in the top viewport row
+    pub fn viewport_top_line(&self) -> usize {
+        self.scrollback.len().saturating_sub(self.scroll_offset)
+    }
+
+    /// Scroll so that absolute line `index` is visible (centered when
+    /// possible) and stop following new output
+    pub fn reveal_line(&mut self, index: usize) {
+        let top = index.saturating_sub(self.config.rows / 2);
+        self.scroll_offset = self.scrollback.len().saturating_sub(top);
+        self.follow_mode = false;
+    }
+
+    /// Scroll as little as needed to show line `index`
+    pub fn scroll_line_into_view(&mut self, index: usize) {
+        let top = self.viewport_top_line();
+        if index < top {
+            self.set_scroll_offset(self.scrollback.len() - index);
+        } else if index >= top + self.config.rows {
+            let top = (index + 1).saturating_sub(self.config.rows);
+            self.set_scroll_offset(self.scrollback.len().saturating_sub(top));
+            if self.scroll_offset == 0 {
+                self.follow_mode = true;
             }
-        } else {
-            // At bottom - showing current surface
-            viewport_row
-        };
+        }
+    }
 
-        // Get from surface using screen_lines()
-        let lines = self.surface.screen_lines();
-        if actual_row < lines.len() {
-            (0..self.config.cols)
-                .map(|i| {
-                    lines[actual_row]
-                        .get_cell(i)
-                        .map(|cr| cr.as_cell())
-                        .unwrap_or_default()
-                })
-                .collect()
-        } else {
-            // Empty row
-            vec![Cell::default(); self.config.cols]
+    // ========== Selection ==========
+
+    /// Clamp `point` to the existing lines and columns
+    fn clamp_point(&self, point: SelectionPoint) -> SelectionPoint {
+        SelectionPoint {
+            line: point.line.min(self.total_lines().saturating_sub(1)),
+            col: point.col.min(self.config.cols.saturating_sub(1)),
+        }
+    }
+
+    /// Start a selection at `point`, replacing any previous one
+    pub fn start_selection(&mut self, point: SelectionPoint) {
+        self.selection = Some(Selection::new(self.clamp_point(point)));
+    }
+
+    /// Move the moving end of the selection to `point`
+    pub fn extend_selection(&mut self, point: SelectionPoint) {
+        let point = self.clamp_point(point);
+        if let Some(ref mut selection) = self.selection {
+            selection.head = point;
+        }
+    }
+
+    /// Drop the selection
+    pub fn clear_selection(&mut self) {
+        self.selection = None;
+    }
+
+    /// Current selection, if any
+    pub fn selection(&self) -> Option<&Selection> {
+        self.selection.as_ref()
+    }
+
+    /// Text of the selection
+    ///
+    /// Each row becomes a line with trailing blanks trimmed, except rows
+    /// that wrapped at the right margin, which are joined with the next.
+    pub fn selected_text(&self) -> Option<String> {
+        let selection = self.selection?;
+        let (start, end) = selection.ordered();
+        let mut text = String::new();
+        for index in start.line..=end.line {
+            let Some(cells) = self.line(index) else {
+                break;
+            };
+            if let Some(mut cols) = selection.cols_in_line(index, cells.len()) {
+                // Starting on the right half of a wide character takes
+                // the whole character
+                if cols.start > 0 && cells[cols.start - 1].width() == 2 {
+                    cols.start -= 1;
+                }
+                text.push_str(&row_text(&cells[cols]));
+            }
+            if index != end.line && !reflow::is_wrapped(cells) {
+                text.push('\n');
+            }
         }
+        Some(text)
     }
 
     /// Get all visible rows
     #[allow(dead_code)] // Used in tests
-    pub fn get_visible_rows(&self) -> Vec<Vec<Cell>> {
+    pub fn get_visible_rows(&self) -> Vec<&[Cell]> {
         (0..self.config.rows).map(|row| self.get_row(row)).collect()
     }
 
     /// Get all terminal content as text (scrollback + visible)
     pub fn content_as_text(&self) -> String {
-        let mut lines = Vec::new();
-
-        // Scrollback lines
-        for row in &self.scrollback {
-            let line: String = row
-                .iter()
-                .map(|cell| cell.str())
-                .collect::<String>()
-                .trim_end()
-                .to_string();
-            lines.push(line);
-        }
-
-        // Visible rows
-        let screen_lines = self.surface.screen_lines();
-        for line in screen_lines {
-            let text: String = (0..self.config.cols)
-                .map(|i| {
-                    line.get_cell(i)
-                        .map(|cr| cr.as_cell().str().to_string())
-                        .unwrap_or_default()
-                })
-                .collect::<String>()
-                .trim_end()
-                .to_string();
-            lines.push(text);
-        }
+        // Scrollback lines, then visible rows
+        let mut lines: Vec<String> = (0..self.total_lines())
+            .filter_map(|index| self.line(index))
+            .map(row_text)
+            .collect();
 
         // Trim trailing empty lines
         while lines.last().map(|s| s.is_empty()).unwrap_or(false) {
@@ -303,6 +628,22 @@ impl EmbeddedTerminal {
         lines.join("\n")
     }
 
+    /// URIs of the OSC 8 hyperlinks in the scrollback and on screen, in
+    /// order of first appearance and without duplicates
+    pub fn hyperlinks(&self) -> Vec<String> {
+        let mut links: Vec<String> = Vec::new();
+        for cells in (0..self.total_lines()).filter_map(|index| self.line(index)) {
+            for cell in cells {
+                if let Some(link) = cell.attrs().hyperlink() {
+                    if !links.iter().any(|l| l == link.uri()) {
+                        links.push(link.uri().to_string());
+                    }
+                }
+            }
+        }
+        links
+    }
+
     // ========== Follow Mode ==========
 
     /// Enable/disable follow mode
@@ -332,8 +673,28 @@ impl EmbeddedTerminal {
         self.scroll_offset = 0;
         self.cursor = CursorPosition::default();
         self.current_attrs = CellAttributes::default();
+        self.charsets = [Charset::Ascii; 2];
+        self.shift_out = false;
+        self.saved_cursor = None;
+        self.primary_saved_cursor = None;
+        self.saved_alternate = None;
         // Clear surface by recreating it
         self.surface = Surface::new(self.config.cols, self.config.rows);
+        self.sync_screen();
+        // Modes a previous command may have left on
+        self.margins = None;
+        self.title = None;
+        self.selection = None;
+        self.tab_stops = default_tab_stops(self.config.cols);
+        self.autowrap = true;
+        self.cursor_visible = true;
+        self.cursor_shape = CursorShape::Block;
+        self.cursor_blink = true;
+        self.mouse_tracking = MouseTracking::Off;
+        self.mouse_encoding = MouseEncoding::X10;
+        self.bracketed_paste = false;
+        self.focus_reporting = false;
+        self.application_keypad = false;
     }
 
     // ========== Input Handling ==========
@@ -351,10 +712,84 @@ impl EmbeddedTerminal {
         key.encode(modifiers, modes, true).unwrap_or_default()
     }
 
-    /// Check if mouse reporting is enabled
+    /// Encode a key typed on the numeric keypad for sending to the PTY
+    ///
+    /// In application keypad mode (DECKPAM) the digits, operators and
+    /// Enter send SS3 sequences, so programs can tell them from the main
+    /// keyboard. With NumLock off the keypad reports navigation keys, which
+    /// are encoded like their main keyboard counterparts, as is everything
+    /// in normal keypad mode.
+    pub fn encode_keypad_key(&self, key: KeyCode, modifiers: Modifiers) -> String {
+        if !self.application_keypad {
+            return self.encode_key(key, modifiers);
+        }
+        let code = match key {
+            KeyCode::Char(c @ '0'..='9') => (b'p' + (c as u8 - b'0')) as char,
+            KeyCode::Char('*') => 'j',
+            KeyCode::Char('+') => 'k',
+            KeyCode::Char(',') => 'l',
+            KeyCode::Char('-') => 'm',
+            KeyCode::Char('.') => 'n',
+            KeyCode::Char('/') => 'o',
+            KeyCode::Char('=') => 'X',
+            KeyCode::Enter => 'M',
+            _ => return self.encode_key(key, modifiers),
+        };
+        format!("\x1bO{}", code)
+    }
+
+    /// Encode pasted text for sending to the PTY
+    ///
+    /// Line breaks become CR, as typed Enter would. If the child enabled
+    /// bracketed paste (mode 2004) the text is wrapped in ESC[200~ ...
+    /// ESC[201~, so a REPL can tell it apart from typing and doesn't run
+    /// each line as it arrives.
+    pub fn encode_paste(&self, text: &str) -> String {
+        let text = text.replace("\r\n", "\r").replace('\n', "\r");
+        if self.bracketed_paste {
+            // A pasted end marker would end the paste early
+            let text = text.replace("\x1b[201~", "");
+            format!("\x1b[200~{}\x1b[201~", text)
+        } else {
+            text
+        }
+    }
+
+    /// Check if bracketed paste mode is enabled
     #[allow(dead_code)] // Public API for future use
+    pub fn bracketed_paste(&self) -> bool {
+        self.bracketed_paste
+    }
+
+    /// Check if focus reporting is enabled
+    #[allow(dead_code)] // Public API for future use
+    pub fn focus_reporting(&self) -> bool {
+        self.focus_reporting
+    }
+
+    /// Encode gaining (`CSI I`) or losing (`CSI O`) focus, if the child
+    /// asked for focus reports; editors use them to reload changed files
+    pub fn encode_focus(&self, focused: bool) -> Option<&'static str> {
+        match (self.focus_reporting, focused) {
+            (false, _) => None,
+            (true, true) => Some("\x1b[I"),
+            (true, false) => Some("\x1b[O"),
+        }
+    }
+
+    /// Check if mouse reporting is enabled
     pub fn mouse_enabled(&self) -> bool {
-        self.mouse_reporting
+        self.mouse_tracking != MouseTracking::Off
+    }
+
+    /// Mouse events the child asked for
+    pub fn mouse_tracking(&self) -> MouseTracking {
+        self.mouse_tracking
+    }
+
+    /// How the child wants mouse events encoded
+    pub fn mouse_encoding(&self) -> MouseEncoding {
+        self.mouse_encoding
     }
 
     /// Check if application cursor keys mode is enabled
@@ -363,6 +798,12 @@ impl EmbeddedTerminal {
         self.application_cursor_keys
     }
 
+    /// Check if application keypad mode is enabled
+    #[cfg(test)]
+    pub fn application_keypad(&self) -> bool {
+        self.application_keypad
+    }
+
     // ========== Escape Sequence Processing ==========
 
     /// Process raw bytes from PTY
@@ -372,11 +813,32 @@ impl EmbeddedTerminal {
         for action in actions {
             self.handle_action(action);
         }
+        self.sync_screen();
 
         // Notify that content was added
         self.on_content_added();
     }
 
+    /// Take the replies to queries seen by `write`, for sending to the PTY
+    ///
+    /// Programs that ask for the cursor position (`CSI 6n`) or device
+    /// attributes (`CSI c`) wait for the answer, so these must be written
+    /// back after every `write`.
+    pub fn take_responses(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.responses)
+    }
+
+    /// Take the text the child last copied with OSC 52, if any
+    pub fn take_clipboard(&mut self) -> Option<String> {
+        self.clipboard.take()
+    }
+
+    /// Queue a reply to the child (`body` follows the CSI)
+    fn respond(&mut self, body: &str) {
+        self.responses.extend_from_slice(b"\x1b[");
+        self.responses.extend_from_slice(body.as_bytes());
+    }
+
     /// Handle a parsed action by delegating to EscapeHandler
     fn handle_action(&mut self, action: termwiz::escape::Action) {
         // Check for full reset first (needs special handling)
@@ -425,6 +887,29 @@ impl EmbeddedTerminal {
 
     fn print_char(&mut self, c: char) {
         use termwiz::surface::{Change, Position};
+        use unicode_width::UnicodeWidthChar;
+
+        let c = self.charsets[usize::from(self.shift_out)].translate(c);
+
+        // Combining marks and other zero-width characters don't take a
+        // cell of their own
+        let width = c.width().unwrap_or(0);
+        if width == 0 {
+            self.combine(c);
+            return;
+        }
+
+        // A wide character that doesn't fit on the line wraps first, or
+        // without autowrap goes in the last cells
+        if self.cursor.col + width > self.config.cols && self.cursor.col > 0 {
+            if self.autowrap {
+                self.mark_wrapped();
+                self.cursor.col = 0;
+                self.newline();
+            } else {
+                self.cursor.col = self.config.cols.saturating_sub(width);
+            }
+        }
 
         self.surface.add_change(Change::CursorPosition {
             x: Position::Absolute(self.cursor.col),
@@ -432,22 +917,106 @@ impl EmbeddedTerminal {
         });
         self.surface
             .add_change(Change::AllAttributes(self.current_attrs.clone()));
+        // The surface marks the cell after a wide character as its
+        // continuation
         self.surface.add_change(Change::Text(c.to_string()));
+        self.last_printed = Some(LastPrinted {
+            cell: self.cursor,
+            after: self.cursor,
+        });
 
-        self.cursor.col += 1;
+        self.cursor.col += width;
 
         if self.cursor.col >= self.config.cols {
-            self.cursor.col = 0;
-            self.newline();
+            if self.autowrap {
+                self.mark_wrapped();
+                self.cursor.col = 0;
+                self.newline();
+            } else {
+                // The next character overwrites the last column
+                self.cursor.col = self.config.cols - 1;
+            }
+        }
+        if let Some(ref mut last) = self.last_printed {
+            last.after = self.cursor;
+        }
+    }
+
+    /// Add a zero-width character (combining mark, variation selector) to
+    /// the character printed last, which it modifies
+    ///
+    /// Dropped if the cursor moved since.
+    fn combine(&mut self, c: char) {
+        use termwiz::surface::{Change, Position};
+
+        let Some(last) = self.last_printed.filter(|last| last.after == self.cursor) else {
+            return;
+        };
+        let (text, attrs) = {
+            let mut lines = self.surface.screen_cells();
+            let Some(cell) = lines.get_mut(last.cell.row).and_then(|line| line.get(last.cell.col)) else {
+                return;
+            };
+            (format!("{}{}", cell.str(), c), cell.attrs().clone())
+        };
+        self.surface.add_change(Change::CursorPosition {
+            x: Position::Absolute(last.cell.col),
+            y: Position::Absolute(last.cell.row),
+        });
+        self.surface.add_change(Change::AllAttributes(attrs));
+        self.surface.add_change(Change::Text(text));
+        self.surface
+            .add_change(Change::AllAttributes(self.current_attrs.clone()));
+    }
+
+    /// Save the cursor position and attributes (DECSC)
+    fn save_cursor(&mut self) {
+        self.saved_cursor = Some(SavedCursorState {
+            pos: self.cursor,
+            attrs: self.current_attrs.clone(),
+            charsets: self.charsets,
+            shift_out: self.shift_out,
+        });
+    }
+
+    /// Restore what `save_cursor` saved (DECRC); the position is clamped in
+    /// case the terminal shrank since
+    fn restore_cursor(&mut self) {
+        if let Some(ref saved) = self.saved_cursor {
+            self.cursor = CursorPosition {
+                col: saved.pos.col.min(self.config.cols.saturating_sub(1)),
+                row: saved.pos.row.min(self.config.rows.saturating_sub(1)),
+            };
+            self.current_attrs = saved.attrs.clone();
+            self.charsets = saved.charsets;
+            self.shift_out = saved.shift_out;
+        }
+    }
+
+    /// Mark the cursor row as continuing on the next row, for reflow
+    fn mark_wrapped(&mut self) {
+        let row = self.cursor.row;
+        let last = self
+            .surface
+            .screen_cells()
+            .into_iter()
+            .nth(row)
+            .and_then(reflow::last_char_mut);
+        if let Some(cell) = last {
+            cell.attrs_mut().set_wrapped(true);
         }
     }
 
     fn newline(&mut self) {
-        self.cursor.row += 1;
+        let (_, bottom) = self.scroll_region();
 
-        if self.cursor.row >= self.config.rows {
+        if self.cursor.row == bottom {
+            // At the bottom margin: scroll the region, the cursor stays
             self.scroll_screen_up(1);
-            self.cursor.row = self.config.rows - 1;
+        } else if self.cursor.row + 1 < self.config.rows {
+            // Below the region the cursor moves down to the last row, but
+            // never scrolls
+            self.cursor.row += 1;
         }
     }
 
@@ -461,10 +1030,7 @@ impl EmbeddedTerminal {
                 self.cursor.col = self.cursor.col.saturating_sub(1);
             }
             ControlCode::HorizontalTab => {
-                self.cursor.col = ((self.cursor.col / 8) + 1) * 8;
-                if self.cursor.col >= self.config.cols {
-                    self.cursor.col = self.config.cols - 1;
-                }
+                self.cursor.col = self.next_tab_stop(self.cursor.col);
             }
             ControlCode::LineFeed | ControlCode::VerticalTab | ControlCode::FormFeed => {
                 self.newline();
@@ -472,10 +1038,28 @@ impl EmbeddedTerminal {
             ControlCode::CarriageReturn => {
                 self.cursor.col = 0;
             }
+            ControlCode::ShiftOut => self.shift_out = true,
+            ControlCode::ShiftIn => self.shift_out = false,
             _ => {}
         }
     }
 
+    /// Column of the first tab stop after `col`, or the last column
+    fn next_tab_stop(&self, col: usize) -> usize {
+        let last = self.config.cols.saturating_sub(1);
+        (col + 1..last)
+            .find(|&c| self.tab_stops.get(c).copied().unwrap_or(false))
+            .unwrap_or(last)
+    }
+
+    /// Column of the last tab stop before `col`, or the first column
+    fn previous_tab_stop(&self, col: usize) -> usize {
+        (1..col)
+            .rev()
+            .find(|&c| self.tab_stops.get(c).copied().unwrap_or(false))
+            .unwrap_or(0)
+    }
+
     fn handle_csi(&mut self, csi: termwiz::escape::csi::CSI) {
         use termwiz::escape::csi::CSI;
 
@@ -484,15 +1068,58 @@ impl EmbeddedTerminal {
             CSI::Edit(op) => self.handle_edit(op),
             CSI::Sgr(sgr) => self.handle_sgr(sgr),
             CSI::Mode(mode) => self.handle_mode(mode),
-            CSI::Device(_) | CSI::Window(_) => {}
+            CSI::Device(device) => self.handle_device(*device),
+            CSI::Window(window) => self.handle_window(*window),
             _ => {
                 tracing::debug!("Unhandled CSI: {:?}", csi);
             }
         }
     }
 
+    fn handle_device(&mut self, device: termwiz::escape::csi::Device) {
+        use termwiz::escape::csi::Device;
+
+        match device {
+            // DA1: a VT220 with ANSI color
+            Device::RequestPrimaryDeviceAttributes => self.respond("?62;22c"),
+            // DA2: terminal type 1 (VT220), firmware version 0
+            Device::RequestSecondaryDeviceAttributes => self.respond(">1;0;0c"),
+            // DSR 5: "terminal OK"
+            Device::StatusReport => self.respond("0n"),
+            _ => {
+                tracing::debug!("Unhandled device request: {:?}", device);
+            }
+        }
+    }
+
+    /// Answer XTWINOPS size reports; the window manipulations are ignored
+    fn handle_window(&mut self, window: termwiz::escape::csi::Window) {
+        use termwiz::escape::csi::Window;
+
+        let (cols, rows) = (self.config.cols, self.config.rows);
+        match window {
+            // 18t / 19t: text area and screen size in characters
+            Window::ReportTextAreaSizeCells => self.respond(&format!("8;{};{}t", rows, cols)),
+            Window::ReportScreenSizeCells => self.respond(&format!("9;{};{}t", rows, cols)),
+            // 14t / 16t: text area and cell size in pixels
+            Window::ReportTextAreaSizePixels => {
+                if let Some((width, height)) = self.config.cell_pixels {
+                    self.respond(&format!("4;{};{}t", rows * height, cols * width));
+                }
+            }
+            Window::ReportCellSizePixels => {
+                if let Some((width, height)) = self.config.cell_pixels {
+                    self.respond(&format!("6;{};{}t", height, width));
+                }
+            }
+            _ => {
+                tracing::debug!("Unhandled window op: {:?}", window);
+            }
+        }
+    }
+
     fn handle_cursor(&mut self, op: termwiz::escape::csi::Cursor) {
-        use termwiz::escape::csi::Cursor;
+        use termwiz::escape::csi::{Cursor, TabulationClear};
 
         match op {
             Cursor::Up(n) => {
@@ -531,13 +1158,59 @@ impl EmbeddedTerminal {
             Cursor::LinePositionAbsolute(row) => {
                 self.cursor.row = (row as usize).saturating_sub(1).min(self.config.rows - 1);
             }
-            Cursor::SaveCursor => {
-                self.saved_cursor = Some(self.cursor);
+            Cursor::SaveCursor => self.save_cursor(),
+            Cursor::RestoreCursor => self.restore_cursor(),
+            // DECSTBM: set the scrolling region and home the cursor
+            Cursor::SetTopAndBottomMargins { top, bottom } => {
+                let top = (top.as_one_based() as usize).saturating_sub(1);
+                let bottom = (bottom.as_one_based() as usize)
+                    .saturating_sub(1)
+                    .min(self.config.rows - 1);
+                if top < bottom {
+                    self.margins = (top > 0 || bottom < self.config.rows - 1).then_some((top, bottom));
+                }
+                self.cursor = CursorPosition::default();
             }
-            Cursor::RestoreCursor => {
-                if let Some(pos) = self.saved_cursor {
-                    self.cursor = pos;
+            // TBC: clear the tab stop under the cursor, or all of them
+            Cursor::TabulationClear(clear) => match clear {
+                TabulationClear::ClearCharacterTabStopAtActivePosition => {
+                    if let Some(stop) = self.tab_stops.get_mut(self.cursor.col) {
+                        *stop = false;
+                    }
+                }
+                TabulationClear::ClearAllCharacterTabStops | TabulationClear::ClearAllTabStops => {
+                    self.tab_stops.fill(false);
                 }
+                _ => {}
+            },
+            // CHT / CBT: move n tab stops forward / back
+            Cursor::ForwardTabulation(n) => {
+                for _ in 0..n {
+                    self.cursor.col = self.next_tab_stop(self.cursor.col);
+                }
+            }
+            Cursor::BackwardTabulation(n) => {
+                for _ in 0..n {
+                    self.cursor.col = self.previous_tab_stop(self.cursor.col);
+                }
+            }
+            // DECSCUSR: 0 is the default blinking block
+            Cursor::CursorStyle(style) => {
+                use termwiz::escape::csi::CursorStyle;
+                (self.cursor_shape, self.cursor_blink) = match style {
+                    CursorStyle::Default | CursorStyle::BlinkingBlock => (CursorShape::Block, true),
+                    CursorStyle::SteadyBlock => (CursorShape::Block, false),
+                    CursorStyle::BlinkingUnderline => (CursorShape::Underline, true),
+                    CursorStyle::SteadyUnderline => (CursorShape::Underline, false),
+                    CursorStyle::BlinkingBar => (CursorShape::Bar, true),
+                    CursorStyle::SteadyBar => (CursorShape::Bar, false),
+                };
+            }
+            // CPR (DSR 6): one-based row and column
+            Cursor::RequestActivePositionReport => {
+                let row = self.cursor.row.min(self.config.rows - 1) + 1;
+                let col = self.cursor.col.min(self.config.cols - 1) + 1;
+                self.respond(&format!("{};{}R", row, col));
             }
             Cursor::NextLine(n) => {
                 self.cursor.col = 0;
@@ -570,6 +1243,7 @@ impl EmbeddedTerminal {
         use termwiz::surface::{Change, Position};
 
         match op {
+            // Erased cells take the current background (see `clear_rows`)
             Edit::EraseInLine(erase) => match erase {
                 EraseInLine::EraseToEndOfLine => {
                     self.surface.add_change(Change::CursorPosition {
@@ -577,25 +1251,10 @@ impl EmbeddedTerminal {
                         y: Position::Absolute(self.cursor.row),
                     });
                     self.surface
-                        .add_change(Change::ClearToEndOfLine(ColorAttribute::Default));
-                }
-                EraseInLine::EraseToStartOfLine => {
-                    self.surface.add_change(Change::CursorPosition {
-                        x: Position::Absolute(0),
-                        y: Position::Absolute(self.cursor.row),
-                    });
-                    for _ in 0..=self.cursor.col {
-                        self.surface.add_change(Change::Text(" ".to_string()));
-                    }
-                }
-                EraseInLine::EraseLine => {
-                    self.surface.add_change(Change::CursorPosition {
-                        x: Position::Absolute(0),
-                        y: Position::Absolute(self.cursor.row),
-                    });
-                    self.surface
-                        .add_change(Change::ClearToEndOfLine(ColorAttribute::Default));
+                        .add_change(Change::ClearToEndOfLine(self.current_attrs.background()));
                 }
+                EraseInLine::EraseToStartOfLine => self.erase_to_cursor(),
+                EraseInLine::EraseLine => self.clear_rows(self.cursor.row..self.cursor.row + 1),
             },
             Edit::EraseInDisplay(erase) => match erase {
                 EraseInDisplay::EraseToEndOfDisplay => {
@@ -604,66 +1263,74 @@ impl EmbeddedTerminal {
                         y: Position::Absolute(self.cursor.row),
                     });
                     self.surface
-                        .add_change(Change::ClearToEndOfLine(ColorAttribute::Default));
-                    for y in (self.cursor.row + 1)..self.config.rows {
-                        self.surface.add_change(Change::CursorPosition {
-                            x: Position::Absolute(0),
-                            y: Position::Absolute(y),
-                        });
-                        self.surface
-                            .add_change(Change::ClearToEndOfLine(ColorAttribute::Default));
-                    }
+                        .add_change(Change::ClearToEndOfLine(self.current_attrs.background()));
+                    self.clear_rows(self.cursor.row + 1..self.config.rows);
                 }
                 EraseInDisplay::EraseToStartOfDisplay => {
-                    for y in 0..self.cursor.row {
-                        self.surface.add_change(Change::CursorPosition {
-                            x: Position::Absolute(0),
-                            y: Position::Absolute(y),
-                        });
-                        self.surface
-                            .add_change(Change::ClearToEndOfLine(ColorAttribute::Default));
-                    }
-                    self.surface.add_change(Change::CursorPosition {
-                        x: Position::Absolute(0),
-                        y: Position::Absolute(self.cursor.row),
-                    });
-                    for _ in 0..=self.cursor.col {
-                        self.surface.add_change(Change::Text(" ".to_string()));
-                    }
+                    self.clear_rows(0..self.cursor.row);
+                    self.erase_to_cursor();
                 }
                 EraseInDisplay::EraseDisplay => {
                     self.surface
-                        .add_change(Change::ClearScreen(ColorAttribute::Default));
+                        .add_change(Change::ClearScreen(self.current_attrs.background()));
                 }
                 EraseInDisplay::EraseScrollback => {
                     self.scrollback.clear();
                 }
             },
+            // DCH/ICH shift the rest of the row, so they work on its cells
+            // directly; vacated cells are blanks in the current background
             Edit::DeleteCharacter(n) => {
-                self.surface.add_change(Change::CursorPosition {
-                    x: Position::Absolute(self.cursor.col),
-                    y: Position::Absolute(self.cursor.row),
-                });
-                for _ in 0..n {
-                    self.surface.add_change(Change::Text(" ".to_string()));
+                let blank = self.blank_cell();
+                if let Some(row) = self.cursor_row_cells() {
+                    let n = (n as usize).min(row.len());
+                    row.rotate_left(n);
+                    let len = row.len();
+                    row[len - n..].fill(blank);
+                }
+            }
+            // ECH blanks cells in place, without moving the cursor or the
+            // rest of the line
+            Edit::EraseCharacter(n) => {
+                let blank = self.blank_cell();
+                if let Some(row) = self.cursor_row_cells() {
+                    let n = (n as usize).min(row.len());
+                    row[..n].fill(blank);
                 }
             }
+            Edit::InsertCharacter(n) => {
+                let blank = self.blank_cell();
+                if let Some(row) = self.cursor_row_cells() {
+                    let n = (n as usize).min(row.len());
+                    row.rotate_right(n);
+                    row[..n].fill(blank);
+                }
+            }
+            // Lines below the cursor move within the scrolling region;
+            // outside the region these have no effect. The lines moved in
+            // are blank in the current background.
             Edit::DeleteLine(n) => {
-                for _ in 0..n {
+                let (top, bottom) = self.scroll_region();
+                if (top..=bottom).contains(&self.cursor.row) {
+                    let n = (n as usize).min(bottom + 1 - self.cursor.row);
                     self.surface.add_change(Change::ScrollRegionUp {
                         first_row: self.cursor.row,
-                        region_size: self.config.rows - self.cursor.row,
-                        scroll_count: 1,
+                        region_size: bottom + 1 - self.cursor.row,
+                        scroll_count: n,
                     });
+                    self.clear_rows(bottom + 1 - n..bottom + 1);
                 }
             }
             Edit::InsertLine(n) => {
-                for _ in 0..n {
+                let (top, bottom) = self.scroll_region();
+                if (top..=bottom).contains(&self.cursor.row) {
+                    let n = (n as usize).min(bottom + 1 - self.cursor.row);
                     self.surface.add_change(Change::ScrollRegionDown {
                         first_row: self.cursor.row,
-                        region_size: self.config.rows - self.cursor.row,
-                        scroll_count: 1,
+                        region_size: bottom + 1 - self.cursor.row,
+                        scroll_count: n,
                     });
+                    self.clear_rows(self.cursor.row..self.cursor.row + n);
                 }
             }
             _ => {
@@ -672,12 +1339,35 @@ impl EmbeddedTerminal {
         }
     }
 
+    /// Blank the cursor row from its start up to and including the cursor
+    fn erase_to_cursor(&mut self) {
+        let blank = self.blank_cell();
+        let col = self.cursor.col;
+        if let Some(row) = self.surface.screen_cells().into_iter().nth(self.cursor.row) {
+            let end = (col + 1).min(row.len());
+            row[..end].fill(blank);
+        }
+    }
+
+    /// Cells of the cursor row from the cursor to the end of the line
+    fn cursor_row_cells(&mut self) -> Option<&mut [Cell]> {
+        let (row, col) = (self.cursor.row, self.cursor.col);
+        self.surface
+            .screen_cells()
+            .into_iter()
+            .nth(row)
+            .and_then(|cells| cells.get_mut(col..))
+    }
+
     fn handle_sgr(&mut self, sgr: termwiz::escape::csi::Sgr) {
         use termwiz::escape::csi::Sgr;
 
         match sgr {
+            // SGR 0 ends colors and styles, but not an open hyperlink
             Sgr::Reset => {
+                let link = self.current_attrs.hyperlink().cloned();
                 self.current_attrs = CellAttributes::default();
+                self.current_attrs.set_hyperlink(link);
             }
             Sgr::Intensity(intensity) => {
                 self.current_attrs.set_intensity(intensity);
@@ -726,12 +1416,51 @@ impl EmbeddedTerminal {
             Mode::ResetDecPrivateMode(DecPrivateMode::Code(code)) => {
                 self.set_dec_mode(code, false);
             }
+            // DECRQM: 1 = set, 2 = reset, 0 = not recognized
+            Mode::QueryDecPrivateMode(mode) => {
+                let (number, state) = match mode {
+                    DecPrivateMode::Code(code) => {
+                        let state = match self.dec_mode(&code) {
+                            Some(true) => 1,
+                            Some(false) => 2,
+                            None => 0,
+                        };
+                        (code as u16, state)
+                    }
+                    DecPrivateMode::Unspecified(number) => (number, 0),
+                };
+                self.respond(&format!("?{};{}$y", number, state));
+            }
             _ => {
                 tracing::debug!("Unhandled mode: {:?}", mode);
             }
         }
     }
 
+    /// Current state of a DEC private mode, None for modes not tracked
+    fn dec_mode(&self, code: &termwiz::escape::csi::DecPrivateModeCode) -> Option<bool> {
+        use termwiz::escape::csi::DecPrivateModeCode;
+
+        match code {
+            DecPrivateModeCode::ApplicationCursorKeys => Some(self.application_cursor_keys),
+            DecPrivateModeCode::AutoWrap => Some(self.autowrap),
+            DecPrivateModeCode::ShowCursor => Some(self.cursor_visible),
+            DecPrivateModeCode::MouseTracking | DecPrivateModeCode::HighlightMouseTracking => {
+                Some(self.mouse_tracking == MouseTracking::Click)
+            }
+            DecPrivateModeCode::ButtonEventMouse => Some(self.mouse_tracking == MouseTracking::Drag),
+            DecPrivateModeCode::AnyEventMouse => Some(self.mouse_tracking == MouseTracking::Motion),
+            DecPrivateModeCode::Utf8Mouse => Some(self.mouse_encoding == MouseEncoding::Utf8),
+            DecPrivateModeCode::SGRMouse => Some(self.mouse_encoding == MouseEncoding::Sgr),
+            DecPrivateModeCode::BracketedPaste => Some(self.bracketed_paste),
+            DecPrivateModeCode::FocusTracking => Some(self.focus_reporting),
+            DecPrivateModeCode::ClearAndEnableAlternateScreen
+            | DecPrivateModeCode::EnableAlternateScreen
+            | DecPrivateModeCode::OptEnableAlternateScreen => Some(self.in_alternate_screen),
+            _ => None,
+        }
+    }
+
     fn set_dec_mode(&mut self, code: termwiz::escape::csi::DecPrivateModeCode, enable: bool) {
         use termwiz::escape::csi::DecPrivateModeCode;
 
@@ -739,32 +1468,80 @@ impl EmbeddedTerminal {
             DecPrivateModeCode::ApplicationCursorKeys => {
                 self.application_cursor_keys = enable;
             }
-            DecPrivateModeCode::AutoWrap | DecPrivateModeCode::ShowCursor => {}
+            DecPrivateModeCode::AutoWrap => {
+                self.autowrap = enable;
+            }
+            DecPrivateModeCode::ShowCursor => {
+                self.cursor_visible = enable;
+            }
             DecPrivateModeCode::MouseTracking
             | DecPrivateModeCode::HighlightMouseTracking
             | DecPrivateModeCode::ButtonEventMouse
             | DecPrivateModeCode::AnyEventMouse => {
-                self.mouse_reporting = enable;
+                let tracking = match code {
+                    DecPrivateModeCode::ButtonEventMouse => MouseTracking::Drag,
+                    DecPrivateModeCode::AnyEventMouse => MouseTracking::Motion,
+                    _ => MouseTracking::Click,
+                };
+                self.mouse_tracking = if enable { tracking } else { MouseTracking::Off };
+            }
+            // Turning off the encoding in use goes back to X10
+            DecPrivateModeCode::Utf8Mouse | DecPrivateModeCode::SGRMouse => {
+                let encoding = if code == DecPrivateModeCode::SGRMouse {
+                    MouseEncoding::Sgr
+                } else {
+                    MouseEncoding::Utf8
+                };
+                if enable {
+                    self.mouse_encoding = encoding;
+                } else if self.mouse_encoding == encoding {
+                    self.mouse_encoding = MouseEncoding::X10;
+                }
             }
-            DecPrivateModeCode::SGRMouse => {}
+            // 47 switches screens, 1047 also clears the alternate screen
+            // when leaving it, and 1049 saves the cursor, clears the
+            // alternate screen and homes the cursor on the way in and
+            // restores the cursor on the way out
             DecPrivateModeCode::ClearAndEnableAlternateScreen
+            | DecPrivateModeCode::OptEnableAlternateScreen
             | DecPrivateModeCode::EnableAlternateScreen => {
+                let full = code == DecPrivateModeCode::ClearAndEnableAlternateScreen;
                 if enable {
                     if !self.in_alternate_screen {
-                        self.saved_primary = Some(std::mem::replace(
-                            &mut self.surface,
-                            Surface::new(self.config.cols, self.config.rows),
-                        ));
+                        if full {
+                            self.save_cursor();
+                        }
+                        let alternate = match self.saved_alternate.take() {
+                            Some(alternate) if !full => alternate,
+                            _ => Surface::new(self.config.cols, self.config.rows),
+                        };
+                        self.saved_primary = Some(std::mem::replace(&mut self.surface, alternate));
+                        self.primary_saved_cursor = self.saved_cursor.take();
                         self.in_alternate_screen = true;
+                        if full {
+                            self.cursor = CursorPosition::default();
+                        }
                     }
                 } else if self.in_alternate_screen {
                     if let Some(primary) = self.saved_primary.take() {
-                        self.surface = primary;
+                        let alternate = std::mem::replace(&mut self.surface, primary);
+                        if code == DecPrivateModeCode::EnableAlternateScreen {
+                            self.saved_alternate = Some(alternate);
+                        }
                     }
+                    self.saved_cursor = self.primary_saved_cursor.take();
                     self.in_alternate_screen = false;
+                    if full {
+                        self.restore_cursor();
+                    }
                 }
             }
-            DecPrivateModeCode::BracketedPaste => {}
+            DecPrivateModeCode::BracketedPaste => {
+                self.bracketed_paste = enable;
+            }
+            DecPrivateModeCode::FocusTracking => {
+                self.focus_reporting = enable;
+            }
             _ => {
                 tracing::debug!("Unhandled DEC mode: {:?} = {}", code, enable);
             }
@@ -776,24 +1553,27 @@ impl EmbeddedTerminal {
         use termwiz::escape::Esc;
 
         match esc {
-            Esc::Code(EscCode::DecSaveCursorPosition) => {
-                self.saved_cursor = Some(self.cursor);
-            }
-            Esc::Code(EscCode::DecRestoreCursorPosition) => {
-                if let Some(pos) = self.saved_cursor {
-                    self.cursor = pos;
-                }
-            }
+            Esc::Code(EscCode::DecSaveCursorPosition) => self.save_cursor(),
+            Esc::Code(EscCode::DecRestoreCursorPosition) => self.restore_cursor(),
             Esc::Code(EscCode::ReverseIndex) => {
-                if self.cursor.row == 0 {
+                // At the top margin the region scrolls down instead
+                let (top, bottom) = self.scroll_region();
+                if self.cursor.row == top {
                     self.surface
                         .add_change(termwiz::surface::Change::ScrollRegionDown {
-                            first_row: 0,
-                            region_size: self.config.rows,
+                            first_row: top,
+                            region_size: bottom + 1 - top,
                             scroll_count: 1,
                         });
+                    self.clear_rows(top..top + 1);
                 } else {
-                    self.cursor.row -= 1;
+                    self.cursor.row = self.cursor.row.saturating_sub(1);
+                }
+            }
+            // HTS: set a tab stop at the cursor column
+            Esc::Code(EscCode::HorizontalTabSet) => {
+                if let Some(stop) = self.tab_stops.get_mut(self.cursor.col) {
+                    *stop = true;
                 }
             }
             Esc::Code(EscCode::Index) => {
@@ -806,6 +1586,19 @@ impl EmbeddedTerminal {
             Esc::Code(EscCode::FullReset) => {
                 self.clear();
             }
+            Esc::Code(EscCode::DecApplicationKeyPad) => self.application_keypad = true,
+            Esc::Code(EscCode::DecNormalKeyPad) => self.application_keypad = false,
+            // SCS: designate the G0 / G1 character set
+            Esc::Code(EscCode::AsciiCharacterSetG0) => self.charsets[0] = Charset::Ascii,
+            Esc::Code(EscCode::UkCharacterSetG0) => self.charsets[0] = Charset::Uk,
+            Esc::Code(EscCode::DecLineDrawingG0) => {
+                self.charsets[0] = Charset::DecSpecialGraphics;
+            }
+            Esc::Code(EscCode::AsciiCharacterSetG1) => self.charsets[1] = Charset::Ascii,
+            Esc::Code(EscCode::UkCharacterSetG1) => self.charsets[1] = Charset::Uk,
+            Esc::Code(EscCode::DecLineDrawingG1) => {
+                self.charsets[1] = Charset::DecSpecialGraphics;
+            }
             _ => {
                 tracing::debug!("Unhandled ESC: {:?}", esc);
             }
@@ -820,6 +1613,28 @@ impl EmbeddedTerminal {
             | OperatingSystemCommand::SetWindowTitleSun(title)
             | OperatingSystemCommand::SetIconNameAndWindowTitle(title) => {
                 tracing::debug!("Window title: {}", title);
+                // Control characters would garble the header it's shown in
+                let title: String = title.chars().filter(|c| !c.is_control()).collect();
+                self.title = (!title.trim().is_empty()).then_some(title);
+            }
+            // OSC 52: copy to the clipboard (termwiz decodes the base64).
+            // Clipboard reads are never answered, so commands can't see
+            // what else was copied.
+            OperatingSystemCommand::SetSelection(_, mut text) => {
+                if text.len() > MAX_CLIPBOARD_BYTES {
+                    let mut end = MAX_CLIPBOARD_BYTES;
+                    while !text.is_char_boundary(end) {
+                        end -= 1;
+                    }
+                    tracing::warn!("OSC 52 clipboard data truncated from {} bytes", text.len());
+                    text.truncate(end);
+                }
+                self.clipboard = Some(text);
+            }
+            // OSC 8: text printed until the link is closed carries its URI
+            OperatingSystemCommand::SetHyperlink(link) => {
+                self.current_attrs
+                    .set_hyperlink(link.map(std::sync::Arc::new));
             }
             _ => {
                 tracing::debug!("Unhandled OSC: {:?}", osc);
@@ -827,3 +1642,51 @@ impl EmbeddedTerminal {
         }
     }
 }
+
+/// Tab stops every 8 columns, as on a freshly reset terminal
+fn default_tab_stops(cols: usize) -> Vec<bool> {
+    (0..cols).map(|col| col > 0 && col % 8 == 0).collect()
+}
+
+/// Text of a row of cells without trailing blanks, skipping the
+/// continuation cells of wide characters
+fn row_text(cells: &[Cell]) -> String {
+    let mut text = String::new();
+    let mut skip = 0;
+    for cell in cells {
+        if skip > 0 {
+            skip -= 1;
+            continue;
+        }
+        skip = cell.width().saturating_sub(1);
+        text.push_str(cell.str());
+    }
+    text.trim_end().to_string()
+}
+
+/// Cells of `line`, one per column up to `cols`
+///
+/// Columns covered by a wide character read back as blanks, as with
+/// `Line::get_cell`, which is avoided here because it searches the line
+/// from the start for every column.
+fn line_cells(line: &Line, cols: usize) -> Vec<Cell> {
+    let mut cells = vec![Cell::default(); cols];
+    for cell in line.visible_cells() {
+        if let Some(slot) = cells.get_mut(cell.cell_index()) {
+            *slot = cell.as_cell();
+        }
+    }
+    cells
+}
+
+/// Drop the trailing blanks of a row moving into the scrollback, so
+/// mostly empty rows don't keep a full row of cells
+fn trim_row(mut row: Vec<Cell>) -> Vec<Cell> {
+    let len = row
+        .iter()
+        .rposition(|cell| !reflow::is_blank(cell))
+        .map_or(0, |last| last + 1);
+    row.truncate(len);
+    row.shrink_to_fit();
+    row
+}
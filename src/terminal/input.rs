@@ -1,10 +1,12 @@
 //! Crossterm key conversion utilities
 //!
 //! Provides conversion from crossterm key events to termwiz key codes
-//! for forwarding input to the embedded terminal.
+//! for forwarding input to the embedded terminal, and encoding of mouse
+//! events for children that enabled mouse reporting.
 
 use termwiz::input::{KeyCode, Modifiers};
 
+use super::emulator::{MouseEncoding, MouseTracking};
 use super::EmbeddedTerminal;
 
 /// Convert crossterm key modifiers to termwiz modifiers
@@ -27,10 +29,13 @@ pub fn convert_modifiers(ct_mods: crossterm::event::KeyModifiers) -> Modifiers {
 }
 
 /// Convert crossterm key code to termwiz key code
-pub fn convert_keycode(ct_code: crossterm::event::KeyCode) -> KeyCode {
-    use crossterm::event::KeyCode as CtKeyCode;
+///
+/// Returns None for keys termwiz has no equivalent for (most media keys),
+/// which shouldn't be sent to the child at all.
+pub fn convert_keycode(ct_code: crossterm::event::KeyCode) -> Option<KeyCode> {
+    use crossterm::event::{KeyCode as CtKeyCode, MediaKeyCode, ModifierKeyCode};
 
-    match ct_code {
+    let code = match ct_code {
         CtKeyCode::Char(c) => KeyCode::Char(c),
         CtKeyCode::Enter => KeyCode::Enter,
         CtKeyCode::Backspace => KeyCode::Backspace,
@@ -55,19 +60,129 @@ pub fn convert_keycode(ct_code: crossterm::event::KeyCode) -> KeyCode {
         CtKeyCode::PrintScreen => KeyCode::PrintScreen,
         CtKeyCode::Pause => KeyCode::Pause,
         CtKeyCode::Menu => KeyCode::Menu,
-        _ => KeyCode::Char(' '), // Fallback for unmapped keys
-    }
+        CtKeyCode::KeypadBegin => KeyCode::KeyPadBegin,
+        CtKeyCode::Media(media) => match media {
+            MediaKeyCode::PlayPause => KeyCode::MediaPlayPause,
+            MediaKeyCode::Stop => KeyCode::MediaStop,
+            MediaKeyCode::TrackNext => KeyCode::MediaNextTrack,
+            MediaKeyCode::TrackPrevious => KeyCode::MediaPrevTrack,
+            MediaKeyCode::LowerVolume => KeyCode::VolumeDown,
+            MediaKeyCode::RaiseVolume => KeyCode::VolumeUp,
+            MediaKeyCode::MuteVolume => KeyCode::VolumeMute,
+            MediaKeyCode::Play
+            | MediaKeyCode::Pause
+            | MediaKeyCode::Reverse
+            | MediaKeyCode::FastForward
+            | MediaKeyCode::Rewind
+            | MediaKeyCode::Record => return None,
+        },
+        CtKeyCode::Modifier(modifier) => match modifier {
+            ModifierKeyCode::LeftShift => KeyCode::LeftShift,
+            ModifierKeyCode::RightShift => KeyCode::RightShift,
+            ModifierKeyCode::LeftControl => KeyCode::LeftControl,
+            ModifierKeyCode::RightControl => KeyCode::RightControl,
+            ModifierKeyCode::LeftAlt => KeyCode::LeftAlt,
+            ModifierKeyCode::RightAlt => KeyCode::RightAlt,
+            ModifierKeyCode::LeftSuper => KeyCode::LeftWindows,
+            ModifierKeyCode::RightSuper => KeyCode::RightWindows,
+            ModifierKeyCode::LeftHyper | ModifierKeyCode::RightHyper => KeyCode::Hyper,
+            ModifierKeyCode::LeftMeta | ModifierKeyCode::RightMeta => KeyCode::Meta,
+            ModifierKeyCode::IsoLevel3Shift | ModifierKeyCode::IsoLevel5Shift => return None,
+        },
+    };
+    Some(code)
 }
 
 /// Convert a crossterm key event to encoded bytes for the PTY
-#[allow(dead_code)] // Public API for future use
+///
+/// Keys crossterm marks as typed on the keypad (reported by terminals
+/// using the kitty keyboard protocol) follow the child's keypad mode.
+/// Keys with no terminal equivalent encode to nothing.
 pub fn encode_crossterm_key(
     terminal: &EmbeddedTerminal,
     key: &crossterm::event::KeyEvent,
 ) -> String {
+    use crossterm::event::KeyEventState;
+
     let modifiers = convert_modifiers(key.modifiers);
-    let keycode = convert_keycode(key.code);
-    terminal.encode_key(keycode, modifiers)
+    let Some(keycode) = convert_keycode(key.code) else {
+        return String::new();
+    };
+    if key.state.contains(KeyEventState::KEYPAD) {
+        terminal.encode_keypad_key(keycode, modifiers)
+    } else {
+        terminal.encode_key(keycode, modifiers)
+    }
+}
+
+/// Encode a crossterm mouse event the way the child asked for: SGR
+/// (`CSI < b;x;y M`), UTF-8 or X10 (`CSI M` and three bytes)
+///
+/// `col` and `row` are 0-based cells within the terminal. Returns None when
+/// the child did not ask for this kind of event, or when the position
+/// doesn't fit the encoding.
+pub fn encode_crossterm_mouse(
+    terminal: &EmbeddedTerminal,
+    mouse: &crossterm::event::MouseEvent,
+    col: u16,
+    row: u16,
+) -> Option<Vec<u8>> {
+    use crossterm::event::{KeyModifiers, MouseButton, MouseEventKind};
+
+    let tracking = terminal.mouse_tracking();
+    let button_code = |button: MouseButton| match button {
+        MouseButton::Left => 0,
+        MouseButton::Middle => 1,
+        MouseButton::Right => 2,
+    };
+    let (code, release) = match mouse.kind {
+        _ if tracking == MouseTracking::Off => return None,
+        MouseEventKind::Down(button) => (button_code(button), false),
+        MouseEventKind::Up(button) => (button_code(button), true),
+        MouseEventKind::Drag(button) if tracking >= MouseTracking::Drag => {
+            (button_code(button) + 32, false)
+        }
+        // Motion with no button pressed
+        MouseEventKind::Moved if tracking == MouseTracking::Motion => (3 + 32, false),
+        MouseEventKind::ScrollUp => (64, false),
+        MouseEventKind::ScrollDown => (65, false),
+        MouseEventKind::ScrollLeft => (66, false),
+        MouseEventKind::ScrollRight => (67, false),
+        MouseEventKind::Drag(_) | MouseEventKind::Moved => return None,
+    };
+
+    let mut modifiers = 0;
+    if mouse.modifiers.contains(KeyModifiers::SHIFT) {
+        modifiers += 4;
+    }
+    if mouse.modifiers.contains(KeyModifiers::ALT) {
+        modifiers += 8;
+    }
+    if mouse.modifiers.contains(KeyModifiers::CONTROL) {
+        modifiers += 16;
+    }
+
+    let (x, y) = (u32::from(col) + 1, u32::from(row) + 1);
+    let encoding = terminal.mouse_encoding();
+    if encoding == MouseEncoding::Sgr {
+        let end = if release { 'm' } else { 'M' };
+        return Some(format!("\x1b[<{};{};{}{}", code + modifiers, x, y, end).into_bytes());
+    }
+
+    // Releases don't say which button (3); all values are offset by 32
+    let code = if release { 3 } else { code } + modifiers;
+    let mut seq = b"\x1b[M".to_vec();
+    seq.push(32 + code as u8);
+    for value in [x + 32, y + 32] {
+        match encoding {
+            MouseEncoding::Utf8 => {
+                let c = char::from_u32(value).filter(|_| value < 0x800)?;
+                seq.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+            }
+            _ => seq.push(u8::try_from(value).ok()?),
+        }
+    }
+    Some(seq)
 }
 
 #[cfg(test)]
@@ -135,9 +250,62 @@ mod tests {
         assert!(mods.contains(Modifiers::SHIFT));
 
         // Test keycode conversion
-        assert!(matches!(convert_keycode(CtKeyCode::Enter), KeyCode::Enter));
-        assert!(matches!(convert_keycode(CtKeyCode::Up), KeyCode::UpArrow));
-        assert!(matches!(convert_keycode(CtKeyCode::Char('x')), KeyCode::Char('x')));
+        assert!(matches!(convert_keycode(CtKeyCode::Enter), Some(KeyCode::Enter)));
+        assert!(matches!(convert_keycode(CtKeyCode::Up), Some(KeyCode::UpArrow)));
+        assert!(matches!(convert_keycode(CtKeyCode::Char('x')), Some(KeyCode::Char('x'))));
+    }
+
+    #[test]
+    fn test_application_keypad_mode() {
+        use crossterm::event::{KeyCode as CtKeyCode, KeyEvent, KeyEventState, KeyModifiers};
+
+        let keypad = |code| {
+            let mut key = KeyEvent::new(code, KeyModifiers::NONE);
+            key.state = KeyEventState::KEYPAD;
+            key
+        };
+        let mut term = EmbeddedTerminal::new(TerminalConfig::default());
+
+        // Normal keypad mode: same as the main keys
+        assert_eq!(encode_crossterm_key(&term, &keypad(CtKeyCode::Char('5'))), "5");
+        assert_eq!(encode_crossterm_key(&term, &keypad(CtKeyCode::Enter)), "\r");
+
+        term.write(b"\x1b=");
+        assert!(term.application_keypad());
+        assert_eq!(encode_crossterm_key(&term, &keypad(CtKeyCode::Char('5'))), "\x1bOu");
+        assert_eq!(encode_crossterm_key(&term, &keypad(CtKeyCode::Char('+'))), "\x1bOk");
+        assert_eq!(encode_crossterm_key(&term, &keypad(CtKeyCode::Enter)), "\x1bOM");
+        // NumLock off: keypad navigation keys stay cursor keys
+        assert_eq!(encode_crossterm_key(&term, &keypad(CtKeyCode::Up)), "\x1b[A");
+        // The main keyboard isn't affected
+        let key = KeyEvent::new(CtKeyCode::Char('5'), KeyModifiers::NONE);
+        assert_eq!(encode_crossterm_key(&term, &key), "5");
+
+        term.write(b"\x1b>");
+        assert_eq!(encode_crossterm_key(&term, &keypad(CtKeyCode::Char('5'))), "5");
+    }
+
+    #[test]
+    fn test_unmapped_keys_are_dropped() {
+        use crossterm::event::{
+            KeyCode as CtKeyCode, KeyEvent, KeyModifiers, MediaKeyCode, ModifierKeyCode,
+        };
+
+        assert!(matches!(convert_keycode(CtKeyCode::KeypadBegin), Some(KeyCode::KeyPadBegin)));
+        assert!(matches!(
+            convert_keycode(CtKeyCode::Media(MediaKeyCode::PlayPause)),
+            Some(KeyCode::MediaPlayPause)
+        ));
+        assert!(matches!(
+            convert_keycode(CtKeyCode::Modifier(ModifierKeyCode::LeftSuper)),
+            Some(KeyCode::LeftWindows)
+        ));
+        assert!(convert_keycode(CtKeyCode::Media(MediaKeyCode::Record)).is_none());
+
+        // No stray space for a key with no equivalent
+        let term = EmbeddedTerminal::new(TerminalConfig::default());
+        let key = KeyEvent::new(CtKeyCode::Media(MediaKeyCode::Rewind), KeyModifiers::NONE);
+        assert_eq!(encode_crossterm_key(&term, &key), "");
     }
 
     #[test]
@@ -181,4 +349,130 @@ mod tests {
         term.write(b"\x1b[?1000l");
         assert!(!term.mouse_enabled());
     }
+
+    #[test]
+    fn test_bracketed_paste() {
+        let mut term = EmbeddedTerminal::new(TerminalConfig::default());
+
+        // Without mode 2004 the text is sent as typed
+        assert!(!term.bracketed_paste());
+        assert_eq!(term.encode_paste("a = 1\nb = 2\r\n"), "a = 1\rb = 2\r");
+
+        term.write(b"\x1b[?2004h");
+        assert!(term.bracketed_paste());
+        assert_eq!(
+            term.encode_paste("a = 1\nb = 2"),
+            "\x1b[200~a = 1\rb = 2\x1b[201~"
+        );
+        // An embedded end marker cannot break out of the paste
+        assert_eq!(term.encode_paste("x\x1b[201~y"), "\x1b[200~xy\x1b[201~");
+
+        term.write(b"\x1b[?2004l");
+        assert_eq!(term.encode_paste("x"), "x");
+
+        // A new command starts without it
+        term.write(b"\x1b[?2004h");
+        term.clear();
+        assert!(!term.bracketed_paste());
+    }
+
+    #[test]
+    fn test_focus_reporting() {
+        let mut term = EmbeddedTerminal::new(TerminalConfig::default());
+        assert_eq!(term.encode_focus(true), None);
+
+        term.write(b"\x1b[?1004h");
+        assert!(term.focus_reporting());
+        assert_eq!(term.encode_focus(true), Some("\x1b[I"));
+        assert_eq!(term.encode_focus(false), Some("\x1b[O"));
+        term.write(b"\x1b[?1004$p");
+        assert_eq!(term.take_responses(), b"\x1b[?1004;1$y");
+
+        term.write(b"\x1b[?1004l");
+        assert_eq!(term.encode_focus(false), None);
+    }
+
+    #[test]
+    fn test_mouse_sgr_encoding() {
+        use crossterm::event::{KeyModifiers, MouseButton, MouseEvent, MouseEventKind};
+
+        let mouse = |kind, modifiers| MouseEvent {
+            kind,
+            column: 0,
+            row: 0,
+            modifiers,
+        };
+        let left_down = mouse(MouseEventKind::Down(MouseButton::Left), KeyModifiers::NONE);
+        let drag = mouse(MouseEventKind::Drag(MouseButton::Left), KeyModifiers::NONE);
+        let mut term = EmbeddedTerminal::new(TerminalConfig::default());
+
+        assert_eq!(encode_crossterm_mouse(&term, &left_down, 4, 2), None);
+
+        term.write(b"\x1b[?1000h\x1b[?1006h");
+        assert_eq!(
+            encode_crossterm_mouse(&term, &left_down, 4, 2).as_deref(),
+            Some(&b"\x1b[<0;5;3M"[..])
+        );
+        assert_eq!(
+            encode_crossterm_mouse(
+                &term,
+                &mouse(MouseEventKind::Up(MouseButton::Right), KeyModifiers::CONTROL),
+                0,
+                0
+            )
+            .as_deref(),
+            Some(&b"\x1b[<18;1;1m"[..])
+        );
+        assert_eq!(
+            encode_crossterm_mouse(
+                &term,
+                &mouse(MouseEventKind::ScrollDown, KeyModifiers::NONE),
+                9,
+                9
+            )
+            .as_deref(),
+            Some(&b"\x1b[<65;10;10M"[..])
+        );
+        // Drags need button event tracking
+        assert_eq!(encode_crossterm_mouse(&term, &drag, 1, 1), None);
+        term.write(b"\x1b[?1002h");
+        assert_eq!(
+            encode_crossterm_mouse(&term, &drag, 1, 1).as_deref(),
+            Some(&b"\x1b[<32;2;2M"[..])
+        );
+    }
+
+    #[test]
+    fn test_mouse_x10_and_utf8_encoding() {
+        use crossterm::event::{KeyModifiers, MouseButton, MouseEvent, MouseEventKind};
+
+        let mouse = |kind| MouseEvent {
+            kind,
+            column: 0,
+            row: 0,
+            modifiers: KeyModifiers::NONE,
+        };
+        let down = mouse(MouseEventKind::Down(MouseButton::Left));
+        let up = mouse(MouseEventKind::Up(MouseButton::Right));
+        let mut term = EmbeddedTerminal::new(TerminalConfig::default());
+
+        // X10 unless asked otherwise: releases don't tell the button
+        term.write(b"\x1b[?1000h");
+        assert_eq!(encode_crossterm_mouse(&term, &down, 4, 2).as_deref(), Some(&b"\x1b[M %#"[..]));
+        assert_eq!(encode_crossterm_mouse(&term, &up, 0, 0).as_deref(), Some(&b"\x1b[M#!!"[..]));
+        // Past column 223 X10 can't say where
+        assert_eq!(encode_crossterm_mouse(&term, &down, 223, 0), None);
+
+        term.write(b"\x1b[?1005h");
+        assert_eq!(
+            encode_crossterm_mouse(&term, &down, 223, 0).as_deref(),
+            Some("\x1b[M \u{100}!".as_bytes())
+        );
+
+        // SGR wins while set; turning it off goes back to X10
+        term.write(b"\x1b[?1006h");
+        assert_eq!(encode_crossterm_mouse(&term, &up, 0, 0).as_deref(), Some(&b"\x1b[<2;1;1m"[..]));
+        term.write(b"\x1b[?1006l");
+        assert_eq!(encode_crossterm_mouse(&term, &up, 0, 0).as_deref(), Some(&b"\x1b[M#!!"[..]));
+    }
 }
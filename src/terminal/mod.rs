@@ -8,21 +8,31 @@
 //!
 //! # Module Structure
 //!
+//! - `charset` - DEC special graphics and other designatable character sets
 //! - `config` - Terminal configuration and cursor types
 //! - `emulator` - Core terminal emulator implementation
 //! - `widget` - Ratatui widget for rendering terminal content
 //! - `input` - Crossterm key conversion utilities
+//! - `reflow` - Re-wrapping rows when the terminal is resized
+//! - `search` - Text search over scrollback and screen
+//! - `selection` - Text selection for copying parts of the output
 
+mod charset;
 mod config;
 mod emulator;
 mod input;
+mod reflow;
+mod search;
+mod selection;
 mod widget;
 
 #[cfg(test)]
 mod tests;
 
 // Re-export public API
-pub use config::{CursorPosition, TerminalConfig};
+pub use config::{CursorShape, TerminalConfig};
 pub use emulator::EmbeddedTerminal;
-pub use input::{convert_keycode, convert_modifiers};
+pub use input::{encode_crossterm_key, encode_crossterm_mouse};
+pub use search::{SearchMatch, SearchQuery};
+pub use selection::SelectionPoint;
 pub use widget::TerminalWidget;
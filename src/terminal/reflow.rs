@@ -0,0 +1,133 @@
+//! Re-wrapping terminal rows to a new width
+//!
+//! When text runs past the right margin, the last cell of the row it
+//! wrapped from gets the `wrapped` attribute. On resize, rows joined by
+//! that attribute are put back together into logical lines and wrapped
+//! again at the new width, so shrinking and growing the terminal keeps the
+//! output intact instead of cutting it off at the old width.
+
+use termwiz::cell::{Cell, CellAttributes};
+
+/// Whether `cell` is an untouched blank (padding, not output)
+pub(super) fn is_blank(cell: &Cell) -> bool {
+    cell.str() == " " && *cell.attrs() == CellAttributes::default()
+}
+
+/// Index of the character in the last column of `row`, whose cell carries
+/// the `wrapped` attribute (a wide character's continuation cell isn't kept)
+fn last_char_index(row: &[Cell]) -> Option<usize> {
+    let len = row.len();
+    if len >= 2 && row[len - 2].width() == 2 {
+        return Some(len - 2);
+    }
+    len.checked_sub(1)
+}
+
+/// Cell of the character in the last column of `row`
+pub(super) fn last_char_mut(row: &mut [Cell]) -> Option<&mut Cell> {
+    last_char_index(row).map(|index| &mut row[index])
+}
+
+/// Whether `row` wrapped at the right margin and continues on the next row
+pub(super) fn is_wrapped(row: &[Cell]) -> bool {
+    last_char_index(row).is_some_and(|index| row[index].attrs().wrapped())
+}
+
+/// Re-wrap `rows` (oldest first) to rows of exactly `cols` cells
+///
+/// `cursor` is a (row, col) position in `rows`; it is moved along with the
+/// cell it is on, and its new position returned.
+pub(super) fn reflow(
+    rows: Vec<Vec<Cell>>,
+    cols: usize,
+    cursor: Option<(usize, usize)>,
+) -> (Vec<Vec<Cell>>, Option<(usize, usize)>) {
+    let cols = cols.max(1);
+    let mut out = Vec::with_capacity(rows.len());
+    let mut new_cursor = None;
+
+    // Logical line being collected, and the cursor's offset in it
+    let mut line: Vec<Cell> = Vec::new();
+    let mut cursor_offset = None;
+    let mut continues = false;
+
+    for (index, mut row) in rows.into_iter().enumerate() {
+        // A wide character that didn't fit at the end of the previous row
+        // left a blank there; it's not part of the text
+        if continues
+            && row.first().is_some_and(|cell| cell.width() == 2)
+            && line.last().is_some_and(is_blank)
+        {
+            line.pop();
+        }
+        continues = false;
+        if let Some(last) = last_char_mut(&mut row) {
+            continues = last.attrs().wrapped();
+            last.attrs_mut().set_wrapped(false);
+        }
+        if let Some((cursor_row, cursor_col)) = cursor {
+            if cursor_row == index {
+                cursor_offset = Some(line.len() + cursor_col);
+            }
+        }
+        line.extend(row);
+
+        if !continues {
+            let cursor = wrap_line(std::mem::take(&mut line), cols, cursor_offset.take(), &mut out);
+            new_cursor = new_cursor.or(cursor);
+        }
+    }
+    // The last row wrapped, but nothing was written after it yet
+    if continues {
+        let cursor = wrap_line(line, cols, cursor_offset, &mut out);
+        new_cursor = new_cursor.or(cursor);
+    }
+
+    (out, new_cursor)
+}
+
+/// Wrap one logical line into rows of `cols` cells appended to `out`
+///
+/// Returns the new position of the cell at `cursor_offset`.
+fn wrap_line(
+    mut line: Vec<Cell>,
+    cols: usize,
+    cursor_offset: Option<usize>,
+    out: &mut Vec<Vec<Cell>>,
+) -> Option<(usize, usize)> {
+    // Trailing blanks are the old row's padding, except up to the cursor
+    let keep = cursor_offset.map_or(0, |offset| offset + 1);
+    while line.len() > keep && line.last().is_some_and(is_blank) {
+        line.pop();
+    }
+    line.resize(line.len().max(keep), Cell::default());
+
+    let mut cursor = None;
+    let mut row: Vec<Cell> = Vec::with_capacity(cols);
+    let mut index = 0;
+    while index < line.len() {
+        // A wide character and its continuation cells move together
+        let width = line[index].width().max(1).min(line.len() - index);
+        if row.len() + width > cols && !row.is_empty() {
+            out.push(finish_row(std::mem::take(&mut row), cols, true));
+        }
+        if let Some(offset) = cursor_offset {
+            if (index..index + width).contains(&offset) {
+                cursor = Some((out.len(), (row.len() + offset - index).min(cols - 1)));
+            }
+        }
+        row.extend_from_slice(&line[index..index + width]);
+        index += width;
+    }
+    out.push(finish_row(row, cols, false));
+    cursor
+}
+
+/// Pad or cut `row` to `cols` cells, marking it as wrapped if it continues
+fn finish_row(mut row: Vec<Cell>, cols: usize, wrapped: bool) -> Vec<Cell> {
+    row.resize(cols, Cell::default());
+    if let Some(last) = last_char_mut(&mut row) {
+        last.attrs_mut().set_wrapped(wrapped);
+    }
+    row
+}
@@ -0,0 +1,172 @@
+//! Text search over terminal output (scrollback and screen)
+//!
+//! Lines are addressed by absolute index: 0 is the oldest scrollback line
+//! and the visible surface follows the scrollback. Match columns are cell
+//! indices, so wide characters and multi-byte text map back to the grid.
+
+use std::ops::Range;
+
+use termwiz::cell::Cell;
+
+use super::EmbeddedTerminal;
+
+/// What to search for
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SearchQuery {
+    pub text: String,
+    /// Match case exactly (case-insensitive by default)
+    pub case_sensitive: bool,
+}
+
+/// A match: absolute line and range of cell columns
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchMatch {
+    pub line: usize,
+    pub cols: Range<usize>,
+}
+
+impl SearchQuery {
+    fn normalize(&self, text: &str) -> String {
+        if self.case_sensitive {
+            text.to_string()
+        } else {
+            text.to_lowercase()
+        }
+    }
+
+    /// Cell column ranges matching the query in a row of cells
+    pub fn find_in_row(&self, cells: &[Cell]) -> Vec<Range<usize>> {
+        if self.text.is_empty() {
+            return Vec::new();
+        }
+        let needle = self.normalize(&self.text);
+
+        // Row text plus the cell each byte came from
+        let mut haystack = String::new();
+        let mut byte_cells = Vec::new();
+        // Skip the continuation cells of wide characters
+        let mut skip = 0;
+        for (col, cell) in cells.iter().enumerate() {
+            if skip > 0 {
+                skip -= 1;
+                continue;
+            }
+            skip = cell.width().saturating_sub(1);
+            let text = self.normalize(cell.str());
+            byte_cells.extend(std::iter::repeat_n(col, text.len()));
+            haystack.push_str(&text);
+        }
+
+        let mut matches = Vec::new();
+        let mut from = 0;
+        while let Some(pos) = haystack[from..].find(&needle) {
+            let start = from + pos;
+            let end = start + needle.len();
+            let last = byte_cells[end - 1];
+            matches.push(byte_cells[start]..last + cells[last].width().max(1));
+            // Continue after the first character of this match
+            from = start + haystack[start..].chars().next().map_or(1, char::len_utf8);
+        }
+        matches
+    }
+}
+
+impl EmbeddedTerminal {
+    /// All matches in the scrollback and on screen, top to bottom
+    pub fn search(&self, query: &SearchQuery) -> Vec<SearchMatch> {
+        if query.text.is_empty() {
+            return Vec::new();
+        }
+        (0..self.total_lines())
+            .filter_map(|line| self.line(line).map(|cells| (line, cells)))
+            .flat_map(|(line, cells)| {
+                query
+                    .find_in_row(cells)
+                    .into_iter()
+                    .map(move |cols| SearchMatch { line, cols })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::terminal::TerminalConfig;
+
+    fn query(text: &str, case_sensitive: bool) -> SearchQuery {
+        SearchQuery {
+            text: text.to_string(),
+            case_sensitive,
+        }
+    }
+
+    #[test]
+    fn test_search_scrollback_and_screen() {
+        let mut term = EmbeddedTerminal::new(TerminalConfig {
+            cols: 20,
+            rows: 3,
+            scrollback: 100,
+            ..Default::default()
+        });
+        term.write(b"error: first\r\nok\r\nok\r\nok\r\nok\r\nwarn ERROR x");
+
+        let matches = term.search(&query("error", false));
+        assert_eq!(
+            matches,
+            vec![
+                SearchMatch { line: 0, cols: 0..5 },
+                SearchMatch { line: 5, cols: 5..10 },
+            ]
+        );
+        // First match is in the scrollback, the second on screen
+        assert!(matches[0].line < term.scrollback().len());
+        assert!(matches[1].line >= term.scrollback().len());
+
+        assert_eq!(term.search(&query("ERROR", true)).len(), 1);
+        assert!(term.search(&query("", false)).is_empty());
+    }
+
+    #[test]
+    fn test_reveal_line_scrolls_and_stops_following() {
+        let mut term = EmbeddedTerminal::new(TerminalConfig {
+            cols: 20,
+            rows: 3,
+            scrollback: 100,
+            ..Default::default()
+        });
+        for i in 0..20 {
+            term.write(format!("line {}\r\n", i).as_bytes());
+        }
+
+        term.reveal_line(2);
+        assert!(!term.is_following());
+        let top = term.viewport_top_line();
+        assert!((top..top + 3).contains(&2));
+        assert_eq!(term.line(2).unwrap()[5].str(), "2");
+    }
+
+    #[test]
+    fn test_find_in_row_overlapping_and_unicode() {
+        let cells: Vec<Cell> = "aaä Ä"
+            .chars()
+            .map(|c| Cell::new(c, Default::default()))
+            .collect();
+        assert_eq!(query("aa", false).find_in_row(&cells), vec![0..2]);
+        assert_eq!(query("ä", false).find_in_row(&cells), vec![2..3, 4..5]);
+        assert_eq!(query("ä", true).find_in_row(&cells), vec![2..3]);
+    }
+
+    #[test]
+    fn test_find_in_row_wide_characters() {
+        let mut term = EmbeddedTerminal::new(TerminalConfig {
+            cols: 10,
+            rows: 2,
+            ..Default::default()
+        });
+        term.write("日本語!".as_bytes());
+        let row = term.get_row(0);
+        assert_eq!(query("本語", false).find_in_row(row), vec![2..6]);
+        assert_eq!(query("!", false).find_in_row(row), vec![6..7]);
+    }
+}
@@ -0,0 +1,55 @@
+//! Text selection in the terminal output
+//!
+//! A selection runs in reading order from one cell to another, both
+//! included, like a visual selection in vim. Positions are absolute line
+//! indices (scrollback first, then the screen), so a selection stays put
+//! while the view scrolls.
+
+use std::ops::Range;
+
+/// A cell in the output: absolute line index and column
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct SelectionPoint {
+    pub line: usize,
+    pub col: usize,
+}
+
+/// Selected region between where it was started and where it was extended to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Selection {
+    /// Where the selection was started
+    pub anchor: SelectionPoint,
+    /// End that moves when the selection is extended
+    pub head: SelectionPoint,
+}
+
+impl Selection {
+    /// Empty selection of the single cell at `point`
+    pub fn new(point: SelectionPoint) -> Self {
+        Self {
+            anchor: point,
+            head: point,
+        }
+    }
+
+    /// First and last selected cell, in reading order
+    pub fn ordered(&self) -> (SelectionPoint, SelectionPoint) {
+        if self.anchor <= self.head {
+            (self.anchor, self.head)
+        } else {
+            (self.head, self.anchor)
+        }
+    }
+
+    /// Selected columns of `line` in a terminal `cols` wide
+    pub fn cols_in_line(&self, line: usize, cols: usize) -> Option<Range<usize>> {
+        let (start, end) = self.ordered();
+        if line < start.line || line > end.line {
+            return None;
+        }
+        let first = if line == start.line { start.col } else { 0 };
+        let last = if line == end.line { end.col + 1 } else { cols };
+        let range = first.min(cols)..last.min(cols);
+        (!range.is_empty()).then_some(range)
+    }
+}
@@ -109,7 +109,7 @@ fn test_get_row_empty() {
     let row = term.get_row(0);
     assert_eq!(row.len(), 10);
     // All cells should be default (space)
-    for cell in &row {
+    for cell in row {
         assert_eq!(cell.str(), " ");
     }
 }
@@ -173,6 +173,25 @@ fn test_carriage_return() {
     assert!(text.starts_with("Progress: 100%"));
 }
 
+#[test]
+fn test_progress_bar_keeps_final_state() {
+    let mut term = EmbeddedTerminal::new(TerminalConfig {
+        cols: 20,
+        rows: 5,
+        scrollback: 100,
+        ..Default::default()
+    });
+
+    // Progress updates redraw the same line; \r\n is still a newline
+    term.write(b"10%\r50%\r100%\r\ndone");
+
+    let row0: String = term.get_row(0).iter().map(|c| c.str()).collect();
+    let row1: String = term.get_row(1).iter().map(|c| c.str()).collect();
+    assert_eq!(row0.trim_end(), "100%");
+    assert_eq!(row1.trim_end(), "done");
+    assert_eq!(term.cursor().row, 1);
+}
+
 #[test]
 fn test_newline() {
     let mut term = EmbeddedTerminal::new(TerminalConfig {
@@ -218,6 +237,34 @@ fn test_cursor_position() {
     assert_eq!(term.cursor().col, 9);
 }
 
+#[test]
+fn test_query_responses() {
+    let mut term = EmbeddedTerminal::new(TerminalConfig {
+        cols: 20,
+        rows: 10,
+        scrollback: 100,
+        ..Default::default()
+    });
+    assert!(term.take_responses().is_empty());
+
+    // Cursor position report is one-based
+    term.write(b"\x1b[5;10Hab\x1b[6n");
+    assert_eq!(term.take_responses(), b"\x1b[5;12R");
+    // Taken responses are not sent twice
+    assert!(term.take_responses().is_empty());
+
+    // Primary and secondary device attributes, status report
+    term.write(b"\x1b[c\x1b[>c\x1b[5n");
+    assert_eq!(term.take_responses(), b"\x1b[?62;22c\x1b[>1;0;0c\x1b[0n");
+
+    // DECRQM for tracked, changed and unknown modes
+    term.write(b"\x1b[?2004h\x1b[?2004$p\x1b[?1$p\x1b[?9999$p");
+    assert_eq!(
+        term.take_responses(),
+        b"\x1b[?2004;1$y\x1b[?1;2$y\x1b[?9999;0$y"
+    );
+}
+
 #[test]
 fn test_cursor_movement() {
     let mut term = EmbeddedTerminal::new(TerminalConfig {
@@ -303,3 +350,716 @@ fn test_line_wrap() {
     let text1: String = row1.iter().map(|c| c.str()).collect();
     assert!(text1.starts_with("ABC"));
 }
+
+/// Visible rows as trimmed text
+fn screen_text(term: &EmbeddedTerminal) -> Vec<String> {
+    term.get_visible_rows()
+        .iter()
+        .map(|row| row.iter().map(|c| c.str()).collect::<String>().trim_end().to_string())
+        .collect()
+}
+
+#[test]
+fn test_scroll_region_keeps_rows_outside() {
+    let mut term = EmbeddedTerminal::new(TerminalConfig {
+        cols: 20,
+        rows: 5,
+        scrollback: 100,
+        ..Default::default()
+    });
+
+    // Header on row 1, footer on row 5, region rows 2-4
+    term.write(b"header\x1b[5;1Hfooter\x1b[2;4r");
+    assert_eq!((term.cursor().row, term.cursor().col), (0, 0));
+
+    // Fill and overflow the region
+    term.write(b"\x1b[2;1Ha\r\nb\r\nc\r\nd\r\ne");
+    assert_eq!(screen_text(&term), ["header", "c", "d", "e", "footer"]);
+    assert_eq!(term.cursor().row, 3);
+    // Lines scrolled out of a partial region are not history
+    assert!(term.scrollback().is_empty());
+
+    // Reverse index at the top margin scrolls the region down
+    term.write(b"\x1b[2;1H\x1bMz");
+    assert_eq!(screen_text(&term), ["header", "z", "c", "d", "footer"]);
+
+    // Insert and delete line stay within the region
+    term.write(b"\x1b[3;1H\x1b[L");
+    assert_eq!(screen_text(&term), ["header", "z", "", "c", "footer"]);
+    term.write(b"\x1b[2;1H\x1b[2M");
+    assert_eq!(screen_text(&term), ["header", "c", "", "", "footer"]);
+
+    // Resetting the region scrolls the whole screen again
+    term.write(b"\x1b[r\x1b[5;1H\r\n");
+    assert_eq!(screen_text(&term), ["c", "", "", "footer", ""]);
+    assert_eq!(term.scrollback().len(), 1);
+}
+
+#[test]
+fn test_resize_resets_scroll_region() {
+    let mut term = EmbeddedTerminal::new(TerminalConfig {
+        cols: 20,
+        rows: 5,
+        scrollback: 100,
+        ..Default::default()
+    });
+    term.write(b"\x1b[2;3r");
+    term.resize(20, 4);
+    term.write(b"1\r\n2\r\n3\r\n4\r\n5");
+    assert_eq!(screen_text(&term), ["2", "3", "4", "5"]);
+    assert_eq!(term.scrollback().len(), 1);
+}
+
+#[test]
+fn test_wide_characters() {
+    let mut term = EmbeddedTerminal::new(TerminalConfig {
+        cols: 7,
+        rows: 3,
+        scrollback: 10,
+        ..Default::default()
+    });
+
+    // Three double-width characters take six cells
+    term.write("日本語".as_bytes());
+    assert_eq!((term.cursor().row, term.cursor().col), (0, 6));
+    let row = term.get_row(0);
+    assert_eq!(row[0].str(), "日");
+    assert_eq!(row[2].str(), "本");
+    assert_eq!(row[4].str(), "語");
+
+    // The next one doesn't fit in the last cell and wraps first
+    term.write("日x".as_bytes());
+    assert_eq!((term.cursor().row, term.cursor().col), (1, 3));
+    assert_eq!(term.get_row(1)[0].str(), "日");
+    assert_eq!(term.get_row(1)[2].str(), "x");
+
+    assert_eq!(term.content_as_text(), "日本語\n日x");
+
+    // Combining marks take no cell, and stay with the character they
+    // modify
+    term.write("e\u{301}".as_bytes());
+    assert_eq!(term.cursor().col, 4);
+    assert_eq!(term.get_row(1)[3].str(), "e\u{301}");
+    assert_eq!(term.content_as_text(), "日本語\n日xe\u{301}");
+}
+
+#[test]
+fn test_combining_mark_after_wrap() {
+    let mut term = EmbeddedTerminal::new(TerminalConfig {
+        cols: 3,
+        rows: 2,
+        scrollback: 10,
+        ..Default::default()
+    });
+    // The base character fills the line, which wraps and scrolls before
+    // the mark arrives
+    term.write("abcxye\u{301}".as_bytes());
+    assert_eq!(term.get_row(0)[2].str(), "e\u{301}");
+
+    // Once the cursor moved on, a mark has nothing to attach to
+    term.write("z\x1b[1;1H\u{301}".as_bytes());
+    assert_eq!(term.content_as_text(), "abc\nxye\u{301}\nz");
+}
+
+#[test]
+fn test_insert_and_delete_characters_shift_the_row() {
+    let mut term = EmbeddedTerminal::new(TerminalConfig {
+        cols: 10,
+        rows: 2,
+        scrollback: 10,
+        ..Default::default()
+    });
+    let row_text = |term: &EmbeddedTerminal| {
+        term.get_row(0).iter().map(|c| c.str()).collect::<String>()
+    };
+
+    term.write(b"abcdef");
+    // Delete "cd": the rest moves left, blanks fill the end
+    term.write(b"\x1b[3G\x1b[2P");
+    assert_eq!(row_text(&term), "abef      ");
+    assert_eq!(term.cursor().col, 2);
+
+    // Insert two blanks before "e" and type into them
+    term.write(b"\x1b[2@XY");
+    assert_eq!(row_text(&term), "abXYef    ");
+
+    // Counts past the end of the row clear the rest of it
+    term.write(b"\x1b[9G\x1b[5@\x1b[1G\x1b[41m\x1b[99P");
+    assert_eq!(row_text(&term), "          ");
+    // Vacated cells carry the current attributes
+    assert_eq!(
+        term.get_row(0)[9].attrs().background(),
+        ColorAttribute::PaletteIndex(1)
+    );
+}
+
+#[test]
+fn test_erase_characters_in_place() {
+    let mut term = EmbeddedTerminal::new(TerminalConfig {
+        cols: 10,
+        rows: 2,
+        scrollback: 10,
+        ..Default::default()
+    });
+
+    // Erase "cd" with a red background: nothing shifts, the cursor stays
+    term.write(b"abcdef\x1b[3G\x1b[41m\x1b[2X");
+    assert_eq!(screen_text(&term), ["ab  ef", ""]);
+    assert_eq!(term.cursor().col, 2);
+    assert_eq!(
+        term.get_row(0)[3].attrs().background(),
+        ColorAttribute::PaletteIndex(1)
+    );
+
+    // Counts past the end of the row stop at the right margin
+    term.write(b"\x1b[0m\x1b[5G\x1b[99X");
+    assert_eq!(screen_text(&term), ["ab", ""]);
+}
+
+#[test]
+fn test_tab_stops_set_and_cleared() {
+    let mut term = EmbeddedTerminal::new(TerminalConfig {
+        cols: 40,
+        rows: 2,
+        scrollback: 10,
+        ..Default::default()
+    });
+
+    // Default stops every 8 columns
+    term.write(b"\t");
+    assert_eq!(term.cursor().col, 8);
+
+    // HTS at column 4, then tab from the start of the line
+    term.write(b"\r\x1b[5G\x1bH\r\t");
+    assert_eq!(term.cursor().col, 4);
+
+    // TBC 0 clears the stop under the cursor
+    term.write(b"\x1b[g\r\t");
+    assert_eq!(term.cursor().col, 8);
+
+    // TBC 3 clears all stops: tab goes to the last column
+    term.write(b"\x1b[3g\r\t");
+    assert_eq!(term.cursor().col, 39);
+
+    // A full reset restores the defaults
+    term.write(b"\x1bc\t");
+    assert_eq!(term.cursor().col, 8);
+}
+
+#[test]
+fn test_autowrap_disabled_overwrites_last_column() {
+    let mut term = EmbeddedTerminal::new(TerminalConfig {
+        cols: 5,
+        rows: 3,
+        scrollback: 10,
+        ..Default::default()
+    });
+
+    term.write(b"\x1b[?7labcdefg");
+    assert_eq!(screen_text(&term), ["abcdg", "", ""]);
+    assert_eq!(term.cursor().row, 0);
+
+    // DECRQM reports the mode as reset
+    term.write(b"\x1b[?7$p");
+    assert_eq!(term.take_responses(), b"\x1b[?7;2$y");
+
+    // Re-enabled, writing past the margin wraps again
+    term.write(b"\x1b[?7h\rabcdefg");
+    assert_eq!(screen_text(&term), ["abcde", "fg", ""]);
+}
+
+#[test]
+fn test_osc8_hyperlinks() {
+    let mut term = EmbeddedTerminal::new(TerminalConfig {
+        cols: 40,
+        rows: 3,
+        scrollback: 10,
+        ..Default::default()
+    });
+
+    // Two links, one repeated; SGR 0 inside a link does not end it
+    term.write(b"see \x1b]8;;https://a.example/\x1b\\do\x1b[0mcs\x1b]8;;\x1b\\ and ");
+    term.write(b"\x1b]8;id=x;https://b.example/\x07b\x1b]8;;\x07\r\n");
+    term.write(b"\x1b]8;;https://a.example/\x1b\\again\x1b]8;;\x1b\\ plain");
+
+    assert_eq!(term.hyperlinks(), ["https://a.example/", "https://b.example/"]);
+    let row = term.get_row(0);
+    assert_eq!(
+        row[4].attrs().hyperlink().map(|l| l.uri().to_string()).as_deref(),
+        Some("https://a.example/")
+    );
+    assert!(row[7].attrs().hyperlink().is_some());
+    // Text after the link is closed is plain
+    assert!(row[8].attrs().hyperlink().is_none());
+    assert!(term.get_row(1)[6].attrs().hyperlink().is_none());
+}
+
+#[test]
+fn test_osc52_clipboard_write() {
+    let mut term = EmbeddedTerminal::default_size();
+
+    // base64 of "hello world", terminated by BEL and by ST
+    term.write(b"\x1b]52;c;aGVsbG8gd29ybGQ=\x07");
+    assert_eq!(term.take_clipboard().as_deref(), Some("hello world"));
+    assert_eq!(term.take_clipboard(), None);
+    term.write(b"\x1b]52;;w6lsw6g=\x1b\\");
+    assert_eq!(term.take_clipboard().as_deref(), Some("\u{e9}l\u{e8}"));
+
+    // Clipboard reads are ignored
+    term.write(b"\x1b]52;c;?\x07");
+    assert_eq!(term.take_clipboard(), None);
+    assert!(term.take_responses().is_empty());
+}
+
+#[test]
+fn test_osc52_clipboard_write_is_truncated() {
+    use termwiz::escape::osc::{OperatingSystemCommand, Selection};
+
+    let mut term = EmbeddedTerminal::default_size();
+    // One byte too many, with a two-byte character across the limit
+    let text = format!("{}\u{e9}", "a".repeat(1024 * 1024 - 1));
+    let osc = OperatingSystemCommand::SetSelection(Selection::CLIPBOARD, text).to_string();
+    term.write(osc.as_bytes());
+
+    let copied = term.take_clipboard().unwrap();
+    assert_eq!(copied.len(), 1024 * 1024 - 1);
+    assert!(copied.chars().all(|c| c == 'a'));
+}
+
+#[test]
+fn test_window_title() {
+    let mut term = EmbeddedTerminal::default_size();
+    assert_eq!(term.title(), None);
+
+    // OSC 0 and OSC 2 set the title; the latest wins
+    term.write(b"\x1b]0;builder@buildbox: ~\x07");
+    assert_eq!(term.title(), Some("builder@buildbox: ~"));
+    term.write(b"\x1b]2;main.rs - NVIM\x1b\\");
+    assert_eq!(term.title(), Some("main.rs - NVIM"));
+
+    // An empty title clears it, as does a full reset
+    term.write(b"\x1b]2;\x07");
+    assert_eq!(term.title(), None);
+    term.write(b"\x1b]2;again\x07\x1bc");
+    assert_eq!(term.title(), None);
+
+    term.write(b"\x1b]2;again\x07");
+    term.clear();
+    assert_eq!(term.title(), None);
+}
+
+/// Scrollback and screen rows as text, oldest first
+fn all_text(term: &EmbeddedTerminal) -> Vec<String> {
+    (0..term.total_lines())
+        .filter_map(|i| term.line(i))
+        .map(|row| row.iter().map(|c| c.str()).collect::<String>().trim_end().to_string())
+        .collect()
+}
+
+fn small_terminal(cols: usize, rows: usize) -> EmbeddedTerminal {
+    EmbeddedTerminal::new(TerminalConfig {
+        cols,
+        rows,
+        scrollback: 100,
+        ..Default::default()
+    })
+}
+
+#[test]
+fn test_resize_shrink_rewraps_lines() {
+    let mut term = small_terminal(10, 3);
+    term.write(b"abcdefgh\r\nxy\r\n$ ");
+
+    term.resize(5, 3);
+    // "abcdefgh" now takes two rows; the top one went to the scrollback
+    assert_eq!(all_text(&term), ["abcde", "fgh", "xy", "$"]);
+    assert_eq!(screen_text(&term), ["fgh", "xy", "$"]);
+    // The cursor stays after the prompt
+    assert_eq!((term.cursor().row, term.cursor().col), (2, 2));
+    term.write(b"ls");
+    assert_eq!(screen_text(&term), ["fgh", "xy", "$ ls"]);
+}
+
+#[test]
+fn test_resize_grow_joins_wrapped_lines() {
+    let mut term = small_terminal(4, 6);
+    // Wraps at the right margin: "abcd" / "efgh" / "ij"
+    term.write(b"abcdefghij\r\nkl");
+    assert_eq!(screen_text(&term), ["abcd", "efgh", "ij", "kl", "", ""]);
+
+    term.resize(12, 6);
+    assert_eq!(screen_text(&term), ["abcdefghij", "kl", "", "", "", ""]);
+    assert_eq!((term.cursor().row, term.cursor().col), (1, 2));
+    // Lines ended by a newline are not joined
+    assert_eq!(term.content_as_text(), "abcdefghij\nkl");
+}
+
+#[test]
+fn test_resize_shrink_then_grow_round_trip() {
+    let mut term = small_terminal(20, 5);
+    term.write(b"\x1b[31mred text\x1b[0m and more\r\n");
+    for i in 0..6 {
+        term.write(format!("line {} is long enough\r\n", i).as_bytes());
+    }
+    term.write(b"$ ");
+    let before = term.content_as_text();
+
+    term.resize(6, 5);
+    term.resize(13, 3);
+    term.resize(20, 5);
+    assert_eq!(term.content_as_text(), before);
+    assert_eq!(screen_text(&term)[4], "$");
+    assert_eq!((term.cursor().row, term.cursor().col), (4, 2));
+    // Attributes move with their cells
+    let first = term.line(0).unwrap();
+    assert_eq!(first[0].attrs().foreground(), ColorAttribute::PaletteIndex(1));
+    assert_eq!(first[8].attrs().foreground(), ColorAttribute::Default);
+}
+
+#[test]
+fn test_resize_height_moves_rows_through_scrollback() {
+    let mut term = small_terminal(10, 4);
+    term.write(b"a\r\nb\r\nc\r\nd");
+
+    // Rows above the cursor go to the scrollback instead of being cut off
+    term.resize(10, 2);
+    assert_eq!(term.scrollback().len(), 2);
+    assert_eq!(screen_text(&term), ["c", "d"]);
+    assert_eq!(term.cursor().row, 1);
+
+    // Growing brings them back
+    term.resize(10, 4);
+    assert!(term.scrollback().is_empty());
+    assert_eq!(screen_text(&term), ["a", "b", "c", "d"]);
+    assert_eq!(term.cursor().row, 3);
+}
+
+#[test]
+fn test_resize_keeps_wide_characters_whole() {
+    let mut term = small_terminal(6, 3);
+    term.write("ab\u{4e2d}\u{6587}".as_bytes());
+
+    // The second wide character doesn't fit after "ab中" on 5 columns
+    term.resize(5, 3);
+    assert_eq!(term.content_as_text(), "ab\u{4e2d}\n\u{6587}");
+
+    // Growing back doesn't leave the gap it was wrapped at
+    term.resize(6, 3);
+    assert_eq!(term.content_as_text(), "ab\u{4e2d}\u{6587}");
+}
+
+#[test]
+fn test_resize_in_alternate_screen_rewraps_primary() {
+    let mut term = small_terminal(10, 3);
+    term.write(b"abcdefgh\r\n\x1b[?1049hfull screen");
+
+    term.resize(4, 3);
+    assert_eq!(term.size(), (4, 3));
+    term.write(b"\x1b[?1049l");
+    assert_eq!(screen_text(&term), ["abcd", "efgh", ""]);
+}
+
+#[test]
+fn test_selected_text_spans_lines() {
+    let mut term = small_terminal(10, 4);
+    term.write(b"first\r\nsecond\r\nthird");
+
+    term.start_selection(SelectionPoint { line: 0, col: 2 });
+    term.extend_selection(SelectionPoint { line: 2, col: 1 });
+    assert_eq!(term.selected_text().as_deref(), Some("rst\nsecond\nth"));
+
+    // Extending backwards selects from the new head to the anchor
+    term.extend_selection(SelectionPoint { line: 0, col: 0 });
+    assert_eq!(term.selected_text().as_deref(), Some("fir"));
+
+    term.clear_selection();
+    assert_eq!(term.selected_text(), None);
+}
+
+#[test]
+fn test_selected_text_joins_wrapped_rows() {
+    let mut term = small_terminal(4, 4);
+    term.write("abcdef\r\n\u{4e2d}x".as_bytes());
+
+    // Past the last column is clamped
+    term.start_selection(SelectionPoint { line: 0, col: 1 });
+    term.extend_selection(SelectionPoint { line: 2, col: 9 });
+    assert_eq!(term.selected_text().as_deref(), Some("bcdef\n\u{4e2d}x"));
+
+    // Starting on the right half of a wide character takes all of it
+    term.start_selection(SelectionPoint { line: 2, col: 1 });
+    term.extend_selection(SelectionPoint { line: 2, col: 2 });
+    assert_eq!(term.selected_text().as_deref(), Some("\u{4e2d}x"));
+}
+
+#[test]
+fn test_resize_clears_selection() {
+    let mut term = small_terminal(10, 4);
+    term.write(b"text");
+    term.start_selection(SelectionPoint { line: 0, col: 0 });
+    term.resize(8, 4);
+    assert!(term.selection().is_none());
+}
+
+/// Throughput of writing and drawing a large amount of colored output
+///
+/// Run with `cargo test --release -- --ignored --nocapture bench_`.
+#[test]
+#[ignore]
+fn bench_write_and_render() {
+    use ratatui::buffer::Buffer;
+    use ratatui::layout::Rect;
+    use ratatui::widgets::Widget;
+    use std::time::Instant;
+
+    let (cols, rows) = (200, 60);
+    let mut output = Vec::new();
+    let mut n = 0;
+    while output.len() < 1024 * 1024 {
+        output.extend_from_slice(
+            format!("\x1b[3{}mline {} \x1b[1;4{}m{}\x1b[0m\r\n", n % 8, n, (n + 1) % 8, "x".repeat(n % 150))
+                .as_bytes(),
+        );
+        n += 1;
+    }
+
+    let mut term = EmbeddedTerminal::new(TerminalConfig {
+        cols,
+        rows,
+        scrollback: 10_000,
+        ..Default::default()
+    });
+    let start = Instant::now();
+    // Chunks of the size the PTY is read in
+    for chunk in output.chunks(16 * 1024) {
+        term.write(chunk);
+    }
+    let write_time = start.elapsed();
+
+    let area = Rect::new(0, 0, cols as u16, rows as u16);
+    let mut buf = Buffer::empty(area);
+    let start = Instant::now();
+    for frame in 0..100 {
+        // Half of the frames show the scrollback
+        term.set_scroll_offset(if frame % 2 == 0 { 0 } else { 1000 });
+        TerminalWidget::new(&term).render(area, &mut buf);
+    }
+    let render_time = start.elapsed();
+
+    println!(
+        "write 1 MiB: {:?}, render 100 frames: {:?} ({:?}/frame)",
+        write_time,
+        render_time,
+        render_time / 100
+    );
+}
+
+/// Time to scroll 100k lines through a full scrollback
+///
+/// Run with `cargo test --release -- --ignored --nocapture bench_`.
+#[test]
+#[ignore]
+fn bench_scrollback() {
+    use std::time::Instant;
+
+    let mut term = EmbeddedTerminal::new(TerminalConfig {
+        cols: 120,
+        rows: 40,
+        scrollback: 10_000,
+        ..Default::default()
+    });
+    let output: String = (0..100_000).map(|n| format!("line {}\r\n", n)).collect();
+
+    let start = Instant::now();
+    for chunk in output.as_bytes().chunks(16 * 1024) {
+        term.write(chunk);
+    }
+    println!("scroll 100k lines: {:?}", start.elapsed());
+    assert_eq!(term.scrollback().len(), 10_000);
+}
+
+#[test]
+fn test_scrollback_keeps_newest_lines_trimmed() {
+    let mut term = EmbeddedTerminal::new(TerminalConfig {
+        cols: 20,
+        rows: 2,
+        scrollback: 3,
+        ..Default::default()
+    });
+    for n in 0..10 {
+        term.write(format!("line {}\r\n", n).as_bytes());
+    }
+
+    let scrollback: Vec<String> = term
+        .scrollback()
+        .iter()
+        .map(|row| row.iter().map(|cell| cell.str()).collect())
+        .collect();
+    assert_eq!(scrollback, ["line 6", "line 7", "line 8"]);
+    // Trailing blanks aren't kept
+    assert_eq!(term.scrollback()[0].len(), 6);
+
+    // Screen rows keep their full width
+    term.scroll_up(1);
+    assert_eq!(term.get_row(0).len(), 6);
+    assert_eq!(term.get_row(1).len(), 20);
+}
+
+#[test]
+fn test_restore_cursor_restores_attributes() {
+    for (save, restore) in [(&b"\x1b7"[..], &b"\x1b8"[..]), (b"\x1b[s", b"\x1b[u")] {
+        let mut term = EmbeddedTerminal::default_size();
+        term.write(b"\x1b[31m");
+        term.write(save);
+        term.write(b"\x1b[34mblue");
+        term.write(restore);
+        term.write(b"x");
+
+        // Back at the saved position, in red
+        let cell = &term.get_row(0)[0];
+        assert_eq!(cell.str(), "x");
+        assert_eq!(cell.attrs().foreground(), ColorAttribute::PaletteIndex(1));
+        assert_eq!(term.get_row(0)[1].attrs().foreground(), ColorAttribute::PaletteIndex(4));
+    }
+}
+
+#[test]
+fn test_alternate_screen_has_its_own_saved_cursor() {
+    let mut term = EmbeddedTerminal::default_size();
+    term.write(b"\x1b[3;5H\x1b7");
+
+    // A save on the alternate screen doesn't replace the primary's
+    term.write(b"\x1b[?47h\x1b[10;10H\x1b7\x1b[?47l");
+    term.write(b"\x1b[1;1H\x1b8");
+    assert_eq!((term.cursor().row, term.cursor().col), (2, 4));
+
+    // 1049 puts the cursor back where it was when the program started
+    term.write(b"\x1b[?1049h\x1b[20;20Hfull screen\x1b[?1049l");
+    assert_eq!((term.cursor().row, term.cursor().col), (2, 4));
+}
+
+#[test]
+fn test_full_screen_program_leaves_no_trace() {
+    let mut term = small_terminal(20, 4);
+    term.write(b"$ less file");
+    let before = term.cursor();
+
+    // Like less: enter, draw more than a screenful, exit
+    term.write(b"\x1b[?1049h\x1b[1m");
+    assert_eq!((term.cursor().row, term.cursor().col), (0, 0));
+    for n in 0..10 {
+        term.write(format!("page line {}\r\n", n).as_bytes());
+    }
+    term.write(b"\x1b[?1049l");
+
+    // Frames scrolled on the alternate screen aren't history
+    assert!(term.scrollback().is_empty());
+    assert_eq!(screen_text(&term), ["$ less file", "", "", ""]);
+    assert_eq!((term.cursor().row, term.cursor().col), (before.row, before.col));
+    // Attributes are back to what the prompt used
+    term.write(b"x");
+    assert_eq!(term.get_row(0)[11].attrs().intensity(), termwiz::cell::Intensity::Normal);
+}
+
+#[test]
+fn test_alternate_screen_modes_differ_in_clearing() {
+    let mut term = small_terminal(20, 4);
+
+    // 47 returns to what was left on the alternate screen
+    term.write(b"\x1b[?47hkept\x1b[?47l");
+    term.write(b"\x1b[?47h");
+    assert_eq!(screen_text(&term)[0], "kept");
+    term.write(b"\x1b[?47l");
+
+    // 1047 clears it when leaving
+    term.write(b"\x1b[?1047h\x1b[Hdropped\x1b[?1047l\x1b[?1047h");
+    assert_eq!(screen_text(&term)[0], "");
+    term.write(b"\x1b[?1047l");
+
+    // 1049 always starts on a clear screen
+    term.write(b"\x1b[?47hstale\x1b[?47l\x1b[?1049h");
+    assert_eq!(screen_text(&term)[0], "");
+}
+
+#[test]
+fn test_dec_special_graphics_draws_boxes() {
+    let mut term = small_terminal(10, 4);
+    // A dialog-style box: line drawing in G0, back to ASCII for the text
+    term.write(b"\x1b(0lqqk\x1b(B\r\n\x1b(0x\x1b(Bok\x1b(0x\x1b(B\r\n\x1b(0mqqj\x1b(B\r\nqx");
+    assert_eq!(screen_text(&term), ["┌──┐", "│ok│", "└──┘", "qx"]);
+}
+
+#[test]
+fn test_shift_out_selects_g1() {
+    let mut term = small_terminal(10, 2);
+    term.write(b"\x1b)0q\x0eq\x0fq");
+    assert_eq!(screen_text(&term)[0], "q─q");
+
+    // DECSC saves the character sets, a reset drops them
+    term.write(b"\x0e\x1b7\x0f\x1b8q");
+    assert_eq!(screen_text(&term)[0], "q─q─");
+    term.write(b"\x1bcq");
+    assert_eq!(screen_text(&term)[0], "q");
+}
+
+#[test]
+fn test_erase_uses_current_background() {
+    let blue = ColorAttribute::PaletteIndex(4);
+    let mut term = small_terminal(6, 3);
+    term.write(b"abcdef\x1b[44m\x1b[2K");
+    for cell in term.get_row(1) {
+        assert_eq!(cell.str(), " ");
+        assert_eq!(cell.attrs().background(), blue);
+        // Only the background carries over
+        assert!(!cell.attrs().wrapped());
+    }
+
+    // Erasing to the cursor, ECH and the whole display too
+    term.write(b"\x1b[0m\x1b[H\x1b[41mab\x1b[1K");
+    assert_eq!(term.get_row(0)[2].str(), " ");
+    assert_eq!(term.get_row(0)[2].attrs().background(), ColorAttribute::PaletteIndex(1));
+    assert_eq!(term.get_row(0)[3].str(), "d");
+    term.write(b"\x1b[42m\x1b[2J\x1b[1;1H\x1b[43m\x1b[X");
+    assert_eq!(term.get_row(0)[0].attrs().background(), ColorAttribute::PaletteIndex(3));
+    assert_eq!(term.get_row(2)[5].attrs().background(), ColorAttribute::PaletteIndex(2));
+}
+
+#[test]
+fn test_inserted_and_deleted_lines_use_current_background() {
+    let blue = ColorAttribute::PaletteIndex(4);
+    let mut term = small_terminal(4, 3);
+    term.write(b"a\r\nb\r\nc\x1b[H\x1b[44m");
+
+    // DL pulls a blank line in at the bottom, IL pushes one in at the top
+    term.write(b"\x1b[M");
+    assert_eq!(screen_text(&term), ["b", "c", ""]);
+    assert_eq!(term.get_row(2)[0].attrs().background(), blue);
+    term.write(b"\x1b[2L");
+    assert_eq!(screen_text(&term), ["", "", "b"]);
+    assert_eq!(term.get_row(1)[3].attrs().background(), blue);
+
+    // ICH and DCH blanks
+    term.write(b"\x1b[3;1H\x1b[@");
+    assert_eq!(term.get_row(2)[0].attrs().background(), blue);
+    assert_eq!(term.get_row(2)[1].str(), "b");
+}
+
+#[test]
+fn test_window_size_reports() {
+    let mut term = small_terminal(100, 30);
+    term.write(b"\x1b[18t\x1b[19t");
+    assert_eq!(term.take_responses(), b"\x1b[8;30;100t\x1b[9;30;100t");
+
+    // Pixel sizes need the cell size, and other ops are ignored
+    term.write(b"\x1b[14t\x1b[16t\x1b[2t");
+    assert!(term.take_responses().is_empty());
+
+    let mut term = EmbeddedTerminal::new(TerminalConfig {
+        cols: 100,
+        rows: 30,
+        cell_pixels: Some((9, 18)),
+        ..Default::default()
+    });
+    term.write(b"\x1b[14t\x1b[16t");
+    assert_eq!(term.take_responses(), b"\x1b[4;540;900t\x1b[6;18;9t");
+}
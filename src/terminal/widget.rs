@@ -11,7 +11,7 @@ use ratatui::widgets::Widget;
 use termwiz::cell::CellAttributes;
 use termwiz::color::ColorAttribute;
 
-use super::EmbeddedTerminal;
+use super::{CursorShape, EmbeddedTerminal, SearchMatch, SearchQuery};
 
 /// Convert termwiz color to ratatui color
 pub fn termwiz_to_ratatui_color(color: &ColorAttribute) -> ratatui::style::Color {
@@ -72,8 +72,15 @@ pub fn convert_attrs(attrs: &CellAttributes) -> Style {
         modifiers |= Modifier::ITALIC;
     }
 
-    if attrs.underline() != Underline::None {
+    // Hyperlinks are underlined so they stand out from plain text.
+    // Ratatui's cells have a single underline modifier, so double, curly,
+    // dotted and dashed underlines are drawn as plain ones; their color
+    // (SGR 58) is kept.
+    if attrs.underline() != Underline::None || attrs.hyperlink().is_some() {
         modifiers |= Modifier::UNDERLINED;
+        if attrs.underline_color() != ColorAttribute::Default {
+            style = style.underline_color(termwiz_to_ratatui_color(&attrs.underline_color()));
+        }
     }
 
     if attrs.blink() != Blink::None {
@@ -95,11 +102,23 @@ pub fn convert_attrs(attrs: &CellAttributes) -> Style {
     style.add_modifier(modifiers)
 }
 
+/// Search matches to highlight while rendering
+struct Highlight<'a> {
+    query: &'a SearchQuery,
+    /// Match the user jumped to, drawn with `current_style`
+    current: Option<&'a SearchMatch>,
+    style: Style,
+    current_style: Style,
+}
+
 /// Widget for rendering an embedded terminal to ratatui
 pub struct TerminalWidget<'a> {
     terminal: &'a EmbeddedTerminal,
     /// Whether to show cursor
     show_cursor: bool,
+    highlight: Option<Highlight<'a>>,
+    /// Style of selected cells
+    selection_style: Style,
 }
 
 impl<'a> TerminalWidget<'a> {
@@ -107,25 +126,62 @@ impl<'a> TerminalWidget<'a> {
         Self {
             terminal,
             show_cursor: true,
+            highlight: None,
+            selection_style: Style::default().add_modifier(Modifier::REVERSED),
         }
     }
 
+    /// Draw selected cells with `style`
+    pub fn selection_style(mut self, style: Style) -> Self {
+        self.selection_style = style;
+        self
+    }
+
     pub fn show_cursor(mut self, show: bool) -> Self {
         self.show_cursor = show;
         self
     }
+
+    /// Highlight matches of `query` with `style`, and `current` with
+    /// `current_style`
+    pub fn highlight(
+        mut self,
+        query: &'a SearchQuery,
+        current: Option<&'a SearchMatch>,
+        style: Style,
+        current_style: Style,
+    ) -> Self {
+        self.highlight = Some(Highlight {
+            query,
+            current,
+            style,
+            current_style,
+        });
+        self
+    }
 }
 
 impl<'a> Widget for TerminalWidget<'a> {
     fn render(self, area: Rect, buf: &mut Buffer) {
         let (term_cols, term_rows) = self.terminal.size();
+        let top_line = self.terminal.viewport_top_line();
+        // Scrollback rows are stored without their trailing blanks
+        let blank_style = convert_attrs(&CellAttributes::default());
 
         // Render each cell
         for y in 0..area.height.min(term_rows as u16) {
             let row = self.terminal.get_row(y as usize);
 
+            // Cells covered by the previous (wide) character
+            let mut skip = 0;
             for x in 0..area.width.min(term_cols as u16) {
+                if skip > 0 {
+                    skip -= 1;
+                    continue;
+                }
                 if let Some(cell) = row.get(x as usize) {
+                    skip = cell.width().saturating_sub(1);
+
                     let buf_x = area.x + x;
                     let buf_y = area.y + y;
 
@@ -138,20 +194,64 @@ impl<'a> Widget for TerminalWidget<'a> {
 
                     // Set in buffer
                     buf.set_string(buf_x, buf_y, display_char, style);
+                } else {
+                    buf.set_string(area.x + x, area.y + y, " ", blank_style);
+                }
+            }
+
+            // Search highlights on top of the cell styles
+            if let Some(ref highlight) = self.highlight {
+                let line = top_line + y as usize;
+                for cols in highlight.query.find_in_row(row) {
+                    let is_current = highlight
+                        .current
+                        .is_some_and(|m| m.line == line && m.cols == cols);
+                    let style = if is_current {
+                        highlight.current_style
+                    } else {
+                        highlight.style
+                    };
+                    let end = cols.end.min(area.width as usize);
+                    for x in cols.start..end {
+                        if let Some(buf_cell) = buf.cell_mut((area.x + x as u16, area.y + y)) {
+                            buf_cell.set_style(style);
+                        }
+                    }
                 }
             }
         }
 
-        // Render cursor if visible and at bottom (following)
-        if self.show_cursor && self.terminal.is_at_bottom() {
+        // Selection on top of everything else
+        if let Some(selection) = self.terminal.selection() {
+            for y in 0..area.height.min(term_rows as u16) {
+                let line = top_line + y as usize;
+                let Some(cols) = selection.cols_in_line(line, term_cols) else {
+                    continue;
+                };
+                for x in cols.start..cols.end.min(area.width as usize) {
+                    if let Some(buf_cell) = buf.cell_mut((area.x + x as u16, area.y + y)) {
+                        buf_cell.set_style(self.selection_style);
+                    }
+                }
+            }
+        }
+
+        // Render cursor if visible and at bottom (following); the child
+        // hides it while redrawing
+        if self.show_cursor && self.terminal.cursor_visible() && self.terminal.is_at_bottom() {
             let cursor = self.terminal.cursor();
             if cursor.col < area.width as usize && cursor.row < area.height as usize {
                 let buf_x = area.x + cursor.col as u16;
                 let buf_y = area.y + cursor.row as u16;
 
-                // Invert the cell at cursor position
+                // Underline the cell for an underline cursor, otherwise
+                // invert it (a bar can't be drawn inside a cell)
+                let modifier = match self.terminal.cursor_shape() {
+                    CursorShape::Underline => Modifier::UNDERLINED,
+                    CursorShape::Block | CursorShape::Bar => Modifier::REVERSED,
+                };
                 if let Some(buf_cell) = buf.cell_mut((buf_x, buf_y)) {
-                    buf_cell.set_style(buf_cell.style().add_modifier(Modifier::REVERSED));
+                    buf_cell.set_style(buf_cell.style().add_modifier(modifier));
                 }
             }
         }
@@ -189,6 +289,105 @@ mod tests {
         assert_eq!(buf.cell((4, 0)).unwrap().symbol(), "o");
     }
 
+    #[test]
+    fn test_widget_renders_sgr_colors() {
+        use ratatui::style::{Color, Modifier};
+
+        let mut term = EmbeddedTerminal::new(TerminalConfig {
+            cols: 10,
+            rows: 2,
+            scrollback: 100,
+            ..Default::default()
+        });
+
+        // e.g. `ls --color`: bold red "ab", then plain "c"
+        term.write(b"\x1b[1;31mab\x1b[0mc");
+
+        let mut buf = Buffer::empty(Rect::new(0, 0, 10, 2));
+        TerminalWidget::new(&term).render(Rect::new(0, 0, 10, 2), &mut buf);
+
+        let colored = buf.cell((0, 0)).unwrap();
+        assert_eq!(colored.fg, Color::Red);
+        assert!(colored.modifier.contains(Modifier::BOLD));
+        let plain = buf.cell((2, 0)).unwrap();
+        assert_eq!(plain.symbol(), "c");
+        assert_eq!(plain.fg, Color::Reset);
+        assert!(!plain.modifier.contains(Modifier::BOLD));
+    }
+
+    #[test]
+    fn test_widget_blanks_rest_of_scrollback_row() {
+        let mut term = EmbeddedTerminal::new(TerminalConfig {
+            cols: 10,
+            rows: 2,
+            scrollback: 100,
+            ..Default::default()
+        });
+        term.write(b"ab\r\ncd\r\nef");
+        term.scroll_up(1);
+
+        // Cells past the end of a stored row overwrite what was there
+        let mut buf = Buffer::empty(Rect::new(0, 0, 10, 2));
+        buf.set_string(0, 0, "xxxxxxxxxx", Style::default());
+        TerminalWidget::new(&term).render(Rect::new(0, 0, 10, 2), &mut buf);
+
+        assert_eq!(buf.cell((1, 0)).unwrap().symbol(), "b");
+        assert_eq!(buf.cell((2, 0)).unwrap().symbol(), " ");
+        assert_eq!(buf.cell((9, 0)).unwrap().symbol(), " ");
+    }
+
+    #[test]
+    fn test_convert_underline_styles() {
+        use ratatui::style::Color;
+        use termwiz::cell::Underline;
+
+        for underline in [
+            Underline::Single,
+            Underline::Double,
+            Underline::Curly,
+            Underline::Dotted,
+            Underline::Dashed,
+        ] {
+            let mut attrs = CellAttributes::default();
+            attrs.set_underline(underline);
+            let style = convert_attrs(&attrs);
+            assert!(style.add_modifier.contains(Modifier::UNDERLINED), "{:?}", underline);
+            assert_eq!(style.underline_color, None);
+
+            attrs.set_underline_color(ColorAttribute::PaletteIndex(1));
+            assert_eq!(convert_attrs(&attrs).underline_color, Some(Color::Red));
+        }
+
+        // A color alone doesn't underline
+        let mut attrs = CellAttributes::default();
+        attrs.set_underline_color(ColorAttribute::PaletteIndex(1));
+        let style = convert_attrs(&attrs);
+        assert!(!style.add_modifier.contains(Modifier::UNDERLINED));
+        assert_eq!(style.underline_color, None);
+    }
+
+    #[test]
+    fn test_widget_renders_curly_underline_color() {
+        use ratatui::style::Color;
+
+        let mut term = EmbeddedTerminal::new(TerminalConfig {
+            cols: 10,
+            rows: 2,
+            scrollback: 100,
+            ..Default::default()
+        });
+        // nvim diagnostics: curly underline in red
+        term.write(b"\x1b[4:3m\x1b[58:5:1merr\x1b[0m");
+
+        let mut buf = Buffer::empty(Rect::new(0, 0, 10, 2));
+        TerminalWidget::new(&term).render(Rect::new(0, 0, 10, 2), &mut buf);
+
+        let cell = buf.cell((0, 0)).unwrap();
+        assert!(cell.modifier.contains(Modifier::UNDERLINED));
+        assert_eq!(cell.underline_color, Color::Red);
+        assert!(!buf.cell((3, 0)).unwrap().modifier.contains(Modifier::UNDERLINED));
+    }
+
     #[test]
     fn test_color_conversion() {
         use ratatui::style::Color;
@@ -263,4 +462,103 @@ mod tests {
         let cursor_cell = buf.cell((2, 0)).unwrap();
         assert!(cursor_cell.modifier.contains(Modifier::REVERSED));
     }
+
+    #[test]
+    fn test_widget_cursor_visibility_and_shape() {
+        let mut term = EmbeddedTerminal::new(TerminalConfig {
+            cols: 10,
+            rows: 2,
+            scrollback: 100,
+            ..Default::default()
+        });
+        let render = |term: &EmbeddedTerminal| {
+            let mut buf = Buffer::empty(Rect::new(0, 0, 10, 2));
+            TerminalWidget::new(term).render(Rect::new(0, 0, 10, 2), &mut buf);
+            buf.cell((2, 0)).unwrap().modifier
+        };
+
+        // Hidden with DECTCEM: no cursor drawn
+        term.write(b"Hi\x1b[?25l");
+        assert!(!term.cursor_visible());
+        assert_eq!(render(&term), Modifier::empty());
+
+        // Shown again: reversed block
+        term.write(b"\x1b[?25h");
+        assert_eq!(render(&term), Modifier::REVERSED);
+
+        // DECSCUSR 4: steady underline
+        term.write(b"\x1b[4 q");
+        assert_eq!(term.cursor_shape(), CursorShape::Underline);
+        assert!(!term.cursor_blink());
+        assert_eq!(render(&term), Modifier::UNDERLINED);
+
+        // DECSCUSR 0 returns to the default blinking block
+        term.write(b"\x1b[0 q");
+        assert_eq!(term.cursor_shape(), CursorShape::Block);
+        assert!(term.cursor_blink());
+    }
+
+    #[test]
+    fn test_widget_renders_selection() {
+        use crate::terminal::SelectionPoint;
+        use ratatui::style::Color;
+
+        let mut term = EmbeddedTerminal::new(TerminalConfig {
+            cols: 10,
+            rows: 3,
+            scrollback: 100,
+            ..Default::default()
+        });
+        term.write(b"first\r\nsecond\r\nthird");
+        term.start_selection(SelectionPoint { line: 0, col: 3 });
+        term.extend_selection(SelectionPoint { line: 1, col: 1 });
+
+        let mut buf = Buffer::empty(Rect::new(0, 0, 10, 3));
+        TerminalWidget::new(&term)
+            .show_cursor(false)
+            .selection_style(Style::default().bg(Color::Blue))
+            .render(Rect::new(0, 0, 10, 3), &mut buf);
+
+        assert_eq!(buf.cell((2, 0)).unwrap().bg, Color::Reset);
+        // To the end of the first row, then the start of the second
+        assert_eq!(buf.cell((3, 0)).unwrap().bg, Color::Blue);
+        assert_eq!(buf.cell((9, 0)).unwrap().bg, Color::Blue);
+        assert_eq!(buf.cell((1, 1)).unwrap().bg, Color::Blue);
+        assert_eq!(buf.cell((2, 1)).unwrap().bg, Color::Reset);
+        assert_eq!(buf.cell((0, 2)).unwrap().bg, Color::Reset);
+    }
+
+    #[test]
+    fn test_widget_highlights_search_matches() {
+        use crate::terminal::SearchQuery;
+        use ratatui::style::Color;
+
+        let mut term = EmbeddedTerminal::new(TerminalConfig {
+            cols: 20,
+            rows: 2,
+            scrollback: 100,
+            ..Default::default()
+        });
+        term.write(b"an Error, an error");
+
+        let query = SearchQuery {
+            text: "error".to_string(),
+            case_sensitive: false,
+        };
+        let current = SearchMatch { line: 0, cols: 13..18 };
+        let style = Style::default().bg(Color::Yellow);
+        let current_style = Style::default().bg(Color::Red);
+
+        let mut buf = Buffer::empty(Rect::new(0, 0, 20, 2));
+        TerminalWidget::new(&term)
+            .show_cursor(false)
+            .highlight(&query, Some(&current), style, current_style)
+            .render(Rect::new(0, 0, 20, 2), &mut buf);
+
+        assert_eq!(buf.cell((2, 0)).unwrap().bg, Color::Reset);
+        assert_eq!(buf.cell((3, 0)).unwrap().bg, Color::Yellow);
+        assert_eq!(buf.cell((7, 0)).unwrap().bg, Color::Yellow);
+        assert_eq!(buf.cell((8, 0)).unwrap().bg, Color::Reset);
+        assert_eq!(buf.cell((13, 0)).unwrap().bg, Color::Red);
+    }
 }
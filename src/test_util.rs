@@ -0,0 +1,39 @@
+//! Fixtures shared by the unit tests
+
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+
+/// An empty directory under the system temp dir, deleted when dropped
+///
+/// Named after the process and `name`, so tests running in parallel each
+/// get their own; a directory left behind by a killed run is emptied.
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    pub fn new(name: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!("drun-test-{}-{}", std::process::id(), name));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        Self(dir)
+    }
+}
+
+impl Deref for TempDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl AsRef<Path> for TempDir {
+    fn as_ref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
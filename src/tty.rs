@@ -0,0 +1,152 @@
+//! Asking the controlling terminal with escape sequences
+//!
+//! Startup queries (background color, graphics support) talk to `/dev/tty`
+//! directly, before the event reader starts and would swallow the answers.
+//! Every query ends with a device attributes request (DA1), which all
+//! terminals answer, and answers come back in order: once the DA1 answer
+//! is in, so is everything the terminal was going to say. A terminal that
+//! ignores the rest doesn't cost the whole timeout.
+
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Write};
+use std::os::fd::AsRawFd;
+use std::time::{Duration, Instant};
+
+/// Device attributes request, the sentinel ending every query
+const DA1: &[u8] = b"\x1b[c";
+
+/// How long to wait for the terminal to answer the keyboard protocol query
+pub const KEYBOARD_QUERY_TIMEOUT: Duration = Duration::from_millis(200);
+
+/// `/dev/tty` with echo and line buffering off, restored when dropped
+pub struct RawTty {
+    tty: File,
+    saved: libc::termios,
+}
+
+impl RawTty {
+    /// None without a controlling terminal
+    pub fn open() -> Option<Self> {
+        let tty = OpenOptions::new().read(true).write(true).open("/dev/tty").ok()?;
+        let fd = tty.as_raw_fd();
+        // SAFETY: termios is plain data; tcgetattr only writes to it
+        let saved = unsafe {
+            let mut termios: libc::termios = std::mem::zeroed();
+            (libc::tcgetattr(fd, &mut termios) == 0).then_some(termios)?
+        };
+        let mut raw = saved;
+        raw.c_lflag &= !(libc::ICANON | libc::ECHO);
+        raw.c_cc[libc::VMIN] = 0;
+        raw.c_cc[libc::VTIME] = 0;
+        // SAFETY: fd is open and raw is a valid termios
+        unsafe { libc::tcsetattr(fd, libc::TCSANOW, &raw) };
+        Some(Self { tty, saved })
+    }
+
+    /// Send `request` followed by DA1 and collect the answers
+    ///
+    /// None if the DA1 answer doesn't arrive within `timeout`: the terminal
+    /// is too slow or doesn't answer at all, and late answers may still
+    /// come in.
+    pub fn query(&mut self, request: &[u8], timeout: Duration) -> Option<Vec<u8>> {
+        self.tty.write_all(request).ok()?;
+        self.tty.write_all(DA1).ok()?;
+        self.tty.flush().ok()?;
+
+        let deadline = Instant::now() + timeout;
+        let mut reply = Vec::new();
+        let mut buf = [0u8; 256];
+        while !answered_da1(&reply) {
+            let left = deadline.saturating_duration_since(Instant::now());
+            let mut pfd = libc::pollfd {
+                fd: self.tty.as_raw_fd(),
+                events: libc::POLLIN,
+                revents: 0,
+            };
+            // SAFETY: pfd is a valid pollfd for the duration of the call
+            let ready = unsafe { libc::poll(&mut pfd, 1, left.as_millis().max(1) as libc::c_int) };
+            if ready <= 0 || left.is_zero() {
+                return None;
+            }
+            match self.tty.read(&mut buf) {
+                Ok(n) if n > 0 => reply.extend_from_slice(&buf[..n]),
+                _ => return None,
+            }
+        }
+        Some(reply)
+    }
+
+    /// Terminal size in cells and pixels
+    pub fn window_size(&self) -> Option<libc::winsize> {
+        // SAFETY: winsize is plain data; TIOCGWINSZ only writes to it
+        unsafe {
+            let mut size: libc::winsize = std::mem::zeroed();
+            (libc::ioctl(self.tty.as_raw_fd(), libc::TIOCGWINSZ, &mut size) == 0).then_some(size)
+        }
+    }
+}
+
+impl Drop for RawTty {
+    fn drop(&mut self) {
+        // SAFETY: saved came from tcgetattr on the same fd
+        unsafe { libc::tcsetattr(self.tty.as_raw_fd(), libc::TCSANOW, &self.saved) };
+    }
+}
+
+/// Whether the terminal speaks the kitty keyboard protocol
+///
+/// Terminals that do answer `CSI ? u` with their current flags
+/// (`ESC [ ? flags u`). With it, keys typed on the keypad are told apart
+/// from the main ones, for commands in application keypad mode.
+pub fn supports_keyboard_enhancement(timeout: Duration) -> bool {
+    let reply = RawTty::open().and_then(|mut tty| tty.query(b"\x1b[?u", timeout));
+    let supported = reply.is_some_and(|reply| answered_keyboard_flags(&reply));
+    tracing::info!("Kitty keyboard protocol: {}", supported);
+    supported
+}
+
+/// Whether `reply` holds an answer to `CSI ? u`
+fn answered_keyboard_flags(reply: &[u8]) -> bool {
+    reply.windows(3).enumerate().any(|(start, w)| {
+        if w != b"\x1b[?" {
+            return false;
+        }
+        let rest = &reply[start + 3..];
+        let digits = rest.iter().take_while(|b| b.is_ascii_digit()).count();
+        digits > 0 && rest.get(digits) == Some(&b'u')
+    })
+}
+
+/// Whether `reply` holds the answer to DA1 (`ESC [ ? ... c`)
+fn answered_da1(reply: &[u8]) -> bool {
+    da1_answer(reply).is_some()
+}
+
+/// Parameters of the DA1 answer in `reply`, like `62;4;22`
+pub fn da1_answer(reply: &[u8]) -> Option<&[u8]> {
+    let start = reply.windows(3).position(|w| w == b"\x1b[?")? + 3;
+    let len = reply[start..].iter().position(|&b| b == b'c')?;
+    Some(&reply[start..start + len])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_da1_answer() {
+        assert!(answered_da1(b"\x1b]11;rgb:0/0/0\x07\x1b[?62;22c"));
+        assert!(!answered_da1(b"\x1b]11;rgb:0/0/0\x07\x1b[?62;2"));
+        assert_eq!(da1_answer(b"\x1b[6;18;9t\x1b[?62;4;22c"), Some(&b"62;4;22"[..]));
+        assert_eq!(da1_answer(b"\x1b[?c"), Some(&b""[..]));
+    }
+
+    #[test]
+    fn test_keyboard_flags_answer() {
+        assert!(answered_keyboard_flags(b"\x1b[?0u\x1b[?62;22c"));
+        assert!(answered_keyboard_flags(b"\x1b[?15u\x1b[?62;22c"));
+        // Only DA1: no kitty keyboard protocol
+        assert!(!answered_keyboard_flags(b"\x1b[?62;22c"));
+        assert!(!answered_keyboard_flags(b"\x1b[?u"));
+    }
+}
@@ -0,0 +1,197 @@
+//! Entry card templates (`appearance.entry.format`)
+//!
+//! Each line of the template is a line of the card. `{name}`, `{generic}`,
+//! `{comment}`, `{categories}`, `{exec}` and `{uses}` (launch count) stand
+//! for the entry's fields, `{comment|generic}` for the first of them the
+//! entry has, and `{{`/`}}` for literal braces. Anything else in braces is
+//! shown as written, with a warning when the config is loaded.
+
+use serde::{Deserialize, Serialize};
+
+/// A field of the entry a placeholder stands for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    Name,
+    Generic,
+    Comment,
+    Categories,
+    Exec,
+    /// How often the entry was launched
+    Uses,
+}
+
+impl Field {
+    fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "name" => Self::Name,
+            "generic" => Self::Generic,
+            "comment" => Self::Comment,
+            "categories" => Self::Categories,
+            "exec" => Self::Exec,
+            "uses" => Self::Uses,
+            _ => return None,
+        })
+    }
+}
+
+/// Part of a card line
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Segment {
+    Text(String),
+    /// The first of these fields the entry has
+    Field(Vec<Field>),
+}
+
+/// A parsed card template
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub struct CardFormat {
+    source: String,
+    lines: Vec<Vec<Segment>>,
+    /// Placeholders that aren't fields, as written
+    unknown: Vec<String>,
+}
+
+impl CardFormat {
+    /// Parse `source`; never fails, unknown placeholders become text
+    pub fn parse(source: &str) -> Self {
+        let mut unknown = Vec::new();
+        let lines = source.lines().map(|line| parse_line(line, &mut unknown)).collect();
+        Self {
+            source: source.to_string(),
+            lines,
+            unknown,
+        }
+    }
+
+    pub fn lines(&self) -> &[Vec<Segment>] {
+        &self.lines
+    }
+
+    /// Lines of a card, at least the name's
+    pub fn height(&self) -> u16 {
+        self.lines.len().clamp(1, u16::MAX as usize) as u16
+    }
+
+    /// Placeholders shown literally because they name no field
+    pub fn unknown(&self) -> &[String] {
+        &self.unknown
+    }
+}
+
+impl From<String> for CardFormat {
+    fn from(source: String) -> Self {
+        let format = Self::parse(&source);
+        for placeholder in format.unknown() {
+            tracing::warn!("Unknown placeholder {} in appearance.entry.format", placeholder);
+        }
+        format
+    }
+}
+
+impl From<CardFormat> for String {
+    fn from(format: CardFormat) -> Self {
+        format.source
+    }
+}
+
+fn parse_line(line: &str, unknown: &mut Vec<String>) -> Vec<Segment> {
+    let mut segments = Vec::new();
+    let mut text = String::new();
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' | '}' if chars.peek() == Some(&c) => {
+                chars.next();
+                text.push(c);
+            }
+            '{' => {
+                let mut name = String::new();
+                let mut closed = false;
+                for c in chars.by_ref() {
+                    if c == '}' {
+                        closed = true;
+                        break;
+                    }
+                    name.push(c);
+                }
+                let fields = closed
+                    .then(|| name.split('|').map(|field| Field::from_name(field.trim())).collect())
+                    .flatten();
+                match fields {
+                    Some(fields) => {
+                        if !text.is_empty() {
+                            segments.push(Segment::Text(std::mem::take(&mut text)));
+                        }
+                        segments.push(Segment::Field(fields));
+                    }
+                    None => {
+                        let written = format!("{{{}{}", name, if closed { "}" } else { "" });
+                        text.push_str(&written);
+                        unknown.push(written);
+                    }
+                }
+            }
+            c => text.push(c),
+        }
+    }
+    if !text.is_empty() {
+        segments.push(Segment::Text(text));
+    }
+    segments
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(s: &str) -> Segment {
+        Segment::Text(s.to_string())
+    }
+
+    #[test]
+    fn test_parse_fields() {
+        let format = CardFormat::parse("{name}\n{comment|generic}\nin {categories} ({uses}x)");
+        assert_eq!(format.height(), 3);
+        assert_eq!(
+            format.lines(),
+            [
+                vec![Segment::Field(vec![Field::Name])],
+                vec![Segment::Field(vec![Field::Comment, Field::Generic])],
+                vec![
+                    text("in "),
+                    Segment::Field(vec![Field::Categories]),
+                    text(" ("),
+                    Segment::Field(vec![Field::Uses]),
+                    text("x)"),
+                ],
+            ]
+        );
+        assert!(format.unknown().is_empty());
+        assert_eq!(String::from(format), "{name}\n{comment|generic}\nin {categories} ({uses}x)");
+    }
+
+    #[test]
+    fn test_parse_escaped_braces() {
+        let format = CardFormat::parse("{{name}} {exec} }} {{");
+        assert_eq!(
+            format.lines(),
+            [vec![text("{name} "), Segment::Field(vec![Field::Exec]), text(" } {")]]
+        );
+        assert!(format.unknown().is_empty());
+    }
+
+    #[test]
+    fn test_parse_unknown_placeholders() {
+        // Shown as written, and listed for the warning
+        let format = CardFormat::parse("{name} {icon} {comment|keywords} {} {exec");
+        assert_eq!(
+            format.lines(),
+            [vec![
+                Segment::Field(vec![Field::Name]),
+                text(" {icon} {comment|keywords} {} {exec"),
+            ]]
+        );
+        assert_eq!(format.unknown(), ["{icon}", "{comment|keywords}", "{}", "{exec"]);
+    }
+}
@@ -4,26 +4,176 @@
 //! - Executing mode (live output)
 //! - Post-execution mode (results display)
 
+use std::time::Duration;
+
 use ratatui::{
-    layout::{Constraint, Direction, Layout},
-    style::Style,
-    widgets::{Block, Borders, Paragraph},
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::Line,
+    widgets::{Block, Clear, Paragraph},
     Frame,
 };
+use unicode_width::UnicodeWidthStr;
 
-use crate::app::App;
+use crate::app::{App, LinkPicker, OutputSearch};
 use crate::executor::CommandStatus;
 use crate::terminal::TerminalWidget;
+use crate::util::format_duration;
+use crate::ui::layout::BorderStyle;
+use crate::ui::text::truncate;
 use crate::ui::theme::Theme;
 
+/// Braille spinner frames shown while a command runs
+const SPINNER: &[&str] = &["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
+/// Time each spinner frame is shown
+const SPINNER_FRAME_MS: u128 = 80;
+
+/// Spinner frame for a command that has been running for `elapsed`
+pub(super) fn spinner_frame(elapsed: Duration) -> &'static str {
+    SPINNER[(elapsed.as_millis() / SPINNER_FRAME_MS) as usize % SPINNER.len()]
+}
+
+/// Exit status label and color
+pub(super) fn exit_label(exit_status: &CommandStatus, theme: &Theme) -> (String, Color) {
+    match exit_status {
+        CommandStatus::Exited(0) => ("Exit: 0".to_string(), theme.exit_success),
+        CommandStatus::Exited(code) => (format!("Exit: {}", code), theme.exit_failure),
+        CommandStatus::Signaled(sig) => (format!("Signal: {}", sig), theme.exit_failure),
+        CommandStatus::Running => ("Running".to_string(), theme.accent),
+        CommandStatus::Unknown => ("Unknown".to_string(), theme.dimmed),
+    }
+}
+
+/// Terminal widget for the output area, highlighting output search matches
+/// and the selection
+fn output_widget<'a>(app: &'a App, theme: &Theme) -> TerminalWidget<'a> {
+    let widget = TerminalWidget::new(app.terminal())
+        .selection_style(Style::default().fg(theme.selection_fg).bg(theme.selection_bg));
+    match app.output_search() {
+        Some(search) => {
+            let style = Style::default().fg(theme.background).bg(theme.search_highlight);
+            let current_style = style.add_modifier(Modifier::BOLD | Modifier::UNDERLINED);
+            widget.highlight(&search.query, search.current.as_ref(), style, current_style)
+        }
+        None => widget,
+    }
+}
+
+/// Header line for `command`, with the title the command set if enabled
+fn command_header(app: &App, command: &str) -> String {
+    match app.terminal().title() {
+        Some(title) if app.config().appearance.show_window_title => {
+            format!("$ {} — \"{}\"", command, title)
+        }
+        _ => format!("$ {}", command),
+    }
+}
+
+/// Status bar for the output views: the search prompt while searching,
+/// else a fresh save message, else `status`
+fn output_status(app: &App, status: String, color: Color, theme: &Theme) -> (String, Color) {
+    if let Some(search) = app.output_search() {
+        return (search_status(search), theme.search_highlight);
+    }
+    match app.status_message() {
+        Some(message) if message.error => (format!(" {}", message.text), theme.exit_failure),
+        Some(message) => (format!(" {}", message.text), theme.exit_success),
+        None => (status, color),
+    }
+}
+
+/// Status bar text while searching the output
+fn search_status(search: &OutputSearch) -> String {
+    if search.editing {
+        let case = if search.query.case_sensitive {
+            "match case"
+        } else {
+            "ignore case"
+        };
+        return format!(
+            " /{}▏ [{}] | Enter: find | Tab: toggle case | ESC: cancel",
+            search.query.text, case
+        );
+    }
+    let position = match search.position {
+        Some((_, 0)) => "no matches".to_string(),
+        Some((index, count)) => format!("{}/{}", index, count),
+        None => "-".to_string(),
+    };
+    format!(
+        " /{} {} | n/N: next/prev | /: new search | ESC: close search",
+        search.query.text, position
+    )
+}
+
+/// Draw the link picker as a numbered list over the bottom of `area`
+fn draw_link_picker(f: &mut Frame, picker: &LinkPicker, area: Rect, border: BorderStyle, theme: &Theme) {
+    let height = (picker.links.len() as u16 + 2).min(area.height);
+    let popup = Rect::new(area.x, area.bottom() - height, area.width, height);
+    f.render_widget(Clear, popup);
+
+    let block = border.block()
+        .border_style(Style::default().fg(theme.accent))
+        .title(" Links ")
+        .style(Style::default().bg(theme.background));
+    let inner = block.inner(popup);
+    f.render_widget(block, popup);
+    if inner.height == 0 {
+        return;
+    }
+
+    // Keep the selection visible
+    let rows = inner.height as usize;
+    let offset = picker.selected.saturating_sub(rows - 1);
+    let lines: Vec<Line> = picker
+        .links
+        .iter()
+        .enumerate()
+        .skip(offset)
+        .take(rows)
+        .map(|(i, link)| {
+            let style = if i == picker.selected {
+                Style::default().fg(theme.selection_fg).bg(theme.selection_bg)
+            } else {
+                Style::default().fg(theme.foreground)
+            };
+            // Only the first nine links have a number key
+            let number = if i < 9 { format!("{}", i + 1) } else { " ".to_string() };
+            Line::styled(format!(" {} {}", number, link), style)
+        })
+        .collect();
+    f.render_widget(Paragraph::new(lines), inner);
+}
+
+/// What the executing status bar should call out
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ExecutingStatus {
+    Running,
+    /// Oneshot command ran past `behavior.oneshot_timeout_secs`
+    TimedOut,
+    /// Ctrl+C was pressed, waiting for the command to exit
+    Terminating,
+}
+
 /// Draw the executing UI - shows command output using terminal emulator
+///
+/// Returns where the output went.
 /// TEAM_000: Phase 2, Unit 2.2 - Output display
 /// TEAM_004: Updated to use theme
 /// TEAM_010: Updated to use TerminalWidget
-pub(crate) fn draw_executing(f: &mut Frame, app: &App, command: &str, theme: &Theme) {
+pub(crate) fn draw_executing(
+    f: &mut Frame,
+    app: &App,
+    area: Rect,
+    command: &str,
+    elapsed: Duration,
+    status: ExecutingStatus,
+    theme: &Theme,
+) -> Rect {
+    let border = app.config().appearance.border;
     // Fill background
     let bg_block = Block::default().style(Style::default().bg(theme.background));
-    f.render_widget(bg_block, f.area());
+    f.render_widget(bg_block, area);
 
     let chunks = Layout::default()
         .direction(Direction::Vertical)
@@ -32,27 +182,33 @@ pub(crate) fn draw_executing(f: &mut Frame, app: &App, command: &str, theme: &Th
             Constraint::Min(1),    // Output
             Constraint::Length(1), // Status bar
         ])
-        .split(f.area());
+        .split(area);
 
-    // Command header
-    let header = Paragraph::new(format!("$ {}", command))
+    // Command header with spinner and elapsed time (the spinner is driven
+    // by elapsed time so it advances on every redraw)
+    let spinner = spinner_frame(elapsed);
+    let width = chunks[0].width.saturating_sub(2) as usize;
+    let header = Paragraph::new(truncate(&command_header(app, command), width).into_owned())
         .style(
             Style::default()
                 .fg(theme.exit_success)
                 .bg(theme.background),
         )
         .block(
-            Block::default()
-                .borders(Borders::ALL)
+            border.block()
                 .border_style(Style::default().fg(theme.exit_success))
-                .title(" Running ")
+                .title(format!(
+                    " Running{} {} {} ",
+                    if app.last_run_elevated() { " (elevated)" } else { "" },
+                    spinner,
+                    format_duration(elapsed)
+                ))
                 .style(Style::default().bg(theme.background)),
         );
     f.render_widget(header, chunks[0]);
 
     // Output area - render terminal widget inside a block
-    let output_block = Block::default()
-        .borders(Borders::ALL)
+    let output_block = border.block()
         .border_style(Style::default().fg(theme.dimmed_alt))
         .title(" Output ")
         .style(Style::default().bg(theme.background));
@@ -62,7 +218,7 @@ pub(crate) fn draw_executing(f: &mut Frame, app: &App, command: &str, theme: &Th
 
     // Render terminal widget
     let terminal = app.terminal();
-    let widget = TerminalWidget::new(terminal).show_cursor(true);
+    let widget = output_widget(app, theme).show_cursor(true);
     f.render_widget(widget, inner_area);
 
     // Status bar - show follow mode indicator
@@ -73,38 +229,45 @@ pub(crate) fn draw_executing(f: &mut Frame, app: &App, command: &str, theme: &Th
     } else {
         "[paused]"
     };
+    let (kill_hint, color) = match status {
+        ExecutingStatus::Running => ("Ctrl+C: kill", theme.accent),
+        ExecutingStatus::TimedOut => ("⚠ timed out, K: kill", theme.exit_failure),
+        ExecutingStatus::Terminating => ("terminating... Ctrl+C: force kill", theme.accent),
+    };
     let status = format!(
-        " {} lines {} | Ctrl+C: kill | j/k: scroll | g/G: top/bottom",
-        total_lines, follow_indicator
+        " {} lines {} | {} | j/k: scroll | g/G: top/bottom | /: search | s: save",
+        total_lines, follow_indicator, kill_hint
     );
+    let (status, color) = output_status(app, status, color, theme);
     let status_bar =
-        Paragraph::new(status).style(Style::default().fg(theme.accent).bg(theme.background));
+        Paragraph::new(status).style(Style::default().fg(color).bg(theme.background));
     f.render_widget(status_bar, chunks[2]);
+    inner_area
 }
 
 /// Draw the post-execution UI - reuses TerminalWidget like Executing mode
+///
+/// Returns where the output went.
 /// TEAM_000: Phase 2, Unit 2.3 - Return to launcher
 /// TEAM_004: Updated to use theme
+#[allow(clippy::too_many_arguments)]
 pub(crate) fn draw_post_execution(
     f: &mut Frame,
     app: &App,
+    area: Rect,
     command: &str,
     exit_status: &CommandStatus,
+    duration: Duration,
     copy_feedback: &Option<std::time::Instant>,
     theme: &Theme,
-) {
+) -> Rect {
     // Determine colors based on exit status
-    let (exit_text, exit_color) = match exit_status {
-        CommandStatus::Exited(0) => ("Exit: 0".to_string(), theme.exit_success),
-        CommandStatus::Exited(code) => (format!("Exit: {}", code), theme.exit_failure),
-        CommandStatus::Signaled(sig) => (format!("Signal: {}", sig), theme.exit_failure),
-        CommandStatus::Running => ("Running".to_string(), theme.accent),
-        CommandStatus::Unknown => ("Unknown".to_string(), theme.dimmed),
-    };
+    let (exit_text, exit_color) = exit_label(exit_status, theme);
+    let border = app.config().appearance.border;
 
     // Fill background
     let bg_block = Block::default().style(Style::default().bg(theme.background));
-    f.render_widget(bg_block, f.area());
+    f.render_widget(bg_block, area);
 
     let chunks = Layout::default()
         .direction(Direction::Vertical)
@@ -113,23 +276,27 @@ pub(crate) fn draw_post_execution(
             Constraint::Min(1),    // Output (terminal widget)
             Constraint::Length(1), // Status bar
         ])
-        .split(f.area());
+        .split(area);
 
-    // Command header with exit status
-    let header = Paragraph::new(format!("$ {} [{}]", command, exit_text))
+    // Command header with exit status, which is never cut off
+    let exit = format!(" [{} · {}]", exit_text, format_duration(duration));
+    let width = (chunks[0].width.saturating_sub(2) as usize).saturating_sub(exit.width());
+    let header = Paragraph::new(format!("{}{}", truncate(&command_header(app, command), width), exit))
         .style(Style::default().fg(exit_color).bg(theme.background))
         .block(
-            Block::default()
-                .borders(Borders::ALL)
+            border.block()
                 .border_style(Style::default().fg(exit_color))
-                .title(" Last Command ")
+                .title(if app.last_run_elevated() {
+                    " Last Command (elevated) "
+                } else {
+                    " Last Command "
+                })
                 .style(Style::default().bg(theme.background)),
         );
     f.render_widget(header, chunks[0]);
 
     // Output area - render terminal widget (same as Executing mode)
-    let output_block = Block::default()
-        .borders(Borders::ALL)
+    let output_block = border.block()
         .border_style(Style::default().fg(theme.dimmed_alt))
         .title(" Output ")
         .style(Style::default().bg(theme.background));
@@ -139,9 +306,18 @@ pub(crate) fn draw_post_execution(
 
     // Render terminal widget (no cursor in post-execution)
     let terminal = app.terminal();
-    let widget = TerminalWidget::new(terminal).show_cursor(false);
+    let widget = output_widget(app, theme).show_cursor(false);
     f.render_widget(widget, inner_area);
 
+    if let Some(picker) = app.link_picker() {
+        draw_link_picker(f, picker, inner_area, border, theme);
+        let status = " Enter/1-9: open link | ↑↓: select | ESC: close";
+        let status_bar =
+            Paragraph::new(status).style(Style::default().fg(theme.accent).bg(theme.background));
+        f.render_widget(status_bar, chunks[2]);
+        return inner_area;
+    }
+
     // Status bar - show scroll info and copy feedback
     let is_at_bottom = terminal.is_at_bottom();
     let total_lines = terminal.total_lines();
@@ -164,12 +340,12 @@ pub(crate) fn draw_post_execution(
 
     let status = if let Some(msg) = copy_feedback {
         format!(
-            " {} lines {} | {} | y: copy | Enter: dismiss | q: quit",
+            " {} lines {} | {} | r: re-run | y: copy | Enter: dismiss | q: quit",
             total_lines, scroll_indicator, msg
         )
     } else {
         format!(
-            " {} lines {} | y: copy | Enter: dismiss | q: quit",
+            " {} lines {} | r: re-run | y: copy | v: select | s: save | /: search | o: links | Enter: dismiss | q: quit",
             total_lines, scroll_indicator
         )
     };
@@ -180,7 +356,35 @@ pub(crate) fn draw_post_execution(
         theme.dimmed
     };
 
+    // Countdown to returning to the launcher
+    let status = match app.auto_dismiss_remaining() {
+        Some(left) => format!(
+            " Returning in {}s (any key to stay) |{}",
+            left.as_secs_f32().ceil() as u64,
+            status
+        ),
+        None => status,
+    };
+
+    let (status, status_color) = output_status(app, status, status_color, theme);
+
+    // Selection keys while selecting
+    let (status, status_color) = match terminal.selection() {
+        Some(selection) => {
+            let (start, end) = selection.ordered();
+            (
+                format!(
+                    " SELECT {} lines | hjkl/arrows: extend | 0/$: line start/end | y: copy | ESC: cancel",
+                    end.line - start.line + 1
+                ),
+                theme.accent,
+            )
+        }
+        None => (status, status_color),
+    };
+
     let status_bar =
         Paragraph::new(status).style(Style::default().fg(status_color).bg(theme.background));
     f.render_widget(status_bar, chunks[2]);
+    inner_area
 }
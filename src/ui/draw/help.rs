@@ -0,0 +1,80 @@
+//! Help overlay listing the keybindings
+
+use ratatui::{
+    layout::Rect,
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{Clear, Paragraph},
+    Frame,
+};
+
+use crate::app::App;
+use crate::keymap;
+use crate::ui::theme::Theme;
+use unicode_width::UnicodeWidthStr;
+
+/// Draw the keybindings, the current mode's first, centered over `area`
+pub(crate) fn draw_help(f: &mut Frame, app: &App, area: Rect, scroll: usize, theme: &Theme) {
+    let border = app.config().appearance.border;
+    let label = |binding| {
+        let keys = keymap::keys_label(binding);
+        let width = keys.width();
+        (keys, width)
+    };
+    // Keys column as wide as the longest keys, in every section alike
+    let keys_width = keymap::KEYMAP
+        .iter()
+        .flat_map(|section| section.bindings)
+        .map(|binding| label(binding).1)
+        .max()
+        .unwrap_or(0);
+    let description_width = keymap::KEYMAP
+        .iter()
+        .flat_map(|section| section.bindings)
+        .map(|binding| binding.description.width())
+        .max()
+        .unwrap_or(0);
+
+    let lines: Vec<Line> = keymap::sections_for(app.key_context())
+        .flat_map(|section| {
+            let title = Line::styled(
+                section.title,
+                Style::default().fg(theme.accent).add_modifier(Modifier::BOLD),
+            );
+            let bindings = section.bindings.iter().map(move |binding| {
+                let (keys, width) = label(binding);
+                Line::from(vec![
+                    Span::styled(
+                        format!("  {}{}", keys, " ".repeat(keys_width - width + 2)),
+                        Style::default().fg(theme.search_highlight),
+                    ),
+                    Span::styled(binding.description, Style::default().fg(theme.foreground)),
+                ])
+            });
+            std::iter::once(title).chain(bindings).chain([Line::default()])
+        })
+        .collect();
+
+    // Indent, keys, gap and description, inside the border
+    let width = ((keys_width + description_width + 6) as u16).min(area.width);
+    let height = (keymap::visible_help_lines(area.height) + 2) as u16;
+    let popup = Rect::new(
+        area.x + (area.width - width) / 2,
+        area.y + (area.height - height.min(area.height)) / 2,
+        width,
+        height.min(area.height),
+    );
+    f.render_widget(Clear, popup);
+
+    let scroll = scroll.min(keymap::max_help_scroll(area.height));
+    let help = Paragraph::new(lines)
+        .scroll((scroll as u16, 0))
+        .block(
+            border.block()
+                .border_style(Style::default().fg(theme.accent))
+                .title(" Keys ")
+                .title_bottom(" ↑↓: scroll | any other key: close ")
+                .style(Style::default().bg(theme.background)),
+        );
+    f.render_widget(help, popup);
+}
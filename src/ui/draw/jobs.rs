@@ -0,0 +1,100 @@
+//! Background jobs view drawing
+
+use ratatui::{
+    layout::{Constraint, Direction, Layout, Rect},
+    style::Style,
+    text::{Line, Span},
+    widgets::{Block, Paragraph},
+    Frame,
+};
+
+use crate::app::App;
+use crate::ui::theme::Theme;
+use crate::util::format_duration;
+
+use super::execution::{exit_label, spinner_frame};
+
+/// Draw the list of background jobs
+pub(crate) fn draw_jobs(f: &mut Frame, app: &App, area: Rect, confirm_exit: bool, theme: &Theme) {
+    let border = app.config().appearance.border;
+    // Fill background
+    let bg_block = Block::default().style(Style::default().bg(theme.background));
+    f.render_widget(bg_block, area);
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Min(1),    // Job list
+            Constraint::Length(1), // Status bar
+        ])
+        .split(area);
+
+    let jobs = app.jobs();
+    let block = border.block()
+        .border_style(Style::default().fg(theme.dimmed_alt))
+        .title(format!(" Jobs ({}) ", jobs.len()))
+        .style(Style::default().bg(theme.background));
+    let inner = block.inner(chunks[0]);
+    f.render_widget(block, chunks[0]);
+
+    if jobs.is_empty() {
+        let empty = Paragraph::new(" No background jobs (Ctrl+B while a command runs)")
+            .style(Style::default().fg(theme.dimmed));
+        f.render_widget(empty, inner);
+    } else if inner.height > 0 {
+        // Keep the selection visible
+        let height = inner.height as usize;
+        let offset = app.selected_job().saturating_sub(height - 1);
+
+        let lines: Vec<Line> = jobs
+            .iter()
+            .enumerate()
+            .skip(offset)
+            .take(height)
+            .map(|(i, job)| {
+                let (state, color) = match job.finished {
+                    Some((ref status, duration)) => {
+                        let (label, color) = exit_label(status, theme);
+                        (format!("{} · {}", label, format_duration(duration)), color)
+                    }
+                    None => {
+                        let elapsed = job.started.elapsed();
+                        let state = format!(
+                            "{} Running · {}",
+                            spinner_frame(elapsed),
+                            format_duration(elapsed)
+                        );
+                        (state, theme.accent)
+                    }
+                };
+                let style = if i == app.selected_job() {
+                    Style::default().fg(theme.selection_fg).bg(theme.selection_bg)
+                } else {
+                    Style::default().fg(theme.foreground)
+                };
+                Line::from(vec![
+                    Span::styled(format!(" {:<22}", state), style.fg(color)),
+                    Span::styled(format!(" $ {}", job.command), style),
+                ])
+            })
+            .collect();
+        f.render_widget(Paragraph::new(lines), inner);
+    }
+
+    let status = if confirm_exit {
+        format!(
+            " {} jobs still running - y: kill them and quit | n/ESC: cancel",
+            app.running_job_count()
+        )
+    } else {
+        " ↑↓: nav | Enter: attach | d: dismiss finished | ESC: back | q: quit".to_string()
+    };
+    let color = if confirm_exit {
+        theme.exit_failure
+    } else {
+        theme.dimmed
+    };
+    let status_bar =
+        Paragraph::new(status).style(Style::default().fg(color).bg(theme.background));
+    f.render_widget(status_bar, chunks[1]);
+}
@@ -8,46 +8,95 @@
 
 use parking_lot::Mutex;
 use ratatui::{
-    layout::{Constraint, Direction, Layout, Rect},
-    style::Style,
-    widgets::{Block, Borders, Paragraph},
+    layout::{Constraint, Direction, Layout, Position, Rect},
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Paragraph, Scrollbar, ScrollbarOrientation, ScrollbarState, Wrap},
     Frame,
 };
 use ratatui_image::{Resize, StatefulImage};
 use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
 use unicode_width::UnicodeWidthStr;
 
-use crate::app::App;
+use crate::app::{App, CategoryList, CompositorHealth, CompositorList};
 use crate::icons::IconManager;
 use crate::ui::entry_card::{EntryCard, EntryDisplayConfig};
+use crate::ui::layout::BorderStyle;
 use crate::ui::theme::Theme;
 
 /// Width of icon column in characters when graphics are supported
 const ICON_COLUMN_WIDTH: u16 = 6;
-/// Gap between columns
-const COLUMN_GAP: u16 = 2;
-
-/// Pre-computed grid dimensions for rendering
-struct GridDimensions {
-    card_height: u16,
-    column_width: u16,
+/// Narrowest screen that has room for the detail pane next to the grid
+const PREVIEW_MIN_WIDTH: u16 = 60;
+
+/// Areas of the launcher screen
+pub struct LauncherAreas {
+    pub search: Rect,
+    /// Entry list, including its border
+    pub list: Rect,
+    /// Detail pane right of the list, when shown
+    pub preview: Option<Rect>,
+    pub status: Rect,
 }
 
-impl GridDimensions {
-    fn compute(
-        inner_width: u16,
-        grid: &crate::ui::layout::GridLayout,
-        entry_config: &EntryDisplayConfig,
-    ) -> Self {
-        let columns = grid.columns as usize;
-        let column_width = if columns > 1 {
-            (inner_width.saturating_sub(COLUMN_GAP * (columns as u16 - 1))) / columns as u16
+impl LauncherAreas {
+    /// Split the screen `area` into the launcher's parts, with room for the
+    /// detail pane if `preview` is set and the screen is wide enough
+    pub fn new(area: Rect, preview: bool) -> Self {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(3), // Search bar
+                Constraint::Min(1),    // Entry list
+                Constraint::Length(1), // Status bar
+            ])
+            .split(area);
+        let (list, preview) = if preview && area.width >= PREVIEW_MIN_WIDTH {
+            let split = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Percentage(60), Constraint::Percentage(40)])
+                .split(chunks[1]);
+            (split[0], Some(split[1]))
         } else {
-            inner_width
+            (chunks[1], None)
         };
         Self {
-            card_height: entry_config.card_height(),
-            column_width,
+            search: chunks[0],
+            list,
+            preview,
+            status: chunks[2],
+        }
+    }
+
+    /// Scrollbar track: the entry list's right border between its corners
+    pub fn scrollbar(&self) -> Rect {
+        Rect {
+            x: (self.list.x + self.list.width).saturating_sub(1),
+            y: self.list.y + 1,
+            width: self.list.width.min(1),
+            height: self.list.height.saturating_sub(2),
+        }
+    }
+
+    /// Page of `pages` for a click `y` rows down the screen on the
+    /// scrollbar, or None if `x`, `y` is not on it
+    pub fn scrollbar_page(&self, x: u16, y: u16, pages: usize) -> Option<usize> {
+        let track = self.scrollbar();
+        if !track.contains(Position::new(x, y)) {
+            return None;
+        }
+        let offset = (y - track.y) as usize;
+        Some((offset * pages / track.height as usize).min(pages.saturating_sub(1)))
+    }
+
+    /// Area inside the entry list's border, where the cards go
+    pub fn list_inner(&self) -> Rect {
+        Rect {
+            x: self.list.x + 1,
+            y: self.list.y + 1,
+            width: self.list.width.saturating_sub(2),
+            height: self.list.height.saturating_sub(2),
         }
     }
 }
@@ -57,26 +106,26 @@ impl GridDimensions {
 pub(crate) fn draw_launcher(
     f: &mut Frame,
     app: &App,
+    area: Rect,
     icon_manager: Option<&Arc<Mutex<IconManager>>>,
     theme: &Theme,
 ) {
     // TEAM_004: Fill background with theme color
-    let area = f.area();
+    let config = app.config();
     let bg_block = Block::default().style(Style::default().bg(theme.background));
     f.render_widget(bg_block, area);
 
-    let chunks = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints([
-            Constraint::Length(3), // Search bar
-            Constraint::Min(1),    // Entry list
-            Constraint::Length(1), // Status bar
-        ])
-        .split(area);
-
-    draw_search_bar(f, app, chunks[0], theme);
-    draw_entry_list(f, app, chunks[1], icon_manager, theme);
-    draw_status_bar(f, app, chunks[2], theme);
+    let areas = LauncherAreas::new(area, app.show_preview() && app.category_list().is_none());
+    draw_search_bar(f, app, areas.search, theme);
+    if let Some(list) = app.category_list() {
+        draw_category_list(f, list, areas.list, config.appearance.border, theme);
+    } else {
+        draw_entry_list(f, app, &areas, icon_manager, theme);
+    }
+    if let Some(preview) = areas.preview {
+        draw_preview(f, app, preview, icon_manager, theme);
+    }
+    draw_status_bar(f, app, areas.status, theme);
 }
 
 /// Draw the search/filter bar
@@ -98,11 +147,22 @@ fn draw_search_bar(f: &mut Frame, app: &App, area: Rect, theme: &Theme) {
         Style::default().fg(theme.dimmed).bg(theme.background)
     };
 
+    // Breadcrumb while a #category filter is active
+    let title = match app.active_category() {
+        Some(category) => format!(" darkwall-drun › {} ", category),
+        None if app.is_browsing_categories() => " darkwall-drun › Categories ".to_string(),
+        None if app.showing_executables() => " darkwall-drun › Run ".to_string(),
+        None => match app.compositor_list() {
+            Some(CompositorList::Windows) => " darkwall-drun › Windows ".to_string(),
+            Some(CompositorList::Workspaces) => " darkwall-drun › Workspaces ".to_string(),
+            None => " darkwall-drun ".to_string(),
+        },
+    };
+
     let search = Paragraph::new(filter_text).style(style).block(
-        Block::default()
-            .borders(Borders::ALL)
+        config.appearance.border.block()
             .border_style(Style::default().fg(theme.accent))
-            .title(" darkwall-drun ")
+            .title(title)
             .style(Style::default().bg(theme.background)),
     );
 
@@ -125,7 +185,7 @@ fn draw_search_bar(f: &mut Frame, app: &App, area: Rect, theme: &Theme) {
 fn draw_entry_list(
     f: &mut Frame,
     app: &App,
-    area: Rect,
+    areas: &LauncherAreas,
     icon_manager: Option<&Arc<Mutex<IconManager>>>,
     theme: &Theme,
 ) {
@@ -141,61 +201,96 @@ fn draw_entry_list(
         .map(|m| m.lock().supports_graphics())
         .unwrap_or(false);
 
-    // Draw border
-    let block = Block::default()
-        .borders(Borders::ALL)
+    // Text icons stand in for images the terminal can't show
+    let glyphs = if has_graphics { None } else { app.glyph_icons() };
+
+    // Draw border, marked where more entries are above or below the page
+    let page = selected / grid.visible_count();
+    let pages = grid.page_count(entries.len());
+    let mut block = config.appearance.border.block()
         .border_style(Style::default().fg(theme.dimmed_alt))
         .style(Style::default().bg(theme.background));
-    f.render_widget(block, area);
+    if page > 0 {
+        block = block.title(Line::styled(" ↑ more ", Style::default().fg(theme.dimmed)).right_aligned());
+    }
+    if page + 1 < pages {
+        block = block.title_bottom(Line::styled(" ↓ more ", Style::default().fg(theme.dimmed)).right_aligned());
+    }
+    f.render_widget(block, areas.list);
+
+    // Scrollbar over the right border, one step per page
+    if pages > 1 {
+        let scrollbar = Scrollbar::new(ScrollbarOrientation::VerticalRight)
+            .begin_symbol(None)
+            .end_symbol(None)
+            .track_style(Style::default().fg(theme.dimmed_alt))
+            .thumb_style(Style::default().fg(theme.accent));
+        let mut state = ScrollbarState::new(pages).position(page).viewport_content_length(1);
+        f.render_stateful_widget(scrollbar, areas.scrollbar(), &mut state);
+    }
 
-    // Calculate inner area (inside border)
-    let inner = Rect {
-        x: area.x + 1,
-        y: area.y + 1,
-        width: area.width.saturating_sub(2),
-        height: area.height.saturating_sub(2),
-    };
+    let inner = areas.list_inner();
 
     if inner.width == 0 || inner.height == 0 {
         return;
     }
 
+    // The compositor couldn't be asked for the list
+    if let (true, Some(error)) = (entries.is_empty(), app.compositor_list_error()) {
+        let row = Rect { height: 1, ..inner };
+        f.render_widget(
+            Paragraph::new(format!(" ⚠ {}", error)).style(Style::default().fg(theme.exit_failure)),
+            row,
+        );
+        return;
+    }
+
+    // Nothing matches: offer to run the filter text as a command
+    if entries.is_empty() {
+        if let Some(cmd) = app.typed_command() {
+            let row = Rect { height: 1, ..inner };
+            let label = if app.config().behavior.enter_runs_typed_command {
+                " ▶ Run: "
+            } else {
+                " ▶ Alt+Enter runs: "
+            };
+            let line = Line::from(vec![
+                Span::styled(label, Style::default().fg(theme.accent)),
+                Span::styled(cmd, Style::default().fg(theme.foreground)),
+            ]);
+            f.render_widget(
+                Paragraph::new(line).style(Style::default().bg(theme.selection_bg)),
+                row,
+            );
+        }
+        return;
+    }
+
     // Calculate visible range based on selection
     let visible_range = grid.visible_range(selected, entries.len());
     let visible_entries: Vec<_> = entries[visible_range.clone()].to_vec();
     let page_start = visible_range.start;
 
-    // Calculate card dimensions
-    let dims = GridDimensions::compute(inner.width, grid, &entry_config);
+    let card_height = entry_config.card_height();
 
     // Render each visible entry as a card
     for (local_idx, entry) in visible_entries.iter().enumerate() {
         let global_idx = page_start + local_idx;
         let is_selected = global_idx == selected;
 
-        // Calculate grid position (column-major order)
-        let (row, col) = grid.index_to_position(local_idx);
-
-        // Calculate card area
-        let card_x = inner.x + col * (dims.column_width + COLUMN_GAP);
-        let card_y = inner.y + row * dims.card_height;
-        let card_area = Rect {
-            x: card_x,
-            y: card_y,
-            width: dims.column_width,
-            height: dims.card_height,
-        };
-
         // Skip if card is outside visible area
-        if card_y + dims.card_height > inner.y + inner.height {
+        let Some(card_area) = grid.card_rect(local_idx, inner, card_height) else {
             continue;
-        }
+        };
 
         // Render entry card
         let card = EntryCard::new(entry, theme)
             .selected(is_selected)
-            .config(entry_config)
-            .icon_space(has_graphics);
+            .config(entry_config.clone())
+            .icon_space(has_graphics)
+            .glyph(glyphs.map(|glyphs| glyphs.glyph(entry)))
+            .highlight(app.name_match_indices(global_idx))
+            .uses(app.usage_stats(&entry.id).map(|stats| stats.count));
         f.render_widget(card, card_area);
     }
 
@@ -219,11 +314,11 @@ fn render_graphics_icons_grid(
 ) {
     let entries = app.visible_entries();
     let grid = app.grid_layout();
-    let dims = GridDimensions::compute(inner.width, grid, entry_config);
+    let card_height = entry_config.card_height();
 
     // Icon dimensions
-    let icon_width = ICON_COLUMN_WIDTH;
-    let icon_height = dims.card_height.min(2); // Max 2 rows per icon
+    let icon_width = entry_config.icon_width();
+    let icon_height = card_height.min(2); // Max 2 rows per icon
 
     // Get visible range
     let visible_range = grid.visible_range(app.selected_index(), entries.len());
@@ -235,20 +330,14 @@ fn render_graphics_icons_grid(
         let mgr = icon_manager.lock();
 
         for (local_idx, entry) in visible_entries.iter().enumerate() {
-            let (row, col) = grid.index_to_position(local_idx);
-
-            // Calculate position
-            let card_x = inner.x + col * (dims.column_width + COLUMN_GAP);
-            let card_y = inner.y + row * dims.card_height;
-
             // Skip if outside visible area
-            if card_y + dims.card_height > inner.y + inner.height {
+            let Some(card) = grid.card_rect(local_idx, inner, card_height) else {
                 continue;
-            }
+            };
 
             // Only get cached icons - don't block rendering
             if let Some(protocol) = mgr.get_cached(&entry.id) {
-                icons_to_render.push((card_x, card_y, protocol));
+                icons_to_render.push((card.x, card.y, protocol));
             }
         }
     } // Release lock before rendering
@@ -268,33 +357,320 @@ fn render_graphics_icons_grid(
     }
 }
 
+/// Draw details of the selected entry: what it runs, where it comes from
+/// and how often it was used
+fn draw_preview(
+    f: &mut Frame,
+    app: &App,
+    area: Rect,
+    icon_manager: Option<&Arc<Mutex<IconManager>>>,
+    theme: &Theme,
+) {
+    let block = app.config().appearance.border.block()
+        .border_style(Style::default().fg(theme.dimmed_alt))
+        .title(" Details ")
+        .style(Style::default().bg(theme.background));
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let Some(entry) = app.selected_entry() else {
+        return;
+    };
+    if inner.width == 0 || inner.height == 0 {
+        return;
+    }
+
+    // Icon at the same size as in the grid, so the image isn't re-encoded
+    // every time it's drawn in the other place
+    let icon = icon_manager.and_then(|mgr| {
+        let mgr = mgr.lock();
+        if mgr.supports_graphics() {
+            mgr.get_cached(&entry.id)
+        } else {
+            None
+        }
+    });
+    let text_area = match icon {
+        Some(protocol) if inner.width > ICON_COLUMN_WIDTH + 1 => {
+            let icon_area = Rect {
+                height: inner.height.min(2),
+                width: ICON_COLUMN_WIDTH,
+                ..inner
+            };
+            let image = StatefulImage::new(None).resize(Resize::Fit(None));
+            f.render_stateful_widget(image, icon_area, &mut *protocol.lock());
+            Rect {
+                x: inner.x + ICON_COLUMN_WIDTH + 1,
+                width: inner.width - ICON_COLUMN_WIDTH - 1,
+                ..inner
+            }
+        }
+        _ => inner,
+    };
+
+    let label = Style::default().fg(theme.dimmed);
+    let value = Style::default().fg(theme.foreground);
+    let field = |name: &str, text: String| {
+        Line::from(vec![
+            Span::styled(format!("{:<11}", name), label),
+            Span::styled(text, value),
+        ])
+    };
+
+    let mut lines = vec![Line::styled(
+        entry.name.clone(),
+        Style::default().fg(theme.accent).add_modifier(Modifier::BOLD),
+    )];
+    if let Some(ref generic) = entry.generic_name {
+        lines.push(Line::styled(generic.clone(), value));
+    }
+    if let Some(ref comment) = entry.comment {
+        lines.push(Line::styled(comment.clone(), label));
+    }
+    lines.push(Line::default());
+
+    if let Some(ref exec) = entry.exec {
+        lines.push(field("Exec", exec.clone()));
+    }
+    if let Some(mode) = app.entry_mode(entry) {
+        lines.push(field("Mode", mode.to_string()));
+    }
+    let source = if entry.id.starts_with(crate::windows::WINDOW_PREFIX) {
+        "open window".to_string()
+    } else if entry.id.starts_with(crate::windows::WORKSPACE_PREFIX) {
+        "workspace".to_string()
+    } else if entry.is_typed_command() {
+        "typed command".to_string()
+    } else if entry.custom {
+        "custom entry (config)".to_string()
+    } else {
+        entry.path.display().to_string()
+    };
+    lines.push(field("Source", source));
+    if let Some(ref dir) = entry.working_dir {
+        lines.push(field("Directory", dir.display().to_string()));
+    }
+    if !entry.categories.is_empty() {
+        lines.push(field("Categories", entry.categories.join(", ")));
+    }
+    if !entry.keywords.is_empty() {
+        lines.push(field("Keywords", entry.keywords.join(", ")));
+    }
+    let usage = match app.usage_stats(&entry.id) {
+        Some(stats) => format!(
+            "{} {}, last {}",
+            stats.count,
+            if stats.count == 1 { "time" } else { "times" },
+            format_age(stats.last_used)
+        ),
+        None => "never".to_string(),
+    };
+    lines.push(field("Launched", usage));
+
+    f.render_widget(Paragraph::new(lines).wrap(Wrap { trim: false }), text_area);
+}
+
+/// How long ago the Unix timestamp `then` was, roughly
+fn format_age(then: u64) -> String {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    let secs = now.saturating_sub(then);
+    match secs {
+        0..60 => "just now".to_string(),
+        60..3600 => format!("{} min ago", secs / 60),
+        3600..86400 => format!("{} h ago", secs / 3600),
+        _ if secs < 2 * 86400 => "yesterday".to_string(),
+        _ => format!("{} days ago", secs / 86400),
+    }
+}
+
+/// Draw the category browser as a single-column list with entry counts
+fn draw_category_list(f: &mut Frame, list: &CategoryList, area: Rect, border: BorderStyle, theme: &Theme) {
+    let block = border.block()
+        .border_style(Style::default().fg(theme.dimmed_alt))
+        .style(Style::default().bg(theme.background));
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    if inner.height == 0 {
+        return;
+    }
+
+    // Keep the selection visible
+    let height = inner.height as usize;
+    let offset = list.selected.saturating_sub(height - 1);
+
+    let lines: Vec<Line> = list
+        .categories
+        .iter()
+        .enumerate()
+        .skip(offset)
+        .take(height)
+        .map(|(i, (name, count))| {
+            let style = if i == list.selected {
+                Style::default().fg(theme.selection_fg).bg(theme.selection_bg)
+            } else {
+                Style::default().fg(theme.foreground)
+            };
+            Line::from(vec![
+                Span::styled(format!(" {}", name), style),
+                Span::styled(format!(" ({})", count), style.fg(theme.dimmed)),
+            ])
+        })
+        .collect();
+
+    f.render_widget(Paragraph::new(lines), inner);
+}
+
 /// Draw the status bar
 /// TEAM_004: Updated to use theme and show grid navigation hints
 fn draw_status_bar(f: &mut Frame, app: &App, area: Rect, theme: &Theme) {
     let entries = app.visible_entries();
     let total = entries.len();
     let grid = app.grid_layout();
+    // Operators narrowing the matches, so a short list is explained
+    let operators = app.search_operators();
+    let operators = match operators.is_empty() {
+        true => String::new(),
+        false => format!(" ({})", operators.join(" ")),
+    };
 
-    let status = if app.is_filtering() || !app.filter_text().is_empty() {
+    let status = if let Some(awaited) = app.awaiting_window() {
+        format!(" Starting {}…", awaited.name)
+    } else if let Some(command) = app.pending_launch() {
+        format!(" Running pre_exec hooks for {}…", command)
+    } else if let (true, Some(entry)) = (app.is_elevation_armed(), app.selected_entry()) {
+        format!(
+            " Run as root with `{}`: {} | Enter: confirm | ESC: cancel",
+            app.config().behavior.elevate_command.trim(),
+            entry.name
+        )
+    } else if let Some(list) = app.category_list() {
         format!(
-            " {} matches | ESC: clear | Enter: run | Ctrl+C: quit",
-            total
+            " {} categories | ↑↓: nav | Enter: open | ESC: back",
+            list.categories.len()
+        )
+    } else if let Some(list) = app.compositor_list() {
+        let esc = if app.filter_text().is_empty() { "quit" } else { "clear" };
+        match list {
+            CompositorList::Windows => format!(
+                " {} windows | ↑↓←→: nav | Enter: focus | Ctrl+W: applications | ESC: {}",
+                total, esc
+            ),
+            CompositorList::Workspaces => format!(
+                " {} workspaces | Enter: switch | Alt+Enter: move focused window there | Ctrl+W: windows | ESC: {}",
+                total, esc
+            ),
+        }
+    } else if app.dmenu().is_some() {
+        let typed = match app.typed_command() {
+            Some(_) if total == 0 => " | Enter: use typed text",
+            Some(_) => " | Alt+Enter: use typed text",
+            None => "",
+        };
+        format!(
+            " {}/{} | ↑↓←→: nav | Enter: select{} | ESC: cancel",
+            if total == 0 { 0 } else { app.selected_index() + 1 },
+            total,
+            typed
+        )
+    } else if total == 0 && app.typed_command().is_some() {
+        let key = if app.config().behavior.enter_runs_typed_command { "Enter" } else { "Alt+Enter" };
+        format!(" 0 matches{} | {}: run as command | ESC: clear | Ctrl+C: quit", operators, key)
+    } else if app.is_filtering() || !app.filter_text().is_empty() {
+        format!(
+            " {} matches{} | ESC: clear | Enter: run | Alt+Enter: run as command | Ctrl+C: quit",
+            total, operators
         )
     } else {
         // Show current position and grid info
         let page = app.selected_index() / grid.visible_count() + 1;
-        let total_pages = (total + grid.visible_count() - 1) / grid.visible_count();
+        let total_pages = grid.page_count(total);
         format!(
-            " {}/{} | Page {}/{} | ↑↓←→: nav | Tab: next | Enter: run | ESC: quit",
+            " {}/{} | Page {}/{} | ↑↓←→: nav | Tab: next | Enter: run | Ctrl+E: run as root | Ctrl+G: categories | ?: help | ESC: quit",
             app.selected_index() + 1,
             total,
             page,
-            total_pages.max(1)
+            total_pages
         )
     };
 
-    let status_bar =
-        Paragraph::new(status).style(Style::default().fg(theme.dimmed).bg(theme.background));
+    // Background job count, with a hint to open the jobs view
+    let status = match app.jobs().len() {
+        0 => status,
+        1 => format!(" [1 job] Ctrl+B |{}", status),
+        n => format!(" [{} jobs] Ctrl+B |{}", n, status),
+    };
+
+    // The elevation prompt stands out so a root run is never a surprise
+    let color = if app.is_elevation_armed() {
+        theme.accent
+    } else {
+        theme.dimmed
+    };
+    // A message asked for (Ctrl+N) replaces the hints for a while
+    let (status, color) = match app.status_message() {
+        Some(message) if message.error => (format!(" {}", message.text), theme.exit_failure),
+        Some(message) => (format!(" {}", message.text), theme.foreground),
+        None => (status, color),
+    };
+
+    let mut spans = Vec::new();
+    match app.compositor_health() {
+        CompositorHealth::Off => {}
+        CompositorHealth::Connected => spans.push(Span::styled(" ◉", Style::default().fg(theme.exit_success))),
+        CompositorHealth::Disconnected { expected, .. } => {
+            let color = if *expected { theme.exit_failure } else { theme.dimmed };
+            spans.push(Span::styled(" ◎", Style::default().fg(color)));
+        }
+    }
+    spans.push(Span::styled(status, Style::default().fg(color)));
+    let status_bar = Paragraph::new(Line::from(spans)).style(Style::default().bg(theme.background));
 
     f.render_widget(status_bar, area);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_age() {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+        assert_eq!(format_age(now), "just now");
+        assert_eq!(format_age(now - 150), "2 min ago");
+        assert_eq!(format_age(now - 3 * 3600), "3 h ago");
+        assert_eq!(format_age(now - 30 * 3600), "yesterday");
+        assert_eq!(format_age(now - 10 * 86400), "10 days ago");
+    }
+
+    #[test]
+    fn test_preview_area() {
+        let area = Rect::new(0, 0, 100, 30);
+        let areas = LauncherAreas::new(area, true);
+        assert_eq!(areas.list, Rect::new(0, 3, 60, 26));
+        assert_eq!(areas.preview, Some(Rect::new(60, 3, 40, 26)));
+
+        // Too narrow: the grid keeps the full width
+        let areas = LauncherAreas::new(Rect::new(0, 0, 50, 30), true);
+        assert_eq!(areas.list.width, 50);
+        assert_eq!(areas.preview, None);
+    }
+
+    #[test]
+    fn test_scrollbar_page() {
+        // List rows 3..29; the track is rows 4..28 of the last column
+        let areas = LauncherAreas::new(Rect::new(0, 0, 80, 30), false);
+        assert_eq!(areas.scrollbar(), Rect::new(79, 4, 1, 24));
+
+        assert_eq!(areas.scrollbar_page(79, 4, 4), Some(0));
+        assert_eq!(areas.scrollbar_page(79, 15, 4), Some(1));
+        assert_eq!(areas.scrollbar_page(79, 16, 4), Some(2));
+        assert_eq!(areas.scrollbar_page(79, 27, 4), Some(3));
+        // Corners and the rest of the list aren't the scrollbar
+        assert_eq!(areas.scrollbar_page(79, 3, 4), None);
+        assert_eq!(areas.scrollbar_page(79, 28, 4), None);
+        assert_eq!(areas.scrollbar_page(40, 10, 4), None);
+    }
+}
@@ -3,18 +3,30 @@
 //! This module contains all rendering logic, split by mode:
 //! - `launcher` - Main launcher UI (search, entry list, status)
 //! - `execution` - Command execution and post-execution views
+//! - `jobs` - Background jobs list
+//! - `help` - Keybindings overlay, drawn over any mode
 
 mod execution;
+mod help;
+mod jobs;
 mod launcher;
 
 use parking_lot::Mutex;
-use ratatui::{style::Style, widgets::Paragraph, Frame};
+use ratatui::{
+    layout::Rect,
+    style::Style,
+    widgets::{Block, Paragraph},
+    Frame,
+};
 use std::sync::Arc;
 
 use crate::app::{App, AppMode};
 use crate::icons::IconManager;
 
-use execution::{draw_executing, draw_post_execution};
+use execution::{draw_executing, draw_post_execution, ExecutingStatus};
+use help::draw_help;
+use jobs::draw_jobs;
+pub use launcher::LauncherAreas;
 use launcher::draw_launcher;
 
 /// Main draw function
@@ -24,24 +36,74 @@ use launcher::draw_launcher;
 pub fn draw(f: &mut Frame, app: &mut App, icon_manager: Option<&Arc<Mutex<IconManager>>>) {
     // TEAM_004: Resolve theme from config
     let theme = app.config().resolve_theme();
+    // A hidden daemon idles on an almost empty screen
+    if app.is_hidden() {
+        let hint = Paragraph::new("drun is idle. Press a key or run drun --toggle")
+            .style(Style::default().fg(theme.dimmed))
+            .alignment(ratatui::layout::Alignment::Center);
+        let area = f.area();
+        let line = ratatui::layout::Rect::new(area.x, area.y + area.height / 2, area.width, area.height.min(1));
+        f.render_widget(hint, line);
+        return;
+    }
+    // Outside a launcher smaller than the terminal is just background
+    let area = app.config().appearance.area(f.area());
+    if area != f.area() {
+        f.render_widget(Block::default().style(Style::default().bg(theme.background)), f.area());
+    }
     // Clone mode to avoid borrow conflict with &mut app
     let mode = app.mode().clone();
     match mode {
-        AppMode::Launcher => draw_launcher(f, app, icon_manager, &theme),
-        AppMode::Executing { ref command, .. } => draw_executing(f, app, command, &theme),
+        AppMode::Launcher => draw_launcher(f, app, area, icon_manager, &theme),
+        AppMode::Executing {
+            ref command,
+            started,
+            terminating,
+            ..
+        } => {
+            let status = if terminating.is_some() {
+                ExecutingStatus::Terminating
+            } else if app.is_timed_out() {
+                ExecutingStatus::TimedOut
+            } else {
+                ExecutingStatus::Running
+            };
+            let output = draw_executing(f, app, area, command, started.elapsed(), status, &theme);
+            app.set_output_area(relative_to(output, area));
+        }
         AppMode::PostExecution {
             ref command,
             ref exit_status,
+            duration,
             ref copy_feedback,
-        } => draw_post_execution(f, app, command, exit_status, copy_feedback, &theme),
+            ..
+        } => {
+            let output =
+                draw_post_execution(f, app, area, command, exit_status, duration, copy_feedback, &theme);
+            app.set_output_area(relative_to(output, area));
+        }
+        AppMode::Jobs { confirm_exit } => draw_jobs(f, app, area, confirm_exit, &theme),
         AppMode::TuiHandover { .. } => {
             // TUI handover - we shouldn't be drawing, but show a message just in case
             let msg = Paragraph::new("Running TUI application...")
                 .style(Style::default().fg(theme.accent));
-            f.render_widget(msg, f.area());
+            f.render_widget(msg, area);
         }
         AppMode::Exit => {
             // Exit mode - shouldn't be drawing, but handle gracefully
         }
     }
+
+    if let Some(scroll) = app.help_scroll() {
+        draw_help(f, app, area, scroll, &theme);
+    }
+}
+
+/// `rect` with its position relative to `area`'s
+fn relative_to(rect: Rect, area: Rect) -> Rect {
+    Rect {
+        x: rect.x - area.x,
+        y: rect.y - area.y,
+        ..rect
+    }
 }
@@ -3,39 +3,66 @@
 //! Unit 5.4.2: Entry Cards
 //!
 //! Renders each entry as a multi-line card with:
-//! - Icon + Name (bold)
+//! - Icon (image or glyph) + Name (bold, characters matching the filter
+//!   highlighted)
 //! - GenericName
 //! - Comment (dimmed)
 //! - Categories (more dimmed)
+//!
+//! or, in the compact layout, as a single line: icon, name and the
+//! GenericName dimmed after it.
 
 use ratatui::{
     buffer::Buffer,
     layout::Rect,
-    style::{Modifier, Style},
+    style::{Color, Modifier, Style},
     widgets::Widget,
 };
-use unicode_width::UnicodeWidthStr;
+use serde::{Deserialize, Serialize};
+use unicode_segmentation::UnicodeSegmentation;
+
+use std::borrow::Cow;
 
 use crate::desktop_entry::Entry;
+use crate::glyphs::{fit_width, GLYPH_COLUMN_WIDTH};
+use super::card_format::{CardFormat, Field, Segment};
+use super::text::truncate;
 use super::theme::Theme;
 
+/// How entries are drawn (`appearance.layout`)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EntryLayout {
+    /// A card of up to four lines per entry
+    #[default]
+    Cards,
+    /// One line per entry
+    Compact,
+}
+
 /// Configuration for entry display
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 pub struct EntryDisplayConfig {
+    /// Cards or single lines
+    pub layout: EntryLayout,
     /// Show GenericName line
     pub show_generic: bool,
     /// Show Comment line
     pub show_comment: bool,
     /// Show Categories line
     pub show_categories: bool,
+    /// Lines of a card, instead of the ones the show_* switches select
+    pub format: Option<CardFormat>,
 }
 
 impl Default for EntryDisplayConfig {
     fn default() -> Self {
         Self {
+            layout: EntryLayout::Cards,
             show_generic: true,
             show_comment: true,
             show_categories: true,
+            format: None,
         }
     }
 }
@@ -43,12 +70,48 @@ impl Default for EntryDisplayConfig {
 impl EntryDisplayConfig {
     /// Calculate the height of an entry card in lines
     pub fn card_height(&self) -> u16 {
+        if self.layout == EntryLayout::Compact {
+            return 1;
+        }
+        if let Some(ref format) = self.format {
+            return format.height();
+        }
         let mut height = 1; // Name line always shown
         if self.show_generic { height += 1; }
         if self.show_comment { height += 1; }
         if self.show_categories { height += 1; }
         height
     }
+
+    /// The lines of a card: `format`, or the ones the show_* switches
+    /// select
+    pub fn card_format(&self) -> Cow<'_, CardFormat> {
+        match self.format {
+            Some(ref format) => Cow::Borrowed(format),
+            None => {
+                let mut source = String::from("{name}");
+                let lines = [
+                    (self.show_generic, "{generic}"),
+                    (self.show_comment, "{comment}"),
+                    (self.show_categories, "{categories}"),
+                ];
+                for (_, line) in lines.iter().filter(|(show, _)| *show) {
+                    source.push('\n');
+                    source.push_str(line);
+                }
+                Cow::Owned(CardFormat::parse(&source))
+            }
+        }
+    }
+
+    /// Cells left of the name for an image icon
+    pub fn icon_width(&self) -> u16 {
+        match self.layout {
+            EntryLayout::Cards => 6,
+            // A one row image is about two cells wide
+            EntryLayout::Compact => GLYPH_COLUMN_WIDTH,
+        }
+    }
 }
 
 /// Entry card widget
@@ -59,6 +122,12 @@ pub struct EntryCard<'a> {
     config: EntryDisplayConfig,
     /// Whether to show icon space (for alignment when graphics are supported)
     icon_space: bool,
+    /// Char positions in the name that matched the filter
+    highlight: &'a [u32],
+    /// Text icon shown before the name when there is no image icon
+    glyph: Option<&'a str>,
+    /// Launch count, for `{uses}`
+    uses: Option<u32>,
 }
 
 impl<'a> EntryCard<'a> {
@@ -69,6 +138,9 @@ impl<'a> EntryCard<'a> {
             theme,
             config: EntryDisplayConfig::default(),
             icon_space: false,
+            highlight: &[],
+            glyph: None,
+            uses: None,
         }
     }
 
@@ -86,6 +158,21 @@ impl<'a> EntryCard<'a> {
         self.icon_space = icon_space;
         self
     }
+
+    pub fn highlight(mut self, highlight: &'a [u32]) -> Self {
+        self.highlight = highlight;
+        self
+    }
+
+    pub fn glyph(mut self, glyph: Option<&'a str>) -> Self {
+        self.glyph = glyph;
+        self
+    }
+
+    pub fn uses(mut self, uses: Option<u32>) -> Self {
+        self.uses = uses;
+        self
+    }
 }
 
 impl<'a> Widget for EntryCard<'a> {
@@ -95,7 +182,15 @@ impl<'a> Widget for EntryCard<'a> {
         }
 
         let bg = if self.selected { self.theme.selection_bg } else { self.theme.background };
-        let fg = if self.selected { self.theme.selection_fg } else { self.theme.foreground };
+        // Entries that would normally be hidden (behavior.show_hidden) or
+        // whose TryExec binary is missing are dimmed
+        let fg = if self.entry.suppressed || self.entry.missing {
+            self.theme.dimmed_alt
+        } else if self.selected {
+            self.theme.selection_fg
+        } else {
+            self.theme.foreground
+        };
 
         // Fill background
         for y in area.y..area.y + area.height {
@@ -115,74 +210,138 @@ impl<'a> Widget for EntryCard<'a> {
 
         let inner_x = area.x + padding_x;
         let max_y = area.y + area.height;
-        let mut y = area.y;
+        let y = area.y;
 
-        // Icon space offset (for alignment when graphics icons are shown elsewhere)
-        let icon_offset = if self.icon_space { 6 } else { 0 };
+        // Icon space offset (for alignment when graphics icons are shown
+        // elsewhere), or a glyph column if there's room for it and a name
+        let glyph = self.glyph.filter(|_| !self.icon_space && inner_width > GLYPH_COLUMN_WIDTH);
+        let icon_offset = if self.icon_space {
+            self.config.icon_width()
+        } else if glyph.is_some() {
+            GLYPH_COLUMN_WIDTH
+        } else {
+            0
+        };
         let text_x = inner_x + icon_offset;
         let text_width = inner_width.saturating_sub(icon_offset) as usize;
-
-        // Line 1: Name (bold) - always rendered
-        let name_style = Style::default().fg(fg).bg(bg).add_modifier(Modifier::BOLD);
-        buf.set_string(text_x, y, truncate(&self.entry.name, text_width), name_style);
-        y += 1;
-
-        // Indent for subsequent lines
-        let indent = 3u16;
-        let sub_x = text_x + indent;
-        let sub_width = text_width.saturating_sub(indent as usize);
-
-        // Line 2: GenericName (if enabled and room available)
-        if self.config.show_generic && y < max_y {
-            if let Some(ref generic) = self.entry.generic_name {
-                if generic != &self.entry.name {
-                    let style = Style::default().fg(fg).bg(bg);
-                    buf.set_string(sub_x, y, truncate(generic, sub_width), style);
-                }
-            }
-            y += 1;
+        if let Some(glyph) = glyph {
+            // Cut to the two cells before the space, whatever it is
+            buf.set_stringn(inner_x, y, fit_width(glyph, 2), 2, Style::default().fg(fg).bg(bg));
         }
 
-        // Line 3: Comment (if enabled and room available)
-        if self.config.show_comment && y < max_y {
-            if let Some(ref comment) = self.entry.comment {
+        // Compact: the name, then the GenericName dimmed on the same line
+        if self.config.layout == EntryLayout::Compact {
+            let name_end = self.render_name(text_x, y, text_width, fg, bg, buf);
+            let gap = 2;
+            let x = name_end + gap;
+            let width = (text_x as usize + text_width).saturating_sub(x as usize);
+            let generic = self.generic_name().filter(|_| self.config.show_generic && width > 1);
+            if let Some(generic) = generic {
                 let style = Style::default().fg(self.theme.dimmed).bg(bg);
-                buf.set_string(sub_x, y, truncate(comment, sub_width), style);
+                buf.set_string(x, y, truncate(generic, width), style);
             }
-            y += 1;
+            return;
         }
 
-        // Line 4: Categories (if enabled, non-empty, and room available)
-        if self.config.show_categories && y < max_y && !self.entry.categories.is_empty() {
-            let cats = self.entry.categories.join(",");
-            let style = Style::default().fg(self.theme.dimmed_alt).bg(bg);
-            buf.set_string(sub_x, y, truncate(&cats, sub_width), style);
+        // Cards: the first line next to the icon, the others indented
+        // below it, each cut to fit
+        let indent = 3u16;
+        let format = self.config.card_format();
+        for (line, y) in format.lines().iter().zip(area.y..max_y) {
+            if y == area.y {
+                self.render_line(line, text_x, y, text_width, fg, bg, buf);
+            } else {
+                let width = text_width.saturating_sub(indent as usize);
+                self.render_line(line, text_x + indent, y, width, fg, bg, buf);
+            }
         }
     }
 }
 
-/// Truncate string to fit within max_width, adding ellipsis if needed
-fn truncate(s: &str, max_width: usize) -> String {
-    let width = s.width();
-    if width <= max_width {
-        s.to_string()
-    } else if max_width <= 1 {
-        "…".to_string()
-    } else {
-        let mut result = String::new();
-        let mut current_width = 0;
-        
-        for c in s.chars() {
-            let char_width = unicode_width::UnicodeWidthChar::width(c).unwrap_or(0);
-            if current_width + char_width + 1 > max_width {
-                result.push('…');
+impl EntryCard<'_> {
+    /// Draw the name at `x`, `y`, at most `width` cells, with the
+    /// characters matching the filter highlighted; returns where it ends
+    fn render_name(&self, x: u16, y: u16, width: usize, fg: Color, bg: Color, buf: &mut Buffer) -> u16 {
+        let name_style = Style::default().fg(fg).bg(bg).add_modifier(Modifier::BOLD);
+        let match_style = name_style.fg(self.theme.search_highlight);
+        let name = if self.entry.missing {
+            format!("{} (missing)", self.entry.name)
+        } else {
+            self.entry.name.clone()
+        };
+        let shown = truncate(&name, width);
+        // The ellipsis of a truncated name is never a match
+        let kept = if shown == name { usize::MAX } else { shown.chars().count() - 1 };
+        let mut x = x;
+        // Whole graphemes, so combining characters stay on their base;
+        // the highlight is by char position
+        let mut i = 0;
+        for grapheme in shown.graphemes(true) {
+            let matched = i < kept && self.highlight.contains(&(i as u32));
+            let style = if matched { match_style } else { name_style };
+            x = buf.set_stringn(x, y, grapheme, usize::MAX, style).0;
+            i += grapheme.chars().count();
+        }
+        x
+    }
+
+    /// Draw a template line at `x`, `y`, at most `width` cells
+    #[allow(clippy::too_many_arguments)]
+    fn render_line(&self, line: &[Segment], x: u16, y: u16, width: usize, fg: Color, bg: Color, buf: &mut Buffer) {
+        let end = x as usize + width;
+        let mut x = x;
+        for segment in line {
+            let room = end.saturating_sub(x as usize);
+            if room == 0 {
+                break;
+            }
+            let (text, style) = match segment {
+                Segment::Text(text) => (Cow::Borrowed(text.as_str()), Style::default().fg(self.theme.dimmed)),
+                Segment::Field(fields) => {
+                    let Some((field, value)) = fields.iter().find_map(|&field| Some((field, self.field(field)?))) else {
+                        continue;
+                    };
+                    let color = match field {
+                        Field::Name => {
+                            x = self.render_name(x, y, room, fg, bg, buf);
+                            continue;
+                        }
+                        Field::Generic => fg,
+                        Field::Comment | Field::Uses => self.theme.dimmed,
+                        Field::Categories | Field::Exec => self.theme.dimmed_alt,
+                    };
+                    (value, Style::default().fg(color))
+                }
+            };
+            let shown = truncate(&text, room);
+            x = buf.set_stringn(x, y, &shown, room, style.bg(bg)).0;
+            if shown != text {
                 break;
             }
-            result.push(c);
-            current_width += char_width;
         }
-        
-        result
+    }
+
+    /// The entry's `field`, None if it has none
+    fn field(&self, field: Field) -> Option<Cow<'_, str>> {
+        let value = match field {
+            Field::Name => Some(Cow::Borrowed(self.entry.name.as_str())),
+            Field::Generic => self.generic_name().map(Cow::Borrowed),
+            Field::Comment => self.entry.comment.as_deref().map(Cow::Borrowed),
+            Field::Categories => {
+                Some(Cow::Owned(self.entry.categories.join(","))).filter(|_| !self.entry.categories.is_empty())
+            }
+            Field::Exec => self.entry.exec.as_deref().map(Cow::Borrowed),
+            Field::Uses => self.uses.filter(|&count| count > 0).map(|count| Cow::Owned(count.to_string())),
+        };
+        value.filter(|value| !value.is_empty())
+    }
+
+    /// The GenericName, unless it's just the name again
+    fn generic_name(&self) -> Option<&str> {
+        self.entry
+            .generic_name
+            .as_deref()
+            .filter(|generic| *generic != self.entry.name)
     }
 }
 
@@ -191,11 +350,76 @@ mod tests {
     use super::*;
 
     #[test]
-    fn test_truncate() {
-        assert_eq!(truncate("hello", 10), "hello");
-        assert_eq!(truncate("hello world", 8), "hello w…");
-        assert_eq!(truncate("hi", 2), "hi");
-        assert_eq!(truncate("hello", 1), "…");
+    fn test_name_highlight() {
+        let theme = Theme::default();
+        let entry = Entry {
+            name: "Firefox".to_string(),
+            ..Default::default()
+        };
+        let area = Rect::new(0, 0, 7, 1);
+        let mut buf = Buffer::empty(area);
+        EntryCard::new(&entry, &theme)
+            .highlight(&[0, 4, 6])
+            .render(area, &mut buf);
+
+        // Padding, then the name with the matched characters highlighted
+        assert_eq!(buf[(1, 0)].symbol(), "F");
+        assert_eq!(buf[(1, 0)].fg, theme.search_highlight);
+        assert_eq!(buf[(2, 0)].fg, theme.foreground);
+        // Cut to "Fire…": the ellipsis in place of the 'f' isn't highlighted
+        assert_eq!(buf[(5, 0)].symbol(), "…");
+        assert_eq!(buf[(5, 0)].fg, theme.foreground);
+    }
+
+    #[test]
+    fn test_wide_name_cut_whole() {
+        let theme = Theme::default();
+        let entry = Entry {
+            name: "日本語エディタ".to_string(),
+            comment: Some("Cafe\u{301} au lait".to_string()),
+            ..Default::default()
+        };
+        let area = Rect::new(0, 0, 10, 3);
+        let mut buf = Buffer::empty(area);
+        EntryCard::new(&entry, &theme).highlight(&[1]).render(area, &mut buf);
+
+        // Three wide characters and the ellipsis in the 8 cells inside the
+        // padding, and no half character
+        let symbols: Vec<_> = (1..9).map(|x| buf[(x, 0)].symbol()).collect();
+        assert_eq!(symbols, ["日", " ", "本", " ", "語", " ", "…", " "]);
+        assert_eq!(buf[(3, 0)].fg, theme.search_highlight);
+        // The combining accent stays on its letter
+        assert_eq!(buf[(7, 2)].symbol(), "e\u{301}");
+        assert_eq!(buf[(8, 2)].symbol(), "…");
+    }
+
+    #[test]
+    fn test_glyph_column() {
+        let theme = Theme::default();
+        let entry = Entry {
+            name: "Files".to_string(),
+            ..Default::default()
+        };
+        let area = Rect::new(0, 0, 12, 1);
+
+        // A wide glyph takes two cells; the name starts at the same column
+        // as after a narrow one
+        for glyph in ["🌐", "⚙"] {
+            let mut buf = Buffer::empty(area);
+            EntryCard::new(&entry, &theme).glyph(Some(glyph)).render(area, &mut buf);
+            assert_eq!(buf[(1, 0)].symbol(), glyph);
+            assert_eq!(buf[(3, 0)].symbol(), " ");
+            assert_eq!(buf[(4, 0)].symbol(), "F");
+        }
+
+        // Image icons take precedence
+        let mut buf = Buffer::empty(area);
+        EntryCard::new(&entry, &theme)
+            .glyph(Some("⚙"))
+            .icon_space(true)
+            .render(area, &mut buf);
+        assert_eq!(buf[(1, 0)].symbol(), " ");
+        assert_eq!(buf[(7, 0)].symbol(), "F");
     }
 
     #[test]
@@ -207,7 +431,81 @@ mod tests {
             show_generic: false,
             show_comment: true,
             show_categories: false,
+            ..Default::default()
         };
         assert_eq!(config.card_height(), 2);
+
+        let config = EntryDisplayConfig {
+            layout: EntryLayout::Compact,
+            ..Default::default()
+        };
+        assert_eq!(config.card_height(), 1);
+    }
+
+    #[test]
+    fn test_card_format() {
+        let theme = Theme::default();
+        let entry = Entry {
+            name: "Firefox".to_string(),
+            generic_name: Some("Web Browser".to_string()),
+            exec: Some("firefox %u".to_string()),
+            ..Default::default()
+        };
+        let config = EntryDisplayConfig {
+            format: Some(CardFormat::parse("{name} ({uses}x)\n{comment|generic}\n{comment}\n{exec} {{%u}} x")),
+            ..Default::default()
+        };
+        assert_eq!(config.card_height(), 4);
+        let area = Rect::new(0, 0, 20, 4);
+        let mut buf = Buffer::empty(area);
+        EntryCard::new(&entry, &theme)
+            .config(config)
+            .uses(Some(12))
+            .render(area, &mut buf);
+
+        let line = |y| (0..area.width).map(|x| buf[(x, y)].symbol()).collect::<String>();
+        assert_eq!(line(0), " Firefox (12x)      ");
+        // No comment: the fallback, or an empty line
+        assert_eq!(line(1), "    Web Browser     ");
+        assert_eq!(line(2), "                    ");
+        // Cut where the line runs out of room
+        assert_eq!(line(3), "    firefox %u {%u… ");
+        assert_eq!(buf[(9, 0)].fg, theme.dimmed);
+        assert_eq!(buf[(4, 3)].fg, theme.dimmed_alt);
+    }
+
+    #[test]
+    fn test_compact_line() {
+        let theme = Theme::default();
+        let entry = Entry {
+            name: "Firefox".to_string(),
+            generic_name: Some("Web Browser".to_string()),
+            comment: Some("Browse the Web".to_string()),
+            ..Default::default()
+        };
+        let config = EntryDisplayConfig {
+            layout: EntryLayout::Compact,
+            ..Default::default()
+        };
+        let area = Rect::new(0, 0, 30, 1);
+        let mut buf = Buffer::empty(area);
+        EntryCard::new(&entry, &theme)
+            .config(config.clone())
+            .selected(true)
+            .glyph(Some("⚙"))
+            .render(area, &mut buf);
+
+        let line: String = (0..area.width).map(|x| buf[(x, 0)].symbol()).collect();
+        assert_eq!(line, " ⚙  Firefox  Web Browser      ");
+        assert_eq!(buf[(13, 0)].fg, theme.dimmed);
+        // The selection spans the whole row
+        assert!((0..area.width).all(|x| buf[(x, 0)].bg == theme.selection_bg));
+
+        // The GenericName is cut to the room left
+        let area = Rect::new(0, 0, 18, 1);
+        let mut buf = Buffer::empty(area);
+        EntryCard::new(&entry, &theme).config(config).render(area, &mut buf);
+        let line: String = (0..area.width).map(|x| buf[(x, 0)].symbol()).collect();
+        assert_eq!(line, " Firefox  Web Br… ");
     }
 }
@@ -4,12 +4,32 @@
 //!
 //! Provides:
 //! - 2-column (configurable) grid layout
-//! - Column-major ordering (like rofi)
+//! - Column-major (default) or row-major ordering
 //! - Navigation helpers (up/down/left/right)
 //! - Pagination
+//! - Card geometry and mouse hit testing
+//! - Border style and the launcher's size within the terminal
 
+use ratatui::layout::Rect;
+use ratatui::symbols::border;
+use ratatui::widgets::{Block, Borders};
+use serde::{Deserialize, Serialize};
 use std::ops::Range;
 
+/// Gap between columns in cells
+pub const COLUMN_GAP: u16 = 2;
+
+/// Order entries fill the grid in (`appearance.grid_order`)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GridOrder {
+    /// Top to bottom, then the next column
+    #[default]
+    Column,
+    /// Left to right, then the next row
+    Row,
+}
+
 /// Grid layout configuration
 #[derive(Debug, Clone, Copy)]
 pub struct GridLayout {
@@ -17,6 +37,8 @@ pub struct GridLayout {
     pub columns: u16,
     /// Number of visible rows (default: 5)
     pub visible_rows: u16,
+    /// Order entries fill the grid in
+    pub order: GridOrder,
 }
 
 impl Default for GridLayout {
@@ -24,26 +46,146 @@ impl Default for GridLayout {
         Self {
             columns: 2,
             visible_rows: 5,
+            order: GridOrder::Column,
         }
     }
 }
 
+/// Most columns at least `min_width` wide (with gaps between them) that
+/// fit in `width`, between 1 and the 10 `GridLayout` allows
+pub fn auto_columns(width: u16, min_width: u16) -> u16 {
+    let columns = width.saturating_add(COLUMN_GAP) / min_width.max(1).saturating_add(COLUMN_GAP);
+    columns.clamp(1, 10)
+}
+
+/// Lines the launcher's boxes are drawn with (`appearance.border`)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BorderStyle {
+    Rounded,
+    #[default]
+    Plain,
+    Thick,
+    /// No lines, but the boxes keep their padding and titles
+    None,
+}
+
+impl BorderStyle {
+    /// A block with all borders in this style
+    pub fn block(self) -> Block<'static> {
+        let set = match self {
+            Self::Rounded => border::ROUNDED,
+            Self::Plain => border::PLAIN,
+            Self::Thick => border::THICK,
+            Self::None => border::EMPTY,
+        };
+        Block::default().borders(Borders::ALL).border_set(set)
+    }
+}
+
+/// `appearance.width`/`max_height`: a number of cells, or a percentage of
+/// the terminal written as "80%"
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "ExtentValue", into = "ExtentValue")]
+pub enum Extent {
+    Cells(u16),
+    Percent(u16),
+}
+
+/// How an `Extent` is written in the config
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum ExtentValue {
+    Cells(u16),
+    Text(String),
+}
+
+impl TryFrom<ExtentValue> for Extent {
+    type Error = String;
+
+    fn try_from(value: ExtentValue) -> Result<Self, Self::Error> {
+        let extent = match value {
+            ExtentValue::Cells(cells) => Self::Cells(cells),
+            ExtentValue::Text(text) => {
+                let number = text.trim().strip_suffix('%');
+                match number.unwrap_or(text.trim()).trim().parse::<u16>() {
+                    Ok(n) if number.is_some() => Self::Percent(n),
+                    Ok(n) => Self::Cells(n),
+                    Err(_) => return Err(format!("expected cells or a percentage like \"80%\", got \"{}\"", text)),
+                }
+            }
+        };
+        match extent {
+            Self::Cells(0) | Self::Percent(0) => Err("must be more than 0".to_string()),
+            Self::Percent(n) if n > 100 => Err(format!("{}% is more than the whole terminal", n)),
+            extent => Ok(extent),
+        }
+    }
+}
+
+impl From<Extent> for ExtentValue {
+    fn from(extent: Extent) -> Self {
+        match extent {
+            Extent::Cells(cells) => Self::Cells(cells),
+            Extent::Percent(percent) => Self::Text(format!("{}%", percent)),
+        }
+    }
+}
+
+impl Extent {
+    /// Cells this extent takes of `total`, at most all of them
+    pub fn of(self, total: u16) -> u16 {
+        match self {
+            Self::Cells(cells) => cells.min(total),
+            Self::Percent(percent) => (total as u32 * percent as u32 / 100) as u16,
+        }
+    }
+}
+
+/// The launcher's part of the `screen`: at most `width` wide and
+/// `max_height` high, centered
+pub fn launcher_area(screen: Rect, width: Option<Extent>, max_height: Option<Extent>) -> Rect {
+    let width = width.map_or(screen.width, |w| w.of(screen.width));
+    let height = max_height.map_or(screen.height, |h| h.of(screen.height));
+    Rect::new(
+        screen.x + (screen.width - width) / 2,
+        screen.y + (screen.height - height) / 2,
+        width,
+        height,
+    )
+}
+
 impl GridLayout {
     /// Create a new grid layout
     pub fn new(columns: u16, visible_rows: u16) -> Self {
         Self {
-            columns: columns.max(1).min(10),
-            visible_rows: visible_rows.max(1).min(20),
+            columns: columns.clamp(1, 10),
+            visible_rows: visible_rows.clamp(1, 20),
+            order: GridOrder::Column,
         }
     }
 
+    /// Fill the grid in `order`
+    pub fn with_order(mut self, order: GridOrder) -> Self {
+        self.order = order;
+        self
+    }
+
     /// Total number of visible entries (columns × rows)
     pub fn visible_count(&self) -> usize {
         (self.columns as usize) * (self.visible_rows as usize)
     }
 
+    /// Number of pages `total` entries take (at least one)
+    pub fn page_count(&self, total: usize) -> usize {
+        total.div_ceil(self.visible_count()).max(1)
+    }
+
     /// Calculate the range of entries visible for a given selection
     /// Returns the start..end indices of entries to display
+    ///
+    /// A page holds the same entries in either order, only placed
+    /// differently.
     pub fn visible_range(&self, selected: usize, total: usize) -> Range<usize> {
         if total == 0 {
             return 0..0;
@@ -57,35 +199,84 @@ impl GridLayout {
     }
 
     /// Convert flat index to (row, col) position
-    /// Uses column-major ordering (rofi-style):
+    /// Column-major ordering (rofi-style) by default, or row-major:
     /// ```text
-    /// Index:  0 5
-    ///         1 6
-    ///         2 7
-    ///         3 8
-    ///         4 9
+    /// Column:  0 5    Row:  0 1
+    ///          1 6          2 3
+    ///          2 7          4 5
+    ///          3 8          6 7
+    ///          4 9          8 9
     /// ```
     pub fn index_to_position(&self, index: usize) -> (u16, u16) {
-        let rows = self.visible_rows as usize;
-        let col = index / rows;
-        let row = index % rows;
+        let (row, col) = match self.order {
+            GridOrder::Column => {
+                let rows = self.visible_rows as usize;
+                (index % rows, index / rows)
+            }
+            GridOrder::Row => {
+                let columns = self.columns as usize;
+                (index / columns, index % columns)
+            }
+        };
         (row as u16, col as u16)
     }
 
     /// Convert (row, col) position to flat index
     #[allow(dead_code)] // Public API for future use
     pub fn position_to_index(&self, row: u16, col: u16) -> usize {
-        let rows = self.visible_rows as usize;
-        (col as usize) * rows + (row as usize)
+        match self.order {
+            GridOrder::Column => (col as usize) * (self.visible_rows as usize) + (row as usize),
+            GridOrder::Row => (row as usize) * (self.columns as usize) + (col as usize),
+        }
     }
 
-    /// Calculate new selection after moving up
-    pub fn move_up(&self, current: usize) -> usize {
+    /// Width of one column in a list `inner_width` cells wide
+    pub fn column_width(&self, inner_width: u16) -> u16 {
+        let columns = self.columns;
+        if columns > 1 {
+            inner_width.saturating_sub(COLUMN_GAP * (columns - 1)) / columns
+        } else {
+            inner_width
+        }
+    }
+
+    /// Area of the card in `slot` (index on the current page) inside the
+    /// list area `inner`, or None if it doesn't fit
+    pub fn card_rect(&self, slot: usize, inner: Rect, card_height: u16) -> Option<Rect> {
+        let (row, col) = self.index_to_position(slot);
+        let column_width = self.column_width(inner.width);
+        let x = inner.x as u32 + col as u32 * (column_width + COLUMN_GAP) as u32;
+        let y = inner.y as u32 + row as u32 * card_height as u32;
+        if col >= self.columns || y + card_height as u32 > (inner.y + inner.height) as u32 {
+            return None;
+        }
+        Some(Rect {
+            x: x as u16,
+            y: y as u16,
+            width: column_width,
+            height: card_height,
+        })
+    }
+
+    /// Slot on the current page of the card at screen position (`x`, `y`)
+    ///
+    /// Uses the same geometry as drawing, so the gap between columns and
+    /// the cards cut off at the bottom hit nothing.
+    pub fn hit_test(&self, x: u16, y: u16, inner: Rect, card_height: u16) -> Option<usize> {
+        (0..self.visible_count()).find(|&slot| {
+            self.card_rect(slot, inner, card_height).is_some_and(|card| {
+                (card.x..card.x + card.width).contains(&x) && (card.y..card.y + card.height).contains(&y)
+            })
+        })
+    }
+
+    /// Move to the previous entry in fill order
+    fn step_back(&self, current: usize) -> usize {
         current.saturating_sub(1)
     }
 
-    /// Calculate new selection after moving down
-    pub fn move_down(&self, current: usize, total: usize) -> usize {
+    /// Move to the next entry in fill order
+    fn step_forward(&self, current: usize, total: usize) -> usize {
         if current + 1 < total {
             current + 1
         } else {
@@ -93,16 +284,51 @@ impl GridLayout {
         }
     }
 
+    /// Calculate new selection after moving up
+    ///
+    /// Row-major, moving up from the top row stays put.
+    pub fn move_up(&self, current: usize) -> usize {
+        match self.order {
+            GridOrder::Column => self.step_back(current),
+            GridOrder::Row => current.checked_sub(self.columns as usize).unwrap_or(current),
+        }
+    }
+
+    /// Calculate new selection after moving down
+    ///
+    /// Row-major, moving down into a ragged last row that has no entry
+    /// under the current one selects the last entry.
+    pub fn move_down(&self, current: usize, total: usize) -> usize {
+        match self.order {
+            GridOrder::Column => self.step_forward(current, total),
+            GridOrder::Row => {
+                let columns = self.columns as usize;
+                let last = total.saturating_sub(1);
+                if current / columns < last / columns {
+                    (current + columns).min(last)
+                } else {
+                    current
+                }
+            }
+        }
+    }
+
     /// Calculate new selection after moving left (previous column)
     pub fn move_left(&self, current: usize) -> usize {
-        let rows = self.visible_rows as usize;
-        current.saturating_sub(rows)
+        match self.order {
+            GridOrder::Column => current.saturating_sub(self.visible_rows as usize),
+            GridOrder::Row => self.step_back(current),
+        }
     }
 
     /// Calculate new selection after moving right (next column)
     pub fn move_right(&self, current: usize, total: usize) -> usize {
-        let rows = self.visible_rows as usize;
-        (current + rows).min(total.saturating_sub(1))
+        match self.order {
+            GridOrder::Column => {
+                (current + self.visible_rows as usize).min(total.saturating_sub(1))
+            }
+            GridOrder::Row => self.step_forward(current, total),
+        }
     }
 
     /// Calculate new selection after page up
@@ -128,6 +354,8 @@ impl GridLayout {
     }
 
     /// Tab navigation (next with wrap)
+    ///
+    /// Tab follows the fill order, so it is the same in either order.
     pub fn tab_next(&self, current: usize, total: usize) -> usize {
         if total == 0 {
             0
@@ -180,19 +408,30 @@ mod tests {
 
     #[test]
     fn test_visible_range() {
+        for order in [GridOrder::Column, GridOrder::Row] {
+            let layout = GridLayout::new(2, 5).with_order(order);
+
+            // First page
+            assert_eq!(layout.visible_range(0, 25), 0..10);
+            assert_eq!(layout.visible_range(5, 25), 0..10);
+            assert_eq!(layout.visible_range(9, 25), 0..10);
+
+            // Second page
+            assert_eq!(layout.visible_range(10, 25), 10..20);
+            assert_eq!(layout.visible_range(15, 25), 10..20);
+
+            // Third page (partial)
+            assert_eq!(layout.visible_range(20, 25), 20..25);
+        }
+    }
+
+    #[test]
+    fn test_page_count() {
         let layout = GridLayout::new(2, 5);
-        
-        // First page
-        assert_eq!(layout.visible_range(0, 25), 0..10);
-        assert_eq!(layout.visible_range(5, 25), 0..10);
-        assert_eq!(layout.visible_range(9, 25), 0..10);
-        
-        // Second page
-        assert_eq!(layout.visible_range(10, 25), 10..20);
-        assert_eq!(layout.visible_range(15, 25), 10..20);
-        
-        // Third page (partial)
-        assert_eq!(layout.visible_range(20, 25), 20..25);
+        assert_eq!(layout.page_count(0), 1);
+        assert_eq!(layout.page_count(10), 1);
+        assert_eq!(layout.page_count(11), 2);
+        assert_eq!(layout.page_count(900), 90);
     }
 
     #[test]
@@ -240,10 +479,143 @@ mod tests {
 
     #[test]
     fn test_tab_wrap() {
+        for order in [GridOrder::Column, GridOrder::Row] {
+            let layout = GridLayout::new(2, 5).with_order(order);
+            let total = 15;
+
+            assert_eq!(layout.tab_next(14, total), 0); // Wrap to start
+            assert_eq!(layout.tab_prev(0, total), 14); // Wrap to end
+        }
+    }
+
+    #[test]
+    fn test_row_index_to_position() {
+        let layout = GridLayout::new(2, 5).with_order(GridOrder::Row);
+
+        // Row 0, row 1, ..., row 4
+        assert_eq!(layout.index_to_position(0), (0, 0));
+        assert_eq!(layout.index_to_position(1), (0, 1));
+        assert_eq!(layout.index_to_position(2), (1, 0));
+        assert_eq!(layout.index_to_position(9), (4, 1));
+
+        for index in 0..10 {
+            let (row, col) = layout.index_to_position(index);
+            assert_eq!(layout.position_to_index(row, col), index);
+        }
+    }
+
+    #[test]
+    fn test_row_navigation() {
+        let layout = GridLayout::new(3, 5).with_order(GridOrder::Row);
+        let total = 14;
+
+        // Left/Right step through the row and wrap to the next one
+        assert_eq!(layout.move_right(1, total), 2);
+        assert_eq!(layout.move_right(2, total), 3);
+        assert_eq!(layout.move_right(13, total), 13); // Can't exceed total
+        assert_eq!(layout.move_left(3), 2);
+        assert_eq!(layout.move_left(0), 0);
+
+        // Up/Down jump a whole row
+        assert_eq!(layout.move_down(1, total), 4);
+        assert_eq!(layout.move_up(4), 1);
+        assert_eq!(layout.move_up(2), 2); // Top row stays put
+        assert_eq!(layout.move_up(0), 0);
+    }
+
+    #[test]
+    fn test_row_ragged_last_row() {
+        // Last row holds 12 and 13 only
+        let layout = GridLayout::new(3, 5).with_order(GridOrder::Row);
+        let total = 14;
+
+        assert_eq!(layout.move_down(10, total), 13); // Straight down
+        assert_eq!(layout.move_down(11, total), 13); // Nothing below: last entry
+        assert_eq!(layout.move_down(12, total), 12); // Already on the last row
+        assert_eq!(layout.move_down(13, total), 13);
+        assert_eq!(layout.move_up(13), 10);
+    }
+
+    #[test]
+    fn test_row_card_rect() {
+        let layout = GridLayout::new(2, 5).with_order(GridOrder::Row);
+        let inner = Rect::new(1, 4, 42, 10);
+
+        assert_eq!(layout.card_rect(1, inner, 2), Some(Rect::new(23, 4, 20, 2)));
+        assert_eq!(layout.card_rect(2, inner, 2), Some(Rect::new(1, 6, 20, 2)));
+        assert_eq!(layout.hit_test(23, 4, inner, 2), Some(1));
+    }
+
+    #[test]
+    fn test_auto_columns() {
+        // n columns need n * 30 + (n - 1) * 2 cells
+        assert_eq!(auto_columns(0, 30), 1);
+        assert_eq!(auto_columns(61, 30), 1);
+        assert_eq!(auto_columns(62, 30), 2);
+        assert_eq!(auto_columns(93, 30), 2);
+        assert_eq!(auto_columns(94, 30), 3);
+        assert_eq!(auto_columns(158, 30), 5);
+        // Capped on ultrawide terminals
+        assert_eq!(auto_columns(1000, 30), 10);
+        assert_eq!(auto_columns(80, 0), 10);
+    }
+
+    #[test]
+    fn test_card_rect() {
         let layout = GridLayout::new(2, 5);
-        let total = 15;
-        
-        assert_eq!(layout.tab_next(14, total), 0); // Wrap to start
-        assert_eq!(layout.tab_prev(0, total), 14); // Wrap to end
+        let inner = Rect::new(1, 4, 42, 10);
+
+        // (42 - 2) / 2 = 20 wide, 2 rows high
+        assert_eq!(layout.card_rect(0, inner, 2), Some(Rect::new(1, 4, 20, 2)));
+        assert_eq!(layout.card_rect(1, inner, 2), Some(Rect::new(1, 6, 20, 2)));
+        assert_eq!(layout.card_rect(5, inner, 2), Some(Rect::new(23, 4, 20, 2)));
+        // Fifth row doesn't fit in 10 rows of 3
+        assert_eq!(layout.card_rect(3, inner, 3), None);
+        assert_eq!(layout.card_rect(10, inner, 2), None);
+    }
+
+    #[test]
+    fn test_hit_test() {
+        let layout = GridLayout::new(2, 5);
+        let inner = Rect::new(1, 4, 42, 10);
+
+        assert_eq!(layout.hit_test(1, 4, inner, 2), Some(0));
+        assert_eq!(layout.hit_test(20, 5, inner, 2), Some(0));
+        assert_eq!(layout.hit_test(5, 6, inner, 2), Some(1));
+        assert_eq!(layout.hit_test(23, 13, inner, 2), Some(9));
+        // Gap between the columns, and outside the list
+        assert_eq!(layout.hit_test(21, 4, inner, 2), None);
+        assert_eq!(layout.hit_test(0, 4, inner, 2), None);
+        assert_eq!(layout.hit_test(5, 14, inner, 2), None);
+    }
+
+    #[test]
+    fn test_launcher_area() {
+        let screen = Rect::new(0, 0, 200, 60);
+        assert_eq!(launcher_area(screen, None, None), screen);
+        assert_eq!(
+            launcher_area(screen, Some(Extent::Cells(100)), Some(Extent::Percent(50))),
+            Rect::new(50, 15, 100, 30)
+        );
+        // Never bigger than the screen
+        assert_eq!(
+            launcher_area(Rect::new(0, 0, 80, 24), Some(Extent::Cells(100)), Some(Extent::Cells(30))),
+            Rect::new(0, 0, 80, 24)
+        );
+    }
+
+    #[test]
+    fn test_extent_config() {
+        #[derive(Deserialize)]
+        struct Test {
+            size: Extent,
+        }
+        let parse = |value: &str| toml::from_str::<Test>(&format!("size = {}", value)).map(|t| t.size);
+        assert_eq!(parse("100").unwrap(), Extent::Cells(100));
+        assert_eq!(parse("\"80%\"").unwrap(), Extent::Percent(80));
+        assert_eq!(parse("\"40\"").unwrap(), Extent::Cells(40));
+        assert!(parse("0").is_err());
+        assert!(parse("\"120%\"").is_err());
+        assert!(parse("\"wide\"").unwrap_err().message().contains("percentage"));
     }
 }
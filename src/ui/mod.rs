@@ -5,11 +5,15 @@
 //! - `theme.rs` - Color themes and presets
 //! - `layout.rs` - Grid layout logic
 //! - `entry_card.rs` - Entry card widget
+//! - `card_format.rs` - Entry card templates
+//! - `text.rs` - Truncating text to a width
 
+pub mod card_format;
 mod draw;
 pub mod entry_card;
 pub mod layout;
+mod text;
 pub mod theme;
 
 // Re-export main draw function (used by main.rs)
-pub use draw::draw;
+pub use draw::{draw, LauncherAreas};
@@ -0,0 +1,78 @@
+//! Fitting text into a number of cells
+
+use std::borrow::Cow;
+
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+/// `s` cut to at most `max_width` cells, ending in "…" when cut
+///
+/// Cuts between graphemes, so a wide character is never split and
+/// combining characters stay with their base.
+pub fn truncate(s: &str, max_width: usize) -> Cow<'_, str> {
+    if s.width() <= max_width {
+        return Cow::Borrowed(s);
+    }
+    if max_width == 0 {
+        return Cow::Borrowed("");
+    }
+    // Room for the ellipsis
+    let mut width = 1;
+    let mut end = 0;
+    for (index, grapheme) in s.grapheme_indices(true) {
+        width += grapheme.width();
+        if width > max_width {
+            break;
+        }
+        end = index + grapheme.len();
+    }
+    Cow::Owned(format!("{}…", &s[..end]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_truncate() {
+        assert_eq!(truncate("hello", 10), "hello");
+        assert_eq!(truncate("hello world", 8), "hello w…");
+        assert_eq!(truncate("hello", 1), "…");
+    }
+
+    #[test]
+    fn test_truncate_exact_width() {
+        assert_eq!(truncate("hi", 2), "hi");
+        assert!(matches!(truncate("hello", 5), Cow::Borrowed("hello")));
+        assert_eq!(truncate("日本", 4), "日本");
+    }
+
+    #[test]
+    fn test_truncate_zero_width() {
+        assert_eq!(truncate("hello", 0), "");
+        assert_eq!(truncate("", 0), "");
+        assert_eq!(truncate("", 3), "");
+    }
+
+    #[test]
+    fn test_truncate_wide_characters() {
+        // Never half of a two-cell character: one cell short instead
+        assert_eq!(truncate("日本語の名前", 6), "日本…");
+        assert_eq!(truncate("日本語の名前", 5), "日本…");
+        assert_eq!(truncate("日本語", 2), "…");
+        assert!(truncate("日本語の名前", 7).width() <= 7);
+    }
+
+    #[test]
+    fn test_truncate_combining_characters() {
+        // "é" as e + U+0301 is one cell and stays whole
+        let name = "Cafe\u{301} Re\u{301}sume\u{301}";
+        assert_eq!(name.width(), 11);
+        assert_eq!(truncate(name, 11), name);
+        assert_eq!(truncate(name, 5), "Cafe\u{301}…");
+        assert_eq!(truncate(name, 4), "Caf…");
+        // A family emoji is one two-cell grapheme, kept or cut whole
+        assert_eq!(truncate("👨\u{200d}👩\u{200d}👧 family", 3), "👨\u{200d}👩\u{200d}👧…");
+        assert_eq!(truncate("👨\u{200d}👩\u{200d}👧 family", 2), "…");
+    }
+}
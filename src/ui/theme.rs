@@ -4,14 +4,53 @@
 //!
 //! Provides:
 //! - Theme struct with all UI colors
-//! - Built-in presets (darkwall, catppuccin, nord, gruvbox)
+//! - Built-in presets (darkwall, catppuccin, nord, gruvbox, tokyonight,
+//!   dracula, solarized, rose-pine, everforest)
+//! - User themes from `~/.config/darkwall-drun/themes/<name>.toml`
+//! - Light/dark detection of the terminal background (OSC 11)
 //! - Hex color parsing
 //! - 256-color fallback
 
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::{Context, Result};
 use ratatui::style::Color;
 
+use crate::tty::RawTty;
+
+/// Names of the built-in presets, as listed in the docs
+pub const PRESETS: [&str; 12] = [
+    "darkwall",
+    "catppuccin-mocha",
+    "catppuccin-latte",
+    "nord",
+    "gruvbox",
+    "tokyonight",
+    "tokyonight-storm",
+    "dracula",
+    "solarized-dark",
+    "solarized-light",
+    "rose-pine",
+    "everforest",
+];
+
+/// Color names, as used in `[theme.colors]` and theme files
+pub const COLOR_NAMES: [&str; 10] = [
+    "background",
+    "foreground",
+    "selection_bg",
+    "selection_fg",
+    "accent",
+    "dimmed",
+    "dimmed_alt",
+    "search_highlight",
+    "exit_success",
+    "exit_failure",
+];
+
 /// Theme colors for the UI
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Theme {
     /// Main background color
     pub background: Color,
@@ -122,18 +161,182 @@ impl Theme {
         }
     }
 
+    /// Tokyo Night (night) theme
+    pub fn tokyonight_night() -> Self {
+        Self {
+            background: Color::Rgb(26, 27, 38),       // #1a1b26 (bg)
+            foreground: Color::Rgb(192, 202, 245),    // #c0caf5 (fg)
+            selection_bg: Color::Rgb(40, 52, 87),     // #283457 (bg_visual)
+            selection_fg: Color::Rgb(192, 202, 245),  // #c0caf5 (fg)
+            accent: Color::Rgb(122, 162, 247),        // #7aa2f7 (blue)
+            dimmed: Color::Rgb(169, 177, 214),        // #a9b1d6 (fg_dark)
+            dimmed_alt: Color::Rgb(86, 95, 137),      // #565f89 (comment)
+            search_highlight: Color::Rgb(224, 175, 104),// #e0af68 (yellow)
+            exit_success: Color::Rgb(158, 206, 106),  // #9ece6a (green)
+            exit_failure: Color::Rgb(247, 118, 142),  // #f7768e (red)
+        }
+    }
+
+    /// Tokyo Night Storm theme
+    pub fn tokyonight_storm() -> Self {
+        Self {
+            background: Color::Rgb(36, 40, 59),       // #24283b (bg)
+            foreground: Color::Rgb(192, 202, 245),    // #c0caf5 (fg)
+            selection_bg: Color::Rgb(46, 60, 100),    // #2e3c64 (bg_visual)
+            selection_fg: Color::Rgb(192, 202, 245),  // #c0caf5 (fg)
+            accent: Color::Rgb(122, 162, 247),        // #7aa2f7 (blue)
+            dimmed: Color::Rgb(169, 177, 214),        // #a9b1d6 (fg_dark)
+            dimmed_alt: Color::Rgb(86, 95, 137),      // #565f89 (comment)
+            search_highlight: Color::Rgb(224, 175, 104),// #e0af68 (yellow)
+            exit_success: Color::Rgb(158, 206, 106),  // #9ece6a (green)
+            exit_failure: Color::Rgb(247, 118, 142),  // #f7768e (red)
+        }
+    }
+
+    /// Dracula theme
+    pub fn dracula() -> Self {
+        Self {
+            background: Color::Rgb(40, 42, 54),       // #282a36 (background)
+            foreground: Color::Rgb(248, 248, 242),    // #f8f8f2 (foreground)
+            selection_bg: Color::Rgb(68, 71, 90),     // #44475a (current line)
+            selection_fg: Color::Rgb(248, 248, 242),  // #f8f8f2 (foreground)
+            accent: Color::Rgb(189, 147, 249),        // #bd93f9 (purple)
+            dimmed: Color::Rgb(186, 186, 186),        // #bababa (foreground at 70%)
+            dimmed_alt: Color::Rgb(98, 114, 164),     // #6272a4 (comment)
+            search_highlight: Color::Rgb(241, 250, 140),// #f1fa8c (yellow)
+            exit_success: Color::Rgb(80, 250, 123),   // #50fa7b (green)
+            exit_failure: Color::Rgb(255, 85, 85),    // #ff5555 (red)
+        }
+    }
+
+    /// Solarized dark theme
+    pub fn solarized_dark() -> Self {
+        Self {
+            background: Color::Rgb(0, 43, 54),        // #002b36 (base03)
+            foreground: Color::Rgb(131, 148, 150),    // #839496 (base0)
+            selection_bg: Color::Rgb(7, 54, 66),      // #073642 (base02)
+            selection_fg: Color::Rgb(147, 161, 161),  // #93a1a1 (base1)
+            accent: Color::Rgb(38, 139, 210),         // #268bd2 (blue)
+            dimmed: Color::Rgb(101, 123, 131),        // #657b83 (base00)
+            dimmed_alt: Color::Rgb(88, 110, 117),     // #586e75 (base01)
+            search_highlight: Color::Rgb(181, 137, 0),// #b58900 (yellow)
+            exit_success: Color::Rgb(133, 153, 0),    // #859900 (green)
+            exit_failure: Color::Rgb(220, 50, 47),    // #dc322f (red)
+        }
+    }
+
+    /// Solarized light theme
+    pub fn solarized_light() -> Self {
+        Self {
+            background: Color::Rgb(253, 246, 227),    // #fdf6e3 (base3)
+            foreground: Color::Rgb(101, 123, 131),    // #657b83 (base00)
+            selection_bg: Color::Rgb(238, 232, 213),  // #eee8d5 (base2)
+            selection_fg: Color::Rgb(88, 110, 117),   // #586e75 (base01)
+            accent: Color::Rgb(38, 139, 210),         // #268bd2 (blue)
+            dimmed: Color::Rgb(131, 148, 150),        // #839496 (base0)
+            dimmed_alt: Color::Rgb(147, 161, 161),    // #93a1a1 (base1)
+            search_highlight: Color::Rgb(181, 137, 0),// #b58900 (yellow)
+            exit_success: Color::Rgb(133, 153, 0),    // #859900 (green)
+            exit_failure: Color::Rgb(220, 50, 47),    // #dc322f (red)
+        }
+    }
+
+    /// Rosé Pine theme
+    pub fn rose_pine() -> Self {
+        Self {
+            background: Color::Rgb(25, 23, 36),       // #191724 (base)
+            foreground: Color::Rgb(224, 222, 244),    // #e0def4 (text)
+            selection_bg: Color::Rgb(64, 61, 82),     // #403d52 (highlight med)
+            selection_fg: Color::Rgb(224, 222, 244),  // #e0def4 (text)
+            accent: Color::Rgb(235, 188, 186),        // #ebbcba (rose)
+            dimmed: Color::Rgb(144, 140, 170),        // #908caa (subtle)
+            dimmed_alt: Color::Rgb(110, 106, 134),    // #6e6a86 (muted)
+            search_highlight: Color::Rgb(246, 193, 119),// #f6c177 (gold)
+            exit_success: Color::Rgb(156, 207, 216),  // #9ccfd8 (foam)
+            exit_failure: Color::Rgb(235, 111, 146),  // #eb6f92 (love)
+        }
+    }
+
+    /// Everforest dark (medium) theme
+    pub fn everforest() -> Self {
+        Self {
+            background: Color::Rgb(45, 53, 59),       // #2d353b (bg0)
+            foreground: Color::Rgb(211, 198, 170),    // #d3c6aa (fg)
+            selection_bg: Color::Rgb(71, 82, 88),     // #475258 (bg3)
+            selection_fg: Color::Rgb(211, 198, 170),  // #d3c6aa (fg)
+            accent: Color::Rgb(167, 192, 128),        // #a7c080 (green)
+            dimmed: Color::Rgb(157, 169, 160),        // #9da9a0 (grey2)
+            dimmed_alt: Color::Rgb(133, 146, 137),    // #859289 (grey1)
+            search_highlight: Color::Rgb(219, 188, 127),// #dbbc7f (yellow)
+            exit_success: Color::Rgb(167, 192, 128),  // #a7c080 (green)
+            exit_failure: Color::Rgb(230, 126, 128),  // #e67e80 (red)
+        }
+    }
+
     /// Load theme from preset name
+    ///
+    /// Case, `-`, `_` and spaces don't matter: "TokyoNight_Storm" is
+    /// "tokyonight-storm".
     pub fn from_preset(name: &str) -> Option<Self> {
-        match name.to_lowercase().as_str() {
+        match normalize_preset_name(name).as_str() {
             "darkwall" | "default" => Some(Self::darkwall()),
-            "catppuccin-mocha" | "catppuccin_mocha" | "catppuccin" => Some(Self::catppuccin_mocha()),
-            "catppuccin-latte" | "catppuccin_latte" => Some(Self::catppuccin_latte()),
+            "catppuccinmocha" | "catppuccin" => Some(Self::catppuccin_mocha()),
+            "catppuccinlatte" => Some(Self::catppuccin_latte()),
             "nord" => Some(Self::nord()),
-            "gruvbox" | "gruvbox-dark" | "gruvbox_dark" => Some(Self::gruvbox()),
+            "gruvbox" | "gruvboxdark" => Some(Self::gruvbox()),
+            "tokyonight" | "tokyonightnight" => Some(Self::tokyonight_night()),
+            "tokyonightstorm" => Some(Self::tokyonight_storm()),
+            "dracula" => Some(Self::dracula()),
+            "solarized" | "solarizeddark" => Some(Self::solarized_dark()),
+            "solarizedlight" => Some(Self::solarized_light()),
+            "rosepine" | "rosépine" | "rosepinemain" => Some(Self::rose_pine()),
+            "everforest" | "everforestdark" => Some(Self::everforest()),
             _ => None,
         }
     }
 
+    /// Load a theme file: every color in `COLOR_NAMES` as a hex string
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read theme {}", path.display()))?;
+        let table: toml::Table = toml::from_str(&text)
+            .with_context(|| format!("Failed to parse theme {}", path.display()))?;
+
+        let mut theme = Self::darkwall();
+        for (name, value) in &table {
+            let Some(slot) = theme.color_mut(name) else {
+                anyhow::bail!("Unknown color \"{}\" in theme {}", name, path.display());
+            };
+            let hex = value.as_str().with_context(|| {
+                format!("{} must be a hex color string in theme {}", name, path.display())
+            })?;
+            *slot = parse_hex_color(hex)
+                .with_context(|| format!("Invalid {} = \"{}\" in theme {}", name, hex, path.display()))?;
+        }
+        let missing: Vec<&str> = COLOR_NAMES.into_iter().filter(|name| !table.contains_key(*name)).collect();
+        if !missing.is_empty() {
+            anyhow::bail!("Theme {} is missing {}", path.display(), missing.join(", "));
+        }
+        Ok(theme)
+    }
+
+    /// The color called `name` (one of `COLOR_NAMES`)
+    fn color_mut(&mut self, name: &str) -> Option<&mut Color> {
+        Some(match name {
+            "background" => &mut self.background,
+            "foreground" => &mut self.foreground,
+            "selection_bg" => &mut self.selection_bg,
+            "selection_fg" => &mut self.selection_fg,
+            "accent" => &mut self.accent,
+            "dimmed" => &mut self.dimmed,
+            "dimmed_alt" => &mut self.dimmed_alt,
+            "search_highlight" => &mut self.search_highlight,
+            "exit_success" => &mut self.exit_success,
+            "exit_failure" => &mut self.exit_failure,
+            _ => return None,
+        })
+    }
+
     /// Convert to 256-color approximation for limited terminals
     #[allow(dead_code)]
     pub fn to_256_color(&self) -> Self {
@@ -152,6 +355,83 @@ impl Theme {
     }
 }
 
+/// Whether the terminal's background is dark or light
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Background {
+    Dark,
+    Light,
+}
+
+impl Background {
+    /// By the perceived brightness of an 8-bit RGB color
+    pub fn of((r, g, b): (u8, u8, u8)) -> Self {
+        let luma = 0.2126 * f64::from(r) + 0.7152 * f64::from(g) + 0.0722 * f64::from(b);
+        if luma < 128.0 {
+            Self::Dark
+        } else {
+            Self::Light
+        }
+    }
+}
+
+/// How long to wait for the terminal to answer the background query
+pub const BACKGROUND_QUERY_TIMEOUT: Duration = Duration::from_millis(200);
+
+/// Ask the terminal for its background color (OSC 11)
+///
+/// Talks to `/dev/tty`, so stdout may carry a result, and must run before
+/// the event reader starts, which would swallow the answer.
+pub fn detect_background(timeout: Duration) -> Option<Background> {
+    let reply = RawTty::open()?.query(b"\x1b]11;?\x07", timeout).unwrap_or_default();
+    let background = parse_background_reply(&reply).map(Background::of);
+    tracing::info!("Terminal background: {:?}", background);
+    background
+}
+
+/// The color in an OSC 11 answer like `ESC ]11;rgb:1e1e/1e1e/2e2e ESC \`
+///
+/// Components have 1 to 4 hex digits and are scaled to 8 bits.
+pub fn parse_background_reply(reply: &[u8]) -> Option<(u8, u8, u8)> {
+    const PREFIX: &[u8] = b"\x1b]11;";
+    let start = reply.windows(PREFIX.len()).position(|w| w == PREFIX)? + PREFIX.len();
+    let body = &reply[start..];
+    let end = body.iter().position(|&b| b == 0x07 || b == 0x1b)?;
+    let body = std::str::from_utf8(&body[..end]).ok()?;
+    let spec = body.strip_prefix("rgb:").or_else(|| body.strip_prefix("rgba:"))?;
+
+    let mut parts = spec.split('/').map(|part| {
+        if part.is_empty() || part.len() > 4 {
+            return None;
+        }
+        let value = u32::from_str_radix(part, 16).ok()?;
+        let max = (1u32 << (4 * part.len())) - 1;
+        Some((value * 255 / max) as u8)
+    });
+    Some((parts.next()??, parts.next()??, parts.next()??))
+}
+
+/// Lowercase `name` without `-`, `_` and spaces
+fn normalize_preset_name(name: &str) -> String {
+    name.chars()
+        .filter(|c| !matches!(c, '-' | '_' | ' '))
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Directory of user themes
+pub fn themes_dir() -> Option<PathBuf> {
+    dirs::config_dir().map(|d| d.join("darkwall-drun").join("themes"))
+}
+
+/// File of the user theme `name` in `themes_dir()`, if `name` is a plain
+/// file name
+pub fn theme_file(name: &str) -> Option<PathBuf> {
+    if name.is_empty() || name.contains('/') || name.starts_with('.') {
+        return None;
+    }
+    themes_dir().map(|dir| dir.join(format!("{}.toml", name)))
+}
+
 /// Parse hex color string to Color
 /// Supports: #rrggbb, #rgb, rrggbb, rgb
 pub fn parse_hex_color(s: &str) -> Result<Color, ColorError> {
@@ -249,6 +529,7 @@ pub mod serde_color {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_util::TempDir;
 
     #[test]
     fn test_parse_hex_6() {
@@ -277,10 +558,70 @@ mod tests {
 
     #[test]
     fn test_presets() {
-        assert!(Theme::from_preset("darkwall").is_some());
-        assert!(Theme::from_preset("catppuccin-mocha").is_some());
-        assert!(Theme::from_preset("nord").is_some());
-        assert!(Theme::from_preset("gruvbox").is_some());
+        for name in PRESETS {
+            assert!(Theme::from_preset(name).is_some(), "{}", name);
+        }
+        let storm = Theme::from_preset("tokyonight-storm");
+        assert_eq!(Theme::from_preset("TokyoNight_Storm"), storm);
+        assert_eq!(Theme::from_preset("Tokyo Night Storm"), storm);
+        assert_eq!(Theme::from_preset("tokyonight"), Theme::from_preset("tokyo_night-night"));
+        assert_eq!(Theme::from_preset("Rosé Pine"), Theme::from_preset("rose-pine"));
+        assert_eq!(Theme::from_preset("solarized"), Theme::from_preset("solarized-dark"));
+        assert_eq!(Theme::from_preset("catppuccin"), Theme::from_preset("Catppuccin_Mocha"));
         assert!(Theme::from_preset("nonexistent").is_none());
     }
+
+    #[test]
+    fn test_parse_background_reply() {
+        // ST-terminated, as most terminals answer
+        let dark = parse_background_reply(b"\x1b]11;rgb:1e1e/1e1e/2e2e\x1b\\");
+        assert_eq!(dark, Some((30, 30, 46)));
+        assert_eq!(Background::of(dark.unwrap()), Background::Dark);
+
+        // BEL-terminated, followed by the DA1 answer
+        let light = parse_background_reply(b"\x1b]11;rgb:fdfd/f6f6/e3e3\x07\x1b[?62;22c");
+        assert_eq!(light, Some((253, 246, 227)));
+        assert_eq!(Background::of(light.unwrap()), Background::Light);
+
+        // Short components and alpha
+        assert_eq!(parse_background_reply(b"\x1b]11;rgb:f/8/0\x07"), Some((255, 136, 0)));
+        assert_eq!(
+            parse_background_reply(b"\x1b]11;rgba:0000/0000/0000/ffff\x1b\\"),
+            Some((0, 0, 0))
+        );
+
+        // Only DA1: the terminal doesn't support OSC 11
+        assert_eq!(parse_background_reply(b"\x1b[?1;2c"), None);
+        assert_eq!(parse_background_reply(b"\x1b]11;rgb:1e1e/1e1e"), None);
+        assert_eq!(parse_background_reply(b"\x1b]11;#1e1e2e\x07"), None);
+    }
+
+    #[test]
+    fn test_example_theme_files_match_presets() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("themes");
+        for name in PRESETS {
+            let path = dir.join(format!("{}.toml", name));
+            assert_eq!(Theme::from_file(&path).unwrap(), Theme::from_preset(name).unwrap(), "{}", name);
+        }
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), PRESETS.len());
+    }
+
+    #[test]
+    fn test_theme_file_errors() {
+        let dir = TempDir::new("theme");
+        let path = dir.join("theme.toml");
+        let error = |text: &str| {
+            std::fs::write(&path, text).unwrap();
+            format!("{:#}", Theme::from_file(&path).unwrap_err())
+        };
+
+        assert!(error("background = \"#12\"").contains("Invalid background = \"#12\""));
+        assert!(error("acent = \"#123456\"").contains("Unknown color \"acent\""));
+        assert!(error("accent = 3").contains("accent must be a hex color string"));
+        let missing = error("background = \"#000\"\nforeground = \"#fff\"");
+        assert!(missing.contains("missing selection_bg, selection_fg, accent"), "{}", missing);
+
+        assert!(theme_file("../config").is_none());
+        assert!(theme_file("mine").unwrap().ends_with("darkwall-drun/themes/mine.toml"));
+    }
 }
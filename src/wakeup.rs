@@ -0,0 +1,84 @@
+//! Waking the main loop from other threads
+//!
+//! The main loop sleeps until terminal input arrives. Threads with news
+//! for it (the PTY reader, when a command prints) signal a [`Wakeup`],
+//! an eventfd the loop waits on alongside the terminal, so output shows
+//! up at once without the loop polling on a timer.
+
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Wakes the main loop out of [`Wakeup::wait`]; clones share the eventfd
+#[derive(Clone)]
+pub struct Wakeup {
+    fd: Arc<OwnedFd>,
+}
+
+impl Wakeup {
+    pub fn new() -> std::io::Result<Self> {
+        // SAFETY: eventfd has no preconditions
+        let fd = unsafe { libc::eventfd(0, libc::EFD_CLOEXEC | libc::EFD_NONBLOCK) };
+        if fd < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        Ok(Self {
+            // SAFETY: fd was just returned by eventfd and is owned by nobody else
+            fd: Arc::new(unsafe { OwnedFd::from_raw_fd(fd) }),
+        })
+    }
+
+    /// End the current or next wait
+    pub fn wake(&self) {
+        let one: u64 = 1;
+        // SAFETY: fd is a valid eventfd and one is 8 readable bytes
+        unsafe { libc::write(self.fd.as_raw_fd(), (&one as *const u64).cast(), 8) };
+    }
+
+    /// Wait up to `timeout` for `input` to become readable or a wakeup
+    ///
+    /// Returns whether `input` is readable.
+    pub fn wait(&self, input: &impl AsRawFd, timeout: Duration) -> bool {
+        let mut pfds = [input.as_raw_fd(), self.fd.as_raw_fd()].map(|fd| libc::pollfd {
+            fd,
+            events: libc::POLLIN,
+            revents: 0,
+        });
+        // SAFETY: pfds is a valid array of 2 pollfds
+        unsafe { libc::poll(pfds.as_mut_ptr(), 2, timeout.as_millis().min(i32::MAX as u128) as i32) };
+        if pfds[1].revents & libc::POLLIN != 0 {
+            // Reset the counter, so the next wait blocks again
+            let mut count: u64 = 0;
+            // SAFETY: fd is a valid eventfd and count has room for 8 bytes
+            unsafe { libc::read(self.fd.as_raw_fd(), (&mut count as *mut u64).cast(), 8) };
+        }
+        pfds[0].revents & libc::POLLIN != 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    #[test]
+    fn test_wake_ends_wait() {
+        let wakeup = Wakeup::new().unwrap();
+        let (idle, _keep) = std::os::unix::net::UnixStream::pair().unwrap();
+
+        let waker = wakeup.clone();
+        let thread = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(20));
+            waker.wake();
+        });
+        let start = Instant::now();
+        assert!(!wakeup.wait(&idle, Duration::from_secs(10)));
+        assert!(start.elapsed() < Duration::from_secs(5));
+        thread.join().unwrap();
+
+        // Consumed: the next wait runs to its timeout
+        let start = Instant::now();
+        assert!(!wakeup.wait(&idle, Duration::from_millis(50)));
+        assert!(start.elapsed() >= Duration::from_millis(50));
+    }
+}
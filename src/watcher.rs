@@ -0,0 +1,594 @@
+//! Watch desktop entry directories and the config file for changes.
+//!
+//! # Architecture
+//!
+//! An inotify instance watches every configured `desktop_entry_dirs` entry
+//! (and its subdirectories). A background thread blocks on the inotify fd
+//! and, after a quiet period, signals the main loop through a channel. The
+//! main loop then re-scans the directories and swaps in the new entries.
+//! A directory that doesn't exist yet (`~/.local/share/applications` before
+//! the first user install) is waited for by watching its nearest existing
+//! parent, and watched itself once it's created.
+//!
+//! The config file is watched the same way through its directory, since
+//! editors and home-manager replace the file rather than write to it, and
+//! so are the theme files. SIGUSR1 also asks for a config reload.
+//!
+//! Each watcher owns its inotify fd and thread: dropping it stops the
+//! thread, which closes the fd.
+//!
+//! # Debouncing
+//!
+//! Package managers touch dozens of files at once, so changes are only
+//! reported once no relevant event has arrived for `DEBOUNCE`.
+//!
+//! # Graceful Degradation
+//!
+//! If inotify is unavailable (or no directory can be watched), `try_new()`
+//! logs the reason and returns None; entries are then only loaded at startup.
+
+use std::collections::HashMap;
+use std::ffi::{CString, OsStr};
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+/// Quiet period before a batch of changes is reported
+const DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// Events that can add, remove or change a desktop entry
+const WATCH_MASK: u32 = libc::IN_CREATE
+    | libc::IN_DELETE
+    | libc::IN_CLOSE_WRITE
+    | libc::IN_MOVED_FROM
+    | libc::IN_MOVED_TO
+    | libc::IN_DELETE_SELF
+    | libc::IN_MOVE_SELF;
+
+/// Watches desktop entry directories and reports debounced changes
+pub struct EntryWatcher {
+    rx: Receiver<()>,
+    _thread: WatchThread,
+}
+
+impl EntryWatcher {
+    /// Start watching the given directories
+    ///
+    /// Returns None if inotify is unavailable or none of the directories
+    /// (nor a parent) could be watched.
+    pub fn try_new(dirs: &[PathBuf]) -> Option<Self> {
+        let targets = dirs
+            .iter()
+            .map(|dir| Target {
+                dir: dir.clone(),
+                recursive: true,
+                relevant: Box::new(is_relevant),
+            })
+            .collect();
+        let watches = match Watches::new(targets) {
+            Ok(watches) => watches,
+            Err(e) => {
+                tracing::warn!("inotify unavailable, desktop entries won't reload: {}", e);
+                return None;
+            }
+        };
+        if watches.dirs.is_empty() {
+            tracing::info!("No desktop entry directories could be watched");
+            return None;
+        }
+        tracing::debug!("Watching {} desktop entry directories", watches.dirs.len());
+
+        let (tx, rx) = mpsc::channel();
+        match WatchThread::spawn(watches, tx) {
+            Ok(thread) => Some(Self { rx, _thread: thread }),
+            Err(e) => {
+                tracing::warn!("Cannot start the desktop entry watcher: {}", e);
+                None
+            }
+        }
+    }
+
+    /// Check whether the directories changed since the last call
+    ///
+    /// Never blocks. Multiple pending notifications are collapsed into one.
+    pub fn poll_changed(&self) -> bool {
+        drain(&self.rx)
+    }
+}
+
+/// Watches the config file and SIGUSR1, reporting debounced changes
+pub struct ConfigWatcher {
+    rx: Receiver<()>,
+    _thread: Option<WatchThread>,
+    signals: Option<tokio::task::JoinHandle<()>>,
+}
+
+impl ConfigWatcher {
+    /// Watch the config file at `path` and the theme files in `themes`
+    ///
+    /// Without inotify only SIGUSR1 triggers a reload. Must be called
+    /// within the tokio runtime.
+    pub fn new(path: &Path, themes: Option<&Path>) -> Self {
+        let (tx, rx) = mpsc::channel();
+
+        let mut targets = Vec::new();
+        match (path.parent(), path.file_name()) {
+            (Some(dir), Some(name)) => {
+                let name = name.as_bytes().to_vec();
+                targets.push(Target {
+                    dir: dir.to_path_buf(),
+                    recursive: false,
+                    relevant: Box::new(move |_, changed| changed == name.as_slice()),
+                });
+            }
+            _ => tracing::info!("Not watching config {}", path.display()),
+        }
+        if let Some(themes) = themes {
+            targets.push(Target {
+                dir: themes.to_path_buf(),
+                recursive: false,
+                relevant: Box::new(|_, changed| changed.ends_with(b".toml")),
+            });
+        }
+        let thread = match Watches::new(targets).and_then(|watches| WatchThread::spawn(watches, tx.clone())) {
+            Ok(thread) => Some(thread),
+            Err(e) => {
+                tracing::warn!("inotify unavailable, config won't reload on change: {}", e);
+                None
+            }
+        };
+
+        use tokio::signal::unix::{signal, SignalKind};
+        let signals = match signal(SignalKind::user_defined1()) {
+            Ok(mut signals) => Some(tokio::spawn(async move {
+                while signals.recv().await.is_some() {
+                    tracing::info!("SIGUSR1 received, reloading config");
+                    if tx.send(()).is_err() {
+                        break;
+                    }
+                }
+            })),
+            Err(e) => {
+                tracing::warn!("Cannot listen for SIGUSR1: {}", e);
+                None
+            }
+        };
+
+        Self {
+            rx,
+            _thread: thread,
+            signals,
+        }
+    }
+
+    /// Check whether the config changed since the last call
+    ///
+    /// Never blocks. Multiple pending notifications are collapsed into one.
+    pub fn poll_changed(&self) -> bool {
+        drain(&self.rx)
+    }
+}
+
+impl Drop for ConfigWatcher {
+    fn drop(&mut self) {
+        if let Some(ref signals) = self.signals {
+            signals.abort();
+        }
+    }
+}
+
+/// Empty `rx`, returning whether anything was in it
+fn drain(rx: &Receiver<()>) -> bool {
+    let mut changed = false;
+    while rx.try_recv().is_ok() {
+        changed = true;
+    }
+    changed
+}
+
+/// A directory to watch, and the changes in it that count
+struct Target {
+    dir: PathBuf,
+    /// Watch the subdirectories too
+    recursive: bool,
+    relevant: Relevant,
+}
+
+/// Picks the events that count, by mask and file name
+type Relevant = Box<dyn Fn(u32, &[u8]) -> bool + Send>;
+
+/// An inotify instance watching the targets, or their nearest existing
+/// parents while they don't exist
+struct Watches {
+    fd: OwnedFd,
+    targets: Vec<Target>,
+    /// Watched directories by watch descriptor
+    dirs: HashMap<i32, PathBuf>,
+}
+
+impl Watches {
+    fn new(targets: Vec<Target>) -> std::io::Result<Self> {
+        // SAFETY: inotify_init1 has no preconditions
+        let fd = unsafe { libc::inotify_init1(libc::IN_CLOEXEC) };
+        if fd < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        let mut watches = Self {
+            // SAFETY: fd was just returned by inotify_init1 and is owned by nobody else
+            fd: unsafe { OwnedFd::from_raw_fd(fd) },
+            targets,
+            dirs: HashMap::new(),
+        };
+        for i in 0..watches.targets.len() {
+            watches.watch_target(i);
+        }
+        Ok(watches)
+    }
+
+    /// Watch target `i`, or its nearest existing parent if it doesn't exist
+    ///
+    /// Returns whether the target itself is watched.
+    fn watch_target(&mut self, i: usize) -> bool {
+        let Target { ref dir, recursive, .. } = self.targets[i];
+        let dir = dir.clone();
+        if dir.is_dir() {
+            return if recursive { self.add_watches(&dir) > 0 } else { self.add_watch(&dir) };
+        }
+        if let Some(parent) = dir.ancestors().skip(1).find(|parent| parent.is_dir()) {
+            tracing::debug!("{} doesn't exist, watching {}", dir.display(), parent.display());
+            self.add_watch(parent);
+        }
+        false
+    }
+
+    /// Add a watch on `dir`; failures are logged (e.g. filesystems without
+    /// inotify support)
+    fn add_watch(&mut self, dir: &Path) -> bool {
+        let Ok(c_path) = CString::new(dir.as_os_str().as_bytes()) else {
+            return false;
+        };
+        // SAFETY: fd is a valid inotify fd and c_path is NUL-terminated
+        let wd = unsafe { libc::inotify_add_watch(self.fd.as_raw_fd(), c_path.as_ptr(), WATCH_MASK) };
+        if wd < 0 {
+            tracing::warn!("Cannot watch {}: {}", dir.display(), std::io::Error::last_os_error());
+            return false;
+        }
+        self.dirs.insert(wd, dir.to_path_buf());
+        true
+    }
+
+    /// Add a watch on `dir` and all of its subdirectories
+    ///
+    /// Returns the number of directories watched.
+    fn add_watches(&mut self, dir: &Path) -> usize {
+        if !dir.is_dir() || !self.add_watch(dir) {
+            return 0;
+        }
+        let mut watched = 1;
+        if let Ok(read_dir) = std::fs::read_dir(dir) {
+            for child in read_dir.flatten() {
+                if child.file_type().map(|t| t.is_dir()).unwrap_or(false) {
+                    watched += self.add_watches(&child.path());
+                }
+            }
+        }
+        watched
+    }
+
+    /// Read one batch of events, following created and removed directories
+    ///
+    /// Returns whether any of them is relevant, or None on a read error.
+    fn read_events(&mut self, buf: &mut [u8]) -> Option<bool> {
+        // SAFETY: buf is valid for buf.len() bytes
+        let n = unsafe { libc::read(self.fd.as_raw_fd(), buf.as_mut_ptr().cast(), buf.len()) };
+        if n < 0 {
+            let err = std::io::Error::last_os_error();
+            if err.kind() == std::io::ErrorKind::Interrupted {
+                return Some(false);
+            }
+            tracing::warn!("File watcher stopped: {}", err);
+            return None;
+        }
+        let mut relevant = false;
+        for (wd, mask, name) in parse_events(&buf[..n as usize]) {
+            relevant |= self.handle_event(wd, mask, name);
+        }
+        Some(relevant)
+    }
+
+    /// Follow one event; returns whether it's relevant
+    fn handle_event(&mut self, wd: i32, mask: u32, name: &[u8]) -> bool {
+        if mask & libc::IN_Q_OVERFLOW != 0 {
+            return true;
+        }
+        let Some(dir) = self.dirs.get(&wd).cloned() else {
+            return false;
+        };
+        if mask & libc::IN_IGNORED != 0 {
+            self.dirs.remove(&wd);
+        }
+        let path = dir.join(OsStr::from_bytes(name));
+        let dir_added = mask & libc::IN_ISDIR != 0 && mask & (libc::IN_CREATE | libc::IN_MOVED_TO) != 0;
+        let dir_gone = mask & (libc::IN_DELETE_SELF | libc::IN_MOVE_SELF) != 0;
+
+        let mut relevant = false;
+        for i in 0..self.targets.len() {
+            let target = &self.targets[i];
+            let inside = dir == target.dir || (target.recursive && dir.starts_with(&target.dir));
+            relevant |= inside && (target.relevant)(mask, name);
+            if dir_added && inside && target.recursive {
+                self.add_watches(&path);
+            } else if dir_added && target.dir.starts_with(&path) {
+                // On the way to a target that doesn't exist yet; it may
+                // have been created along with its parents already
+                relevant |= self.watch_target(i);
+            } else if dir_gone && dir == target.dir {
+                // Wait for it to come back
+                self.watch_target(i);
+            }
+        }
+        relevant
+    }
+}
+
+/// A thread reporting debounced changes of [`Watches`]; stopped and
+/// joined when dropped
+struct WatchThread {
+    /// eventfd that wakes the thread to stop
+    stop: Arc<OwnedFd>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl WatchThread {
+    fn spawn(watches: Watches, tx: Sender<()>) -> std::io::Result<Self> {
+        // SAFETY: eventfd has no preconditions
+        let stop = unsafe { libc::eventfd(0, libc::EFD_CLOEXEC) };
+        if stop < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        // SAFETY: stop was just returned by eventfd and is owned by nobody else
+        let stop = Arc::new(unsafe { OwnedFd::from_raw_fd(stop) });
+        let thread = std::thread::Builder::new().name("watcher".to_string()).spawn({
+            let stop = Arc::clone(&stop);
+            move || watch_loop(watches, &stop, tx)
+        })?;
+        Ok(Self {
+            stop,
+            thread: Some(thread),
+        })
+    }
+}
+
+impl Drop for WatchThread {
+    fn drop(&mut self) {
+        let one: u64 = 1;
+        // SAFETY: stop is a valid eventfd and one is 8 readable bytes
+        unsafe { libc::write(self.stop.as_raw_fd(), (&one as *const u64).cast(), 8) };
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Background loop: read events, debounce, notify
+///
+/// Ends when `stop` is signaled or the receiver is gone; dropping
+/// `watches` then closes the inotify fd.
+fn watch_loop(mut watches: Watches, stop: &OwnedFd, tx: Sender<()>) {
+    let mut buf = [0u8; 4096];
+    loop {
+        // Block until the first relevant event
+        match wait(&watches.fd, stop, None) {
+            Wake::Events => {}
+            Wake::Quiet => continue,
+            Wake::Stop => break,
+        }
+        match watches.read_events(&mut buf) {
+            Some(true) => {}
+            Some(false) => continue,
+            None => break,
+        }
+
+        // Keep draining until the directories have been quiet for DEBOUNCE
+        loop {
+            match wait(&watches.fd, stop, Some(DEBOUNCE)) {
+                Wake::Events => {
+                    if watches.read_events(&mut buf).is_none() {
+                        return;
+                    }
+                }
+                Wake::Quiet => break,
+                Wake::Stop => return,
+            }
+        }
+
+        if tx.send(()).is_err() {
+            // Receiver dropped, nobody is listening anymore
+            break;
+        }
+    }
+}
+
+/// Why [`wait`] returned
+enum Wake {
+    /// Events to read
+    Events,
+    /// `timeout` passed (or a signal interrupted the wait)
+    Quiet,
+    /// The watcher is dropped, or polling failed
+    Stop,
+}
+
+/// Wait up to `timeout` (forever if None) for events on `fd` or a wakeup
+/// on `stop`
+fn wait(fd: &OwnedFd, stop: &OwnedFd, timeout: Option<Duration>) -> Wake {
+    let mut pfds = [fd.as_raw_fd(), stop.as_raw_fd()].map(|fd| libc::pollfd {
+        fd,
+        events: libc::POLLIN,
+        revents: 0,
+    });
+    let timeout = timeout.map_or(-1, |timeout| timeout.as_millis() as i32);
+    // SAFETY: pfds is a valid array of 2 pollfds
+    let ret = unsafe { libc::poll(pfds.as_mut_ptr(), 2, timeout) };
+    if ret < 0 {
+        let err = std::io::Error::last_os_error();
+        if err.kind() == std::io::ErrorKind::Interrupted {
+            return Wake::Quiet;
+        }
+        tracing::warn!("File watcher stopped: {}", err);
+        return Wake::Stop;
+    }
+    if pfds[1].revents != 0 {
+        Wake::Stop
+    } else if pfds[0].revents & libc::POLLIN != 0 {
+        Wake::Events
+    } else {
+        Wake::Quiet
+    }
+}
+
+/// Iterate over `(wd, mask, name)` of a buffer of raw inotify events
+fn parse_events(buf: &[u8]) -> impl Iterator<Item = (i32, u32, &[u8])> {
+    const HEADER: usize = std::mem::size_of::<libc::inotify_event>();
+    let mut offset = 0;
+    std::iter::from_fn(move || {
+        if offset + HEADER > buf.len() {
+            return None;
+        }
+        // SAFETY: the header is fully in bounds; read_unaligned handles alignment
+        let event: libc::inotify_event =
+            unsafe { std::ptr::read_unaligned(buf[offset..].as_ptr().cast()) };
+        let name_start = offset + HEADER;
+        let name_end = (name_start + event.len as usize).min(buf.len());
+        let name = &buf[name_start..name_end];
+        // The name is NUL-padded
+        let name = &name[..name.iter().position(|&b| b == 0).unwrap_or(name.len())];
+        offset = name_end;
+        Some((event.wd, event.mask, name))
+    })
+}
+
+/// Whether an event can affect the list of entries
+///
+/// Ignores unrelated files such as `mimeinfo.cache`, which package managers
+/// rewrite alongside the desktop files.
+fn is_relevant(mask: u32, name: &[u8]) -> bool {
+    name.is_empty()
+        || mask & libc::IN_ISDIR != 0
+        || mask & libc::IN_Q_OVERFLOW != 0
+        || name.ends_with(b".desktop")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+
+    #[test]
+    fn test_is_relevant() {
+        assert!(is_relevant(libc::IN_CREATE, b"firefox.desktop"));
+        assert!(is_relevant(libc::IN_CREATE | libc::IN_ISDIR, b"kde4"));
+        assert!(is_relevant(libc::IN_DELETE_SELF, b""));
+        assert!(!is_relevant(libc::IN_CLOSE_WRITE, b"mimeinfo.cache"));
+        assert!(!is_relevant(libc::IN_CREATE, b".firefox.desktop.swp"));
+    }
+
+    #[test]
+    fn test_watcher_reports_new_entry() {
+        let dir = TempDir::new("watcher");
+
+        let Some(watcher) = EntryWatcher::try_new(&[dir.to_path_buf()]) else {
+            // inotify not available in this environment
+            return;
+        };
+        assert!(!watcher.poll_changed());
+
+        std::fs::write(dir.join("new.desktop"), "[Desktop Entry]\nName=New\n").unwrap();
+        assert!(changed(&watcher));
+    }
+
+    /// Wait up to 3 seconds for `watcher` to report a change
+    fn changed(watcher: &EntryWatcher) -> bool {
+        let start = std::time::Instant::now();
+        while start.elapsed() < Duration::from_secs(3) {
+            if watcher.poll_changed() {
+                return true;
+            }
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        false
+    }
+
+    #[test]
+    fn test_watcher_waits_for_missing_dir() {
+        let dir = TempDir::new("watcher-missing");
+        let apps = dir.join("share").join("applications");
+
+        let Some(watcher) = EntryWatcher::try_new(std::slice::from_ref(&apps)) else {
+            return;
+        };
+        // Unrelated files next to the missing directory don't count
+        std::fs::write(dir.join("other.desktop"), "").unwrap();
+        std::thread::sleep(DEBOUNCE * 2);
+        assert!(!watcher.poll_changed());
+
+        // Created with its parent, like `mkdir -p`
+        std::fs::create_dir_all(&apps).unwrap();
+        assert!(changed(&watcher));
+        std::fs::write(apps.join("new.desktop"), "[Desktop Entry]\nName=New\n").unwrap();
+        assert!(changed(&watcher));
+
+        // Subdirectories created later are watched too
+        std::fs::create_dir(apps.join("kde4")).unwrap();
+        assert!(changed(&watcher));
+        std::fs::write(apps.join("kde4").join("old.desktop"), "").unwrap();
+        assert!(changed(&watcher));
+
+        // Dropping stops the thread rather than leaving it blocked
+        drop(watcher);
+    }
+
+    #[tokio::test]
+    async fn test_config_watcher_reports_replaced_file() {
+        let dir = TempDir::new("config-watcher");
+        let config = dir.join("config.toml");
+        std::fs::write(&config, "").unwrap();
+
+        let themes = dir.join("themes");
+        let watcher = ConfigWatcher::new(&config, Some(&themes));
+        // Other files in the directory don't count
+        std::fs::write(dir.join("other.toml"), "").unwrap();
+        tokio::time::sleep(DEBOUNCE * 2).await;
+        assert!(!watcher.poll_changed());
+
+        // Editors write a new file and rename it over the config
+        std::fs::write(dir.join("config.toml.tmp"), "[appearance]\n").unwrap();
+        std::fs::rename(dir.join("config.toml.tmp"), &config).unwrap();
+        let start = std::time::Instant::now();
+        let mut changed = false;
+        while start.elapsed() < Duration::from_secs(3) {
+            if watcher.poll_changed() {
+                changed = true;
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        assert!(changed);
+
+        // So do theme files, in a themes directory created later (which
+        // counts itself, as it may have been moved in with themes)
+        std::fs::create_dir(&themes).unwrap();
+        tokio::time::sleep(DEBOUNCE * 2).await;
+        assert!(watcher.poll_changed());
+        std::fs::write(themes.join("mine.toml"), "").unwrap();
+        let start = std::time::Instant::now();
+        while !watcher.poll_changed() {
+            assert!(start.elapsed() < Duration::from_secs(3));
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+    }
+}
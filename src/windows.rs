@@ -0,0 +1,256 @@
+//! The compositor's open windows and workspaces as launcher entries
+//!
+//! The window switcher (`--windows`, Ctrl+W) lists the windows the
+//! compositor reports (niri's `Windows` request, sway's tree, Hyprland's
+//! `clients`) in the same grid as applications, so the
+//! fuzzy filter and icons work unchanged. Each window becomes an `Entry`
+//! with a `window:<id>` id; running it focuses the window instead. The
+//! workspace switcher (`--workspaces`) does the same with `workspace:<id>`
+//! entries.
+//!
+//! drun's own window is left out: the window of the terminal drun runs in
+//! has one of drun's parent processes as its pid. Terminals that serve
+//! all their windows from one process (`footclient`) aren't a parent, so
+//! the focused window, which is drun's while it is in use, is left out too.
+
+use std::collections::HashMap;
+
+use crate::desktop_entry::Entry;
+use crate::compositor::{WindowInfo, WorkspaceInfo};
+
+/// Id prefix of window entries
+pub const WINDOW_PREFIX: &str = "window:";
+
+/// Id prefix of workspace entries
+pub const WORKSPACE_PREFIX: &str = "workspace:";
+
+/// Compositor window id of a window entry
+pub fn window_id(entry: &Entry) -> Option<u64> {
+    entry.id.strip_prefix(WINDOW_PREFIX)?.parse().ok()
+}
+
+/// Compositor workspace id of a workspace entry
+pub fn workspace_id(entry: &Entry) -> Option<u64> {
+    entry.id.strip_prefix(WORKSPACE_PREFIX)?.parse().ok()
+}
+
+/// Entries for `workspaces`, by output and position
+pub fn workspace_entries(workspaces: &[WorkspaceInfo]) -> Vec<Entry> {
+    let mut workspaces: Vec<&WorkspaceInfo> = workspaces.iter().collect();
+    workspaces.sort_by(|a, b| (&a.output, a.idx).cmp(&(&b.output, b.idx)));
+    workspaces
+        .into_iter()
+        .map(|ws| {
+            let state = if ws.is_focused {
+                "Focused"
+            } else if ws.is_active {
+                "Active"
+            } else {
+                "Not shown"
+            };
+            Entry {
+                id: format!("{}{}", WORKSPACE_PREFIX, ws.id),
+                name: workspace_name(ws),
+                generic_name: ws.output.clone(),
+                comment: Some(state.to_string()),
+                // The index finds named workspaces too
+                keywords: vec![ws.idx.to_string()],
+                custom: true,
+                ..Default::default()
+            }
+        })
+        .collect()
+}
+
+/// Entries for `windows`, except drun's own
+///
+/// Application entries from `apps` lend their name and icon to the
+/// windows they opened; other windows use their app_id as icon name.
+pub fn entries(windows: &[WindowInfo], workspaces: &[WorkspaceInfo], apps: &[Entry]) -> Vec<Entry> {
+    let own_pids = ancestor_pids();
+    let workspaces: HashMap<u64, &WorkspaceInfo> = workspaces.iter().map(|ws| (ws.id, ws)).collect();
+    windows
+        .iter()
+        .filter(|window| !window.is_focused && !window.pid.is_some_and(|pid| own_pids.contains(&pid)))
+        .map(|window| {
+            let app = app_for(&window.app_id, apps);
+            let title = if window.title.is_empty() {
+                window.app_id.clone()
+            } else {
+                window.title.clone()
+            };
+            let workspace = window
+                .workspace_id
+                .and_then(|id| workspaces.get(&id))
+                .map(|ws| workspace_label(ws));
+            Entry {
+                id: format!("{}{}", WINDOW_PREFIX, window.id),
+                name: title,
+                generic_name: Some(app.map_or_else(|| window.app_id.clone(), |app| app.name.clone())),
+                comment: workspace,
+                icon: app
+                    .and_then(|app| app.icon.clone())
+                    .or_else(|| (!window.app_id.is_empty()).then(|| window.app_id.clone())),
+                keywords: vec![window.app_id.clone()],
+                custom: true,
+                ..Default::default()
+            }
+        })
+        .collect()
+}
+
+/// drun's own window among `windows`: the one of the terminal drun runs in
+pub fn own_window(windows: &[WindowInfo]) -> Option<u64> {
+    let own_pids = ancestor_pids();
+    windows
+        .iter()
+        .find(|window| window.pid.is_some_and(|pid| own_pids.contains(&pid)))
+        .map(|window| window.id)
+}
+
+/// Application whose windows have `app_id`: by StartupWMClass, then by
+/// desktop file id (`org.gnome.Nautilus`) or its last part (`firefox`)
+fn app_for<'a>(app_id: &str, apps: &'a [Entry]) -> Option<&'a Entry> {
+    if app_id.is_empty() {
+        return None;
+    }
+    let matches = |name: &str| name.eq_ignore_ascii_case(app_id);
+    apps.iter()
+        .filter(|app| !app.custom)
+        .find(|app| app.startup_wm_class.as_deref().is_some_and(matches))
+        .or_else(|| {
+            apps.iter().filter(|app| !app.custom).find(|app| {
+                matches(&app.id) || app.id.rsplit('.').next().is_some_and(matches)
+            })
+        })
+}
+
+/// The workspace's name, or "Workspace 2"
+fn workspace_name(workspace: &WorkspaceInfo) -> String {
+    match workspace.name {
+        Some(ref name) => name.clone(),
+        None => format!("Workspace {}", workspace.idx),
+    }
+}
+
+/// "Workspace 2 on DP-1"
+fn workspace_label(workspace: &WorkspaceInfo) -> String {
+    match workspace.output {
+        Some(ref output) => format!("{} on {}", workspace_name(workspace), output),
+        None => workspace_name(workspace),
+    }
+}
+
+/// drun's parent, its parent and so on
+fn ancestor_pids() -> Vec<i32> {
+    let mut pids = Vec::new();
+    let mut pid = std::os::unix::process::parent_id() as i32;
+    while pid > 1 && !pids.contains(&pid) {
+        pids.push(pid);
+        let Some(parent) = std::fs::read_to_string(format!("/proc/{}/stat", pid))
+            .ok()
+            .and_then(|stat| parent_pid(&stat))
+        else {
+            break;
+        };
+        pid = parent;
+    }
+    pids
+}
+
+/// Parent pid from the content of `/proc/<pid>/stat`
+///
+/// The command name in parentheses may itself hold spaces and
+/// parentheses, so fields are counted from the last `)`.
+fn parent_pid(stat: &str) -> Option<i32> {
+    let rest = &stat[stat.rfind(')')? + 1..];
+    rest.split_whitespace().nth(1)?.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn window(id: u64, app_id: &str, title: &str) -> WindowInfo {
+        WindowInfo {
+            id,
+            app_id: app_id.to_string(),
+            title: title.to_string(),
+            workspace_id: Some(1),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_window_entries() {
+        let workspaces = [WorkspaceInfo {
+            id: 1,
+            idx: 2,
+            output: Some("DP-1".to_string()),
+            ..Default::default()
+        }];
+        let apps = [Entry {
+            id: "org.mozilla.firefox".to_string(),
+            name: "Firefox".to_string(),
+            icon: Some("firefox-icon".to_string()),
+            ..Default::default()
+        }];
+        let focused = WindowInfo {
+            is_focused: true,
+            ..window(3, "foot", "drun")
+        };
+        let windows = [window(7, "firefox", "Rust docs"), window(8, "mpv", ""), focused];
+
+        let entries = entries(&windows, &workspaces, &apps);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(window_id(&entries[0]), Some(7));
+        assert_eq!(entries[0].name, "Rust docs");
+        assert_eq!(entries[0].generic_name.as_deref(), Some("Firefox"));
+        assert_eq!(entries[0].icon.as_deref(), Some("firefox-icon"));
+        assert_eq!(entries[0].comment.as_deref(), Some("Workspace 2 on DP-1"));
+
+        // Unknown apps: the app_id names the window and its icon
+        assert_eq!(entries[1].name, "mpv");
+        assert_eq!(entries[1].icon.as_deref(), Some("mpv"));
+
+        assert_eq!(window_id(&apps[0]), None);
+    }
+
+    #[test]
+    fn test_workspace_entries() {
+        let workspace = |id, idx, output: &str| WorkspaceInfo {
+            id,
+            idx,
+            output: Some(output.to_string()),
+            ..Default::default()
+        };
+        let workspaces = [
+            workspace(4, 2, "eDP-1"),
+            WorkspaceInfo {
+                name: Some("chat".to_string()),
+                is_active: true,
+                is_focused: true,
+                ..workspace(3, 1, "eDP-1")
+            },
+            workspace(9, 1, "DP-1"),
+        ];
+
+        let entries = workspace_entries(&workspaces);
+        let ids: Vec<_> = entries.iter().filter_map(workspace_id).collect();
+        assert_eq!(ids, [9, 3, 4]);
+        assert_eq!(entries[1].name, "chat");
+        assert_eq!(entries[1].comment.as_deref(), Some("Focused"));
+        assert_eq!(entries[1].keywords, ["1"]);
+        assert_eq!(entries[2].name, "Workspace 2");
+        assert_eq!(entries[2].generic_name.as_deref(), Some("eDP-1"));
+        assert_eq!(window_id(&entries[0]), None);
+    }
+
+    #[test]
+    fn test_parent_pid() {
+        assert_eq!(parent_pid("412 (foot) S 1 412 412 0 -1"), Some(1));
+        assert_eq!(parent_pid("500 (my (odd) cmd) R 412 500 412 0"), Some(412));
+        assert_eq!(parent_pid("garbage"), None);
+        assert!(!ancestor_pids().contains(&(std::process::id() as i32)));
+    }
+}